@@ -0,0 +1,267 @@
+use crate::cascade::CascadeChunk;
+use crate::chunk::TerrainChunk;
+use crate::chunk_manager::SdfResource;
+use crate::material::ChunkTag;
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The biome and feature tags a loaded chunk advertises, such as "forest", "peak" or "river".
+///
+/// Added to terrain chunks by [tag_chunk_features] from the tag of their SDF and the
+/// [ChunkFeatureTagger], if there is one.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct ChunkFeatures {
+	pub tags: Vec<ChunkTag>,
+}
+
+impl ChunkFeatures {
+	pub fn has(&self, tag: ChunkTag) -> bool {
+		self.tags.contains(&tag)
+	}
+}
+
+/// Finds the feature tags of a chunk from its SDF, with the chunk in the local space of the SDF.
+pub type ChunkTagFn<S> = Arc<dyn Fn(&S, &CascadeChunk) -> Vec<ChunkTag> + Send + Sync>;
+
+/// Decides which features each loaded chunk of the SDF advertises.
+#[derive(Resource)]
+pub struct ChunkFeatureTagger<S: Sdf + Send + Sync> {
+	tagger: ChunkTagFn<S>,
+}
+
+impl<S: Sdf + Send + Sync> ChunkFeatureTagger<S> {
+	pub fn new(
+		tagger: impl Fn(&S, &CascadeChunk) -> Vec<ChunkTag> + Send + Sync + 'static,
+	) -> Self {
+		Self { tagger: Arc::new(tagger) }
+	}
+
+	pub fn tags(&self, sdf: &S, chunk: &CascadeChunk) -> Vec<ChunkTag> {
+		(self.tagger)(sdf, chunk)
+	}
+}
+
+/// Tags chunks with their [ChunkFeatures] as they are spawned or regenerated.
+pub fn tag_chunk_features<S: Sdf + Send + Sync + 'static>(
+	mut commands: Commands,
+	sdf_resource: Res<SdfResource<S>>,
+	tagger: Option<Res<ChunkFeatureTagger<S>>>,
+	chunks: Query<(Entity, &TerrainChunk), Changed<TerrainChunk>>,
+) {
+	for (entity, chunk) in &chunks {
+		let mut tags = vec![sdf_resource.tag];
+		if let Some(tagger) = &tagger {
+			for tag in tagger.tags(&sdf_resource.sdf, &chunk.chunk) {
+				if !tags.contains(&tag) {
+					tags.push(tag);
+				}
+			}
+		}
+		commands.entity(entity).insert(ChunkFeatures { tags });
+	}
+}
+
+/// A looping audio bed that plays where chunks with its tag are around the camera.
+#[derive(Debug, Clone)]
+pub struct AmbienceBed {
+	pub tag: ChunkTag,
+	pub source: Handle<AudioSource>,
+	/// Volume when every chunk around the camera has the tag
+	pub volume: f32,
+	level: f32,
+	player: Option<Entity>,
+}
+
+/// Crossfades registered audio beds by the [ChunkFeatures] of the chunks around the camera.
+///
+/// Each bed plays at its volume scaled by the share of nearby chunks with its tag, nearer
+/// chunks weighing more, and fades toward that level over the crossfade time. The audio is
+/// provided by the user; beds start silent and are spawned as looping players on first use.
+#[derive(Resource)]
+pub struct AmbienceMixer<S: Sdf + Send + Sync> {
+	/// Distance from the camera within which chunks are heard
+	pub radius: f32,
+	/// Time a bed takes to fade from silent to its full volume
+	pub crossfade: Duration,
+	beds: Vec<AmbienceBed>,
+	/// Marker for the SDF whose chunks are heard
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for AmbienceMixer<S> {
+	fn default() -> Self {
+		Self { radius: 64.0, crossfade: Duration::from_secs(2), beds: Vec::new(), sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync> AmbienceMixer<S> {
+	pub fn with_radius(mut self, radius: f32) -> Self {
+		self.radius = radius;
+		self
+	}
+
+	pub fn with_crossfade(mut self, crossfade: Duration) -> Self {
+		self.crossfade = crossfade;
+		self
+	}
+
+	pub fn with_bed(mut self, tag: ChunkTag, source: Handle<AudioSource>, volume: f32) -> Self {
+		self.register(tag, source, volume);
+		self
+	}
+
+	/// Plays the source where chunks have the tag, replacing the bed of the tag if there is one.
+	pub fn register(&mut self, tag: ChunkTag, source: Handle<AudioSource>, volume: f32) {
+		self.beds.retain(|bed| bed.tag != tag);
+		self.beds.push(AmbienceBed { tag, source, volume, level: 0.0, player: None });
+	}
+
+	pub fn beds(&self) -> &[AmbienceBed] {
+		&self.beds
+	}
+
+	/// The current volume of the bed of the tag
+	pub fn level(&self, tag: ChunkTag) -> Option<f32> {
+		self.beds.iter().find(|bed| bed.tag == tag).map(|bed| bed.level)
+	}
+
+	/// Share of the chunks around the position with each bed's tag, weighted by nearness.
+	pub fn presence<'a>(
+		&self,
+		position: Vec3,
+		chunks: impl IntoIterator<Item = (&'a CascadeChunk, &'a ChunkFeatures)>,
+	) -> Vec<f32> {
+		let mut presence = vec![0.0; self.beds.len()];
+		let mut total = 0.0;
+		for (chunk, features) in chunks {
+			let nearest = position.clamp(chunk.origin, chunk.origin + chunk.size);
+			let weight = 1.0 - nearest.distance(position) / self.radius;
+			if weight <= 0.0 {
+				continue;
+			}
+			total += weight;
+			for (bed, presence) in self.beds.iter().zip(&mut presence) {
+				if features.has(bed.tag) {
+					*presence += weight;
+				}
+			}
+		}
+		if total > 0.0 {
+			presence.iter_mut().for_each(|presence| *presence /= total);
+		}
+		presence
+	}
+
+	/// Fades each bed toward its volume scaled by its presence, over dt seconds.
+	pub fn fade(&mut self, presence: &[f32], dt: f32) {
+		let crossfade = self.crossfade.as_secs_f32();
+		for (bed, presence) in self.beds.iter_mut().zip(presence) {
+			let target = bed.volume * presence;
+			let step = if crossfade > 0.0 { bed.volume * dt / crossfade } else { f32::MAX };
+			bed.level += (target - bed.level).clamp(-step, step);
+		}
+	}
+}
+
+/// Mixes the ambience for the chunks around the camera and sets the volume of each bed.
+pub fn mix_ambience<S: Sdf + Send + Sync + 'static>(
+	mut commands: Commands,
+	time: Res<Time>,
+	camera_query: Query<&Transform, With<Camera3d>>,
+	sdf_resource: Res<SdfResource<S>>,
+	mut mixer: ResMut<AmbienceMixer<S>>,
+	chunks: Query<(&TerrainChunk, &ChunkFeatures)>,
+	mut sinks: Query<&mut AudioSink>,
+) {
+	let Ok(camera_transform) = camera_query.single() else {
+		return;
+	};
+
+	let position = sdf_resource.transform.to_local(camera_transform.translation);
+	let presence =
+		mixer.presence(position, chunks.iter().map(|(chunk, features)| (&chunk.chunk, features)));
+	mixer.fade(&presence, time.delta_secs());
+
+	for bed in &mut mixer.beds {
+		let Some(player) = bed.player else {
+			if bed.level > 0.0 {
+				let settings = PlaybackSettings::LOOP.with_volume(Volume::Linear(bed.level));
+				bed.player = Some(commands.spawn((AudioPlayer(bed.source.clone()), settings)).id());
+			}
+			continue;
+		};
+		if let Ok(mut sink) = sinks.get_mut(player) {
+			sink.set_volume(Volume::Linear(bed.level));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::Ground;
+	use bevy::time::TimeUpdateStrategy;
+
+	const FOREST: ChunkTag = ChunkTag("forest");
+	const RIVER: ChunkTag = ChunkTag("river");
+
+	#[test]
+	fn test_ambience_crossfades_between_zones() {
+		let mut app = App::new();
+		app.add_plugins((AssetPlugin::default(), bevy::time::TimePlugin))
+			.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(250)))
+			.init_asset::<AudioSource>()
+			.insert_resource(SdfResource::new(Ground(0.0)))
+			.insert_resource(ChunkFeatureTagger::<Ground>::new(|_, chunk| {
+				if chunk.origin.x < 0.0 {
+					vec![FOREST]
+				} else {
+					vec![RIVER]
+				}
+			}))
+			.insert_resource(
+				AmbienceMixer::<Ground>::default()
+					.with_radius(16.0)
+					.with_crossfade(Duration::from_secs(2))
+					.with_bed(FOREST, Handle::default(), 0.8)
+					.with_bed(RIVER, Handle::default(), 0.5),
+			)
+			.add_systems(Update, (tag_chunk_features::<Ground>, mix_ambience::<Ground>).chain());
+		for x in [-40.0, -8.0, 0.0, 32.0] {
+			let chunk = CascadeChunk::cube(Vec3::new(x, -4.0, -4.0), 8.0, 2);
+			app.world_mut().spawn(TerrainChunk { chunk });
+		}
+		let camera = app.world_mut().spawn((Camera3d::default(), Transform::default())).id();
+
+		// Standing between the forest and the river, both beds fade in to half their volume
+		for _ in 0..3 {
+			app.update();
+		}
+		let mixer = app.world().resource::<AmbienceMixer<Ground>>();
+		let (Some(forest), Some(river)) = (mixer.level(FOREST), mixer.level(RIVER)) else {
+			panic!("expected both beds");
+		};
+		assert!(forest > 0.0 && forest < 0.4, "The forest is at {forest}");
+		assert!(river > 0.0 && river < 0.25, "The river is at {river}");
+		for _ in 0..8 {
+			app.update();
+		}
+		let mixer = app.world().resource::<AmbienceMixer<Ground>>();
+		assert_eq!(mixer.level(FOREST), Some(0.4));
+		assert_eq!(mixer.level(RIVER), Some(0.25));
+		assert!(mixer.beds().iter().all(|bed| bed.player.is_some()));
+
+		// Deep in the forest, the river fades out
+		app.world_mut().entity_mut(camera).insert(Transform::from_xyz(-36.0, 0.0, 0.0));
+		for _ in 0..12 {
+			app.update();
+		}
+		let mixer = app.world().resource::<AmbienceMixer<Ground>>();
+		assert_eq!(mixer.level(FOREST), Some(0.8));
+		assert_eq!(mixer.level(RIVER), Some(0.0));
+	}
+}
@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+use std::fmt;
+use std::sync::Arc;
+
+/// A material parameter over time, in seconds since startup.
+#[derive(Clone)]
+pub enum ParamCurve {
+	Constant(f32),
+	/// Oscillates around the center
+	Sine {
+		center: f32,
+		amplitude: f32,
+		period: f32,
+		phase: f32,
+	},
+	/// Climbs at a fixed rate, wrapping back to 0 at the period when it is positive,
+	/// for scrolling and phases
+	Ramp {
+		rate: f32,
+		period: f32,
+	},
+	/// Linear between keyframes of (time, value), looping over the last keyframe's time
+	Keyframes(Vec<(f32, f32)>),
+	/// Any function of the time
+	Expression(Arc<dyn Fn(f32) -> f32 + Send + Sync>),
+}
+
+impl fmt::Debug for ParamCurve {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Constant(value) => f.debug_tuple("Constant").field(value).finish(),
+			Self::Sine { center, amplitude, period, phase } => f
+				.debug_struct("Sine")
+				.field("center", center)
+				.field("amplitude", amplitude)
+				.field("period", period)
+				.field("phase", phase)
+				.finish(),
+			Self::Ramp { rate, period } => {
+				f.debug_struct("Ramp").field("rate", rate).field("period", period).finish()
+			}
+			Self::Keyframes(keyframes) => f.debug_tuple("Keyframes").field(keyframes).finish(),
+			Self::Expression(_) => f.write_str("Expression"),
+		}
+	}
+}
+
+impl ParamCurve {
+	pub fn expression(f: impl Fn(f32) -> f32 + Send + Sync + 'static) -> Self {
+		Self::Expression(Arc::new(f))
+	}
+
+	pub fn sample(&self, time: f32) -> f32 {
+		match self {
+			Self::Constant(value) => *value,
+			Self::Sine { center, amplitude, period, phase } => {
+				if *period <= 0.0 {
+					return *center;
+				}
+				center + amplitude * (std::f32::consts::TAU * (time / period + phase)).sin()
+			}
+			Self::Ramp { rate, period } => {
+				let value = rate * time;
+				if *period > 0.0 {
+					value.rem_euclid(*period)
+				} else {
+					value
+				}
+			}
+			Self::Keyframes(keyframes) => {
+				let (Some(first), Some(last)) = (keyframes.first(), keyframes.last()) else {
+					return 0.0;
+				};
+				let time = if last.0 > 0.0 { time.rem_euclid(last.0) } else { time };
+				if time <= first.0 {
+					return first.1;
+				}
+				keyframes
+					.windows(2)
+					.find(|pair| time <= pair[1].0)
+					.map(|pair| {
+						let span = pair[1].0 - pair[0].0;
+						let t = if span > 0.0 { (time - pair[0].0) / span } else { 1.0 };
+						pair[0].1 + (pair[1].1 - pair[0].1) * t
+					})
+					.unwrap_or(last.1)
+			}
+			Self::Expression(f) => f(time),
+		}
+	}
+}
+
+/// Writes a sampled value into a material, such as a color channel or a scroll offset.
+pub type ParamSetter<M> = Arc<dyn Fn(&mut M, f32) + Send + Sync>;
+
+/// Handle of a registered material animation, for removing it later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialAnimationId(u64);
+
+struct MaterialAnimation<M: Asset> {
+	id: MaterialAnimationId,
+	material: Handle<M>,
+	curve: ParamCurve,
+	setter: ParamSetter<M>,
+}
+
+/// Material parameters driven by curves, registered by material handle.
+///
+/// The [animate_materials] system samples every curve once per frame and writes the value
+/// through its setter, so a new look needs a curve rather than a new system.
+#[derive(Resource)]
+pub struct MaterialAnimations<M: Asset> {
+	animations: Vec<MaterialAnimation<M>>,
+	next_id: u64,
+}
+
+impl<M: Asset> Default for MaterialAnimations<M> {
+	fn default() -> Self {
+		Self { animations: Vec::new(), next_id: 0 }
+	}
+}
+
+impl<M: Asset> MaterialAnimations<M> {
+	/// Drives a parameter of the material with the curve.
+	///
+	/// The animation holds the handle, keeping the material loaded until it is removed.
+	pub fn animate(
+		&mut self,
+		material: Handle<M>,
+		curve: ParamCurve,
+		setter: impl Fn(&mut M, f32) + Send + Sync + 'static,
+	) -> MaterialAnimationId {
+		let id = MaterialAnimationId(self.next_id);
+		self.next_id += 1;
+		self.animations
+			.push(MaterialAnimation { id, material, curve, setter: Arc::new(setter) });
+		id
+	}
+
+	/// Stops an animation, leaving the parameter at its last value.
+	pub fn remove(&mut self, id: MaterialAnimationId) -> bool {
+		let len = self.animations.len();
+		self.animations.retain(|animation| animation.id != id);
+		self.animations.len() != len
+	}
+
+	/// Stops every animation of the material.
+	pub fn remove_material(&mut self, material: &AssetId<M>) {
+		self.animations.retain(|animation| animation.material.id() != *material);
+	}
+
+	pub fn len(&self) -> usize {
+		self.animations.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.animations.is_empty()
+	}
+}
+
+/// Samples the registered curves and writes them into their materials.
+///
+/// Animations of materials that were removed are dropped.
+pub fn animate_materials<M: Asset>(
+	time: Res<Time>,
+	mut animations: ResMut<MaterialAnimations<M>>,
+	mut materials: ResMut<Assets<M>>,
+) {
+	if animations.is_empty() {
+		return;
+	}
+
+	let elapsed = time.elapsed_secs();
+	animations.animations.retain(|animation| {
+		let Some(material) = materials.get_mut(&animation.material) else {
+			log::debug!("Dropping the animation of removed material {:?}", animation.material);
+			return false;
+		};
+		(animation.setter)(material, animation.curve.sample(elapsed));
+		true
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shaders::outline::EdgeMaterial;
+	use bevy::time::TimeUpdateStrategy;
+	use std::time::Duration;
+
+	#[test]
+	fn test_curves_drive_material_params() {
+		let keyframes = ParamCurve::Keyframes(vec![(0.0, 0.0), (1.0, 2.0), (2.0, 0.0)]);
+		assert!((keyframes.sample(0.5) - 1.0).abs() < 1e-6);
+		assert!((keyframes.sample(3.5) - 1.0).abs() < 1e-6);
+		assert!((ParamCurve::Ramp { rate: 2.0, period: 3.0 }.sample(2.0) - 1.0).abs() < 1e-6);
+
+		let mut app = App::new();
+		app.add_plugins((AssetPlugin::default(), bevy::time::TimePlugin))
+			.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(250)))
+			.init_asset::<EdgeMaterial>()
+			.init_resource::<MaterialAnimations<EdgeMaterial>>()
+			.add_systems(Update, animate_materials::<EdgeMaterial>);
+		let material = app
+			.world_mut()
+			.resource_mut::<Assets<EdgeMaterial>>()
+			.add(EdgeMaterial::new(Vec4::ONE));
+		let removed = app
+			.world_mut()
+			.resource_mut::<Assets<EdgeMaterial>>()
+			.add(EdgeMaterial::new(Vec4::ONE));
+
+		let mut animations = app.world_mut().resource_mut::<MaterialAnimations<EdgeMaterial>>();
+		animations.animate(material.clone(), ParamCurve::expression(|t| t * 2.0), |m, value| {
+			m.base_color.x = value;
+		});
+		animations.animate(removed.clone(), ParamCurve::Constant(0.5), |m, value| {
+			m.base_color.y = value;
+		});
+		app.world_mut().resource_mut::<Assets<EdgeMaterial>>().remove(&removed);
+		drop(removed);
+
+		for _ in 0..3 {
+			app.update();
+		}
+		let elapsed = app.world().resource::<Time>().elapsed_secs();
+		let Some(animated) = app.world().resource::<Assets<EdgeMaterial>>().get(&material) else {
+			panic!("The animated material is gone");
+		};
+		assert!((animated.base_color.x - elapsed * 2.0).abs() < 1e-5);
+		assert_eq!(app.world().resource::<MaterialAnimations<EdgeMaterial>>().len(), 1);
+	}
+}
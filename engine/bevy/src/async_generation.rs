@@ -0,0 +1,292 @@
+use crate::cascade::CascadeChunk;
+use crate::chunk::{ChunkId, LoadedChunks};
+use crate::chunk_manager::{ChunkPipeline, SdfResource};
+use crate::cpu::ChunkSpawner;
+use crate::material::{ChunkKind, ChunkMaterialRegistry};
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use sdf::Sdf;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Generates chunk meshes on the [AsyncComputeTaskPool] instead of within the frame
+/// [manage_chunks](crate::manage_chunks) asks for them, so crossing a cascade boundary streams
+/// the new chunks in over the following frames rather than stalling on all of them at once.
+///
+/// Each chunk is generated by a [ChunkMeshTask] entity, and spawned by
+/// [finish_chunk_mesh_tasks] once its task completes. Tasks of chunks the camera moves away
+/// from are cancelled, as are all tasks when the SDF or its configs change. The
+/// [GenerationPool](crate::GenerationPool) lanes are not used while this resource is registered.
+#[derive(Resource)]
+pub struct AsyncChunkGeneration<S: Sdf + Send + Sync> {
+	/// Most chunks generating at once, nearest first, so a fast camera doesn't queue up chunks
+	/// it has left behind by the time they finish
+	pub max_in_flight: usize,
+	/// Most finished chunks spawned per frame, spreading out the mesh uploads
+	pub max_spawned_per_frame: usize,
+	/// The task entity generating each chunk, so tasks superseded by a newer one for the same
+	/// chunk are dropped rather than spawned
+	in_flight: HashMap<ChunkId, Entity>,
+	/// Marker for the SDF whose chunks are generated
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for AsyncChunkGeneration<S> {
+	fn default() -> Self {
+		Self {
+			max_in_flight: 64,
+			max_spawned_per_frame: 16,
+			in_flight: HashMap::new(),
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> AsyncChunkGeneration<S> {
+	pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+		self.max_in_flight = max_in_flight.max(1);
+		self
+	}
+
+	pub fn with_max_spawned_per_frame(mut self, max_spawned_per_frame: usize) -> Self {
+		self.max_spawned_per_frame = max_spawned_per_frame.max(1);
+		self
+	}
+
+	/// Whether the chunk is being generated
+	pub fn is_in_flight(&self, id: ChunkId) -> bool {
+		self.in_flight.contains_key(&id)
+	}
+
+	/// Number of chunks being generated
+	pub fn in_flight_len(&self) -> usize {
+		self.in_flight.len()
+	}
+
+	/// Number of chunks that can start generating before the limit is reached
+	pub(crate) fn capacity(&self) -> usize {
+		self.max_in_flight.saturating_sub(self.in_flight.len())
+	}
+
+	/// Starts generating the chunks on the task pool, up to the capacity, in the given order.
+	pub(crate) fn spawn(
+		&mut self,
+		commands: &mut Commands,
+		chunks: Vec<(CascadeChunk, ChunkId, ChunkKind)>,
+		pipeline: ChunkPipeline<'static, S>,
+	) where
+		S: 'static,
+	{
+		let chunks: Vec<_> = chunks.into_iter().take(self.capacity()).collect();
+		if chunks.is_empty() {
+			return;
+		}
+		let pipeline = Arc::new(pipeline);
+		let task_pool = AsyncComputeTaskPool::get();
+		for (chunk, id, kind) in chunks {
+			let pipeline = Arc::clone(&pipeline);
+			let task = task_pool.spawn(async move { pipeline.generate(&chunk) });
+			let entity = commands
+				.spawn(ChunkMeshTask::<S> { chunk, id, kind, task, sdf: PhantomData })
+				.id();
+			if let Some(superseded) = self.in_flight.insert(id, entity) {
+				commands.entity(superseded).try_despawn();
+			}
+		}
+	}
+
+	/// Cancels the chunks that are no longer wanted, despawning their tasks.
+	pub(crate) fn retain(
+		&mut self,
+		commands: &mut Commands,
+		mut wanted: impl FnMut(&ChunkId) -> bool,
+	) {
+		self.in_flight.retain(|id, entity| {
+			let keep = wanted(id);
+			if !keep {
+				commands.entity(*entity).try_despawn();
+			}
+			keep
+		});
+	}
+
+	/// Whether the task entity is the one generating its chunk, rather than one that was
+	/// cancelled or superseded.
+	fn is_current(&self, id: ChunkId, entity: Entity) -> bool {
+		self.in_flight.get(&id) == Some(&entity)
+	}
+}
+
+/// A chunk mesh being generated on the [AsyncComputeTaskPool].
+///
+/// Despawning the entity cancels the task.
+#[derive(Component)]
+pub struct ChunkMeshTask<S: Sdf + Send + Sync + 'static> {
+	pub chunk: CascadeChunk,
+	pub id: ChunkId,
+	pub kind: ChunkKind,
+	task: Task<Option<Mesh>>,
+	/// Marker for the SDF whose chunk is generated
+	pub sdf: PhantomData<S>,
+}
+
+/// Spawns the chunks whose meshes finished generating, marking them loaded, and drops the tasks
+/// that were cancelled.
+///
+/// Run it after manage_chunks, with an [AsyncChunkGeneration] resource registered.
+pub fn finish_chunk_mesh_tasks<S: Sdf + Send + Sync + 'static>(
+	mut commands: Commands,
+	mut task_query: Query<(Entity, &mut ChunkMeshTask<S>)>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	materials: Res<ChunkMaterialRegistry>,
+	sdf_resource: Res<SdfResource<S>>,
+	mut loaded_chunks: ResMut<LoadedChunks>,
+	mut generation: ResMut<AsyncChunkGeneration<S>>,
+) {
+	let mut spawned = 0;
+	for (entity, mut task) in task_query.iter_mut() {
+		if !generation.is_current(task.id, entity) {
+			commands.entity(entity).try_despawn();
+			continue;
+		}
+		if spawned >= generation.max_spawned_per_frame {
+			continue;
+		}
+		let Some(mesh) = block_on(future::poll_once(&mut task.task)) else {
+			continue;
+		};
+
+		commands.entity(entity).despawn();
+		generation.in_flight.remove(&task.id);
+		if let Some(mesh) = mesh {
+			ChunkSpawner::spawn_chunk_with_mesh(
+				&sdf_resource,
+				&mut commands,
+				&mut meshes,
+				&materials,
+				task.chunk,
+				mesh,
+				task.kind,
+			);
+			spawned += 1;
+		}
+		loaded_chunks.mark_loaded(task.id);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::chunk::{ChunkConfig, TerrainChunk};
+	use crate::chunk_manager::manage_chunks;
+	use crate::test_support::{ground_app, Ground};
+	use bevy::app::TaskPoolPlugin;
+	use bevy::ecs::system::RunSystemOnce;
+	use std::collections::HashSet;
+
+	#[test]
+	fn test_chunks_stream_in_over_frames() {
+		let mut app = ground_app(0.5);
+		app.world_mut().resource_mut::<ChunkConfig<Ground>>().number_of_rings = 2;
+		app.add_plugins(TaskPoolPlugin::default())
+			.insert_resource(AsyncChunkGeneration::<Ground>::default().with_max_in_flight(4))
+			.add_systems(
+				Update,
+				(manage_chunks::<Ground>, finish_chunk_mesh_tasks::<Ground>).chain(),
+			);
+
+		// The first frame only starts generating, up to the limit
+		app.update();
+		let in_flight =
+			|app: &App| app.world().resource::<AsyncChunkGeneration<Ground>>().in_flight_len();
+		assert!(in_flight(&app) <= 4);
+		let mut tasks = app.world_mut().query::<&ChunkMeshTask<Ground>>();
+		assert_eq!(tasks.iter(app.world()).count(), in_flight(&app));
+
+		let mut frames = 1;
+		while in_flight(&app) > 0 {
+			std::thread::sleep(std::time::Duration::from_millis(1));
+			app.update();
+			frames += 1;
+			assert!(frames < 1000, "The chunks never finished generating");
+		}
+		let mut chunks = app.world_mut().query::<&TerrainChunk>();
+		assert!(chunks.iter(app.world()).count() > 0);
+		assert!(!app.world().resource::<LoadedChunks>().chunks.is_empty());
+		assert_eq!(tasks.iter(app.world()).count(), 0);
+
+		// Moving away cancels the chunks left behind
+		app.world_mut()
+			.query_filtered::<&mut Transform, With<Camera3d>>()
+			.iter_mut(app.world_mut())
+			.for_each(|mut transform| transform.translation.x = 100.0);
+		app.update();
+		let started = in_flight(&app);
+		assert!(started > 0);
+		app.world_mut()
+			.query_filtered::<&mut Transform, With<Camera3d>>()
+			.iter_mut(app.world_mut())
+			.for_each(|mut transform| transform.translation.x = -100.0);
+		app.update();
+		app.update();
+		let wanted: HashSet<ChunkId> = tasks.iter(app.world()).map(|task| task.id).collect();
+		let generation = app.world().resource::<AsyncChunkGeneration<Ground>>();
+		assert!(wanted.iter().all(|id| generation.is_in_flight(*id)));
+	}
+
+	#[test]
+	fn test_tasks_of_a_replaced_sdf_are_never_spawned() {
+		let mut app = ground_app(0.75);
+		app.add_plugins(TaskPoolPlugin::default())
+			.insert_resource(AsyncChunkGeneration::<Ground>::default())
+			.add_systems(
+				Update,
+				(manage_chunks::<Ground>, finish_chunk_mesh_tasks::<Ground>).chain(),
+			);
+
+		// Start the chunks of the first SDF, then replace it while they are in flight
+		let Ok(()) = app.world_mut().run_system_once(manage_chunks::<Ground>) else {
+			panic!("expected manage_chunks to run");
+		};
+		let stale = app.world_mut().query::<&ChunkMeshTask<Ground>>().iter(app.world()).count();
+		assert!(stale > 0);
+		app.insert_resource(SdfResource::new(Ground(0.25)));
+
+		let in_flight =
+			|app: &App| app.world().resource::<AsyncChunkGeneration<Ground>>().in_flight_len();
+		let mut frames = 0;
+		loop {
+			app.update();
+			let tasks = app.world_mut().query::<&ChunkMeshTask<Ground>>().iter(app.world()).count();
+			assert_eq!(tasks, in_flight(&app), "Superseded tasks are despawned");
+			if in_flight(&app) == 0 {
+				break;
+			}
+			std::thread::sleep(std::time::Duration::from_millis(1));
+			frames += 1;
+			assert!(frames < 1000, "The chunks never finished generating");
+		}
+
+		// Every spawned surface is at the height of the new SDF
+		let mut chunks = app.world_mut().query::<(&TerrainChunk, &Mesh3d)>();
+		let meshes = app.world().resource::<Assets<Mesh>>();
+		let mut spawned = 0;
+		for (chunk, mesh) in chunks.iter(app.world()) {
+			let Some(positions) = meshes
+				.get(&mesh.0)
+				.and_then(|mesh| mesh.attribute(Mesh::ATTRIBUTE_POSITION))
+				.and_then(|positions| positions.as_float3())
+			else {
+				panic!("expected the chunk mesh to have positions");
+			};
+			let heights: HashSet<i32> = positions
+				.iter()
+				.map(|position| ((position[1] + chunk.chunk.origin.y) * 100.0).round() as i32)
+				.collect();
+			assert_eq!(heights, HashSet::from([25]), "Chunk at {}", chunk.chunk.origin);
+			spawned += 1;
+		}
+		assert!(spawned > 0);
+	}
+}
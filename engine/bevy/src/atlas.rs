@@ -0,0 +1,235 @@
+use bevy::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The kinds of generated content the [WorldAtlas] lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AtlasKind {
+	Valley,
+	Road,
+	Lake,
+	/// A point of interest, such as a rare landmark
+	Landmark,
+	Settlement,
+}
+
+impl AtlasKind {
+	pub const ALL: [AtlasKind; 5] = [
+		AtlasKind::Valley,
+		AtlasKind::Road,
+		AtlasKind::Lake,
+		AtlasKind::Landmark,
+		AtlasKind::Settlement,
+	];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			AtlasKind::Valley => "valley",
+			AtlasKind::Road => "road",
+			AtlasKind::Lake => "lake",
+			AtlasKind::Landmark => "landmark",
+			AtlasKind::Settlement => "settlement",
+		}
+	}
+}
+
+/// Identifies an [AtlasEntry] across runs, derived from the seed, kind and name of the entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AtlasId(pub u64);
+
+impl AtlasId {
+	pub fn new(seed: u32, kind: AtlasKind, name: &str) -> Self {
+		let bytes = seed.to_le_bytes().into_iter().chain(kind.name().bytes()).chain([0]);
+		Self(bytes.chain(name.bytes()).fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+			(hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+		}))
+	}
+}
+
+/// A generated feature in the atlas, with its extent in the xz plane of the terrain SDF.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtlasEntry {
+	pub id: AtlasId,
+	pub kind: AtlasKind,
+	pub name: String,
+	pub min: [f32; 2],
+	pub max: [f32; 2],
+}
+
+impl AtlasEntry {
+	pub fn min(&self) -> Vec2 {
+		Vec2::from_array(self.min)
+	}
+
+	pub fn max(&self) -> Vec2 {
+		Vec2::from_array(self.max)
+	}
+
+	pub fn center(&self) -> Vec2 {
+		(self.min() + self.max()) / 2.0
+	}
+
+	/// Distance from a position to the extent of the entry, 0 within it.
+	pub fn distance(&self, position: Vec2) -> f32 {
+		position.clamp(self.min(), self.max()).distance(position)
+	}
+
+	pub fn overlaps(&self, min: Vec2, max: Vec2) -> bool {
+		self.min().cmple(max).all() && self.max().cmpge(min).all()
+	}
+}
+
+/// A queryable register of the valleys, roads, landmarks and settlements generated for a
+/// world, so quests, map UIs and debugging can refer to generated content by a stable id.
+///
+/// Generators register what they produce as they go. Entries are kept in the local xz plane
+/// of the terrain SDF and export to JSON, like a [WorldSave](crate::WorldSave).
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorldAtlas {
+	/// The seed the ids are derived from
+	pub seed: u32,
+	/// Sorted by id
+	entries: Vec<AtlasEntry>,
+}
+
+impl WorldAtlas {
+	pub fn new(seed: u32) -> Self {
+		Self { seed, entries: Vec::new() }
+	}
+
+	/// Registers a feature spanning min to max, replacing any entry of the same kind and name.
+	pub fn register(
+		&mut self,
+		kind: AtlasKind,
+		name: impl Into<String>,
+		min: Vec2,
+		max: Vec2,
+	) -> AtlasId {
+		let name = name.into();
+		let id = AtlasId::new(self.seed, kind, &name);
+		let entry = AtlasEntry {
+			id,
+			kind,
+			name,
+			min: min.min(max).to_array(),
+			max: max.max(min).to_array(),
+		};
+		match self.entries.binary_search_by_key(&id, |entry| entry.id) {
+			Ok(index) => self.entries[index] = entry,
+			Err(index) => self.entries.insert(index, entry),
+		}
+		id
+	}
+
+	/// Registers a feature at a point, such as a landmark.
+	pub fn register_point(
+		&mut self,
+		kind: AtlasKind,
+		name: impl Into<String>,
+		position: Vec2,
+	) -> AtlasId {
+		self.register(kind, name, position, position)
+	}
+
+	pub fn get(&self, id: AtlasId) -> Option<&AtlasEntry> {
+		let index = self.entries.binary_search_by_key(&id, |entry| entry.id).ok()?;
+		self.entries.get(index)
+	}
+
+	pub fn entries(&self) -> &[AtlasEntry] {
+		&self.entries
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	pub fn clear(&mut self) {
+		self.entries.clear();
+	}
+
+	pub fn of_kind(&self, kind: AtlasKind) -> impl Iterator<Item = &AtlasEntry> {
+		self.entries.iter().filter(move |entry| entry.kind == kind)
+	}
+
+	/// The entries whose extent overlaps the region from min to max.
+	pub fn in_region(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = &AtlasEntry> {
+		self.entries.iter().filter(move |entry| entry.overlaps(min, max))
+	}
+
+	/// The entry nearest a position, of the kind if one is given.
+	pub fn nearest(&self, position: Vec2, kind: Option<AtlasKind>) -> Option<&AtlasEntry> {
+		self.entries
+			.iter()
+			.filter(|entry| kind.is_none_or(|kind| entry.kind == kind))
+			.min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)))
+	}
+
+	pub fn to_json(&self) -> Result<String, String> {
+		serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize atlas: {e}"))
+	}
+
+	pub fn from_json(json: &str) -> Result<Self, String> {
+		let mut atlas: Self =
+			serde_json::from_str(json).map_err(|e| format!("Failed to parse atlas: {e}"))?;
+		atlas.entries.sort_by_key(|entry| entry.id);
+		Ok(atlas)
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), String> {
+		std::fs::write(path, self.to_json()?)
+			.map_err(|e| format!("Failed to write atlas to {}: {e}", path.display()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_atlas_queries_by_kind_region_and_distance() {
+		let mut atlas = WorldAtlas::new(7);
+		let valley =
+			atlas.register(AtlasKind::Valley, "valley 0", Vec2::new(-10.0, -10.0), Vec2::ZERO);
+		let road =
+			atlas.register(AtlasKind::Road, "road 0", Vec2::new(0.0, -1.0), Vec2::new(80.0, 1.0));
+		let tree = atlas.register_point(AtlasKind::Landmark, "giant_tree", Vec2::new(40.0, 30.0));
+		assert_eq!(atlas.len(), 3);
+
+		// Ids are stable across runs and registrations, and differ between seeds
+		assert_eq!(valley, AtlasId::new(7, AtlasKind::Valley, "valley 0"));
+		assert_ne!(valley, AtlasId::new(8, AtlasKind::Valley, "valley 0"));
+		assert_ne!(valley, AtlasId::new(7, AtlasKind::Lake, "valley 0"));
+		atlas.register(AtlasKind::Road, "road 0", Vec2::new(0.0, -2.0), Vec2::new(80.0, 2.0));
+		assert_eq!(atlas.len(), 3);
+		assert_eq!(atlas.get(road).map(|road| road.max), Some([80.0, 2.0]));
+
+		let landmarks: Vec<_> = atlas.of_kind(AtlasKind::Landmark).map(|entry| entry.id).collect();
+		assert_eq!(landmarks, vec![tree]);
+		let mut near_origin: Vec<_> = atlas
+			.in_region(Vec2::splat(-5.0), Vec2::splat(5.0))
+			.map(|entry| entry.id)
+			.collect();
+		near_origin.sort();
+		let mut expected = vec![valley, road];
+		expected.sort();
+		assert_eq!(near_origin, expected);
+
+		let position = Vec2::new(38.0, 20.0);
+		assert_eq!(atlas.nearest(position, None).map(|entry| entry.id), Some(tree));
+		assert_eq!(
+			atlas.nearest(position, Some(AtlasKind::Valley)).map(|entry| entry.id),
+			Some(valley)
+		);
+		assert_eq!(atlas.nearest(position, Some(AtlasKind::Settlement)), None);
+
+		let json = atlas.to_json().unwrap_or_default();
+		assert!(json.contains("\"kind\": \"landmark\""));
+		assert_eq!(WorldAtlas::from_json(&json), Ok(atlas));
+	}
+}
@@ -0,0 +1,318 @@
+use crate::shaders::outline::Bark;
+use bevy::{
+	asset::RenderAssetUsages,
+	image::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor},
+	prelude::*,
+	render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// A species of bark, generated into tileable normal and roughness textures.
+///
+/// The bark is ridged noise on a lattice that wraps around the tile, with fewer cells down the
+/// tile than across it, so ridges run up the trunk and the textures tile without seams.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BarkTemplate {
+	/// Name the textures are cached by in a [BarkLibrary]
+	pub name: String,
+	pub seed: u32,
+	/// Pixels along each side of the textures
+	pub size: u32,
+	/// Ridges across the tile
+	pub ridges: u32,
+	/// How many times longer a ridge runs than it is wide
+	pub stretch: u32,
+	/// Octaves of ridged noise, each with twice the ridges of the last
+	pub octaves: u32,
+	/// Exponent narrowing the ridges, 1 for rounded ridges
+	pub sharpness: f32,
+	/// Height of the ridges in tile widths, which steepens the normals
+	pub depth: f32,
+	/// Roughness in the furrows and on the ridges
+	pub roughness: (f32, f32),
+	/// World size of a tile on the trunk
+	pub tile_size: f32,
+}
+
+impl BarkTemplate {
+	pub fn new(name: impl Into<String>) -> Self {
+		Self {
+			name: name.into(),
+			seed: 0,
+			size: 128,
+			ridges: 8,
+			stretch: 4,
+			octaves: 3,
+			sharpness: 2.0,
+			depth: 0.02,
+			roughness: (0.95, 0.75),
+			tile_size: 0.5,
+		}
+	}
+
+	/// Deep, blocky furrows.
+	pub fn oak() -> Self {
+		Self::new("oak").with_ridges(6, 3).with_sharpness(3.0).with_depth(0.03)
+	}
+
+	/// Smooth bark with faint bands running around the trunk.
+	pub fn birch() -> Self {
+		Self { seed: 1, stretch: 1, octaves: 2, ..Self::new("birch") }
+			.with_sharpness(0.5)
+			.with_depth(0.005)
+			.with_roughness(0.7, 0.45)
+	}
+
+	/// Long, narrow plates.
+	pub fn pine() -> Self {
+		Self { seed: 2, ..Self::new("pine") }.with_ridges(10, 5).with_depth(0.02)
+	}
+
+	pub fn with_seed(mut self, seed: u32) -> Self {
+		self.seed = seed;
+		self
+	}
+
+	pub fn with_size(mut self, size: u32) -> Self {
+		self.size = size.max(1);
+		self
+	}
+
+	/// Ridges across the tile, each running stretch times longer than it is wide.
+	pub fn with_ridges(mut self, ridges: u32, stretch: u32) -> Self {
+		self.ridges = ridges.max(1);
+		self.stretch = stretch.max(1);
+		self
+	}
+
+	pub fn with_sharpness(mut self, sharpness: f32) -> Self {
+		self.sharpness = sharpness.max(f32::EPSILON);
+		self
+	}
+
+	pub fn with_depth(mut self, depth: f32) -> Self {
+		self.depth = depth.max(0.0);
+		self
+	}
+
+	pub fn with_roughness(mut self, furrows: f32, ridges: f32) -> Self {
+		self.roughness = (furrows.clamp(0.0, 1.0), ridges.clamp(0.0, 1.0));
+		self
+	}
+
+	pub fn with_tile_size(mut self, tile_size: f32) -> Self {
+		self.tile_size = tile_size.max(f32::EPSILON);
+		self
+	}
+
+	/// Height of the bark at a point of the tile, from 0 in the furrows to 1 on the ridges.
+	///
+	/// Repeats every 1 along both axes.
+	pub fn height(&self, uv: Vec2) -> f32 {
+		let mut period = UVec2::new(self.ridges, (self.ridges / self.stretch).max(1));
+		let (mut total, mut amplitude, mut weight) = (0.0, 1.0, 0.0);
+		for octave in 0..self.octaves.max(1) {
+			let noise = tiled_value_noise(uv, period, self.seed.wrapping_add(octave));
+			total += (1.0 - (2.0 * noise - 1.0).abs()).powf(self.sharpness) * amplitude;
+			weight += amplitude;
+			amplitude *= 0.5;
+			period *= 2;
+		}
+		total / weight
+	}
+
+	/// Heights of every pixel, in rows from the top of the tile.
+	pub fn heights(&self) -> Vec<f32> {
+		let size = self.size.max(1);
+		(0..size * size)
+			.into_par_iter()
+			.map(|index| {
+				let pixel = UVec2::new(index % size, index / size);
+				self.height((pixel.as_vec2() + 0.5) / size as f32)
+			})
+			.collect()
+	}
+
+	/// A tangent space normal map of the heights, wrapping around the edges of the tile.
+	pub fn normal_image(&self, heights: &[f32]) -> Image {
+		let size = self.size.max(1) as usize;
+		let at = |x: usize, y: usize| heights[(y % size) * size + x % size];
+		let scale = self.depth * size as f32 / 2.0;
+		let pixels = (0..size * size).flat_map(|index| {
+			let (x, y) = (index % size, index / size);
+			let dx = (at(x + 1, y) - at(x + size - 1, y)) * scale;
+			let dy = (at(x, y + 1) - at(x, y + size - 1)) * scale;
+			let normal = Vec3::new(-dx, -dy, 1.0).normalize();
+			let encoded = (normal * 0.5 + 0.5) * 255.0;
+			[encoded.x as u8, encoded.y as u8, encoded.z as u8, 255]
+		});
+		tiled_image(self.size, pixels.collect(), TextureFormat::Rgba8Unorm)
+	}
+
+	/// Roughness of the heights, rougher in the furrows than on the ridges.
+	pub fn roughness_image(&self, heights: &[f32]) -> Image {
+		let (furrows, ridges) = self.roughness;
+		let pixels = heights
+			.iter()
+			.map(|height| ((furrows + (ridges - furrows) * height) * 255.0) as u8)
+			.collect();
+		tiled_image(self.size, pixels, TextureFormat::R8Unorm)
+	}
+
+	/// Generates the textures and adds them to the image assets.
+	pub fn generate(&self, images: &mut Assets<Image>) -> BarkTextures {
+		let heights = self.heights();
+		BarkTextures {
+			normal: images.add(self.normal_image(&heights)),
+			roughness: images.add(self.roughness_image(&heights)),
+			bark: Bark::new(self.tile_size),
+		}
+	}
+}
+
+/// The generated textures of a [BarkTemplate], laid over a material with
+/// [EdgeMaterial::with_bark](crate::shaders::outline::EdgeMaterial::with_bark).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BarkTextures {
+	pub normal: Handle<Image>,
+	pub roughness: Handle<Image>,
+	/// How the textures are laid over a material
+	pub bark: Bark,
+}
+
+/// Bark textures generated from their templates, cached by template name.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct BarkLibrary {
+	textures: HashMap<String, BarkTextures>,
+}
+
+impl BarkLibrary {
+	pub fn get(&self, name: &str) -> Option<&BarkTextures> {
+		self.textures.get(name)
+	}
+
+	/// The textures of the template, generated the first time they are asked for.
+	pub fn textures(
+		&mut self,
+		template: &BarkTemplate,
+		images: &mut Assets<Image>,
+	) -> BarkTextures {
+		self.textures
+			.entry(template.name.clone())
+			.or_insert_with(|| {
+				log::info!("Generating {} bark textures", template.name);
+				template.generate(images)
+			})
+			.clone()
+	}
+
+	pub fn len(&self) -> usize {
+		self.textures.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.textures.is_empty()
+	}
+}
+
+/// A square image of the pixels that repeats when sampled past its edges.
+fn tiled_image(size: u32, pixels: Vec<u8>, format: TextureFormat) -> Image {
+	let size = size.max(1);
+	let mut image = Image::new(
+		Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+		TextureDimension::D2,
+		pixels,
+		format,
+		RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+	);
+	image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+		address_mode_u: ImageAddressMode::Repeat,
+		address_mode_v: ImageAddressMode::Repeat,
+		..ImageSamplerDescriptor::linear()
+	});
+	image
+}
+
+/// Hash of a lattice point into 0 to 1.
+fn lattice_hash(x: u32, y: u32, seed: u32) -> f32 {
+	let mut hash = x.wrapping_mul(0x8da6_b343) ^ y.wrapping_mul(0xd816_3841) ^ seed;
+	hash ^= hash >> 13;
+	hash = hash.wrapping_mul(0x5bd1_e995);
+	hash ^= hash >> 15;
+	hash as f32 / u32::MAX as f32
+}
+
+/// Smoothly interpolated lattice noise from 0 to 1 with the lattice wrapping around the
+/// period, so it repeats every 1 along both axes.
+fn tiled_value_noise(uv: Vec2, period: UVec2, seed: u32) -> f32 {
+	let p = uv * period.as_vec2();
+	let cell = p.floor();
+	let f = p - cell;
+	let u = f * f * (Vec2::splat(3.0) - 2.0 * f);
+	let corner = |dx: i64, dy: i64| {
+		let x = (cell.x as i64 + dx).rem_euclid(period.x as i64) as u32;
+		let y = (cell.y as i64 + dy).rem_euclid(period.y as i64) as u32;
+		lattice_hash(x, y, seed)
+	};
+	let bottom = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * u.x;
+	let top = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * u.x;
+	bottom + (top - bottom) * u.y
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_bark_tiles_with_ridges_up_the_trunk() {
+		let oak = BarkTemplate::oak().with_size(32);
+
+		// The bark repeats across and down the tile
+		for i in 0..16 {
+			let uv = Vec2::new(i as f32 * 0.137, i as f32 * 0.291).fract();
+			let height = oak.height(uv);
+			assert!((0.0..=1.0).contains(&height));
+			assert!((height - oak.height(uv + Vec2::X)).abs() < 1e-4);
+			assert!((height - oak.height(uv + Vec2::Y)).abs() < 1e-4);
+		}
+
+		// Ridges run up the trunk, so the bark changes faster across the tile than down it
+		let step = 0.01;
+		let (mut across, mut down) = (0.0, 0.0);
+		for i in 0..100 {
+			let uv = Vec2::new(i as f32 * 0.0731, i as f32 * 0.0417).fract();
+			across += (oak.height(uv + Vec2::X * step) - oak.height(uv)).abs();
+			down += (oak.height(uv + Vec2::Y * step) - oak.height(uv)).abs();
+		}
+		assert!(across > down * 1.5, "{across} across and {down} down the tile");
+
+		let heights = oak.heights();
+		assert_eq!(heights.len(), 32 * 32);
+		let normal = oak.normal_image(&heights);
+		assert_eq!(normal.texture_descriptor.format, TextureFormat::Rgba8Unorm);
+		let roughness = oak.roughness_image(&heights);
+		assert_eq!(roughness.texture_descriptor.size.width, 32);
+		assert!(roughness
+			.data
+			.as_ref()
+			.is_some_and(|data| data.len() == 32 * 32 && data.iter().all(|&r| r >= 191)));
+	}
+
+	#[test]
+	fn test_library_generates_each_template_once() {
+		let mut images = Assets::<Image>::default();
+		let mut library = BarkLibrary::default();
+		let pine = BarkTemplate::pine().with_size(8);
+
+		let textures = library.textures(&pine, &mut images);
+		assert_eq!(library.textures(&pine, &mut images), textures);
+		assert_eq!(images.len(), 2);
+		assert_eq!(library.get("pine"), Some(&textures));
+		assert_eq!(textures.bark.tile_size, pine.tile_size);
+
+		library.textures(&BarkTemplate::birch().with_size(8), &mut images);
+		assert_eq!(library.len(), 2);
+		assert_eq!(images.len(), 4);
+	}
+}
@@ -0,0 +1,212 @@
+use crate::cascade::CascadeChunk;
+use crate::chunk::ChunkId;
+use crate::chunk_manager::ChunkSources;
+use crate::generation_pool::GenerationPool;
+use crate::occupancy::ChunkOccupancy;
+use bevy::prelude::*;
+use rayon::prelude::*;
+use sdf::Sdf;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Whether the world is still generating the ground around the spawn point.
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WorldBootState {
+	#[default]
+	Booting,
+	Playing,
+}
+
+/// How far the boot has got, for a loading screen to show.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BootProgress {
+	/// Chunks generated so far
+	pub generated: usize,
+	/// Chunks to generate before play starts
+	pub total: usize,
+}
+
+impl BootProgress {
+	/// Share of the chunks generated, from 0 to 1.
+	pub fn fraction(&self) -> f32 {
+		if self.total == 0 {
+			return 1.0;
+		}
+		self.generated as f32 / self.total as f32
+	}
+}
+
+/// Generates the innermost cascade rings around the camera before play starts, so play
+/// starts on solid ground rather than with the world popping in around the spawn point.
+///
+/// The rings are planned from where the camera is when [boot_world] first runs, and generated
+/// a few chunks a frame while the state is [WorldBootState::Booting]. Once they are all
+/// generated the state moves to [WorldBootState::Playing], and
+/// [manage_chunks](crate::manage_chunks) takes the booted meshes instead of generating them.
+#[derive(Resource)]
+pub struct WorldBoot<S: Sdf + Send + Sync> {
+	/// Innermost cascade rings generated before play, 1 for the ring around the camera
+	pub rings: u8,
+	/// Most chunks generated per frame, so a loading screen keeps drawing
+	pub chunks_per_frame: usize,
+	pending: Vec<(ChunkId, CascadeChunk)>,
+	booted: HashMap<ChunkId, (CascadeChunk, Option<Mesh>)>,
+	planned: bool,
+	/// Marker for the SDF whose chunks are booted
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for WorldBoot<S> {
+	fn default() -> Self {
+		Self {
+			rings: 1,
+			chunks_per_frame: 8,
+			pending: Vec::new(),
+			booted: HashMap::new(),
+			planned: false,
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> WorldBoot<S> {
+	pub fn with_rings(mut self, rings: u8) -> Self {
+		self.rings = rings.max(1);
+		self
+	}
+
+	pub fn with_chunks_per_frame(mut self, chunks_per_frame: usize) -> Self {
+		self.chunks_per_frame = chunks_per_frame.max(1);
+		self
+	}
+
+	/// Number of booted chunks waiting to be loaded
+	pub fn len(&self) -> usize {
+		self.booted.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.booted.is_empty()
+	}
+
+	/// Takes the booted mesh of the chunk, if it was booted at the same resolution.
+	pub(crate) fn take(&mut self, id: &ChunkId, chunk: &CascadeChunk) -> Option<Option<Mesh>> {
+		let (booted, mesh) = self.booted.remove(id)?;
+		(booted.res_2 == chunk.res_2).then_some(mesh)
+	}
+}
+
+/// Generates the chunks of the [WorldBoot] rings within the frame budget, reporting them in
+/// [BootProgress], and starts play once they are all generated.
+///
+/// Run it while the state is [WorldBootState::Booting], and manage_chunks only once it is
+/// [WorldBootState::Playing]. Chunks are generated on the near lane of the [GenerationPool]
+/// when there is one.
+pub fn boot_world<S: Sdf + Send + Sync + 'static>(
+	camera_query: Query<&Transform, With<Camera3d>>,
+	mut boot: ResMut<WorldBoot<S>>,
+	mut progress: ResMut<BootProgress>,
+	sources: ChunkSources<S>,
+	generation_pool: Option<Res<GenerationPool>>,
+	mut next_state: ResMut<NextState<WorldBootState>>,
+) {
+	let Ok(camera_transform) = camera_query.single() else {
+		return;
+	};
+
+	// The rings are planned again if anything that goes into them changes while booting
+	if !boot.planned || sources.is_changed() {
+		let position = sources.sdf_resource.transform.to_local(camera_transform.translation);
+		let cascade = sources.cascade();
+		let Ok(output) = cascade.chunks(position) else {
+			return;
+		};
+		// With some slack, as ring sizes are products of floats
+		let largest = cascade.size_for_ring(boot.rings.max(1) - 1).max_element() * 1.001;
+		// Chunks the occupancy check skips are never generated
+		let occupied = |chunk: &CascadeChunk| {
+			sources.chunk_config.occupancy.is_none_or(|check| {
+				check.classify_at(
+					sources.sdf_resource.sdf.as_ref(),
+					chunk,
+					sources.sdf_resource.iso_level,
+				) == ChunkOccupancy::Mixed
+			})
+		};
+		let pending: Vec<_> = output
+			.cascade()
+			.into_iter()
+			.filter(|chunk| chunk.size.max_element() <= largest && occupied(chunk))
+			.map(|chunk| (sources.chunk_config.chunk_id(&chunk), chunk))
+			.collect();
+		*progress = BootProgress { generated: 0, total: pending.len() };
+		boot.pending = pending;
+		boot.booted.clear();
+		boot.planned = true;
+	}
+
+	let count = boot.chunks_per_frame.max(1).min(boot.pending.len());
+	let batch: Vec<_> = boot.pending.drain(..count).collect();
+	let pipeline = sources.pipeline();
+	let generate = |(id, chunk): &(ChunkId, CascadeChunk)| (*id, *chunk, pipeline.generate(chunk));
+	let booted: Vec<_> = match generation_pool.as_deref() {
+		Some(pool) => pool.near.run(&batch, generate),
+		None => batch.par_iter().map(generate).collect(),
+	};
+	progress.generated += booted.len();
+	for (id, chunk, mesh) in booted {
+		boot.booted.insert(id, (chunk, mesh));
+	}
+
+	if boot.pending.is_empty() {
+		log::info!("Booted {} chunks around the spawn point", progress.total);
+		next_state.set(WorldBootState::Playing);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::chunk::{ChunkConfig, LoadedChunks};
+	use crate::chunk_manager::manage_chunks;
+	use crate::test_support::{ground_app, Ground};
+	use bevy::state::app::StatesPlugin;
+
+	#[test]
+	fn test_play_starts_once_the_inner_ring_is_generated() {
+		let mut app = ground_app(0.5);
+		app.world_mut().resource_mut::<ChunkConfig<Ground>>().number_of_rings = 2;
+		app.add_plugins(StatesPlugin)
+			.insert_resource(WorldBoot::<Ground>::default().with_chunks_per_frame(4))
+			.init_resource::<BootProgress>()
+			.init_state::<WorldBootState>()
+			.add_systems(
+				Update,
+				(
+					boot_world::<Ground>.run_if(in_state(WorldBootState::Booting)),
+					manage_chunks::<Ground>.run_if(in_state(WorldBootState::Playing)),
+				),
+			);
+
+		// The inner ring takes a few frames, and nothing is spawned meanwhile
+		app.update();
+		let progress = *app.world().resource::<BootProgress>();
+		assert_eq!(progress.generated, 4);
+		assert!(progress.total > 4, "{progress:?}");
+		assert!(app.world().resource::<LoadedChunks>().chunks.is_empty());
+
+		let mut frames = 1;
+		while *app.world().resource::<State<WorldBootState>>() == WorldBootState::Booting {
+			app.update();
+			frames += 1;
+			assert!(frames < 100, "The boot never finished");
+		}
+		assert_eq!(frames, progress.total.div_ceil(4) + 1);
+		assert_eq!(app.world().resource::<BootProgress>().fraction(), 1.0);
+
+		// The first frame of play loads the booted chunks rather than generating them again
+		app.update();
+		assert!(app.world().resource::<WorldBoot<Ground>>().is_empty());
+		assert!(!app.world().resource::<LoadedChunks>().chunks.is_empty());
+	}
+}
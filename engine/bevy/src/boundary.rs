@@ -0,0 +1,191 @@
+use crate::chunk::ChunkConfig;
+use crate::chunk_manager::SdfResource;
+use bevy::prelude::*;
+use sdf::{Bounds, Sdf, SignUniformIntervals};
+
+/// What lies past world_size in X and Z.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, Default, PartialEq))]
+pub enum WorldBoundary {
+	/// The world repeats every world_size, so there is no edge
+	#[default]
+	Wrap,
+	/// The world ends at the edge of a square of world_size centered on the SDF origin
+	Edge(WorldEdge),
+}
+
+/// How the end of a world that doesn't wrap is presented.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, PartialEq))]
+pub struct WorldEdge {
+	/// Width of the band inside the edge over which the terrain sinks
+	pub fade_width: f32,
+	/// How far the terrain has sunk at the edge
+	pub fade_depth: f32,
+	/// Distance from the edge within which the edge is pointed out
+	pub hint_distance: f32,
+}
+
+impl WorldEdge {
+	/// An edge that sinks the terrain by fade_depth over fade_width, hinted from the start of
+	/// the fade.
+	pub fn new(fade_width: f32, fade_depth: f32) -> Self {
+		Self { fade_width, fade_depth, hint_distance: fade_width }
+	}
+
+	pub fn with_hint_distance(mut self, hint_distance: f32) -> Self {
+		self.hint_distance = hint_distance;
+		self
+	}
+}
+
+/// The extent of a world that ends at an edge, in the local space of its SDF.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldBounds {
+	/// Half the world size
+	pub half_extent: f32,
+	pub edge: WorldEdge,
+}
+
+impl WorldBounds {
+	pub fn new(world_size: f32, edge: WorldEdge) -> Self {
+		Self { half_extent: world_size / 2.0, edge }
+	}
+
+	/// Distance in XZ from the point to the nearest edge, negative past it
+	pub fn distance_to_edge(&self, p: Vec3) -> f32 {
+		self.half_extent - p.x.abs().max(p.z.abs())
+	}
+
+	/// Keeps the point inside the edge, leaving its height alone
+	pub fn clamp(&self, p: Vec3) -> Vec3 {
+		Vec3::new(
+			p.x.clamp(-self.half_extent, self.half_extent),
+			p.y,
+			p.z.clamp(-self.half_extent, self.half_extent),
+		)
+	}
+
+	/// How far the terrain has sunk at the point, from 0 inside the fade to 1 at the edge
+	pub fn fade(&self, p: Vec3) -> f32 {
+		if self.edge.fade_width <= 0.0 {
+			return 0.0;
+		}
+		let t = 1.0 - self.distance_to_edge(p) / self.edge.fade_width;
+		let t = t.clamp(0.0, 1.0);
+		t * t * (3.0 - 2.0 * t)
+	}
+
+	/// Distance to the edge when the point is close enough to point it out
+	pub fn hint(&self, p: Vec3) -> Option<f32> {
+		let distance = self.distance_to_edge(p);
+		(distance <= self.edge.hint_distance).then_some(distance.max(0.0))
+	}
+}
+
+/// Sinks an SDF toward the edge of the world.
+///
+/// The sinking steepens the field by up to 1.5 fade_depth / fade_width, so keep the fade wide
+/// relative to its depth for the distance to stay a usable bound.
+pub struct EdgeFade<S> {
+	sdf: S,
+	bounds: WorldBounds,
+}
+
+impl<S: Sdf> EdgeFade<S> {
+	pub fn new(sdf: S, bounds: WorldBounds) -> Self {
+		Self { sdf, bounds }
+	}
+}
+
+impl<S: Sdf> Sdf for EdgeFade<S> {
+	fn distance(&self, p: Vec3) -> f32 {
+		self.sdf.distance(p) + self.bounds.edge.fade_depth * self.bounds.fade(p)
+	}
+
+	fn sign_uniform_on_y(&self, x: f32, z: f32) -> SignUniformIntervals {
+		// Intervals of the unsunk SDF no longer hold within the fade
+		if self.bounds.fade(Vec3::new(x, 0.0, z)) > 0.0 {
+			return SignUniformIntervals::default();
+		}
+		self.sdf.sign_uniform_on_y(x, z)
+	}
+
+	fn bounds(&self) -> Bounds {
+		self.sdf.bounds()
+	}
+}
+
+/// Marks an entity, such as a character controller, that can't walk past the world edge.
+#[derive(Component, Default)]
+pub struct WorldConfined;
+
+/// Holds confined entities inside the edge of the world, acting as an invisible wall.
+///
+/// Does nothing while the world wraps.
+pub fn confine_to_world<S: Sdf + Send + Sync + 'static>(
+	chunk_config: Res<ChunkConfig<S>>,
+	sdf_resource: Res<SdfResource<S>>,
+	mut query: Query<&mut Transform, With<WorldConfined>>,
+) {
+	let Some(bounds) = chunk_config.world_bounds() else {
+		return;
+	};
+
+	for mut transform in &mut query {
+		let local = sdf_resource.transform.to_local(transform.translation);
+		if bounds.distance_to_edge(local) < 0.0 {
+			transform.translation = sdf_resource.transform.to_world(bounds.clamp(local));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::Ground;
+
+	#[test]
+	fn test_terrain_sinks_toward_the_edge() {
+		let bounds = WorldBounds::new(100.0, WorldEdge::new(10.0, 4.0));
+		let sdf = EdgeFade::new(Ground(0.0), bounds);
+
+		// Untouched inside the fade, fully sunk at and past the edge
+		assert_eq!(sdf.distance(Vec3::new(39.0, 0.0, 0.0)), 0.0);
+		assert!((sdf.distance(Vec3::new(0.0, 0.0, -50.0)) - 4.0).abs() < 1e-5);
+		assert!((sdf.distance(Vec3::new(80.0, 0.0, 0.0)) - 4.0).abs() < 1e-5);
+		let mid = sdf.distance(Vec3::new(45.0, 0.0, 0.0));
+		assert!(mid > 0.0 && mid < 4.0);
+
+		assert_eq!(bounds.hint(Vec3::new(30.0, 0.0, 0.0)), None);
+		assert_eq!(bounds.hint(Vec3::new(47.0, 0.0, 0.0)), Some(3.0));
+		assert_eq!(bounds.hint(Vec3::new(60.0, 0.0, 0.0)), Some(0.0));
+	}
+
+	#[test]
+	fn test_confined_entities_stop_at_the_edge() {
+		let mut app = App::new();
+		app.insert_resource(ChunkConfig::<Ground> {
+			world_size: 100.0,
+			boundary: WorldBoundary::Edge(WorldEdge::new(10.0, 4.0)),
+			..default()
+		})
+		.insert_resource(SdfResource::new(Ground(0.0)))
+		.add_systems(Update, confine_to_world::<Ground>);
+		let confined = app
+			.world_mut()
+			.spawn((WorldConfined, Transform::from_xyz(70.0, 3.0, -20.0)))
+			.id();
+		let free = app.world_mut().spawn(Transform::from_xyz(70.0, 3.0, -20.0)).id();
+		app.update();
+
+		let translation = |entity: Entity| {
+			let Some(transform) = app.world().get::<Transform>(entity) else {
+				panic!("{entity:?} has no transform");
+			};
+			transform.translation
+		};
+		assert_eq!(translation(confined), Vec3::new(50.0, 3.0, -20.0));
+		assert_eq!(translation(free), Vec3::new(70.0, 3.0, -20.0));
+	}
+}
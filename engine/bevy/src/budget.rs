@@ -0,0 +1,187 @@
+use crate::cascade::CascadeChunk;
+use crate::chunk::ChunkId;
+use crate::material::ChunkKind;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// Limits how much chunk generation [manage_chunks](crate::manage_chunks) does in a frame, so
+/// a teleport that dirties the whole cascade spreads its cost over the following frames.
+///
+/// The chunks nearest the camera are generated first, cascade and grid alike. Chunks over the
+/// budget are left unloaded and picked up again by the next frame, nearest first again, so
+/// those the camera has moved away from in the meantime are never generated. Only chunks that
+/// are generated count towards it, not those respawned from a cache, booted or prewarmed.
+#[derive(Resource)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Resource, Default))]
+pub struct ChunkBudgetConfig<S: Sdf + Send + Sync> {
+	/// Most chunks meshed per frame
+	pub max_chunks_per_frame: usize,
+	/// Milliseconds of generation after which a frame stops starting on more chunks. The
+	/// chunks are generated a batch per thread at a time, so the last batch may overrun it.
+	pub max_millis_per_frame: Option<f32>,
+	/// Marker for the SDF whose chunks are budgeted
+	#[cfg_attr(feature = "reflect", reflect(ignore))]
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for ChunkBudgetConfig<S> {
+	fn default() -> Self {
+		Self { max_chunks_per_frame: 16, max_millis_per_frame: None, sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync> ChunkBudgetConfig<S> {
+	pub fn with_max_chunks_per_frame(mut self, max_chunks_per_frame: usize) -> Self {
+		self.max_chunks_per_frame = max_chunks_per_frame.max(1);
+		self
+	}
+
+	pub fn with_max_millis_per_frame(mut self, max_millis_per_frame: f32) -> Self {
+		self.max_millis_per_frame = Some(max_millis_per_frame.max(0.0));
+		self
+	}
+
+	/// The time a frame may spend generating, if limited
+	pub fn max_duration(&self) -> Option<Duration> {
+		self.max_millis_per_frame.map(|millis| Duration::from_secs_f32(millis / 1000.0))
+	}
+
+	/// Keeps the cascade and grid chunks nearest the camera, up to the chunks per frame, each
+	/// ordered nearest first.
+	pub(crate) fn nearest(
+		&self,
+		cascade: Vec<(CascadeChunk, ChunkId)>,
+		grid: Vec<(CascadeChunk, ChunkId)>,
+		camera_pos: Vec3,
+	) -> (Vec<(CascadeChunk, ChunkId)>, Vec<(CascadeChunk, ChunkId)>) {
+		let mut chunks = by_distance(&cascade, &grid, camera_pos);
+		chunks.truncate(self.max_chunks_per_frame);
+		split_kinds(&chunks)
+	}
+}
+
+/// The cascade and grid chunks together, nearest the camera first.
+pub(crate) fn by_distance(
+	cascade: &[(CascadeChunk, ChunkId)],
+	grid: &[(CascadeChunk, ChunkId)],
+	camera_pos: Vec3,
+) -> Vec<(CascadeChunk, ChunkId, ChunkKind)> {
+	let distance = |chunk: &CascadeChunk| {
+		let center = chunk.origin + chunk.size / 2.0;
+		center.distance_squared(camera_pos)
+	};
+	let tagged = |chunks: &[(CascadeChunk, ChunkId)], kind: ChunkKind| {
+		chunks.iter().map(move |(chunk, id)| (*chunk, *id, kind)).collect::<Vec<_>>()
+	};
+	let mut chunks = tagged(cascade, ChunkKind::Cascade);
+	chunks.extend(tagged(grid, ChunkKind::Grid));
+	chunks.sort_by(|(a, ..), (b, ..)| distance(a).total_cmp(&distance(b)));
+	chunks
+}
+
+/// Splits the chunks into the cascade and grid chunks, each in the order given.
+pub(crate) fn split_kinds(
+	chunks: &[(CascadeChunk, ChunkId, ChunkKind)],
+) -> (Vec<(CascadeChunk, ChunkId)>, Vec<(CascadeChunk, ChunkId)>) {
+	let of_kind = |of: ChunkKind| {
+		chunks
+			.iter()
+			.filter(|(.., kind)| *kind == of)
+			.map(|(chunk, id, _)| (*chunk, *id))
+			.collect()
+	};
+	(of_kind(ChunkKind::Cascade), of_kind(ChunkKind::Grid))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::boot::{boot_world, BootProgress, WorldBoot, WorldBootState};
+	use crate::chunk::{ChunkConfig, LoadedChunks, TerrainChunk};
+	use crate::chunk_manager::manage_chunks;
+	use crate::test_support::{ground_app, Ground};
+	use bevy::state::app::StatesPlugin;
+
+	/// Two rings of chunks, none of them skipped by the occupancy check
+	fn app() -> App {
+		let mut app = ground_app(0.5);
+		{
+			let mut config = app.world_mut().resource_mut::<ChunkConfig<Ground>>();
+			config.number_of_rings = 2;
+			config.occupancy = None;
+		}
+		app
+	}
+
+	#[test]
+	fn test_chunks_load_nearest_first_within_budget() {
+		let mut app = app();
+		app.insert_resource(ChunkBudgetConfig::<Ground>::default().with_max_chunks_per_frame(3))
+			.add_systems(Update, manage_chunks::<Ground>);
+
+		let loaded = |app: &App| app.world().resource::<LoadedChunks>().chunks.len();
+		app.update();
+		assert_eq!(loaded(&app), 3);
+
+		// The first chunks are those nearest the camera
+		let mut chunks = app.world_mut().query::<(Entity, &TerrainChunk)>();
+		let distance =
+			|chunk: &TerrainChunk| (chunk.chunk.origin + chunk.chunk.size / 2.0).length();
+		let first: Vec<(Entity, f32)> = chunks
+			.iter(app.world())
+			.map(|(entity, chunk)| (entity, distance(chunk)))
+			.collect();
+		let nearest = first.iter().map(|(_, distance)| *distance).fold(0.0, f32::max);
+
+		// The rest stream in over the following frames
+		let mut frames = 1;
+		let mut last = loaded(&app);
+		loop {
+			app.update();
+			frames += 1;
+			let now = loaded(&app);
+			assert!(now - last <= 3);
+			if now == last {
+				break;
+			}
+			last = now;
+		}
+		assert!(frames > 2);
+		for (entity, chunk) in chunks.iter(app.world()) {
+			if !first.iter().any(|(first, _)| *first == entity) {
+				assert!(distance(chunk) >= nearest - 1e-5);
+			}
+		}
+	}
+
+	#[test]
+	fn test_booted_chunks_load_beyond_the_budget() {
+		let mut app = app();
+		app.add_plugins(StatesPlugin)
+			.insert_resource(ChunkBudgetConfig::<Ground>::default().with_max_chunks_per_frame(1))
+			.insert_resource(WorldBoot::<Ground>::default().with_rings(2))
+			.init_resource::<BootProgress>()
+			.init_state::<WorldBootState>()
+			.add_systems(
+				Update,
+				(
+					boot_world::<Ground>.run_if(in_state(WorldBootState::Booting)),
+					manage_chunks::<Ground>.run_if(in_state(WorldBootState::Playing)),
+				),
+			);
+		while *app.world().resource::<State<WorldBootState>>() == WorldBootState::Booting {
+			app.update();
+		}
+		let booted = app.world().resource::<BootProgress>().total;
+		assert!(booted > 1);
+
+		// Every booted chunk loads in the first frame of play, beside at most one generated
+		app.update();
+		assert!(app.world().resource::<WorldBoot<Ground>>().is_empty());
+		let loaded = app.world().resource::<LoadedChunks>().chunks.len();
+		assert!(loaded >= booted, "{loaded} of {booted} booted chunks");
+		assert!(loaded <= booted + 1, "{loaded} of {booted} booted chunks");
+	}
+}
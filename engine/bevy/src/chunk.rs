@@ -0,0 +1,363 @@
+use crate::boundary::{WorldBoundary, WorldBounds};
+use crate::cascade::{CascadeChunk, OriginSnapping};
+use crate::chunk_manager::wrap_coordinate;
+use crate::mesh_checks::MeshCheckConfig;
+use crate::occupancy::{ChunkOccupancy, OccupancyCheck};
+use bevy::prelude::*;
+use sdf::Sdf;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+/// Chunk coordinate in the world grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkCoord {
+	pub x: i32,
+	pub z: i32,
+}
+
+impl ChunkCoord {
+	pub fn new(x: i32, z: i32) -> Self {
+		Self { x, z }
+	}
+
+	/// Wrap chunk coordinates to world bounds (torus topology)
+	pub fn wrap(&self, world_size_chunks: i32) -> Self {
+		Self {
+			x: ((self.x % world_size_chunks) + world_size_chunks) % world_size_chunks,
+			z: ((self.z % world_size_chunks) + world_size_chunks) % world_size_chunks,
+		}
+	}
+
+	/// Convert world position to chunk coordinate (with wrapping)
+	/// Returns both the wrapped coordinate and the "display" coordinate for spawning
+	pub fn from_world_pos(
+		world_pos: Vec3,
+		chunk_size: f32,
+		world_size_chunks: i32,
+	) -> (Self, Self) {
+		// Calculate unwrapped chunk coordinate
+		let unwrapped = Self {
+			x: (world_pos.x / chunk_size).floor() as i32,
+			z: (world_pos.z / chunk_size).floor() as i32,
+		};
+
+		// Calculate wrapped coordinate for indexing
+		let wrapped =
+			if world_size_chunks > 0 { unwrapped.wrap(world_size_chunks) } else { unwrapped };
+
+		(wrapped, unwrapped)
+	}
+
+	/// Get world position of chunk center (with wrapping)
+	pub fn to_world_pos(&self, chunk_size: f32, world_size_chunks: i32) -> Vec3 {
+		let wrapped = self.wrap(world_size_chunks);
+		let x = (wrapped.x as f32 + 0.5) * chunk_size;
+		let z = (wrapped.z as f32 + 0.5) * chunk_size;
+		Vec3::new(x, 0.0, z)
+	}
+
+	/// Get world position of chunk origin (corner) with wrapping
+	/// If use_wrapped_pos is true, uses wrapped coordinates; otherwise uses unwrapped
+	pub fn to_world_origin(
+		&self,
+		chunk_size: f32,
+		world_size_chunks: i32,
+		use_wrapped_pos: bool,
+	) -> Vec3 {
+		if use_wrapped_pos && world_size_chunks > 0 {
+			let wrapped = self.wrap(world_size_chunks);
+			Vec3::new(wrapped.x as f32 * chunk_size, 0.0, wrapped.z as f32 * chunk_size)
+		} else {
+			Vec3::new(self.x as f32 * chunk_size, 0.0, self.z as f32 * chunk_size)
+		}
+	}
+
+	/// Get unwrapped world position for noise generation (allows seamless wrapping)
+	pub fn to_unwrapped_world_pos(&self, chunk_size: f32) -> Vec3 {
+		Vec3::new(self.x as f32 * chunk_size, 0.0, self.z as f32 * chunk_size)
+	}
+
+	/// Calculate Manhattan distance between chunks (accounting for wrapping)
+	pub fn manhattan_distance(&self, other: &Self, world_size_chunks: i32) -> i32 {
+		let wrapped_self = self.wrap(world_size_chunks);
+		let wrapped_other = other.wrap(world_size_chunks);
+
+		// Calculate distance in both directions (wrapped and unwrapped)
+		let dx = (wrapped_self.x - wrapped_other.x).abs();
+		let dz = (wrapped_self.z - wrapped_other.z).abs();
+
+		// Account for wrapping - use the shorter path
+		let dx_wrapped = world_size_chunks - dx;
+		let dz_wrapped = world_size_chunks - dz;
+
+		let dx_min = dx.min(dx_wrapped);
+		let dz_min = dz.min(dz_wrapped);
+
+		dx_min + dz_min
+	}
+}
+
+/// Component marking a terrain chunk entity
+#[derive(Component, Debug, Clone, Copy)]
+pub struct TerrainChunk {
+	pub chunk: CascadeChunk,
+}
+
+impl TerrainChunk {
+	/// Inspector label of the chunk, such as "chunk (0, -8, 16) r2=3".
+	pub fn name(&self) -> String {
+		let (origin, res_2) = (self.chunk.origin, self.chunk.res_2);
+		let res_2 = if res_2 == UVec3::splat(res_2.x) {
+			res_2.x.to_string()
+		} else {
+			format!("({}, {}, {})", res_2.x, res_2.y, res_2.z)
+		};
+		format!("chunk ({}, {}, {}) r2={res_2}", origin.x, origin.y, origin.z)
+	}
+}
+
+/// Stable integer identity of a chunk, for referring to chunks across systems and in saves.
+///
+/// The origin is quantized to the lattice of the smallest chunk size and the size is kept as
+/// a multiple of it, so ids don't rely on float equality. Rings and the grid have chunks of
+/// different sizes, so chunks that share an origin still have different ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ChunkId {
+	/// Origin in multiples of the smallest chunk size
+	pub lattice: [i32; 3],
+	/// Size in multiples of the smallest chunk size
+	pub scale: u32,
+}
+
+impl ChunkId {
+	pub fn new(origin: Vec3, size: Vec3, min_size: Vec3) -> Self {
+		let lattice = (origin / min_size).round().as_ivec3();
+		let scale = (size.x / min_size.x).round().max(1.0) as u32;
+		Self { lattice: lattice.to_array(), scale }
+	}
+
+	/// The id of a chunk, without wrapping its origin.
+	pub fn from_chunk(chunk: &CascadeChunk, min_size: Vec3) -> Self {
+		Self::new(chunk.origin, chunk.size, min_size)
+	}
+
+	pub fn origin(&self, min_size: Vec3) -> Vec3 {
+		IVec3::from_array(self.lattice).as_vec3() * min_size
+	}
+
+	pub fn size(&self, min_size: Vec3) -> Vec3 {
+		min_size * self.scale as f32
+	}
+
+	/// The ring of the cascade the chunk belongs to, from the power of 3 in its scale; the
+	/// ring past the last is the grid.
+	///
+	/// Ring chunks are 3^ring times the smallest chunk, and grid chunks are the span of the
+	/// cascade times a power of 2, so both land on their ring whatever the grid multiple.
+	pub fn ring(&self) -> u32 {
+		let (mut scale, mut ring) = (self.scale.max(1), 0);
+		while scale % 3 == 0 {
+			scale /= 3;
+			ring += 1;
+		}
+		ring
+	}
+
+	/// The chunk with the id at the resolution.
+	pub fn to_chunk(&self, min_size: Vec3, res_2: UVec3) -> CascadeChunk {
+		CascadeChunk { origin: self.origin(min_size), size: self.size(min_size), res_2, omit: None }
+	}
+}
+
+/// Resource tracking loaded chunks by the [ChunkId] of their wrapped origin.
+///
+/// Ids come from [ChunkConfig::chunk_id]. Chunks can be pinned to keep them loaded wherever
+/// the camera is, such as around a base or a quest area: [manage_chunks](crate::manage_chunks)
+/// generates pinned chunks that aren't loaded, at full resolution, and never unloads them
+/// while they are pinned.
+#[derive(Resource, Default)]
+pub struct LoadedChunks {
+	pub chunks: HashSet<ChunkId>,
+	/// Loaded chunks whose generation was skipped, with what the occupancy check found
+	skipped: HashMap<ChunkId, ChunkOccupancy>,
+	/// Chunks kept loaded wherever the camera is
+	pinned: HashSet<ChunkId>,
+}
+
+impl LoadedChunks {
+	pub fn is_loaded(&self, id: ChunkId) -> bool {
+		self.chunks.contains(&id)
+	}
+
+	pub fn mark_loaded(&mut self, id: ChunkId) {
+		self.chunks.insert(id);
+	}
+
+	/// Marks a chunk loaded without generating it, as it is all air or all ground.
+	pub fn mark_skipped(&mut self, id: ChunkId, occupancy: ChunkOccupancy) {
+		self.chunks.insert(id);
+		self.skipped.insert(id, occupancy);
+	}
+
+	pub fn mark_unloaded(&mut self, id: ChunkId) {
+		self.chunks.remove(&id);
+		self.skipped.remove(&id);
+	}
+
+	/// What the occupancy check found for a loaded chunk whose generation was skipped.
+	pub fn skipped(&self, id: ChunkId) -> Option<ChunkOccupancy> {
+		self.skipped.get(&id).copied()
+	}
+
+	/// Number of loaded chunks whose generation was skipped.
+	pub fn skipped_len(&self) -> usize {
+		self.skipped.len()
+	}
+
+	/// Keeps the chunk loaded wherever the camera is, until it is unpinned.
+	///
+	/// Returns whether the chunk wasn't already pinned.
+	pub fn pin(&mut self, id: ChunkId) -> bool {
+		self.pinned.insert(id)
+	}
+
+	/// Lets the chunk unload again once the camera leaves it.
+	///
+	/// Returns whether the chunk was pinned.
+	pub fn unpin(&mut self, id: ChunkId) -> bool {
+		self.pinned.remove(&id)
+	}
+
+	pub fn is_pinned(&self, id: ChunkId) -> bool {
+		self.pinned.contains(&id)
+	}
+
+	pub fn pinned(&self) -> impl Iterator<Item = ChunkId> + '_ {
+		self.pinned.iter().copied()
+	}
+
+	/// Number of pinned chunks, loaded or not.
+	pub fn pinned_len(&self) -> usize {
+		self.pinned.len()
+	}
+
+	/// Keeps only the loaded chunks for which the predicate holds, leaving the pins.
+	pub fn retain(&mut self, mut keep: impl FnMut(&ChunkId) -> bool) {
+		self.chunks.retain(&mut keep);
+		let chunks = &self.chunks;
+		self.skipped.retain(|key, _| chunks.contains(key));
+	}
+}
+
+/// Configuration for chunk system using cascade
+#[derive(Resource)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Resource, Default))]
+pub struct ChunkConfig<S: Sdf + Send + Sync> {
+	/// Minimum chunk size per axis (size of center chunk and ring 0)
+	pub min_size: Vec3,
+	/// Number of rings in the cascade
+	pub number_of_rings: usize,
+	/// World size in world units (for wrapping/torus topology). If 0, no wrapping.
+	/// Should be a multiple of cascade span for proper alignment.
+	pub world_size: f32,
+	/// Whether the world wraps at world_size or ends at an edge
+	pub boundary: WorldBoundary,
+	/// Grid radius in chunks
+	pub grid_radius: usize,
+	/// Grid multiple in base two power
+	pub grid_multiple_2: u8,
+	/// How the rings of the cascade snap to the camera
+	#[cfg_attr(feature = "reflect", reflect(ignore))]
+	pub origin_snapping: OriginSnapping,
+	/// Validates every generated mesh and logs the problems found, for catching generator
+	/// regressions in debug builds
+	#[cfg_attr(feature = "reflect", reflect(ignore))]
+	pub mesh_checks: Option<MeshCheckConfig>,
+	/// Skips generating chunks whose sign intervals show them all air or all ground, for
+	/// heightfields no steeper than its slope
+	#[cfg_attr(feature = "reflect", reflect(ignore))]
+	pub occupancy: Option<OccupancyCheck>,
+	/// Marker for the SDF that defines the chunk boundaries
+	#[cfg_attr(feature = "reflect", reflect(ignore))]
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for ChunkConfig<S> {
+	fn default() -> Self {
+		Self {
+			min_size: Vec3::splat(0.1), // Cascade begins at 100m resolution
+			number_of_rings: 0,         // 4 rings: center + 2 rings = 3^2 = 9 chunks = 900m total
+			world_size: 0.0,            // No wrapping by default
+			boundary: WorldBoundary::Wrap,
+			grid_radius: 8,     // a radius of 8 chunks
+			grid_multiple_2: 7, // 300 * 64 = 19200m = 19.2km per grid chunk
+			origin_snapping: OriginSnapping::MinSize,
+			mesh_checks: None,
+			occupancy: None,
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> ChunkConfig<S> {
+	/// The period chunk origins wrap at, 0 when the world doesn't wrap
+	pub fn wrap_size(&self) -> f32 {
+		match self.boundary {
+			WorldBoundary::Wrap => self.world_size,
+			WorldBoundary::Edge(_) => 0.0,
+		}
+	}
+
+	/// The id the chunk is loaded under, from its origin wrapped to the world.
+	pub fn chunk_id(&self, chunk: &CascadeChunk) -> ChunkId {
+		ChunkId::new(wrap_coordinate(chunk.origin, self.wrap_size()), chunk.size, self.min_size)
+	}
+
+	/// The bounds of a world that ends at an edge, in the local space of the SDF
+	pub fn world_bounds(&self) -> Option<WorldBounds> {
+		match self.boundary {
+			WorldBoundary::Edge(edge) if self.world_size > 0.0 => {
+				Some(WorldBounds::new(self.world_size, edge))
+			}
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_chunk_names_are_deterministic() {
+		let chunk = TerrainChunk { chunk: CascadeChunk::cube(Vec3::new(0.0, -8.0, 16.0), 8.0, 3) };
+		assert_eq!(chunk.name(), "chunk (0, -8, 16) r2=3");
+		assert_eq!(chunk.name(), TerrainChunk { chunk: chunk.chunk }.name());
+
+		let anisotropic =
+			TerrainChunk { chunk: CascadeChunk { res_2: UVec3::new(3, 2, 3), ..chunk.chunk } };
+		assert_eq!(anisotropic.name(), "chunk (0, -8, 16) r2=(3, 2, 3)");
+	}
+	#[test]
+	fn test_chunk_ids_round_trip_through_chunks() {
+		let min_size = Vec3::splat(0.1);
+		// Origins that drift off the lattice by float error keep their id
+		let chunk = CascadeChunk::cube(Vec3::new(-0.3, 0.0, 0.9000001), 0.9, 3);
+		let id = ChunkId::from_chunk(&chunk, min_size);
+		assert_eq!(id, ChunkId { lattice: [-3, 0, 9], scale: 9 });
+		assert_eq!(id, ChunkId::new(Vec3::new(-0.29999998, 0.0, 0.9), Vec3::splat(0.9), min_size));
+		assert_eq!(id.ring(), 2);
+		// Grid chunks of two rings, at four times the span, are past the second ring
+		assert_eq!(ChunkId { lattice: [0; 3], scale: 36 }.ring(), 2);
+		assert_eq!(ChunkId::from_chunk(&id.to_chunk(min_size, chunk.res_2), min_size), id);
+
+		// Chunks of different rings can share an origin
+		let inner = CascadeChunk::cube(chunk.origin, 0.3, 3);
+		assert_ne!(ChunkId::from_chunk(&inner, min_size), id);
+
+		let config = ChunkConfig::<sdf::SphereSdf> { min_size, world_size: 2.0, ..default() };
+		let wrapped = CascadeChunk { origin: chunk.origin + Vec3::X * 2.0, ..chunk };
+		assert_eq!(config.chunk_id(&wrapped), config.chunk_id(&chunk));
+	}
+}
@@ -0,0 +1,220 @@
+use crate::chunk::{ChunkId, LoadedChunks};
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A face of a chunk, by the axis and direction it faces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChunkFace {
+	NegX,
+	PosX,
+	NegY,
+	PosY,
+	NegZ,
+	PosZ,
+}
+
+impl ChunkFace {
+	fn from_axis(axis: usize, positive: bool) -> Self {
+		match (axis, positive) {
+			(0, false) => Self::NegX,
+			(0, true) => Self::PosX,
+			(1, false) => Self::NegY,
+			(1, true) => Self::PosY,
+			(2, false) => Self::NegZ,
+			_ => Self::PosZ,
+		}
+	}
+
+	/// The face on the other side of the shared boundary.
+	pub fn opposite(&self) -> Self {
+		match self {
+			Self::NegX => Self::PosX,
+			Self::PosX => Self::NegX,
+			Self::NegY => Self::PosY,
+			Self::PosY => Self::NegY,
+			Self::NegZ => Self::PosZ,
+			Self::PosZ => Self::NegZ,
+		}
+	}
+}
+
+/// A loaded chunk sharing part of a face with another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkNeighbor {
+	pub id: ChunkId,
+	/// The face of the chunk the neighbor lies against
+	pub face: ChunkFace,
+	/// Rings the neighbor is coarser by, negative when it is finer
+	pub lod_delta: i32,
+}
+
+/// Resource tracking which loaded chunks share faces, for stitching seams, reconciling normals
+/// and merging navigation meshes across chunks, and for flood fills over the loaded world.
+///
+/// Chunks are linked when their boxes touch over an area, so a coarse chunk is linked to every
+/// finer chunk along its face. Ids are of wrapped origins, so chunks aren't linked across the
+/// wrap seam, and grid chunks aren't linked to the cascade carved out of them.
+/// Kept in step with [LoadedChunks] by the [update_chunk_graph] system.
+#[derive(Resource, Debug, Default)]
+pub struct ChunkGraph {
+	neighbors: HashMap<ChunkId, Vec<ChunkNeighbor>>,
+}
+
+impl ChunkGraph {
+	pub fn len(&self) -> usize {
+		self.neighbors.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.neighbors.is_empty()
+	}
+
+	pub fn contains(&self, id: ChunkId) -> bool {
+		self.neighbors.contains_key(&id)
+	}
+
+	/// The loaded chunks sharing a face with the chunk, empty if it isn't loaded.
+	pub fn neighbors_of(&self, id: ChunkId) -> &[ChunkNeighbor] {
+		self.neighbors.get(&id).map(Vec::as_slice).unwrap_or_default()
+	}
+
+	/// The neighbors of the chunk against one of its faces.
+	pub fn neighbors_on(&self, id: ChunkId, face: ChunkFace) -> impl Iterator<Item = ChunkId> + '_ {
+		self.neighbors_of(id).iter().filter(move |n| n.face == face).map(|n| n.id)
+	}
+
+	/// Rings `b` is coarser than `a` by, if the chunks are neighbors.
+	pub fn lod_delta(&self, a: ChunkId, b: ChunkId) -> Option<i32> {
+		self.neighbors_of(a).iter().find(|n| n.id == b).map(|n| n.lod_delta)
+	}
+
+	/// Adds a chunk and links it to the loaded chunks it shares faces with.
+	pub fn insert(&mut self, id: ChunkId) {
+		if self.contains(id) {
+			return;
+		}
+		let mut links = Vec::new();
+		for (other, other_links) in &mut self.neighbors {
+			if let Some(face) = shared_face(id, *other) {
+				let lod_delta = other.ring() as i32 - id.ring() as i32;
+				links.push(ChunkNeighbor { id: *other, face, lod_delta });
+				other_links.push(ChunkNeighbor {
+					id,
+					face: face.opposite(),
+					lod_delta: -lod_delta,
+				});
+			}
+		}
+		self.neighbors.insert(id, links);
+	}
+
+	/// Removes a chunk and its links.
+	pub fn remove(&mut self, id: ChunkId) {
+		let Some(links) = self.neighbors.remove(&id) else {
+			return;
+		};
+		for link in links {
+			if let Some(other_links) = self.neighbors.get_mut(&link.id) {
+				other_links.retain(|n| n.id != id);
+			}
+		}
+	}
+
+	/// Brings the graph in line with a set of loaded chunks.
+	pub fn sync(&mut self, loaded: &HashSet<ChunkId>) {
+		let unloaded: Vec<ChunkId> =
+			self.neighbors.keys().filter(|id| !loaded.contains(id)).copied().collect();
+		for id in unloaded {
+			self.remove(id);
+		}
+		for id in loaded {
+			self.insert(*id);
+		}
+	}
+
+	/// The chunks reachable from `start` through neighbors for which `pass` holds,
+	/// in breadth-first order and including `start` if it is loaded.
+	pub fn flood_fill(
+		&self,
+		start: ChunkId,
+		mut pass: impl FnMut(&ChunkNeighbor) -> bool,
+	) -> Vec<ChunkId> {
+		if !self.contains(start) {
+			return Vec::new();
+		}
+		let mut visited = HashSet::from([start]);
+		let mut queue = VecDeque::from([start]);
+		let mut order = Vec::new();
+		while let Some(id) = queue.pop_front() {
+			order.push(id);
+			for neighbor in self.neighbors_of(id) {
+				if pass(neighbor) && visited.insert(neighbor.id) {
+					queue.push_back(neighbor.id);
+				}
+			}
+		}
+		order
+	}
+}
+
+/// The face of `a` that `b` lies against, if their boxes touch over an area.
+fn shared_face(a: ChunkId, b: ChunkId) -> Option<ChunkFace> {
+	let (a_min, a_max) =
+		(IVec3::from_array(a.lattice), IVec3::from_array(a.lattice) + a.scale as i32);
+	let (b_min, b_max) =
+		(IVec3::from_array(b.lattice), IVec3::from_array(b.lattice) + b.scale as i32);
+	let mut face = None;
+	for axis in 0..3 {
+		if a_max[axis] == b_min[axis] || b_max[axis] == a_min[axis] {
+			if face.is_some() {
+				return None;
+			}
+			face = Some(ChunkFace::from_axis(axis, a_max[axis] == b_min[axis]));
+		} else if a_min[axis] >= b_max[axis] || b_min[axis] >= a_max[axis] {
+			return None;
+		}
+	}
+	// Boxes that overlap on every axis share a volume, not a face
+	face
+}
+
+/// Updates the [ChunkGraph] when chunks load or unload.
+pub fn update_chunk_graph(loaded_chunks: Res<LoadedChunks>, mut graph: ResMut<ChunkGraph>) {
+	if loaded_chunks.is_changed() {
+		graph.sync(&loaded_chunks.chunks);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn id(x: i32, y: i32, z: i32, scale: u32) -> ChunkId {
+		ChunkId { lattice: [x, y, z], scale }
+	}
+
+	#[test]
+	fn test_graph_links_chunks_sharing_faces() {
+		let center = id(0, 0, 0, 1);
+		let east = id(1, 0, 0, 1);
+		let corner = id(1, 1, 0, 1);
+		let coarse = id(-3, -1, -1, 3);
+		let far = id(5, 0, 0, 1);
+
+		let mut graph = ChunkGraph::default();
+		graph.sync(&HashSet::from([center, east, corner, coarse, far]));
+
+		let mut neighbors: Vec<_> = graph.neighbors_of(center).iter().map(|n| n.id).collect();
+		neighbors.sort();
+		assert_eq!(neighbors, vec![coarse, east]);
+		assert_eq!(graph.neighbors_on(center, ChunkFace::PosX).collect::<Vec<_>>(), vec![east]);
+		assert_eq!(graph.lod_delta(center, coarse), Some(1));
+		assert_eq!(graph.lod_delta(coarse, center), Some(-1));
+		assert_eq!(graph.lod_delta(center, corner), None);
+
+		assert_eq!(graph.flood_fill(center, |_| true).len(), 4);
+		graph.sync(&HashSet::from([center, corner, coarse, far]));
+		assert!(graph.neighbors_on(center, ChunkFace::PosX).next().is_none());
+		assert_eq!(graph.neighbors_of(corner), &[]);
+	}
+}
@@ -0,0 +1,851 @@
+use crate::async_generation::AsyncChunkGeneration;
+use crate::boot::WorldBoot;
+use crate::budget::{by_distance, split_kinds, ChunkBudgetConfig};
+use crate::cascade::{
+	Cascade, CascadeChunk, ChunkResolutionMap, ConstantResolutionMap, ScreenSpaceResolutionMap,
+};
+use crate::chunk::{ChunkConfig, ChunkId, LoadedChunks, TerrainChunk};
+use crate::cpu::{ChunkSpawner, CpuMeshGenerator, IntoMesh, MeshData};
+use crate::double_buffer::ChunkSwapOuts;
+use crate::focus::ResolutionFocus;
+use crate::generation_pool::GenerationPool;
+use crate::marching_cubes::VertexInterpolation;
+use crate::material::{ChunkKind, ChunkMaterialRegistry, ChunkTag};
+use crate::mesh_cache::ChunkMeshCache;
+use crate::mesh_checks::{check_mesh, MeshCheckConfig};
+use crate::occupancy::ChunkOccupancy;
+use crate::portal::{carve_portals, PortalVolume, PortalVolumes};
+use crate::prewarm::ChunkPrewarm;
+use crate::probes::LightProbes;
+use crate::processor::MeshProcessors;
+use crate::regeneration::ChunkRegenerationQueue;
+use crate::replay::GenerationRecorder;
+use crate::stats::ChunkMeshSize;
+use crate::transform::WorldTransform;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use rayon::prelude::*;
+use sdf::Sdf;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Configuration for chunk resolution
+#[derive(Resource, Clone, Copy)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Resource, Default))]
+pub struct ChunkResolutionConfig<S: Sdf + Send + Sync> {
+	/// Full resolution vertices per chunk side (as power of 2)
+	pub base_res_2: u8,
+	/// Picks ring resolutions up to base_res_2 from their on-screen voxel size, rather than
+	/// giving every ring base_res_2
+	pub screen_space: Option<ScreenSpaceError>,
+	/// Marker for the SDF that defines the chunk boundaries
+	#[cfg_attr(feature = "reflect", reflect(ignore))]
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for ChunkResolutionConfig<S> {
+	fn default() -> Self {
+		// 128x128x128 voxels per chunk at full resolution
+		Self { base_res_2: 7, screen_space: None, sdf: PhantomData }
+	}
+}
+
+/// The on-screen voxel size that chunk resolutions are picked for, and the view it is seen in.
+///
+/// The view is kept up to date with the camera by [track_camera_projection].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, Default, PartialEq))]
+pub struct ScreenSpaceError {
+	/// Largest on-screen voxel size, in pixels
+	pub max_pixel_error: f32,
+	/// Lowest resolution of any ring, as a power of 2
+	pub min_res_2: u8,
+	/// Vertical field of view of the camera, in radians
+	pub fov_y: f32,
+	/// Viewport height of the camera, in pixels
+	pub viewport_height: f32,
+}
+
+impl Default for ScreenSpaceError {
+	fn default() -> Self {
+		Self {
+			max_pixel_error: 2.0,
+			min_res_2: 1,
+			fov_y: std::f32::consts::FRAC_PI_4,
+			viewport_height: 720.0,
+		}
+	}
+}
+
+impl ScreenSpaceError {
+	pub fn with_max_pixel_error(mut self, max_pixel_error: f32) -> Self {
+		self.max_pixel_error = max_pixel_error;
+		self
+	}
+
+	pub fn with_min_res_2(mut self, min_res_2: u8) -> Self {
+		self.min_res_2 = min_res_2;
+		self
+	}
+}
+
+/// Keeps the view of the [ScreenSpaceError] in line with the camera's projection and viewport.
+pub fn track_camera_projection<S: Sdf + Send + Sync + 'static>(
+	camera_query: Query<(&Camera, &Projection), With<Camera3d>>,
+	mut resolution_config: ResMut<ChunkResolutionConfig<S>>,
+) {
+	let Some(error) = resolution_config.screen_space else {
+		return;
+	};
+	let Ok((camera, Projection::Perspective(perspective))) = camera_query.single() else {
+		return;
+	};
+	let Some(viewport) = camera.physical_viewport_size() else {
+		return;
+	};
+
+	let tracked =
+		ScreenSpaceError { fov_y: perspective.fov, viewport_height: viewport.y as f32, ..error };
+	if tracked != error {
+		resolution_config.screen_space = Some(tracked);
+	}
+}
+
+/// Resource wrapper for SDF that can be shared across threads
+/// Generic over SDF type to allow different layers at render time
+#[derive(Resource)]
+pub struct SdfResource<S: Sdf + Send + Sync> {
+	pub sdf: Arc<S>,
+	/// Where the SDF is placed in the world
+	pub transform: WorldTransform,
+	/// Tag used to pick chunk materials
+	pub tag: ChunkTag,
+	/// Value of the SDF whose isosurface is meshed, 0 for the surface itself
+	pub iso_level: f32,
+	/// How vertices are placed along the cube edges the surface crosses
+	pub interpolation: VertexInterpolation,
+}
+
+impl<S: Sdf + Send + Sync> SdfResource<S> {
+	/// Create from a concrete SDF type
+	pub fn new(sdf: S) -> Self {
+		Self::from_arc(Arc::new(sdf))
+	}
+
+	/// Create from an Arc of a concrete SDF type
+	pub fn from_arc(sdf: Arc<S>) -> Self {
+		Self {
+			sdf,
+			transform: WorldTransform::default(),
+			tag: ChunkTag::DEFAULT,
+			iso_level: 0.0,
+			interpolation: VertexInterpolation::default(),
+		}
+	}
+
+	/// Places the SDF in the world
+	pub fn with_transform(mut self, transform: WorldTransform) -> Self {
+		self.transform = transform;
+		self
+	}
+
+	/// Tags the chunks of the SDF for material lookup
+	pub fn with_tag(mut self, tag: ChunkTag) -> Self {
+		self.tag = tag;
+		self
+	}
+
+	/// Meshes the isosurface at the level instead of the surface, such as a slightly positive
+	/// level for a snow shell over another layer of the same SDF
+	pub fn with_iso_level(mut self, iso_level: f32) -> Self {
+		self.iso_level = iso_level;
+		self
+	}
+
+	/// Places vertices along the cube edges by the interpolation, such as at the midpoints for
+	/// a blocky world
+	pub fn with_interpolation(mut self, interpolation: VertexInterpolation) -> Self {
+		self.interpolation = interpolation;
+		self
+	}
+
+	/// Samples the SDF at a world position
+	pub fn distance(&self, p: Vec3) -> f32 {
+		self.transform.distance_to_world(self.sdf.distance(self.transform.to_local(p)))
+	}
+}
+
+/// Helper function to wrap a Vec3 coordinate within world bounds
+/// If world_size is 0, returns the coordinate unchanged (no wrapping)
+pub(crate) fn wrap_coordinate(pos: Vec3, world_size: f32) -> Vec3 {
+	if world_size <= 0.0 {
+		return pos;
+	}
+	Vec3::new(
+		((pos.x % world_size) + world_size) % world_size,
+		((pos.y % world_size) + world_size) % world_size,
+		((pos.z % world_size) + world_size) % world_size,
+	)
+}
+
+/// The cascade of chunks described by the configs.
+pub(crate) fn chunk_cascade<S: Sdf + Send + Sync>(
+	chunk_config: &ChunkConfig<S>,
+	resolution_config: &ChunkResolutionConfig<S>,
+) -> Cascade<ChunkResolutionMap> {
+	let base_res_2 = resolution_config.base_res_2;
+	let resolution_map = match resolution_config.screen_space {
+		None => ChunkResolutionMap::Constant(ConstantResolutionMap { res_2: base_res_2 }),
+		Some(error) => ChunkResolutionMap::ScreenSpace(ScreenSpaceResolutionMap {
+			min_size: chunk_config.min_size.max_element(),
+			number_of_rings: chunk_config.number_of_rings as u8,
+			grid_multiple_2: chunk_config.grid_multiple_2,
+			fov_y: error.fov_y,
+			viewport_height: error.viewport_height,
+			max_pixel_error: error.max_pixel_error,
+			min_res_2: error.min_res_2,
+			max_res_2: base_res_2,
+		}),
+	};
+	Cascade {
+		min_size: chunk_config.min_size,
+		number_of_rings: chunk_config.number_of_rings as u8,
+		resolution_map,
+		grid_radius: chunk_config.grid_radius,
+		grid_multiple_2: chunk_config.grid_multiple_2,
+		snapping: chunk_config.origin_snapping,
+	}
+}
+
+/// The resources that lay out the chunks of an SDF and go into their meshes.
+#[derive(SystemParam)]
+pub struct ChunkSources<'w, S: Sdf + Send + Sync + 'static> {
+	pub chunk_config: Res<'w, ChunkConfig<S>>,
+	pub resolution_config: Res<'w, ChunkResolutionConfig<S>>,
+	pub sdf_resource: Res<'w, SdfResource<S>>,
+	pub portals: Option<Res<'w, PortalVolumes<S>>>,
+	pub light_probes: Option<Res<'w, LightProbes<S>>>,
+	pub processors: Option<Res<'w, MeshProcessors<S>>>,
+	pub recorder: Option<Res<'w, GenerationRecorder<S>>>,
+}
+
+impl<S: Sdf + Send + Sync + 'static> ChunkSources<'_, S> {
+	/// Whether any of the sources changed since the system last ran
+	pub fn is_changed(&self) -> bool {
+		self.chunk_config.is_changed()
+			|| self.resolution_config.is_changed()
+			|| self.sdf_resource.is_changed()
+			|| self.portals.as_ref().is_some_and(|portals| portals.is_changed())
+			|| self.light_probes.as_ref().is_some_and(|probes| probes.is_changed())
+			|| self.processors.as_ref().is_some_and(|processors| processors.is_changed())
+	}
+
+	pub(crate) fn cascade(&self) -> Cascade<ChunkResolutionMap> {
+		chunk_cascade(&self.chunk_config, &self.resolution_config)
+	}
+
+	pub(crate) fn pipeline(&self) -> ChunkPipeline<'_, S> {
+		ChunkPipeline::new(
+			&self.sdf_resource,
+			self.chunk_config.mesh_checks,
+			self.portals.as_deref(),
+			self.light_probes.as_deref(),
+		)
+		.with_processors(self.processors.as_deref())
+		.with_recorder(self.recorder.as_deref())
+	}
+}
+
+/// Everything that goes into a chunk mesh, from sampling the SDF to shading.
+pub(crate) struct ChunkPipeline<'a, S: Sdf + Send + Sync> {
+	sdf: Arc<S>,
+	iso_level: f32,
+	interpolation: VertexInterpolation,
+	mesh_checks: Option<MeshCheckConfig>,
+	portals: Vec<PortalVolume>,
+	light_probes: Option<Cow<'a, LightProbes<S>>>,
+	processors: Option<MeshProcessors<S>>,
+	recorder: Option<GenerationRecorder<S>>,
+}
+
+impl<'a, S: Sdf + Send + Sync> ChunkPipeline<'a, S> {
+	pub(crate) fn new(
+		sdf_resource: &SdfResource<S>,
+		mesh_checks: Option<MeshCheckConfig>,
+		portals: Option<&PortalVolumes<S>>,
+		light_probes: Option<&'a LightProbes<S>>,
+	) -> Self {
+		Self {
+			sdf: Arc::clone(&sdf_resource.sdf),
+			iso_level: sdf_resource.iso_level,
+			interpolation: sdf_resource.interpolation,
+			mesh_checks,
+			portals: portals.map(PortalVolumes::volumes).unwrap_or_default(),
+			light_probes: light_probes.map(Cow::Borrowed),
+			processors: None,
+			recorder: None,
+		}
+	}
+
+	/// Runs the processors over every mesh after shading it.
+	pub(crate) fn with_processors(mut self, processors: Option<&MeshProcessors<S>>) -> Self {
+		self.processors = processors.filter(|processors| !processors.is_empty()).cloned();
+		self
+	}
+
+	/// Records every chunk generated into the recorder.
+	pub(crate) fn with_recorder(mut self, recorder: Option<&GenerationRecorder<S>>) -> Self {
+		self.recorder = recorder.cloned();
+		self
+	}
+
+	/// The pipeline with its own handle on the light probes, which share their baked probes
+	/// rather than copying them, to generate chunks off the frame that made it.
+	pub(crate) fn into_owned(self) -> ChunkPipeline<'static, S> {
+		ChunkPipeline {
+			sdf: self.sdf,
+			iso_level: self.iso_level,
+			interpolation: self.interpolation,
+			mesh_checks: self.mesh_checks,
+			portals: self.portals,
+			light_probes: self.light_probes.map(|probes| Cow::Owned(probes.into_owned())),
+			processors: self.processors,
+			recorder: self.recorder,
+		}
+	}
+
+	/// Generates the mesh of the chunk, or None when the chunk has no surface
+	pub(crate) fn generate(&self, cascade_chunk: &CascadeChunk) -> Option<Mesh> {
+		let sdf = Arc::clone(&self.sdf);
+		let mesh = CpuMeshGenerator::generate_chunk_mesh_data_with(
+			cascade_chunk,
+			sdf,
+			self.iso_level,
+			self.interpolation,
+		)
+		.inspect(|mesh| {
+			let Some(config) = &self.mesh_checks else {
+				return;
+			};
+			let report = check_mesh(mesh, config);
+			if !report.is_valid() {
+				log::warn!(
+					"Invalid mesh for chunk at {:?} of size {:?}: {report}",
+					cascade_chunk.origin,
+					cascade_chunk.size
+				);
+			}
+		})
+		.and_then(|mesh| carve_portals(cascade_chunk, mesh, &self.portals))
+		.map(|mesh| match &self.light_probes {
+			Some(probes) => probes.shade(&self.sdf, cascade_chunk, mesh),
+			None => mesh,
+		})
+		.and_then(|mesh| match &self.processors {
+			Some(processors) => processors.process(&self.sdf, cascade_chunk, mesh),
+			None => Some(mesh),
+		});
+		if let Some(recorder) = &self.recorder {
+			recorder.record(
+				&self.sdf,
+				cascade_chunk,
+				self.iso_level,
+				self.interpolation,
+				mesh.as_ref(),
+			);
+		}
+		mesh.map(MeshData::into_mesh)
+	}
+}
+
+/// Removes the asset of a chunk's mesh when the chunk holds its only handle.
+///
+/// Bevy frees an asset the frame after its last handle drops, so a ring reloading at once would
+/// hold its old and new meshes together for a frame. Meshes with other users are left to them.
+pub(crate) fn release_chunk_mesh(mesh: &Mesh3d, meshes: &mut Assets<Mesh>) {
+	if matches!(&mesh.0, Handle::Strong(handle) if Arc::strong_count(handle) == 1) {
+		meshes.remove(mesh.id());
+	}
+}
+
+/// System that manages chunk loading and unloading based on camera position
+/// Generic over SDF type to allow different layers at render time
+pub fn manage_chunks<S: Sdf + Send + Sync + 'static>(
+	mut commands: Commands,
+	camera_query: Query<(&Transform, Option<&ResolutionFocus>), With<Camera3d>>,
+	chunk_query: Query<(Entity, &TerrainChunk, Option<&Mesh3d>, Option<&ChunkMeshSize>)>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	materials: Res<ChunkMaterialRegistry>,
+	sources: ChunkSources<S>,
+	mut loaded_chunks: ResMut<LoadedChunks>,
+	generation_pool: Option<Res<GenerationPool>>,
+	regeneration_queue: Option<ResMut<ChunkRegenerationQueue<S>>>,
+	mut prewarm: Option<ResMut<ChunkPrewarm<S>>>,
+	mut mesh_cache: Option<ResMut<ChunkMeshCache<S>>>,
+	mut boot: Option<ResMut<WorldBoot<S>>>,
+	mut async_generation: Option<ResMut<AsyncChunkGeneration<S>>>,
+	budget: Option<Res<ChunkBudgetConfig<S>>>,
+	double_buffer: ChunkSwapOuts<S>,
+) {
+	let Ok((camera_transform, focus)) = camera_query.single() else {
+		return;
+	};
+	let (chunk_config, sdf_resource) = (&sources.chunk_config, &sources.sdf_resource);
+
+	// Chunks are computed in the local space of the SDF
+	let camera_pos = sdf_resource.transform.to_local(camera_transform.translation);
+
+	let cascade = sources.cascade();
+
+	// Get chunks from cascade (separate cascade and grid)
+	let cascade_output = match cascade.chunks(camera_pos) {
+		Ok(chunks) => chunks,
+		Err(e) => {
+			log::error!("Failed to get cascade chunks: {}", e);
+			return;
+		}
+	};
+
+	let mut cascade_chunks = cascade_output.cascade();
+	if let Some(focus) = focus {
+		let forward = sdf_resource
+			.transform
+			.to_local(camera_transform.translation + *camera_transform.forward())
+			- camera_pos;
+		focus.apply(&mut cascade_chunks, camera_pos, forward);
+	}
+	let mut grid_chunks = cascade_output.grid();
+
+	// Pinned chunks the camera is away from are kept at full resolution
+	let around_camera: HashSet<ChunkId> = cascade_chunks
+		.iter()
+		.chain(grid_chunks.iter())
+		.map(|chunk| chunk_config.chunk_id(chunk))
+		.collect();
+	let res_2 = UVec3::splat(sources.resolution_config.base_res_2 as u32);
+	for id in loaded_chunks.pinned().filter(|id| !around_camera.contains(id)) {
+		let chunk = id.to_chunk(chunk_config.min_size, res_2);
+		if id.ring() < chunk_config.number_of_rings as u32 {
+			cascade_chunks.push(chunk);
+		} else {
+			grid_chunks.push(chunk);
+		}
+	}
+
+	// Combine for lookup set
+	let all_chunks: Vec<_> = cascade_chunks.iter().chain(grid_chunks.iter()).collect();
+
+	// Create set of chunk ids for quick lookup (with wrapping)
+	let chunks_to_load_set: HashSet<ChunkId> =
+		all_chunks.iter().map(|chunk| chunk_config.chunk_id(chunk)).collect();
+
+	// Resolutions of the cascade chunks, which a focus may have moved off the ring resolution
+	let cascade_res: HashMap<ChunkId, UVec3> = cascade_chunks
+		.iter()
+		.map(|chunk| (chunk_config.chunk_id(chunk), chunk.res_2))
+		.collect();
+	let mut regeneration_queue = regeneration_queue;
+
+	// Cached meshes are stale once anything that goes into them changes
+	if let Some(cache) = mesh_cache.as_deref_mut().filter(|_| sources.is_changed()) {
+		for mesh in cache.invalidate() {
+			meshes.remove(mesh.id());
+		}
+	}
+
+	// Check existing chunks for unloading
+	let mut chunks_to_unload = Vec::new();
+	for (entity, chunk, mesh, mesh_size) in chunk_query.iter() {
+		let id = chunk_config.chunk_id(&chunk.chunk);
+		if !chunks_to_load_set.contains(&id) {
+			// Meshes are cached rather than released, unless they are already stale
+			let cached = mesh_cache.as_deref_mut().zip(mesh).filter(|_| !sources.is_changed());
+			match cached {
+				Some((cache, mesh)) => {
+					let size = mesh_size.copied().unwrap_or_default();
+					for evicted in cache.insert(id, &chunk.chunk, mesh.0.clone(), size) {
+						meshes.remove(evicted.id());
+					}
+				}
+				None => {
+					if let Some(mesh) = mesh {
+						release_chunk_mesh(mesh, &mut meshes);
+					}
+				}
+			}
+			chunks_to_unload.push((entity, chunk.chunk.origin));
+			continue;
+		}
+
+		// Swap the mesh in place when the resolution changes, or reload without a queue
+		let Some(&res_2) = cascade_res.get(&id) else {
+			continue;
+		};
+		if res_2 != chunk.chunk.res_2 {
+			match regeneration_queue.as_deref_mut() {
+				Some(queue) => queue.push(entity, CascadeChunk { res_2, ..chunk.chunk }),
+				None => {
+					// The old mesh stays until the reloaded chunk is alive, when double buffered
+					match double_buffer.double_buffer.as_deref().filter(|_| mesh.is_some()) {
+						Some(double_buffer) => {
+							commands
+								.entity(entity)
+								.remove::<TerrainChunk>()
+								.insert(double_buffer.swap_out(id));
+						}
+						None => {
+							if let Some(mesh) = mesh {
+								release_chunk_mesh(mesh, &mut meshes);
+							}
+							chunks_to_unload.push((entity, chunk.chunk.origin));
+						}
+					}
+					loaded_chunks.mark_unloaded(id);
+				}
+			}
+		}
+	}
+
+	// Unload chunks that are too far away, their meshes released above. Materials are shared
+	// through the ChunkMaterialRegistry and are kept.
+	for (entity, origin) in chunks_to_unload {
+		commands.entity(entity).despawn();
+		log::debug!("Unloaded chunk at {:?}", origin);
+	}
+	// Old meshes of chunks left behind have nothing coming to replace them
+	double_buffer.drop_unwanted(&mut commands, &mut meshes, |id| chunks_to_load_set.contains(id));
+	// Also forgets chunks that were loaded without a mesh
+	loaded_chunks.retain(|id| chunks_to_load_set.contains(id));
+
+	// Chunks left behind stop generating, and every chunk does when what goes into them changed
+	if let Some(generation) = async_generation.as_deref_mut() {
+		let changed = sources.is_changed();
+		generation.retain(&mut commands, |id| !changed && chunks_to_load_set.contains(id));
+	}
+	let in_flight = |id: ChunkId| {
+		async_generation
+			.as_deref()
+			.is_some_and(|generation| generation.is_in_flight(id))
+	};
+
+	// Load new chunks from cascade - process cascade and grid separately
+	// Helper to collect chunks that need to be loaded
+	let collect_chunks_to_load = |chunks: &[CascadeChunk]| -> Vec<(CascadeChunk, ChunkId)> {
+		chunks
+			.iter()
+			.filter_map(|cascade_chunk| {
+				let id = chunk_config.chunk_id(cascade_chunk);
+				if !loaded_chunks.is_loaded(id) && !in_flight(id) {
+					Some((*cascade_chunk, id))
+				} else {
+					None
+				}
+			})
+			.collect()
+	};
+
+	let cascade_chunks_to_generate = collect_chunks_to_load(&cascade_chunks);
+	let grid_chunks_to_generate = collect_chunks_to_load(&grid_chunks);
+
+	// Recently unloaded chunks are respawned from their cached meshes
+	let mut respawn_cached = |chunks: Vec<(CascadeChunk, ChunkId)>, kind: ChunkKind| {
+		let Some(cache) = mesh_cache.as_deref_mut() else {
+			return chunks;
+		};
+		chunks
+			.into_iter()
+			.filter(|(chunk, id)| {
+				let Some((mesh, size)) = cache.take(*id, chunk) else {
+					return true;
+				};
+				ChunkSpawner::spawn_chunk_with_handle(
+					sdf_resource,
+					&mut commands,
+					&materials,
+					*chunk,
+					mesh,
+					size,
+					kind,
+				);
+				loaded_chunks.mark_loaded(*id);
+				false
+			})
+			.collect::<Vec<_>>()
+	};
+	let cascade_chunks_to_generate = respawn_cached(cascade_chunks_to_generate, ChunkKind::Cascade);
+	let grid_chunks_to_generate = respawn_cached(grid_chunks_to_generate, ChunkKind::Grid);
+
+	// Chunks of all air or all ground are marked loaded without sampling them
+	let mut skip_unoccupied = |chunks: Vec<(CascadeChunk, ChunkId)>| {
+		let Some(check) = chunk_config.occupancy else {
+			return chunks;
+		};
+		chunks
+			.into_iter()
+			.filter(|(chunk, id)| {
+				match check.classify_at(sdf_resource.sdf.as_ref(), chunk, sdf_resource.iso_level) {
+					ChunkOccupancy::Mixed => true,
+					occupancy => {
+						loaded_chunks.mark_skipped(*id, occupancy);
+						false
+					}
+				}
+			})
+			.collect::<Vec<_>>()
+	};
+	let cascade_chunks_to_generate = skip_unoccupied(cascade_chunks_to_generate);
+	let grid_chunks_to_generate = skip_unoccupied(grid_chunks_to_generate);
+
+	// Generate meshes in parallel using CPU
+	let start_time = std::time::Instant::now();
+	let generate = |kind: ChunkKind| {
+		let pipeline = sources.pipeline();
+		move |(cascade_chunk, _): &(CascadeChunk, ChunkId)| {
+			(*cascade_chunk, pipeline.generate(cascade_chunk), kind)
+		}
+	};
+
+	// Chunks generated while booting or ahead along the camera path are taken instead of
+	// generated again
+	let mut take_warmed = |chunks: Vec<(CascadeChunk, ChunkId)>, kind: ChunkKind| {
+		if prewarm.is_none() && boot.is_none() {
+			return (chunks, Vec::new());
+		}
+		let mut warmed = Vec::new();
+		let chunks = chunks
+			.into_iter()
+			.filter(|(chunk, id)| {
+				let booted = boot.as_deref_mut().and_then(|boot| boot.take(id, chunk));
+				let taken = booted
+					.or_else(|| prewarm.as_deref_mut().and_then(|prewarm| prewarm.take(id, chunk)));
+				match taken {
+					Some(mesh) => {
+						warmed.push((*chunk, mesh, kind));
+						false
+					}
+					None => true,
+				}
+			})
+			.collect();
+		(chunks, warmed)
+	};
+	let (cascade_chunks_to_generate, cascade_warmed) =
+		take_warmed(cascade_chunks_to_generate, ChunkKind::Cascade);
+	let (grid_chunks_to_generate, grid_warmed) =
+		take_warmed(grid_chunks_to_generate, ChunkKind::Grid);
+
+	// The chunks nearest the camera are generated first, and the rest wait for later frames
+	let (mut cascade_chunks_to_generate, mut grid_chunks_to_generate) = match budget.as_deref() {
+		Some(budget) => {
+			budget.nearest(cascade_chunks_to_generate, grid_chunks_to_generate, camera_pos)
+		}
+		None => (cascade_chunks_to_generate, grid_chunks_to_generate),
+	};
+
+	// Chunks generated asynchronously are spawned by finish_chunk_mesh_tasks once they finish
+	if let Some(generation) = async_generation.as_deref_mut() {
+		let tagged = |chunks: Vec<(CascadeChunk, ChunkId)>, kind: ChunkKind| {
+			chunks.into_iter().map(move |(chunk, id)| (chunk, id, kind))
+		};
+		let chunks: Vec<_> =
+			tagged(std::mem::take(&mut cascade_chunks_to_generate), ChunkKind::Cascade)
+				.chain(tagged(std::mem::take(&mut grid_chunks_to_generate), ChunkKind::Grid))
+				.take(generation.capacity())
+				.collect();
+		if !chunks.is_empty() {
+			generation.spawn(&mut commands, chunks, sources.pipeline().into_owned());
+		}
+	}
+
+	// Cascade and grid chunks are generated at once, each on its own lane when a generation pool
+	// is registered
+	let generate_all = |cascade: &[(CascadeChunk, ChunkId)], grid: &[(CascadeChunk, ChunkId)]| {
+		match generation_pool.as_deref() {
+			Some(pool) => rayon::join(
+				|| pool.near.run(cascade, generate(ChunkKind::Cascade)),
+				|| pool.far.run(grid, generate(ChunkKind::Grid)),
+			),
+			None => (
+				cascade.par_iter().map(generate(ChunkKind::Cascade)).collect(),
+				grid.par_iter().map(generate(ChunkKind::Grid)).collect(),
+			),
+		}
+	};
+	let (mut cascade_mesh_results, mut grid_mesh_results) =
+		match budget.as_deref().and_then(ChunkBudgetConfig::max_duration) {
+			Some(max_duration) => {
+				// A batch per thread at a time, nearest first across the cascade and grid, until
+				// the frame's time is spent, always generating the first so a tight budget still
+				// makes progress
+				let threads = match generation_pool.as_deref() {
+					Some(pool) => pool.near.threads() + pool.far.threads(),
+					None => rayon::current_num_threads(),
+				};
+				let batch = threads.max(1);
+				let nearest =
+					by_distance(&cascade_chunks_to_generate, &grid_chunks_to_generate, camera_pos);
+				let mut results = (Vec::new(), Vec::new());
+				for (index, batch) in nearest.chunks(batch).enumerate() {
+					if index > 0 && start_time.elapsed() >= max_duration {
+						break;
+					}
+					let (cascade, grid) = split_kinds(batch);
+					let (cascade, grid) = generate_all(&cascade, &grid);
+					results.0.extend(cascade);
+					results.1.extend(grid);
+				}
+				results
+			}
+			None => generate_all(&cascade_chunks_to_generate, &grid_chunks_to_generate),
+		};
+	cascade_mesh_results.extend(cascade_warmed);
+	grid_mesh_results.extend(grid_warmed);
+
+	// Spawn cascade chunks
+	for (cascade_chunk, mesh_opt, kind) in cascade_mesh_results {
+		let id = chunk_config.chunk_id(&cascade_chunk);
+		if let Some(mesh) = mesh_opt {
+			ChunkSpawner::spawn_chunk_with_mesh(
+				sdf_resource,
+				&mut commands,
+				&mut meshes,
+				&materials,
+				cascade_chunk,
+				mesh,
+				kind,
+			);
+			loaded_chunks.mark_loaded(id);
+		} else {
+			log::debug!(
+				"Skipping cascade chunk at origin {:?} - entirely above terrain",
+				cascade_chunk.origin
+			);
+			loaded_chunks.mark_loaded(id);
+		}
+	}
+
+	// Spawn grid chunks
+	for (cascade_chunk, mesh_opt, kind) in grid_mesh_results {
+		let id = chunk_config.chunk_id(&cascade_chunk);
+		if let Some(mesh) = mesh_opt {
+			ChunkSpawner::spawn_chunk_with_mesh(
+				sdf_resource,
+				&mut commands,
+				&mut meshes,
+				&materials,
+				cascade_chunk,
+				mesh,
+				kind,
+			);
+			loaded_chunks.mark_loaded(id);
+		} else {
+			log::debug!(
+				"Skipping grid chunk at origin {:?} - entirely above terrain",
+				cascade_chunk.origin
+			);
+			loaded_chunks.mark_loaded(id);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shaders::outline::EdgeMaterial;
+	use crate::test_support::{camera, ground_app, Ground};
+
+	#[test]
+	fn test_mesh_assets_stay_bounded() {
+		let mut app = ground_app(0.0);
+		app.add_systems(Update, manage_chunks::<Ground>);
+		let camera = camera(&mut app);
+
+		let mut max_meshes = 0;
+		for step in 0..40 {
+			app.world_mut()
+				.entity_mut(camera)
+				.insert(Transform::from_xyz(step as f32, 0.0, 0.0));
+			app.update();
+
+			let meshes = app.world().resource::<Assets<Mesh>>().len();
+			let chunks = app.world_mut().query::<&TerrainChunk>().iter(app.world()).count();
+			assert!(chunks > 0);
+			assert!(meshes <= chunks, "{meshes} meshes for {chunks} chunks at step {step}");
+			max_meshes = max_meshes.max(meshes);
+		}
+		assert_eq!(app.world().resource::<Assets<EdgeMaterial>>().len(), 1);
+		assert!(max_meshes > 0);
+	}
+
+	#[test]
+	fn test_meshes_with_other_users_outlive_their_chunks() {
+		let mut app = ground_app(0.0);
+		app.add_systems(Update, manage_chunks::<Ground>);
+		let camera = camera(&mut app);
+		app.update();
+
+		// Something else draws the mesh of a chunk the camera then leaves behind
+		let world = app.world_mut();
+		let Some(mesh) = world.query_filtered::<&Mesh3d, With<TerrainChunk>>().iter(world).next()
+		else {
+			panic!("No chunks were loaded around the camera");
+		};
+		let mesh = mesh.clone();
+		let user = app.world_mut().spawn(mesh.clone()).id();
+		app.world_mut().entity_mut(camera).insert(Transform::from_xyz(200.0, 0.0, 0.0));
+		app.update();
+		assert!(app.world().resource::<Assets<Mesh>>().contains(mesh.id()));
+
+		// Freed by Bevy once its last user is gone
+		app.world_mut().despawn(user);
+		let id = mesh.id();
+		drop(mesh);
+		app.update();
+		assert!(!app.world().resource::<Assets<Mesh>>().contains(id));
+	}
+
+	#[test]
+	fn test_pinned_chunks_stay_loaded_away_from_the_camera() {
+		let mut app = ground_app(0.0);
+		app.add_systems(Update, manage_chunks::<Ground>);
+		let camera = camera(&mut app);
+		app.update();
+
+		let chunk_ids = |app: &mut App| -> HashSet<ChunkId> {
+			let chunks: Vec<_> = app
+				.world_mut()
+				.query::<&TerrainChunk>()
+				.iter(app.world())
+				.map(|c| c.chunk)
+				.collect();
+			let config = app.world().resource::<ChunkConfig<Ground>>();
+			chunks.iter().map(|chunk| config.chunk_id(chunk)).collect()
+		};
+		let Some(pinned) = chunk_ids(&mut app).into_iter().min_by_key(|id| id.scale) else {
+			panic!("No chunks were loaded around the camera");
+		};
+		let move_camera = |app: &mut App, x: f32| {
+			app.world_mut().entity_mut(camera).insert(Transform::from_xyz(x, 0.0, 0.0));
+			app.update();
+		};
+
+		// Chunks the camera leaves unload, until they are pinned
+		move_camera(&mut app, 200.0);
+		assert!(!app.world().resource::<LoadedChunks>().is_loaded(pinned));
+		assert!(app.world_mut().resource_mut::<LoadedChunks>().pin(pinned));
+		app.update();
+		assert!(app.world().resource::<LoadedChunks>().is_loaded(pinned));
+		assert!(chunk_ids(&mut app).contains(&pinned));
+
+		move_camera(&mut app, 400.0);
+		assert!(chunk_ids(&mut app).contains(&pinned));
+		assert_eq!(app.world().resource::<LoadedChunks>().pinned_len(), 1);
+
+		assert!(app.world_mut().resource_mut::<LoadedChunks>().unpin(pinned));
+		app.update();
+		assert!(!app.world().resource::<LoadedChunks>().is_loaded(pinned));
+		assert!(!chunk_ids(&mut app).contains(&pinned));
+	}
+}
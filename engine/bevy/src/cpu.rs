@@ -0,0 +1,166 @@
+use crate::cascade::CascadeChunk;
+use crate::chunk::TerrainChunk;
+use crate::chunk_manager::SdfResource;
+use crate::material::{ChunkKind, ChunkMaterialRegistry};
+use crate::shaders::outline::EdgeMaterial;
+use crate::stats::ChunkMeshSize;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::sync::Arc;
+
+pub use engine_core::cpu::{sdf_normal, sparse_cubes, CpuMeshGenerator, MeshData};
+
+/// Converts generated mesh buffers into a Bevy [Mesh].
+pub trait IntoMesh {
+	/// Builds a render-world-only Bevy mesh from the buffers.
+	fn into_mesh(self) -> Mesh;
+}
+
+impl IntoMesh for MeshData {
+	fn into_mesh(self) -> Mesh {
+		let mut mesh = Mesh::new(
+			bevy::mesh::PrimitiveTopology::TriangleList,
+			bevy::asset::RenderAssetUsages::RENDER_WORLD,
+		);
+		mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+		mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+		mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+		if !self.colors.is_empty() {
+			mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+		}
+		mesh.insert_indices(bevy::mesh::Indices::U32(self.indices));
+		mesh
+	}
+}
+
+/// Spawns CPU generated chunk meshes as terrain chunk entities
+pub struct ChunkSpawner;
+
+impl ChunkSpawner {
+	/// Generate a terrain mesh for a specific chunk by sampling an SDF
+	/// Supports both heightfield (fast, no caves) and volumetric (marching cubes, supports caves)
+	/// Returns None if the chunk is entirely above the terrain surface
+	pub fn generate_chunk_mesh<S: Sdf + Send + Sync>(
+		cascade_chunk: &CascadeChunk,
+		sdf: Arc<S>,
+	) -> Option<Mesh> {
+		CpuMeshGenerator::generate_chunk_mesh_data(cascade_chunk, sdf).map(MeshData::into_mesh)
+	}
+
+	/// Spawn a terrain chunk entity from a pre-generated mesh
+	pub fn spawn_chunk_with_mesh<S: Sdf + Send + Sync>(
+		sdf_resource: &SdfResource<S>,
+		commands: &mut Commands,
+		meshes: &mut ResMut<Assets<Mesh>>,
+		materials: &ChunkMaterialRegistry,
+		cascade_chunk: CascadeChunk,
+		mesh: Mesh,
+		kind: ChunkKind,
+	) -> Entity {
+		let mesh_size = ChunkMeshSize::of(&mesh);
+		let mesh_handle = meshes.add(mesh);
+		Self::spawn_chunk_with_handle(
+			sdf_resource,
+			commands,
+			materials,
+			cascade_chunk,
+			mesh_handle,
+			mesh_size,
+			kind,
+		)
+	}
+
+	/// Spawn a terrain chunk entity from a mesh already in the assets, such as a cached one
+	pub fn spawn_chunk_with_handle<S: Sdf + Send + Sync>(
+		sdf_resource: &SdfResource<S>,
+		commands: &mut Commands,
+		materials: &ChunkMaterialRegistry,
+		cascade_chunk: CascadeChunk,
+		mesh_handle: Handle<Mesh>,
+		mesh_size: ChunkMeshSize,
+		kind: ChunkKind,
+	) -> Entity {
+		// Share the registered material (shader handles the rendering)
+		let material_handle = materials.get(kind, sdf_resource.tag).unwrap_or_else(|| {
+			log::warn!("No chunk material registered for {:?} {:?}", kind, sdf_resource.tag);
+			Handle::default()
+		});
+
+		// Use cascade chunk origin for the position in the local space of the SDF
+		// Note: mesh vertices are in local space relative to chunk origin
+		let sdf = &sdf_resource.sdf;
+		let local_pos = cascade_chunk.origin + sdf.translation();
+		log::info!(
+			"Typename: {:?}, Translation: {:?}",
+			std::any::type_name::<S>(),
+			sdf.translation()
+		);
+
+		let terrain_chunk = TerrainChunk { chunk: cascade_chunk };
+		let entity = commands
+			.spawn((
+				terrain_chunk,
+				mesh_size,
+				Mesh3d(mesh_handle.clone()),
+				MeshMaterial3d::<EdgeMaterial>(material_handle.clone()),
+				sdf_resource.transform.to_transform()
+					* Transform::from_translation(local_pos)
+						.with_rotation(sdf.rotation())
+						.with_scale(sdf.scale()),
+			))
+			.id();
+		#[cfg(feature = "debug-names")]
+		commands.entity(entity).insert(Name::new(terrain_chunk.name()));
+
+		log::debug!(
+			"Spawned chunk (CPU) at origin {:?} with size {} and resolution {}",
+			cascade_chunk.origin,
+			cascade_chunk.size,
+			cascade_chunk.resolution()
+		);
+
+		entity
+	}
+
+	/// Spawn a terrain chunk entity using CPU mesh generation
+	pub fn spawn_chunk<S: Sdf + Send + Sync>(
+		commands: &mut Commands,
+		meshes: &mut ResMut<Assets<Mesh>>,
+		materials: &ChunkMaterialRegistry,
+		cascade_chunk: CascadeChunk,
+		sdf_resource: &SdfResource<S>,
+	) -> Entity {
+		// Generate mesh using cascade chunk
+		let start_time = std::time::Instant::now();
+		let sdf = sdf_resource.sdf.clone();
+		let mesh = CpuMeshGenerator::generate_chunk_mesh_data_with(
+			&cascade_chunk,
+			sdf,
+			sdf_resource.iso_level,
+			sdf_resource.interpolation,
+		);
+		let Some(mesh) = mesh.map(MeshData::into_mesh) else {
+			// Chunk is entirely above terrain, don't spawn it
+			log::debug!(
+				"Skipping chunk at origin {:?} - entirely above terrain",
+				cascade_chunk.origin
+			);
+			// Return a dummy entity that will be cleaned up
+			return commands.spawn_empty().id();
+		};
+		let end_time = std::time::Instant::now();
+		let duration = end_time.duration_since(start_time);
+		log::info!("Mesh time: {:?}", duration);
+
+		// Default to grid for backward compatibility when called directly
+		Self::spawn_chunk_with_mesh(
+			sdf_resource,
+			commands,
+			meshes,
+			materials,
+			cascade_chunk,
+			mesh,
+			ChunkKind::Grid,
+		)
+	}
+}
@@ -0,0 +1,173 @@
+use crate::shaders::outline::EdgeMaterial;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::marker::PhantomData;
+
+/// Crossfades chunks whose resolution changes, rather than swapping their meshes in one frame.
+///
+/// While registered, [regenerate_queued_chunks](crate::regenerate_queued_chunks) keeps the old
+/// mesh on a copy of the chunk that dissolves over the frames while the new mesh dissolves in,
+/// each with its own copy of the chunk material. Requires the [crossfade_chunks] system.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct ChunkCrossfade<S: Sdf + Send + Sync> {
+	/// Frames the old and new meshes take to swap
+	pub frames: u32,
+	/// Marker for the SDF that defines the chunk boundaries
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for ChunkCrossfade<S> {
+	fn default() -> Self {
+		Self { frames: 12, sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync> ChunkCrossfade<S> {
+	pub fn with_frames(mut self, frames: u32) -> Self {
+		self.frames = frames;
+		self
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FadeDirection {
+	In,
+	Out,
+}
+
+/// A chunk mesh dissolving in or out, drawn with its own copy of the chunk material.
+#[derive(Component, Debug, Clone)]
+pub struct ChunkFade {
+	/// The shared material the chunk goes back to once it has faded in
+	pub shared: Handle<EdgeMaterial>,
+	pub direction: FadeDirection,
+	pub frame: u32,
+	pub frames: u32,
+}
+
+impl ChunkFade {
+	/// The dissolve of the material at the current frame.
+	///
+	/// Meshes fading out dissolve the fragments the meshes fading in have dissolved in,
+	/// so the pair covers the chunk on every frame.
+	pub fn dissolve(&self) -> f32 {
+		let progress = (self.frame as f32 / self.frames.max(1) as f32).min(1.0);
+		match self.direction {
+			FadeDirection::Out => progress,
+			FadeDirection::In => progress - 1.0,
+		}
+	}
+
+	pub fn is_done(&self) -> bool {
+		self.frame >= self.frames
+	}
+}
+
+/// Starts crossfading a chunk from its old mesh to the new mesh it was just given.
+///
+/// The old mesh fades out on a new entity, which is despawned when the fade is done.
+pub(crate) fn start_crossfade(
+	commands: &mut Commands,
+	materials: &mut Assets<EdgeMaterial>,
+	entity: Entity,
+	old_mesh: Handle<Mesh>,
+	transform: Transform,
+	shared: Handle<EdgeMaterial>,
+	frames: u32,
+) {
+	let Some(material) = materials.get(&shared).cloned() else {
+		return;
+	};
+	let fade = |direction| ChunkFade { shared: shared.clone(), direction, frame: 0, frames };
+
+	let fade_out = fade(FadeDirection::Out);
+	let out_material = materials.add(material.clone().with_dissolve(fade_out.dissolve()));
+	commands.spawn((Mesh3d(old_mesh), MeshMaterial3d(out_material), transform, fade_out));
+
+	let fade_in = fade(FadeDirection::In);
+	let in_material = materials.add(material.with_dissolve(fade_in.dissolve()));
+	commands.entity(entity).insert((MeshMaterial3d(in_material), fade_in));
+}
+
+/// Advances the chunk crossfades, despawning old meshes that faded out and returning chunks
+/// that faded in to the shared material.
+pub fn crossfade_chunks(
+	mut commands: Commands,
+	mut materials: ResMut<Assets<EdgeMaterial>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut fade_query: Query<(Entity, &mut ChunkFade, &MeshMaterial3d<EdgeMaterial>, &Mesh3d)>,
+) {
+	for (entity, mut fade, material, mesh) in &mut fade_query {
+		fade.frame += 1;
+		if !fade.is_done() {
+			if let Some(material) = materials.get_mut(&material.0) {
+				material.dissolve = fade.dissolve();
+			}
+			continue;
+		}
+
+		materials.remove(material.id());
+		match fade.direction {
+			FadeDirection::Out => {
+				meshes.remove(mesh.id());
+				commands.entity(entity).despawn();
+			}
+			FadeDirection::In => {
+				commands
+					.entity(entity)
+					.insert(MeshMaterial3d(fade.shared.clone()))
+					.remove::<ChunkFade>();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::chunk::TerrainChunk;
+	use crate::chunk_manager::{manage_chunks, ChunkResolutionConfig};
+	use crate::regeneration::{regenerate_queued_chunks, ChunkRegenerationQueue};
+	use crate::test_support::{ground_app, Ground};
+
+	#[test]
+	fn test_resolution_change_crossfades() {
+		let mut app = ground_app(0.0);
+		app.insert_resource(ChunkRegenerationQueue::<Ground>::default().with_chunks_per_frame(64))
+			.insert_resource(ChunkCrossfade::<Ground>::default().with_frames(3))
+			.add_systems(
+				Update,
+				(manage_chunks::<Ground>, regenerate_queued_chunks::<Ground>, crossfade_chunks)
+					.chain(),
+			);
+		app.update();
+
+		let chunks = app.world_mut().query::<&TerrainChunk>().iter(app.world()).count();
+		app.world_mut().resource_mut::<ChunkResolutionConfig<Ground>>().base_res_2 = 3;
+		app.update();
+
+		// The old meshes fade out beside the chunks, which fade in with the new meshes
+		let mut fades = app.world_mut().query::<&ChunkFade>();
+		let fading_in = fades
+			.iter(app.world())
+			.filter(|fade| fade.direction == FadeDirection::In)
+			.count();
+		// Only the cascade chunks change resolution, the grid keeps its own
+		assert!(fading_in > 0 && fading_in < chunks);
+		assert_eq!(fades.iter(app.world()).count(), 2 * fading_in);
+		assert_eq!(app.world().resource::<Assets<EdgeMaterial>>().len(), 2 * fading_in + 1);
+
+		for _ in 0..3 {
+			app.update();
+		}
+		assert_eq!(fades.iter(app.world()).count(), 0);
+		assert_eq!(app.world_mut().query::<&Mesh3d>().iter(app.world()).count(), chunks);
+		assert_eq!(app.world().resource::<Assets<EdgeMaterial>>().len(), 1);
+		let mut terrain_chunks = app.world_mut().query::<&TerrainChunk>();
+		let finer = terrain_chunks
+			.iter(app.world())
+			.filter(|terrain_chunk| terrain_chunk.chunk.res_2 == UVec3::splat(3))
+			.count();
+		assert_eq!(finer, fading_in);
+	}
+}
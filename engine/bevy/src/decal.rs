@@ -0,0 +1,470 @@
+use crate::cascade::CascadeChunk;
+use crate::chunk::TerrainChunk;
+use crate::cpu::{IntoMesh, MeshData};
+use crate::shaders::decal_material::{DecalMaterial, DecalSettings};
+use bevy::camera::primitives::Aabb;
+use bevy::math::bounding::{Aabb3d, IntersectsVolume};
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+/// How far decal triangles are lifted off the surface, to avoid z-fighting.
+const DECAL_LIFT: f32 = 0.02;
+
+/// Surfaces facing the projection axis less than this are skipped.
+const MIN_FACING: f32 = 0.2;
+
+/// The kinds of ground detail a decal can paint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecalKind {
+	/// Twin ruts along a road, running along the decal's local Z
+	TireRuts,
+	/// Speckled leaf litter under trees
+	LeafLitter,
+	/// A soft dirt patch around the foot of a building
+	DirtSkirt,
+}
+
+impl DecalKind {
+	pub const ALL: [DecalKind; 3] =
+		[DecalKind::TireRuts, DecalKind::LeafLitter, DecalKind::DirtSkirt];
+
+	pub fn color(&self) -> Vec4 {
+		match self {
+			DecalKind::TireRuts => Vec4::new(0.3, 0.24, 0.18, 0.8),
+			DecalKind::LeafLitter => Vec4::new(0.55, 0.36, 0.16, 0.9),
+			DecalKind::DirtSkirt => Vec4::new(0.4, 0.33, 0.25, 0.7),
+		}
+	}
+
+	/// The pattern the decal shader draws for the kind.
+	pub fn pattern(&self) -> u32 {
+		match self {
+			DecalKind::DirtSkirt => 0,
+			DecalKind::TireRuts => 1,
+			DecalKind::LeafLitter => 2,
+		}
+	}
+}
+
+/// A box projected down its local Y axis onto the terrain beneath it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decal {
+	pub kind: DecalKind,
+	pub center: Vec3,
+	pub half_extents: Vec3,
+	pub rotation: Quat,
+}
+
+impl Decal {
+	pub fn new(kind: DecalKind, center: Vec3, half_extents: Vec3) -> Self {
+		Self {
+			kind,
+			center,
+			half_extents: half_extents.max(Vec3::splat(1e-3)),
+			rotation: Quat::IDENTITY,
+		}
+	}
+
+	pub fn with_rotation(mut self, rotation: Quat) -> Self {
+		self.rotation = rotation;
+		self
+	}
+
+	/// Turns the decal about the vertical axis.
+	pub fn with_yaw(self, yaw: f32) -> Self {
+		self.with_rotation(Quat::from_rotation_y(yaw))
+	}
+
+	/// The axis surfaces must face to receive the decal.
+	pub fn up(&self) -> Vec3 {
+		self.rotation * Vec3::Y
+	}
+
+	/// Maps a point into the decal box, which spans -1 to 1 on each axis.
+	pub fn to_decal_space(&self, point: Vec3) -> Vec3 {
+		(self.rotation.inverse() * (point - self.center)) / self.half_extents
+	}
+
+	/// Axis aligned bounds of the decal box.
+	pub fn bounds(&self) -> Aabb3d {
+		let extents = (Mat3::from_quat(self.rotation).abs()) * self.half_extents;
+		Aabb3d::new(self.center, extents)
+	}
+}
+
+/// Identifies a registered [Decal].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecalId(u64);
+
+/// The decals on the terrain of an SDF, in the SDF's local space like its chunks.
+///
+/// Chunks overlapping a decal that is added or removed are projected again by
+/// [project_chunk_decals].
+#[derive(Resource)]
+pub struct Decals<S: Sdf + Send + Sync> {
+	decals: HashMap<DecalId, Decal>,
+	next_id: u64,
+	/// Bounds of the decals added or removed since their chunks were projected
+	changed: Vec<Aabb3d>,
+	/// Marker for the SDF whose terrain the decals are projected onto
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for Decals<S> {
+	fn default() -> Self {
+		Self { decals: HashMap::new(), next_id: 0, changed: Vec::new(), sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync> Decals<S> {
+	pub fn insert(&mut self, decal: Decal) -> DecalId {
+		let id = DecalId(self.next_id);
+		self.next_id += 1;
+		self.changed.push(decal.bounds());
+		self.decals.insert(id, decal);
+		id
+	}
+
+	pub fn remove(&mut self, id: DecalId) -> Option<Decal> {
+		let decal = self.decals.remove(&id)?;
+		self.changed.push(decal.bounds());
+		Some(decal)
+	}
+
+	pub fn get(&self, id: DecalId) -> Option<&Decal> {
+		self.decals.get(&id)
+	}
+
+	pub fn len(&self) -> usize {
+		self.decals.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.decals.is_empty()
+	}
+
+	/// The decals overlapping a region.
+	pub fn overlapping(&self, region: &Aabb3d) -> Vec<Decal> {
+		self.decals
+			.values()
+			.filter(|decal| decal.bounds().intersects(region))
+			.copied()
+			.collect()
+	}
+}
+
+/// Distances from the camera over which decals fade out.
+///
+/// Read when [DecalMaterials] are created. Decal batches past the end are also hidden.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct DecalFade {
+	pub start: f32,
+	pub end: f32,
+}
+
+impl Default for DecalFade {
+	fn default() -> Self {
+		Self { start: 48.0, end: 96.0 }
+	}
+}
+
+/// One shared material per [DecalKind].
+#[derive(Resource, Debug, Clone)]
+pub struct DecalMaterials {
+	materials: HashMap<DecalKind, Handle<DecalMaterial>>,
+}
+
+impl FromWorld for DecalMaterials {
+	fn from_world(world: &mut World) -> Self {
+		let fade = world.get_resource::<DecalFade>().copied().unwrap_or_default();
+		let mut assets = world.resource_mut::<Assets<DecalMaterial>>();
+		let materials = DecalKind::ALL
+			.into_iter()
+			.map(|kind| {
+				let settings = DecalSettings {
+					color: kind.color(),
+					pattern: kind.pattern(),
+					fade_start: fade.start,
+					fade_end: fade.end,
+				};
+				(kind, assets.add(DecalMaterial { settings }))
+			})
+			.collect();
+		Self { materials }
+	}
+}
+
+impl DecalMaterials {
+	pub fn get(&self, kind: DecalKind) -> Option<Handle<DecalMaterial>> {
+		self.materials.get(&kind).cloned()
+	}
+}
+
+/// A batch of decals of one kind projected onto a chunk, spawned as a child of the chunk
+/// so it unloads with it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkDecals {
+	pub kind: DecalKind,
+}
+
+#[derive(Clone, Copy)]
+struct ClipVertex {
+	/// Position in the decal box
+	decal: Vec3,
+	/// Position relative to the chunk origin
+	local: Vec3,
+}
+
+/// Clips a polygon to the decal box, one face at a time.
+fn clip_to_box(mut polygon: Vec<ClipVertex>) -> Vec<ClipVertex> {
+	for axis in 0..3 {
+		for side in [-1.0f32, 1.0] {
+			let inside = |vertex: &ClipVertex| vertex.decal[axis] * side <= 1.0;
+			let mut clipped = Vec::with_capacity(polygon.len() + 2);
+			for (index, current) in polygon.iter().enumerate() {
+				let next = &polygon[(index + 1) % polygon.len()];
+				if inside(current) {
+					clipped.push(*current);
+				}
+				if inside(current) != inside(next) {
+					let t = (side - current.decal[axis]) / (next.decal[axis] - current.decal[axis]);
+					clipped.push(ClipVertex {
+						decal: current.decal.lerp(next.decal, t),
+						local: current.local.lerp(next.local, t),
+					});
+				}
+			}
+			polygon = clipped;
+			if polygon.len() < 3 {
+				return Vec::new();
+			}
+		}
+	}
+	polygon
+}
+
+/// Projects decals onto the triangles of a chunk mesh, batching them into one mesh per kind.
+///
+/// Positions are relative to the chunk origin, like the chunk mesh, and UVs span the decal box.
+pub fn project_decals(
+	chunk: &CascadeChunk,
+	positions: &[[f32; 3]],
+	normals: &[[f32; 3]],
+	indices: &[u32],
+	decals: &[Decal],
+) -> Vec<(DecalKind, MeshData)> {
+	let mut batches: HashMap<DecalKind, MeshData> = HashMap::new();
+	let vertex = |index: u32| Vec3::from_array(positions[index as usize]);
+	let normal = |index: u32| {
+		normals
+			.get(index as usize)
+			.map(|normal| Vec3::from_array(*normal))
+			.unwrap_or_default()
+	};
+
+	for decal in decals {
+		let bounds = decal.bounds();
+		let up = decal.up();
+		let batch = batches.entry(decal.kind).or_default();
+		for triangle in indices.chunks_exact(3) {
+			let local = [vertex(triangle[0]), vertex(triangle[1]), vertex(triangle[2])];
+			let min = local[0].min(local[1]).min(local[2]) + chunk.origin;
+			let max = local[0].max(local[1]).max(local[2]) + chunk.origin;
+			if !bounds.intersects(&Aabb3d { min: min.into(), max: max.into() }) {
+				continue;
+			}
+
+			let face_normal = triangle.iter().map(|&index| normal(index)).sum::<Vec3>();
+			let face_normal = face_normal.try_normalize().unwrap_or_else(|| {
+				(local[1] - local[0]).cross(local[2] - local[0]).normalize_or_zero()
+			});
+			if face_normal.dot(up) < MIN_FACING {
+				continue;
+			}
+
+			let polygon = local
+				.iter()
+				.map(|&local| ClipVertex {
+					decal: decal.to_decal_space(local + chunk.origin),
+					local,
+				})
+				.collect();
+			let polygon = clip_to_box(polygon);
+			if polygon.is_empty() {
+				continue;
+			}
+
+			let first = batch.positions.len() as u32;
+			for vertex in &polygon {
+				batch.positions.push((vertex.local + face_normal * DECAL_LIFT).to_array());
+				batch.normals.push(face_normal.to_array());
+				batch.uvs.push([vertex.decal.x * 0.5 + 0.5, vertex.decal.z * 0.5 + 0.5]);
+			}
+			for index in 1..polygon.len() as u32 - 1 {
+				batch.indices.extend([first, first + index, first + index + 1]);
+			}
+		}
+	}
+
+	let mut batches: Vec<_> = batches.into_iter().filter(|(_, mesh)| !mesh.is_empty()).collect();
+	batches.sort_by_key(|(kind, _)| kind.pattern());
+	batches
+}
+
+fn chunk_aabb(chunk: &CascadeChunk) -> Aabb3d {
+	Aabb3d { min: chunk.origin.into(), max: (chunk.origin + chunk.size).into() }
+}
+
+/// Projects decals onto chunks whose mesh changed, and onto chunks overlapping decals that were
+/// added or removed, replacing their [ChunkDecals] batches.
+pub fn project_chunk_decals<S: Sdf + Send + Sync + 'static>(
+	mut commands: Commands,
+	mut decals: ResMut<Decals<S>>,
+	decal_materials: Res<DecalMaterials>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	chunk_query: Query<(Entity, &TerrainChunk, &Mesh3d, Option<&Children>)>,
+	changed_chunks: Query<Entity, (With<TerrainChunk>, Changed<Mesh3d>)>,
+	batch_query: Query<&Mesh3d, With<ChunkDecals>>,
+) {
+	let changed = std::mem::take(&mut decals.changed);
+	let mut to_project: HashSet<Entity> = changed_chunks.iter().collect();
+	if !changed.is_empty() {
+		for (entity, chunk, _, _) in &chunk_query {
+			let aabb = chunk_aabb(&chunk.chunk);
+			if changed.iter().any(|region| region.intersects(&aabb)) {
+				to_project.insert(entity);
+			}
+		}
+	}
+
+	for entity in to_project {
+		let Ok((entity, chunk, mesh, children)) = chunk_query.get(entity) else {
+			continue;
+		};
+		for child in children.into_iter().flatten() {
+			if let Ok(batch) = batch_query.get(*child) {
+				meshes.remove(batch.id());
+				commands.entity(*child).despawn();
+			}
+		}
+
+		let overlapping = decals.overlapping(&chunk_aabb(&chunk.chunk));
+		if overlapping.is_empty() {
+			continue;
+		}
+		let Some(mesh) = meshes.get(mesh.id()) else {
+			continue;
+		};
+		let Some(positions) = mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(|p| p.as_float3())
+		else {
+			continue;
+		};
+		let normals = mesh
+			.attribute(Mesh::ATTRIBUTE_NORMAL)
+			.and_then(|n| n.as_float3())
+			.unwrap_or(&[]);
+		let indices: Vec<u32> = match mesh.indices() {
+			Some(indices) => indices.iter().map(|index| index as u32).collect(),
+			None => (0..positions.len() as u32).collect(),
+		};
+
+		for (kind, batch) in
+			project_decals(&chunk.chunk, positions, normals, &indices, &overlapping)
+		{
+			let Some(material) = decal_materials.get(kind) else {
+				log::warn!("No decal material registered for {kind:?}");
+				continue;
+			};
+			commands.spawn((
+				ChunkDecals { kind },
+				Mesh3d(meshes.add(batch.into_mesh())),
+				MeshMaterial3d(material),
+				Transform::IDENTITY,
+				ChildOf(entity),
+			));
+		}
+	}
+}
+
+/// Hides decal batches beyond the fade distance of the camera.
+pub fn fade_distant_decals(
+	fade: Option<Res<DecalFade>>,
+	camera_query: Query<&Transform, With<Camera3d>>,
+	mut batch_query: Query<(&GlobalTransform, Option<&Aabb>, &mut Visibility), With<ChunkDecals>>,
+) {
+	let Ok(camera) = camera_query.single() else {
+		return;
+	};
+	let fade = fade.as_deref().copied().unwrap_or_default();
+	for (transform, aabb, mut visibility) in &mut batch_query {
+		let (center, radius) = match aabb {
+			Some(aabb) => (
+				transform.transform_point(aabb.center.into()),
+				(transform.affine().matrix3 * Vec3::from(aabb.half_extents)).length(),
+			),
+			None => (transform.translation(), 0.0),
+		};
+		let visible = camera.translation.distance(center) - radius <= fade.end;
+		visibility.set_if_neq(if visible { Visibility::Inherited } else { Visibility::Hidden });
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A flat 4x4 quad at height 1, relative to the chunk origin.
+	fn ground() -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u32>) {
+		let positions = vec![[0.0, 1.0, 0.0], [4.0, 1.0, 0.0], [4.0, 1.0, 4.0], [0.0, 1.0, 4.0]];
+		(positions, vec![[0.0, 1.0, 0.0]; 4], vec![0, 2, 1, 0, 3, 2])
+	}
+
+	#[test]
+	fn test_decals_are_clipped_to_their_box() {
+		let chunk = CascadeChunk::cube(Vec3::new(10.0, 0.0, 10.0), 4.0, 2);
+		let (positions, normals, indices) = ground();
+		let ruts =
+			Decal::new(DecalKind::TireRuts, Vec3::new(12.0, 1.0, 12.0), Vec3::new(1.0, 0.5, 1.0));
+		let litter = Decal::new(DecalKind::LeafLitter, Vec3::new(11.0, 1.0, 14.0), Vec3::ONE);
+		// Too high above the ground to reach it
+		let floating = Decal::new(DecalKind::DirtSkirt, Vec3::new(12.0, 4.0, 12.0), Vec3::ONE);
+
+		let batches =
+			project_decals(&chunk, &positions, &normals, &indices, &[ruts, litter, floating]);
+		let kinds: Vec<_> = batches.iter().map(|(kind, _)| *kind).collect();
+		assert_eq!(kinds, vec![DecalKind::TireRuts, DecalKind::LeafLitter]);
+
+		let (_, ruts_mesh) = &batches[0];
+		for (position, uv) in ruts_mesh.positions.iter().zip(&ruts_mesh.uvs) {
+			let world = chunk.origin + Vec3::from_array(*position);
+			assert!((world.x - 12.0).abs() <= 1.0 + 1e-4 && (world.z - 12.0).abs() <= 1.0 + 1e-4);
+			assert!((world.y - 1.0 - DECAL_LIFT).abs() < 1e-5);
+			assert!(uv.iter().all(|c| (-1e-4..=1.0 + 1e-4).contains(c)));
+		}
+		let area: f32 = ruts_mesh
+			.indices
+			.chunks_exact(3)
+			.map(|t| {
+				let [a, b, c] =
+					[0, 1, 2].map(|i| Vec3::from_array(ruts_mesh.positions[t[i] as usize]));
+				(b - a).cross(c - a).length() / 2.0
+			})
+			.sum();
+		assert!((area - 4.0).abs() < 1e-3);
+
+		// The litter hangs over the edge of the chunk and is cut to the quad
+		let (_, litter_mesh) = &batches[1];
+		assert!(litter_mesh.positions.iter().all(|p| p[2] <= 4.0 + 1e-4));
+	}
+
+	#[test]
+	fn test_decals_skip_surfaces_facing_away() {
+		let chunk = CascadeChunk::cube(Vec3::ZERO, 4.0, 2);
+		let (positions, _, indices) = ground();
+		let walls = vec![[1.0, 0.0, 0.0]; 4];
+		let dirt = Decal::new(DecalKind::DirtSkirt, Vec3::new(2.0, 1.0, 2.0), Vec3::ONE);
+		assert!(project_decals(&chunk, &positions, &walls, &indices, &[dirt]).is_empty());
+	}
+}
@@ -0,0 +1,278 @@
+use crate::chunk::{ChunkId, LoadedChunks};
+use crate::chunk_manager::release_chunk_mesh;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::marker::PhantomData;
+
+/// Keeps the old mesh of a chunk on screen until the mesh replacing it is alive, rather than
+/// leaving a hole for the frames between despawning one and drawing the other.
+///
+/// While registered, chunks that [manage_chunks](crate::manage_chunks) reloads at a new
+/// resolution and chunks that [regenerate_queued_chunks](crate::regenerate_queued_chunks)
+/// swaps in place leave their old mesh behind as a [ChunkSwapOut]. Reloads generated by
+/// [AsyncChunkGeneration](crate::AsyncChunkGeneration) are covered too, since the old mesh
+/// waits on the chunk being loaded again rather than on the system that loads it. Old meshes
+/// of chunks the camera leaves behind are despawned right away by manage_chunks, as nothing
+/// is coming to replace them. Requires the [swap_chunk_buffers] system.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct ChunkDoubleBuffer<S: Sdf + Send + Sync> {
+	/// Frames the new mesh is drawn beside the old one before the old one goes
+	pub frames: u32,
+	/// Most frames an old mesh waits for a replacement that never comes, such as one whose
+	/// chunk was generated without a mesh
+	pub max_frames: u32,
+	/// Marker for the SDF that defines the chunk boundaries
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for ChunkDoubleBuffer<S> {
+	fn default() -> Self {
+		Self { frames: 1, max_frames: 120, sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync> ChunkDoubleBuffer<S> {
+	pub fn with_frames(mut self, frames: u32) -> Self {
+		self.frames = frames;
+		self
+	}
+
+	pub fn with_max_frames(mut self, max_frames: u32) -> Self {
+		self.max_frames = max_frames;
+		self
+	}
+
+	/// Marks the old mesh of the chunk as waiting for its replacement.
+	pub(crate) fn swap_out(&self, id: ChunkId) -> ChunkSwapOut {
+		ChunkSwapOut { id, frames: self.frames, max_frames: self.max_frames, alive: 0, waited: 0 }
+	}
+}
+
+/// The old mesh of a chunk, drawn until the mesh replacing it has been alive for some frames.
+///
+/// It is no longer a terrain chunk, so the chunk systems only see its replacement.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ChunkSwapOut {
+	/// The chunk being replaced
+	pub id: ChunkId,
+	pub frames: u32,
+	pub max_frames: u32,
+	/// Frames the replacement has been loaded
+	pub alive: u32,
+	/// Frames spent waiting for the replacement to load
+	pub waited: u32,
+}
+
+impl ChunkSwapOut {
+	/// Whether the replacement was alive for the frames before this one, so its mesh was
+	/// extracted and prepared while the old one was still drawn.
+	pub fn is_done(&self) -> bool {
+		self.alive > self.frames || self.waited > self.max_frames
+	}
+}
+
+/// The [ChunkDoubleBuffer] of an SDF, if registered, and the old meshes waiting on their
+/// replacements.
+#[derive(SystemParam)]
+pub struct ChunkSwapOuts<'w, 's, S: Sdf + Send + Sync + 'static> {
+	pub double_buffer: Option<Res<'w, ChunkDoubleBuffer<S>>>,
+	swap_query: Query<'w, 's, (Entity, &'static ChunkSwapOut, Option<&'static Mesh3d>)>,
+}
+
+impl<S: Sdf + Send + Sync + 'static> ChunkSwapOuts<'_, '_, S> {
+	/// Despawns the old meshes of the chunks that are no longer wanted, and releases their
+	/// assets.
+	pub(crate) fn drop_unwanted(
+		&self,
+		commands: &mut Commands,
+		meshes: &mut Assets<Mesh>,
+		wanted: impl Fn(&ChunkId) -> bool,
+	) {
+		if self.double_buffer.is_none() {
+			return;
+		}
+		for (entity, swap, mesh) in &self.swap_query {
+			if wanted(&swap.id) {
+				continue;
+			}
+			if let Some(mesh) = mesh {
+				release_chunk_mesh(mesh, meshes);
+			}
+			commands.entity(entity).try_despawn();
+		}
+	}
+}
+
+/// Despawns the old chunk meshes whose replacements are alive, and releases their assets.
+///
+/// A chunk loaded without a mesh has nothing to wait for, so its old mesh goes as soon as the
+/// chunk is loaded again. Add it after manage_chunks and regenerate_queued_chunks.
+pub fn swap_chunk_buffers(
+	mut commands: Commands,
+	mut meshes: ResMut<Assets<Mesh>>,
+	loaded_chunks: Res<LoadedChunks>,
+	mut swap_query: Query<(Entity, &mut ChunkSwapOut, Option<&Mesh3d>)>,
+) {
+	for (entity, mut swap, mesh) in &mut swap_query {
+		match loaded_chunks.is_loaded(swap.id) {
+			true => swap.alive += 1,
+			false => swap.waited += 1,
+		}
+		if !swap.is_done() {
+			continue;
+		}
+
+		if let Some(mesh) = mesh {
+			release_chunk_mesh(mesh, &mut meshes);
+		}
+		commands.entity(entity).despawn();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::async_generation::{finish_chunk_mesh_tasks, AsyncChunkGeneration};
+	use crate::budget::ChunkBudgetConfig;
+	use crate::chunk::TerrainChunk;
+	use crate::chunk_manager::{manage_chunks, ChunkResolutionConfig};
+	use crate::regeneration::{
+		queue_dirty_chunks, regenerate_queued_chunks, ChunkRegenerationQueue, TerrainDirty,
+	};
+	use crate::test_support::{camera, ground_app, Ground};
+	use bevy::app::TaskPoolPlugin;
+	use std::collections::HashSet;
+
+	fn app() -> App {
+		let mut app = ground_app(0.0);
+		app.add_message::<TerrainDirty>()
+			.insert_resource(ChunkDoubleBuffer::<Ground>::default());
+		app
+	}
+
+	fn chunk_meshes(app: &mut App) -> HashSet<AssetId<Mesh>> {
+		let world = app.world_mut();
+		let mut query = world.query_filtered::<&Mesh3d, With<TerrainChunk>>();
+		query.iter(world).map(|mesh| mesh.id()).collect()
+	}
+
+	fn swapping_meshes(app: &mut App) -> HashSet<AssetId<Mesh>> {
+		let world = app.world_mut();
+		let mut query = world.query_filtered::<&Mesh3d, With<ChunkSwapOut>>();
+		query.iter(world).map(|mesh| mesh.id()).collect()
+	}
+
+	#[test]
+	fn test_regenerated_chunks_keep_their_old_meshes_until_the_new_ones_are_alive() {
+		let mut app = app();
+		app.insert_resource(ChunkRegenerationQueue::<Ground>::default().with_chunks_per_frame(64))
+			.add_systems(
+				Update,
+				(
+					manage_chunks::<Ground>,
+					queue_dirty_chunks::<Ground>,
+					regenerate_queued_chunks::<Ground>,
+					swap_chunk_buffers,
+				)
+					.chain(),
+			);
+		app.update();
+		let old = chunk_meshes(&mut app);
+
+		app.world_mut().write_message(TerrainDirty);
+		app.update();
+
+		// The chunks drew their new meshes beside the old ones this frame
+		let new = chunk_meshes(&mut app);
+		assert_eq!(new.len(), old.len());
+		assert!(new.is_disjoint(&old));
+		assert_eq!(swapping_meshes(&mut app), old);
+		let meshes = app.world().resource::<Assets<Mesh>>();
+		assert!(old.iter().all(|mesh| meshes.contains(*mesh)));
+
+		app.update();
+		assert!(swapping_meshes(&mut app).is_empty());
+		let meshes = app.world().resource::<Assets<Mesh>>();
+		assert!(old.iter().all(|mesh| !meshes.contains(*mesh)));
+	}
+
+	#[test]
+	fn test_reloaded_chunks_spawn_before_the_old_ones_despawn() {
+		let mut app = app();
+		app.add_systems(Update, (manage_chunks::<Ground>, swap_chunk_buffers).chain());
+		app.update();
+		let chunks = chunk_meshes(&mut app).len();
+
+		// Without a regeneration queue, new resolutions reload the chunks
+		app.world_mut().resource_mut::<ChunkResolutionConfig<Ground>>().base_res_2 = 3;
+		app.update();
+		let swapping = swapping_meshes(&mut app).len();
+		assert!(swapping > 0);
+		assert_eq!(chunk_meshes(&mut app).len(), chunks);
+
+		app.update();
+		assert!(swapping_meshes(&mut app).is_empty());
+		assert_eq!(chunk_meshes(&mut app).len(), chunks);
+	}
+
+	#[test]
+	fn test_old_meshes_of_chunks_left_behind_despawn_at_once() {
+		let mut app = app();
+		app.add_systems(Update, (manage_chunks::<Ground>, swap_chunk_buffers).chain());
+		app.update();
+
+		// A chunk per frame leaves most reloads waiting on their replacements
+		app.insert_resource(ChunkBudgetConfig::<Ground>::default().with_max_chunks_per_frame(1));
+		app.world_mut().resource_mut::<ChunkResolutionConfig<Ground>>().base_res_2 = 3;
+		app.update();
+		let swapping = swapping_meshes(&mut app);
+		assert!(swapping.len() > 1);
+
+		let camera = camera(&mut app);
+		app.world_mut().entity_mut(camera).insert(Transform::from_xyz(100.0, 0.0, 0.0));
+		app.update();
+		assert!(swapping_meshes(&mut app).is_empty());
+		let meshes = app.world().resource::<Assets<Mesh>>();
+		assert!(swapping.iter().all(|mesh| !meshes.contains(*mesh)));
+	}
+
+	#[test]
+	fn test_async_reloads_keep_their_old_meshes_until_the_new_ones_are_alive() {
+		let mut app = app();
+		app.add_plugins(TaskPoolPlugin::default())
+			.insert_resource(AsyncChunkGeneration::<Ground>::default())
+			.add_systems(
+				Update,
+				(manage_chunks::<Ground>, finish_chunk_mesh_tasks::<Ground>, swap_chunk_buffers)
+					.chain(),
+			);
+		let settle = |app: &mut App| {
+			for _ in 0..1000 {
+				app.update();
+				let generation = app.world().resource::<AsyncChunkGeneration<Ground>>();
+				if generation.in_flight_len() == 0 && swapping_meshes(app).is_empty() {
+					return;
+				}
+				std::thread::sleep(std::time::Duration::from_millis(1));
+			}
+			panic!("Chunks never finished generating");
+		};
+		settle(&mut app);
+		let old = chunk_meshes(&mut app);
+
+		app.world_mut().resource_mut::<ChunkResolutionConfig<Ground>>().base_res_2 = 3;
+		app.update();
+		let swapping = swapping_meshes(&mut app);
+		assert!(!swapping.is_empty());
+		assert!(swapping.is_subset(&old));
+
+		// The old meshes go once the chunks generated on the task pool are alive
+		settle(&mut app);
+		let new = chunk_meshes(&mut app);
+		assert_eq!(new.len(), old.len());
+		assert!(new.is_disjoint(&swapping));
+		let meshes = app.world().resource::<Assets<Mesh>>();
+		assert!(swapping.iter().all(|mesh| !meshes.contains(*mesh)));
+	}
+}
@@ -0,0 +1,324 @@
+use crate::chunk_manager::SdfResource;
+use crate::shaders::far_terrain_material::{FarTerrainMaterial, FarTerrainSettings};
+use crate::terrain_shadow::column_heights;
+use bevy::{
+	asset::RenderAssetUsages,
+	light::{NotShadowCaster, NotShadowReceiver},
+	prelude::*,
+	render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use sdf::Sdf;
+use std::marker::PhantomData;
+
+/// Marks the shell that [FarTerrain] raymarches the distant terrain on.
+#[derive(Component)]
+pub struct FarTerrainShell;
+
+/// Terrain past the chunks raymarched from a heightfield of the SDF, as an alternative to far
+/// grid chunks, so the horizon reaches tens of kilometers without meshing anything.
+///
+/// The heightfield covers a square of the world xz plane centered on the camera, and is baked
+/// again when the camera leaves the middle of it or the SDF changes. It is drawn on an inside-out
+/// sphere of the near radius that follows the camera, whose fragments march their view ray from
+/// the near distance on through the heightfield and are discarded where they miss it. The
+/// chunks within the near distance draw over the shell, so the near distance should be where the
+/// chunks end, and within the far plane of the camera.
+#[derive(Resource)]
+pub struct FarTerrain<S: Sdf + Send + Sync> {
+	/// Texels along each side of the heightfield
+	pub resolution: u32,
+	/// World size of the square the heightfield covers
+	pub extent: f32,
+	/// World heights the ground is searched for between
+	pub height_range: (f32, f32),
+	/// Distance from the camera where the far terrain starts
+	pub near: f32,
+	/// Share of the extent the camera moves before the heightfield is baked again around it
+	pub recenter: f32,
+	/// Most steps a ray takes through the heightfield
+	pub max_steps: u32,
+	/// Ground color, lit by the brightest directional light
+	pub color: Color,
+	/// Color the ground fades into over the haze distance
+	pub haze: Color,
+	/// Distance past the near distance over which the ground fades into the haze
+	pub haze_distance: f32,
+	image: Option<Handle<Image>>,
+	material: Option<Handle<FarTerrainMaterial>>,
+	/// Center of the heightfield when it was last baked
+	baked: Option<Vec2>,
+	/// Marker for the SDF whose terrain is raymarched
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for FarTerrain<S> {
+	fn default() -> Self {
+		Self {
+			resolution: 512,
+			extent: 32768.0,
+			height_range: (-256.0, 256.0),
+			near: 900.0,
+			recenter: 0.125,
+			max_steps: 256,
+			color: Color::srgb(0.35, 0.4, 0.3),
+			haze: Color::srgb(0.7, 0.8, 0.9),
+			haze_distance: 12000.0,
+			image: None,
+			material: None,
+			baked: None,
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> FarTerrain<S> {
+	pub fn with_resolution(mut self, resolution: u32) -> Self {
+		self.resolution = resolution.max(2);
+		self
+	}
+
+	pub fn with_extent(mut self, extent: f32) -> Self {
+		self.extent = extent.max(f32::EPSILON);
+		self
+	}
+
+	pub fn with_height_range(mut self, bottom: f32, top: f32) -> Self {
+		self.height_range = (bottom.min(top), top.max(bottom));
+		self
+	}
+
+	pub fn with_near(mut self, near: f32) -> Self {
+		self.near = near.max(0.0);
+		self
+	}
+
+	pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+		self.max_steps = max_steps.max(1);
+		self
+	}
+
+	pub fn with_colors(mut self, color: Color, haze: Color) -> Self {
+		self.color = color;
+		self.haze = haze;
+		self
+	}
+
+	pub fn with_haze_distance(mut self, haze_distance: f32) -> Self {
+		self.haze_distance = haze_distance.max(f32::EPSILON);
+		self
+	}
+
+	/// The baked heightfield, once it has been baked.
+	pub fn image(&self) -> Option<&Handle<Image>> {
+		self.image.as_ref()
+	}
+
+	/// World size of a texel.
+	pub fn texel_size(&self) -> f32 {
+		self.extent / self.resolution.max(1) as f32
+	}
+
+	/// The center of the heightfield around a position, snapped to the texels so the distant
+	/// terrain holds still as the heightfield follows the camera.
+	pub fn center_for(&self, position: Vec2) -> Vec2 {
+		(position / self.texel_size()).round() * self.texel_size()
+	}
+
+	/// Whether the heightfield needs baking around the center.
+	pub fn needs_bake(&self, center: Vec2) -> bool {
+		self.baked
+			.is_none_or(|baked| baked.distance(center) > self.extent * self.recenter)
+	}
+
+	/// World heights of the ground under the texels of the heightfield around the center, in
+	/// rows along z.
+	pub fn heights(&self, sdf_resource: &SdfResource<S>, center: Vec2) -> Vec<f32> {
+		let min = center - Vec2::splat(self.extent / 2.0);
+		column_heights(sdf_resource, min, self.texel_size(), self.resolution, self.height_range)
+	}
+
+	/// The heightfield of the heights, read by the shader without filtering.
+	pub fn height_image(&self, heights: &[f32]) -> Image {
+		let size = self.resolution.max(1);
+		Image::new(
+			Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+			TextureDimension::D2,
+			heights.iter().flat_map(|height| height.to_le_bytes()).collect(),
+			TextureFormat::R32Float,
+			RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+		)
+	}
+
+	/// The uniforms of the far terrain for the heightfield baked around the center.
+	pub fn settings(&self, center: Vec2, toward_sun: Vec3) -> FarTerrainSettings {
+		FarTerrainSettings {
+			color: self.color.to_linear().to_vec4(),
+			haze: self.haze.to_linear().to_vec4(),
+			toward_sun,
+			resolution: self.resolution.max(1),
+			min: center - Vec2::splat(self.extent / 2.0),
+			size: self.extent,
+			top: self.height_range.1,
+			near: self.near,
+			far: self.extent / 2.0,
+			haze_distance: self.haze_distance,
+			max_steps: self.max_steps,
+		}
+	}
+}
+
+/// Bakes the heightfield of the [FarTerrain] around the camera when it needs baking, and keeps
+/// its shell around the camera and lit by the brightest directional light.
+///
+/// Register the MaterialPlugin of [FarTerrainMaterial] to draw it.
+pub fn update_far_terrain<S: Sdf + Send + Sync + 'static>(
+	mut commands: Commands,
+	camera_query: Query<&GlobalTransform, With<Camera3d>>,
+	light_query: Query<(&DirectionalLight, &GlobalTransform)>,
+	mut shell_query: Query<&mut Transform, With<FarTerrainShell>>,
+	mut far_terrain: ResMut<FarTerrain<S>>,
+	sdf_resource: Res<SdfResource<S>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<FarTerrainMaterial>>,
+	mut images: ResMut<Assets<Image>>,
+) {
+	let Ok(camera_transform) = camera_query.single() else {
+		return;
+	};
+	let camera_pos = camera_transform.translation();
+	let toward_sun = light_query
+		.iter()
+		.max_by(|(a, _), (b, _)| a.illuminance.total_cmp(&b.illuminance))
+		.map(|(_, transform)| -*transform.forward())
+		.unwrap_or(Vec3::Y);
+
+	let mut center = far_terrain.baked.unwrap_or_default();
+	let recentered = far_terrain.center_for(camera_pos.xz());
+	if far_terrain.needs_bake(recentered) || sdf_resource.is_changed() {
+		center = recentered;
+		let heights = far_terrain.heights(&sdf_resource, center);
+		let image = far_terrain.height_image(&heights);
+		let handle = match far_terrain.image.clone() {
+			Some(handle) if images.get(&handle).is_some() => {
+				if let Some(existing) = images.get_mut(&handle) {
+					*existing = image;
+				}
+				handle
+			}
+			_ => images.add(image),
+		};
+		far_terrain.image = Some(handle);
+		far_terrain.baked = Some(center);
+		log::debug!("Baked the far terrain heightfield around {center}");
+	}
+
+	let settings = far_terrain.settings(center, toward_sun);
+	let heights = far_terrain.image.clone();
+	let material = far_terrain.material.clone().filter(|handle| materials.get(handle).is_some());
+	let material = match material {
+		Some(handle) => {
+			let stale = materials.get(&handle).is_some_and(|material| {
+				material.settings != settings || material.heights != heights
+			});
+			if let Some(material) = stale.then(|| materials.get_mut(&handle)).flatten() {
+				material.settings = settings;
+				material.heights = heights;
+			}
+			None
+		}
+		None => Some(materials.add(FarTerrainMaterial { settings, heights })),
+	};
+
+	if let Some(material) = material {
+		let shell = Sphere::new(far_terrain.near).mesh().uv(48, 24);
+		let Ok(shell) = shell.with_inverted_winding() else {
+			log::warn!("Failed to turn the far terrain shell inside out");
+			return;
+		};
+		far_terrain.material = Some(material.clone());
+		commands.spawn((
+			FarTerrainShell,
+			Mesh3d(meshes.add(shell)),
+			MeshMaterial3d(material),
+			Transform::from_translation(camera_pos),
+			NotShadowCaster,
+			NotShadowReceiver,
+		));
+	}
+	for mut transform in shell_query.iter_mut() {
+		transform.translation = camera_pos;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Flat ground with a mountain at x 1000
+	struct Mountain;
+
+	impl Sdf for Mountain {
+		fn distance(&self, p: Vec3) -> f32 {
+			let peak = 100.0 - Vec2::new(p.x - 1000.0, p.z).length() * 0.5;
+			p.y - peak.max(0.0)
+		}
+	}
+
+	#[test]
+	fn test_far_terrain_bakes_the_heightfield_around_the_camera() {
+		let mut app = App::new();
+		app.add_plugins(AssetPlugin::default())
+			.init_asset::<Mesh>()
+			.init_asset::<Image>()
+			.init_asset::<FarTerrainMaterial>()
+			.insert_resource(SdfResource::new(Mountain))
+			.insert_resource(
+				FarTerrain::<Mountain>::default()
+					.with_resolution(64)
+					.with_extent(4096.0)
+					.with_height_range(-50.0, 200.0)
+					.with_near(500.0),
+			)
+			.add_systems(Update, update_far_terrain::<Mountain>);
+		app.world_mut().spawn((Camera3d::default(), GlobalTransform::default()));
+		app.update();
+
+		// The heightfield finds the ground and the peak of the mountain
+		let far_terrain = app.world().resource::<FarTerrain<Mountain>>();
+		let heights =
+			far_terrain.heights(app.world().resource::<SdfResource<Mountain>>(), Vec2::ZERO);
+		let texel = |x: f32, z: f32| {
+			let texel = ((Vec2::new(x, z) + 2048.0) / far_terrain.texel_size()).floor().as_uvec2();
+			heights[(texel.y * 64 + texel.x) as usize]
+		};
+		assert!(texel(-1500.0, 0.0).abs() < 0.5, "{}", texel(-1500.0, 0.0));
+		assert!(texel(1000.0, 0.0) > 80.0, "{}", texel(1000.0, 0.0));
+		let image = far_terrain.image().cloned();
+		assert!(image.is_some());
+
+		// The shell follows the camera, drawn with the baked heightfield
+		let mut shells = app
+			.world_mut()
+			.query_filtered::<(&Transform, &MeshMaterial3d<FarTerrainMaterial>), With<FarTerrainShell>>(
+			);
+		assert_eq!(shells.iter(app.world()).count(), 1);
+		app.world_mut()
+			.query_filtered::<&mut GlobalTransform, With<Camera3d>>()
+			.iter_mut(app.world_mut())
+			.for_each(|mut transform| *transform = GlobalTransform::from_xyz(50.0, 10.0, 0.0));
+		app.update();
+		let materials = app.world().resource::<Assets<FarTerrainMaterial>>();
+		for (transform, material) in shells.iter(app.world()) {
+			assert_eq!(transform.translation, Vec3::new(50.0, 10.0, 0.0));
+			let material = materials.get(&material.0);
+			assert!(material.is_some_and(|material| material.heights == image
+				&& material.settings.near == 500.0
+				&& material.settings.min == Vec2::splat(-2048.0)));
+		}
+
+		// Moving far enough bakes it again around the camera
+		let far_terrain = app.world().resource::<FarTerrain<Mountain>>();
+		assert!(!far_terrain.needs_bake(far_terrain.center_for(Vec2::new(50.0, 0.0))));
+		assert!(far_terrain.needs_bake(far_terrain.center_for(Vec2::new(1000.0, 0.0))));
+	}
+}
@@ -0,0 +1,84 @@
+use crate::cascade::CascadeChunk;
+use bevy::prelude::*;
+
+/// Biases the cascade resolution toward where the camera looks, such as ground detail in front
+/// of a character.
+///
+/// While on the camera, the nearest cascade chunks in the view direction get one more level of
+/// resolution and the chunks behind the camera one less. Chunks that are already loaded change
+/// resolution through the [ChunkRegenerationQueue](crate::ChunkRegenerationQueue) when there is
+/// one, keeping their old mesh until the new one is ready.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct ResolutionFocus {
+	/// Number of chunks in front of the camera that are boosted
+	pub chunks: usize,
+	/// Cosine of the angle off the view direction within which chunks are in front, and past
+	/// which, behind the camera, they are lowered
+	pub min_alignment: f32,
+}
+
+impl Default for ResolutionFocus {
+	fn default() -> Self {
+		Self { chunks: 4, min_alignment: 0.5 }
+	}
+}
+
+impl ResolutionFocus {
+	pub fn with_chunks(mut self, chunks: usize) -> Self {
+		self.chunks = chunks;
+		self
+	}
+
+	pub fn with_min_alignment(mut self, min_alignment: f32) -> Self {
+		self.min_alignment = min_alignment;
+		self
+	}
+
+	/// How well the chunk lines up with the view, 1 when the chunk holds the position
+	fn alignment(chunk: &CascadeChunk, position: Vec3, forward: Vec3) -> f32 {
+		if position.cmpge(chunk.origin).all() && position.cmplt(chunk.origin + chunk.size).all() {
+			return 1.0;
+		}
+		let to_chunk = chunk.origin + chunk.size / 2.0 - position;
+		to_chunk.normalize_or_zero().dot(forward)
+	}
+
+	/// Raises the resolution of the chunks in front of the position and lowers the ones behind.
+	pub fn apply(&self, chunks: &mut [CascadeChunk], position: Vec3, forward: Vec3) {
+		let forward = forward.normalize_or_zero();
+		let distance = |chunk: &CascadeChunk| (chunk.origin + chunk.size / 2.0).distance(position);
+
+		let mut in_front: Vec<usize> = (0..chunks.len())
+			.filter(|&i| Self::alignment(&chunks[i], position, forward) >= self.min_alignment)
+			.collect();
+		in_front.sort_by(|&a, &b| distance(&chunks[a]).total_cmp(&distance(&chunks[b])));
+		for &i in in_front.iter().take(self.chunks) {
+			chunks[i].res_2 += UVec3::ONE;
+		}
+
+		for chunk in chunks.iter_mut() {
+			if Self::alignment(chunk, position, forward) <= -self.min_alignment {
+				chunk.res_2 = chunk.res_2.saturating_sub(UVec3::ONE).max(UVec3::ONE);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_focus_boosts_ahead_and_lowers_behind() {
+		let mut chunks: Vec<CascadeChunk> = (-3..3)
+			.map(|x| CascadeChunk::cube(Vec3::new(x as f32, 0.0, 0.0), 1.0, 3))
+			.collect();
+		let focus = ResolutionFocus::default().with_chunks(2);
+		focus.apply(&mut chunks, Vec3::new(0.5, 0.5, 0.5), Vec3::X);
+
+		let res: Vec<u32> = chunks.iter().map(|chunk| chunk.res_2.x).collect();
+		// The chunk holding the camera and the next one ahead are boosted, the rest ahead
+		// are left alone
+		assert_eq!(res, vec![2, 2, 2, 4, 4, 3]);
+	}
+}
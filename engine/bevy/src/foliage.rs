@@ -0,0 +1,274 @@
+use crate::shaders::leaf_material::{
+	FoliageBender, FoliageBending, FoliageFade, LeafMaterial, MAX_FOLIAGE_BENDERS,
+};
+use bevy::prelude::*;
+
+/// An entity that bends foliage within its radius as it walks through.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct FoliageActor {
+	pub radius: f32,
+}
+
+impl FoliageActor {
+	pub fn new(radius: f32) -> Self {
+		Self { radius }
+	}
+}
+
+/// Where foliage is bent, by actors and the imprints they leave behind.
+///
+/// An actor bends the foliage around it fully. Once it has moved on by the spacing, the
+/// foliage it left springs back over the spring back time, so actors leave a fading trail.
+/// Uploaded to every [LeafMaterial] by [bend_foliage].
+#[derive(Resource, Debug, Clone)]
+pub struct FoliageInteraction {
+	/// Seconds foliage takes to spring back once an actor has moved on
+	pub spring_back: f32,
+	/// How far foliage at the center of an actor is pushed, as a share of its radius
+	pub bend: f32,
+	/// Distance an actor moves, as a share of its radius, before leaving a new imprint
+	pub spacing: f32,
+	imprints: Vec<FoliageBender>,
+}
+
+impl Default for FoliageInteraction {
+	fn default() -> Self {
+		Self { spring_back: 1.5, bend: 0.6, spacing: 0.5, imprints: Vec::new() }
+	}
+}
+
+impl FoliageInteraction {
+	pub fn with_spring_back(mut self, spring_back: f32) -> Self {
+		self.spring_back = spring_back;
+		self
+	}
+
+	pub fn with_bend(mut self, bend: f32) -> Self {
+		self.bend = bend;
+		self
+	}
+
+	pub fn with_spacing(mut self, spacing: f32) -> Self {
+		self.spacing = spacing;
+		self
+	}
+
+	pub fn imprints(&self) -> &[FoliageBender] {
+		&self.imprints
+	}
+
+	/// Whether no foliage is bent.
+	pub fn is_idle(&self) -> bool {
+		self.imprints.is_empty()
+	}
+
+	/// Springs the imprints back by the elapsed seconds, then presses imprints at the actors,
+	/// given as positions and radii.
+	///
+	/// When there are more imprints than the shader holds, the faintest are dropped.
+	pub fn update(&mut self, actors: &[(Vec3, f32)], delta: f32) {
+		let decay = if self.spring_back > 0.0 { delta / self.spring_back } else { 1.0 };
+		for imprint in &mut self.imprints {
+			imprint.strength -= decay;
+		}
+		self.imprints.retain(|imprint| imprint.strength > 0.0);
+
+		for &(position, radius) in actors {
+			let spacing = radius * self.spacing;
+			match self.imprints.iter_mut().find(|imprint| {
+				imprint.radius == radius && imprint.position.distance(position) < spacing
+			}) {
+				Some(imprint) => imprint.strength = 1.0,
+				None => self.imprints.push(FoliageBender { position, radius, strength: 1.0 }),
+			}
+		}
+
+		if self.imprints.len() > MAX_FOLIAGE_BENDERS {
+			self.imprints.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+			self.imprints.truncate(MAX_FOLIAGE_BENDERS);
+		}
+	}
+
+	/// The imprints as a material uniform.
+	pub fn bending(&self) -> FoliageBending {
+		let mut bending = FoliageBending { bend: self.bend, ..default() };
+		for (bender, imprint) in bending.benders.iter_mut().zip(&self.imprints) {
+			*bender = *imprint;
+			bending.count += 1;
+		}
+		bending
+	}
+}
+
+/// Presses the [FoliageActor]s into the [FoliageInteraction] and springs back what they left.
+pub fn track_foliage_actors(
+	time: Res<Time>,
+	mut interaction: ResMut<FoliageInteraction>,
+	actors: Query<(&GlobalTransform, &FoliageActor)>,
+) {
+	if actors.is_empty() && interaction.is_idle() {
+		return;
+	}
+	let actors: Vec<_> = actors
+		.iter()
+		.map(|(transform, actor)| (transform.translation(), actor.radius))
+		.collect();
+	interaction.update(&actors, time.delta_secs());
+}
+
+/// Uploads the [FoliageInteraction] to every [LeafMaterial] when it changes.
+pub fn bend_foliage(
+	interaction: Res<FoliageInteraction>,
+	mut materials: ResMut<Assets<LeafMaterial>>,
+) {
+	if !interaction.is_changed() {
+		return;
+	}
+	let bending = interaction.bending();
+	for (_, material) in materials.iter_mut() {
+		material.bending = bending;
+	}
+}
+
+/// How far from the camera foliage reaches, and the span over which it thins out and fades
+/// before it ends.
+///
+/// Scatterers thin foliage out by [density](FoliageFalloff::density), from whole at the start to
+/// none at the end, and [LeafMaterial]s dither what is left away over the same span, so no edge
+/// is seen where foliage pops in. Uploaded to every LeafMaterial by [fade_foliage].
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Resource, Default, Debug))]
+pub struct FoliageFalloff {
+	/// Distance from the camera where foliage starts thinning out
+	pub start: f32,
+	/// Distance from the camera past which there is no foliage
+	pub end: f32,
+}
+
+impl Default for FoliageFalloff {
+	fn default() -> Self {
+		Self { start: 48.0, end: 64.0 }
+	}
+}
+
+impl FoliageFalloff {
+	pub fn new(start: f32, end: f32) -> Self {
+		Self { start, end: end.max(start) }
+	}
+
+	/// Share of the foliage scattered at the distance from the camera.
+	pub fn density(&self, distance: f32) -> f32 {
+		if distance <= self.start {
+			return 1.0;
+		}
+		if distance >= self.end {
+			return 0.0;
+		}
+		let t = (distance - self.start) / (self.end - self.start);
+		1.0 - t * t * (3.0 - 2.0 * t)
+	}
+
+	/// The falloff as a material uniform.
+	pub fn fade(&self) -> FoliageFade {
+		FoliageFade { start: self.start, end: self.end }
+	}
+}
+
+/// Uploads the [FoliageFalloff] to every [LeafMaterial] when it changes, and to those added
+/// since.
+pub fn fade_foliage(
+	falloff: Res<FoliageFalloff>,
+	mut events: MessageReader<AssetEvent<LeafMaterial>>,
+	mut materials: ResMut<Assets<LeafMaterial>>,
+) {
+	let added: Vec<_> = events
+		.read()
+		.filter_map(|event| match event {
+			AssetEvent::Added { id } => Some(*id),
+			_ => None,
+		})
+		.collect();
+	let fade = falloff.fade();
+	if falloff.is_changed() {
+		for (_, material) in materials.iter_mut() {
+			material.fade = fade;
+		}
+		return;
+	}
+	for id in added {
+		if let Some(material) = materials.get_mut(id) {
+			material.fade = fade;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_foliage_springs_back_behind_actors() {
+		let mut interaction = FoliageInteraction::default().with_spring_back(1.0);
+		interaction.update(&[(Vec3::ZERO, 1.0)], 0.1);
+		// Standing still keeps pressing the same imprint
+		interaction.update(&[(Vec3::new(0.2, 0.0, 0.0), 1.0)], 0.1);
+		assert_eq!(interaction.imprints().len(), 1);
+
+		// Walking on leaves the old imprint to spring back
+		interaction.update(&[(Vec3::new(2.0, 0.0, 0.0), 1.0)], 0.25);
+		let strengths: Vec<_> = interaction.imprints().iter().map(|i| i.strength).collect();
+		assert_eq!(strengths, vec![0.75, 1.0]);
+		let bending = interaction.bending();
+		assert_eq!(bending.count, 2);
+		assert_eq!(bending.benders[1].position, Vec3::new(2.0, 0.0, 0.0));
+
+		// Once the actor is gone, everything springs back
+		interaction.update(&[], 1.0);
+		assert!(interaction.is_idle());
+
+		// Crowds keep only what the shader holds
+		let crowd: Vec<_> = (0..12).map(|i| (Vec3::X * 10.0 * i as f32, 1.0)).collect();
+		interaction.update(&crowd, 0.1);
+		assert_eq!(interaction.imprints().len(), MAX_FOLIAGE_BENDERS);
+	}
+
+	#[test]
+	fn test_foliage_fades_over_the_falloff() {
+		let falloff = FoliageFalloff::new(10.0, 20.0);
+		assert_eq!(falloff.density(5.0), 1.0);
+		assert_eq!(falloff.density(15.0), 0.5);
+		assert_eq!(falloff.density(25.0), 0.0);
+		assert!(falloff.density(12.0) > falloff.density(18.0));
+
+		let mut app = App::new();
+		app.add_plugins(AssetPlugin::default())
+			.init_asset::<LeafMaterial>()
+			.insert_resource(falloff)
+			.add_systems(Update, fade_foliage);
+		let early = app
+			.world_mut()
+			.resource_mut::<Assets<LeafMaterial>>()
+			.add(LeafMaterial::new(Vec4::ONE));
+		app.update();
+		let fade = |app: &App, handle: &Handle<LeafMaterial>| {
+			app.world()
+				.resource::<Assets<LeafMaterial>>()
+				.get(handle)
+				.map(|material| material.fade)
+		};
+		assert_eq!(fade(&app, &early), Some(falloff.fade()));
+
+		// Materials added later fade too, and all follow the falloff when it changes
+		let late = app
+			.world_mut()
+			.resource_mut::<Assets<LeafMaterial>>()
+			.add(LeafMaterial::new(Vec4::ONE));
+		app.update();
+		app.update();
+		assert_eq!(fade(&app, &late), Some(falloff.fade()));
+		app.insert_resource(FoliageFalloff::new(30.0, 40.0));
+		app.update();
+		assert_eq!(fade(&app, &early), Some(FoliageFade { start: 30.0, end: 40.0 }));
+		assert_eq!(fade(&app, &late), Some(FoliageFade { start: 30.0, end: 40.0 }));
+	}
+}
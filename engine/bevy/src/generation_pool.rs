@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Thread counts for the world generation lanes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerationPoolConfig {
+	/// Threads for the near cascade chunks
+	pub near_threads: usize,
+	/// Threads for the far grid chunks
+	pub far_threads: usize,
+}
+
+impl Default for GenerationPoolConfig {
+	/// Half of the available threads for the near lane and a quarter for the far lane,
+	/// leaving the rest for Bevy.
+	fn default() -> Self {
+		let available = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+		Self { near_threads: (available / 2).max(1), far_threads: (available / 4).max(1) }
+	}
+}
+
+impl GenerationPoolConfig {
+	pub fn with_near_threads(mut self, threads: usize) -> Self {
+		self.near_threads = threads;
+		self
+	}
+
+	pub fn with_far_threads(mut self, threads: usize) -> Self {
+		self.far_threads = threads;
+		self
+	}
+}
+
+/// Utilization of a generation lane since it was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LaneMetrics {
+	pub threads: usize,
+	/// Number of chunks generated
+	pub jobs: u64,
+	/// Time spent generating, summed over all threads
+	pub busy: Duration,
+	/// Wall time spent in batches
+	pub wall: Duration,
+}
+
+impl LaneMetrics {
+	/// Fraction of the lane's thread time spent generating while batches ran.
+	pub fn utilization(&self) -> f32 {
+		let capacity = self.wall.as_secs_f32() * self.threads as f32;
+		if capacity <= 0.0 {
+			return 0.0;
+		}
+		(self.busy.as_secs_f32() / capacity).min(1.0)
+	}
+}
+
+/// A dedicated thread pool that generates one batch of chunks at a time.
+pub struct GenerationLane {
+	pool: ThreadPool,
+	jobs: AtomicU64,
+	busy_nanos: AtomicU64,
+	wall_nanos: AtomicU64,
+}
+
+impl GenerationLane {
+	pub fn new(name: &'static str, threads: usize) -> Result<Self, String> {
+		let pool = ThreadPoolBuilder::new()
+			.num_threads(threads.max(1))
+			.thread_name(move |index| format!("{name}-generation-{index}"))
+			.build()
+			.map_err(|e| format!("Failed to build {name} generation pool: {e}"))?;
+		Ok(Self {
+			pool,
+			jobs: AtomicU64::new(0),
+			busy_nanos: AtomicU64::new(0),
+			wall_nanos: AtomicU64::new(0),
+		})
+	}
+
+	/// Maps the items in parallel on this lane, keeping their order.
+	pub fn run<T, R, F>(&self, items: &[T], f: F) -> Vec<R>
+	where
+		T: Sync,
+		R: Send,
+		F: Fn(&T) -> R + Send + Sync,
+	{
+		if items.is_empty() {
+			return Vec::new();
+		}
+
+		let start = Instant::now();
+		let results = self.pool.install(|| {
+			items
+				.par_iter()
+				.map(|item| {
+					let job_start = Instant::now();
+					let result = f(item);
+					self.busy_nanos.fetch_add(nanos(job_start.elapsed()), Ordering::Relaxed);
+					result
+				})
+				.collect()
+		});
+		self.wall_nanos.fetch_add(nanos(start.elapsed()), Ordering::Relaxed);
+		self.jobs.fetch_add(items.len() as u64, Ordering::Relaxed);
+		results
+	}
+
+	pub fn threads(&self) -> usize {
+		self.pool.current_num_threads()
+	}
+
+	pub fn metrics(&self) -> LaneMetrics {
+		LaneMetrics {
+			threads: self.threads(),
+			jobs: self.jobs.load(Ordering::Relaxed),
+			busy: Duration::from_nanos(self.busy_nanos.load(Ordering::Relaxed)),
+			wall: Duration::from_nanos(self.wall_nanos.load(Ordering::Relaxed)),
+		}
+	}
+}
+
+fn nanos(duration: Duration) -> u64 {
+	u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// Dedicated thread pools for world generation, kept apart from Bevy's and rayon's global pools.
+///
+/// Near cascade chunks and far grid chunks are generated at the same time, each on the threads
+/// of its own lane, and the frame waits for both.
+/// Without this resource, chunks are generated on rayon's global pool.
+#[derive(Resource)]
+pub struct GenerationPool {
+	pub near: GenerationLane,
+	pub far: GenerationLane,
+}
+
+impl GenerationPool {
+	pub fn new(config: GenerationPoolConfig) -> Result<Self, String> {
+		Ok(Self {
+			near: GenerationLane::new("near", config.near_threads)?,
+			far: GenerationLane::new("far", config.far_threads)?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_lanes_keep_order_and_count_jobs() -> Result<(), String> {
+		let pool = GenerationPool::new(
+			GenerationPoolConfig::default().with_near_threads(2).with_far_threads(1),
+		)?;
+		let items: Vec<u32> = (0..100).collect();
+
+		let squares = pool.near.run(&items, |i| i * i);
+		assert_eq!(squares, items.iter().map(|i| i * i).collect::<Vec<_>>());
+		assert!(pool.far.run(&[] as &[u32], |i| *i).is_empty());
+
+		let near = pool.near.metrics();
+		assert_eq!((near.threads, near.jobs), (2, 100));
+		assert!((0.0..=1.0).contains(&near.utilization()));
+		assert_eq!(pool.far.metrics().jobs, 0);
+		assert_eq!(pool.far.metrics().utilization(), 0.0);
+		Ok(())
+	}
+}
@@ -0,0 +1,168 @@
+use crate::chunk::TerrainChunk;
+use crate::chunk_manager::SdfResource;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::marker::PhantomData;
+use terrain_sdf::region::Region2D;
+
+/// Iterations of the bisection that projects region boundaries onto the surface
+const SURFACE_ITERATIONS: usize = 16;
+
+/// Draws the bounds of every loaded cascade chunk, colored by its resolution.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct CascadeGizmos {
+	pub enabled: bool,
+}
+
+/// Draws the boundaries of 2D regions, such as valleys and roads, on the terrain surface.
+///
+/// Regions are in the local xz plane of the SDF. Only the part of each boundary within the
+/// radius of the camera is traced, with the given step, and columns of the SDF with no surface
+/// between the bottom and top heights are skipped.
+#[derive(Resource, Debug, Clone)]
+pub struct RegionGizmos<S: Sdf + Send + Sync> {
+	pub enabled: bool,
+	pub regions: Vec<(Region2D, Color)>,
+	pub radius: f32,
+	pub step: f32,
+	pub bottom: f32,
+	pub top: f32,
+	/// Marker for the SDF the regions are projected onto
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for RegionGizmos<S> {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			regions: Vec::new(),
+			radius: 128.0,
+			step: 1.0,
+			bottom: -256.0,
+			top: 256.0,
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> RegionGizmos<S> {
+	pub fn with_region(mut self, region: Region2D, color: Color) -> Self {
+		self.regions.push((region, color));
+		self
+	}
+
+	pub fn with_radius(mut self, radius: f32) -> Self {
+		self.radius = radius;
+		self
+	}
+
+	pub fn with_step(mut self, step: f32) -> Self {
+		self.step = step;
+		self
+	}
+
+	pub fn with_height_range(mut self, bottom: f32, top: f32) -> Self {
+		self.bottom = bottom;
+		self.top = top;
+		self
+	}
+}
+
+/// The height of the topmost surface of the SDF between bottom and top in the column at xz.
+pub fn surface_height<S: Sdf>(sdf: &S, xz: Vec2, bottom: f32, top: f32) -> Option<f32> {
+	column_surface(|y| sdf.distance(Vec3::new(xz.x, y, xz.y)), bottom, top)
+}
+
+/// The height between bottom and top where a column's distance goes from ground to air.
+pub(crate) fn column_surface(distance: impl Fn(f32) -> f32, bottom: f32, top: f32) -> Option<f32> {
+	if distance(top) <= 0.0 || distance(bottom) > 0.0 {
+		return None;
+	}
+
+	let (mut low, mut high) = (bottom, top);
+	for _ in 0..SURFACE_ITERATIONS {
+		let mid = (low + high) / 2.0;
+		if distance(mid) > 0.0 {
+			high = mid;
+		} else {
+			low = mid;
+		}
+	}
+	Some((low + high) / 2.0)
+}
+
+/// Draws the bounds of the loaded chunks while [CascadeGizmos] is enabled.
+pub fn draw_cascade_bounds<S: Sdf + Send + Sync + 'static>(
+	mut gizmos: Gizmos,
+	cascade_gizmos: Res<CascadeGizmos>,
+	sdf_resource: Res<SdfResource<S>>,
+	chunks: Query<&TerrainChunk>,
+) {
+	if !cascade_gizmos.enabled {
+		return;
+	}
+
+	let world = sdf_resource.transform.to_transform();
+	for chunk in &chunks {
+		let chunk = &chunk.chunk;
+		let center = chunk.origin + chunk.size / 2.0;
+		// Finer chunks are drawn warmer
+		let hue = 240.0 - 30.0 * chunk.res_2.max_element() as f32;
+		gizmos.cuboid(
+			world * Transform::from_translation(center).with_scale(chunk.size),
+			Color::hsl(hue.rem_euclid(360.0), 0.9, 0.6),
+		);
+	}
+}
+
+/// Draws the region boundaries around the camera while [RegionGizmos] is enabled.
+pub fn draw_region_boundaries<S: Sdf + Send + Sync + 'static>(
+	mut gizmos: Gizmos,
+	region_gizmos: Res<RegionGizmos<S>>,
+	sdf_resource: Res<SdfResource<S>>,
+	camera_query: Query<&Transform, With<Camera3d>>,
+) {
+	if !region_gizmos.enabled {
+		return;
+	}
+	let Ok(camera_transform) = camera_query.single() else {
+		return;
+	};
+
+	let transform = &sdf_resource.transform;
+	let camera = transform.to_local(camera_transform.translation).xz();
+	let (min, max) = (camera - region_gizmos.radius, camera + region_gizmos.radius);
+	let project = |xz: Vec2| {
+		surface_height(&*sdf_resource.sdf, xz, region_gizmos.bottom, region_gizmos.top)
+			.map(|y| transform.to_world(Vec3::new(xz.x, y, xz.y)))
+	};
+	for (region, color) in &region_gizmos.regions {
+		for (a, b) in region.contour(min, max, region_gizmos.step) {
+			if let (Some(a), Some(b)) = (project(a), project(b)) {
+				gizmos.line(a, b, *color);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Slope;
+
+	impl Sdf for Slope {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.y - 0.5 * p.x
+		}
+	}
+
+	#[test]
+	fn test_surface_height_finds_slope() {
+		let Some(height) = surface_height(&Slope, Vec2::new(8.0, 3.0), -64.0, 64.0) else {
+			panic!("expected a surface");
+		};
+		assert!((height - 4.0).abs() < 0.01, "The surface is at {height}");
+		assert_eq!(surface_height(&Slope, Vec2::new(200.0, 0.0), -64.0, 64.0), None);
+	}
+}
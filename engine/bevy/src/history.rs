@@ -0,0 +1,211 @@
+use crate::regeneration::TerrainRegionDirty;
+use bevy::math::bounding::Aabb3d;
+use bevy::prelude::*;
+
+/// A reversible change to the world, such as placing an object or cutting into the terrain.
+pub trait WorldEdit: Send + Sync + 'static {
+	fn apply(&mut self, world: &mut World);
+
+	/// Undoes [WorldEdit::apply], leaving the world as it was before.
+	fn revert(&mut self, world: &mut World);
+
+	/// The region of terrain, in the local space of its SDF, whose chunks change with the edit
+	/// in either direction.
+	fn dirty_region(&self) -> Option<Aabb3d> {
+		None
+	}
+
+	/// A short description for logs.
+	fn describe(&self) -> String {
+		std::any::type_name::<Self>()
+			.rsplit("::")
+			.next()
+			.unwrap_or_default()
+			.to_string()
+	}
+}
+
+enum PendingEdit {
+	Apply(Box<dyn WorldEdit>),
+	Undo,
+	Redo,
+}
+
+/// Undo and redo stacks of [WorldEdit]s.
+///
+/// Edits, undos and redos are queued and carried out in order by [apply_world_edits].
+/// Edits that change the terrain write a [TerrainRegionDirty] message each way,
+/// so its chunks are regenerated on undo as well.
+#[derive(Resource)]
+pub struct WorldEditHistory {
+	pending: Vec<PendingEdit>,
+	done: Vec<Box<dyn WorldEdit>>,
+	undone: Vec<Box<dyn WorldEdit>>,
+	/// Oldest edits are forgotten beyond this many
+	pub max_edits: usize,
+}
+
+impl Default for WorldEditHistory {
+	fn default() -> Self {
+		Self { pending: Vec::new(), done: Vec::new(), undone: Vec::new(), max_edits: 256 }
+	}
+}
+
+impl WorldEditHistory {
+	pub fn with_max_edits(mut self, max_edits: usize) -> Self {
+		self.max_edits = max_edits;
+		self
+	}
+
+	/// Queues an edit to apply. Applying it clears the redo stack.
+	pub fn push(&mut self, edit: impl WorldEdit) {
+		self.pending.push(PendingEdit::Apply(Box::new(edit)));
+	}
+
+	pub fn undo(&mut self) {
+		self.pending.push(PendingEdit::Undo);
+	}
+
+	pub fn redo(&mut self) {
+		self.pending.push(PendingEdit::Redo);
+	}
+
+	/// Number of applied edits that can be undone.
+	pub fn undo_len(&self) -> usize {
+		self.done.len()
+	}
+
+	/// Number of undone edits that can be redone.
+	pub fn redo_len(&self) -> usize {
+		self.undone.len()
+	}
+
+	/// Forgets every edit, leaving the world as it is.
+	pub fn clear(&mut self) {
+		self.pending.clear();
+		self.done.clear();
+		self.undone.clear();
+	}
+}
+
+fn mark_dirty(world: &mut World, edit: &dyn WorldEdit) {
+	let Some(region) = edit.dirty_region() else {
+		return;
+	};
+	match world.get_resource_mut::<Messages<TerrainRegionDirty>>() {
+		Some(mut messages) => {
+			messages.write(TerrainRegionDirty(region));
+		}
+		None => log::warn!("TerrainRegionDirty is not registered, chunks will not be regenerated"),
+	}
+}
+
+/// Applies, undoes and redoes the queued edits in order.
+pub fn apply_world_edits(world: &mut World) {
+	let pending = match world.get_resource_mut::<WorldEditHistory>() {
+		Some(mut history) if !history.pending.is_empty() => std::mem::take(&mut history.pending),
+		_ => return,
+	};
+
+	for pending_edit in pending {
+		match pending_edit {
+			PendingEdit::Apply(mut edit) => {
+				log::debug!("Applying {}", edit.describe());
+				edit.apply(world);
+				mark_dirty(world, edit.as_ref());
+				let mut history = world.resource_mut::<WorldEditHistory>();
+				history.done.push(edit);
+				history.undone.clear();
+				let excess = history.done.len().saturating_sub(history.max_edits);
+				history.done.drain(..excess);
+			}
+			PendingEdit::Undo => {
+				let Some(mut edit) = world.resource_mut::<WorldEditHistory>().done.pop() else {
+					continue;
+				};
+				log::debug!("Undoing {}", edit.describe());
+				edit.revert(world);
+				mark_dirty(world, edit.as_ref());
+				world.resource_mut::<WorldEditHistory>().undone.push(edit);
+			}
+			PendingEdit::Redo => {
+				let Some(mut edit) = world.resource_mut::<WorldEditHistory>().undone.pop() else {
+					continue;
+				};
+				log::debug!("Redoing {}", edit.describe());
+				edit.apply(world);
+				mark_dirty(world, edit.as_ref());
+				world.resource_mut::<WorldEditHistory>().done.push(edit);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Resource, Default)]
+	struct Counter(i32);
+
+	struct Add(i32);
+
+	impl WorldEdit for Add {
+		fn apply(&mut self, world: &mut World) {
+			world.resource_mut::<Counter>().0 += self.0;
+		}
+
+		fn revert(&mut self, world: &mut World) {
+			world.resource_mut::<Counter>().0 -= self.0;
+		}
+
+		fn dirty_region(&self) -> Option<Aabb3d> {
+			Some(Aabb3d::new(Vec3::ZERO, Vec3::splat(self.0 as f32)))
+		}
+	}
+
+	#[test]
+	fn test_undo_and_redo_edits() {
+		let mut app = App::new();
+		app.init_resource::<Counter>()
+			.insert_resource(WorldEditHistory::default().with_max_edits(2))
+			.add_message::<TerrainRegionDirty>()
+			.add_systems(Update, apply_world_edits);
+		let counter = |app: &App| app.world().resource::<Counter>().0;
+		let dirty = |app: &mut App| {
+			let mut messages = app.world_mut().resource_mut::<Messages<TerrainRegionDirty>>();
+			messages.drain().count()
+		};
+
+		let mut history = app.world_mut().resource_mut::<WorldEditHistory>();
+		history.push(Add(1));
+		history.push(Add(2));
+		history.push(Add(4));
+		app.update();
+		assert_eq!(counter(&app), 7);
+		assert_eq!(dirty(&mut app), 3);
+
+		// The first edit was forgotten
+		let mut history = app.world_mut().resource_mut::<WorldEditHistory>();
+		history.undo();
+		history.undo();
+		history.undo();
+		app.update();
+		assert_eq!(counter(&app), 1);
+		assert_eq!(dirty(&mut app), 2);
+
+		app.world_mut().resource_mut::<WorldEditHistory>().redo();
+		app.update();
+		assert_eq!(counter(&app), 3);
+		assert_eq!(app.world().resource::<WorldEditHistory>().redo_len(), 1);
+
+		// A new edit clears the redo stack
+		let mut history = app.world_mut().resource_mut::<WorldEditHistory>();
+		history.push(Add(8));
+		history.redo();
+		app.update();
+		assert_eq!(counter(&app), 11);
+		assert_eq!(app.world().resource::<WorldEditHistory>().undo_len(), 2);
+		assert_eq!(app.world().resource::<WorldEditHistory>().redo_len(), 0);
+	}
+}
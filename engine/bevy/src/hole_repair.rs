@@ -0,0 +1,337 @@
+use crate::cascade::CascadeChunk;
+use crate::cpu::MeshData;
+use crate::processor::MeshProcessor;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Holes found in chunk meshes by [HoleRepair], counted across every mesh it has processed.
+///
+/// Clones share their counts, so a clone registered as a resource sees what the processor
+/// in [MeshProcessors](crate::MeshProcessors) finds; [collect_world_stats](crate::collect_world_stats)
+/// copies them into the [WorldStats](crate::WorldStats).
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MeshHoles {
+	repaired: Arc<AtomicUsize>,
+	open: Arc<AtomicUsize>,
+}
+
+impl MeshHoles {
+	/// Holes closed by snapping or filling.
+	pub fn repaired(&self) -> usize {
+		self.repaired.load(Ordering::Relaxed)
+	}
+
+	/// Holes that were left open, either too large to fill or not closed loops.
+	pub fn open(&self) -> usize {
+		self.open.load(Ordering::Relaxed)
+	}
+}
+
+/// A [MeshProcessor] closing cracks inside chunk meshes.
+///
+/// Edges used by a single triangle away from the chunk faces are holes. Their points are first
+/// snapped onto boundary points within `snap` voxels, which closes the hairline cracks float
+/// error leaves between cubes, and the loops still open are then filled with triangle fans if
+/// they have at most `max_hole_edges` edges. Larger openings, such as carved portals, are left
+/// open and counted.
+#[derive(Debug, Clone)]
+pub struct HoleRepair {
+	/// Distance, in voxels, under which vertices count as the same point
+	pub weld: f32,
+	/// Distance, in voxels, over which boundary points are snapped together
+	pub snap: f32,
+	/// Most edges of a hole that is filled
+	pub max_hole_edges: usize,
+	pub holes: MeshHoles,
+}
+
+impl Default for HoleRepair {
+	fn default() -> Self {
+		Self { weld: 1e-3, snap: 0.05, max_hole_edges: 8, holes: MeshHoles::default() }
+	}
+}
+
+impl HoleRepair {
+	pub fn new(holes: MeshHoles) -> Self {
+		Self { holes, ..default() }
+	}
+
+	pub fn with_weld(mut self, weld: f32) -> Self {
+		self.weld = weld;
+		self
+	}
+
+	pub fn with_snap(mut self, snap: f32) -> Self {
+		self.snap = snap;
+		self
+	}
+
+	pub fn with_max_hole_edges(mut self, max_hole_edges: usize) -> Self {
+		self.max_hole_edges = max_hole_edges;
+		self
+	}
+
+	/// Repairs the mesh of the chunk, returning how many holes were repaired and left open.
+	pub fn repair(&self, chunk: &CascadeChunk, mesh: &mut MeshData) -> (usize, usize) {
+		let voxel = (chunk.size / (UVec3::ONE << chunk.res_2).as_vec3()).min_element();
+		let welding = Welding::new(mesh, voxel * self.weld, chunk.size);
+		let boundary = welding.boundary_edges(mesh);
+		if boundary.is_empty() {
+			return (0, 0);
+		}
+		let found = count_loops(&boundary);
+
+		// Snap boundary points that nearly meet, then look at what is still open
+		// Points snap onto the lowest point near them, which has already snapped itself
+		let mut points: Vec<usize> = boundary.iter().flat_map(|(a, b)| [*a, *b]).collect();
+		points.sort();
+		points.dedup();
+		let snap = (voxel * self.snap).max(f32::EPSILON);
+		let cell = |point: usize| (welding.position(point) / snap).floor().as_ivec3();
+		let mut cells: HashMap<IVec3, Vec<usize>> = HashMap::new();
+		for &point in &points {
+			cells.entry(cell(point)).or_default().push(point);
+		}
+		let mut snapped = false;
+		for &b in &points {
+			let near = (-1..=1)
+				.flat_map(|x| {
+					(-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z)))
+				})
+				.filter_map(|offset| cells.get(&(cell(b) + offset)))
+				.flatten()
+				.copied()
+				.filter(|&a| a < b && welding.position(a).distance(welding.position(b)) < snap)
+				.min();
+			if let Some(a) = near {
+				let target = mesh.positions[welding.vertex(a) as usize];
+				for &vertex in &welding.vertices[b] {
+					mesh.positions[vertex as usize] = target;
+				}
+				snapped = true;
+			}
+		}
+		let (welding, boundary) = if snapped {
+			let welding = Welding::new(mesh, voxel * self.weld, chunk.size);
+			welding.drop_collapsed(mesh);
+			let boundary = welding.boundary_edges(mesh);
+			(welding, boundary)
+		} else {
+			(welding, boundary)
+		};
+
+		// Fill the small loops that are left, winding the fill against the hole's edges
+		let mut open = 0;
+		for hole in boundary_loops(&boundary) {
+			match hole {
+				Some(hole) if hole.len() <= self.max_hole_edges => {
+					for i in 1..hole.len() - 1 {
+						mesh.indices.extend([
+							welding.vertex(hole[0]),
+							welding.vertex(hole[i + 1]),
+							welding.vertex(hole[i]),
+						]);
+					}
+				}
+				_ => open += 1,
+			}
+		}
+		(found.saturating_sub(open), open)
+	}
+}
+
+impl<S: Sdf> MeshProcessor<S> for HoleRepair {
+	fn name(&self) -> &str {
+		"hole repair"
+	}
+
+	fn process(&self, _sdf: &S, chunk: &CascadeChunk, mut mesh: MeshData) -> Option<MeshData> {
+		let (repaired, open) = self.repair(chunk, &mut mesh);
+		if repaired + open > 0 {
+			log::debug!(
+				"Repaired {repaired} holes in the chunk at {:?}, {open} left open",
+				chunk.origin
+			);
+		}
+		self.holes.repaired.fetch_add(repaired, Ordering::Relaxed);
+		self.holes.open.fetch_add(open, Ordering::Relaxed);
+		(!mesh.is_empty()).then_some(mesh)
+	}
+}
+
+/// Vertices welded into points, since neighbouring cubes don't share vertices.
+struct Welding {
+	/// The point of each vertex
+	points: Vec<usize>,
+	/// The vertices of each point
+	vertices: Vec<Vec<u32>>,
+	positions: Vec<Vec3>,
+	/// Points on a face of the chunk, by the faces they lie on as bits
+	faces: Vec<u8>,
+}
+
+impl Welding {
+	fn new(mesh: &MeshData, weld: f32, size: Vec3) -> Self {
+		let weld = weld.max(f32::EPSILON);
+		let mut ids: HashMap<IVec3, usize> = HashMap::new();
+		let mut welding = Self {
+			points: Vec::new(),
+			vertices: Vec::new(),
+			positions: Vec::new(),
+			faces: Vec::new(),
+		};
+		for (vertex, position) in mesh.positions.iter().enumerate() {
+			let position = Vec3::from_array(*position);
+			let id = *ids.entry((position / weld).round().as_ivec3()).or_insert_with(|| {
+				let mut faces = 0;
+				for axis in 0..3 {
+					faces |= u8::from(position[axis].abs() < weld) << (axis * 2);
+					faces |= u8::from((position[axis] - size[axis]).abs() < weld) << (axis * 2 + 1);
+				}
+				welding.vertices.push(Vec::new());
+				welding.positions.push(position);
+				welding.faces.push(faces);
+				welding.vertices.len() - 1
+			});
+			welding.points.push(id);
+			welding.vertices[id].push(vertex as u32);
+		}
+		welding
+	}
+
+	fn position(&self, point: usize) -> Vec3 {
+		self.positions[point]
+	}
+
+	/// A vertex of the point, for indexing new triangles.
+	fn vertex(&self, point: usize) -> u32 {
+		self.vertices[point][0]
+	}
+
+	fn triangles<'a>(&'a self, mesh: &'a MeshData) -> impl Iterator<Item = [usize; 3]> + 'a {
+		mesh.indices
+			.chunks_exact(3)
+			.map(|t| [t[0], t[1], t[2]].map(|v| self.points[v as usize]))
+	}
+
+	/// Removes triangles whose points were snapped onto each other.
+	fn drop_collapsed(&self, mesh: &mut MeshData) {
+		let kept: Vec<u32> = mesh
+			.indices
+			.chunks_exact(3)
+			.filter(|t| {
+				let [a, b, c] = [t[0], t[1], t[2]].map(|v| self.points[v as usize]);
+				a != b && b != c && a != c
+			})
+			.flatten()
+			.copied()
+			.collect();
+		mesh.indices = kept;
+	}
+
+	/// Directed edges between points with no triangle running the other way, leaving out
+	/// edges along a face of the chunk, where the neighbouring chunk continues the surface.
+	fn boundary_edges(&self, mesh: &MeshData) -> Vec<(usize, usize)> {
+		let edges: HashSet<(usize, usize)> = self
+			.triangles(mesh)
+			.flat_map(|[a, b, c]| [(a, b), (b, c), (c, a)])
+			.filter(|(a, b)| a != b)
+			.collect();
+		let mut boundary: Vec<(usize, usize)> = edges
+			.iter()
+			.filter(|(a, b)| !edges.contains(&(*b, *a)) && self.faces[*a] & self.faces[*b] == 0)
+			.copied()
+			.collect();
+		boundary.sort();
+		boundary
+	}
+}
+
+/// Follows the boundary edges into the loops around each hole; chains that don't close,
+/// or that branch, come back as `None`.
+fn boundary_loops(boundary: &[(usize, usize)]) -> Vec<Option<Vec<usize>>> {
+	let mut next: HashMap<usize, Vec<usize>> = HashMap::new();
+	for (a, b) in boundary {
+		next.entry(*a).or_default().push(*b);
+	}
+	let mut visited = HashSet::new();
+	let mut loops = Vec::new();
+	for &(start, _) in boundary {
+		if visited.contains(&start) {
+			continue;
+		}
+		let mut hole = vec![start];
+		let mut closed = false;
+		let mut current = start;
+		visited.insert(start);
+		while let Some([to]) = next.get(&current).map(Vec::as_slice) {
+			if *to == start {
+				closed = true;
+				break;
+			}
+			if !visited.insert(*to) {
+				break;
+			}
+			hole.push(*to);
+			current = *to;
+		}
+		loops.push((closed && hole.len() >= 3).then_some(hole));
+	}
+	loops
+}
+
+fn count_loops(boundary: &[(usize, usize)]) -> usize {
+	boundary_loops(boundary).len()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::cpu::CpuMeshGenerator;
+	use crate::mesh_checks::{check_mesh, MeshCheckConfig};
+
+	struct Sphere;
+
+	impl Sdf for Sphere {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.distance(Vec3::new(0.1, 0.2, 0.3)) - 2.27
+		}
+	}
+
+	fn open_edges(chunk: &CascadeChunk, mesh: &MeshData) -> usize {
+		let voxel = chunk.size.x / (1 << chunk.res_2.x) as f32;
+		Welding::new(mesh, voxel * 1e-3, chunk.size).boundary_edges(mesh).len()
+	}
+
+	#[test]
+	fn test_hole_repair_closes_cracks_and_small_holes() {
+		let chunk = CascadeChunk::cube(Vec3::splat(-4.0), 8.0, 4);
+		let Some(sphere) = CpuMeshGenerator::generate_chunk_mesh_data(&chunk, Arc::new(Sphere))
+		else {
+			panic!("expected a sphere mesh");
+		};
+		let repair = HoleRepair::default();
+		let mut closed = sphere.clone();
+		assert_eq!(repair.repair(&chunk, &mut closed), (0, 0));
+
+		// Knock a triangle out and nudge a vertex off its neighbours
+		let mut damaged = sphere.clone();
+		damaged.indices.drain(0..3);
+		damaged.positions[40][0] += 0.01 * chunk.size.x / 16.0;
+		assert!(open_edges(&chunk, &damaged) > 0);
+
+		let holes = MeshHoles::default();
+		let Some(repaired) = HoleRepair::new(holes.clone()).process(&Sphere, &chunk, damaged)
+		else {
+			panic!("expected the repaired mesh");
+		};
+		assert_eq!(open_edges(&chunk, &repaired), 0);
+		assert!(holes.repaired() >= 1);
+		assert_eq!(holes.open(), 0);
+		let report = check_mesh(&repaired, &MeshCheckConfig::default().with_manifold(true));
+		assert_eq!(report.non_manifold_edges, 0, "{report}");
+	}
+}
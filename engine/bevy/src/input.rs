@@ -0,0 +1,227 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+/// Actions that controls can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputAction {
+	MoveForward,
+	MoveBack,
+	MoveLeft,
+	MoveRight,
+	MoveUp,
+	MoveDown,
+	Jump,
+	ToggleCharacterMode,
+	ToggleDebugView,
+	ToggleTweakPanel,
+	TweakPrevious,
+	TweakNext,
+	TweakIncrease,
+	TweakDecrease,
+	ToggleEditor,
+	EditorNextStamp,
+	EditorSave,
+	Undo,
+	Redo,
+	Interact,
+	ToggleCascadeGizmos,
+	ToggleRegionGizmos,
+	ToggleSkeletonGizmos,
+	ToggleStatsOverlay,
+}
+
+/// A physical input that triggers an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputBinding {
+	Key(KeyCode),
+	Gamepad(GamepadButton),
+}
+
+/// Rebindable mapping from actions to inputs.
+///
+/// The left gamepad stick always contributes to movement and the right stick to looking.
+#[derive(Resource, Debug, Clone)]
+pub struct InputMap {
+	bindings: HashMap<InputAction, Vec<InputBinding>>,
+	/// Stick values below this magnitude are ignored.
+	pub gamepad_deadzone: f32,
+	/// Radians per second at full right stick deflection.
+	pub gamepad_look_speed: f32,
+}
+
+impl Default for InputMap {
+	fn default() -> Self {
+		use InputAction::*;
+		use InputBinding::{Gamepad, Key};
+
+		Self::empty()
+			.with_binding(MoveForward, Key(KeyCode::KeyW))
+			.with_binding(MoveBack, Key(KeyCode::KeyS))
+			.with_binding(MoveLeft, Key(KeyCode::KeyA))
+			.with_binding(MoveRight, Key(KeyCode::KeyD))
+			.with_binding(MoveUp, Key(KeyCode::Space))
+			.with_binding(MoveUp, Gamepad(GamepadButton::RightTrigger2))
+			.with_binding(MoveDown, Key(KeyCode::ShiftLeft))
+			.with_binding(MoveDown, Gamepad(GamepadButton::LeftTrigger2))
+			.with_binding(Jump, Key(KeyCode::Space))
+			.with_binding(Jump, Gamepad(GamepadButton::South))
+			.with_binding(ToggleCharacterMode, Key(KeyCode::KeyC))
+			.with_binding(ToggleCharacterMode, Gamepad(GamepadButton::North))
+			.with_binding(ToggleDebugView, Key(KeyCode::F3))
+			.with_binding(ToggleTweakPanel, Key(KeyCode::F4))
+			.with_binding(ToggleTweakPanel, Gamepad(GamepadButton::Select))
+			.with_binding(TweakPrevious, Key(KeyCode::ArrowUp))
+			.with_binding(TweakPrevious, Gamepad(GamepadButton::DPadUp))
+			.with_binding(TweakNext, Key(KeyCode::ArrowDown))
+			.with_binding(TweakNext, Gamepad(GamepadButton::DPadDown))
+			.with_binding(TweakIncrease, Key(KeyCode::ArrowRight))
+			.with_binding(TweakIncrease, Gamepad(GamepadButton::DPadRight))
+			.with_binding(TweakDecrease, Key(KeyCode::ArrowLeft))
+			.with_binding(TweakDecrease, Gamepad(GamepadButton::DPadLeft))
+			.with_binding(ToggleEditor, Key(KeyCode::F5))
+			.with_binding(EditorNextStamp, Key(KeyCode::Tab))
+			.with_binding(EditorSave, Key(KeyCode::F6))
+			.with_binding(Undo, Key(KeyCode::KeyZ))
+			.with_binding(Redo, Key(KeyCode::KeyY))
+			.with_binding(Interact, Key(KeyCode::KeyF))
+			.with_binding(Interact, Gamepad(GamepadButton::West))
+			.with_binding(ToggleCascadeGizmos, Key(KeyCode::F7))
+			.with_binding(ToggleRegionGizmos, Key(KeyCode::F8))
+			.with_binding(ToggleSkeletonGizmos, Key(KeyCode::F9))
+			.with_binding(ToggleStatsOverlay, Key(KeyCode::F10))
+	}
+}
+
+impl InputMap {
+	/// A map with no bindings.
+	pub fn empty() -> Self {
+		Self { bindings: HashMap::new(), gamepad_deadzone: 0.15, gamepad_look_speed: 2.5 }
+	}
+
+	pub fn with_binding(mut self, action: InputAction, binding: InputBinding) -> Self {
+		self.bind(action, binding);
+		self
+	}
+
+	/// Adds a binding to an action, keeping any existing bindings.
+	pub fn bind(&mut self, action: InputAction, binding: InputBinding) {
+		let bindings = self.bindings.entry(action).or_default();
+		if !bindings.contains(&binding) {
+			bindings.push(binding);
+		}
+	}
+
+	/// Replaces all bindings of an action.
+	pub fn rebind(&mut self, action: InputAction, bindings: Vec<InputBinding>) {
+		self.bindings.insert(action, bindings);
+	}
+
+	/// Removes all bindings of an action.
+	pub fn unbind(&mut self, action: InputAction) {
+		self.bindings.remove(&action);
+	}
+
+	pub fn bindings(&self, action: InputAction) -> &[InputBinding] {
+		self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+	}
+
+	fn apply_deadzone(&self, stick: Vec2) -> Vec2 {
+		if stick.length() < self.gamepad_deadzone {
+			Vec2::ZERO
+		} else {
+			stick
+		}
+	}
+}
+
+/// Reads actions from the keyboard and all connected gamepads through the [InputMap].
+#[derive(SystemParam)]
+pub struct Actions<'w, 's> {
+	input_map: Res<'w, InputMap>,
+	keyboard: Res<'w, ButtonInput<KeyCode>>,
+	gamepads: Query<'w, 's, &'static Gamepad>,
+}
+
+impl Actions<'_, '_> {
+	pub fn pressed(&self, action: InputAction) -> bool {
+		self.input_map.bindings(action).iter().any(|binding| match binding {
+			InputBinding::Key(key) => self.keyboard.pressed(*key),
+			InputBinding::Gamepad(button) => {
+				self.gamepads.iter().any(|gamepad| gamepad.pressed(*button))
+			}
+		})
+	}
+
+	pub fn just_pressed(&self, action: InputAction) -> bool {
+		self.input_map.bindings(action).iter().any(|binding| match binding {
+			InputBinding::Key(key) => self.keyboard.just_pressed(*key),
+			InputBinding::Gamepad(button) => {
+				self.gamepads.iter().any(|gamepad| gamepad.just_pressed(*button))
+			}
+		})
+	}
+
+	/// Planar movement with x to the right and y forward, clamped to unit length.
+	pub fn move_axis(&self) -> Vec2 {
+		let mut axis = Vec2::ZERO;
+		if self.pressed(InputAction::MoveForward) {
+			axis.y += 1.0;
+		}
+		if self.pressed(InputAction::MoveBack) {
+			axis.y -= 1.0;
+		}
+		if self.pressed(InputAction::MoveLeft) {
+			axis.x -= 1.0;
+		}
+		if self.pressed(InputAction::MoveRight) {
+			axis.x += 1.0;
+		}
+		for gamepad in self.gamepads.iter() {
+			axis += self.input_map.apply_deadzone(gamepad.left_stick());
+		}
+		axis.clamp_length_max(1.0)
+	}
+
+	/// Vertical movement in [-1, 1].
+	pub fn vertical_axis(&self) -> f32 {
+		let mut axis = 0.0;
+		if self.pressed(InputAction::MoveUp) {
+			axis += 1.0;
+		}
+		if self.pressed(InputAction::MoveDown) {
+			axis -= 1.0;
+		}
+		axis
+	}
+
+	/// Look rotation in radians for this frame, from the right gamepad stick.
+	pub fn look_delta(&self, delta_secs: f32) -> Vec2 {
+		let stick = self
+			.gamepads
+			.iter()
+			.map(|gamepad| self.input_map.apply_deadzone(gamepad.right_stick()))
+			.sum::<Vec2>()
+			.clamp_length_max(1.0);
+		stick * self.input_map.gamepad_look_speed * delta_secs
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_rebind_action() {
+		let mut input_map = InputMap::default();
+		assert!(input_map
+			.bindings(InputAction::Jump)
+			.contains(&InputBinding::Key(KeyCode::Space)));
+
+		input_map.rebind(InputAction::Jump, vec![InputBinding::Key(KeyCode::KeyJ)]);
+		assert_eq!(input_map.bindings(InputAction::Jump), &[InputBinding::Key(KeyCode::KeyJ)]);
+
+		input_map.unbind(InputAction::Jump);
+		assert!(input_map.bindings(InputAction::Jump).is_empty());
+	}
+}
@@ -0,0 +1,242 @@
+pub use engine_core::{cascade, compression, generator, marching_cubes, mesh_checks, occupancy};
+
+pub mod ambience;
+pub mod animation;
+pub mod async_generation;
+pub mod atlas;
+pub mod bark;
+pub mod boot;
+pub mod boundary;
+pub mod budget;
+pub mod chunk;
+pub mod chunk_graph;
+pub mod chunk_manager;
+pub mod cpu;
+pub mod crossfade;
+pub mod decal;
+pub mod double_buffer;
+pub mod far_terrain;
+pub mod focus;
+pub mod foliage;
+pub mod generation_pool;
+pub mod gizmos;
+pub mod history;
+pub mod hole_repair;
+pub mod input;
+pub mod lighting;
+pub mod material;
+pub mod mesh_cache;
+pub mod palette;
+pub mod particles;
+pub mod portal;
+pub mod prewarm;
+pub mod probes;
+pub mod processor;
+#[cfg(feature = "reflect")]
+pub mod reflection;
+pub mod regeneration;
+pub mod replay;
+pub mod save;
+pub mod scaling;
+pub mod shaders;
+pub mod skirts;
+pub mod stats;
+pub mod structures;
+pub mod terrain_query;
+pub mod terrain_shadow;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod transform;
+pub mod wind;
+
+pub use ambience::{
+	mix_ambience, tag_chunk_features, AmbienceBed, AmbienceMixer, ChunkFeatureTagger,
+	ChunkFeatures, ChunkTagFn,
+};
+pub use animation::{
+	animate_materials, MaterialAnimationId, MaterialAnimations, ParamCurve, ParamSetter,
+};
+pub use async_generation::{finish_chunk_mesh_tasks, AsyncChunkGeneration, ChunkMeshTask};
+pub use atlas::{AtlasEntry, AtlasId, AtlasKind, WorldAtlas};
+pub use bark::{BarkLibrary, BarkTemplate, BarkTextures};
+pub use boot::{boot_world, BootProgress, WorldBoot, WorldBootState};
+pub use boundary::{
+	confine_to_world, EdgeFade, WorldBoundary, WorldBounds, WorldConfined, WorldEdge,
+};
+pub use budget::ChunkBudgetConfig;
+pub use chunk::{ChunkConfig, ChunkCoord, ChunkId, LoadedChunks};
+pub use chunk_graph::{update_chunk_graph, ChunkFace, ChunkGraph, ChunkNeighbor};
+pub use chunk_manager::{
+	manage_chunks, track_camera_projection, ChunkResolutionConfig, ScreenSpaceError, SdfResource,
+};
+pub use cpu::{ChunkSpawner, IntoMesh};
+pub use crossfade::{crossfade_chunks, ChunkCrossfade, ChunkFade, FadeDirection};
+pub use decal::{
+	fade_distant_decals, project_chunk_decals, project_decals, ChunkDecals, Decal, DecalFade,
+	DecalId, DecalKind, DecalMaterials, Decals,
+};
+pub use double_buffer::{swap_chunk_buffers, ChunkDoubleBuffer, ChunkSwapOut};
+pub use engine_core::{
+	check_mesh, ChunkOccupancy, ChunkRegion, CompressedMesh, MeshCheckConfig, MeshData,
+	MeshProcessor, MeshReport, OccupancyCheck, OriginSnapping, VertexInterpolation, WorldGenerator,
+};
+pub use far_terrain::{update_far_terrain, FarTerrain, FarTerrainShell};
+pub use focus::ResolutionFocus;
+pub use foliage::{
+	bend_foliage, fade_foliage, track_foliage_actors, FoliageActor, FoliageFalloff,
+	FoliageInteraction,
+};
+pub use generation_pool::{GenerationPool, GenerationPoolConfig};
+pub use gizmos::{
+	draw_cascade_bounds, draw_region_boundaries, surface_height, CascadeGizmos, RegionGizmos,
+};
+pub use history::{apply_world_edits, WorldEdit, WorldEditHistory};
+pub use hole_repair::{HoleRepair, MeshHoles};
+pub use input::{Actions, InputAction, InputBinding, InputMap};
+pub use lighting::{LightingPreset, StandardLightingPlugin, SunLayers};
+pub use material::{ChunkKind, ChunkMaterialRegistry, ChunkTag};
+pub use mesh_cache::{ChunkMeshCache, ChunkMeshKey};
+pub use palette::{
+	PaletteMaterials, PalettePreset, PaletteRole, PaletteTransition, WorldPalette,
+	WorldPalettePlugin,
+};
+pub use particles::{
+	drift_ambient_particles, emit_ambient_particles, AmbientParticle, AmbientParticles,
+	ChunkParticles, ParticleEmitter, ParticleHours,
+};
+pub use portal::{
+	carve_portals, queue_portal_chunks, InsertPortal, PortalId, PortalVolume, PortalVolumes,
+	RemovePortal,
+};
+pub use prewarm::{prewarm_chunks, ChunkPrewarm};
+pub use probes::{queue_light_probe_chunks, LightProbe, LightProbes, ProbeBakeConfig};
+pub use processor::MeshProcessors;
+#[cfg(feature = "reflect")]
+pub use reflection::EngineReflectPlugin;
+pub use regeneration::{
+	queue_dirty_chunks, queue_dirty_region_chunks, regenerate_queued_chunks,
+	ChunkRegenerationQueue, TerrainDirty, TerrainRegionDirty,
+};
+pub use replay::{
+	mesh_hash, GenerationRecorder, GenerationRecording, GenerationRequest, ReplayMismatch,
+	ReplayReport,
+};
+pub use save::{
+	SaveMigration, SavedStamp, TerrainDelta, WorldSave, WorldSaveMigrations, WORLD_SAVE_VERSION,
+};
+pub use scaling::{scale_resolution, ResolutionScaling};
+pub use sdf;
+pub use skirts::ChunkSkirts;
+pub use stats::{collect_world_stats, mesh_bytes, mesh_triangles, ChunkMeshSize, WorldStats};
+pub use structures::{stream_structures, StructureSpawner, StructureStreaming};
+pub use terrain_query::{TerrainHit, TerrainQuery, WalkMaskFn, Walkability};
+pub use terrain_shadow::{bake_terrain_shadows, TerrainShadowMap};
+pub use transform::WorldTransform;
+pub use wind::WindField;
+
+// Main exports for the engine
+// Users should register:
+// - ChunkConfig resource
+// - ChunkResolutionConfig resource
+// - ChunkMaterialRegistry resource
+// - SdfResource<S> resource (where S: Sdf + Send + Sync)
+// - LoadedChunks resource
+// - InputMap resource, if using Actions for controls
+// - WorldPalettePlugin, to color the sky and built-in materials from a switchable palette
+//   (track other materials in PaletteMaterials)
+// - GenerationPool resource, to generate chunks off rayon's global pool
+// - AsyncChunkGeneration<S> resource and the finish_chunk_mesh_tasks::<S> system after
+//   manage_chunks, to generate chunks on Bevy's async compute pool over several frames instead
+//   of stalling the frame that needs them
+// - ChunkBudgetConfig<S> resource, to spread the chunks a teleport dirties over several frames,
+//   nearest the camera first
+// - ChunkConfig::mesh_checks, to validate generated meshes while debugging the generator
+// - ChunkConfig::occupancy, to skip generating chunks whose sign intervals show them all air or
+//   all ground (for heightfields, with its slope at least as steep as theirs)
+// - TerrainDirty message and ChunkRegenerationQueue<S> resource, to regenerate chunks live
+// - Then add manage_chunks system to their Update schedule
+//   (and queue_dirty_chunks, regenerate_queued_chunks for live regeneration)
+// - PortalVolumes<S> resource and the queue_portal_chunks system, to cut openings for interiors
+//   (requires ChunkRegenerationQueue<S> and regenerate_queued_chunks)
+// - LightProbes<S> resource, baked with LightProbes::bake, to darken caves and tunnels
+//   (and queue_light_probe_chunks to reshade chunks when the probes are baked again)
+// - MeshProcessors<S> resource, to run MeshProcessor steps such as simplification or vertex
+//   color tagging over every chunk mesh before it is spawned
+// - WorldEditHistory resource and the apply_world_edits system, for undoable runtime edits
+//   (with the TerrainRegionDirty message and queue_dirty_region_chunks for terrain edits)
+// - Decals<S> and DecalMaterials resources with the project_chunk_decals system, for ground
+//   detail projected onto chunks (and fade_distant_decals, with the DecalMaterial plugin)
+// - ChunkConfig::boundary set to WorldBoundary::Edge, to end a world that doesn't wrap
+//   (wrap the SDF in EdgeFade, and add confine_to_world for entities marked WorldConfined)
+// - ChunkCrossfade<S> resource and the crossfade_chunks system, to dissolve chunks into their
+//   new resolution (requires ChunkRegenerationQueue<S> and regenerate_queued_chunks)
+// - ChunkDoubleBuffer<S> resource and the swap_chunk_buffers system after manage_chunks and
+//   regenerate_queued_chunks, to keep old chunk meshes drawn until their replacements are alive
+// - ResolutionFocus on the camera, to sharpen the chunks it looks at
+//   (with ChunkRegenerationQueue<S> and regenerate_queued_chunks to swap resolutions smoothly)
+// - ResolutionScaling<S> resource and the scale_resolution system before manage_chunks, to
+//   lower the chunk resolution while frames run over budget
+// - ChunkPrewarm<S> resource and the prewarm_chunks system after manage_chunks, to generate
+//   chunks ahead of a fast camera
+// - ChunkMeshCache<S> resource, to respawn recently unloaded chunks from their meshes instead
+//   of generating them again
+// - ChunkResolutionConfig::screen_space with the track_camera_projection system, to pick ring
+//   resolutions from the on-screen size of their voxels
+// - MaterialAnimations<M> resource and the animate_materials::<M> system, to drive material
+//   parameters from curves
+// - tag_chunk_features::<S> system, with a ChunkFeatureTagger<S> resource, to tag loaded chunks
+//   with their biome and features (and AmbienceMixer<S> with mix_ambience to crossfade audio
+//   beds by the tags around the camera)
+// - AmbientParticles<S> resource with the emit_ambient_particles and drift_ambient_particles
+//   systems after tag_chunk_features, for pollen, flurries and fireflies in the tagged chunks
+//   around the camera (carried by the WindField resource, if there is one)
+// - WorldBootState state, with WorldBoot<S> and BootProgress resources and the boot_world system
+//   while Booting, to generate the innermost cascade rings before play starts (run
+//   manage_chunks only once Playing, and show BootProgress on a loading screen)
+// - The debug-names feature, here and in the procedure crates, to name chunks and generated
+//   content for the inspector
+// - CascadeGizmos and RegionGizmos<S> resources with the draw_cascade_bounds and
+//   draw_region_boundaries systems, to overlay chunk bounds and 2D regions on the terrain
+// - WorldSave resource, to persist terrain cuts, placed stamps and destroyed vegetation
+//   (loaded with WorldSaveMigrations to upgrade saves of older versions)
+// - WorldStats resource and the collect_world_stats::<S> system, to count chunks, triangles
+//   and mesh memory for an overlay (enable it only while shown)
+// - StandardLightingPlugin::with_sun_layers including render_item's SHADOW_PROXY_LAYER, for
+//   groves that cast canopy shadows from proxies
+// - GenerationRecorder<S> resource, to record the chunks generated in a session and replay them
+//   headlessly with GenerationRecording::replay, comparing mesh hashes chunk by chunk
+// - FoliageInteraction resource with the track_foliage_actors and bend_foliage systems, to bend
+//   LeafMaterial foliage away from entities marked FoliageActor
+// - FoliageFalloff resource with the fade_foliage system, to thin foliage out and dither it away
+//   toward the edge of its reach instead of popping (every LeafMaterial fades, so far stand-ins
+//   need a material of their own)
+// - ChunkGraph resource and the update_chunk_graph system after manage_chunks, to query which
+//   loaded chunks share faces and how far apart their rings are
+// - HoleRepair in MeshProcessors<S>, to close cracks in generated meshes (register a clone of
+//   its MeshHoles as a resource to count the holes in WorldStats)
+// - ChunkSkirts in MeshProcessors<S>, to hide the cracks between cascade rings of different
+//   resolution behind skirts hung from the chunk faces
+// - SdfResource::with_iso_level, to mesh an offset shell of the SDF, such as snow cover over a
+//   second layer of the same terrain or a thicker collision surface
+// - SdfResource::with_interpolation, to place vertices at edge midpoints for blocky worlds or
+//   refine them onto the surface with extra SDF samples
+// - TerrainQuery<S> system param, for AI and pathfinding to read ground height, normals,
+//   walkability and line of sight in world space (with a Walkability<S> resource to set the
+//   steepest walkable slope and mask out ground)
+// - BarkLibrary resource, to generate tileable bark textures from BarkTemplate species at startup
+//   and lay them over trunk materials with EdgeMaterial::with_bark
+// - WorldAtlas resource, for generators to register the valleys, roads and landmarks they place,
+//   queried by kind, region or distance and exported to JSON for quests and map UIs
+// - StructureStreaming<S> resource and the stream_structures::<S> system after manage_chunks, to
+//   spawn structures bigger than a chunk once while any chunk they overlap is loaded
+// - EngineReflectPlugin::<S> (with the reflect feature), to register the chunk configs and
+//   materials for inspectors such as bevy_inspector_egui and for scene serialization
+// - TerrainShadowMap<S> resource and the bake_terrain_shadows::<S> system, to shade distant
+//   terrain past the real-time shadow cascades from a heightfield baked for the sun direction
+// - FarTerrain<S> resource, the update_far_terrain::<S> system and MaterialPlugin of
+//   FarTerrainMaterial, to raymarch the terrain past the chunks from a baked heightfield instead
+//   of meshing far grid chunks (keep the camera's far plane past FarTerrain::near)
+//
+// To generate chunks without the ECS, use WorldGenerator::iter_chunks over a ChunkRegion.
+// To persist or send chunk meshes, encode their MeshData with CompressedMesh.
+// Both come from engine-core, which builds without render dependencies.
@@ -0,0 +1,217 @@
+use bevy::camera::visibility::RenderLayers;
+use bevy::prelude::*;
+use std::f32::consts::PI;
+
+/// Preset lighting conditions for the standard rig.
+///
+/// Switch presets at runtime by replacing the [LightingPreset] resource.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LightingPreset {
+	/// Flat, bright ambient light with a weak sun.
+	Overcast,
+	/// High sun with soft fill lights from every side.
+	#[default]
+	Noon,
+	/// Low, warm sun with long shadows.
+	GoldenHour,
+}
+
+impl LightingPreset {
+	pub const ALL: [LightingPreset; 3] =
+		[LightingPreset::Overcast, LightingPreset::Noon, LightingPreset::GoldenHour];
+
+	/// The preset after this one, wrapping around.
+	pub fn next(self) -> Self {
+		let index = Self::ALL.iter().position(|preset| *preset == self).unwrap_or(0);
+		Self::ALL[(index + 1) % Self::ALL.len()]
+	}
+
+	/// The light values for this preset.
+	pub fn rig(self) -> LightingRig {
+		match self {
+			LightingPreset::Overcast => LightingRig {
+				ambient_color: Color::srgb(0.85, 0.88, 0.95),
+				ambient_brightness: 3.0,
+				sun_color: Color::srgb(0.9, 0.92, 1.0),
+				sun_illuminance: 2500.0,
+				sun_rotation: Quat::from_euler(EulerRot::XYZ, -PI / 3.0, PI / 4.0, 0.0),
+				sun_shadows: false,
+				fill_color: Color::srgb(0.9, 0.92, 1.0),
+				fill_illuminance: 1200.0,
+			},
+			LightingPreset::Noon => LightingRig {
+				ambient_color: Color::WHITE,
+				ambient_brightness: 2.0,
+				sun_color: Color::WHITE,
+				sun_illuminance: 10000.0,
+				sun_rotation: Quat::from_euler(EulerRot::XYZ, -PI / 4.0, PI / 4.0, 0.0),
+				sun_shadows: true,
+				fill_color: Color::WHITE,
+				fill_illuminance: 500.0,
+			},
+			LightingPreset::GoldenHour => LightingRig {
+				ambient_color: Color::srgb(1.0, 0.85, 0.7),
+				ambient_brightness: 1.2,
+				sun_color: Color::srgb(1.0, 0.72, 0.45),
+				sun_illuminance: 6000.0,
+				sun_rotation: Quat::from_euler(EulerRot::XYZ, -PI / 12.0, PI / 4.0, 0.0),
+				sun_shadows: true,
+				fill_color: Color::srgb(0.75, 0.8, 1.0),
+				fill_illuminance: 250.0,
+			},
+		}
+	}
+}
+
+/// The values used to build the standard rig: ambient light, a sun, and four fill lights.
+#[derive(Debug, Clone, Copy)]
+pub struct LightingRig {
+	pub ambient_color: Color,
+	pub ambient_brightness: f32,
+	pub sun_color: Color,
+	pub sun_illuminance: f32,
+	pub sun_rotation: Quat,
+	pub sun_shadows: bool,
+	pub fill_color: Color,
+	pub fill_illuminance: f32,
+}
+
+impl LightingRig {
+	/// Fill light directions: opposite the sun, left, right, and top-down.
+	pub const FILL_ROTATIONS: [(f32, f32); 4] =
+		[(PI / 4.0, -PI / 4.0), (0.0, PI / 2.0), (0.0, -PI / 2.0), (-PI / 2.0, 0.0)];
+}
+
+/// Marks lights spawned by the [StandardLightingPlugin].
+#[derive(Component, Debug, Clone, Copy)]
+pub struct StandardLight;
+
+/// Key that cycles through the lighting presets.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct LightingCycleKey(pub KeyCode);
+
+/// Render layers the sun sees when casting shadows, such as layers of shadow proxies.
+#[derive(Resource, Debug, Clone)]
+pub struct SunLayers(pub RenderLayers);
+
+/// Spawns the standard lighting rig and rebuilds it whenever the [LightingPreset] changes.
+#[derive(Debug, Clone, Default)]
+pub struct StandardLightingPlugin {
+	pub preset: LightingPreset,
+	pub cycle_key: Option<KeyCode>,
+	pub sun_layers: Option<RenderLayers>,
+}
+
+impl StandardLightingPlugin {
+	pub fn new(preset: LightingPreset) -> Self {
+		Self { preset, cycle_key: None, sun_layers: None }
+	}
+
+	/// Lets the sun see the given layers, which should include the cameras' layers.
+	pub fn with_sun_layers(mut self, sun_layers: RenderLayers) -> Self {
+		self.sun_layers = Some(sun_layers);
+		self
+	}
+
+	pub fn with_cycle_key(mut self, key: KeyCode) -> Self {
+		self.cycle_key = Some(key);
+		self
+	}
+}
+
+impl Plugin for StandardLightingPlugin {
+	fn build(&self, app: &mut App) {
+		app.insert_resource(self.preset)
+			.add_systems(Update, apply_lighting_preset.run_if(resource_changed::<LightingPreset>));
+
+		if let Some(layers) = &self.sun_layers {
+			app.insert_resource(SunLayers(layers.clone()));
+		}
+
+		if let Some(key) = self.cycle_key {
+			app.insert_resource(LightingCycleKey(key))
+				.add_systems(Update, cycle_lighting_preset.before(apply_lighting_preset));
+		}
+	}
+}
+
+/// Despawns the current rig and spawns the rig for the current preset.
+pub fn apply_lighting_preset(
+	mut commands: Commands,
+	preset: Res<LightingPreset>,
+	sun_layers: Option<Res<SunLayers>>,
+	lights: Query<Entity, With<StandardLight>>,
+) {
+	for entity in lights.iter() {
+		commands.entity(entity).despawn();
+	}
+
+	let rig = preset.rig();
+	log::info!("Applying lighting preset {:?}", *preset);
+
+	commands.insert_resource(AmbientLight {
+		color: rig.ambient_color,
+		brightness: rig.ambient_brightness,
+		affects_lightmapped_meshes: true,
+	});
+
+	let mut sun = commands.spawn((
+		StandardLight,
+		DirectionalLight {
+			color: rig.sun_color,
+			illuminance: rig.sun_illuminance,
+			shadows_enabled: rig.sun_shadows,
+			..default()
+		},
+		Transform::from_rotation(rig.sun_rotation),
+	));
+	if let Some(sun_layers) = sun_layers {
+		sun.insert(sun_layers.0.clone());
+	}
+
+	for (x, y) in LightingRig::FILL_ROTATIONS {
+		commands.spawn((
+			StandardLight,
+			DirectionalLight {
+				color: rig.fill_color,
+				illuminance: rig.fill_illuminance,
+				shadows_enabled: false,
+				..default()
+			},
+			Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, x, y, 0.0)),
+		));
+	}
+}
+
+/// Advances to the next preset when the cycle key is pressed.
+pub fn cycle_lighting_preset(
+	keyboard_input: Res<ButtonInput<KeyCode>>,
+	cycle_key: Res<LightingCycleKey>,
+	mut preset: ResMut<LightingPreset>,
+) {
+	if keyboard_input.just_pressed(cycle_key.0) {
+		*preset = preset.next();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_preset_switching_rebuilds_rig() {
+		let mut app = App::new();
+		app.add_plugins(StandardLightingPlugin::new(LightingPreset::Noon));
+
+		app.update();
+		let mut lights = app.world_mut().query_filtered::<&DirectionalLight, With<StandardLight>>();
+		assert_eq!(lights.iter(app.world()).count(), 5);
+		assert_eq!(app.world().resource::<AmbientLight>().brightness, 2.0);
+
+		app.insert_resource(LightingPreset::Overcast);
+		app.update();
+		assert_eq!(lights.iter(app.world()).count(), 5);
+		assert_eq!(app.world().resource::<AmbientLight>().brightness, 3.0);
+		assert!(lights.iter(app.world()).all(|light| !light.shadows_enabled));
+	}
+}
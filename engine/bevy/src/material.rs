@@ -0,0 +1,107 @@
+use crate::palette::{PaletteRole, WorldPalette};
+use crate::shaders::outline::EdgeMaterial;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+/// Which part of the cascade a chunk belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChunkKind {
+	Cascade,
+	Grid,
+}
+
+/// A biome or layer tag used to pick chunk materials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkTag(pub &'static str);
+
+impl ChunkTag {
+	pub const DEFAULT: ChunkTag = ChunkTag("default");
+}
+
+impl Default for ChunkTag {
+	fn default() -> Self {
+		Self::DEFAULT
+	}
+}
+
+/// Chunk materials keyed by [ChunkKind] and [ChunkTag].
+///
+/// Materials are created once and shared by every chunk with the same key,
+/// rather than adding a material asset per chunk.
+#[derive(Resource, Debug, Clone)]
+pub struct ChunkMaterialRegistry {
+	materials: HashMap<(ChunkKind, ChunkTag), Handle<EdgeMaterial>>,
+}
+
+impl FromWorld for ChunkMaterialRegistry {
+	/// Registers the default terrain material for both chunk kinds, colored from the
+	/// [WorldPalette] if there is one.
+	fn from_world(world: &mut World) -> Self {
+		let palette = world.get_resource::<WorldPalette>().copied().unwrap_or_default();
+		let mut materials = world.resource_mut::<Assets<EdgeMaterial>>();
+		let terrain = materials.add(EdgeMaterial::new(palette.base_color(PaletteRole::Terrain)));
+
+		Self::empty()
+			.with_material(ChunkKind::Cascade, ChunkTag::DEFAULT, terrain.clone())
+			.with_material(ChunkKind::Grid, ChunkTag::DEFAULT, terrain)
+	}
+}
+
+impl ChunkMaterialRegistry {
+	/// A registry with no materials.
+	pub fn empty() -> Self {
+		Self { materials: HashMap::new() }
+	}
+
+	pub fn with_material(
+		mut self,
+		kind: ChunkKind,
+		tag: ChunkTag,
+		material: Handle<EdgeMaterial>,
+	) -> Self {
+		self.insert(kind, tag, material);
+		self
+	}
+
+	pub fn insert(&mut self, kind: ChunkKind, tag: ChunkTag, material: Handle<EdgeMaterial>) {
+		self.materials.insert((kind, tag), material);
+	}
+
+	/// Every registered material, once for each key it is registered under.
+	pub fn materials(&self) -> impl Iterator<Item = &Handle<EdgeMaterial>> {
+		self.materials.values()
+	}
+
+	/// Gets the material for a chunk, falling back to the default tag of the same kind.
+	pub fn get(&self, kind: ChunkKind, tag: ChunkTag) -> Option<Handle<EdgeMaterial>> {
+		self.materials
+			.get(&(kind, tag))
+			.or_else(|| self.materials.get(&(kind, ChunkTag::DEFAULT)))
+			.cloned()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_registry_falls_back_to_default_tag() {
+		let mut app = App::new();
+		app.add_plugins(AssetPlugin::default())
+			.init_asset::<EdgeMaterial>()
+			.init_resource::<ChunkMaterialRegistry>();
+
+		let snow = app
+			.world_mut()
+			.resource_mut::<Assets<EdgeMaterial>>()
+			.add(EdgeMaterial::new(Vec4::ONE));
+		let mut registry = app.world_mut().resource_mut::<ChunkMaterialRegistry>();
+		registry.insert(ChunkKind::Grid, ChunkTag("snow"), snow.clone());
+
+		let default = registry.get(ChunkKind::Grid, ChunkTag::DEFAULT);
+		assert_eq!(registry.get(ChunkKind::Grid, ChunkTag("snow")), Some(snow));
+		assert_eq!(registry.get(ChunkKind::Cascade, ChunkTag("snow")), default);
+		assert_eq!(app.world().resource::<Assets<EdgeMaterial>>().len(), 2);
+	}
+}
@@ -0,0 +1,188 @@
+use crate::cascade::CascadeChunk;
+use crate::chunk::ChunkId;
+use crate::stats::ChunkMeshSize;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+
+/// What a cached chunk mesh was generated for: the chunk, its resolution and the version of the
+/// SDF it sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkMeshKey {
+	pub id: ChunkId,
+	pub res_2: UVec3,
+	pub version: u64,
+}
+
+/// The meshes of recently unloaded chunks, so chunks the camera comes back to are respawned
+/// without running marching cubes again.
+///
+/// Chunk meshes only live in the render world, so the cache keeps their handles rather than
+/// their buffers, and [manage_chunks](crate::manage_chunks) keeps their assets instead of
+/// releasing them. The least recently unloaded meshes are released once the cache is over its
+/// capacity, and all of them are when the SDF, portals or light probes change.
+#[derive(Resource)]
+pub struct ChunkMeshCache<S: Sdf + Send + Sync> {
+	/// Most unloaded chunk meshes kept
+	pub capacity: usize,
+	entries: HashMap<ChunkMeshKey, (Handle<Mesh>, ChunkMeshSize)>,
+	/// Keys from the least to the most recently unloaded
+	order: VecDeque<ChunkMeshKey>,
+	version: u64,
+	hits: u64,
+	/// Marker for the SDF whose chunk meshes are cached
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for ChunkMeshCache<S> {
+	fn default() -> Self {
+		Self {
+			capacity: 256,
+			entries: HashMap::new(),
+			order: VecDeque::new(),
+			version: 0,
+			hits: 0,
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> ChunkMeshCache<S> {
+	pub fn with_capacity(mut self, capacity: usize) -> Self {
+		self.capacity = capacity;
+		self
+	}
+
+	/// Number of cached chunk meshes
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Number of chunks respawned from cached meshes
+	pub fn hits(&self) -> u64 {
+		self.hits
+	}
+
+	/// Version of the SDF the cached meshes were generated from, bumped on every change.
+	pub fn version(&self) -> u64 {
+		self.version
+	}
+
+	pub fn key(&self, id: ChunkId, chunk: &CascadeChunk) -> ChunkMeshKey {
+		ChunkMeshKey { id, res_2: chunk.res_2, version: self.version }
+	}
+
+	/// Moves to a new version of the SDF, returning every cached mesh to release.
+	pub(crate) fn invalidate(&mut self) -> Vec<Handle<Mesh>> {
+		self.version += 1;
+		self.order.clear();
+		self.entries.drain().map(|(_, (mesh, _))| mesh).collect()
+	}
+
+	/// Caches the mesh of an unloaded chunk, returning the least recently unloaded meshes the
+	/// cache no longer has room for.
+	pub(crate) fn insert(
+		&mut self,
+		id: ChunkId,
+		chunk: &CascadeChunk,
+		mesh: Handle<Mesh>,
+		size: ChunkMeshSize,
+	) -> Vec<Handle<Mesh>> {
+		let key = self.key(id, chunk);
+		let mut evicted = Vec::new();
+		if let Some((replaced, _)) = self.entries.insert(key, (mesh, size)) {
+			self.order.retain(|cached| *cached != key);
+			evicted.push(replaced);
+		}
+		self.order.push_back(key);
+		while self.entries.len() > self.capacity {
+			let Some(oldest) = self.order.pop_front() else {
+				break;
+			};
+			evicted.extend(self.entries.remove(&oldest).map(|(mesh, _)| mesh));
+		}
+		evicted
+	}
+
+	/// Takes the cached mesh of the chunk, if it was cached at the same resolution from the
+	/// current version of the SDF.
+	pub(crate) fn take(
+		&mut self,
+		id: ChunkId,
+		chunk: &CascadeChunk,
+	) -> Option<(Handle<Mesh>, ChunkMeshSize)> {
+		let key = self.key(id, chunk);
+		let cached = self.entries.remove(&key)?;
+		self.order.retain(|cached| *cached != key);
+		self.hits += 1;
+		Some(cached)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::chunk::TerrainChunk;
+	use crate::chunk_manager::manage_chunks;
+	use crate::test_support::{camera, ground_app, Ground};
+
+	#[test]
+	fn test_cache_evicts_the_least_recently_unloaded() {
+		let mut cache = ChunkMeshCache::<Ground>::default().with_capacity(2);
+		let chunk = |x: f32| CascadeChunk::cube(Vec3::new(x, 0.0, 0.0), 1.0, 0);
+		let id = |x: f32| ChunkId::from_chunk(&chunk(x), Vec3::ONE);
+		let mut meshes = Assets::<Mesh>::default();
+		let mut handle = || meshes.add(Mesh::from(Cuboid::default()));
+
+		for x in [0.0, 1.0, 2.0] {
+			let evicted = cache.insert(id(x), &chunk(x), handle(), ChunkMeshSize::default());
+			assert_eq!(evicted.len(), usize::from(x == 2.0));
+		}
+		assert_eq!(cache.len(), 2);
+		assert!(cache.take(id(0.0), &chunk(0.0)).is_none());
+		assert!(cache.take(id(1.0), &chunk(1.0)).is_some());
+
+		// Other resolutions and older versions of the SDF miss
+		let finer = CascadeChunk { res_2: chunk(2.0).res_2 + 1, ..chunk(2.0) };
+		assert!(cache.take(id(2.0), &finer).is_none());
+		assert_eq!(cache.invalidate().len(), 1);
+		assert!(cache.take(id(2.0), &chunk(2.0)).is_none());
+		assert_eq!(cache.hits(), 1);
+	}
+
+	#[test]
+	fn test_returning_chunks_are_respawned_from_the_cache() {
+		let mut app = ground_app(0.5);
+		app.init_resource::<ChunkMeshCache<Ground>>()
+			.add_systems(Update, manage_chunks::<Ground>);
+		let camera = camera(&mut app);
+		app.update();
+
+		let meshes = |app: &mut App| {
+			let world = app.world_mut();
+			let mut query = world.query_filtered::<&Mesh3d, With<TerrainChunk>>();
+			query.iter(world).map(|mesh| mesh.id()).collect::<Vec<_>>()
+		};
+		let first = meshes(&mut app);
+		assert!(!first.is_empty());
+
+		// Fly away and back
+		for x in [100.0, 0.0] {
+			app.world_mut().entity_mut(camera).insert(Transform::from_xyz(x, 0.0, 0.0));
+			app.update();
+		}
+
+		let cache = app.world().resource::<ChunkMeshCache<Ground>>();
+		assert_eq!(cache.hits(), first.len() as u64);
+		let returned = meshes(&mut app);
+		assert_eq!(returned.len(), first.len());
+		assert!(returned.iter().all(|mesh| first.contains(mesh)));
+		let assets = app.world().resource::<Assets<Mesh>>();
+		assert!(returned.iter().all(|mesh| assets.contains(*mesh)));
+	}
+}
@@ -0,0 +1,363 @@
+use crate::material::{ChunkKind, ChunkMaterialRegistry, ChunkTag};
+use crate::shaders::{leaf_material::LeafMaterial, outline::EdgeMaterial};
+use bevy::color::Mix;
+use bevy::prelude::*;
+
+/// The colors shared by the built-in materials, the sky and the fog.
+///
+/// Changing the resource recolors every material tracked in [PaletteMaterials] and the
+/// default chunk materials.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct WorldPalette {
+	pub terrain: Color,
+	pub leaf: Color,
+	pub bark: Color,
+	/// Walls and other built structures
+	pub structure: Color,
+	pub sky: Color,
+	pub fog: Color,
+}
+
+impl Default for WorldPalette {
+	fn default() -> Self {
+		PalettePreset::default().palette()
+	}
+}
+
+impl WorldPalette {
+	/// Blends each color towards another palette, in linear space.
+	pub fn lerp(&self, other: &Self, t: f32) -> Self {
+		let mix = |a: Color, b: Color| Color::from(a.to_linear().mix(&b.to_linear(), t));
+		Self {
+			terrain: mix(self.terrain, other.terrain),
+			leaf: mix(self.leaf, other.leaf),
+			bark: mix(self.bark, other.bark),
+			structure: mix(self.structure, other.structure),
+			sky: mix(self.sky, other.sky),
+			fog: mix(self.fog, other.fog),
+		}
+	}
+
+	pub fn color(&self, role: PaletteRole) -> Color {
+		match role {
+			PaletteRole::Terrain => self.terrain,
+			PaletteRole::Leaf => self.leaf,
+			PaletteRole::Bark => self.bark,
+			PaletteRole::Structure => self.structure,
+		}
+	}
+
+	/// A palette color as the base color of the built-in materials.
+	pub fn base_color(&self, role: PaletteRole) -> Vec4 {
+		Vec4::from_array(self.color(role).to_srgba().to_f32_array())
+	}
+}
+
+/// Which palette color a material takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaletteRole {
+	Terrain,
+	Leaf,
+	Bark,
+	Structure,
+}
+
+/// Preset palettes.
+///
+/// Switching presets blends the [WorldPalette] to the new preset over the transition time
+/// of the [WorldPalettePlugin].
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PalettePreset {
+	/// Pale sand and bright greens under a clear sky.
+	#[default]
+	Temperate,
+	/// Warm ochres and rust leaves.
+	Autumn,
+	/// Bleached sand and olive scrub under a hazy sky.
+	Arid,
+	/// Snow and dark evergreens under a grey sky.
+	Winter,
+}
+
+impl PalettePreset {
+	pub const ALL: [PalettePreset; 4] = [
+		PalettePreset::Temperate,
+		PalettePreset::Autumn,
+		PalettePreset::Arid,
+		PalettePreset::Winter,
+	];
+
+	/// The preset after this one, wrapping around.
+	pub fn next(self) -> Self {
+		let index = Self::ALL.iter().position(|preset| *preset == self).unwrap_or(0);
+		Self::ALL[(index + 1) % Self::ALL.len()]
+	}
+
+	pub fn palette(self) -> WorldPalette {
+		match self {
+			PalettePreset::Temperate => WorldPalette {
+				terrain: Color::srgb(0.89, 0.886, 0.604),
+				leaf: Color::srgb(0.2, 0.8, 0.3),
+				bark: Color::srgb(0.52, 0.38, 0.24),
+				structure: Color::srgb(0.89, 0.886, 0.604),
+				sky: Color::hsla(201.0, 0.69, 0.62, 1.0),
+				fog: Color::hsla(201.0, 0.4, 0.8, 1.0),
+			},
+			PalettePreset::Autumn => WorldPalette {
+				terrain: Color::srgb(0.78, 0.64, 0.42),
+				leaf: Color::srgb(0.85, 0.42, 0.15),
+				bark: Color::srgb(0.4, 0.27, 0.18),
+				structure: Color::srgb(0.82, 0.74, 0.6),
+				sky: Color::hsla(35.0, 0.45, 0.7, 1.0),
+				fog: Color::hsla(35.0, 0.3, 0.82, 1.0),
+			},
+			PalettePreset::Arid => WorldPalette {
+				terrain: Color::srgb(0.93, 0.8, 0.58),
+				leaf: Color::srgb(0.5, 0.56, 0.28),
+				bark: Color::srgb(0.6, 0.48, 0.34),
+				structure: Color::srgb(0.88, 0.72, 0.55),
+				sky: Color::hsla(195.0, 0.35, 0.78, 1.0),
+				fog: Color::hsla(40.0, 0.35, 0.85, 1.0),
+			},
+			PalettePreset::Winter => WorldPalette {
+				terrain: Color::srgb(0.93, 0.95, 0.98),
+				leaf: Color::srgb(0.12, 0.35, 0.25),
+				bark: Color::srgb(0.3, 0.24, 0.2),
+				structure: Color::srgb(0.7, 0.7, 0.72),
+				sky: Color::hsla(210.0, 0.15, 0.75, 1.0),
+				fog: Color::hsla(210.0, 0.1, 0.88, 1.0),
+			},
+		}
+	}
+}
+
+/// Materials recolored from the [WorldPalette], with the role each takes.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PaletteMaterials {
+	edge: Vec<(Handle<EdgeMaterial>, PaletteRole)>,
+	leaf: Vec<(Handle<LeafMaterial>, PaletteRole)>,
+}
+
+impl PaletteMaterials {
+	pub fn track_edge(&mut self, material: Handle<EdgeMaterial>, role: PaletteRole) {
+		self.edge.push((material, role));
+	}
+
+	pub fn track_leaf(&mut self, material: Handle<LeafMaterial>, role: PaletteRole) {
+		self.leaf.push((material, role));
+	}
+}
+
+/// A blend from one palette to another.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct PaletteTransition {
+	pub from: WorldPalette,
+	pub to: WorldPalette,
+	pub elapsed: f32,
+	pub duration: f32,
+}
+
+/// Key that cycles through the palette presets.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct PaletteCycleKey(pub KeyCode);
+
+/// Applies the [WorldPalette] and blends it between [PalettePreset]s when the preset changes.
+#[derive(Debug, Clone)]
+pub struct WorldPalettePlugin {
+	pub preset: PalettePreset,
+	pub cycle_key: Option<KeyCode>,
+	/// Seconds to blend between presets
+	pub transition_secs: f32,
+}
+
+impl Default for WorldPalettePlugin {
+	fn default() -> Self {
+		Self { preset: PalettePreset::default(), cycle_key: None, transition_secs: 1.5 }
+	}
+}
+
+impl WorldPalettePlugin {
+	pub fn new(preset: PalettePreset) -> Self {
+		Self { preset, ..default() }
+	}
+
+	pub fn with_cycle_key(mut self, key: KeyCode) -> Self {
+		self.cycle_key = Some(key);
+		self
+	}
+
+	pub fn with_transition_secs(mut self, transition_secs: f32) -> Self {
+		self.transition_secs = transition_secs;
+		self
+	}
+}
+
+/// Seconds to blend between presets.
+#[derive(Resource, Debug, Clone, Copy)]
+struct PaletteTransitionSecs(f32);
+
+impl Plugin for WorldPalettePlugin {
+	fn build(&self, app: &mut App) {
+		app.insert_resource(self.preset)
+			.insert_resource(self.preset.palette())
+			.insert_resource(PaletteTransitionSecs(self.transition_secs))
+			.init_resource::<PaletteMaterials>()
+			.add_systems(
+				Update,
+				(
+					start_palette_transition.run_if(resource_changed::<PalettePreset>),
+					advance_palette_transition.run_if(resource_exists::<PaletteTransition>),
+					apply_world_palette.run_if(resource_changed::<WorldPalette>),
+				)
+					.chain(),
+			);
+
+		if let Some(key) = self.cycle_key {
+			app.insert_resource(PaletteCycleKey(key))
+				.add_systems(Update, cycle_palette_preset.before(start_palette_transition));
+		}
+	}
+}
+
+/// Starts blending the current palette towards the new preset.
+fn start_palette_transition(
+	mut commands: Commands,
+	preset: Res<PalettePreset>,
+	palette: Res<WorldPalette>,
+	secs: Res<PaletteTransitionSecs>,
+) {
+	if preset.is_added() {
+		return;
+	}
+	log::info!("Blending to palette preset {:?}", *preset);
+	commands.insert_resource(PaletteTransition {
+		from: *palette,
+		to: preset.palette(),
+		elapsed: 0.0,
+		duration: secs.0,
+	});
+}
+
+/// Advances the blend, removing it once the target palette is reached.
+pub fn advance_palette_transition(
+	mut commands: Commands,
+	time: Res<Time>,
+	mut transition: ResMut<PaletteTransition>,
+	mut palette: ResMut<WorldPalette>,
+) {
+	transition.elapsed += time.delta_secs();
+	let t = if transition.duration > 0.0 {
+		(transition.elapsed / transition.duration).min(1.0)
+	} else {
+		1.0
+	};
+	if t >= 1.0 {
+		*palette = transition.to;
+		commands.remove_resource::<PaletteTransition>();
+	} else {
+		*palette = transition.from.lerp(&transition.to, t);
+	}
+}
+
+/// Recolors the sky, fog, default chunk materials and tracked materials from the palette.
+pub fn apply_world_palette(
+	palette: Res<WorldPalette>,
+	palette_materials: Res<PaletteMaterials>,
+	chunk_materials: Option<Res<ChunkMaterialRegistry>>,
+	mut edge_materials: Option<ResMut<Assets<EdgeMaterial>>>,
+	mut leaf_materials: Option<ResMut<Assets<LeafMaterial>>>,
+	mut clear_color: Option<ResMut<ClearColor>>,
+	mut fog_query: Query<&mut DistanceFog>,
+) {
+	if let Some(clear_color) = clear_color.as_mut() {
+		clear_color.0 = palette.sky;
+	}
+	for mut fog in &mut fog_query {
+		fog.color = palette.fog;
+	}
+
+	if let Some(materials) = edge_materials.as_mut() {
+		let chunk_defaults = chunk_materials.iter().flat_map(|registry| {
+			[ChunkKind::Cascade, ChunkKind::Grid]
+				.into_iter()
+				.filter_map(|kind| registry.get(kind, ChunkTag::DEFAULT))
+				.map(|handle| (handle, PaletteRole::Terrain))
+				.collect::<Vec<_>>()
+		});
+		for (handle, role) in palette_materials.edge.iter().cloned().chain(chunk_defaults) {
+			if let Some(material) = materials.get_mut(&handle) {
+				material.base_color = palette.base_color(role);
+			}
+		}
+	}
+	if let Some(materials) = leaf_materials.as_mut() {
+		for (handle, role) in &palette_materials.leaf {
+			if let Some(material) = materials.get_mut(handle) {
+				material.base_color = palette.base_color(*role);
+			}
+		}
+	}
+}
+
+/// Advances to the next preset when the cycle key is pressed.
+pub fn cycle_palette_preset(
+	keyboard_input: Res<ButtonInput<KeyCode>>,
+	cycle_key: Res<PaletteCycleKey>,
+	mut preset: ResMut<PalettePreset>,
+) {
+	if keyboard_input.just_pressed(cycle_key.0) {
+		*preset = preset.next();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	#[test]
+	fn test_preset_switch_blends_materials() {
+		let mut app = App::new();
+		app.add_plugins((AssetPlugin::default(), bevy::time::TimePlugin))
+			.init_asset::<EdgeMaterial>()
+			.init_asset::<LeafMaterial>()
+			.insert_resource(ClearColor::default())
+			.insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(
+				Duration::from_secs_f32(0.5),
+			))
+			.add_plugins(
+				WorldPalettePlugin::new(PalettePreset::Temperate).with_transition_secs(1.0),
+			);
+
+		let leaf = app
+			.world_mut()
+			.resource_mut::<Assets<LeafMaterial>>()
+			.add(LeafMaterial::new(Vec4::ZERO));
+		app.world_mut()
+			.resource_mut::<PaletteMaterials>()
+			.track_leaf(leaf.clone(), PaletteRole::Leaf);
+		app.update();
+
+		let temperate = PalettePreset::Temperate.palette();
+		let winter = PalettePreset::Winter.palette();
+		let leaf_color = |app: &App| {
+			app.world().resource::<Assets<LeafMaterial>>().get(&leaf).map(|m| m.base_color)
+		};
+		assert_eq!(leaf_color(&app), Some(temperate.base_color(PaletteRole::Leaf)));
+		assert_eq!(app.world().resource::<ClearColor>().0, temperate.sky);
+
+		// Halfway through the blend, then at the new preset
+		app.insert_resource(PalettePreset::Winter);
+		app.update();
+		app.update();
+		let halfway = temperate.lerp(&winter, 0.5);
+		assert_eq!(*app.world().resource::<WorldPalette>(), halfway);
+		assert_eq!(leaf_color(&app), Some(halfway.base_color(PaletteRole::Leaf)));
+
+		app.update();
+		app.update();
+		assert_eq!(*app.world().resource::<WorldPalette>(), winter);
+		assert!(!app.world().contains_resource::<PaletteTransition>());
+		assert_eq!(app.world().resource::<ClearColor>().0, winter.sky);
+	}
+}
@@ -0,0 +1,508 @@
+use crate::ambience::ChunkFeatures;
+use crate::cascade::CascadeChunk;
+use crate::chunk::TerrainChunk;
+use crate::chunk_manager::SdfResource;
+use crate::material::ChunkTag;
+use crate::wind::WindField;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// When an emitter shows its particles.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ParticleHours {
+	#[default]
+	Always,
+	Day,
+	Night,
+}
+
+impl ParticleHours {
+	pub fn shows(&self, night: bool) -> bool {
+		match self {
+			ParticleHours::Always => true,
+			ParticleHours::Day => !night,
+			ParticleHours::Night => night,
+		}
+	}
+}
+
+/// Particles floating in the air of every chunk with one of its tags.
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+	/// Chunks with any of these tags emit the particles, or every chunk if there are none
+	pub tags: Vec<ChunkTag>,
+	pub color: Color,
+	/// How brightly the particles glow, such as fireflies at night
+	pub glow: f32,
+	pub size: f32,
+	/// Particles in a chunk right by the camera, thinning out toward the edge of the radius
+	pub per_chunk: usize,
+	/// Share of the wind's velocity the particles are carried at
+	pub drift: f32,
+	/// Velocity of the particles in still air, such as falling snow
+	pub settle: Vec3,
+	/// Speed at which the particles wander about their path
+	pub flutter: f32,
+	/// Lowest chunk the particles show in, for flurries on the peaks
+	pub min_altitude: Option<f32>,
+	pub hours: ParticleHours,
+	material: Option<Handle<StandardMaterial>>,
+}
+
+impl ParticleEmitter {
+	pub fn new(tags: Vec<ChunkTag>, color: Color, size: f32, per_chunk: usize) -> Self {
+		Self {
+			tags,
+			color,
+			glow: 0.0,
+			size,
+			per_chunk,
+			drift: 0.5,
+			settle: Vec3::ZERO,
+			flutter: 0.2,
+			min_altitude: None,
+			hours: ParticleHours::Always,
+			material: None,
+		}
+	}
+
+	/// Pollen and dust motes hanging in the light over the tags, such as forests.
+	pub fn motes(tags: Vec<ChunkTag>) -> Self {
+		Self::new(tags, Color::srgb(0.95, 0.9, 0.65), 0.04, 48)
+			.with_drift(0.3)
+			.with_settle(Vec3::new(0.0, -0.02, 0.0))
+			.with_hours(ParticleHours::Day)
+	}
+
+	/// Snow flurries blown across chunks above the altitude.
+	pub fn flurries(min_altitude: f32) -> Self {
+		Self::new(Vec::new(), Color::WHITE, 0.06, 96)
+			.with_drift(1.0)
+			.with_settle(Vec3::new(0.0, -0.8, 0.0))
+			.with_flutter(0.4)
+			.with_min_altitude(min_altitude)
+	}
+
+	/// Fireflies wandering over the tags at night.
+	pub fn fireflies(tags: Vec<ChunkTag>) -> Self {
+		Self::new(tags, Color::srgb(0.85, 1.0, 0.35), 0.05, 16)
+			.with_glow(8.0)
+			.with_drift(0.1)
+			.with_flutter(0.6)
+			.with_hours(ParticleHours::Night)
+	}
+
+	pub fn with_glow(mut self, glow: f32) -> Self {
+		self.glow = glow;
+		self
+	}
+
+	pub fn with_drift(mut self, drift: f32) -> Self {
+		self.drift = drift;
+		self
+	}
+
+	pub fn with_settle(mut self, settle: Vec3) -> Self {
+		self.settle = settle;
+		self
+	}
+
+	pub fn with_flutter(mut self, flutter: f32) -> Self {
+		self.flutter = flutter;
+		self
+	}
+
+	pub fn with_min_altitude(mut self, min_altitude: f32) -> Self {
+		self.min_altitude = Some(min_altitude);
+		self
+	}
+
+	pub fn with_hours(mut self, hours: ParticleHours) -> Self {
+		self.hours = hours;
+		self
+	}
+
+	/// Whether a chunk emits the particles, at night or by day.
+	pub fn emits(&self, chunk: &CascadeChunk, features: &ChunkFeatures, night: bool) -> bool {
+		self.hours.shows(night)
+			&& self
+				.min_altitude
+				.is_none_or(|altitude| chunk.origin.y + chunk.size.y >= altitude)
+			&& (self.tags.is_empty() || self.tags.iter().any(|tag| features.has(*tag)))
+	}
+}
+
+/// Ambient particles spawned into the chunks around the camera by their [ChunkFeatures].
+///
+/// Chunks emit fewer particles the further they are from the camera, none past the radius,
+/// and the nearest chunks take theirs first until the budget runs out. The particles are
+/// children of their chunk, so they are cleaned up as it unloads, and drift on the
+/// [WindField] within it.
+#[derive(Resource)]
+pub struct AmbientParticles<S: Sdf + Send + Sync> {
+	/// Distance from the camera within which chunks emit
+	pub radius: f32,
+	/// Most particles alive at once
+	pub budget: usize,
+	/// Whether it is night, for emitters that show by [ParticleHours]
+	pub night: bool,
+	emitters: Vec<ParticleEmitter>,
+	mesh: Option<Handle<Mesh>>,
+	shown: usize,
+	/// Marker for the SDF whose chunks emit
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for AmbientParticles<S> {
+	fn default() -> Self {
+		Self {
+			radius: 48.0,
+			budget: 2_000,
+			night: false,
+			emitters: Vec::new(),
+			mesh: None,
+			shown: 0,
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> AmbientParticles<S> {
+	pub fn with_radius(mut self, radius: f32) -> Self {
+		self.radius = radius;
+		self
+	}
+
+	pub fn with_budget(mut self, budget: usize) -> Self {
+		self.budget = budget;
+		self
+	}
+
+	pub fn with_emitter(mut self, emitter: ParticleEmitter) -> Self {
+		self.emitters.push(emitter);
+		self
+	}
+
+	pub fn emitters(&self) -> &[ParticleEmitter] {
+		&self.emitters
+	}
+
+	/// Particles alive after the last [emit_ambient_particles].
+	pub fn shown(&self) -> usize {
+		self.shown
+	}
+
+	/// Particles each chunk emits by emitter index, nearest chunks first until the budget
+	/// runs out.
+	pub fn chunk_budgets<'a>(
+		&self,
+		position: Vec3,
+		chunks: impl IntoIterator<Item = (Entity, &'a CascadeChunk, &'a ChunkFeatures)>,
+	) -> HashMap<(Entity, usize), usize> {
+		let mut chunks: Vec<_> = chunks
+			.into_iter()
+			.map(|(entity, chunk, features)| {
+				let nearest = position.clamp(chunk.origin, chunk.origin + chunk.size);
+				(nearest.distance(position), entity, chunk, features)
+			})
+			.filter(|(distance, ..)| *distance < self.radius)
+			.collect();
+		chunks.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+		let mut budgets = HashMap::new();
+		let mut remaining = self.budget;
+		for (distance, entity, chunk, features) in chunks {
+			let nearness = 1.0 - distance / self.radius;
+			for (index, emitter) in self.emitters.iter().enumerate() {
+				if !emitter.emits(chunk, features, self.night) {
+					continue;
+				}
+				let count = ((emitter.per_chunk as f32 * nearness).ceil() as usize).min(remaining);
+				if count > 0 {
+					budgets.insert((entity, index), count);
+					remaining -= count;
+				}
+			}
+		}
+		budgets
+	}
+}
+
+/// The particles of one emitter in a chunk, spawned as a child of the chunk.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkParticles {
+	pub emitter: usize,
+}
+
+/// A particle of a [ChunkParticles] group, kept within the bounds of its chunk.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct AmbientParticle {
+	pub emitter: usize,
+	/// Size of the chunk the particle wraps around in
+	pub bounds: Vec3,
+	phase: f32,
+}
+
+/// A point in the unit cube, scattered by the chunk, emitter and particle.
+fn scatter(chunk: &CascadeChunk, emitter: usize, particle: usize) -> Vec3 {
+	let seed = chunk.origin.to_array().iter().fold(emitter as u32 ^ 0x27D4_EB2F, |seed, x| {
+		(seed ^ x.to_bits()).wrapping_mul(0x9E37_79B9).rotate_left(13)
+	}) ^ (particle as u32).wrapping_mul(0x85EB_CA6B);
+	let unit = |salt: u32| {
+		let hash = (seed ^ salt).wrapping_mul(0xC2B2_AE35);
+		let hash = (hash ^ (hash >> 16)).wrapping_mul(0x27D4_EB2F);
+		((hash ^ (hash >> 15)) >> 8) as f32 / (1 << 24) as f32
+	};
+	Vec3::new(unit(0x68E3_1DA4), unit(0xB529_7A4D), unit(0x1B56_C4E9))
+}
+
+/// Spawns and despawns the particles of the chunks around the camera to match their budgets.
+pub fn emit_ambient_particles<S: Sdf + Send + Sync + 'static>(
+	mut commands: Commands,
+	sdf_resource: Res<SdfResource<S>>,
+	mut particles: ResMut<AmbientParticles<S>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<StandardMaterial>>,
+	camera_query: Query<&Transform, With<Camera3d>>,
+	chunks: Query<(Entity, &TerrainChunk, &ChunkFeatures)>,
+	groups: Query<(Entity, &ChunkParticles, &ChildOf, Option<&Children>)>,
+) {
+	let Ok(camera_transform) = camera_query.single() else {
+		return;
+	};
+
+	let position = sdf_resource.transform.to_local(camera_transform.translation);
+	let mut budgets = particles.chunk_budgets(
+		position,
+		chunks.iter().map(|(entity, chunk, features)| (entity, &chunk.chunk, features)),
+	);
+	particles.shown = budgets.values().sum();
+
+	let particles = particles.into_inner();
+	let mesh = particles
+		.mesh
+		.get_or_insert_with(|| meshes.add(Sphere::new(0.5).mesh().uv(6, 4)))
+		.clone();
+
+	// Groups already in the world grow or shrink to their budget, or go once it is spent
+	for (group, chunk_particles, child_of, children) in &groups {
+		let chunk_entity = child_of.parent();
+		let count = budgets.remove(&(chunk_entity, chunk_particles.emitter)).unwrap_or(0);
+		let (Ok((_, chunk, _)), Some(emitter), true) = (
+			chunks.get(chunk_entity),
+			particles.emitters.get_mut(chunk_particles.emitter),
+			count > 0,
+		) else {
+			commands.entity(group).despawn();
+			continue;
+		};
+
+		let children = children.map_or(&[][..], |children| &children[..]);
+		for extra in children.iter().skip(count) {
+			commands.entity(*extra).despawn();
+		}
+		let material = emitter_material(emitter, &mut materials);
+		for particle in children.len()..count {
+			let bundle =
+				particle_bundle(&chunk.chunk, chunk_particles.emitter, emitter, particle, &mesh);
+			commands.spawn((bundle, MeshMaterial3d(material.clone()), ChildOf(group)));
+		}
+	}
+
+	for ((chunk_entity, index), count) in budgets {
+		let (Ok((_, chunk, _)), Some(emitter)) =
+			(chunks.get(chunk_entity), particles.emitters.get_mut(index))
+		else {
+			continue;
+		};
+		let material = emitter_material(emitter, &mut materials);
+		let group = commands
+			.spawn((
+				ChunkParticles { emitter: index },
+				Transform::IDENTITY,
+				Visibility::default(),
+				ChildOf(chunk_entity),
+			))
+			.id();
+		for particle in 0..count {
+			let bundle = particle_bundle(&chunk.chunk, index, emitter, particle, &mesh);
+			commands.spawn((bundle, MeshMaterial3d(material.clone()), ChildOf(group)));
+		}
+	}
+}
+
+/// The shared material of the emitter's particles, created on first use.
+fn emitter_material(
+	emitter: &mut ParticleEmitter,
+	materials: &mut Assets<StandardMaterial>,
+) -> Handle<StandardMaterial> {
+	let (color, glow) = (emitter.color, emitter.glow);
+	emitter
+		.material
+		.get_or_insert_with(|| {
+			materials.add(StandardMaterial {
+				base_color: color,
+				emissive: color.to_linear() * glow,
+				unlit: true,
+				..default()
+			})
+		})
+		.clone()
+}
+
+fn particle_bundle(
+	chunk: &CascadeChunk,
+	index: usize,
+	emitter: &ParticleEmitter,
+	particle: usize,
+	mesh: &Handle<Mesh>,
+) -> impl Bundle {
+	// Golden angle steps, so neighbouring particles flutter out of step
+	let phase = particle as f32 * 2.399;
+	(
+		AmbientParticle { emitter: index, bounds: chunk.size, phase },
+		Mesh3d(mesh.clone()),
+		Transform::from_translation(scatter(chunk, index, particle) * chunk.size)
+			.with_scale(Vec3::splat(emitter.size)),
+	)
+}
+
+/// Carries the particles on the [WindField], if there is one, wrapping them around their chunk.
+pub fn drift_ambient_particles<S: Sdf + Send + Sync + 'static>(
+	time: Res<Time>,
+	wind: Option<Res<WindField>>,
+	sdf_resource: Res<SdfResource<S>>,
+	particles: Res<AmbientParticles<S>>,
+	mut particle_query: Query<(&mut Transform, &GlobalTransform, &AmbientParticle)>,
+) {
+	let (now, dt) = (time.elapsed_secs(), time.delta_secs());
+	for (mut transform, global, particle) in &mut particle_query {
+		let Some(emitter) = particles.emitters.get(particle.emitter) else {
+			continue;
+		};
+		let world = global.translation();
+		let gust = wind.as_ref().map_or(Vec3::ZERO, |wind| wind.sample(world, now));
+		let phase = particle.phase;
+		let flutter = Vec3::new(
+			(now * 1.3 + phase).sin(),
+			(now * 0.9 + phase * 1.7).sin() * 0.5,
+			(now * 1.1 + phase * 2.3).cos(),
+		) * emitter.flutter;
+		let velocity = gust * emitter.drift + emitter.settle + flutter;
+
+		let local = &sdf_resource.transform;
+		let step = local.to_local(world + velocity * dt) - local.to_local(world);
+		transform.translation = (transform.translation + step).rem_euclid(particle.bounds);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::Ground;
+	use bevy::time::TimeUpdateStrategy;
+	use std::time::Duration;
+
+	const FOREST: ChunkTag = ChunkTag("forest");
+	const MEADOW: ChunkTag = ChunkTag("meadow");
+
+	fn features(tag: ChunkTag) -> ChunkFeatures {
+		ChunkFeatures { tags: vec![tag] }
+	}
+
+	#[test]
+	fn test_nearest_chunks_take_the_budget() {
+		let mut world = World::new();
+		let [near, middle, far, peak] = [(); 4].map(|_| world.spawn_empty().id());
+		let chunks = [
+			(near, CascadeChunk::cube(Vec3::new(0.0, -4.0, 0.0), 8.0, 2), features(FOREST)),
+			(middle, CascadeChunk::cube(Vec3::new(16.0, -4.0, 0.0), 8.0, 2), features(FOREST)),
+			(far, CascadeChunk::cube(Vec3::new(64.0, -4.0, 0.0), 8.0, 2), features(FOREST)),
+			(peak, CascadeChunk::cube(Vec3::new(0.0, 28.0, 0.0), 8.0, 2), features(MEADOW)),
+		];
+		let budgets = |particles: &AmbientParticles<Ground>| {
+			particles.chunk_budgets(
+				Vec3::new(4.0, 0.0, 4.0),
+				chunks.iter().map(|(entity, chunk, features)| (*entity, chunk, features)),
+			)
+		};
+
+		let particles = AmbientParticles::<Ground>::default()
+			.with_radius(40.0)
+			.with_emitter(ParticleEmitter::motes(vec![FOREST]))
+			.with_emitter(ParticleEmitter::flurries(30.0))
+			.with_emitter(ParticleEmitter::fireflies(vec![FOREST]));
+		let by_day = budgets(&particles);
+		assert_eq!(by_day.get(&(near, 0)), Some(&48));
+		// Further chunks thin out, and those past the radius don't emit
+		let middle_motes = by_day[&(middle, 0)];
+		assert!(middle_motes > 0 && middle_motes < 48, "{middle_motes} motes in the middle");
+		assert!(!by_day.contains_key(&(far, 0)));
+		// Only the peak is high enough for flurries
+		assert_eq!(by_day.keys().filter(|(_, emitter)| *emitter == 1).count(), 1);
+		assert!(by_day.contains_key(&(peak, 1)));
+		assert!(!by_day.keys().any(|(_, emitter)| *emitter == 2));
+
+		let at_night = budgets(&AmbientParticles { night: true, ..particles.with_budget(20) });
+		assert_eq!(at_night.get(&(near, 2)), Some(&16));
+		assert!(!at_night.keys().any(|(_, emitter)| *emitter == 0));
+		assert_eq!(at_night.values().sum::<usize>(), 20);
+	}
+
+	#[test]
+	fn test_particles_drift_and_unload_with_their_chunk() {
+		let mut app = App::new();
+		app.add_plugins((AssetPlugin::default(), bevy::time::TimePlugin))
+			.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)))
+			.init_asset::<Mesh>()
+			.init_asset::<StandardMaterial>()
+			.insert_resource(SdfResource::new(Ground(0.0)))
+			.insert_resource(WindField::new(Vec3::new(4.0, 0.0, 0.0)))
+			.insert_resource(
+				AmbientParticles::<Ground>::default()
+					.with_radius(32.0)
+					.with_emitter(ParticleEmitter::motes(vec![FOREST])),
+			)
+			.add_systems(
+				Update,
+				(emit_ambient_particles::<Ground>, drift_ambient_particles::<Ground>).chain(),
+			);
+		let chunk = app
+			.world_mut()
+			.spawn((
+				TerrainChunk { chunk: CascadeChunk::cube(Vec3::new(0.0, -4.0, 0.0), 8.0, 2) },
+				features(FOREST),
+				Transform::from_xyz(0.0, -4.0, 0.0),
+			))
+			.id();
+		let camera = app
+			.world_mut()
+			.spawn((Camera3d::default(), Transform::from_xyz(4.0, 0.0, 4.0)))
+			.id();
+
+		let mut particle_query = app.world_mut().query::<(&Transform, &AmbientParticle)>();
+		for _ in 0..20 {
+			app.update();
+		}
+		let particles: Vec<_> = particle_query.iter(app.world()).collect();
+		assert_eq!(particles.len(), 48);
+		for (transform, particle) in particles {
+			let p = transform.translation;
+			assert!(p.cmpge(Vec3::ZERO).all() && p.cmplt(particle.bounds).all(), "{p} escaped");
+		}
+		assert_eq!(app.world().resource::<AmbientParticles<Ground>>().shown(), 48);
+
+		// Backing away thins the chunk out
+		app.world_mut().entity_mut(camera).insert(Transform::from_xyz(4.0, 0.0, 28.0));
+		app.update();
+		let thinned = particle_query.iter(app.world()).count();
+		assert!(thinned > 0 && thinned < 48, "{thinned} particles left");
+
+		app.world_mut().entity_mut(chunk).despawn();
+		app.update();
+		assert_eq!(particle_query.iter(app.world()).count(), 0);
+		assert_eq!(app.world_mut().query::<&ChunkParticles>().iter(app.world()).count(), 0);
+	}
+}
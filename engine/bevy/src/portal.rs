@@ -0,0 +1,361 @@
+use crate::cascade::CascadeChunk;
+use crate::chunk::{ChunkConfig, LoadedChunks, TerrainChunk};
+use crate::cpu::MeshData;
+use crate::history::WorldEdit;
+use crate::regeneration::{queue_chunks_in_regions, ChunkRegenerationQueue};
+use bevy::math::bounding::{Aabb3d, IntersectsVolume};
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// A region in which terrain triangles are discarded during meshing.
+///
+/// Unlike subtracting from the SDF, which rounds the edges of the cut, this leaves a clean
+/// opening for interiors and dungeons authored as separate scenes to connect to.
+#[derive(Clone)]
+pub enum PortalVolume {
+	Aabb(Aabb3d),
+	/// The inside of an SDF, within bounds containing it
+	Sdf {
+		sdf: Arc<dyn Sdf>,
+		bounds: Aabb3d,
+	},
+}
+
+impl PortalVolume {
+	pub fn aabb(min: Vec3, max: Vec3) -> Self {
+		Self::Aabb(Aabb3d { min: min.into(), max: max.into() })
+	}
+
+	pub fn sdf(sdf: impl Sdf + 'static, bounds: Aabb3d) -> Self {
+		Self::Sdf { sdf: Arc::new(sdf), bounds }
+	}
+
+	/// The bounds of the volume, used to find the chunks it affects.
+	pub fn bounds(&self) -> Aabb3d {
+		match self {
+			PortalVolume::Aabb(aabb) => *aabb,
+			PortalVolume::Sdf { bounds, .. } => *bounds,
+		}
+	}
+
+	pub fn contains(&self, point: Vec3) -> bool {
+		let bounds = self.bounds();
+		let in_bounds =
+			point.cmpge(bounds.min.into()).all() && point.cmple(bounds.max.into()).all();
+		match self {
+			PortalVolume::Aabb(_) => in_bounds,
+			PortalVolume::Sdf { sdf, .. } => in_bounds && sdf.distance(point) < 0.0,
+		}
+	}
+}
+
+/// Identifies a registered [PortalVolume].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PortalId(u64);
+
+/// The portal volumes cut into the terrain of an SDF, in the SDF's local space like its chunks.
+///
+/// Chunks overlapping a volume that is added or removed are regenerated by [queue_portal_chunks].
+#[derive(Resource)]
+pub struct PortalVolumes<S: Sdf + Send + Sync> {
+	volumes: HashMap<PortalId, PortalVolume>,
+	next_id: u64,
+	/// Bounds of the volumes added or removed since their chunks were queued
+	changed: Vec<Aabb3d>,
+	/// Marker for the SDF whose terrain the volumes cut into
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for PortalVolumes<S> {
+	fn default() -> Self {
+		Self { volumes: HashMap::new(), next_id: 0, changed: Vec::new(), sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync> PortalVolumes<S> {
+	pub fn insert(&mut self, volume: PortalVolume) -> PortalId {
+		let id = PortalId(self.next_id);
+		self.next_id += 1;
+		self.changed.push(volume.bounds());
+		self.volumes.insert(id, volume);
+		id
+	}
+
+	pub fn remove(&mut self, id: PortalId) -> Option<PortalVolume> {
+		let volume = self.volumes.remove(&id)?;
+		self.changed.push(volume.bounds());
+		Some(volume)
+	}
+
+	/// Registers a removed volume again under its old id.
+	pub fn restore(&mut self, id: PortalId, volume: PortalVolume) {
+		self.changed.push(volume.bounds());
+		self.volumes.insert(id, volume);
+	}
+
+	pub fn get(&self, id: PortalId) -> Option<&PortalVolume> {
+		self.volumes.get(&id)
+	}
+
+	pub fn len(&self) -> usize {
+		self.volumes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.volumes.is_empty()
+	}
+
+	/// The registered volumes, shared for meshing off the main thread.
+	pub fn volumes(&self) -> Vec<PortalVolume> {
+		self.volumes.values().cloned().collect()
+	}
+}
+
+fn chunk_aabb(origin: Vec3, size: Vec3) -> Aabb3d {
+	Aabb3d { min: origin.into(), max: (origin + size).into() }
+}
+
+/// Discards the triangles of a chunk mesh whose centroid is inside any of the volumes,
+/// and the vertices only they used. Returns `None` if no triangles remain.
+pub fn carve_portals(
+	chunk: &CascadeChunk,
+	mesh: MeshData,
+	volumes: &[PortalVolume],
+) -> Option<MeshData> {
+	let aabb = chunk_aabb(chunk.origin, chunk.size);
+	let volumes: Vec<_> =
+		volumes.iter().filter(|volume| volume.bounds().intersects(&aabb)).collect();
+	if volumes.is_empty() {
+		return (!mesh.is_empty()).then_some(mesh);
+	}
+
+	let position = |index: u32| chunk.origin + Vec3::from_array(mesh.positions[index as usize]);
+	let kept: Vec<u32> = mesh
+		.indices
+		.chunks_exact(3)
+		.filter(|triangle| {
+			let centroid = triangle.iter().map(|&index| position(index)).sum::<Vec3>() / 3.0;
+			!volumes.iter().any(|volume| volume.contains(centroid))
+		})
+		.flatten()
+		.copied()
+		.collect();
+	if kept.is_empty() {
+		return None;
+	}
+
+	// Compact the vertices the remaining triangles use
+	let mut remap = vec![u32::MAX; mesh.positions.len()];
+	let mut carved = MeshData::default();
+	for &index in &kept {
+		let slot = &mut remap[index as usize];
+		if *slot == u32::MAX {
+			*slot = carved.positions.len() as u32;
+			carved.positions.push(mesh.positions[index as usize]);
+			carved.normals.push(mesh.normals[index as usize]);
+			carved.uvs.push(mesh.uvs[index as usize]);
+			if let Some(color) = mesh.colors.get(index as usize) {
+				carved.colors.push(*color);
+			}
+		}
+		carved.indices.push(*slot);
+	}
+	Some(carved)
+}
+
+/// Queues the loaded chunks overlapping added or removed portal volumes for regeneration.
+pub fn queue_portal_chunks<S: Sdf + Send + Sync + 'static>(
+	mut portals: ResMut<PortalVolumes<S>>,
+	mut queue: ResMut<ChunkRegenerationQueue<S>>,
+	mut loaded_chunks: ResMut<LoadedChunks>,
+	chunk_query: Query<(Entity, &TerrainChunk)>,
+	chunk_config: Res<ChunkConfig<S>>,
+) {
+	if portals.changed.is_empty() {
+		return;
+	}
+	let changed = std::mem::take(&mut portals.changed);
+	queue_chunks_in_regions(&changed, &mut queue, &mut loaded_chunks, &chunk_query, &chunk_config);
+}
+
+/// Registers a portal volume as an undoable [WorldEdit].
+pub struct InsertPortal<S: Sdf + Send + Sync> {
+	volume: PortalVolume,
+	id: Option<PortalId>,
+	/// Marker for the SDF whose terrain the volume cuts into
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> InsertPortal<S> {
+	pub fn new(volume: PortalVolume) -> Self {
+		Self { volume, id: None, sdf: PhantomData }
+	}
+
+	/// The id of the volume while the edit is applied.
+	pub fn id(&self) -> Option<PortalId> {
+		self.id
+	}
+}
+
+impl<S: Sdf + Send + Sync + 'static> WorldEdit for InsertPortal<S> {
+	fn apply(&mut self, world: &mut World) {
+		self.id = Some(world.resource_mut::<PortalVolumes<S>>().insert(self.volume.clone()));
+	}
+
+	fn revert(&mut self, world: &mut World) {
+		if let Some(id) = self.id.take() {
+			world.resource_mut::<PortalVolumes<S>>().remove(id);
+		}
+	}
+}
+
+/// Removes a portal volume as an undoable [WorldEdit]. Undoing it restores the volume
+/// under the same id.
+pub struct RemovePortal<S: Sdf + Send + Sync> {
+	id: PortalId,
+	volume: Option<PortalVolume>,
+	/// Marker for the SDF whose terrain the volume cuts into
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> RemovePortal<S> {
+	pub fn new(id: PortalId) -> Self {
+		Self { id, volume: None, sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync + 'static> WorldEdit for RemovePortal<S> {
+	fn apply(&mut self, world: &mut World) {
+		self.volume = world.resource_mut::<PortalVolumes<S>>().remove(self.id);
+	}
+
+	fn revert(&mut self, world: &mut World) {
+		if let Some(volume) = self.volume.take() {
+			world.resource_mut::<PortalVolumes<S>>().restore(self.id, volume);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::chunk_manager::manage_chunks;
+	use crate::cpu::CpuMeshGenerator;
+	use crate::mesh_checks::{check_mesh, MeshCheckConfig};
+	use crate::regeneration::{regenerate_queued_chunks, TerrainDirty};
+	use crate::test_support::{camera, ground_app, Ground};
+
+	#[test]
+	fn test_carve_portals_cuts_a_clean_hole() {
+		let chunk = CascadeChunk::cube(Vec3::new(-4.0, -4.0, -4.0), 8.0, 4);
+		let Some(mesh) = CpuMeshGenerator::generate_chunk_mesh_data(&chunk, Arc::new(Ground(0.3)))
+		else {
+			panic!("expected a ground mesh");
+		};
+		let hole = PortalVolume::aabb(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+		let far = PortalVolume::aabb(Vec3::splat(20.0), Vec3::splat(21.0));
+
+		let untouched = carve_portals(&chunk, mesh.clone(), &[far.clone()]);
+		assert_eq!(untouched.as_ref(), Some(&mesh));
+
+		let Some(carved) = carve_portals(&chunk, mesh.clone(), &[hole.clone(), far]) else {
+			panic!("expected the ground around the hole");
+		};
+		assert!(carved.triangle_count() < mesh.triangle_count());
+		assert!(carved.positions.len() < mesh.positions.len());
+		assert!(check_mesh(&carved, &MeshCheckConfig::default()).is_valid());
+		for triangle in carved.indices.chunks_exact(3) {
+			let centroid = triangle
+				.iter()
+				.map(|&index| chunk.origin + Vec3::from_array(carved.positions[index as usize]))
+				.sum::<Vec3>()
+				/ 3.0;
+			assert!(!hole.contains(centroid));
+		}
+
+		// An SDF volume cuts a round hole within its bounds
+		let sphere = sdf::SphereSdf::new(Vec3::new(0.0, 0.3, 0.0), 1.0);
+		let bounds = Aabb3d::new(Vec3::new(0.0, 0.3, 0.0), Vec3::splat(1.0));
+		let round = PortalVolume::sdf(sphere, bounds);
+		let Some(round_carved) = carve_portals(&chunk, mesh.clone(), &[round]) else {
+			panic!("expected the ground around the hole");
+		};
+		assert!(round_carved.triangle_count() > carved.triangle_count());
+		assert!(round_carved.triangle_count() < mesh.triangle_count());
+	}
+
+	#[test]
+	fn test_portal_edits_undo() {
+		let mut world = World::new();
+		world.init_resource::<PortalVolumes<Ground>>();
+		let volume = PortalVolume::aabb(Vec3::ZERO, Vec3::ONE);
+
+		let mut insert = InsertPortal::<Ground>::new(volume);
+		insert.apply(&mut world);
+		let Some(id) = insert.id() else {
+			panic!("expected the portal to be inserted");
+		};
+		let mut remove = RemovePortal::<Ground>::new(id);
+		remove.apply(&mut world);
+		assert!(world.resource::<PortalVolumes<Ground>>().is_empty());
+		remove.revert(&mut world);
+		assert_eq!(world.resource::<PortalVolumes<Ground>>().len(), 1);
+		insert.revert(&mut world);
+		assert!(world.resource::<PortalVolumes<Ground>>().is_empty());
+
+		// Each change is queued for regeneration
+		assert_eq!(world.resource::<PortalVolumes<Ground>>().changed.len(), 4);
+	}
+
+	#[test]
+	fn test_portal_changes_regenerate_overlapping_chunks() {
+		let mut app = ground_app(0.3);
+		app.add_message::<TerrainDirty>()
+			.init_resource::<PortalVolumes<Ground>>()
+			.insert_resource(ChunkRegenerationQueue::<Ground>::default().with_chunks_per_frame(100))
+			.add_systems(
+				Update,
+				(
+					manage_chunks::<Ground>,
+					queue_portal_chunks::<Ground>,
+					regenerate_queued_chunks::<Ground>,
+				)
+					.chain(),
+			);
+		let camera = camera(&mut app);
+		app.world_mut().entity_mut(camera).insert(Transform::from_xyz(0.5, 0.3, 0.5));
+		app.update();
+
+		let triangles = |app: &mut App| -> usize {
+			let handles: Vec<_> = app
+				.world_mut()
+				.query_filtered::<&Mesh3d, With<TerrainChunk>>()
+				.iter(app.world())
+				.map(|mesh| mesh.id())
+				.collect();
+			let meshes = app.world().resource::<Assets<Mesh>>();
+			handles
+				.iter()
+				.filter_map(|id| meshes.get(*id))
+				.map(|mesh| mesh.indices().map_or(0, |indices| indices.len() / 3))
+				.sum()
+		};
+		let before = triangles(&mut app);
+		assert!(before > 0);
+
+		// A hole through the center chunk only
+		let volume = PortalVolume::aabb(Vec3::new(0.2, -1.0, 0.2), Vec3::new(0.8, 1.0, 0.8));
+		let id = app.world_mut().resource_mut::<PortalVolumes<Ground>>().insert(volume);
+		app.update();
+		let carved = triangles(&mut app);
+		assert!(carved < before);
+
+		app.world_mut().resource_mut::<PortalVolumes<Ground>>().remove(id);
+		app.update();
+		assert_eq!(triangles(&mut app), before);
+		assert!(app.world().resource::<ChunkRegenerationQueue<Ground>>().is_empty());
+	}
+}
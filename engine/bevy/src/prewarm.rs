@@ -0,0 +1,228 @@
+use crate::cascade::CascadeChunk;
+use crate::chunk::{ChunkId, LoadedChunks};
+use crate::chunk_manager::ChunkSources;
+use crate::generation_pool::GenerationPool;
+use crate::occupancy::ChunkOccupancy;
+use bevy::prelude::*;
+use rayon::prelude::*;
+use sdf::Sdf;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+/// Cascade chunks generated ahead of the camera, along the path its velocity predicts.
+///
+/// [manage_chunks](crate::manage_chunks) takes the warmed meshes instead of generating them,
+/// so fast flight rarely outruns generation. Warmed chunks that fall off the predicted path are
+/// dropped, and all of them are dropped when the SDF, portals or light probes change.
+#[derive(Resource)]
+pub struct ChunkPrewarm<S: Sdf + Send + Sync> {
+	/// Seconds ahead that the camera path is predicted
+	pub horizon: f32,
+	/// Points sampled along the predicted path
+	pub samples: usize,
+	/// Most chunks warmed per frame
+	pub chunks_per_frame: usize,
+	/// Speed under which nothing is warmed
+	pub min_speed: f32,
+	/// Weight of each new frame in the smoothed velocity
+	pub smoothing: f32,
+	last_position: Option<Vec3>,
+	velocity: Vec3,
+	warmed: HashMap<ChunkId, (CascadeChunk, Option<Mesh>)>,
+	hits: u64,
+	/// Marker for the SDF whose chunks are warmed
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for ChunkPrewarm<S> {
+	fn default() -> Self {
+		Self {
+			horizon: 2.0,
+			samples: 4,
+			chunks_per_frame: 4,
+			min_speed: 0.0,
+			smoothing: 0.2,
+			last_position: None,
+			velocity: Vec3::ZERO,
+			warmed: HashMap::new(),
+			hits: 0,
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> ChunkPrewarm<S> {
+	pub fn with_horizon(mut self, horizon: f32) -> Self {
+		self.horizon = horizon;
+		self
+	}
+
+	pub fn with_samples(mut self, samples: usize) -> Self {
+		self.samples = samples;
+		self
+	}
+
+	pub fn with_chunks_per_frame(mut self, chunks_per_frame: usize) -> Self {
+		self.chunks_per_frame = chunks_per_frame;
+		self
+	}
+
+	pub fn with_min_speed(mut self, min_speed: f32) -> Self {
+		self.min_speed = min_speed;
+		self
+	}
+
+	/// The smoothed camera velocity, in the local space of the SDF
+	pub fn velocity(&self) -> Vec3 {
+		self.velocity
+	}
+
+	/// Number of warmed chunks waiting to be loaded
+	pub fn len(&self) -> usize {
+		self.warmed.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.warmed.is_empty()
+	}
+
+	/// Number of chunks loaded from warmed meshes
+	pub fn hits(&self) -> u64 {
+		self.hits
+	}
+
+	/// Updates the smoothed velocity with the camera position after dt seconds.
+	pub fn track(&mut self, position: Vec3, dt: f32) {
+		if let Some(last_position) = self.last_position.filter(|_| dt > 0.0) {
+			let velocity = (position - last_position) / dt;
+			self.velocity = self.velocity.lerp(velocity, self.smoothing.clamp(0.0, 1.0));
+		}
+		self.last_position = Some(position);
+	}
+
+	/// Points along the predicted path, nearest first, or none when the camera is slow.
+	pub fn predicted(&self, position: Vec3) -> Vec<Vec3> {
+		if self.velocity.length() <= self.min_speed || self.velocity.length() == 0.0 {
+			return Vec::new();
+		}
+		let samples = self.samples.max(1);
+		(1..=samples)
+			.map(|i| position + self.velocity * self.horizon * i as f32 / samples as f32)
+			.collect()
+	}
+
+	/// Takes the warmed mesh of the chunk, if it was warmed at the same resolution.
+	pub(crate) fn take(&mut self, id: &ChunkId, chunk: &CascadeChunk) -> Option<Option<Mesh>> {
+		let (warmed, mesh) = self.warmed.remove(id)?;
+		if warmed.res_2 != chunk.res_2 {
+			return None;
+		}
+		self.hits += 1;
+		Some(mesh)
+	}
+}
+
+/// Warms the cascade chunks along the predicted camera path, within the frame budget.
+///
+/// Chunks are generated on the far lane of the [GenerationPool] when there is one, behind the
+/// chunks that are needed now. Add it after manage_chunks.
+pub fn prewarm_chunks<S: Sdf + Send + Sync + 'static>(
+	time: Res<Time>,
+	camera_query: Query<&Transform, With<Camera3d>>,
+	mut prewarm: ResMut<ChunkPrewarm<S>>,
+	sources: ChunkSources<S>,
+	loaded_chunks: Res<LoadedChunks>,
+	generation_pool: Option<Res<GenerationPool>>,
+) {
+	let Ok(camera_transform) = camera_query.single() else {
+		return;
+	};
+
+	// Warmed meshes are stale once anything that goes into them changes
+	if sources.is_changed() {
+		prewarm.warmed.clear();
+	}
+
+	let position = sources.sdf_resource.transform.to_local(camera_transform.translation);
+	prewarm.track(position, time.delta_secs());
+
+	// The chunks along the path that aren't loaded yet, nearest point first
+	let cascade = sources.cascade();
+	// Chunks the occupancy check skips are never generated
+	let occupied = |chunk: &CascadeChunk| {
+		sources.chunk_config.occupancy.is_none_or(|check| {
+			check.classify_at(
+				sources.sdf_resource.sdf.as_ref(),
+				chunk,
+				sources.sdf_resource.iso_level,
+			) == ChunkOccupancy::Mixed
+		})
+	};
+	let mut wanted = Vec::new();
+	let mut on_path = HashSet::new();
+	for point in prewarm.predicted(position) {
+		let Ok(output) = cascade.chunks(point) else {
+			continue;
+		};
+		for chunk in output.cascade() {
+			let id = sources.chunk_config.chunk_id(&chunk);
+			if !loaded_chunks.is_loaded(id) && on_path.insert(id) && occupied(&chunk) {
+				wanted.push((id, chunk));
+			}
+		}
+	}
+
+	// Cancel the chunks the camera turned away from
+	prewarm.warmed.retain(|id, _| on_path.contains(id));
+
+	let batch: Vec<(ChunkId, CascadeChunk)> = wanted
+		.into_iter()
+		.filter(|(id, _)| !prewarm.warmed.contains_key(id))
+		.take(prewarm.chunks_per_frame)
+		.collect();
+	if batch.is_empty() {
+		return;
+	}
+
+	let pipeline = sources.pipeline();
+	let generate = |(id, chunk): &(ChunkId, CascadeChunk)| (*id, *chunk, pipeline.generate(chunk));
+	let warmed: Vec<_> = match generation_pool.as_deref() {
+		Some(pool) => pool.far.run(&batch, generate),
+		None => batch.par_iter().map(generate).collect(),
+	};
+	for (id, chunk, mesh) in warmed {
+		prewarm.warmed.insert(id, (chunk, mesh));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::chunk_manager::manage_chunks;
+	use crate::test_support::{camera, ground_app, Ground};
+	use bevy::time::TimeUpdateStrategy;
+	use std::time::Duration;
+
+	#[test]
+	fn test_flight_loads_warmed_chunks() {
+		let mut app = ground_app(0.5);
+		app.add_plugins(bevy::time::TimePlugin)
+			.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)))
+			.insert_resource(ChunkPrewarm::<Ground>::default().with_chunks_per_frame(64))
+			.add_systems(Update, (manage_chunks::<Ground>, prewarm_chunks::<Ground>).chain());
+		let camera = camera(&mut app);
+
+		// Fly at 10 units a second, a chunk per frame
+		for step in 0..20 {
+			app.world_mut()
+				.entity_mut(camera)
+				.insert(Transform::from_xyz(step as f32, 0.0, 0.0));
+			app.update();
+		}
+
+		let prewarm = app.world().resource::<ChunkPrewarm<Ground>>();
+		assert!(prewarm.velocity().x > 5.0, "Velocity is {}", prewarm.velocity());
+		assert!(prewarm.hits() > 0);
+		assert!(prewarm.warmed.keys().all(|id| id.lattice[0] > 0));
+	}
+}
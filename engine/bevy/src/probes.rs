@@ -0,0 +1,412 @@
+use crate::cascade::CascadeChunk;
+use crate::chunk::TerrainChunk;
+use crate::cpu::MeshData;
+use crate::regeneration::ChunkRegenerationQueue;
+use bevy::math::bounding::{Aabb3d, IntersectsVolume};
+use bevy::prelude::*;
+use sdf::{Sdf, Sign};
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// How [LightProbes] are placed and how their sky visibility is traced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbeBakeConfig {
+	/// Distance between neighbouring probes along each axis
+	pub spacing: f32,
+	/// Rays traced per probe: one straight up and the rest in a cone around it
+	pub ray_count: usize,
+	/// Angle of the cone rays from the vertical, in radians
+	pub ray_spread: f32,
+	/// Rays that travel this far without hitting the terrain see the sky
+	pub max_distance: f32,
+	/// Sphere-tracing steps before a ray is considered blocked
+	pub max_steps: usize,
+	/// Fraction of sky visibility lost per probe step away from an opening
+	pub falloff: f32,
+}
+
+impl Default for ProbeBakeConfig {
+	fn default() -> Self {
+		Self {
+			spacing: 1.0,
+			ray_count: 8,
+			ray_spread: 0.6,
+			max_distance: 64.0,
+			max_steps: 128,
+			falloff: 0.15,
+		}
+	}
+}
+
+impl ProbeBakeConfig {
+	pub fn with_spacing(mut self, spacing: f32) -> Self {
+		self.spacing = spacing;
+		self
+	}
+
+	pub fn with_ray_count(mut self, ray_count: usize) -> Self {
+		self.ray_count = ray_count;
+		self
+	}
+
+	pub fn with_ray_spread(mut self, ray_spread: f32) -> Self {
+		self.ray_spread = ray_spread;
+		self
+	}
+
+	pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+		self.max_distance = max_distance;
+		self
+	}
+
+	pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+		self.max_steps = max_steps;
+		self
+	}
+
+	pub fn with_falloff(mut self, falloff: f32) -> Self {
+		self.falloff = falloff;
+		self
+	}
+
+	/// The directions of the traced rays, straight up first.
+	fn ray_directions(&self) -> Vec<Vec3> {
+		let cone = self.ray_count.saturating_sub(1);
+		std::iter::once(Vec3::Y)
+			.chain((0..cone).map(|i| {
+				let angle = std::f32::consts::TAU * i as f32 / cone as f32;
+				Quat::from_rotation_y(angle) * Quat::from_rotation_z(self.ray_spread) * Vec3::Y
+			}))
+			.take(self.ray_count.max(1))
+			.collect()
+	}
+
+	/// Whether a ray sphere-traced from a point in the air reaches the sky.
+	fn trace_sky(&self, sdf: &dyn Sdf, from: Vec3, direction: Vec3) -> bool {
+		let epsilon = self.spacing * 1e-3;
+		let mut t = 0.0;
+		for _ in 0..self.max_steps {
+			if t >= self.max_distance {
+				return true;
+			}
+			let distance = sdf.distance(from + direction * t);
+			if distance < epsilon {
+				return false;
+			}
+			t += distance;
+		}
+		t >= self.max_distance
+	}
+}
+
+/// A baked probe: a point in a carved volume and how much of the sky reaches it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightProbe {
+	pub position: Vec3,
+	/// From 0 for no sky light to 1 for open sky
+	pub sky_visibility: f32,
+}
+
+/// Light probes baked in the volumes carved out of the terrain of an SDF, such as caves and
+/// tunnels, in the SDF's local space like its chunks.
+///
+/// Chunk meshes are shaded by the probes as they are generated, darkening with depth.
+/// Replacing the resource regenerates the affected chunks through [queue_light_probe_chunks].
+#[derive(Resource)]
+pub struct LightProbes<S: Sdf + Send + Sync> {
+	/// The corner of the probe lattice
+	origin: Vec3,
+	spacing: f32,
+	bounds: Aabb3d,
+	/// Sky visibility of the probes, keyed by lattice cell, shared by the clones that chunks
+	/// are generated with off the frame
+	probes: Arc<HashMap<IVec3, f32>>,
+	/// Marker for the SDF whose terrain the probes light
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Clone for LightProbes<S> {
+	fn clone(&self) -> Self {
+		Self {
+			origin: self.origin,
+			spacing: self.spacing,
+			bounds: self.bounds,
+			probes: Arc::clone(&self.probes),
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> LightProbes<S> {
+	/// Bakes probes on a lattice over the region.
+	///
+	/// Probes are placed at the points in the air under the terrain, found from the sign
+	/// intervals of each column where the SDF provides them. Each probe sees the fraction of
+	/// its rays that reach the sky, or the light spreading in from the nearest opening,
+	/// whichever is brighter.
+	pub fn bake(sdf: &S, region: Aabb3d, config: &ProbeBakeConfig) -> Self {
+		let spacing = config.spacing.max(f32::EPSILON);
+		let origin = Vec3::from(region.min);
+		let cells = ((Vec3::from(region.max) - origin) / spacing).floor().as_ivec3();
+		let position = |cell: IVec3| origin + cell.as_vec3() * spacing;
+		let in_region = |cell: IVec3| cell.cmpge(IVec3::ZERO).all() && cell.cmple(cells).all();
+		let directions = config.ray_directions();
+
+		let mut probes = HashMap::new();
+		for x in 0..=cells.x {
+			for z in 0..=cells.z {
+				let column = position(IVec3::new(x, 0, z));
+				let spans: Vec<_> = sdf
+					.sign_uniform_on_y(column.x, column.z)
+					.into_iter()
+					.map(|interval| {
+						let (min, max) = interval.open_range();
+						(min, max, interval.left.sign)
+					})
+					.collect();
+
+				for y in 0..=cells.y {
+					let cell = IVec3::new(x, y, z);
+					let point = position(cell);
+					// Skip the solid ground and the open sky without sampling
+					let Some(index) =
+						spans.iter().position(|(min, max, _)| *min < point.y && point.y < *max)
+					else {
+						continue;
+					};
+					match spans[index].2 {
+						Sign::Negative => continue,
+						Sign::Positive
+							if spans[index + 1..].iter().all(|(_, _, sign)| sign.is_positive()) =>
+						{
+							continue
+						}
+						_ => {}
+					}
+
+					if sdf.distance(point) <= 0.0 || config.trace_sky(sdf, point, Vec3::Y) {
+						continue;
+					}
+					let escaped = directions
+						.iter()
+						.filter(|direction| config.trace_sky(sdf, point, **direction))
+						.count();
+					probes.insert(cell, escaped as f32 / directions.len() as f32);
+				}
+			}
+		}
+
+		// Spread the light in from the openings, losing some with each step
+		const NEIGHBOURS: [IVec3; 6] =
+			[IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y, IVec3::Z, IVec3::NEG_Z];
+		let retained = 1.0 - config.falloff.clamp(0.0, 1.0);
+		let cells_by_probe: Vec<IVec3> = probes.keys().copied().collect();
+		for cell in &cells_by_probe {
+			let open = NEIGHBOURS.iter().map(|offset| cell + offset).any(|neighbour| {
+				in_region(neighbour)
+					&& !probes.contains_key(&neighbour)
+					&& sdf.distance(position(neighbour)) > 0.0
+			});
+			if open {
+				let visibility = probes.entry(*cell).or_default();
+				*visibility = visibility.max(retained);
+			}
+		}
+		let mut queue: VecDeque<IVec3> = cells_by_probe.into();
+		while let Some(cell) = queue.pop_front() {
+			let spread = probes[&cell] * retained;
+			for offset in NEIGHBOURS {
+				let neighbour = cell + offset;
+				if let Some(visibility) = probes.get_mut(&neighbour) {
+					if spread > *visibility + 1e-6 {
+						*visibility = spread;
+						queue.push_back(neighbour);
+					}
+				}
+			}
+		}
+
+		log::info!("Baked {} light probes", probes.len());
+		Self { origin, spacing, bounds: region, probes: Arc::new(probes), sdf: PhantomData }
+	}
+
+	pub fn len(&self) -> usize {
+		self.probes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.probes.is_empty()
+	}
+
+	/// The region the probes were baked over.
+	pub fn bounds(&self) -> Aabb3d {
+		self.bounds
+	}
+
+	pub fn probes(&self) -> impl Iterator<Item = LightProbe> + '_ {
+		self.probes.iter().map(|(cell, sky_visibility)| LightProbe {
+			position: self.origin + cell.as_vec3() * self.spacing,
+			sky_visibility: *sky_visibility,
+		})
+	}
+
+	/// The sky visibility at a point, interpolated from the surrounding probes.
+	///
+	/// Surrounding points in the open air count as fully visible and points in the
+	/// ground are ignored, so surfaces away from the probes are unaffected.
+	pub fn sky_visibility(&self, sdf: &S, point: Vec3) -> f32 {
+		let local = (point - self.origin) / self.spacing;
+		let base = local.floor();
+		let fraction = local - base;
+		let base = base.as_ivec3();
+
+		let corners: Vec<_> = (0..8)
+			.map(|i| IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1))
+			.map(|offset| {
+				let weight = Vec3::select(offset.cmpeq(IVec3::ONE), fraction, 1.0 - fraction);
+				(base + offset, weight.x * weight.y * weight.z)
+			})
+			.collect();
+		if !corners.iter().any(|(cell, _)| self.probes.contains_key(cell)) {
+			return 1.0;
+		}
+
+		let (mut total, mut weights) = (0.0, 0.0);
+		for (cell, weight) in corners {
+			let visibility = match self.probes.get(&cell) {
+				Some(visibility) => *visibility,
+				None if sdf.distance(self.origin + cell.as_vec3() * self.spacing) > 0.0 => 1.0,
+				None => continue,
+			};
+			total += visibility * weight;
+			weights += weight;
+		}
+		if weights > 0.0 {
+			total / weights
+		} else {
+			1.0
+		}
+	}
+
+	/// Stores the sky visibility of each vertex of a chunk mesh in its vertex colors,
+	/// if the chunk is near any probes.
+	pub fn shade(&self, sdf: &S, chunk: &CascadeChunk, mut mesh: MeshData) -> MeshData {
+		if self.probes.is_empty() || !self.affects(chunk) {
+			return mesh;
+		}
+		mesh.colors = mesh
+			.positions
+			.iter()
+			.map(|position| {
+				let visibility =
+					self.sky_visibility(sdf, chunk.origin + Vec3::from_array(*position));
+				[visibility, visibility, visibility, 1.0]
+			})
+			.collect();
+		mesh
+	}
+
+	/// Whether the chunk is within reach of the probes.
+	pub fn affects(&self, chunk: &CascadeChunk) -> bool {
+		let reach = Vec3::splat(self.spacing);
+		let bounds = Aabb3d {
+			min: (Vec3::from(self.bounds.min) - reach).into(),
+			max: (Vec3::from(self.bounds.max) + reach).into(),
+		};
+		let chunk = Aabb3d { min: chunk.origin.into(), max: (chunk.origin + chunk.size).into() };
+		bounds.intersects(&chunk)
+	}
+}
+
+/// Queues the chunks shaded by the previous or current [LightProbes] for regeneration
+/// when the probes are baked again.
+pub fn queue_light_probe_chunks<S: Sdf + Send + Sync + 'static>(
+	probes: Option<Res<LightProbes<S>>>,
+	mut previous: Local<Option<Aabb3d>>,
+	mut queue: ResMut<ChunkRegenerationQueue<S>>,
+	chunk_query: Query<(Entity, &TerrainChunk)>,
+) {
+	let Some(probes) = probes else {
+		return;
+	};
+	if !probes.is_changed() {
+		return;
+	}
+
+	let reach = Vec3::splat(probes.spacing);
+	let regions = [Some(probes.bounds), previous.replace(probes.bounds)];
+	for (entity, terrain_chunk) in &chunk_query {
+		let chunk = terrain_chunk.chunk;
+		let aabb = Aabb3d {
+			min: (chunk.origin - reach).into(),
+			max: (chunk.origin + chunk.size + reach).into(),
+		};
+		if regions.iter().flatten().any(|region| region.intersects(&aabb)) {
+			queue.push(entity, chunk);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::cpu::CpuMeshGenerator;
+	use std::sync::Arc;
+
+	/// Flat ground at y = 0.1 with a tunnel running from an opening in the ground down to x = 12
+	struct Tunnel;
+
+	impl Sdf for Tunnel {
+		fn distance(&self, p: Vec3) -> f32 {
+			let ramp =
+				sdf::CapsuleSdf::new(Vec3::new(-4.0, 1.0, 0.1), Vec3::new(0.0, -3.0, 0.1), 1.2);
+			let shaft =
+				sdf::CapsuleSdf::new(Vec3::new(0.0, -3.0, 0.1), Vec3::new(12.0, -3.0, 0.1), 1.2);
+			let tunnel = ramp.distance(p).min(shaft.distance(p));
+			(p.y - 0.1).max(-tunnel)
+		}
+	}
+
+	fn region() -> Aabb3d {
+		Aabb3d { min: Vec3::new(-8.0, -6.0, -4.0).into(), max: Vec3::new(16.0, 2.0, 4.0).into() }
+	}
+
+	#[test]
+	fn test_probes_darken_with_depth() {
+		let probes = LightProbes::bake(&Tunnel, region(), &ProbeBakeConfig::default());
+		assert!(!probes.is_empty());
+		for probe in probes.probes() {
+			assert!(Tunnel.distance(probe.position) > 0.0);
+			assert!(probe.position.y < 0.1);
+			assert!((0.0..=1.0).contains(&probe.sky_visibility));
+		}
+
+		// Along the tunnel, away from the opening
+		let visibility = |x: f32| probes.sky_visibility(&Tunnel, Vec3::new(x, -3.0, 0.1));
+		assert!(visibility(1.0) > visibility(5.0));
+		assert!(visibility(5.0) > visibility(10.0));
+		assert!(visibility(10.0) < 0.5);
+		assert_eq!(probes.sky_visibility(&Tunnel, Vec3::new(12.0, 1.0, 0.1)), 1.0);
+	}
+
+	#[test]
+	fn test_shade_colors_chunks_near_probes() {
+		let probes = LightProbes::bake(&Tunnel, region(), &ProbeBakeConfig::default());
+		let chunk = CascadeChunk::cube(Vec3::new(4.0, -4.0, -4.0), 8.0, 4);
+		let Some(mesh) = CpuMeshGenerator::generate_chunk_mesh_data(&chunk, Arc::new(Tunnel))
+		else {
+			panic!("expected a tunnel mesh");
+		};
+		let shaded = probes.shade(&Tunnel, &chunk, mesh);
+		assert_eq!(shaded.colors.len(), shaded.positions.len());
+		assert!(shaded.colors.iter().any(|color| color[0] < 0.5));
+		assert!(shaded.colors.iter().any(|color| color[0] == 1.0));
+
+		let far = CascadeChunk::cube(Vec3::new(40.0, -4.0, -4.0), 8.0, 4);
+		let Some(mesh) = CpuMeshGenerator::generate_chunk_mesh_data(&far, Arc::new(Tunnel)) else {
+			panic!("expected a ground mesh");
+		};
+		assert!(probes.shade(&Tunnel, &far, mesh).colors.is_empty());
+	}
+}
@@ -0,0 +1,117 @@
+use crate::cascade::CascadeChunk;
+use crate::cpu::MeshData;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::sync::Arc;
+
+pub use engine_core::processor::MeshProcessor;
+
+/// The [MeshProcessor]s run over chunk meshes of the SDF, in the order they were added.
+///
+/// Processors run after portals are carved and light probes are shaded, on every chunk that is
+/// generated, prewarmed or regenerated.
+#[derive(Resource)]
+pub struct MeshProcessors<S: Sdf + Send + Sync> {
+	processors: Vec<Arc<dyn MeshProcessor<S>>>,
+}
+
+impl<S: Sdf + Send + Sync> Default for MeshProcessors<S> {
+	fn default() -> Self {
+		Self { processors: Vec::new() }
+	}
+}
+
+impl<S: Sdf + Send + Sync> Clone for MeshProcessors<S> {
+	fn clone(&self) -> Self {
+		Self { processors: self.processors.clone() }
+	}
+}
+
+impl<S: Sdf + Send + Sync> MeshProcessors<S> {
+	pub fn with_processor(mut self, processor: impl MeshProcessor<S> + 'static) -> Self {
+		self.push(processor);
+		self
+	}
+
+	/// Adds a processor after the others.
+	pub fn push(&mut self, processor: impl MeshProcessor<S> + 'static) {
+		self.processors.push(Arc::new(processor));
+	}
+
+	pub fn len(&self) -> usize {
+		self.processors.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.processors.is_empty()
+	}
+
+	/// Names of the processors in the order they run.
+	pub fn names(&self) -> Vec<&str> {
+		self.processors.iter().map(|processor| processor.name()).collect()
+	}
+
+	/// Runs the processors over the mesh in order, stopping at the first to drop it.
+	pub fn process(&self, sdf: &S, chunk: &CascadeChunk, mesh: MeshData) -> Option<MeshData> {
+		self.processors.iter().try_fold(mesh, |mesh, processor| {
+			let processed = processor.process(sdf, chunk, mesh);
+			if processed.is_none() {
+				log::debug!(
+					"{} dropped the mesh of the chunk at {:?}",
+					processor.name(),
+					chunk.origin
+				);
+			}
+			processed
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::Ground;
+
+	/// Tags every vertex with the chunk's resolution in the green channel
+	struct TagResolution;
+
+	impl MeshProcessor<Ground> for TagResolution {
+		fn name(&self) -> &str {
+			"tag resolution"
+		}
+
+		fn process(
+			&self,
+			_sdf: &Ground,
+			chunk: &CascadeChunk,
+			mut mesh: MeshData,
+		) -> Option<MeshData> {
+			let green = chunk.res_2.x as f32 / 8.0;
+			mesh.colors = vec![[1.0, green, 0.0, 1.0]; mesh.positions.len()];
+			Some(mesh)
+		}
+	}
+
+	#[test]
+	fn test_processors_run_in_order() {
+		let halve_green = |_: &Ground, _: &CascadeChunk, mut mesh: MeshData| {
+			mesh.colors.iter_mut().for_each(|color| color[1] /= 2.0);
+			Some(mesh)
+		};
+		let processors = MeshProcessors::default()
+			.with_processor(TagResolution)
+			.with_processor(halve_green);
+		assert_eq!(processors.names()[0], "tag resolution");
+
+		let chunk = CascadeChunk::cube(Vec3::ZERO, 1.0, 4);
+		let mesh = MeshData { positions: vec![[0.0; 3]; 3], indices: vec![0, 1, 2], ..default() };
+		let Some(processed) = processors.process(&Ground(0.0), &chunk, mesh.clone()) else {
+			panic!("expected the mesh to be kept");
+		};
+		assert_eq!(processed.colors, vec![[1.0, 0.25, 0.0, 1.0]; 3]);
+
+		let drop_all = |_: &Ground, _: &CascadeChunk, _: MeshData| None;
+		let processors = processors.with_processor(drop_all);
+		assert_eq!(processors.process(&Ground(0.0), &chunk, mesh), None);
+	}
+}
@@ -0,0 +1,83 @@
+use crate::boundary::{WorldBoundary, WorldEdge};
+use crate::budget::ChunkBudgetConfig;
+use crate::chunk::ChunkConfig;
+use crate::chunk_manager::{ChunkResolutionConfig, ScreenSpaceError};
+use crate::foliage::FoliageFalloff;
+use crate::shaders::decal_material::DecalMaterial;
+use crate::shaders::far_terrain_material::FarTerrainMaterial;
+use crate::shaders::leaf_material::LeafMaterial;
+use crate::shaders::outline::EdgeMaterial;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::marker::PhantomData;
+
+/// Registers the configs and materials of the engine for reflection, so inspectors such as
+/// bevy_inspector_egui can edit them and scenes can save them.
+///
+/// Configs generic over the SDF are registered for `S`, which only needs a [TypePath], such as
+/// from `#[derive(TypePath)]` on the SDF. Fields the engine can't reflect, such as the mesh
+/// checks and occupancy of the [ChunkConfig], are left out and keep their defaults when a
+/// config is built from reflection.
+pub struct EngineReflectPlugin<S> {
+	/// Marker for the SDF whose configs are registered
+	pub sdf: PhantomData<S>,
+}
+
+impl<S> Default for EngineReflectPlugin<S> {
+	fn default() -> Self {
+		Self { sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync + TypePath> Plugin for EngineReflectPlugin<S> {
+	fn build(&self, app: &mut App) {
+		app.register_type::<ChunkConfig<S>>()
+			.register_type::<ChunkResolutionConfig<S>>()
+			.register_type::<ChunkBudgetConfig<S>>()
+			.register_type::<ScreenSpaceError>()
+			.register_type::<WorldBoundary>()
+			.register_type::<WorldEdge>()
+			.register_type::<FoliageFalloff>()
+			.register_type::<EdgeMaterial>()
+			.register_type::<LeafMaterial>()
+			.register_type::<DecalMaterial>()
+			.register_type::<FarTerrainMaterial>();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::Ground;
+	use bevy::ecs::reflect::ReflectResource;
+	use bevy::reflect::std_traits::ReflectDefault;
+	use std::any::TypeId;
+
+	#[test]
+	fn test_configs_are_registered_for_inspectors() {
+		let mut app = App::new();
+		app.add_plugins(EngineReflectPlugin::<Ground>::default());
+
+		let registry = app.world().resource::<AppTypeRegistry>().read();
+		let config = TypeId::of::<ChunkConfig<Ground>>();
+		assert!(registry.get_type_data::<ReflectResource>(config).is_some());
+		let Some(default) = registry.get_type_data::<ReflectDefault>(config) else {
+			panic!("expected ChunkConfig to reflect its default");
+		};
+
+		// A config built from reflection keeps the fields left out at their defaults
+		let reflected = default.default();
+		let Some(config) = reflected.downcast_ref::<ChunkConfig<Ground>>() else {
+			panic!("expected a ChunkConfig");
+		};
+		assert_eq!(config.min_size, ChunkConfig::<Ground>::default().min_size);
+		assert_eq!(config.origin_snapping, ChunkConfig::<Ground>::default().origin_snapping);
+		for registered in [
+			TypeId::of::<EdgeMaterial>(),
+			TypeId::of::<LeafMaterial>(),
+			TypeId::of::<ChunkResolutionConfig<Ground>>(),
+		] {
+			assert!(registry.get(registered).is_some());
+		}
+	}
+}
@@ -0,0 +1,304 @@
+use crate::cascade::CascadeChunk;
+use crate::chunk::{ChunkConfig, ChunkId, LoadedChunks, TerrainChunk};
+use crate::chunk_manager::{ChunkSources, SdfResource};
+use crate::crossfade::{start_crossfade, ChunkCrossfade, ChunkFade};
+use crate::double_buffer::ChunkDoubleBuffer;
+use crate::shaders::outline::EdgeMaterial;
+use crate::stats::ChunkMeshSize;
+use bevy::camera::primitives::Aabb;
+use bevy::math::bounding::{Aabb3d, IntersectsVolume};
+use bevy::prelude::*;
+use rayon::prelude::*;
+use sdf::Sdf;
+use std::collections::{HashSet, VecDeque};
+use std::marker::PhantomData;
+
+/// Signals that the terrain SDFs changed and the loaded chunks need to be regenerated.
+#[derive(Message, Debug, Clone, Copy, Default)]
+pub struct TerrainDirty;
+
+/// Signals that the terrain changed within a region, in the local space of its SDF,
+/// and the chunks overlapping it need to be regenerated.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct TerrainRegionDirty(pub Aabb3d);
+
+/// Loaded chunks waiting to be regenerated after a [TerrainDirty] message, nearest to the camera first.
+///
+/// Chunks keep their old meshes until they are regenerated, so the terrain updates in stages
+/// rather than disappearing at once.
+#[derive(Resource)]
+pub struct ChunkRegenerationQueue<S: Sdf + Send + Sync> {
+	queue: VecDeque<(Entity, CascadeChunk)>,
+	/// Chunks regenerated per frame
+	pub chunks_per_frame: usize,
+	/// Marker for the SDF that defines the chunk boundaries
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for ChunkRegenerationQueue<S> {
+	fn default() -> Self {
+		Self { queue: VecDeque::new(), chunks_per_frame: 8, sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync> ChunkRegenerationQueue<S> {
+	pub fn with_chunks_per_frame(mut self, chunks_per_frame: usize) -> Self {
+		self.chunks_per_frame = chunks_per_frame;
+		self
+	}
+
+	pub fn len(&self) -> usize {
+		self.queue.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.queue.is_empty()
+	}
+
+	/// Queues a chunk behind the others. A chunk that is already queued keeps its place and
+	/// takes the new chunk, such as one at another resolution.
+	pub fn push(&mut self, entity: Entity, chunk: CascadeChunk) {
+		match self.queue.iter_mut().find(|(queued, _)| *queued == entity) {
+			Some((_, queued_chunk)) => *queued_chunk = chunk,
+			None => self.queue.push_back((entity, chunk)),
+		}
+	}
+
+	/// The queued chunks in the order they will be regenerated.
+	pub fn chunks(&self) -> impl Iterator<Item = &CascadeChunk> {
+		self.queue.iter().map(|(_, chunk)| chunk)
+	}
+}
+
+/// Queues every loaded chunk for regeneration when the terrain is marked dirty.
+///
+/// Loaded chunks without a mesh have no entity, so they are marked unloaded instead
+/// and regenerated by [manage_chunks](crate::manage_chunks).
+pub fn queue_dirty_chunks<S: Sdf + Send + Sync + 'static>(
+	mut dirty: MessageReader<TerrainDirty>,
+	mut queue: ResMut<ChunkRegenerationQueue<S>>,
+	mut loaded_chunks: ResMut<LoadedChunks>,
+	camera_query: Query<&Transform, With<Camera3d>>,
+	chunk_query: Query<(Entity, &TerrainChunk)>,
+	chunk_config: Res<ChunkConfig<S>>,
+	sdf_resource: Res<SdfResource<S>>,
+) {
+	if dirty.read().count() == 0 {
+		return;
+	}
+
+	let camera_pos = camera_query
+		.single()
+		.map(|transform| sdf_resource.transform.to_local(transform.translation))
+		.unwrap_or(Vec3::ZERO);
+	let distance = |chunk: &CascadeChunk| (chunk.origin + chunk.size / 2.0).distance(camera_pos);
+
+	let mut chunks: Vec<_> = chunk_query
+		.iter()
+		.map(|(entity, terrain_chunk)| (entity, terrain_chunk.chunk))
+		.collect();
+	chunks.sort_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)));
+
+	// Chunks that had no mesh may have a surface now
+	let with_mesh: HashSet<ChunkId> =
+		chunks.iter().map(|(_, chunk)| chunk_config.chunk_id(chunk)).collect();
+	loaded_chunks.retain(|id| with_mesh.contains(id));
+
+	log::info!("Regenerating {} chunks", chunks.len());
+	queue.queue = chunks.into();
+}
+
+/// Queues the loaded chunks overlapping any of the regions for regeneration.
+///
+/// Chunks without a mesh have no entity, so they are marked unloaded instead
+/// and regenerated by [manage_chunks](crate::manage_chunks).
+pub(crate) fn queue_chunks_in_regions<S: Sdf + Send + Sync>(
+	regions: &[Aabb3d],
+	queue: &mut ChunkRegenerationQueue<S>,
+	loaded_chunks: &mut LoadedChunks,
+	chunk_query: &Query<(Entity, &TerrainChunk)>,
+	chunk_config: &ChunkConfig<S>,
+) {
+	let affected = |origin: Vec3, size: Vec3| {
+		let aabb = Aabb3d { min: origin.into(), max: (origin + size).into() };
+		regions.iter().any(|region| region.intersects(&aabb))
+	};
+
+	let mut with_mesh = HashSet::new();
+	for (entity, terrain_chunk) in chunk_query {
+		let chunk = terrain_chunk.chunk;
+		with_mesh.insert(chunk_config.chunk_id(&chunk));
+		if affected(chunk.origin, chunk.size) {
+			queue.push(entity, chunk);
+		}
+	}
+
+	// Chunks that had no mesh may have a surface now
+	let min_size = chunk_config.min_size;
+	loaded_chunks
+		.retain(|id| with_mesh.contains(id) || !affected(id.origin(min_size), id.size(min_size)));
+}
+
+/// Queues the loaded chunks in the regions of [TerrainRegionDirty] messages for regeneration.
+pub fn queue_dirty_region_chunks<S: Sdf + Send + Sync + 'static>(
+	mut dirty: MessageReader<TerrainRegionDirty>,
+	mut queue: ResMut<ChunkRegenerationQueue<S>>,
+	mut loaded_chunks: ResMut<LoadedChunks>,
+	chunk_query: Query<(Entity, &TerrainChunk)>,
+	chunk_config: Res<ChunkConfig<S>>,
+) {
+	let regions: Vec<Aabb3d> = dirty.read().map(|dirty| dirty.0).collect();
+	if regions.is_empty() {
+		return;
+	}
+	queue_chunks_in_regions(&regions, &mut queue, &mut loaded_chunks, &chunk_query, &chunk_config);
+}
+
+/// The meshes, materials and placement of the chunks being regenerated
+type RegeneratedChunk<'a> = (
+	&'a Mesh3d,
+	&'a TerrainChunk,
+	&'a Transform,
+	Option<&'a MeshMaterial3d<EdgeMaterial>>,
+	Option<&'a ChunkFade>,
+);
+
+/// The mesh assets chunks are swapped in, the crossfade settings and materials for chunks
+/// that fade between their meshes, and the double buffering of chunks that don't
+type ChunkSwap<'w, S> = (
+	ResMut<'w, Assets<Mesh>>,
+	Option<Res<'w, ChunkCrossfade<S>>>,
+	Option<ResMut<'w, Assets<EdgeMaterial>>>,
+	Option<Res<'w, ChunkDoubleBuffer<S>>>,
+);
+
+/// Regenerates the next queued chunks with the current SDF, swapping their meshes in place.
+///
+/// Chunks that change resolution crossfade to their new meshes while a [ChunkCrossfade]
+/// is registered, and the others keep their old meshes beside them while a
+/// [ChunkDoubleBuffer] is.
+pub fn regenerate_queued_chunks<S: Sdf + Send + Sync + 'static>(
+	mut commands: Commands,
+	mut queue: ResMut<ChunkRegenerationQueue<S>>,
+	mesh_query: Query<RegeneratedChunk>,
+	sources: ChunkSources<S>,
+	(mut meshes, crossfade, mut materials, double_buffer): ChunkSwap<S>,
+) {
+	let count = queue.chunks_per_frame.max(1).min(queue.queue.len());
+	let batch: Vec<_> = queue.queue.drain(..count).collect();
+	if batch.is_empty() {
+		return;
+	}
+
+	let pipeline = sources.pipeline();
+	let regenerated: Vec<_> = batch
+		.par_iter()
+		.map(|(entity, chunk)| (*entity, *chunk, pipeline.generate(chunk)))
+		.collect();
+
+	for (entity, chunk, mesh) in regenerated {
+		// The chunk may have been unloaded while queued
+		let Ok((old_mesh, old_chunk, transform, material, fade)) = mesh_query.get(entity) else {
+			continue;
+		};
+		// The old mesh fades out on its own entity when the resolution changes
+		let fading = match (crossfade.as_deref(), materials.as_deref_mut(), material, &mesh) {
+			(Some(crossfade), Some(materials), Some(material), Some(_))
+				if old_chunk.chunk.res_2 != chunk.res_2 =>
+			{
+				let shared = fade.map_or_else(|| material.0.clone(), |fade| fade.shared.clone());
+				start_crossfade(
+					&mut commands,
+					materials,
+					entity,
+					old_mesh.0.clone(),
+					*transform,
+					shared,
+					crossfade.frames,
+				);
+				true
+			}
+			_ => false,
+		};
+		// The old mesh stays on its own entity until the new one is alive
+		let buffered = match (double_buffer.as_deref(), material, &mesh) {
+			(Some(double_buffer), Some(material), Some(_)) if !fading => {
+				let shared = fade.map_or_else(|| material.0.clone(), |fade| fade.shared.clone());
+				commands.spawn((
+					Mesh3d(old_mesh.0.clone()),
+					MeshMaterial3d(shared),
+					*transform,
+					double_buffer.swap_out(sources.chunk_config.chunk_id(&old_chunk.chunk)),
+				));
+				true
+			}
+			_ => false,
+		};
+		if !fading && !buffered {
+			meshes.remove(old_mesh.id());
+		}
+
+		match mesh {
+			Some(mesh) => {
+				// Bevy only computes the bounds of meshes without an Aabb
+				let terrain_chunk = TerrainChunk { chunk };
+				let mesh_size = ChunkMeshSize::of(&mesh);
+				commands
+					.entity(entity)
+					.insert((Mesh3d(meshes.add(mesh)), terrain_chunk, mesh_size))
+					.remove::<Aabb>();
+				#[cfg(feature = "debug-names")]
+				commands.entity(entity).insert(Name::new(terrain_chunk.name()));
+			}
+			None => {
+				commands.entity(entity).despawn();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::chunk_manager::manage_chunks;
+	use crate::test_support::{camera, ground_app, Ground};
+
+	#[test]
+	fn test_dirty_chunks_regenerate_nearest_first() {
+		let mut app = ground_app(0.0);
+		app.add_message::<TerrainDirty>()
+			.insert_resource(ChunkRegenerationQueue::<Ground>::default().with_chunks_per_frame(2))
+			.add_systems(
+				Update,
+				(
+					manage_chunks::<Ground>,
+					queue_dirty_chunks::<Ground>,
+					regenerate_queued_chunks::<Ground>,
+				)
+					.chain(),
+			);
+		let position = Vec3::new(0.3, 0.2, 0.1);
+		let camera = camera(&mut app);
+		app.world_mut().entity_mut(camera).insert(Transform::from_translation(position));
+		app.update();
+
+		let chunks = app.world_mut().query::<&TerrainChunk>().iter(app.world()).count();
+		app.world_mut().write_message(TerrainDirty);
+		app.update();
+
+		// The first two were regenerated this frame, the rest wait by distance
+		let queue = app.world().resource::<ChunkRegenerationQueue<Ground>>();
+		assert_eq!(queue.len(), chunks - 2);
+		let distances: Vec<f32> = queue
+			.chunks()
+			.map(|chunk| (chunk.origin + chunk.size / 2.0).distance(position))
+			.collect();
+		assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+
+		for _ in 0..chunks {
+			app.update();
+		}
+		assert!(app.world().resource::<ChunkRegenerationQueue<Ground>>().is_empty());
+		assert_eq!(app.world_mut().query::<&TerrainChunk>().iter(app.world()).count(), chunks);
+	}
+}
@@ -0,0 +1,301 @@
+use crate::cascade::CascadeChunk;
+use crate::cpu::{CpuMeshGenerator, MeshData};
+use crate::marching_cubes::VertexInterpolation;
+use bevy::prelude::*;
+use rayon::prelude::*;
+use sdf::Sdf;
+use serde_derive::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// A chunk generation request as it was made, with a hash of the mesh it produced.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GenerationRequest {
+	pub origin: [f32; 3],
+	pub size: [f32; 3],
+	pub res_2: [u32; 3],
+	/// Which SDF the chunk was generated from, counted up each time the SDF is replaced
+	pub sdf_version: u32,
+	/// [mesh_hash] of the generated mesh, or None when the chunk had no surface
+	pub hash: Option<u64>,
+	/// Iso level the surface was extracted at
+	#[serde(default)]
+	pub iso_level: f32,
+	/// How vertices were placed along the cube edges
+	#[serde(default, with = "RecordedInterpolation")]
+	pub interpolation: VertexInterpolation,
+}
+
+/// [VertexInterpolation] as it is recorded, since engine-core doesn't depend on serde.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "VertexInterpolation")]
+enum RecordedInterpolation {
+	Linear,
+	Midpoint,
+	Refined(u8),
+}
+
+impl GenerationRequest {
+	pub fn new(chunk: &CascadeChunk, sdf_version: u32, mesh: Option<&MeshData>) -> Self {
+		Self {
+			origin: chunk.origin.to_array(),
+			size: chunk.size.to_array(),
+			res_2: chunk.res_2.to_array(),
+			sdf_version,
+			hash: mesh.filter(|mesh| !mesh.is_empty()).map(mesh_hash),
+			iso_level: 0.0,
+			interpolation: VertexInterpolation::default(),
+		}
+	}
+
+	pub fn with_iso_level(mut self, iso_level: f32) -> Self {
+		self.iso_level = iso_level;
+		self
+	}
+
+	pub fn with_interpolation(mut self, interpolation: VertexInterpolation) -> Self {
+		self.interpolation = interpolation;
+		self
+	}
+
+	pub fn chunk(&self) -> CascadeChunk {
+		CascadeChunk {
+			origin: Vec3::from_array(self.origin),
+			size: Vec3::from_array(self.size),
+			res_2: UVec3::from_array(self.res_2),
+			omit: None,
+		}
+	}
+}
+
+/// An FNV-1a hash of the buffers of a mesh, stable across runs and builds.
+pub fn mesh_hash(mesh: &MeshData) -> u64 {
+	let buffers: [&[u8]; 5] = [
+		bytemuck::cast_slice(&mesh.positions),
+		bytemuck::cast_slice(&mesh.normals),
+		bytemuck::cast_slice(&mesh.uvs),
+		bytemuck::cast_slice(&mesh.colors),
+		bytemuck::cast_slice(&mesh.indices),
+	];
+	buffers.iter().fold(0xcbf2_9ce4_8422_2325, |hash, buffer| {
+		// Lengths keep a vertex moving between buffers from hashing the same
+		let hash = (buffer.len() as u64).to_le_bytes().iter().fold(hash, fnv);
+		buffer.iter().fold(hash, fnv)
+	})
+}
+
+fn fnv(hash: u64, byte: &u8) -> u64 {
+	(hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+}
+
+/// The chunk generation requests of a session, in the order they finished.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationRecording {
+	pub requests: Vec<GenerationRequest>,
+}
+
+impl GenerationRecording {
+	pub fn len(&self) -> usize {
+		self.requests.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.requests.is_empty()
+	}
+
+	/// The highest SDF version the recording generated from.
+	pub fn sdf_versions(&self) -> u32 {
+		self.requests
+			.iter()
+			.map(|request| request.sdf_version)
+			.max()
+			.map_or(0, |v| v + 1)
+	}
+
+	/// Generates every request again and compares the hashes chunk by chunk.
+	///
+	/// The generate function is given each request and returns its mesh, so replays can run
+	/// the SDF of the request's version through the same steps as the recorded session.
+	pub fn replay(
+		&self,
+		generate: impl Fn(&GenerationRequest) -> Option<MeshData> + Sync,
+	) -> ReplayReport {
+		let mismatches = self
+			.requests
+			.par_iter()
+			.enumerate()
+			.filter_map(|(index, request)| {
+				let replayed = generate(request).filter(|mesh| !mesh.is_empty());
+				let hash = replayed.as_ref().map(mesh_hash);
+				(hash != request.hash).then_some(ReplayMismatch { index, request: *request, hash })
+			})
+			.collect();
+		ReplayReport { requests: self.len(), mismatches }
+	}
+
+	/// Replays the recording with the bare mesh generator, for sessions without portals,
+	/// light probes or processors.
+	pub fn replay_sdf<S: Sdf + Send + Sync + 'static>(
+		&self,
+		sdf_for_version: impl Fn(u32) -> Arc<S> + Sync,
+	) -> ReplayReport {
+		self.replay(|request| {
+			CpuMeshGenerator::generate_chunk_mesh_data_with(
+				&request.chunk(),
+				sdf_for_version(request.sdf_version),
+				request.iso_level,
+				request.interpolation,
+			)
+		})
+	}
+
+	pub fn to_json(&self) -> Result<String, String> {
+		serde_json::to_string(self).map_err(|e| format!("Failed to serialize recording: {e}"))
+	}
+
+	pub fn from_json(json: &str) -> Result<Self, String> {
+		serde_json::from_str(json).map_err(|e| format!("Failed to parse recording: {e}"))
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), String> {
+		std::fs::write(path, self.to_json()?)
+			.map_err(|e| format!("Failed to write recording to {}: {e}", path.display()))
+	}
+
+	pub fn load(path: &Path) -> Result<Self, String> {
+		let contents = std::fs::read_to_string(path)
+			.map_err(|e| format!("Failed to read recording from {}: {e}", path.display()))?;
+		Self::from_json(&contents).map_err(|e| format!("{e} in {}", path.display()))
+	}
+}
+
+/// A replayed request whose mesh differs from the recorded one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayMismatch {
+	/// Position of the request in the recording
+	pub index: usize,
+	pub request: GenerationRequest,
+	/// Hash of the replayed mesh
+	pub hash: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+	pub requests: usize,
+	pub mismatches: Vec<ReplayMismatch>,
+}
+
+impl ReplayReport {
+	pub fn is_deterministic(&self) -> bool {
+		self.mismatches.is_empty()
+	}
+}
+
+#[derive(Debug, Default)]
+struct RecorderState {
+	recording: GenerationRecording,
+	/// Address of the SDF the last request was generated from
+	sdf: usize,
+	sdf_version: Option<u32>,
+}
+
+/// Records every chunk the SDF's pipeline generates, prewarms or regenerates while registered.
+///
+/// The SDF version counts up each time the [SdfResource](crate::SdfResource) is given a new SDF.
+/// Take the recording to save it and replay it headlessly with [GenerationRecording::replay].
+#[derive(Resource)]
+pub struct GenerationRecorder<S: Sdf + Send + Sync> {
+	state: Arc<Mutex<RecorderState>>,
+	sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for GenerationRecorder<S> {
+	fn default() -> Self {
+		Self { state: Arc::default(), sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync> Clone for GenerationRecorder<S> {
+	fn clone(&self) -> Self {
+		Self { state: Arc::clone(&self.state), sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync> GenerationRecorder<S> {
+	/// Records the generation of a chunk from an SDF.
+	pub fn record(
+		&self,
+		sdf: &Arc<S>,
+		chunk: &CascadeChunk,
+		iso_level: f32,
+		interpolation: VertexInterpolation,
+		mesh: Option<&MeshData>,
+	) {
+		let Ok(mut state) = self.state.lock() else {
+			log::error!("Generation recorder lock poisoned");
+			return;
+		};
+		let address = Arc::as_ptr(sdf) as *const () as usize;
+		let sdf_version = match state.sdf_version {
+			Some(version) if state.sdf == address => version,
+			Some(version) => version + 1,
+			None => 0,
+		};
+		state.sdf = address;
+		state.sdf_version = Some(sdf_version);
+		let request = GenerationRequest::new(chunk, sdf_version, mesh)
+			.with_iso_level(iso_level)
+			.with_interpolation(interpolation);
+		state.recording.requests.push(request);
+	}
+
+	pub fn len(&self) -> usize {
+		self.state.lock().map_or(0, |state| state.recording.len())
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Takes what was recorded so far, leaving the recorder recording afresh.
+	pub fn take(&self) -> GenerationRecording {
+		self.state
+			.lock()
+			.map(|mut state| std::mem::take(&mut state.recording))
+			.unwrap_or_default()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::chunk_manager::manage_chunks;
+	use crate::test_support::{ground_app, Ground};
+
+	#[test]
+	fn test_recorded_session_replays() {
+		let mut app = ground_app(0.25);
+		app.init_resource::<GenerationRecorder<Ground>>()
+			.add_systems(Update, manage_chunks::<Ground>);
+		app.update();
+
+		let recording = app.world().resource::<GenerationRecorder<Ground>>().take();
+		assert!(!recording.is_empty());
+		assert!(recording.requests.iter().any(|request| request.hash.is_some()));
+		assert_eq!(recording.sdf_versions(), 1);
+		let Ok(recording) =
+			GenerationRecording::from_json(&recording.to_json().unwrap_or_default())
+		else {
+			panic!("expected the recording to round trip");
+		};
+
+		let ground = Arc::new(Ground(0.25));
+		assert!(recording.replay_sdf(|_| Arc::clone(&ground)).is_deterministic());
+
+		// A different SDF replays to different meshes wherever there was a surface
+		let report = recording.replay_sdf(|_| Arc::new(Ground(0.3)));
+		let surfaces = recording.requests.iter().filter(|request| request.hash.is_some()).count();
+		assert_eq!(report.mismatches.len(), surfaces);
+	}
+}
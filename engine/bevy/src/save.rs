@@ -0,0 +1,228 @@
+use crate::portal::{PortalId, PortalVolume, PortalVolumes};
+use bevy::math::bounding::Aabb3d;
+use bevy::prelude::*;
+use sdf::Sdf;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The version of the [WorldSave] format written by this build.
+pub const WORLD_SAVE_VERSION: u32 = 1;
+
+/// A change to the terrain SDF, in its local space.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TerrainDelta {
+	/// A box cut out of the terrain as a [PortalVolume]
+	Cut { min: [f32; 3], max: [f32; 3] },
+}
+
+/// An object placed on the terrain, named by the game that placed it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedStamp {
+	pub kind: String,
+	pub position: [f32; 3],
+}
+
+/// The player's changes to the procedural world, saved apart from everything the seed rebuilds.
+///
+/// Terrain deltas, placed stamps and the bases of destroyed vegetation are in the local space
+/// of the terrain SDF. Saves are JSON and carry their version, so saves of older versions are
+/// brought up to date by the [WorldSaveMigrations] they are loaded with.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldSave {
+	pub version: u32,
+	/// The seed of the world the changes were made to
+	pub seed: u32,
+	#[serde(default)]
+	pub terrain: Vec<TerrainDelta>,
+	#[serde(default)]
+	pub stamps: Vec<SavedStamp>,
+	#[serde(default)]
+	pub destroyed_vegetation: Vec<[f32; 3]>,
+}
+
+impl Default for WorldSave {
+	fn default() -> Self {
+		Self::new(0)
+	}
+}
+
+/// Rewrites a save of one version as a save of the next.
+pub type SaveMigration = fn(Value) -> Result<Value, String>;
+
+/// Upgrades older saves, one version at a time, before they are read.
+///
+/// Saves without a version field are version 0.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct WorldSaveMigrations {
+	migrations: HashMap<u32, SaveMigration>,
+}
+
+impl WorldSaveMigrations {
+	/// Migrates saves of the version to the version after it.
+	pub fn with_migration(mut self, from: u32, migration: SaveMigration) -> Self {
+		self.migrations.insert(from, migration);
+		self
+	}
+
+	/// Brings a parsed save up to [WORLD_SAVE_VERSION].
+	pub fn migrate(&self, mut save: Value) -> Result<Value, String> {
+		let mut version = save.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+		if version > WORLD_SAVE_VERSION {
+			return Err(format!(
+				"Save version {version} is newer than the supported version {WORLD_SAVE_VERSION}"
+			));
+		}
+		while version < WORLD_SAVE_VERSION {
+			let migration = self
+				.migrations
+				.get(&version)
+				.ok_or_else(|| format!("No migration from save version {version}"))?;
+			save = migration(save)?;
+			version += 1;
+			if let Some(object) = save.as_object_mut() {
+				object.insert("version".to_string(), Value::from(version));
+			}
+		}
+		Ok(save)
+	}
+}
+
+impl WorldSave {
+	pub fn new(seed: u32) -> Self {
+		Self {
+			version: WORLD_SAVE_VERSION,
+			seed,
+			terrain: Vec::new(),
+			stamps: Vec::new(),
+			destroyed_vegetation: Vec::new(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.terrain.is_empty() && self.stamps.is_empty() && self.destroyed_vegetation.is_empty()
+	}
+
+	pub fn record_stamp(&mut self, kind: impl Into<String>, position: Vec3) {
+		self.stamps
+			.push(SavedStamp { kind: kind.into(), position: position.to_array() });
+	}
+
+	/// Records vegetation destroyed at its base, such as a chopped tree.
+	pub fn record_destroyed(&mut self, base: Vec3) {
+		self.destroyed_vegetation.push(base.to_array());
+	}
+
+	/// Whether vegetation with its base within the radius of the position was destroyed.
+	pub fn is_destroyed(&self, position: Vec3, radius: f32) -> bool {
+		self.destroyed_vegetation
+			.iter()
+			.any(|base| Vec3::from_array(*base).distance(position) <= radius)
+	}
+
+	/// Replaces the terrain deltas with the cuts of the portal volumes.
+	///
+	/// Volumes shaped by an SDF can't be saved and are skipped.
+	pub fn capture_portals<S: Sdf + Send + Sync>(&mut self, portals: &PortalVolumes<S>) {
+		self.terrain.clear();
+		for volume in portals.volumes() {
+			match volume {
+				PortalVolume::Aabb(aabb) => self.terrain.push(TerrainDelta::Cut {
+					min: Vec3::from(aabb.min).to_array(),
+					max: Vec3::from(aabb.max).to_array(),
+				}),
+				PortalVolume::Sdf { .. } => log::warn!("Skipping an SDF portal volume in the save"),
+			}
+		}
+	}
+
+	/// Cuts the saved terrain deltas into the terrain as portal volumes.
+	pub fn apply_portals<S: Sdf + Send + Sync>(
+		&self,
+		portals: &mut PortalVolumes<S>,
+	) -> Vec<PortalId> {
+		self.terrain
+			.iter()
+			.map(|delta| match delta {
+				TerrainDelta::Cut { min, max } => portals.insert(PortalVolume::Aabb(Aabb3d {
+					min: Vec3::from_array(*min).into(),
+					max: Vec3::from_array(*max).into(),
+				})),
+			})
+			.collect()
+	}
+
+	pub fn to_json(&self) -> Result<String, String> {
+		serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize save: {e}"))
+	}
+
+	/// Reads a save of any version the migrations can bring up to date.
+	pub fn from_json(json: &str, migrations: &WorldSaveMigrations) -> Result<Self, String> {
+		let save = serde_json::from_str(json).map_err(|e| format!("Failed to parse save: {e}"))?;
+		serde_json::from_value(migrations.migrate(save)?)
+			.map_err(|e| format!("Failed to read save: {e}"))
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), String> {
+		let contents = self.to_json()?;
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)
+				.map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+		}
+		std::fs::write(path, contents)
+			.map_err(|e| format!("Failed to write save to {}: {e}", path.display()))
+	}
+
+	/// Loads a save from a file, or an empty save if the file doesn't exist yet.
+	pub fn load(path: &Path, migrations: &WorldSaveMigrations) -> Result<Self, String> {
+		if !path.exists() {
+			return Ok(Self::default());
+		}
+		let contents = std::fs::read_to_string(path)
+			.map_err(|e| format!("Failed to read save from {}: {e}", path.display()))?;
+		Self::from_json(&contents, migrations).map_err(|e| format!("{e} in {}", path.display()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::Ground;
+
+	/// Version 0 saves kept destroyed trees as a list of positions named "chopped"
+	fn rename_chopped(mut save: Value) -> Result<Value, String> {
+		let object = save.as_object_mut().ok_or("Save is not an object")?;
+		let chopped = object.remove("chopped").unwrap_or_default();
+		object.insert("destroyed_vegetation".to_string(), chopped);
+		object.insert("seed".to_string(), Value::from(0));
+		Ok(save)
+	}
+
+	#[test]
+	fn test_save_round_trips_and_migrates() {
+		let mut portals = PortalVolumes::<Ground>::default();
+		portals.insert(PortalVolume::aabb(Vec3::splat(-1.0), Vec3::ONE));
+		let mut save = WorldSave::new(7);
+		save.capture_portals(&portals);
+		save.record_stamp("rock", Vec3::new(1.0, 2.0, 3.0));
+		save.record_destroyed(Vec3::new(4.0, 0.0, 4.0));
+
+		let migrations = WorldSaveMigrations::default().with_migration(0, rename_chopped);
+		let Ok(json) = save.to_json() else {
+			panic!("expected the save to serialize");
+		};
+		assert_eq!(WorldSave::from_json(&json, &migrations), Ok(save.clone()));
+		let mut restored = PortalVolumes::<Ground>::default();
+		save.apply_portals(&mut restored);
+		assert_eq!(restored.len(), 1);
+
+		let Ok(old) = WorldSave::from_json(r#"{"chopped": [[4.0, 0.0, 4.0]]}"#, &migrations) else {
+			panic!("expected the old save to migrate");
+		};
+		assert_eq!(old.version, WORLD_SAVE_VERSION);
+		assert!(old.is_destroyed(Vec3::new(4.5, 0.0, 4.0), 1.0));
+		assert!(WorldSave::from_json("{}", &WorldSaveMigrations::default()).is_err());
+		assert!(WorldSave::from_json(r#"{"version": 99}"#, &migrations).is_err());
+	}
+}
@@ -0,0 +1,173 @@
+use crate::chunk_manager::ChunkResolutionConfig;
+use crate::generation_pool::GenerationPool;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// Lowers the chunk resolution while frames run over budget and restores it once they recover.
+///
+/// Watches the frame time and the time spent generating chunks on the [GenerationPool], if there
+/// is one, and steps [ChunkResolutionConfig::base_res_2] down one level at a time. The
+/// resolution configured when the scaling starts is the one it restores to, so the scaling owns
+/// base_res_2 while it runs.
+#[derive(Resource)]
+pub struct ResolutionScaling<S: Sdf + Send + Sync> {
+	/// Frame time to stay under
+	pub frame_budget: Duration,
+	/// Share of the frame budget that chunk generation may take
+	pub generation_share: f32,
+	/// Fraction of the budgets under which the resolution is restored
+	pub restore_below: f32,
+	/// Most levels the resolution is lowered by
+	pub max_reduction: u8,
+	/// Least time between two steps, so a step can take effect before the next
+	pub cooldown: Duration,
+	/// Weight of each new frame in the smoothed times
+	pub smoothing: f32,
+	reduction: u8,
+	base_res_2: Option<u8>,
+	frame_time: f32,
+	generation_time: f32,
+	generation_wall: Duration,
+	since_step: f32,
+	/// Marker for the SDF whose chunks are scaled
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for ResolutionScaling<S> {
+	fn default() -> Self {
+		Self {
+			frame_budget: Duration::from_secs_f32(1.0 / 30.0),
+			generation_share: 0.5,
+			restore_below: 0.6,
+			max_reduction: 2,
+			cooldown: Duration::from_secs(1),
+			smoothing: 0.1,
+			reduction: 0,
+			base_res_2: None,
+			frame_time: 0.0,
+			generation_time: 0.0,
+			generation_wall: Duration::ZERO,
+			since_step: 0.0,
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> ResolutionScaling<S> {
+	pub fn with_frame_budget(mut self, frame_budget: Duration) -> Self {
+		self.frame_budget = frame_budget;
+		self
+	}
+
+	pub fn with_max_reduction(mut self, max_reduction: u8) -> Self {
+		self.max_reduction = max_reduction;
+		self
+	}
+
+	pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+		self.cooldown = cooldown;
+		self
+	}
+
+	/// Levels the resolution is currently lowered by
+	pub fn reduction(&self) -> u8 {
+		self.reduction
+	}
+
+	/// Takes in the frame and generation times of a frame, in seconds, and steps the reduction
+	/// when the smoothed times call for it.
+	pub fn observe(&mut self, frame_time: f32, generation_time: f32) -> u8 {
+		let smoothing = self.smoothing.clamp(0.0, 1.0);
+		self.frame_time += (frame_time - self.frame_time) * smoothing;
+		self.generation_time += (generation_time - self.generation_time) * smoothing;
+		self.since_step += frame_time;
+		if self.since_step < self.cooldown.as_secs_f32() {
+			return self.reduction;
+		}
+
+		let frame_budget = self.frame_budget.as_secs_f32();
+		let generation_budget = frame_budget * self.generation_share;
+		let over = self.frame_time > frame_budget || self.generation_time > generation_budget;
+		let headroom = self.frame_time < frame_budget * self.restore_below
+			&& self.generation_time < generation_budget * self.restore_below;
+
+		if over && self.reduction < self.max_reduction {
+			self.reduction += 1;
+			self.since_step = 0.0;
+			log::info!("Frames over budget, lowering chunk resolution by {}", self.reduction);
+		} else if headroom && self.reduction > 0 {
+			self.reduction -= 1;
+			self.since_step = 0.0;
+			log::info!(
+				"Frames within budget, raising chunk resolution to {} below",
+				self.reduction
+			);
+		}
+		self.reduction
+	}
+}
+
+/// Feeds the frame and generation times to the [ResolutionScaling] and applies its reduction.
+///
+/// Add it before manage_chunks; chunks that change resolution are swapped through the
+/// [ChunkRegenerationQueue](crate::ChunkRegenerationQueue) when there is one.
+pub fn scale_resolution<S: Sdf + Send + Sync + 'static>(
+	time: Res<Time>,
+	mut scaling: ResMut<ResolutionScaling<S>>,
+	mut resolution_config: ResMut<ChunkResolutionConfig<S>>,
+	generation_pool: Option<Res<GenerationPool>>,
+) {
+	let base_res_2 = *scaling.base_res_2.get_or_insert(resolution_config.base_res_2);
+
+	let generation_wall = generation_pool
+		.as_deref()
+		.map(|pool| pool.near.metrics().wall + pool.far.metrics().wall)
+		.unwrap_or_default();
+	let generation_time = generation_wall.saturating_sub(scaling.generation_wall).as_secs_f32();
+	scaling.generation_wall = generation_wall;
+
+	let reduction = scaling.observe(time.delta_secs(), generation_time);
+	let res_2 = base_res_2.saturating_sub(reduction).max(1);
+	if resolution_config.base_res_2 != res_2 {
+		resolution_config.base_res_2 = res_2;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::Ground;
+
+	#[test]
+	fn test_resolution_drops_under_pressure_and_recovers() {
+		let mut scaling = ResolutionScaling::<Ground>::default()
+			.with_frame_budget(Duration::from_millis(20))
+			.with_cooldown(Duration::from_millis(500));
+
+		// Slow frames step down one level per cooldown, up to the limit
+		let mut reductions = Vec::new();
+		for _ in 0..100 {
+			reductions.push(scaling.observe(0.05, 0.0));
+		}
+		assert_eq!(reductions[0], 0);
+		assert_eq!(reductions.iter().filter(|&&r| r == 1).count(), 10);
+		assert_eq!(scaling.reduction(), 2);
+
+		// Heavy generation alone is pressure too
+		let mut generating = ResolutionScaling::<Ground>::default()
+			.with_frame_budget(Duration::from_millis(20))
+			.with_cooldown(Duration::ZERO);
+		for _ in 0..50 {
+			generating.observe(0.015, 0.015);
+		}
+		assert!(generating.reduction() > 0);
+
+		// Once frames are fast again, the resolution comes back
+		for _ in 0..2000 {
+			scaling.observe(0.005, 0.0);
+		}
+		assert_eq!(scaling.reduction(), 0);
+	}
+}
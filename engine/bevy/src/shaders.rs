@@ -1,3 +1,5 @@
 pub mod custom_material;
+pub mod decal_material;
+pub mod far_terrain_material;
 pub mod leaf_material;
 pub mod outline;
@@ -0,0 +1,39 @@
+use bevy::{
+	prelude::*,
+	render::render_resource::{AsBindGroup, ShaderType},
+	shader::ShaderRef,
+};
+
+/// Uniforms of a [DecalMaterial].
+#[derive(ShaderType, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, PartialEq))]
+pub struct DecalSettings {
+	pub color: Vec4,
+	/// Procedural pattern: 0 for a soft blotch, 1 for twin tracks, 2 for scattered speckles
+	pub pattern: u32,
+	/// Camera distance where the decal starts fading out
+	pub fade_start: f32,
+	/// Camera distance where the decal is gone
+	pub fade_end: f32,
+}
+
+/// Alpha blended material for decals projected onto chunk meshes.
+///
+/// Decal meshes carry UVs across the decal box, which the shader shapes into the pattern.
+#[derive(Asset, AsBindGroup, Debug, Clone)]
+#[cfg_attr(not(feature = "reflect"), derive(TypePath))]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug))]
+pub struct DecalMaterial {
+	#[uniform(0)]
+	pub settings: DecalSettings,
+}
+
+impl Material for DecalMaterial {
+	fn fragment_shader() -> ShaderRef {
+		"shaders/decal_material.wgsl".into()
+	}
+
+	fn alpha_mode(&self) -> AlphaMode {
+		AlphaMode::Blend
+	}
+}
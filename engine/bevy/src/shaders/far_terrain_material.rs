@@ -0,0 +1,72 @@
+use bevy::{
+	prelude::*,
+	render::render_resource::{AsBindGroup, ShaderType},
+	shader::ShaderRef,
+};
+
+/// Uniforms of a [FarTerrainMaterial].
+#[derive(ShaderType, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, PartialEq))]
+pub struct FarTerrainSettings {
+	/// Ground color, lit by the sun
+	pub color: Vec4,
+	/// Color the ground fades into with distance, such as the horizon of the sky
+	pub haze: Vec4,
+	/// Direction toward the sun
+	pub toward_sun: Vec3,
+	/// Texels along each side of the heightfield
+	pub resolution: u32,
+	/// World xz of the corner of the heightfield
+	pub min: Vec2,
+	/// World size of the square the heightfield covers
+	pub size: f32,
+	/// Highest height in the heightfield, above which rays climbing away can't hit anything
+	pub top: f32,
+	/// Distance from the camera where rays start marching
+	pub near: f32,
+	/// Distance from the camera where rays give up
+	pub far: f32,
+	/// Distance over which the ground fades into the haze, from the near distance
+	pub haze_distance: f32,
+	/// Most steps a ray takes
+	pub max_steps: u32,
+}
+
+impl Default for FarTerrainSettings {
+	fn default() -> Self {
+		Self {
+			color: Vec4::new(0.35, 0.4, 0.3, 1.0),
+			haze: Vec4::new(0.7, 0.8, 0.9, 1.0),
+			toward_sun: Vec3::Y,
+			resolution: 1,
+			min: Vec2::ZERO,
+			size: 1.0,
+			top: 0.0,
+			near: 0.0,
+			far: 0.0,
+			haze_distance: 1.0,
+			max_steps: 0,
+		}
+	}
+}
+
+/// Material of the shell that raymarches the distant terrain of a heightfield behind the
+/// chunks, see [FarTerrain](crate::far_terrain::FarTerrain).
+///
+/// Fragments whose rays miss the heightfield are discarded, leaving the sky behind them.
+#[derive(Asset, AsBindGroup, Debug, Clone)]
+#[cfg_attr(not(feature = "reflect"), derive(TypePath))]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug))]
+pub struct FarTerrainMaterial {
+	#[uniform(0)]
+	pub settings: FarTerrainSettings,
+	/// Heights of the ground in R32Float texels, read without filtering
+	#[texture(1, sample_type = "float", filterable = false)]
+	pub heights: Option<Handle<Image>>,
+}
+
+impl Material for FarTerrainMaterial {
+	fn fragment_shader() -> ShaderRef {
+		"shaders/far_terrain_material.wgsl".into()
+	}
+}
@@ -0,0 +1,75 @@
+use bevy::{
+	prelude::*,
+	render::render_resource::{AsBindGroup, ShaderType},
+	shader::ShaderRef,
+};
+
+#[derive(Asset, AsBindGroup, Debug, Clone)]
+#[cfg_attr(not(feature = "reflect"), derive(TypePath))]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug))]
+pub struct LeafMaterial {
+	#[uniform(0)]
+	pub base_color: Vec4, // HSL or RGB in a vec4
+	#[uniform(1)]
+	pub bending: FoliageBending,
+	#[uniform(2)]
+	pub fade: FoliageFade,
+}
+
+impl LeafMaterial {
+	/// A material that no actor bends and that doesn't fade with distance.
+	pub fn new(base_color: Vec4) -> Self {
+		Self { base_color, bending: FoliageBending::default(), fade: FoliageFade::default() }
+	}
+}
+
+impl Material for LeafMaterial {
+	fn vertex_shader() -> ShaderRef {
+		"shaders/leaf_material.wgsl".into()
+	}
+
+	fn fragment_shader() -> ShaderRef {
+		"shaders/leaf_material.wgsl".into()
+	}
+
+	// Enable alpha blending for transparency
+	// This allows the leaf shape alpha to create see-through areas
+	fn alpha_mode(&self) -> AlphaMode {
+		AlphaMode::AlphaToCoverage
+	}
+}
+
+/// Most actors and imprints that bend foliage at once, matching the shader's array.
+pub const MAX_FOLIAGE_BENDERS: usize = 8;
+
+/// A sphere pushing foliage away from its center.
+#[derive(ShaderType, Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, Default, PartialEq))]
+pub struct FoliageBender {
+	pub position: Vec3,
+	pub radius: f32,
+	/// 1 while an actor stands in the sphere, falling to 0 as the foliage springs back
+	pub strength: f32,
+}
+
+/// The benders uploaded to foliage materials.
+#[derive(ShaderType, Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, Default, PartialEq))]
+pub struct FoliageBending {
+	pub benders: [FoliageBender; MAX_FOLIAGE_BENDERS],
+	/// Benders in use, from the start of the array
+	pub count: u32,
+	/// How far foliage at the center of a bender is pushed, as a share of its radius
+	pub bend: f32,
+}
+
+/// The distances from the camera over which foliage is dithered away.
+///
+/// Foliage is whole up to the start and gone past the end. Nothing fades while the end is not
+/// past the start.
+#[derive(ShaderType, Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, Default, PartialEq))]
+pub struct FoliageFade {
+	pub start: f32,
+	pub end: f32,
+}
@@ -0,0 +1,457 @@
+use crate::bark::BarkTextures;
+use bevy::{
+	prelude::*,
+	render::render_resource::{AsBindGroup, ShaderType},
+	shader::ShaderRef,
+};
+
+#[derive(Asset, AsBindGroup, Debug, Clone)]
+#[cfg_attr(not(feature = "reflect"), derive(TypePath))]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug))]
+pub struct EdgeMaterial {
+	#[uniform(0)]
+	pub base_color: Vec4, // HSL or RGB in a vec4
+	#[uniform(1)]
+	pub coverage: Coverage,
+	/// Share of fragments dithered away, from 0 to 1; negative values keep only the share
+	/// that the same positive value would remove, for fading one mesh in as another fades out
+	#[uniform(2)]
+	pub dissolve: f32,
+	#[uniform(3)]
+	pub strata: Strata,
+	#[uniform(4)]
+	pub bark: Bark,
+	#[texture(5)]
+	#[sampler(6)]
+	pub bark_normal: Option<Handle<Image>>,
+	#[texture(7)]
+	#[sampler(8)]
+	pub bark_roughness: Option<Handle<Image>>,
+	#[uniform(9)]
+	pub distant_shadow: DistantShadow,
+	#[texture(10)]
+	#[sampler(11)]
+	pub distant_shadow_map: Option<Handle<Image>>,
+}
+
+impl EdgeMaterial {
+	/// A material with no coverage layer.
+	pub fn new(base_color: Vec4) -> Self {
+		Self {
+			base_color,
+			coverage: Coverage::NONE,
+			dissolve: 0.0,
+			strata: Strata::NONE,
+			bark: Bark::NONE,
+			bark_normal: None,
+			bark_roughness: None,
+			distant_shadow: DistantShadow::NONE,
+			distant_shadow_map: None,
+		}
+	}
+
+	pub fn with_coverage(mut self, coverage: Coverage) -> Self {
+		self.coverage = coverage;
+		self
+	}
+
+	pub fn with_strata(mut self, strata: Strata) -> Self {
+		self.strata = strata;
+		self
+	}
+
+	/// Lays generated bark textures over the material, such as for trunks and sticks.
+	pub fn with_bark(mut self, textures: &BarkTextures) -> Self {
+		self.bark = textures.bark;
+		self.bark_normal = Some(textures.normal.clone());
+		self.bark_roughness = Some(textures.roughness.clone());
+		self
+	}
+
+	/// Shades distant surfaces with a baked shadow map, such as one from a
+	/// [TerrainShadowMap](crate::terrain_shadow::TerrainShadowMap).
+	pub fn with_distant_shadow(mut self, shadow: DistantShadow, map: Handle<Image>) -> Self {
+		self.distant_shadow = shadow;
+		self.distant_shadow_map = Some(map);
+		self
+	}
+
+	pub fn with_dissolve(mut self, dissolve: f32) -> Self {
+		self.dissolve = dissolve.clamp(-1.0, 1.0);
+		self
+	}
+}
+
+impl Material for EdgeMaterial {
+	fn fragment_shader() -> ShaderRef {
+		"shaders/edge_material.wgsl".into()
+	}
+}
+
+/// A layer, such as snow or moss, blended over the upward facing surfaces of a material.
+///
+/// Surfaces are covered once the up component of their world normal passes the threshold,
+/// fading in over the softness. Coverage can also grow with altitude, so only peaks are snowed.
+#[derive(ShaderType, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, PartialEq))]
+pub struct Coverage {
+	pub color: Vec4,
+	/// Strength of the layer on fully covered surfaces, 0 disables it
+	pub amount: f32,
+	/// Up component of the world normal where coverage starts
+	pub threshold: f32,
+	/// Normal range over which coverage fades in above the threshold
+	pub softness: f32,
+	/// World height where coverage starts
+	pub altitude_start: f32,
+	/// Height range over which coverage grows to full, 0 ignores altitude
+	pub altitude_range: f32,
+}
+
+impl Default for Coverage {
+	fn default() -> Self {
+		Self::NONE
+	}
+}
+
+impl Coverage {
+	pub const NONE: Coverage = Coverage {
+		color: Vec4::ONE,
+		amount: 0.0,
+		threshold: 0.7,
+		softness: 0.15,
+		altitude_start: 0.0,
+		altitude_range: 0.0,
+	};
+
+	/// Bright snow settling on flat ground.
+	pub fn snow() -> Self {
+		Self { color: Vec4::new(0.95, 0.97, 1.0, 1.0), amount: 1.0, ..Self::NONE }
+	}
+
+	/// Patchy moss that also clings to steeper surfaces.
+	pub fn moss() -> Self {
+		Self {
+			color: Vec4::new(0.33, 0.45, 0.2, 1.0),
+			amount: 0.7,
+			threshold: 0.45,
+			softness: 0.3,
+			..Self::NONE
+		}
+	}
+
+	pub fn with_color(mut self, color: Vec4) -> Self {
+		self.color = color;
+		self
+	}
+
+	pub fn with_amount(mut self, amount: f32) -> Self {
+		self.amount = amount.clamp(0.0, 1.0);
+		self
+	}
+
+	pub fn with_threshold(mut self, threshold: f32, softness: f32) -> Self {
+		self.threshold = threshold;
+		self.softness = softness.max(0.0);
+		self
+	}
+
+	/// Grows coverage from nothing at `start` to full at `start + range`.
+	pub fn with_altitude(mut self, start: f32, range: f32) -> Self {
+		self.altitude_start = start;
+		self.altitude_range = range.max(0.0);
+		self
+	}
+
+	/// How covered a surface is, from 0 to 1, mirroring the edge material shader.
+	pub fn weight(&self, world_normal: Vec3, world_height: f32) -> f32 {
+		let up = world_normal.normalize_or_zero().y;
+		let slope = if self.softness > 0.0 {
+			let t = ((up - self.threshold) / self.softness).clamp(0.0, 1.0);
+			t * t * (3.0 - 2.0 * t)
+		} else if up >= self.threshold {
+			1.0
+		} else {
+			0.0
+		};
+		let altitude = if self.altitude_range > 0.0 {
+			((world_height - self.altitude_start) / self.altitude_range).clamp(0.0, 1.0)
+		} else {
+			1.0
+		};
+		(slope * altitude * self.amount).clamp(0.0, 1.0)
+	}
+}
+
+/// Tileable bark textures projected onto a material from the three world axes, so meshes
+/// need no UVs, with the ridges of the bark running up the world y axis.
+///
+/// Generated from a [BarkTemplate](crate::bark::BarkTemplate).
+#[derive(ShaderType, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, PartialEq))]
+pub struct Bark {
+	/// World size of a tile of the textures
+	pub tile_size: f32,
+	/// Strength of the normal map, 0 disables the bark
+	pub strength: f32,
+}
+
+impl Default for Bark {
+	fn default() -> Self {
+		Self::NONE
+	}
+}
+
+impl Bark {
+	pub const NONE: Bark = Bark { tile_size: 1.0, strength: 0.0 };
+
+	pub fn new(tile_size: f32) -> Self {
+		Self { tile_size: tile_size.max(f32::EPSILON), strength: 1.0 }
+	}
+
+	pub fn with_strength(mut self, strength: f32) -> Self {
+		self.strength = strength.max(0.0);
+		self
+	}
+}
+
+/// Shadows of distant terrain read from a baked light map covering a square of the world xz
+/// plane, for surfaces beyond the reach of the real-time shadow cascades.
+///
+/// The baked shadows fade in with distance from the camera, from the fade start over the fade
+/// range, so near surfaces keep their real-time shadows. Baked by a
+/// [TerrainShadowMap](crate::terrain_shadow::TerrainShadowMap).
+#[derive(ShaderType, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, PartialEq))]
+pub struct DistantShadow {
+	/// World xz corner of the square the map covers
+	pub min: Vec2,
+	/// World size of the square the map covers
+	pub size: f32,
+	/// Distance from the camera where the baked shadows start to fade in
+	pub fade_start: f32,
+	/// Distance over which the baked shadows fade in to full
+	pub fade_range: f32,
+	/// Share of the light taken away in full shadow, 0 disables the shadows
+	pub strength: f32,
+}
+
+impl Default for DistantShadow {
+	fn default() -> Self {
+		Self::NONE
+	}
+}
+
+impl DistantShadow {
+	pub const NONE: DistantShadow = DistantShadow {
+		min: Vec2::ZERO,
+		size: 1.0,
+		fade_start: 0.0,
+		fade_range: 0.0,
+		strength: 0.0,
+	};
+
+	/// How much the baked shadows show at a distance from the camera, from 0 to 1, mirroring
+	/// the edge material shader.
+	pub fn fade(&self, distance: f32) -> f32 {
+		if self.fade_range <= 0.0 {
+			return if distance >= self.fade_start { 1.0 } else { 0.0 };
+		}
+		let t = ((distance - self.fade_start) / self.fade_range).clamp(0.0, 1.0);
+		t * t * (3.0 - 2.0 * t)
+	}
+}
+
+/// Most layers a [Strata] cycles through.
+pub const STRATA_LAYERS: usize = 4;
+
+/// Horizontal bands of sediment painted over the steep surfaces of a material, such as cliffs.
+///
+/// Layers of the given thickness stack up from the base height, cycling through the colors,
+/// with their boundaries wandering up and down by the wobble. They show where the up component
+/// of the world normal falls below the steepness, fading in over the softness, and sit under
+/// any [Coverage].
+#[derive(ShaderType, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, PartialEq))]
+pub struct Strata {
+	pub colors: [Vec4; STRATA_LAYERS],
+	/// Colors in use, up to [STRATA_LAYERS]; 0 disables the strata
+	pub layers: u32,
+	/// Height of a layer in world units
+	pub thickness: f32,
+	/// World height of the bottom of the first layer
+	pub base_height: f32,
+	/// Strength of the strata on fully steep surfaces
+	pub amount: f32,
+	/// Up component of the world normal below which strata show
+	pub steepness: f32,
+	/// Normal range over which strata fade in below the steepness
+	pub softness: f32,
+	/// How far layer boundaries wander up and down, in world units
+	pub wobble: f32,
+	/// Cells of the wobble noise per world unit across the xz plane
+	pub wobble_frequency: f32,
+}
+
+impl Default for Strata {
+	fn default() -> Self {
+		Self::NONE
+	}
+}
+
+impl Strata {
+	pub const NONE: Strata = Strata {
+		colors: [Vec4::ONE; STRATA_LAYERS],
+		layers: 0,
+		thickness: 1.0,
+		base_height: 0.0,
+		amount: 0.0,
+		steepness: 0.6,
+		softness: 0.2,
+		wobble: 0.0,
+		wobble_frequency: 0.1,
+	};
+
+	/// Warm sandstone layers with a darker shale band.
+	pub fn sandstone() -> Self {
+		Self {
+			colors: [
+				Vec4::new(0.76, 0.6, 0.42, 1.0),
+				Vec4::new(0.66, 0.47, 0.33, 1.0),
+				Vec4::new(0.82, 0.7, 0.52, 1.0),
+				Vec4::new(0.42, 0.36, 0.33, 1.0),
+			],
+			layers: STRATA_LAYERS as u32,
+			thickness: 0.6,
+			amount: 0.8,
+			wobble: 0.25,
+			..Self::NONE
+		}
+	}
+
+	/// Cycles through the colors, up to [STRATA_LAYERS] of them.
+	pub fn with_colors(mut self, colors: &[Vec4]) -> Self {
+		let layers = colors.len().min(STRATA_LAYERS);
+		self.colors[..layers].copy_from_slice(&colors[..layers]);
+		self.layers = layers as u32;
+		self
+	}
+
+	pub fn with_amount(mut self, amount: f32) -> Self {
+		self.amount = amount.clamp(0.0, 1.0);
+		self
+	}
+
+	/// Stacks layers of the thickness up from the base height.
+	pub fn with_layers(mut self, base_height: f32, thickness: f32) -> Self {
+		self.base_height = base_height;
+		self.thickness = thickness.max(f32::EPSILON);
+		self
+	}
+
+	pub fn with_steepness(mut self, steepness: f32, softness: f32) -> Self {
+		self.steepness = steepness;
+		self.softness = softness.max(0.0);
+		self
+	}
+
+	pub fn with_wobble(mut self, wobble: f32, frequency: f32) -> Self {
+		self.wobble = wobble.max(0.0);
+		self.wobble_frequency = frequency;
+		self
+	}
+
+	/// How strongly the strata show on a surface, from 0 to 1, mirroring the edge material
+	/// shader.
+	pub fn weight(&self, world_normal: Vec3) -> f32 {
+		if self.layers == 0 {
+			return 0.0;
+		}
+		let up = world_normal.normalize_or_zero().y.abs();
+		let steep = if self.softness > 0.0 {
+			let t = ((self.steepness - up) / self.softness).clamp(0.0, 1.0);
+			t * t * (3.0 - 2.0 * t)
+		} else if up <= self.steepness {
+			1.0
+		} else {
+			0.0
+		};
+		(steep * self.amount).clamp(0.0, 1.0)
+	}
+
+	/// The layer at a world position, mirroring the edge material shader.
+	pub fn layer(&self, world_position: Vec3) -> usize {
+		if self.layers == 0 {
+			return 0;
+		}
+		let wobble =
+			(value_noise(world_position.xz() * self.wobble_frequency) * 2.0 - 1.0) * self.wobble;
+		let band = ((world_position.y + wobble - self.base_height) / self.thickness).floor();
+		band.rem_euclid(self.layers as f32) as usize
+	}
+}
+
+/// Hash of a lattice point into 0 to 1, as in the edge material shader.
+fn lattice_hash(cell: Vec2) -> f32 {
+	((cell.dot(Vec2::new(127.1, 311.7))).sin() * 43758.547).rem_euclid(1.0)
+}
+
+/// Smoothly interpolated lattice noise from 0 to 1, as in the edge material shader.
+fn value_noise(p: Vec2) -> f32 {
+	let cell = p.floor();
+	let f = p - cell;
+	let u = f * f * (Vec2::splat(3.0) - 2.0 * f);
+	let corner = |x: f32, y: f32| lattice_hash(cell + Vec2::new(x, y));
+	let bottom = corner(0.0, 0.0) + (corner(1.0, 0.0) - corner(0.0, 0.0)) * u.x;
+	let top = corner(0.0, 1.0) + (corner(1.0, 1.0) - corner(0.0, 1.0)) * u.x;
+	bottom + (top - bottom) * u.y
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_coverage_settles_on_high_flat_ground() {
+		let snow = Coverage::snow().with_altitude(10.0, 20.0);
+
+		assert_eq!(snow.weight(Vec3::Y, 40.0), 1.0);
+		assert_eq!(snow.weight(Vec3::X, 40.0), 0.0);
+		assert_eq!(snow.weight(Vec3::Y, 5.0), 0.0);
+		assert!((snow.weight(Vec3::Y, 20.0) - 0.5).abs() < 1e-6);
+
+		// Steeper slopes are covered less
+		let gentle = snow.weight(Vec3::new(0.2, 1.0, 0.0), 40.0);
+		let steep = snow.weight(Vec3::new(0.8, 1.0, 0.0), 40.0);
+		assert!(gentle > steep && steep > 0.0);
+
+		assert_eq!(Coverage::NONE.weight(Vec3::Y, 40.0), 0.0);
+	}
+
+	#[test]
+	fn test_strata_band_steep_surfaces() {
+		let strata = Strata::sandstone().with_layers(2.0, 0.5).with_wobble(0.0, 0.1);
+
+		assert_eq!(strata.weight(Vec3::X), 0.8);
+		assert_eq!(strata.weight(Vec3::Y), 0.0);
+		assert_eq!(Strata::NONE.weight(Vec3::X), 0.0);
+
+		// Layers stack up from the base height and cycle through the colors
+		let layers: Vec<_> = [2.1, 2.6, 3.1, 3.6, 4.1, 1.9]
+			.map(|y| strata.layer(Vec3::new(3.0, y, -7.0)))
+			.into();
+		assert_eq!(layers, vec![0, 1, 2, 3, 0, 3]);
+
+		// The wobble moves boundaries by no more than its size
+		let wobbly = strata.with_wobble(0.2, 0.3);
+		for x in 0..20 {
+			let at = |y: f32| wobbly.layer(Vec3::new(x as f32 * 0.7, y, 1.3));
+			assert_eq!(at(2.21), 0);
+			assert_eq!(at(2.75), 1);
+		}
+
+		let two = Strata::sandstone().with_colors(&[Vec4::ONE, Vec4::ZERO]).with_layers(0.0, 1.0);
+		assert_eq!(two.layers, 2);
+		assert_eq!(two.layer(Vec3::new(0.0, 2.5, 0.0)), 0);
+	}
+}
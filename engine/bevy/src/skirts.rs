@@ -0,0 +1,183 @@
+use crate::cascade::CascadeChunk;
+use crate::cpu::MeshData;
+use crate::processor::MeshProcessor;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::collections::{HashMap, HashSet};
+
+/// A [MeshProcessor] hanging skirts from the edges of chunk meshes along the chunk faces, to
+/// hide the cracks between cascade rings of different resolution.
+///
+/// Where a chunk meets a coarser or finer neighbour, the two meshes cut the shared face along
+/// different lines and leave slivers of sky between them. Each open edge on a chunk face is
+/// extruded into the solid side of the surface, against its normals, and kept in the plane of
+/// the face, so the skirt fills the gap from behind without poking into the neighbour. Skirts
+/// are `depth` voxels of the chunk deep; the crack between rings is at most a voxel of the
+/// coarser ring, so rings that grow their voxels by more than that need deeper skirts.
+#[derive(Debug, Clone)]
+pub struct ChunkSkirts {
+	/// Depth of the skirts, in voxels of the chunk
+	pub depth: f32,
+	/// Distance, in voxels, under which vertices count as the same point
+	pub weld: f32,
+}
+
+impl Default for ChunkSkirts {
+	fn default() -> Self {
+		Self { depth: 2.0, weld: 1e-3 }
+	}
+}
+
+impl ChunkSkirts {
+	pub fn new(depth: f32) -> Self {
+		Self { depth: depth.max(0.0), ..default() }
+	}
+
+	pub fn with_weld(mut self, weld: f32) -> Self {
+		self.weld = weld;
+		self
+	}
+
+	/// Adds the skirts to the mesh of the chunk, returning how many edges got one.
+	pub fn add_skirts(&self, chunk: &CascadeChunk, mesh: &mut MeshData) -> usize {
+		let voxel = (chunk.size / (UVec3::ONE << chunk.res_2).as_vec3()).min_element();
+		let weld = (voxel * self.weld).max(f32::EPSILON);
+		let depth = voxel * self.depth;
+
+		// Neighbouring cubes don't share vertices, so edges are matched by welded points
+		let mut ids: HashMap<IVec3, usize> = HashMap::new();
+		let points: Vec<usize> = mesh
+			.positions
+			.iter()
+			.map(|position| {
+				let next = ids.len();
+				let key = (Vec3::from_array(*position) / weld).round().as_ivec3();
+				*ids.entry(key).or_insert(next)
+			})
+			.collect();
+		let faces = |vertex: u32| {
+			let position = Vec3::from_array(mesh.positions[vertex as usize]);
+			let mut faces = 0u8;
+			for axis in 0..3 {
+				faces |= u8::from(position[axis].abs() < weld) << (axis * 2);
+				faces |=
+					u8::from((position[axis] - chunk.size[axis]).abs() < weld) << (axis * 2 + 1);
+			}
+			faces
+		};
+
+		let edges: Vec<(u32, u32)> = mesh
+			.indices
+			.chunks_exact(3)
+			.flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+			.collect();
+		let welded: HashSet<(usize, usize)> =
+			edges.iter().map(|(a, b)| (points[*a as usize], points[*b as usize])).collect();
+		let open: Vec<(u32, u32, usize)> = edges
+			.into_iter()
+			.filter(|(a, b)| {
+				let (a, b) = (points[*a as usize], points[*b as usize]);
+				a != b && !welded.contains(&(b, a))
+			})
+			.filter_map(|(a, b)| {
+				let shared = faces(a) & faces(b);
+				(shared != 0).then_some((a, b, shared.trailing_zeros() as usize / 2))
+			})
+			.collect();
+
+		// Skirt vertices are shared by the edges meeting at a point on the same face
+		let has_colors = mesh.colors.len() == mesh.positions.len();
+		let mut skirt_vertices: HashMap<(usize, usize), u32> = HashMap::new();
+		let mut skirt_vertex = |mesh: &mut MeshData, vertex: u32, axis: usize| {
+			*skirt_vertices.entry((points[vertex as usize], axis)).or_insert_with(|| {
+				let i = vertex as usize;
+				let position = Vec3::from_array(mesh.positions[i]);
+				let mut lowered = position - Vec3::from_array(mesh.normals[i]) * depth;
+				lowered[axis] = position[axis];
+				mesh.positions.push(lowered.to_array());
+				mesh.normals.push(mesh.normals[i]);
+				mesh.uvs.push(mesh.uvs[i]);
+				if has_colors {
+					mesh.colors.push(mesh.colors[i]);
+				}
+				(mesh.positions.len() - 1) as u32
+			})
+		};
+		for &(a, b, axis) in &open {
+			let lowered_a = skirt_vertex(mesh, a, axis);
+			let lowered_b = skirt_vertex(mesh, b, axis);
+			// Wound like a triangle continuing the surface past the edge
+			mesh.indices.extend([b, a, lowered_a, b, lowered_a, lowered_b]);
+		}
+		open.len()
+	}
+}
+
+impl<S: Sdf> MeshProcessor<S> for ChunkSkirts {
+	fn name(&self) -> &str {
+		"chunk skirts"
+	}
+
+	fn process(&self, _sdf: &S, chunk: &CascadeChunk, mut mesh: MeshData) -> Option<MeshData> {
+		self.add_skirts(chunk, &mut mesh);
+		Some(mesh)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::cpu::CpuMeshGenerator;
+	use std::sync::Arc;
+
+	struct Hills;
+
+	impl Sdf for Hills {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.y - 2.1 - 0.4 * (p.x * 0.9).sin() * (p.z * 0.7).cos()
+		}
+	}
+
+	struct Sphere;
+
+	impl Sdf for Sphere {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.distance(Vec3::splat(2.0)) - 1.1
+		}
+	}
+
+	#[test]
+	fn test_skirts_hang_from_chunk_faces_into_the_ground() {
+		let chunk = CascadeChunk::cube(Vec3::ZERO, 4.0, 3);
+		let Some(mesh) = CpuMeshGenerator::generate_chunk_mesh_data(&chunk, Arc::new(Hills)) else {
+			panic!("expected a hills mesh");
+		};
+		let Some(skirted) = ChunkSkirts::default().process(&Hills, &chunk, mesh.clone()) else {
+			panic!("expected the skirted mesh");
+		};
+		let added = skirted.triangle_count() - mesh.triangle_count();
+		assert!(added > 0);
+		assert_eq!(added % 2, 0);
+		assert_eq!(skirted.uvs.len(), skirted.positions.len());
+
+		// The skirts stay on the chunk faces, below the surface they hang from
+		for position in &skirted.positions[mesh.positions.len()..] {
+			let position = Vec3::from_array(*position);
+			let on_face = (0..3).any(|axis| {
+				position[axis].abs() < 1e-4 || (position[axis] - chunk.size[axis]).abs() < 1e-4
+			});
+			assert!(on_face, "{position} is off the chunk faces");
+			assert!(Hills.distance(position) < 0.0, "{position} is above the ground");
+			assert!(position.y < 2.6);
+		}
+
+		// A mesh that doesn't reach the faces is left alone
+		let Some(sphere) = CpuMeshGenerator::generate_chunk_mesh_data(&chunk, Arc::new(Sphere))
+		else {
+			panic!("expected a sphere mesh");
+		};
+		let mut skirted = sphere.clone();
+		assert_eq!(ChunkSkirts::default().add_skirts(&chunk, &mut skirted), 0);
+		assert_eq!(skirted.indices, sphere.indices);
+	}
+}
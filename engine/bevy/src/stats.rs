@@ -0,0 +1,135 @@
+use crate::chunk::{ChunkConfig, ChunkId, LoadedChunks, TerrainChunk};
+use crate::hole_repair::MeshHoles;
+use bevy::mesh::Indices;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::collections::{BTreeMap, HashSet};
+
+/// Live counts of what the world holds, for debug overlays.
+///
+/// Counting walks every mesh asset, so the counts are only collected while enabled.
+/// Counts the engine can't see, such as vegetation entities and mesh cache sizes, are
+/// reported by the game with [WorldStats::set_count].
+#[derive(Resource, Debug, Clone, Default)]
+pub struct WorldStats {
+	pub enabled: bool,
+	/// Loaded terrain chunks by ring of the cascade; the ring past the last is the grid
+	pub chunks_per_ring: BTreeMap<u32, usize>,
+	/// Chunks found all air or all ground and never generated
+	pub skipped_chunks: usize,
+	/// Chunks pinned in [LoadedChunks] to stay loaded wherever the camera is
+	pub pinned_chunks: usize,
+	pub terrain_triangles: usize,
+	/// Mesh assets in the main world, which leaves out the chunk meshes
+	pub mesh_assets: usize,
+	/// Bytes of vertex and index data across the mesh assets, an estimate of their VRAM
+	pub mesh_bytes: usize,
+	/// Holes closed in generated meshes, if a [MeshHoles] resource is registered
+	pub repaired_holes: usize,
+	/// Holes left open in generated meshes, if a [MeshHoles] resource is registered
+	pub open_holes: usize,
+	/// Counts reported by the game, by name
+	pub counts: BTreeMap<&'static str, usize>,
+}
+
+impl WorldStats {
+	pub fn set_count(&mut self, name: &'static str, count: usize) {
+		self.counts.insert(name, count);
+	}
+
+	pub fn loaded_chunks(&self) -> usize {
+		self.chunks_per_ring.values().sum()
+	}
+}
+
+/// The size of a chunk's mesh, recorded when it is spawned.
+///
+/// Chunk meshes only live in the render world, so they are gone from the mesh assets once
+/// they are extracted.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkMeshSize {
+	pub triangles: usize,
+	pub bytes: usize,
+}
+
+impl ChunkMeshSize {
+	pub fn of(mesh: &Mesh) -> Self {
+		Self { triangles: mesh_triangles(mesh), bytes: mesh_bytes(mesh) }
+	}
+}
+
+/// Triangles of a triangle list mesh.
+pub fn mesh_triangles(mesh: &Mesh) -> usize {
+	mesh.indices().map_or(mesh.count_vertices(), Indices::len) / 3
+}
+
+/// Bytes of the vertex and index buffers of a mesh.
+pub fn mesh_bytes(mesh: &Mesh) -> usize {
+	let vertices = mesh.count_vertices() * mesh.get_vertex_size() as usize;
+	let indices = match mesh.indices() {
+		Some(Indices::U16(indices)) => indices.len() * 2,
+		Some(Indices::U32(indices)) => indices.len() * 4,
+		None => 0,
+	};
+	vertices + indices
+}
+
+/// Collects the [WorldStats] of the terrain while they are enabled.
+///
+/// Mesh memory adds the chunk meshes to the meshes still in the main world.
+pub fn collect_world_stats<S: Sdf + Send + Sync + 'static>(
+	mut stats: ResMut<WorldStats>,
+	config: Res<ChunkConfig<S>>,
+	loaded_chunks: Res<LoadedChunks>,
+	meshes: Res<Assets<Mesh>>,
+	chunks: Query<(&TerrainChunk, Option<&Mesh3d>, Option<&ChunkMeshSize>)>,
+	holes: Option<Res<MeshHoles>>,
+) {
+	if !stats.enabled {
+		return;
+	}
+
+	let stats = &mut *stats;
+	stats.chunks_per_ring.clear();
+	stats.terrain_triangles = 0;
+	stats.mesh_bytes = 0;
+	let mut chunk_meshes = HashSet::new();
+	for (chunk, mesh, size) in &chunks {
+		*stats
+			.chunks_per_ring
+			.entry(ChunkId::from_chunk(&chunk.chunk, config.min_size).ring())
+			.or_default() += 1;
+		if let Some(size) = size {
+			stats.terrain_triangles += size.triangles;
+			stats.mesh_bytes += size.bytes;
+		}
+		if let Some(mesh) = mesh {
+			chunk_meshes.insert(mesh.id());
+		}
+	}
+	stats.skipped_chunks = loaded_chunks.skipped_len();
+	stats.pinned_chunks = loaded_chunks.pinned_len();
+	if let Some(holes) = holes {
+		stats.repaired_holes = holes.repaired();
+		stats.open_holes = holes.open();
+	}
+	stats.mesh_assets = meshes.len();
+	stats.mesh_bytes += meshes
+		.iter()
+		.filter(|(id, _)| !chunk_meshes.contains(id))
+		.map(|(_, mesh)| mesh_bytes(mesh))
+		.sum::<usize>();
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_mesh_stats() {
+		let mesh = Mesh::from(Cuboid::default());
+		assert_eq!(mesh_triangles(&mesh), 12);
+		// Positions, normals and uvs for 24 vertices, and 36 u32 indices
+		assert_eq!(mesh_bytes(&mesh), 24 * 32 + 36 * 4);
+	}
+}
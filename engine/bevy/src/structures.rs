@@ -0,0 +1,277 @@
+use crate::atlas::{AtlasId, AtlasKind, WorldAtlas};
+use crate::chunk::{ChunkConfig, ChunkId, LoadedChunks};
+use bevy::math::bounding::Aabb3d;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::collections::{BTreeMap, HashSet};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Spawns a structure, returning the entity that despawns it with everything it spawned.
+pub type StructureSpawner = Arc<dyn Fn(&mut Commands) -> Entity + Send + Sync>;
+
+/// A structure streamed with the chunks it overlaps.
+struct StreamedStructure {
+	bounds: Aabb3d,
+	spawner: StructureSpawner,
+	/// Loaded chunks the structure overlaps
+	chunks: HashSet<ChunkId>,
+	entity: Option<Entity>,
+}
+
+impl StreamedStructure {
+	fn overlaps(&self, min: Vec3, max: Vec3) -> bool {
+		let (bounds_min, bounds_max) = (Vec3::from(self.bounds.min), Vec3::from(self.bounds.max));
+		bounds_min.cmplt(max).all() && bounds_max.cmpgt(min).all()
+	}
+}
+
+/// Streams structures bigger than a chunk, such as bridges and large ruins, with the chunks
+/// they overlap instead of with any one of them.
+///
+/// A structure spawns once the first chunk it overlaps loads and despawns once the last one
+/// unloads, so it is spawned at most once however many of its chunks come and go. Chunks are
+/// followed through [LoadedChunks], so chunks loaded without a mesh, such as the air under a
+/// bridge, hold a structure too. Structures are registered in the [WorldAtlas] and known by
+/// their atlas id. Bounds are in the local space of the SDF, like the chunks.
+#[derive(Resource)]
+pub struct StructureStreaming<S: Sdf + Send + Sync> {
+	structures: BTreeMap<AtlasId, StreamedStructure>,
+	/// The chunks that were loaded when the structures were last updated
+	loaded: HashSet<ChunkId>,
+	/// Structures registered since, which are checked against every loaded chunk
+	pending: Vec<AtlasId>,
+	/// Entities of replaced and unregistered structures, despawned by the next update
+	retired: Vec<Entity>,
+	/// Marker for the SDF whose chunks the structures follow
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for StructureStreaming<S> {
+	fn default() -> Self {
+		Self {
+			structures: BTreeMap::new(),
+			loaded: HashSet::new(),
+			pending: Vec::new(),
+			retired: Vec::new(),
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> StructureStreaming<S> {
+	/// Registers a structure within the bounds in the atlas, to be spawned by the spawner while
+	/// any chunk it overlaps is loaded.
+	///
+	/// Registering a structure of the same kind and name again replaces it, despawning the old
+	/// one.
+	pub fn register(
+		&mut self,
+		atlas: &mut WorldAtlas,
+		kind: AtlasKind,
+		name: impl Into<String>,
+		bounds: Aabb3d,
+		spawner: impl Fn(&mut Commands) -> Entity + Send + Sync + 'static,
+	) -> AtlasId {
+		let (min, max) = (Vec3::from(bounds.min), Vec3::from(bounds.max));
+		let id = atlas.register(kind, name, min.xz(), max.xz());
+		let structure = StreamedStructure {
+			bounds,
+			spawner: Arc::new(spawner),
+			chunks: HashSet::new(),
+			entity: None,
+		};
+		if let Some(replaced) = self.structures.insert(id, structure) {
+			self.retired.extend(replaced.entity);
+		}
+		self.pending.push(id);
+		id
+	}
+
+	/// Stops streaming a structure, despawning it with the next update.
+	///
+	/// Returns whether the structure was registered. It stays in the atlas.
+	pub fn unregister(&mut self, id: AtlasId) -> bool {
+		let Some(structure) = self.structures.remove(&id) else {
+			return false;
+		};
+		self.retired.extend(structure.entity);
+		true
+	}
+
+	/// The entity of the structure, while it is spawned.
+	pub fn entity(&self, id: AtlasId) -> Option<Entity> {
+		self.structures.get(&id).and_then(|structure| structure.entity)
+	}
+
+	/// The loaded chunks the structure overlaps.
+	pub fn overlapping_chunks(&self, id: AtlasId) -> impl Iterator<Item = ChunkId> + '_ {
+		self.structures
+			.get(&id)
+			.into_iter()
+			.flat_map(|structure| structure.chunks.iter().copied())
+	}
+
+	pub fn len(&self) -> usize {
+		self.structures.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.structures.is_empty()
+	}
+
+	/// Follows the chunks loaded and unloaded since the last update.
+	fn track_chunks(&mut self, loaded: &HashSet<ChunkId>, min_size: Vec3) {
+		let bounds = |id: &ChunkId| {
+			let origin = id.origin(min_size);
+			(origin, origin + id.size(min_size))
+		};
+
+		for id in self.loaded.difference(loaded) {
+			for structure in self.structures.values_mut() {
+				structure.chunks.remove(id);
+			}
+		}
+		for id in loaded.difference(&self.loaded) {
+			let (min, max) = bounds(id);
+			for structure in self.structures.values_mut() {
+				if structure.overlaps(min, max) {
+					structure.chunks.insert(*id);
+				}
+			}
+		}
+
+		for id in std::mem::take(&mut self.pending) {
+			let Some(structure) = self.structures.get_mut(&id) else {
+				continue;
+			};
+			structure.chunks = loaded
+				.iter()
+				.filter(|chunk| {
+					let (min, max) = bounds(chunk);
+					structure.overlaps(min, max)
+				})
+				.copied()
+				.collect();
+		}
+		self.loaded.clone_from(loaded);
+	}
+}
+
+/// Spawns the structures whose first chunk loaded and despawns those whose last chunk
+/// unloaded.
+///
+/// Run it after manage_chunks, with a [StructureStreaming] resource registered.
+pub fn stream_structures<S: Sdf + Send + Sync + 'static>(
+	mut commands: Commands,
+	mut streaming: ResMut<StructureStreaming<S>>,
+	loaded_chunks: Res<LoadedChunks>,
+	chunk_config: Res<ChunkConfig<S>>,
+) {
+	if !loaded_chunks.is_changed() && streaming.pending.is_empty() && streaming.retired.is_empty() {
+		return;
+	}
+	let streaming = streaming.as_mut();
+
+	for entity in streaming.retired.drain(..) {
+		commands.entity(entity).despawn();
+	}
+	streaming.track_chunks(&loaded_chunks.chunks, chunk_config.min_size);
+
+	for structure in streaming.structures.values_mut() {
+		match (structure.entity, structure.chunks.is_empty()) {
+			(None, false) => structure.entity = Some((structure.spawner)(&mut commands)),
+			(Some(entity), true) => {
+				commands.entity(entity).despawn();
+				structure.entity = None;
+			}
+			_ => {}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::Ground;
+
+	#[derive(Component)]
+	struct Bridge;
+
+	#[test]
+	fn test_structures_stream_with_all_their_chunks() {
+		let mut app = App::new();
+		app.insert_resource(ChunkConfig::<Ground> { min_size: Vec3::splat(8.0), ..default() })
+			.insert_resource(LoadedChunks::default())
+			.init_resource::<StructureStreaming<Ground>>()
+			.add_systems(Update, stream_structures::<Ground>);
+
+		// A bridge spanning two chunks side by side
+		let mut atlas = WorldAtlas::new(3);
+		let bounds = Aabb3d::new(Vec3::new(8.0, 4.0, 4.0), Vec3::new(6.0, 1.0, 1.0));
+		let id = app.world_mut().resource_mut::<StructureStreaming<Ground>>().register(
+			&mut atlas,
+			AtlasKind::Landmark,
+			"bridge",
+			bounds,
+			|commands| commands.spawn(Bridge).id(),
+		);
+		assert_eq!(atlas.get(id).map(|entry| entry.center()), Some(Vec2::new(8.0, 4.0)));
+
+		let min_size = Vec3::splat(8.0);
+		let west = ChunkId::new(Vec3::ZERO, min_size, min_size);
+		let east = ChunkId::new(Vec3::new(8.0, 0.0, 0.0), min_size, min_size);
+		let far = ChunkId::new(Vec3::new(32.0, 0.0, 0.0), min_size, min_size);
+		let set_loaded = |app: &mut App, ids: &[ChunkId]| {
+			app.world_mut().resource_mut::<LoadedChunks>().retain(|_| false);
+			for id in ids {
+				app.world_mut().resource_mut::<LoadedChunks>().mark_loaded(*id);
+			}
+			app.update();
+		};
+		let bridges = |app: &mut App| {
+			let mut query = app.world_mut().query::<&Bridge>();
+			query.iter(app.world()).count()
+		};
+		let entity = |app: &App| app.world().resource::<StructureStreaming<Ground>>().entity(id);
+
+		// Nothing spawns for a chunk away from the bridge
+		set_loaded(&mut app, &[far]);
+		assert_eq!(bridges(&mut app), 0);
+
+		// The first chunk spawns it, and the second doesn't spawn it again
+		set_loaded(&mut app, &[far, west]);
+		assert_eq!(bridges(&mut app), 1);
+		let spawned = entity(&app);
+		set_loaded(&mut app, &[west, east]);
+		assert_eq!(bridges(&mut app), 1);
+		assert_eq!(entity(&app), spawned);
+		let streaming = app.world().resource::<StructureStreaming<Ground>>();
+		assert_eq!(streaming.overlapping_chunks(id).count(), 2);
+
+		// It stays while either chunk is loaded, and goes with the last
+		set_loaded(&mut app, &[east]);
+		assert_eq!(bridges(&mut app), 1);
+		assert_eq!(entity(&app), spawned);
+		set_loaded(&mut app, &[far]);
+		assert_eq!(bridges(&mut app), 0);
+		assert_eq!(entity(&app), None);
+
+		// A structure registered over loaded chunks spawns with the next update
+		set_loaded(&mut app, &[west, far]);
+		assert_eq!(bridges(&mut app), 1);
+		let tower = Aabb3d::new(Vec3::new(36.0, 4.0, 4.0), Vec3::splat(2.0));
+		let mut streaming = app.world_mut().resource_mut::<StructureStreaming<Ground>>();
+		let tower =
+			streaming.register(&mut atlas, AtlasKind::Landmark, "tower", tower, |commands| {
+				commands.spawn(Bridge).id()
+			});
+		app.update();
+		assert_eq!(bridges(&mut app), 2);
+
+		// Unregistering it despawns it
+		app.world_mut().resource_mut::<StructureStreaming<Ground>>().unregister(tower);
+		app.update();
+		assert_eq!(bridges(&mut app), 1);
+	}
+}
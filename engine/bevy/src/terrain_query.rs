@@ -0,0 +1,208 @@
+use crate::chunk_manager::SdfResource;
+use crate::cpu::sdf_normal;
+use crate::gizmos::column_surface;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use sdf::Sdf;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Step of the SDF gradient taken for ground normals, in the local space of the SDF
+const NORMAL_STEP: f32 = 0.05;
+
+/// Distance from the surface at which a traced ray has hit it
+const HIT_DISTANCE: f32 = 1e-3;
+
+/// Steps a ray takes before it is treated as blocked
+const MAX_TRACE_STEPS: usize = 256;
+
+/// Share of the SDF distance a ray advances by, as terrain SDFs overestimate on steep slopes
+const TRACE_RELAXATION: f32 = 0.9;
+
+/// Decides whether ground at a world position can be walked on, regardless of its slope.
+pub type WalkMaskFn = Arc<dyn Fn(Vec3) -> bool + Send + Sync>;
+
+/// The ground a [TerrainQuery] reports as walkable: no steeper than the maximum slope, and
+/// allowed by the mask if there is one, such as one keeping agents out of rivers.
+#[derive(Resource)]
+pub struct Walkability<S: Sdf + Send + Sync> {
+	/// Steepest walkable slope, in radians from the horizontal
+	pub max_slope: f32,
+	mask: Option<WalkMaskFn>,
+	/// Marker for the SDF whose ground is walked
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for Walkability<S> {
+	fn default() -> Self {
+		Self { max_slope: 40f32.to_radians(), mask: None, sdf: PhantomData }
+	}
+}
+
+impl<S: Sdf + Send + Sync> Walkability<S> {
+	pub fn with_max_slope(mut self, max_slope: f32) -> Self {
+		self.max_slope = max_slope;
+		self
+	}
+
+	pub fn with_mask(mut self, mask: impl Fn(Vec3) -> bool + Send + Sync + 'static) -> Self {
+		self.mask = Some(Arc::new(mask));
+		self
+	}
+
+	/// Whether ground at the position with the given normal can be walked on.
+	pub fn allows(&self, position: Vec3, normal: Vec3) -> bool {
+		normal.y >= self.max_slope.cos() && self.mask.as_ref().is_none_or(|mask| mask(position))
+	}
+}
+
+/// Where a ray traced by [TerrainQuery::ray_cast] met the terrain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainHit {
+	pub position: Vec3,
+	/// Distance along the ray to the hit
+	pub distance: f32,
+}
+
+/// Read-only queries of the terrain of an SDF for use inside systems, such as by AI and
+/// pathfinding crates.
+///
+/// Positions and distances are in world space, whatever the placement of the SDF, and the
+/// terrain is the SDF itself rather than its chunk meshes, so the queries hold where no
+/// chunks are loaded. Walkability is read from the [Walkability] resource, or its default
+/// if there is none.
+#[derive(SystemParam)]
+pub struct TerrainQuery<'w, S: Sdf + Send + Sync + 'static> {
+	sdf_resource: Res<'w, SdfResource<S>>,
+	walkability: Option<Res<'w, Walkability<S>>>,
+}
+
+impl<S: Sdf + Send + Sync + 'static> TerrainQuery<'_, S> {
+	/// Distance from a position to the terrain, negative underground.
+	///
+	/// A lower bound of the true distance for SDFs placed with a non-uniform scale.
+	pub fn distance(&self, position: Vec3) -> f32 {
+		self.sdf_resource.distance(position) - self.iso_level()
+	}
+
+	/// Whether a position is underground.
+	pub fn is_ground(&self, position: Vec3) -> bool {
+		self.distance(position) <= 0.0
+	}
+
+	/// Height of the ground between bottom and top in the column at xz.
+	///
+	/// None if the column is all air or all ground between them.
+	pub fn ground_height(&self, xz: Vec2, bottom: f32, top: f32) -> Option<f32> {
+		column_surface(|y| self.distance(Vec3::new(xz.x, y, xz.y)), bottom, top)
+	}
+
+	/// Normal of the terrain nearest a position, pointing out of the ground.
+	///
+	/// None where the SDF has no gradient.
+	pub fn normal(&self, position: Vec3) -> Option<Vec3> {
+		let transform = &self.sdf_resource.transform;
+		let local =
+			sdf_normal(self.sdf_resource.sdf.as_ref(), transform.to_local(position), NORMAL_STEP)?;
+		(transform.rotation * (local / transform.scale)).try_normalize()
+	}
+
+	/// Slope of the terrain nearest a position, in radians from the horizontal.
+	pub fn slope(&self, position: Vec3) -> Option<f32> {
+		self.normal(position).map(|normal| normal.y.clamp(-1.0, 1.0).acos())
+	}
+
+	/// Whether the ground at a position can be walked on, by its slope and mask.
+	pub fn is_walkable(&self, position: Vec3) -> bool {
+		let Some(normal) = self.normal(position) else {
+			return false;
+		};
+		match &self.walkability {
+			Some(walkability) => walkability.allows(position, normal),
+			None => Walkability::<S>::default().allows(position, normal),
+		}
+	}
+
+	/// Traces a ray along the direction until it meets the terrain, up to the max distance.
+	///
+	/// Rays starting underground hit where they start. Rays that run out of steps, which
+	/// happens grazing along the surface, are treated as hitting it where they stopped.
+	pub fn ray_cast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<TerrainHit> {
+		let direction = direction.try_normalize()?;
+		let mut travelled = 0.0;
+		for _ in 0..MAX_TRACE_STEPS {
+			let position = origin + direction * travelled;
+			let distance = self.distance(position);
+			if distance < HIT_DISTANCE {
+				return Some(TerrainHit { position, distance: travelled });
+			}
+			travelled += distance * TRACE_RELAXATION;
+			if travelled > max_distance {
+				return None;
+			}
+		}
+		Some(TerrainHit { position: origin + direction * travelled, distance: travelled })
+	}
+
+	/// Whether the terrain leaves a clear line between two positions.
+	pub fn line_of_sight(&self, from: Vec3, to: Vec3) -> bool {
+		self.ray_cast(from, to - from, from.distance(to)).is_none()
+	}
+
+	fn iso_level(&self) -> f32 {
+		self.sdf_resource.transform.distance_to_world(self.sdf_resource.iso_level)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::transform::WorldTransform;
+	use bevy::ecs::system::SystemState;
+
+	/// A ground at y = 0 rising into a 45 degree ramp past x = 10
+	struct Ramp;
+
+	impl Sdf for Ramp {
+		fn distance(&self, p: Vec3) -> f32 {
+			let flat = p.y;
+			let ramp = (p.y - (p.x - 10.0)) * std::f32::consts::FRAC_1_SQRT_2;
+			flat.min(ramp)
+		}
+	}
+
+	#[test]
+	fn test_terrain_queries_in_world_space() {
+		let mut world = World::new();
+		world.insert_resource(
+			SdfResource::new(Ramp).with_transform(WorldTransform::from_translation(Vec3::Y * 5.0)),
+		);
+		world.insert_resource(
+			Walkability::<Ramp>::default().with_mask(|position: Vec3| position.z < 50.0),
+		);
+		let mut state = SystemState::<TerrainQuery<Ramp>>::new(&mut world);
+		let terrain = state.get(&world);
+
+		let height = terrain.ground_height(Vec2::new(0.0, 0.0), -20.0, 20.0);
+		assert!(height.is_some_and(|height| (height - 5.0).abs() < 1e-3), "{height:?}");
+		assert!(terrain.is_ground(Vec3::new(0.0, 4.0, 0.0)));
+		assert!(terrain
+			.normal(Vec3::new(0.0, 5.0, 0.0))
+			.is_some_and(|n| n.distance(Vec3::Y) < 1e-3));
+
+		// Flat ground is walkable, the ramp too steep, and the mask rules out the far side
+		assert!(terrain.is_walkable(Vec3::new(0.0, 5.0, 0.0)));
+		assert!(!terrain.is_walkable(Vec3::new(20.0, 15.0, 0.0)));
+		assert!(!terrain.is_walkable(Vec3::new(0.0, 5.0, 60.0)));
+		let slope = terrain.slope(Vec3::new(20.0, 15.0, 0.0)).unwrap_or_default();
+		assert!((slope - std::f32::consts::FRAC_PI_4).abs() < 1e-3);
+
+		// The ramp blocks the view across it, but not along the flat
+		let eye = Vec3::new(0.0, 6.0, 0.0);
+		assert!(terrain.line_of_sight(eye, Vec3::new(0.0, 6.0, 40.0)));
+		assert!(!terrain.line_of_sight(eye, Vec3::new(30.0, 6.0, 0.0)));
+		let hit = terrain.ray_cast(eye, Vec3::X, 100.0);
+		assert!(hit.is_some_and(|hit| (hit.position.x - 11.0).abs() < 0.01), "{hit:?}");
+		assert_eq!(terrain.ray_cast(eye, Vec3::Y, 100.0), None);
+	}
+}
@@ -0,0 +1,360 @@
+use crate::chunk_manager::SdfResource;
+use crate::gizmos::column_surface;
+use crate::material::ChunkMaterialRegistry;
+use crate::shaders::outline::{DistantShadow, EdgeMaterial};
+use bevy::{
+	asset::RenderAssetUsages,
+	image::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor},
+	prelude::*,
+	render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use rayon::prelude::*;
+use sdf::Sdf;
+use std::marker::PhantomData;
+
+/// Shadows cast by the terrain of an SDF over distances the real-time shadow cascades don't
+/// reach, baked from its heightfield for the direction of the sun.
+///
+/// The map covers a square of the world xz plane centered on the camera. Each texel holds how
+/// much sun reaches the ground there, found by walking the heightfield toward the sun for the
+/// highest horizon. The map is baked again when the sun turns by more than the rebake angle,
+/// the camera leaves the middle of the map, or the SDF changes, and laid over the terrain
+/// materials of the [ChunkMaterialRegistry] by [bake_terrain_shadows].
+#[derive(Resource)]
+pub struct TerrainShadowMap<S: Sdf + Send + Sync> {
+	/// Texels along each side of the map
+	pub resolution: u32,
+	/// World size of the square the map covers
+	pub extent: f32,
+	/// World heights the ground is searched for between
+	pub height_range: (f32, f32),
+	/// Angle the sun turns through before the map is baked again, in radians
+	pub rebake_angle: f32,
+	/// Share of the extent the camera moves before the map is baked again around it
+	pub recenter: f32,
+	/// Angle over which the ground goes from lit to shadowed as the sun sets behind the
+	/// horizon, in radians
+	pub softness: f32,
+	/// Distance from the camera where the baked shadows start to fade in, and the distance
+	/// over which they fade in to full
+	pub fade: (f32, f32),
+	/// Share of the light taken away in full shadow
+	pub strength: f32,
+	image: Option<Handle<Image>>,
+	/// Center of the map and direction toward the sun it was last baked for
+	baked: Option<(Vec2, Vec3)>,
+	/// Marker for the SDF whose terrain casts the shadows
+	pub sdf: PhantomData<S>,
+}
+
+impl<S: Sdf + Send + Sync> Default for TerrainShadowMap<S> {
+	fn default() -> Self {
+		Self {
+			resolution: 256,
+			extent: 1024.0,
+			height_range: (-256.0, 256.0),
+			rebake_angle: 2f32.to_radians(),
+			recenter: 0.125,
+			softness: 3f32.to_radians(),
+			fade: (128.0, 64.0),
+			strength: 0.7,
+			image: None,
+			baked: None,
+			sdf: PhantomData,
+		}
+	}
+}
+
+impl<S: Sdf + Send + Sync> TerrainShadowMap<S> {
+	pub fn with_resolution(mut self, resolution: u32) -> Self {
+		self.resolution = resolution.max(1);
+		self
+	}
+
+	pub fn with_extent(mut self, extent: f32) -> Self {
+		self.extent = extent.max(f32::EPSILON);
+		self
+	}
+
+	pub fn with_height_range(mut self, bottom: f32, top: f32) -> Self {
+		self.height_range = (bottom.min(top), top.max(bottom));
+		self
+	}
+
+	pub fn with_rebake_angle(mut self, rebake_angle: f32) -> Self {
+		self.rebake_angle = rebake_angle.max(0.0);
+		self
+	}
+
+	pub fn with_softness(mut self, softness: f32) -> Self {
+		self.softness = softness.max(f32::EPSILON);
+		self
+	}
+
+	/// Fades the baked shadows in from the start distance over the range.
+	pub fn with_fade(mut self, start: f32, range: f32) -> Self {
+		self.fade = (start.max(0.0), range.max(0.0));
+		self
+	}
+
+	pub fn with_strength(mut self, strength: f32) -> Self {
+		self.strength = strength.clamp(0.0, 1.0);
+		self
+	}
+
+	/// The baked light map, once the map has been baked.
+	pub fn image(&self) -> Option<&Handle<Image>> {
+		self.image.as_ref()
+	}
+
+	/// World size of a texel.
+	pub fn texel_size(&self) -> f32 {
+		self.extent / self.resolution.max(1) as f32
+	}
+
+	/// The center of the map around a position, snapped to the texels so the shadows hold
+	/// still as the map follows the camera.
+	pub fn center_for(&self, position: Vec2) -> Vec2 {
+		(position / self.texel_size()).round() * self.texel_size()
+	}
+
+	/// Whether the map needs baking for a center and direction toward the sun.
+	pub fn needs_bake(&self, center: Vec2, toward_sun: Vec3) -> bool {
+		self.baked.is_none_or(|(baked_center, baked_sun)| {
+			baked_center.distance(center) > self.extent * self.recenter
+				|| baked_sun.angle_between(toward_sun) > self.rebake_angle
+		})
+	}
+
+	/// The shadows of the map as laid over a material, for the map baked around the center.
+	pub fn shadow(&self, center: Vec2) -> DistantShadow {
+		DistantShadow {
+			min: center - Vec2::splat(self.extent / 2.0),
+			size: self.extent,
+			fade_start: self.fade.0,
+			fade_range: self.fade.1,
+			strength: self.strength,
+		}
+	}
+
+	/// World heights of the ground under the texels of the map around the center, in rows
+	/// along z.
+	///
+	/// Columns of open air are at the bottom of the height range and solid columns at the top.
+	pub fn heights(&self, sdf_resource: &SdfResource<S>, center: Vec2) -> Vec<f32> {
+		let min = center - Vec2::splat(self.extent / 2.0);
+		column_heights(sdf_resource, min, self.texel_size(), self.resolution, self.height_range)
+	}
+
+	/// Share of the sun reaching the ground of each texel, from 0 in full shadow to 1 in full
+	/// sun, for light coming from the direction toward the sun.
+	///
+	/// Each texel walks the heightfield toward the sun to the edge of the map, so ground beyond
+	/// the map casts no shadows.
+	pub fn light(&self, heights: &[f32], toward_sun: Vec3) -> Vec<f32> {
+		let size = self.resolution.max(1) as usize;
+		let horizontal = toward_sun.xz();
+		if toward_sun.y <= 0.0 {
+			return vec![0.0; heights.len()];
+		}
+		if horizontal.length() < 1e-4 {
+			return vec![1.0; heights.len()];
+		}
+		let step = horizontal.normalize();
+		let elevation = toward_sun.y.atan2(horizontal.length());
+		let texel_size = self.texel_size();
+		let softness = self.softness.max(f32::EPSILON);
+
+		// Heights between the texel centers, none past the edge of the map
+		let height_at = |p: Vec2| {
+			let last = (size - 1) as f32;
+			if p.x < 0.0 || p.y < 0.0 || p.x > last || p.y > last {
+				return None;
+			}
+			let cell = p.floor().min(Vec2::splat((size.max(2) - 2) as f32));
+			let f = p - cell;
+			let at = |x: usize, y: usize| heights[y.min(size - 1) * size + x.min(size - 1)];
+			let (x, y) = (cell.x as usize, cell.y as usize);
+			let bottom = at(x, y) + (at(x + 1, y) - at(x, y)) * f.x;
+			let top = at(x, y + 1) + (at(x + 1, y + 1) - at(x, y + 1)) * f.x;
+			Some(bottom + (top - bottom) * f.y)
+		};
+
+		(0..heights.len())
+			.into_par_iter()
+			.map(|index| {
+				let origin = Vec2::new((index % size) as f32, (index / size) as f32);
+				let mut horizon = f32::NEG_INFINITY;
+				let mut steps = 1.0;
+				while let Some(height) = height_at(origin + step * steps) {
+					horizon = horizon.max((height - heights[index]).atan2(steps * texel_size));
+					steps += 1.0;
+				}
+				let t = ((elevation - horizon) / softness + 0.5).clamp(0.0, 1.0);
+				t * t * (3.0 - 2.0 * t)
+			})
+			.collect()
+	}
+
+	/// The light map of the texels.
+	pub fn light_image(&self, light: &[f32]) -> Image {
+		let size = self.resolution.max(1);
+		let mut image = Image::new(
+			Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+			TextureDimension::D2,
+			light.iter().map(|light| (light.clamp(0.0, 1.0) * 255.0) as u8).collect(),
+			TextureFormat::R8Unorm,
+			RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+		);
+		image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+			address_mode_u: ImageAddressMode::ClampToEdge,
+			address_mode_v: ImageAddressMode::ClampToEdge,
+			..ImageSamplerDescriptor::linear()
+		});
+		image
+	}
+}
+
+/// World heights of the ground under a square of texels from the min corner, in rows along z.
+///
+/// Columns of open air are at the bottom of the height range and solid columns at the top.
+pub(crate) fn column_heights<S: Sdf + Send + Sync>(
+	sdf_resource: &SdfResource<S>,
+	min: Vec2,
+	texel_size: f32,
+	resolution: u32,
+	(bottom, top): (f32, f32),
+) -> Vec<f32> {
+	let size = resolution.max(1);
+	let iso_level = sdf_resource.transform.distance_to_world(sdf_resource.iso_level);
+	(0..size * size)
+		.into_par_iter()
+		.map(|index| {
+			let texel = UVec2::new(index % size, index / size);
+			let xz = min + (texel.as_vec2() + 0.5) * texel_size;
+			let distance = |y: f32| sdf_resource.distance(Vec3::new(xz.x, y, xz.y)) - iso_level;
+			let solid = distance(top) <= 0.0;
+			column_surface(distance, bottom, top).unwrap_or(if solid { top } else { bottom })
+		})
+		.collect()
+}
+
+/// Bakes the [TerrainShadowMap] around the camera for the brightest directional light when it
+/// needs baking, and lays it over the materials of the [ChunkMaterialRegistry].
+///
+/// Materials registered after a bake are shaded from the next one.
+pub fn bake_terrain_shadows<S: Sdf + Send + Sync + 'static>(
+	camera_query: Query<&GlobalTransform, With<Camera3d>>,
+	light_query: Query<(&DirectionalLight, &GlobalTransform)>,
+	mut shadow_map: ResMut<TerrainShadowMap<S>>,
+	sdf_resource: Res<SdfResource<S>>,
+	registry: Res<ChunkMaterialRegistry>,
+	mut materials: ResMut<Assets<EdgeMaterial>>,
+	mut images: ResMut<Assets<Image>>,
+) {
+	let Ok(camera_transform) = camera_query.single() else {
+		return;
+	};
+	let Some((_, sun_transform)) = light_query
+		.iter()
+		.max_by(|(a, _), (b, _)| a.illuminance.total_cmp(&b.illuminance))
+	else {
+		return;
+	};
+	let toward_sun = -*sun_transform.forward();
+	let center = shadow_map.center_for(camera_transform.translation().xz());
+	if !shadow_map.needs_bake(center, toward_sun) && !sdf_resource.is_changed() {
+		return;
+	}
+
+	let heights = shadow_map.heights(&sdf_resource, center);
+	let image = shadow_map.light_image(&shadow_map.light(&heights, toward_sun));
+	let handle = match shadow_map.image.clone() {
+		Some(handle) if images.get(&handle).is_some() => {
+			if let Some(existing) = images.get_mut(&handle) {
+				*existing = image;
+			}
+			handle
+		}
+		_ => images.add(image),
+	};
+	shadow_map.image = Some(handle.clone());
+	shadow_map.baked = Some((center, toward_sun));
+	log::debug!("Baked terrain shadows around {center} for the sun toward {toward_sun}");
+
+	let shadow = shadow_map.shadow(center);
+	for material in registry.materials() {
+		if let Some(material) = materials.get_mut(material) {
+			material.distant_shadow = shadow;
+			material.distant_shadow_map = Some(handle.clone());
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Flat ground with a wall 10 high along the z axis
+	struct Wall;
+
+	impl Sdf for Wall {
+		fn distance(&self, p: Vec3) -> f32 {
+			let wall = (p.x.abs() - 1.0).max(p.y - 10.0);
+			p.y.min(wall)
+		}
+	}
+
+	#[test]
+	fn test_wall_shades_the_ground_away_from_the_sun() {
+		let mut app = App::new();
+		app.add_plugins(AssetPlugin::default())
+			.init_asset::<Image>()
+			.init_asset::<EdgeMaterial>()
+			.init_resource::<ChunkMaterialRegistry>()
+			.insert_resource(SdfResource::new(Wall))
+			.insert_resource(
+				TerrainShadowMap::<Wall>::default()
+					.with_resolution(64)
+					.with_extent(64.0)
+					.with_height_range(-5.0, 20.0),
+			)
+			.add_systems(Update, bake_terrain_shadows::<Wall>);
+		app.world_mut().spawn((Camera3d::default(), GlobalTransform::default()));
+		// The sun is up 45 degrees in the east
+		let toward_sun = Vec3::new(1.0, 1.0, 0.0).normalize();
+		app.world_mut().spawn((
+			DirectionalLight::default(),
+			GlobalTransform::from(Transform::default().looking_to(-toward_sun, Vec3::Y)),
+		));
+		app.update();
+
+		let shadow_map = app.world().resource::<TerrainShadowMap<Wall>>();
+		let heights = shadow_map.heights(app.world().resource::<SdfResource<Wall>>(), Vec2::ZERO);
+		let light = shadow_map.light(&heights, toward_sun);
+		// Texel 28 is 3.5 west of the wall, 37 east of it and 2 far out to the west
+		let row = 32 * 64;
+		assert!((heights[row + 32] - 10.0).abs() < 0.01, "{}", heights[row + 32]);
+		assert!(light[row + 28] < 0.1, "{}", light[row + 28]);
+		assert!(light[row + 37] > 0.9, "{}", light[row + 37]);
+		assert!(light[row + 2] > 0.9, "{}", light[row + 2]);
+
+		// The bake is laid over the terrain materials
+		let image = shadow_map.image().cloned();
+		assert!(image.is_some());
+		let registry = app.world().resource::<ChunkMaterialRegistry>();
+		let materials = app.world().resource::<Assets<EdgeMaterial>>();
+		for material in registry.materials() {
+			let material = materials.get(material);
+			assert!(material.is_some_and(|material| material.distant_shadow_map == image
+				&& material.distant_shadow.min == Vec2::splat(-32.0)));
+		}
+
+		// Small turns of the sun keep the bake, larger ones bake again
+		let nudged = Quat::from_rotation_z(1f32.to_radians()) * toward_sun;
+		assert!(!shadow_map.needs_bake(Vec2::ZERO, nudged));
+		let setting = Quat::from_rotation_z(5f32.to_radians()) * toward_sun;
+		assert!(shadow_map.needs_bake(Vec2::ZERO, setting));
+		assert!(shadow_map.needs_bake(Vec2::new(10.0, 0.0), toward_sun));
+	}
+}
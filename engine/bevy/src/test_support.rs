@@ -0,0 +1,49 @@
+//! The ground and app the tests of the chunk systems share.
+
+use crate::chunk::{ChunkConfig, LoadedChunks};
+use crate::chunk_manager::{ChunkResolutionConfig, SdfResource};
+use crate::material::ChunkMaterialRegistry;
+use crate::shaders::outline::EdgeMaterial;
+use bevy::prelude::*;
+use sdf::Sdf;
+
+/// A flat ground at the height
+#[derive(TypePath)]
+pub(crate) struct Ground(pub f32);
+
+impl Sdf for Ground {
+	fn distance(&self, p: Vec3) -> f32 {
+		p.y - self.0
+	}
+}
+
+/// An app with the smallest cascade of chunks over the [Ground] at the height, and a camera at
+/// the origin. Tests add the systems and resources they exercise.
+pub(crate) fn ground_app(height: f32) -> App {
+	let mut app = App::new();
+	app.add_plugins(AssetPlugin::default())
+		.init_asset::<Mesh>()
+		.init_asset::<EdgeMaterial>()
+		.init_resource::<ChunkMaterialRegistry>()
+		.insert_resource(ChunkConfig::<Ground> {
+			min_size: Vec3::splat(1.0),
+			number_of_rings: 1,
+			grid_radius: 1,
+			grid_multiple_2: 1,
+			..default()
+		})
+		.insert_resource(ChunkResolutionConfig::<Ground> { base_res_2: 2, ..default() })
+		.insert_resource(SdfResource::new(Ground(height)))
+		.insert_resource(LoadedChunks::default());
+	app.world_mut().spawn((Camera3d::default(), Transform::default()));
+	app
+}
+
+/// The camera of a [ground_app].
+pub(crate) fn camera(app: &mut App) -> Entity {
+	let world = app.world_mut();
+	let Ok(camera) = world.query_filtered::<Entity, With<Camera3d>>().single(world) else {
+		panic!("expected a single camera");
+	};
+	camera
+}
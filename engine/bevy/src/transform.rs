@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+
+/// Places an SDF in the world.
+///
+/// The SDF is sampled in its local space by inverse transforming world positions,
+/// and chunk meshes generated in local space are spawned with the forward transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldTransform {
+	pub translation: Vec3,
+	pub rotation: Quat,
+	pub scale: Vec3,
+}
+
+impl Default for WorldTransform {
+	fn default() -> Self {
+		Self::IDENTITY
+	}
+}
+
+impl WorldTransform {
+	pub const IDENTITY: Self =
+		Self { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: Vec3::ONE };
+
+	pub fn from_translation(translation: Vec3) -> Self {
+		Self { translation, ..Self::IDENTITY }
+	}
+
+	pub fn with_rotation(mut self, rotation: Quat) -> Self {
+		self.rotation = rotation;
+		self
+	}
+
+	pub fn with_scale(mut self, scale: Vec3) -> Self {
+		self.scale = scale;
+		self
+	}
+
+	/// Maps a world position into the local space of the SDF.
+	pub fn to_local(&self, world: Vec3) -> Vec3 {
+		self.rotation.inverse() * (world - self.translation) / self.scale
+	}
+
+	/// Maps a position in the local space of the SDF into the world.
+	pub fn to_world(&self, local: Vec3) -> Vec3 {
+		self.rotation * (local * self.scale) + self.translation
+	}
+
+	/// Converts a local distance into a world distance.
+	///
+	/// Non-uniform scales use the smallest axis, which keeps the distance a lower bound.
+	pub fn distance_to_world(&self, distance: f32) -> f32 {
+		distance * self.scale.abs().min_element()
+	}
+
+	pub fn to_transform(&self) -> Transform {
+		Transform::from_translation(self.translation)
+			.with_rotation(self.rotation)
+			.with_scale(self.scale)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_local_world_round_trip() {
+		let transform = WorldTransform::from_translation(Vec3::new(10.0, -2.0, 3.0))
+			.with_rotation(Quat::from_rotation_y(0.7))
+			.with_scale(Vec3::new(2.0, 0.5, 2.0));
+
+		let world = Vec3::new(1.0, 2.0, 3.0);
+		let local = transform.to_local(world);
+		assert!(transform.to_world(local).distance(world) < 1e-5);
+
+		// the spawn transform agrees with the sampling transform
+		assert!(transform.to_transform().transform_point(local).distance(world) < 1e-5);
+	}
+}
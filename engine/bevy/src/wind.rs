@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+
+/// The wind blowing over the world, sampled by anything that drifts with it.
+///
+/// A prevailing wind, with gusts that roll across the world downwind and a crosswind
+/// that swirls around them.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct WindField {
+	/// Direction and speed of the prevailing wind, in units per second
+	pub prevailing: Vec3,
+	/// How much gusts add to and take from the prevailing speed, as a share of it
+	pub gustiness: f32,
+	/// Width of a gust, in world units
+	pub gust_size: f32,
+}
+
+impl Default for WindField {
+	fn default() -> Self {
+		Self { prevailing: Vec3::new(1.5, 0.0, 0.5), gustiness: 0.6, gust_size: 24.0 }
+	}
+}
+
+impl WindField {
+	pub fn new(prevailing: Vec3) -> Self {
+		Self { prevailing, ..default() }
+	}
+
+	pub fn with_gustiness(mut self, gustiness: f32) -> Self {
+		self.gustiness = gustiness;
+		self
+	}
+
+	pub fn with_gust_size(mut self, gust_size: f32) -> Self {
+		self.gust_size = gust_size;
+		self
+	}
+
+	/// The wind velocity at a position, at the given time in seconds.
+	pub fn sample(&self, position: Vec3, time: f32) -> Vec3 {
+		let speed = self.prevailing.length();
+		let Some(downwind) = self.prevailing.try_normalize() else {
+			return Vec3::ZERO;
+		};
+		let across = downwind.cross(Vec3::Y).try_normalize().unwrap_or(Vec3::X);
+
+		// Gusts are carried by the wind, so they are fixed in the moving air
+		let carried = (position - self.prevailing * time) / self.gust_size.max(f32::EPSILON);
+		let along = carried.dot(downwind);
+		let side = carried.dot(across);
+		let gust =
+			((along * 1.7 + side * 0.6).sin() + (along * 0.9 - side * 1.3 + 1.1).sin()) / 2.0;
+		let swirl = (along * 1.3 + side * 2.1 + 2.3).sin();
+
+		self.prevailing * (1.0 + self.gustiness * gust)
+			+ across * (speed * self.gustiness * 0.5 * swirl)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_gusts_travel_with_the_wind() {
+		let wind = WindField::new(Vec3::new(2.0, 0.0, 0.0)).with_gust_size(8.0);
+		let position = Vec3::new(3.0, 1.0, -5.0);
+		let gust = wind.sample(position, 0.0);
+
+		// The same air has been carried downwind a second later
+		assert!(gust.distance(wind.sample(position + wind.prevailing, 1.0)) < 1e-4);
+
+		// On average the gusts blow with the prevailing wind
+		let mean = (0..400)
+			.map(|step| wind.sample(Vec3::new(step as f32 * 0.37, 0.0, step as f32 * 0.61), 0.0))
+			.sum::<Vec3>()
+			/ 400.0;
+		assert!(mean.dot(wind.prevailing.normalize()) > 1.5, "The mean wind is {mean}");
+
+		assert_eq!(WindField::new(Vec3::ZERO).sample(position, 2.0), Vec3::ZERO);
+	}
+}
@@ -0,0 +1,375 @@
+use crate::cascade::CascadeChunk;
+use crate::cpu::MeshData;
+use bevy_math::prelude::*;
+
+/// Leading bytes of an encoded mesh, with the format version.
+const MAGIC: &[u8; 4] = b"WCM1";
+
+const QUANTIZE_16: f32 = u16::MAX as f32;
+
+fn quantize(value: f32, min: f32, extent: f32) -> u16 {
+	if extent <= 0.0 {
+		return 0;
+	}
+	(((value - min) / extent).clamp(0.0, 1.0) * QUANTIZE_16).round() as u16
+}
+
+fn dequantize(value: u16, min: f32, extent: f32) -> f32 {
+	min + value as f32 / QUANTIZE_16 * extent
+}
+
+fn sign_not_zero(value: f32) -> f32 {
+	if value >= 0.0 {
+		1.0
+	} else {
+		-1.0
+	}
+}
+
+/// Folds a unit normal onto an octahedron and flattens it to two 16-bit components.
+pub fn oct_encode(normal: Vec3) -> [u16; 2] {
+	let normal = normal / (normal.x.abs() + normal.y.abs() + normal.z.abs()).max(f32::EPSILON);
+	let folded = if normal.z >= 0.0 {
+		Vec2::new(normal.x, normal.y)
+	} else {
+		Vec2::new(
+			(1.0 - normal.y.abs()) * sign_not_zero(normal.x),
+			(1.0 - normal.x.abs()) * sign_not_zero(normal.y),
+		)
+	};
+	[quantize(folded.x, -1.0, 2.0), quantize(folded.y, -1.0, 2.0)]
+}
+
+/// Unfolds a normal encoded by [oct_encode].
+pub fn oct_decode(encoded: [u16; 2]) -> Vec3 {
+	let x = dequantize(encoded[0], -1.0, 2.0);
+	let y = dequantize(encoded[1], -1.0, 2.0);
+	let z = 1.0 - x.abs() - y.abs();
+	let t = (-z).max(0.0);
+	Vec3::new(x - t * sign_not_zero(x), y - t * sign_not_zero(y), z).normalize_or_zero()
+}
+
+/// Takes the next `len` bytes, failing if the encoding is truncated.
+fn take<'a>(bytes: &'a [u8], cursor: &mut usize, len: usize) -> Result<&'a [u8], String> {
+	let end = cursor.checked_add(len).ok_or("Encoded mesh counts overflow")?;
+	let slice = bytes
+		.get(*cursor..end)
+		.ok_or_else(|| format!("Encoded mesh truncated at byte {cursor}"))?;
+	*cursor = end;
+	Ok(slice)
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+	while value >= 0x80 {
+		bytes.push((value as u8) | 0x80);
+		value >>= 7;
+	}
+	bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], cursor: &mut usize) -> Result<u64, String> {
+	let mut value = 0u64;
+	for shift in (0..64).step_by(7) {
+		let byte = *bytes.get(*cursor).ok_or("Truncated varint in encoded mesh")?;
+		*cursor += 1;
+		value |= ((byte & 0x7f) as u64) << shift;
+		if byte & 0x80 == 0 {
+			return Ok(value);
+		}
+	}
+	Err("Varint too long in encoded mesh".to_string())
+}
+
+/// Losslessly packs indices as zigzag varints of the difference from the previous index.
+///
+/// Marching cubes emits vertices close to the triangles using them, so most differences
+/// fit in a byte.
+pub fn encode_indices(indices: &[u32]) -> Vec<u8> {
+	let mut bytes = Vec::with_capacity(indices.len());
+	let mut previous = 0i64;
+	for &index in indices {
+		let delta = index as i64 - previous;
+		write_varint(&mut bytes, ((delta << 1) ^ (delta >> 63)) as u64);
+		previous = index as i64;
+	}
+	bytes
+}
+
+/// Unpacks `count` indices encoded by [encode_indices].
+pub fn decode_indices(bytes: &[u8], count: usize) -> Result<Vec<u32>, String> {
+	let mut cursor = 0;
+	let mut previous = 0i64;
+	let mut indices = Vec::with_capacity(count);
+	for _ in 0..count {
+		let zigzag = read_varint(bytes, &mut cursor)?;
+		let delta = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+		previous += delta;
+		let index = u32::try_from(previous)
+			.map_err(|_| format!("Index {previous} out of range in encoded mesh"))?;
+		indices.push(index);
+	}
+	if cursor != bytes.len() {
+		return Err(format!("{} trailing bytes after encoded indices", bytes.len() - cursor));
+	}
+	Ok(indices)
+}
+
+/// A chunk mesh with quantized attributes, for persisting chunks or sending them over the network.
+///
+/// Positions are 16-bit fractions of the bounds of the mesh, which always contain the chunk,
+/// normals are octahedron encoded and UVs are 16-bit fractions of their own range.
+/// Colors, such as baked sky visibility, are 8-bit. Indices are compressed losslessly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressedMesh {
+	/// Lower corner of the position bounds, relative to the chunk origin like the mesh
+	pub min: Vec3,
+	pub extent: Vec3,
+	pub uv_min: Vec2,
+	pub uv_extent: Vec2,
+	positions: Vec<[u16; 3]>,
+	normals: Vec<[u16; 2]>,
+	uvs: Vec<[u16; 2]>,
+	colors: Vec<[u8; 4]>,
+	index_count: usize,
+	indices: Vec<u8>,
+}
+
+impl CompressedMesh {
+	/// Quantizes a chunk mesh, whose positions are relative to the chunk origin.
+	pub fn encode(chunk: &CascadeChunk, mesh: &MeshData) -> Self {
+		let (mut min, mut max) = (Vec3::ZERO, chunk.size);
+		for position in &mesh.positions {
+			min = min.min(Vec3::from_array(*position));
+			max = max.max(Vec3::from_array(*position));
+		}
+		let extent = max - min;
+
+		let (mut uv_min, mut uv_max) = (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN));
+		for uv in &mesh.uvs {
+			uv_min = uv_min.min(Vec2::from_array(*uv));
+			uv_max = uv_max.max(Vec2::from_array(*uv));
+		}
+		if mesh.uvs.is_empty() {
+			(uv_min, uv_max) = (Vec2::ZERO, Vec2::ZERO);
+		}
+		let uv_extent = uv_max - uv_min;
+
+		Self {
+			min,
+			extent,
+			uv_min,
+			uv_extent,
+			positions: mesh
+				.positions
+				.iter()
+				.map(|p| [0, 1, 2].map(|axis| quantize(p[axis], min[axis], extent[axis])))
+				.collect(),
+			normals: mesh.normals.iter().map(|n| oct_encode(Vec3::from_array(*n))).collect(),
+			uvs: mesh
+				.uvs
+				.iter()
+				.map(|uv| [0, 1].map(|axis| quantize(uv[axis], uv_min[axis], uv_extent[axis])))
+				.collect(),
+			colors: mesh
+				.colors
+				.iter()
+				.map(|color| color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8))
+				.collect(),
+			index_count: mesh.indices.len(),
+			indices: encode_indices(&mesh.indices),
+		}
+	}
+
+	/// Restores the mesh, with positions relative to the chunk origin.
+	pub fn decode(&self) -> Result<MeshData, String> {
+		let (min, extent) = (self.min, self.extent);
+		let (uv_min, uv_extent) = (self.uv_min, self.uv_extent);
+		Ok(MeshData {
+			positions: self
+				.positions
+				.iter()
+				.map(|p| [0, 1, 2].map(|axis| dequantize(p[axis], min[axis], extent[axis])))
+				.collect(),
+			normals: self.normals.iter().map(|n| oct_decode(*n).to_array()).collect(),
+			uvs: self
+				.uvs
+				.iter()
+				.map(|uv| [0, 1].map(|axis| dequantize(uv[axis], uv_min[axis], uv_extent[axis])))
+				.collect(),
+			colors: self
+				.colors
+				.iter()
+				.map(|color| color.map(|channel| channel as f32 / 255.0))
+				.collect(),
+			indices: decode_indices(&self.indices, self.index_count)?,
+		})
+	}
+
+	pub fn vertex_count(&self) -> usize {
+		self.positions.len()
+	}
+
+	/// Serializes the mesh to little endian bytes.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(64 + self.positions.len() * 14 + self.indices.len());
+		bytes.extend_from_slice(MAGIC);
+		for value in self.min.to_array().into_iter().chain(self.extent.to_array()) {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+		for value in self.uv_min.to_array().into_iter().chain(self.uv_extent.to_array()) {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+		for count in [
+			self.positions.len(),
+			self.normals.len(),
+			self.uvs.len(),
+			self.colors.len(),
+			self.index_count,
+			self.indices.len(),
+		] {
+			write_varint(&mut bytes, count as u64);
+		}
+		for value in self.positions.iter().flatten() {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+		for value in self.normals.iter().chain(&self.uvs).flatten() {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+		bytes.extend(self.colors.iter().flatten());
+		bytes.extend_from_slice(&self.indices);
+		bytes
+	}
+
+	/// Reads a mesh written by [CompressedMesh::to_bytes].
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+		if bytes.get(..MAGIC.len()) != Some(MAGIC.as_slice()) {
+			return Err("Not an encoded chunk mesh".to_string());
+		}
+		let mut cursor = MAGIC.len();
+		let mut floats = [0.0f32; 10];
+		for float in &mut floats {
+			let mut le = [0u8; 4];
+			le.copy_from_slice(take(bytes, &mut cursor, 4)?);
+			*float = f32::from_le_bytes(le);
+		}
+
+		let mut counts = [0usize; 6];
+		for count in &mut counts {
+			*count = read_varint(bytes, &mut cursor)? as usize;
+		}
+		let [positions, normals, uvs, colors, index_count, index_bytes] = counts;
+
+		let mut take = |len: usize| take(bytes, &mut cursor, len);
+		let u16s = |slice: &[u8]| -> Vec<u16> {
+			slice
+				.chunks_exact(2)
+				.map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+				.collect()
+		};
+		let positions = u16s(take(positions.saturating_mul(6))?)
+			.chunks_exact(3)
+			.map(|p| [p[0], p[1], p[2]])
+			.collect();
+		let normals = u16s(take(normals.saturating_mul(4))?)
+			.chunks_exact(2)
+			.map(|n| [n[0], n[1]])
+			.collect();
+		let uvs = u16s(take(uvs.saturating_mul(4))?)
+			.chunks_exact(2)
+			.map(|uv| [uv[0], uv[1]])
+			.collect();
+		let colors = take(colors.saturating_mul(4))?
+			.chunks_exact(4)
+			.map(|c| [c[0], c[1], c[2], c[3]])
+			.collect();
+		let indices = take(index_bytes)?.to_vec();
+		if cursor != bytes.len() {
+			return Err(format!("{} trailing bytes after encoded mesh", bytes.len() - cursor));
+		}
+
+		Ok(Self {
+			min: Vec3::new(floats[0], floats[1], floats[2]),
+			extent: Vec3::new(floats[3], floats[4], floats[5]),
+			uv_min: Vec2::new(floats[6], floats[7]),
+			uv_extent: Vec2::new(floats[8], floats[9]),
+			positions,
+			normals,
+			uvs,
+			colors,
+			index_count,
+			indices,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::cpu::CpuMeshGenerator;
+	use sdf::Sdf;
+	use std::sync::Arc;
+
+	struct Sphere;
+
+	impl Sdf for Sphere {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.distance(Vec3::new(0.1, 0.2, 0.3)) - 2.27
+		}
+	}
+
+	#[test]
+	fn test_round_trip_within_quantization_error() {
+		let chunk = CascadeChunk::cube(Vec3::splat(-4.0), 8.0, 4);
+		let Some(mut mesh) = CpuMeshGenerator::generate_chunk_mesh_data(&chunk, Arc::new(Sphere))
+		else {
+			panic!("expected a sphere mesh");
+		};
+		mesh.colors = (0..mesh.positions.len())
+			.map(|i| [(i % 7) as f32 / 6.0, 0.0, 1.0, 1.0])
+			.collect();
+
+		let compressed = CompressedMesh::encode(&chunk, &mesh);
+		let bytes = compressed.to_bytes();
+		let raw = mesh.positions.len() * (12 + 12 + 8 + 16) + mesh.indices.len() * 4;
+		assert!(bytes.len() * 2 < raw, "{} bytes against {raw} raw", bytes.len());
+
+		let Ok(decoded) = CompressedMesh::from_bytes(&bytes).and_then(|mesh| mesh.decode()) else {
+			panic!("expected the mesh to decode");
+		};
+		assert_eq!(decoded.indices, mesh.indices);
+		let position_error = chunk.size.max_element() / QUANTIZE_16;
+		for (a, b) in mesh.positions.iter().zip(&decoded.positions) {
+			assert!(Vec3::from_array(*a).abs_diff_eq(Vec3::from_array(*b), position_error));
+		}
+		for (a, b) in mesh.normals.iter().zip(&decoded.normals) {
+			assert!(Vec3::from_array(*a).normalize().dot(Vec3::from_array(*b)) > 0.9999);
+		}
+		for (a, b) in mesh.uvs.iter().zip(&decoded.uvs) {
+			assert!(Vec2::from_array(*a).abs_diff_eq(Vec2::from_array(*b), 1e-3));
+		}
+		for (a, b) in mesh.colors.iter().zip(&decoded.colors) {
+			assert!(Vec4::from_array(*a).abs_diff_eq(Vec4::from_array(*b), 0.5 / 255.0 + 1e-6));
+		}
+	}
+
+	#[test]
+	fn test_corrupt_bytes_are_rejected() {
+		let indices = vec![0, 1, 2, 2, 1, 70_000, 3, 0, u32::MAX];
+		assert_eq!(decode_indices(&encode_indices(&indices), indices.len()), Ok(indices));
+
+		let mesh = MeshData {
+			positions: vec![[0.0; 3], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+			normals: vec![[0.0, 1.0, 0.0]; 3],
+			uvs: vec![[0.0; 2]; 3],
+			colors: Vec::new(),
+			indices: vec![0, 2, 1],
+		};
+		let bytes =
+			CompressedMesh::encode(&CascadeChunk::cube(Vec3::ZERO, 1.0, 1), &mesh).to_bytes();
+		assert!(CompressedMesh::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+		assert!(CompressedMesh::from_bytes(&bytes[1..]).is_err());
+		let mut trailing = bytes.clone();
+		trailing.push(0);
+		assert!(CompressedMesh::from_bytes(&trailing).is_err());
+	}
+}
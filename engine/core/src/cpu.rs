@@ -0,0 +1,692 @@
+pub mod sparse_cubes;
+
+use crate::cascade::CascadeChunk;
+use crate::marching_cubes::VertexInterpolation;
+use bevy_math::prelude::*;
+use rayon::prelude::*;
+use sdf::{Axis, Sign, Sdf};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// The buffers of a generated chunk mesh, usable without a renderer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshData {
+	pub positions: Vec<[f32; 3]>,
+	pub normals: Vec<[f32; 3]>,
+	pub uvs: Vec<[f32; 2]>,
+	/// Optional per-vertex colors, such as baked sky visibility
+	pub colors: Vec<[f32; 4]>,
+	pub indices: Vec<u32>,
+}
+
+impl MeshData {
+	pub fn triangle_count(&self) -> usize {
+		self.indices.len() / 3
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.indices.is_empty()
+	}
+}
+
+/// Step of the SDF gradient at chunk borders, as a fraction of the smallest cube side
+const BORDER_NORMAL_STEP: f32 = 0.5;
+
+/// Samples taken at each end of an interval of known sign before filling its middle
+const TRANSITION_VOXELS: usize = 3;
+
+/// Columns probed across each side of a chunk when picking the axis to skip along
+const AXIS_PROBES: usize = 4;
+
+/// The normalized SDF gradient at a point, by central differences.
+///
+/// Returns None where the gradient vanishes.
+pub fn sdf_normal<S: Sdf + ?Sized>(sdf: &S, p: Vec3, step: f32) -> Option<Vec3> {
+	let difference =
+		|axis: Vec3| sdf.distance(p + axis * step) - sdf.distance(p - axis * step);
+	let gradient = Vec3::new(difference(Vec3::X), difference(Vec3::Y), difference(Vec3::Z));
+	gradient.try_normalize()
+}
+
+/// CPU-based terrain mesh generator
+pub struct CpuMeshGenerator;
+
+impl CpuMeshGenerator {
+	/// Generate the mesh buffers for a chunk by sampling an SDF.
+	/// Vertices are relative to the chunk origin; returns None if the chunk has no surface.
+	pub fn generate_chunk_mesh_data<S: Sdf + Send + Sync>(
+		cascade_chunk: &CascadeChunk,
+		sdf: Arc<S>,
+	) -> Option<MeshData> {
+		Self::generate_chunk_mesh_data_at(cascade_chunk, sdf, 0.0)
+	}
+
+	/// Generate the mesh buffers of the isosurface where the SDF equals `iso_level`.
+	/// Positive levels extract a shell outside the surface, such as snow cover, and negative
+	/// levels one inside it.
+	pub fn generate_chunk_mesh_data_at<S: Sdf + Send + Sync>(
+		cascade_chunk: &CascadeChunk,
+		sdf: Arc<S>,
+		iso_level: f32,
+	) -> Option<MeshData> {
+		Self::generate_chunk_mesh_data_with(
+			cascade_chunk,
+			sdf,
+			iso_level,
+			VertexInterpolation::default(),
+		)
+	}
+
+	/// Generate the mesh buffers of the isosurface at `iso_level`, placing vertices along the
+	/// cube edges by the interpolation.
+	pub fn generate_chunk_mesh_data_with<S: Sdf + Send + Sync>(
+		cascade_chunk: &CascadeChunk,
+		sdf: Arc<S>,
+		iso_level: f32,
+		interpolation: VertexInterpolation,
+	) -> Option<MeshData> {
+		// ---------- grid setup ---------------------------------------------------
+		let chunk_size = cascade_chunk.size;
+		let res = cascade_chunk.resolution();
+		let cube_size = chunk_size / res.as_vec3();
+		let chunk_origin = cascade_chunk.origin;
+
+		// ---------- grid setup ---------------------------------------------------
+		// Grid resolution (sample points); cubes are (n-1) in each axis
+		// Y is now treated the same as X and Z - a voxel cube
+		let nx = res.x as usize + 1;
+		let ny = res.y as usize + 1;
+		let nz = res.z as usize + 1;
+
+		// Helper: linear index with X fastest, then Z, then Y (consistent)
+		let idx = |x: usize, y: usize, z: usize| -> usize { (y * nz + z) * nx + x };
+
+		// Scalar field samples
+		let mut grid = vec![0.0f32; nx * ny * nz];
+
+		// ---------- sample SDF in world space (parallelized) --------------------
+		// Columns run along the axis whose sign uniform intervals skip the most samples, so
+		// cliff walls and caves skip along X or Z as terrain does along Y
+		let transition_voxels = |axis: Axis| {
+			// The shell at the iso level lies up to |iso_level| off the interval boundaries
+			TRANSITION_VOXELS + (iso_level.abs() / cube_size[axis.index()]).ceil() as usize
+		};
+		let axis = Self::skip_axis(sdf.as_ref(), cascade_chunk, transition_voxels);
+		let along = axis.index();
+		let [across_a, across_b] = axis.across();
+		let counts = [nx, ny, nz];
+
+		// time the sampling
+		let start_time = std::time::Instant::now();
+
+		// Parallelize over slices across the second axis, and merge them sequentially
+		let sdf_clone = Arc::clone(&sdf);
+		let slices: Vec<_> = (0..counts[across_b])
+			.into_par_iter()
+			.map(|jb| {
+				let b = chunk_origin[across_b] + jb as f32 * cube_size[across_b];
+				let columns: Vec<Vec<f32>> = (0..counts[across_a])
+					.map(|ja| {
+						let a = chunk_origin[across_a] + ja as f32 * cube_size[across_a];
+						Self::sample_column(
+							sdf_clone.as_ref(),
+							axis,
+							(a, b),
+							(chunk_origin[along], cube_size[along], counts[along]),
+							iso_level,
+							transition_voxels(axis),
+						)
+					})
+					.collect();
+				(jb, columns)
+			})
+			.collect();
+		let end_time = std::time::Instant::now();
+		let duration = end_time.duration_since(start_time);
+		log::debug!("Sparse sampling time along {:?}: {:?}", axis, duration);
+
+		// time the merging
+		let start_time = std::time::Instant::now();
+		// Merge slices into grid
+		for (jb, columns) in slices {
+			for (ja, column) in columns.into_iter().enumerate() {
+				for (k, value) in column.into_iter().enumerate() {
+					let mut index = [0; 3];
+					index[across_a] = ja;
+					index[across_b] = jb;
+					index[along] = k;
+					grid[idx(index[0], index[1], index[2])] = value;
+				}
+			}
+		}
+		let end_time = std::time::Instant::now();
+		let duration = end_time.duration_since(start_time);
+		log::debug!("Merging time: {:?}", duration);
+
+		// ---------- Marching Cubes (parallelized) --------------------------------
+		use crate::marching_cubes::{get_cube_index, place_vertex, TRIANGULATIONS};
+
+		// Number of cubes along each axis
+		let cx = nx - 1;
+		let cy = ny - 1;
+		let cz = nz - 1;
+
+		// Process cubes in parallel, collecting vertices and indices per cube
+		// We'll merge them with proper index offsets afterward
+		// SAFETY: We're only reading from grid, and each thread reads different indices
+		// Flatten cube coordinates into a single iterator
+		let start_time = std::time::Instant::now();
+		let cube_coords: Vec<_> = (0..cy)
+			.flat_map(|y| (0..cz).flat_map(move |z| (0..cx).map(move |x| (x, y, z))))
+			.collect();
+		let end_time = std::time::Instant::now();
+		let duration = end_time.duration_since(start_time);
+		log::debug!("Cube coords time: {:?}", duration);
+
+		// Capture grid as a slice for parallel access (read-only)
+		let start_time = std::time::Instant::now();
+		let grid_slice: &[f32] = &grid;
+		let cube_results: Vec<_> = cube_coords
+			.into_par_iter()
+			.filter_map(|(x, y, z)| {
+				// Local-space cube origin (all dimensions relative to chunk origin)
+				let cube_pos_local = Vec3::new(x as f32, y as f32, z as f32) * cube_size;
+				
+				
+				// Corner scalar values (standard MC corner ordering assumed by your helpers)
+				// Inline index calculation: (y * nz + z) * nx + x
+				let corners = [
+					grid_slice[(y * nz + z) * nx + x],                   // 0 (0,0,0)
+					grid_slice[(y * nz + z) * nx + (x + 1)],             // 1 (1,0,0)
+					grid_slice[(y * nz + (z + 1)) * nx + (x + 1)],       // 2 (1,0,1)
+					grid_slice[(y * nz + (z + 1)) * nx + x],             // 3 (0,0,1)
+					grid_slice[((y + 1) * nz + z) * nx + x],             // 4 (0,1,0)
+					grid_slice[((y + 1) * nz + z) * nx + (x + 1)],       // 5 (1,1,0)
+					grid_slice[((y + 1) * nz + (z + 1)) * nx + (x + 1)], // 6 (1,1,1)
+					grid_slice[((y + 1) * nz + (z + 1)) * nx + x],       // 7 (0,1,1)
+				];
+
+				let cube_index = get_cube_index(corners);
+				if cube_index == 0 || cube_index == 255 {
+					return None; // fully inside or outside
+				}
+
+				// Per-cube edge vertex cache (12 edges)
+				let mut edge_vert: [Option<u32>; 12] = [None; 12];
+
+				let mut cube_vertices = Vec::new();
+				let mut cube_indices = Vec::new();
+
+				let tri = &TRIANGULATIONS[cube_index];
+				let mut i = 0;
+				while i + 2 < tri.len() {
+					let e0 = tri[i];
+					if e0 < 0 {
+						break;
+					}
+					let e1 = tri[i + 1];
+					if e1 < 0 {
+						break;
+					}
+					let e2 = tri[i + 2];
+					if e2 < 0 {
+						break;
+					}
+
+					let mut get_vert = |edge: usize| -> u32 {
+						if let Some(v) = edge_vert[edge] {
+							return v;
+						}
+						let pos_local = place_vertex(
+							edge,
+							cube_pos_local,
+							cube_size,
+							corners,
+							interpolation,
+							|local| sdf.distance(chunk_origin + local) - iso_level,
+						);
+						let v_index = cube_vertices.len() as u32;
+						cube_vertices.push([pos_local.x, pos_local.y, pos_local.z]);
+						edge_vert[edge] = Some(v_index);
+						v_index
+					};
+
+					let v0 = get_vert(e0 as usize);
+					let v1 = get_vert(e1 as usize);
+					let v2 = get_vert(e2 as usize);
+
+					cube_indices.extend_from_slice(&[v0, v1, v2]);
+					i += 3;
+				}
+
+				if cube_vertices.is_empty() {
+					None
+				} else {
+					Some((cube_vertices, cube_indices))
+				}
+			})
+			.collect();
+		let end_time = std::time::Instant::now();
+		let duration = end_time.duration_since(start_time);
+		log::debug!("Cube results time: {:?}", duration);
+
+		// Merge all cube results with proper index offsets
+		let start_time = std::time::Instant::now();
+		let mut vertices: Vec<[f32; 3]> = Vec::new();
+		let mut indices: Vec<u32> = Vec::new();
+
+		for (cube_vertices, cube_indices) in cube_results {
+			let vertex_offset = vertices.len() as u32;
+			vertices.extend(cube_vertices);
+			indices.extend(cube_indices.iter().map(|&idx| idx + vertex_offset));
+		}
+		let end_time = std::time::Instant::now();
+		let duration = end_time.duration_since(start_time);
+		log::debug!("Merging cube results time: {:?}", duration);
+
+		// time the normals
+		let start_time = std::time::Instant::now();
+		// ---------- Normals & UVs (parallelized) ---------------------------------
+		// Normals: compute from voxel grid using finite differences
+		// Vertices are in local space (relative to chunk_origin)
+		let grid_slice: &[f32] = &grid;
+		let normals: Vec<[f32; 3]> = vertices
+			.par_iter()
+			.map(|v| {
+				// Convert vertex local position to grid coordinates
+				let gx = (v[0] / cube_size.x).clamp(0.0, (nx - 1) as f32);
+				let gy = (v[1] / cube_size.y).clamp(0.0, (ny - 1) as f32);
+				let gz = (v[2] / cube_size.z).clamp(0.0, (nz - 1) as f32);
+
+				// Get integer grid indices (truncate for now, could interpolate)
+				let ix = gx as usize;
+				let iy = gy as usize;
+				let iz = gz as usize;
+
+				// Compute finite differences using central differences where possible
+				// ∂f/∂x = (f(x+1) - f(x-1)) / (2 * cube_size.x)
+				let dx = if ix > 0 && ix < nx - 1 {
+					let f_xp1 = grid_slice[idx(ix + 1, iy, iz)];
+					let f_xm1 = grid_slice[idx(ix - 1, iy, iz)];
+					(f_xp1 - f_xm1) / (2.0 * cube_size.x)
+				} else if ix < nx - 1 {
+					// Forward difference at left boundary
+					let f_xp1 = grid_slice[idx(ix + 1, iy, iz)];
+					let f_x = grid_slice[idx(ix, iy, iz)];
+					(f_xp1 - f_x) / cube_size.x
+				} else {
+					// Backward difference at right boundary
+					let f_x = grid_slice[idx(ix, iy, iz)];
+					let f_xm1 = grid_slice[idx(ix - 1, iy, iz)];
+					(f_x - f_xm1) / cube_size.x
+				};
+
+				// ∂f/∂y = (f(y+1) - f(y-1)) / (2 * cube_size.y)
+				let dy = if iy > 0 && iy < ny - 1 {
+					let f_yp1 = grid_slice[idx(ix, iy + 1, iz)];
+					let f_ym1 = grid_slice[idx(ix, iy - 1, iz)];
+					(f_yp1 - f_ym1) / (2.0 * cube_size.y)
+				} else if iy < ny - 1 {
+					// Forward difference at bottom boundary
+					let f_yp1 = grid_slice[idx(ix, iy + 1, iz)];
+					let f_y = grid_slice[idx(ix, iy, iz)];
+					(f_yp1 - f_y) / cube_size.y
+				} else {
+					// Backward difference at top boundary
+					let f_y = grid_slice[idx(ix, iy, iz)];
+					let f_ym1 = grid_slice[idx(ix, iy - 1, iz)];
+					(f_y - f_ym1) / cube_size.y
+				};
+
+				// ∂f/∂z = (f(z+1) - f(z-1)) / (2 * cube_size.z)
+				let dz = if iz > 0 && iz < nz - 1 {
+					let f_zp1 = grid_slice[idx(ix, iy, iz + 1)];
+					let f_zm1 = grid_slice[idx(ix, iy, iz - 1)];
+					(f_zp1 - f_zm1) / (2.0 * cube_size.z)
+				} else if iz < nz - 1 {
+					// Forward difference at front boundary
+					let f_zp1 = grid_slice[idx(ix, iy, iz + 1)];
+					let f_z = grid_slice[idx(ix, iy, iz)];
+					(f_zp1 - f_z) / cube_size.z
+				} else {
+					// Backward difference at back boundary
+					let f_z = grid_slice[idx(ix, iy, iz)];
+					let f_zm1 = grid_slice[idx(ix, iy, iz - 1)];
+					(f_z - f_zm1) / cube_size.z
+				};
+
+				// Normalize the gradient to get the normal
+				let grad = Vec3::new(dx, dy, dz);
+				let len = grad.length();
+				if len > 0.0001 {
+					(grad / len).into()
+				} else {
+					Vec3::Y.into() // Fallback to up if gradient is too small
+				}
+			})
+			.collect();
+
+		// The grid falls back to one sided differences at the chunk faces, so the neighbor
+		// sees a different gradient there. Border vertices take the SDF gradient instead,
+		// which matches across chunks whatever their resolution.
+		let step = cube_size.min_element() * BORDER_NORMAL_STEP;
+		let normals: Vec<[f32; 3]> = vertices
+			.par_iter()
+			.zip(normals)
+			.map(|(v, normal)| {
+				let local = Vec3::from_array(*v);
+				let near_face = local.cmplt(cube_size).any() || local.cmpgt(chunk_size - cube_size).any();
+				if !near_face {
+					return normal;
+				}
+				sdf_normal(sdf.as_ref(), chunk_origin + local, step).map_or(normal, Vec3::into)
+			})
+			.collect();
+		let end_time = std::time::Instant::now();
+		let duration = end_time.duration_since(start_time);
+		log::debug!("Normals time: {:?}", duration);
+
+		// Simple tiled UVs (local X/Z across the chunk)
+		let start_time = std::time::Instant::now();
+		let uvs: Vec<[f32; 2]> = vertices
+			.par_iter()
+			.map(|v| [v[0] / chunk_size.x, v[2] / chunk_size.z])
+			.collect();
+		let end_time = std::time::Instant::now();
+		let duration = end_time.duration_since(start_time);
+		log::debug!("UVs time: {:?}", duration);
+
+		Some(MeshData { positions: vertices, normals, uvs, colors: Vec::new(), indices })
+	}
+
+	/// The axis along which the sign uniform intervals of the SDF skip the most samples of
+	/// the chunk, of those the SDF reports intervals along.
+	///
+	/// A few columns across each axis are probed, and each axis is ranked by the share of
+	/// their samples that fall deep enough inside intervals of known sign to be filled. Y wins
+	/// ties, as most terrain is layered vertically.
+	pub fn skip_axis<S: Sdf + ?Sized>(
+		sdf: &S,
+		cascade_chunk: &CascadeChunk,
+		transition_voxels: impl Fn(Axis) -> usize,
+	) -> Axis {
+		let mut best =
+			(Axis::Y, Self::skip_ratio(sdf, cascade_chunk, Axis::Y, transition_voxels(Axis::Y)));
+		for axis in [Axis::X, Axis::Z] {
+			let ratio = Self::skip_ratio(sdf, cascade_chunk, axis, transition_voxels(axis));
+			if ratio > best.1 {
+				best = (axis, ratio);
+			}
+		}
+		best.0
+	}
+
+	/// Share of the samples of the probed columns along the axis that the sparse sampling
+	/// fills instead of sampling.
+	pub fn skip_ratio<S: Sdf + ?Sized>(
+		sdf: &S,
+		cascade_chunk: &CascadeChunk,
+		axis: Axis,
+		transition_voxels: usize,
+	) -> f32 {
+		let res = cascade_chunk.resolution().as_vec3();
+		let (origin, size) = (cascade_chunk.origin, cascade_chunk.size);
+		let along = axis.index();
+		let [across_a, across_b] = axis.across();
+		let step = size[along] / res[along];
+		let samples = res[along] as usize + 1;
+
+		let probe = |across: usize, k: usize| {
+			origin[across] + size[across] * (k as f32 + 0.5) / AXIS_PROBES as f32
+		};
+		let mut filled = 0;
+		for i in 0..AXIS_PROBES {
+			for j in 0..AXIS_PROBES {
+				let (a, b) = (probe(across_a, i), probe(across_b, j));
+				for interval in sdf.sign_uniform_on_axis(axis, a, b) {
+					if !matches!(interval.left.sign, Sign::Negative | Sign::Positive) {
+						continue;
+					}
+					let (min, max) = interval.open_range();
+					let start = ((min.max(origin[along]) - origin[along]) / step).floor().max(0.0);
+					let end = ((max.min(origin[along] + size[along]) - origin[along]) / step)
+						.ceil()
+						.min(samples as f32);
+					let inside = (end - start).max(0.0) as usize;
+					filled += inside.saturating_sub(transition_voxels * 2);
+				}
+			}
+		}
+		filled as f32 / (AXIS_PROBES * AXIS_PROBES * samples) as f32
+	}
+
+	/// Samples the column at `across` along the axis, starting at `origin` and `step` apart,
+	/// filling the samples deep inside intervals of known sign instead of sampling them.
+	fn sample_column<S: Sdf + ?Sized>(
+		sdf: &S,
+		axis: Axis,
+		(a, b): (f32, f32),
+		(origin, step, n): (f32, f32, usize),
+		iso_level: f32,
+		transition_voxels: usize,
+	) -> Vec<f32> {
+		let sample =
+			|k: usize| sdf.distance(axis.point(a, b, origin + k as f32 * step)) - iso_level;
+		let sample_range = |column: &mut [f32], range: Range<usize>| {
+			for (value, k) in column[range.clone()].iter_mut().zip(range) {
+				*value = sample(k);
+			}
+		};
+		let mut column = vec![0.0f32; n];
+
+		// CRITICAL: Sample near interval boundaries (where sign changes = surface) to avoid
+		// terraced artifacts, and fill the middle of intervals of known sign
+		let mut current = 0;
+		for interval in sdf.sign_uniform_on_axis(axis, a, b) {
+			let (min_world, max_world) = interval.open_range();
+
+			// Convert world coordinates to grid indices, clamped to the chunk
+			let start_world = min_world.max(origin);
+			let end_world = max_world.min(origin + step * (n - 1) as f32);
+			let start = ((start_world - origin) / step).floor() as usize;
+			let end = ((end_world - origin) / step).ceil().min(n as f32) as usize;
+
+			// Only process if this interval overlaps with remaining samples
+			if start >= n || current >= n {
+				break;
+			}
+
+			// Start from the current sample or the interval start, whichever is later
+			let begin = start.max(current);
+			let finish = end.min(n);
+			if begin < finish {
+				let fill_value = match interval.left.sign {
+					Sign::Negative => Some(-1000.0),
+					Sign::Positive => Some(1000.0),
+					// Unknown/undefined sign - need to sample normally
+					Sign::Top | Sign::Bottom => None,
+				};
+				match fill_value {
+					// If the interval is small, just sample everything
+					Some(fill_value) if finish - begin > transition_voxels * 2 => {
+						// Sample at both boundaries, and fill the middle with a constant
+						let fill_start = begin + transition_voxels;
+						let fill_end = finish - transition_voxels;
+						sample_range(&mut column, begin..fill_start);
+						column[fill_start..fill_end].fill(fill_value);
+						sample_range(&mut column, fill_end..finish);
+					}
+					_ => sample_range(&mut column, begin..finish),
+				}
+			}
+
+			// Skip ahead to the end of the interval
+			current = finish;
+			if current >= n {
+				break;
+			}
+		}
+
+		// Sample any remaining values that weren't covered by intervals
+		sample_range(&mut column, current..n);
+		column
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sdf::{SignBoundary, SignUniformIntervals};
+
+	/// Rolling hills, steep enough for the grid normals to drift at the chunk faces
+	struct Hills;
+
+	impl Sdf for Hills {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.y - 0.6 * (p.x * 1.7).sin() - 0.4 * (p.z * 1.3).cos()
+		}
+	}
+
+	/// A cliff wall facing +X, reporting its intervals along X
+	struct Cliff;
+
+	impl Cliff {
+		fn face(y: f32, z: f32) -> f32 {
+			0.3 * (z * 1.1).sin() - 0.2 * y
+		}
+	}
+
+	impl Sdf for Cliff {
+		fn distance(&self, p: Vec3) -> f32 {
+			(p.x - Self::face(p.y, p.z)) / 1.1
+		}
+
+		fn sign_uniform_on_axis(&self, axis: Axis, a: f32, b: f32) -> SignUniformIntervals {
+			let mut intervals = SignUniformIntervals::default();
+			if axis == Axis::X {
+				let face = Self::face(a, b);
+				intervals
+					.insert_boundary(SignBoundary { min: f32::NEG_INFINITY, sign: Sign::Negative });
+				intervals.insert_boundary(SignBoundary { min: face, sign: Sign::Positive });
+			}
+			intervals
+		}
+	}
+
+	/// The cliff without any intervals, sampled everywhere
+	struct DenseCliff;
+
+	impl Sdf for DenseCliff {
+		fn distance(&self, p: Vec3) -> f32 {
+			Cliff.distance(p)
+		}
+	}
+
+	#[test]
+	fn test_sampling_skips_along_the_best_axis() {
+		let chunk = CascadeChunk::cube(Vec3::new(-4.0, -2.0, -2.0), 8.0, 4);
+		let transition = |_| TRANSITION_VOXELS;
+		assert_eq!(CpuMeshGenerator::skip_axis(&Cliff, &chunk, transition), Axis::X);
+		assert!(CpuMeshGenerator::skip_ratio(&Cliff, &chunk, Axis::X, TRANSITION_VOXELS) > 0.2);
+		assert_eq!(CpuMeshGenerator::skip_ratio(&Cliff, &chunk, Axis::Z, TRANSITION_VOXELS), 0.0);
+		assert_eq!(CpuMeshGenerator::skip_axis(&Hills, &chunk, transition), Axis::Y);
+
+		// Filling the wall along X leaves the same surface as sampling every voxel
+		let Some(skipped) = CpuMeshGenerator::generate_chunk_mesh_data(&chunk, Arc::new(Cliff))
+		else {
+			panic!("expected the cliff face");
+		};
+		let Some(dense) = CpuMeshGenerator::generate_chunk_mesh_data(&chunk, Arc::new(DenseCliff))
+		else {
+			panic!("expected the dense cliff face");
+		};
+		assert_eq!(skipped.positions, dense.positions);
+		assert_eq!(skipped.indices, dense.indices);
+	}
+
+	#[test]
+	fn test_border_normals_agree_across_resolutions() {
+		let sdf = Arc::new(Hills);
+		let exact = |p: Vec3| {
+			Vec3::new(-1.02 * (p.x * 1.7).cos(), 1.0, 0.52 * (p.z * 1.3).sin()).normalize()
+		};
+
+		// Neighbors sharing the face at x = 0, one at twice the resolution of the other
+		let coarse = CascadeChunk::cube(Vec3::new(-4.0, -2.0, -2.0), 4.0, 3);
+		let fine = CascadeChunk::cube(Vec3::new(0.0, -2.0, -2.0), 4.0, 4);
+		let mut checked = 0;
+		for chunk in [coarse, fine] {
+			let Some(mesh) = CpuMeshGenerator::generate_chunk_mesh_data(&chunk, sdf.clone())
+			else {
+				panic!("No surface in the chunk at {}", chunk.origin);
+			};
+			for (position, normal) in mesh.positions.iter().zip(&mesh.normals) {
+				let p = chunk.origin + Vec3::from_array(*position);
+				if p.x.abs() > 1e-4 {
+					continue;
+				}
+				let error = Vec3::from_array(*normal).angle_between(exact(p));
+				assert!(error < 0.02, "Normal at {p} is {error} radians off");
+				checked += 1;
+			}
+		}
+		assert!(checked > 0);
+	}
+
+	#[test]
+	fn test_iso_level_offsets_the_surface() {
+		let chunk = CascadeChunk::cube(Vec3::new(-2.0, -2.0, -2.0), 4.0, 4);
+		for iso_level in [0.0, 0.5, -0.75] {
+			let Some(mesh) =
+				CpuMeshGenerator::generate_chunk_mesh_data_at(&chunk, Arc::new(Hills), iso_level)
+			else {
+				panic!("No surface at iso level {iso_level}");
+			};
+			assert!(!mesh.positions.is_empty());
+			for position in &mesh.positions {
+				let p = chunk.origin + Vec3::from_array(*position);
+				let value = Hills.distance(p);
+				assert!((value - iso_level).abs() < 0.05, "Vertex at {p} has value {value}");
+			}
+		}
+	}
+
+	#[test]
+	fn test_interpolation_places_vertices_on_the_edges() {
+		let chunk = CascadeChunk::cube(Vec3::new(-2.0, -2.0, -2.0), 4.0, 3);
+		let cube_size = chunk.size / chunk.resolution().as_vec3();
+		let generate = |interpolation| {
+			CpuMeshGenerator::generate_chunk_mesh_data_with(
+				&chunk,
+				Arc::new(Hills),
+				0.0,
+				interpolation,
+			)
+			.unwrap_or_default()
+		};
+		let error = |mesh: &MeshData| {
+			mesh.positions
+				.iter()
+				.map(|position| Hills.distance(chunk.origin + Vec3::from_array(*position)).abs())
+				.fold(0.0, f32::max)
+		};
+
+		// Midpoints sit halfway along an edge, with the other two coordinates on the grid
+		let midpoint = generate(VertexInterpolation::Midpoint);
+		assert!(!midpoint.positions.is_empty());
+		for position in &midpoint.positions {
+			let cells = Vec3::from_array(*position) / cube_size;
+			let halves = (cells * 2.0).round();
+			assert!((cells * 2.0 - halves).abs().max_element() < 1e-3, "{cells}");
+			let odd = halves.to_array().iter().filter(|half| *half % 2.0 != 0.0).count();
+			assert_eq!(odd, 1, "{cells}");
+		}
+
+		// Refinement lands closer to the surface than the linear guess
+		let linear = generate(VertexInterpolation::Linear);
+		assert_eq!(linear, generate(VertexInterpolation::Refined(0)));
+		let refined = generate(VertexInterpolation::Refined(3));
+		assert_eq!(refined.positions.len(), linear.positions.len());
+		assert!(error(&refined) < error(&linear) * 0.5, "{} {}", error(&refined), error(&linear));
+	}
+}
@@ -0,0 +1,176 @@
+use crate::cascade::CascadeChunk;
+use crate::cpu::{CpuMeshGenerator, MeshData};
+use crate::marching_cubes::VertexInterpolation;
+use bevy_math::prelude::*;
+use rayon::prelude::*;
+use sdf::Sdf;
+use std::sync::Arc;
+
+/// A box of world space tiled by chunks of the same size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkRegion {
+	/// The lower left bottom corner of the first chunk
+	pub origin: Vec3,
+	pub chunk_size: Vec3,
+	/// The number of chunks along each axis
+	pub count: UVec3,
+}
+
+impl ChunkRegion {
+	pub fn new(origin: Vec3, chunk_size: Vec3, count: UVec3) -> Self {
+		Self { origin, chunk_size, count }
+	}
+
+	/// The chunks on the lattice of the chunk size that cover the box from `min` to `max`.
+	pub fn covering(min: Vec3, max: Vec3, chunk_size: Vec3) -> Self {
+		let lower = (min / chunk_size).floor();
+		let upper = (max / chunk_size).ceil().max(lower);
+		Self { origin: lower * chunk_size, chunk_size, count: (upper - lower).as_uvec3() }
+	}
+
+	pub fn len(&self) -> usize {
+		self.count.element_product() as usize
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// The chunks of the region with the given per-axis resolution, X fastest, then Z, then Y.
+	pub fn chunks(&self, res_2: UVec3) -> impl Iterator<Item = CascadeChunk> + '_ {
+		(0..self.count.y).flat_map(move |y| {
+			(0..self.count.z).flat_map(move |z| {
+				(0..self.count.x).map(move |x| CascadeChunk {
+					origin: self.origin + UVec3::new(x, y, z).as_vec3() * self.chunk_size,
+					size: self.chunk_size,
+					res_2,
+					omit: None,
+				})
+			})
+		})
+	}
+}
+
+/// Generates chunk meshes from an SDF outside of the ECS.
+///
+/// For bake tools, tests and servers that need meshes for a region of the world
+/// without a camera, a cascade or a Bevy app.
+pub struct WorldGenerator<S: Sdf + Send + Sync> {
+	sdf: Arc<S>,
+	/// Whether chunks are generated in parallel on rayon's global pool
+	parallel: bool,
+	/// Value of the SDF whose isosurface is meshed
+	iso_level: f32,
+	/// How vertices are placed along the cube edges
+	interpolation: VertexInterpolation,
+}
+
+impl<S: Sdf + Send + Sync + 'static> WorldGenerator<S> {
+	pub fn new(sdf: S) -> Self {
+		Self::from_arc(Arc::new(sdf))
+	}
+
+	/// Shares an SDF that is also used elsewhere, such as by an `SdfResource`.
+	pub fn from_arc(sdf: Arc<S>) -> Self {
+		Self { sdf, parallel: false, iso_level: 0.0, interpolation: VertexInterpolation::default() }
+	}
+
+	pub fn with_parallel(mut self, parallel: bool) -> Self {
+		self.parallel = parallel;
+		self
+	}
+
+	/// Meshes the isosurface at the level instead of the surface, as `SdfResource::with_iso_level`.
+	pub fn with_iso_level(mut self, iso_level: f32) -> Self {
+		self.iso_level = iso_level;
+		self
+	}
+
+	/// Places vertices along the cube edges by the interpolation, as
+	/// `SdfResource::with_interpolation`.
+	pub fn with_interpolation(mut self, interpolation: VertexInterpolation) -> Self {
+		self.interpolation = interpolation;
+		self
+	}
+
+	/// Generates the chunks of the region in order, skipping chunks without a surface.
+	///
+	/// Chunks are generated lazily; in parallel, one batch per pool's worth of threads at a time.
+	pub fn iter_chunks(
+		&self,
+		region: &ChunkRegion,
+		res_2: UVec3,
+	) -> impl Iterator<Item = (CascadeChunk, MeshData)> + 'static {
+		let chunks: Vec<_> = region.chunks(res_2).collect();
+		let batch_size = if self.parallel { rayon::current_num_threads().max(1) * 2 } else { 1 };
+		let batches: Vec<Vec<CascadeChunk>> =
+			chunks.chunks(batch_size).map(<[CascadeChunk]>::to_vec).collect();
+
+		let sdf = Arc::clone(&self.sdf);
+		let parallel = self.parallel;
+		let iso_level = self.iso_level;
+		let interpolation = self.interpolation;
+		batches.into_iter().flat_map(move |batch| {
+			let generate = |chunk: &CascadeChunk| {
+				CpuMeshGenerator::generate_chunk_mesh_data_with(
+					chunk,
+					Arc::clone(&sdf),
+					iso_level,
+					interpolation,
+				)
+				.filter(|mesh| !mesh.is_empty())
+				.map(|mesh| (*chunk, mesh))
+			};
+			let meshes: Vec<_> = if parallel {
+				batch.par_iter().filter_map(generate).collect()
+			} else {
+				batch.iter().filter_map(generate).collect()
+			};
+			meshes
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A flat ground at y = 0
+	struct Ground;
+
+	impl Sdf for Ground {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.y
+		}
+	}
+
+	#[test]
+	fn test_iter_chunks_meshes_the_surface() {
+		let region = ChunkRegion::covering(
+			Vec3::new(-3.0, -1.5, -1.0),
+			Vec3::new(3.0, 1.5, 2.0),
+			Vec3::splat(2.0),
+		);
+		assert_eq!(region.count, UVec3::new(4, 2, 2));
+		assert_eq!(region.origin, Vec3::new(-4.0, -2.0, -2.0));
+
+		let generator = WorldGenerator::new(Ground);
+		let meshes: Vec<_> = generator.iter_chunks(&region, UVec3::splat(2)).collect();
+
+		// Only the chunks touching the ground have a surface
+		assert!(!meshes.is_empty() && meshes.len() <= region.len());
+		for (chunk, mesh) in &meshes {
+			assert!(!mesh.is_empty());
+			assert_eq!(mesh.positions.len(), mesh.normals.len());
+			for position in &mesh.positions {
+				let world = chunk.origin + Vec3::from_array(*position);
+				assert!(world.y.abs() < 1e-4);
+			}
+		}
+
+		// Parallel generation yields the same chunks in the same order
+		let parallel: Vec<_> =
+			generator.with_parallel(true).iter_chunks(&region, UVec3::splat(2)).collect();
+		assert_eq!(parallel, meshes);
+	}
+}
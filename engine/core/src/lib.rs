@@ -0,0 +1,26 @@
+pub mod cascade;
+pub mod compression;
+pub mod cpu;
+pub mod generator;
+pub mod marching_cubes;
+pub mod mesh_checks;
+pub mod occupancy;
+pub mod processor;
+
+pub use cascade::{CascadeChunk, OriginSnapping};
+pub use compression::CompressedMesh;
+pub use cpu::{CpuMeshGenerator, MeshData};
+pub use generator::{ChunkRegion, WorldGenerator};
+pub use marching_cubes::VertexInterpolation;
+pub use mesh_checks::{check_mesh, MeshCheckConfig, MeshReport};
+pub use occupancy::{ChunkOccupancy, OccupancyCheck};
+pub use processor::MeshProcessor;
+pub use sdf;
+
+// Chunk math and meshing without render dependencies, for bake tools, servers and tests.
+// The Bevy systems, materials and spawning built on it live in engine-bevy, and the engine
+// crate re-exports both.
+//
+// To generate chunks, use WorldGenerator::iter_chunks over a ChunkRegion, or
+// CpuMeshGenerator::generate_chunk_mesh_data for a single CascadeChunk.
+// To persist or send chunk meshes, encode their MeshData with CompressedMesh.
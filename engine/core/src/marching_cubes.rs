@@ -23,7 +23,7 @@
 //!     /
 //!    y
 //! ```
-use bevy::prelude::*;
+use bevy_math::prelude::*;
 
 pub const EDGE_VERTEX_INDICES: [(usize, usize); 12] = [
 	(0, 1), // edge 0
@@ -52,26 +52,39 @@ pub fn get_cube_index(corners: [f32; 8]) -> usize {
 	index
 }
 
+/// Standard cube corner positions in local space (same as TRIANGULATIONS assumes)
+const CUBE_CORNERS: [Vec3; 8] = [
+	Vec3::new(0.0, 0.0, 0.0), // 0
+	Vec3::new(1.0, 0.0, 0.0), // 1
+	Vec3::new(1.0, 0.0, 1.0), // 2
+	Vec3::new(0.0, 0.0, 1.0), // 3
+	Vec3::new(0.0, 1.0, 0.0), // 4
+	Vec3::new(1.0, 1.0, 0.0), // 5
+	Vec3::new(1.0, 1.0, 1.0), // 6
+	Vec3::new(0.0, 1.0, 1.0), // 7
+];
+
+/// How vertices are placed along the cube edges the surface crosses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum VertexInterpolation {
+	/// Where the line between the corner distances crosses zero
+	#[default]
+	Linear,
+	/// At the middle of the edge, for blocky stylized worlds
+	Midpoint,
+	/// Linearly, then moved closer to the surface by the given number of secant steps, each
+	/// sampling the SDF once more
+	Refined(u8),
+}
+
 /// Interpolate vertex position along an edge
 #[inline]
 pub fn interpolate_vertex(
 	edge: usize,
 	cube_origin: Vec3,
-	cube_size: f32,
+	cube_size: Vec3,
 	corner_values: [f32; 8],
 ) -> Vec3 {
-	// Standard cube corner positions in local space (same as TRIANGULATIONS assumes)
-	const CUBE_CORNERS: [Vec3; 8] = [
-		Vec3::new(0.0, 0.0, 0.0), // 0
-		Vec3::new(1.0, 0.0, 0.0), // 1
-		Vec3::new(1.0, 0.0, 1.0), // 2
-		Vec3::new(0.0, 0.0, 1.0), // 3
-		Vec3::new(0.0, 1.0, 0.0), // 4
-		Vec3::new(1.0, 1.0, 0.0), // 5
-		Vec3::new(1.0, 1.0, 1.0), // 6
-		Vec3::new(0.0, 1.0, 1.0), // 7
-	];
-
 	let (a, b) = EDGE_VERTEX_INDICES[edge];
 	let v1 = CUBE_CORNERS[a];
 	let v2 = CUBE_CORNERS[b];
@@ -91,6 +104,57 @@ pub fn interpolate_vertex(
 	cube_origin + pos_local * cube_size
 }
 
+/// Place a vertex along an edge by the interpolation.
+///
+/// `field` samples the scalar field at a position in the space of the cube origin, and is only
+/// called for refined placement. Secant steps keep the crossing bracketed between the last
+/// samples on either side of it, so the vertex never leaves the edge.
+#[inline]
+pub fn place_vertex(
+	edge: usize,
+	cube_origin: Vec3,
+	cube_size: Vec3,
+	corner_values: [f32; 8],
+	interpolation: VertexInterpolation,
+	field: impl Fn(Vec3) -> f32,
+) -> Vec3 {
+	let (a, b) = EDGE_VERTEX_INDICES[edge];
+	let (v1, v2) = (CUBE_CORNERS[a], CUBE_CORNERS[b]);
+	let at = |t: f32| cube_origin + (v1 + (v2 - v1) * t) * cube_size;
+	match interpolation {
+		VertexInterpolation::Linear => {
+			interpolate_vertex(edge, cube_origin, cube_size, corner_values)
+		}
+		VertexInterpolation::Midpoint => at(0.5),
+		VertexInterpolation::Refined(steps) => {
+			let crossing = |t1: f32, val1: f32, t2: f32, val2: f32| {
+				if (val1 - val2).abs() < 1e-6 {
+					return (t1 + t2) * 0.5;
+				}
+				(t1 + (t2 - t1) * (-val1) / (val2 - val1)).clamp(t1.min(t2), t1.max(t2))
+			};
+			let (mut t1, mut val1) = (0.0, corner_values[a]);
+			let (mut t2, mut val2) = (1.0, corner_values[b]);
+			let mut t = crossing(t1, val1, t2, val2);
+			let mut position = interpolate_vertex(edge, cube_origin, cube_size, corner_values);
+			for _ in 0..steps {
+				let value = field(position);
+				if value == 0.0 {
+					break;
+				}
+				if (value < 0.0) == (val1 < 0.0) {
+					(t1, val1) = (t, value);
+				} else {
+					(t2, val2) = (t, value);
+				}
+				t = crossing(t1, val1, t2, val2);
+				position = at(t);
+			}
+			position
+		}
+	}
+}
+
 // Full triangulation table - 256 entries, one for each possible cube configuration
 // Each entry is a list of edge indices forming triangles, terminated by -1
 // Based on: https://gist.github.com/dwilliamson/c041e3454a713e58baf6e4f8e5fffecd
@@ -0,0 +1,260 @@
+use crate::cpu::MeshData;
+use bevy_math::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+
+/// What [check_mesh] looks for, beyond the checks it always runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshCheckConfig {
+	/// Triangles with less area than this are degenerate
+	pub min_triangle_area: f32,
+	/// Allowed deviation of the normal lengths from one
+	pub normal_tolerance: f32,
+	/// Whether to check that every edge is shared by at most two consistently wound triangles
+	pub check_manifold: bool,
+	/// Distance under which vertices count as the same point for the manifold check,
+	/// since neighbouring cubes don't share vertices
+	pub weld_distance: f32,
+}
+
+impl Default for MeshCheckConfig {
+	fn default() -> Self {
+		Self {
+			min_triangle_area: 1e-10,
+			normal_tolerance: 1e-3,
+			check_manifold: false,
+			weld_distance: 1e-4,
+		}
+	}
+}
+
+impl MeshCheckConfig {
+	pub fn with_min_triangle_area(mut self, min_triangle_area: f32) -> Self {
+		self.min_triangle_area = min_triangle_area;
+		self
+	}
+
+	pub fn with_normal_tolerance(mut self, normal_tolerance: f32) -> Self {
+		self.normal_tolerance = normal_tolerance;
+		self
+	}
+
+	pub fn with_manifold(mut self, check_manifold: bool) -> Self {
+		self.check_manifold = check_manifold;
+		self
+	}
+
+	pub fn with_weld_distance(mut self, weld_distance: f32) -> Self {
+		self.weld_distance = weld_distance;
+		self
+	}
+}
+
+/// The problems found in a mesh. Indices refer to vertices or triangles of the checked mesh.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshReport {
+	pub vertices: usize,
+	pub triangles: usize,
+	/// The normal, UV or color buffer doesn't match the number of positions
+	pub attribute_mismatch: bool,
+	/// The index count is not a multiple of three
+	pub trailing_indices: bool,
+	/// Vertices with a NaN or infinite position
+	pub non_finite_positions: Vec<usize>,
+	/// Triangles with an index past the vertex buffer
+	pub out_of_bounds_triangles: Vec<usize>,
+	/// Triangles with repeated vertices or no area
+	pub degenerate_triangles: Vec<usize>,
+	/// Vertices with a normal that is not unit length
+	pub bad_normals: Vec<usize>,
+	/// Edges shared by more than two triangles or wound the same way twice, if checked
+	pub non_manifold_edges: usize,
+}
+
+impl MeshReport {
+	pub fn is_valid(&self) -> bool {
+		!self.attribute_mismatch
+			&& !self.trailing_indices
+			&& self.non_finite_positions.is_empty()
+			&& self.out_of_bounds_triangles.is_empty()
+			&& self.degenerate_triangles.is_empty()
+			&& self.bad_normals.is_empty()
+			&& self.non_manifold_edges == 0
+	}
+}
+
+impl fmt::Display for MeshReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} vertices, {} triangles", self.vertices, self.triangles)?;
+		if self.attribute_mismatch {
+			write!(f, ", mismatched attribute buffers")?;
+		}
+		if self.trailing_indices {
+			write!(f, ", trailing indices")?;
+		}
+		let counts = [
+			(self.non_finite_positions.len(), "non-finite positions"),
+			(self.out_of_bounds_triangles.len(), "out of bounds triangles"),
+			(self.degenerate_triangles.len(), "degenerate triangles"),
+			(self.bad_normals.len(), "bad normals"),
+			(self.non_manifold_edges, "non-manifold edges"),
+		];
+		for (count, problem) in counts {
+			if count > 0 {
+				write!(f, ", {count} {problem}")?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Validates the topology and attributes of a generated mesh.
+pub fn check_mesh(mesh: &MeshData, config: &MeshCheckConfig) -> MeshReport {
+	let positions = &mesh.positions;
+	let mut report = MeshReport {
+		vertices: positions.len(),
+		triangles: mesh.triangle_count(),
+		attribute_mismatch: mesh.normals.len() != positions.len()
+			|| mesh.uvs.len() != positions.len()
+			|| (!mesh.colors.is_empty() && mesh.colors.len() != positions.len()),
+		trailing_indices: mesh.indices.len() % 3 != 0,
+		..Default::default()
+	};
+
+	report.non_finite_positions = positions
+		.iter()
+		.enumerate()
+		.filter(|(_, position)| !Vec3::from_array(**position).is_finite())
+		.map(|(i, _)| i)
+		.collect();
+
+	report.bad_normals = mesh
+		.normals
+		.iter()
+		.enumerate()
+		.filter(|(_, normal)| {
+			let length = Vec3::from_array(**normal).length();
+			!length.is_finite() || (length - 1.0).abs() > config.normal_tolerance
+		})
+		.map(|(i, _)| i)
+		.collect();
+
+	let mut triangles = Vec::with_capacity(report.triangles);
+	for (i, triangle) in mesh.indices.chunks_exact(3).enumerate() {
+		let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+		if [a, b, c].iter().any(|&index| index as usize >= positions.len()) {
+			report.out_of_bounds_triangles.push(i);
+			continue;
+		}
+		let [pa, pb, pc] = [a, b, c].map(|index| Vec3::from_array(positions[index as usize]));
+		let area = (pb - pa).cross(pc - pa).length() / 2.0;
+		if a == b || b == c || a == c || area.is_nan() || area < config.min_triangle_area {
+			report.degenerate_triangles.push(i);
+			continue;
+		}
+		triangles.push([pa, pb, pc]);
+	}
+
+	if config.check_manifold {
+		report.non_manifold_edges = count_non_manifold_edges(&triangles, config.weld_distance);
+	}
+	report
+}
+
+/// Counts the edges, between welded points, that are used by more than two triangles
+/// or twice in the same direction.
+fn count_non_manifold_edges(triangles: &[[Vec3; 3]], weld_distance: f32) -> usize {
+	let weld = |point: Vec3| (point / weld_distance.max(f32::EPSILON)).round().as_ivec3();
+
+	// Uses of each undirected edge, and how many of them run from the lower point
+	let mut edges: HashMap<(IVec3, IVec3), (usize, usize)> = HashMap::new();
+	for triangle in triangles {
+		let points = triangle.map(weld);
+		for i in 0..3 {
+			let (from, to) = (points[i], points[(i + 1) % 3]);
+			if from == to {
+				continue;
+			}
+			let forward = from.to_array() < to.to_array();
+			let key = if forward { (from, to) } else { (to, from) };
+			let (uses, forward_uses) = edges.entry(key).or_default();
+			*uses += 1;
+			*forward_uses += usize::from(forward);
+		}
+	}
+
+	edges
+		.values()
+		.filter(|(uses, forward_uses)| *uses > 2 || (*uses == 2 && *forward_uses != 1))
+		.count()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::cascade::CascadeChunk;
+	use crate::cpu::CpuMeshGenerator;
+	use sdf::Sdf;
+	use std::sync::Arc;
+
+	/// Two triangles forming a unit quad in the xz plane
+	fn quad() -> MeshData {
+		MeshData {
+			positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0], [0.0, 0.0, 1.0]],
+			normals: vec![[0.0, 1.0, 0.0]; 4],
+			uvs: vec![[0.0, 0.0]; 4],
+			colors: Vec::new(),
+			indices: vec![0, 2, 1, 0, 3, 2],
+		}
+	}
+
+	#[test]
+	fn test_check_mesh_reports_each_problem() {
+		let config = MeshCheckConfig::default().with_manifold(true);
+		assert!(check_mesh(&quad(), &config).is_valid());
+
+		let mut mesh = quad();
+		mesh.positions[1][0] = f32::NAN;
+		mesh.normals[3] = [0.0, 2.0, 0.0];
+		mesh.indices.extend([0, 1, 9, 1, 1, 2]);
+		let report = check_mesh(&mesh, &config);
+		assert_eq!(report.non_finite_positions, vec![1]);
+		assert_eq!(report.bad_normals, vec![3]);
+		assert_eq!(report.out_of_bounds_triangles, vec![2]);
+		assert!(report.degenerate_triangles.contains(&3));
+		assert!(!report.is_valid());
+
+		// A third triangle on the diagonal, and the second wound the same way as the first
+		let mut mesh = quad();
+		mesh.indices.extend([0, 2, 1]);
+		assert_eq!(check_mesh(&mesh, &config).non_manifold_edges, 3);
+		assert_eq!(check_mesh(&mesh, &MeshCheckConfig::default()).non_manifold_edges, 0);
+
+		let mut mesh = quad();
+		mesh.uvs.pop();
+		mesh.indices.pop();
+		let report = check_mesh(&mesh, &config);
+		assert!(report.attribute_mismatch && report.trailing_indices);
+	}
+
+	/// A sphere with no grid corners on its surface, which would give degenerate triangles
+	struct Sphere;
+
+	impl Sdf for Sphere {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.distance(Vec3::new(0.1, 0.2, 0.3)) - 2.27
+		}
+	}
+
+	#[test]
+	fn test_generated_chunk_is_valid() {
+		let chunk = CascadeChunk::cube(Vec3::splat(-4.0), 8.0, 4);
+		let Some(mesh) = CpuMeshGenerator::generate_chunk_mesh_data(&chunk, Arc::new(Sphere))
+		else {
+			panic!("expected a sphere mesh");
+		};
+		let report = check_mesh(&mesh, &MeshCheckConfig::default().with_manifold(true));
+		assert!(report.triangles > 0);
+		assert!(report.is_valid(), "{report}");
+	}
+}
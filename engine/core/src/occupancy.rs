@@ -0,0 +1,206 @@
+use crate::cascade::CascadeChunk;
+use bevy_math::bounding::{Aabb3d, IntersectsVolume};
+use bevy_math::prelude::*;
+use sdf::{Bounds, Sdf, Sign};
+
+/// What a chunk holds, as far as interval analysis can tell without sampling it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChunkOccupancy {
+	/// All air, with no surface to mesh
+	Empty,
+	/// All ground, with no surface to mesh
+	Solid,
+	/// May hold a surface, so it has to be sampled
+	Mixed,
+}
+
+/// Classifies chunks from the sign intervals of a few of their columns before sampling them.
+///
+/// The columns at the corners and the center of a chunk are checked over the height of the
+/// chunk widened by the margin, which allows the surface to climb between the columns by the
+/// slope times the distance to the nearest checked column. Only the surface the columns pass
+/// through is seen, so the check suits heightfields no steeper than the slope, and is opt-in.
+/// SDFs with bounds, such as a rock or a tree, may lie between the columns, so chunks their
+/// bounds reach are always sampled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OccupancyCheck {
+	/// Steepest rise of the surface per unit of horizontal distance allowed between columns
+	pub slope: f32,
+}
+
+impl Default for OccupancyCheck {
+	fn default() -> Self {
+		Self { slope: 1.0 }
+	}
+}
+
+impl OccupancyCheck {
+	pub fn with_slope(mut self, slope: f32) -> Self {
+		self.slope = slope;
+		self
+	}
+
+	/// Vertical margin by which the chunk's columns are widened.
+	pub fn margin(&self, chunk: &CascadeChunk) -> f32 {
+		// No point of the chunk is further from a checked column than half its longer side
+		self.slope * chunk.size.x.max(chunk.size.z) / 2.0
+	}
+
+	pub fn classify<S: Sdf + ?Sized>(&self, sdf: &S, chunk: &CascadeChunk) -> ChunkOccupancy {
+		self.classify_at(sdf, chunk, 0.0)
+	}
+
+	/// Classifies the chunk for the isosurface at the level.
+	///
+	/// The isosurface lies within the level of the surface at zero, so the chunk is grown by the
+	/// level and checked for the surface at zero, and the side of the isosurface it is on is
+	/// read from the distance at its center.
+	pub fn classify_at<S: Sdf + ?Sized>(
+		&self,
+		sdf: &S,
+		chunk: &CascadeChunk,
+		iso_level: f32,
+	) -> ChunkOccupancy {
+		let grown = CascadeChunk {
+			origin: chunk.origin - Vec3::splat(iso_level.abs()),
+			size: chunk.size + Vec3::splat(iso_level.abs() * 2.0),
+			..*chunk
+		};
+
+		// Nothing is defined outside the bounds of the SDF, and bounded features may lie
+		// between the columns
+		if let Bounds::Cuboid(bounds) = sdf.bounds() {
+			let aabb = Aabb3d { min: grown.origin.into(), max: (grown.origin + grown.size).into() };
+			return match bounds.intersects(&aabb) {
+				true => ChunkOccupancy::Mixed,
+				false => ChunkOccupancy::Empty,
+			};
+		}
+
+		if !self.has_uniform_columns(sdf, &grown) {
+			return ChunkOccupancy::Mixed;
+		}
+		match sdf.distance(chunk.origin + chunk.size / 2.0) > iso_level {
+			true => ChunkOccupancy::Empty,
+			false => ChunkOccupancy::Solid,
+		}
+	}
+
+	/// Whether the checked columns of the chunk have one known sign over its widened height.
+	fn has_uniform_columns<S: Sdf + ?Sized>(&self, sdf: &S, chunk: &CascadeChunk) -> bool {
+		let margin = self.margin(chunk);
+		let (bottom, top) = (chunk.origin.y - margin, chunk.origin.y + chunk.size.y + margin);
+		let (min, max) = (chunk.origin.xz(), chunk.origin.xz() + chunk.size.xz());
+		let columns =
+			[min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y), (min + max) / 2.0];
+
+		let mut column_sign = None;
+		for column in columns {
+			for interval in sdf.sign_uniform_on_y(column.x, column.y) {
+				let (low, high) = interval.open_range();
+				if high <= bottom || low >= top {
+					continue;
+				}
+				if matches!(interval.left.sign, Sign::Top | Sign::Bottom)
+					|| *column_sign.get_or_insert_with(|| interval.left.sign.clone())
+						!= interval.left.sign
+				{
+					return false;
+				}
+			}
+		}
+		column_sign.is_some()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sdf::{SignBoundary, SignUniformIntervals, SphereSdf};
+
+	/// Ground at height 0, with known signs from -100 down
+	struct Ground;
+
+	impl Sdf for Ground {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.y
+		}
+
+		fn sign_uniform_on_y(&self, _x: f32, _z: f32) -> SignUniformIntervals {
+			let mut intervals = SignUniformIntervals::default();
+			intervals.insert_boundary(SignBoundary { min: -100.0, sign: Sign::Negative });
+			intervals.insert_boundary(SignBoundary { min: 0.0, sign: Sign::Positive });
+			intervals
+		}
+	}
+
+	/// A ridge along z rising 6 for every 1 across, to a peak of 12 at x = 2
+	struct Ridge;
+
+	impl Ridge {
+		fn height(x: f32) -> f32 {
+			(12.0 - 6.0 * (x - 2.0).abs()).max(0.0)
+		}
+	}
+
+	impl Sdf for Ridge {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.y - Self::height(p.x)
+		}
+
+		fn sign_uniform_on_y(&self, x: f32, _z: f32) -> SignUniformIntervals {
+			let mut intervals = SignUniformIntervals::default();
+			intervals.insert_boundary(SignBoundary { min: -100.0, sign: Sign::Negative });
+			intervals.insert_boundary(SignBoundary { min: Self::height(x), sign: Sign::Positive });
+			intervals
+		}
+	}
+
+	#[test]
+	fn test_classify_chunks_around_the_ground() {
+		let check = OccupancyCheck::default();
+		let chunk = |y: f32| CascadeChunk::cube(Vec3::new(0.0, y, 0.0), 8.0, 2);
+
+		assert_eq!(check.classify(&Ground, &chunk(16.0)), ChunkOccupancy::Empty);
+		assert_eq!(check.classify(&Ground, &chunk(-40.0)), ChunkOccupancy::Solid);
+		assert_eq!(check.classify(&Ground, &chunk(-4.0)), ChunkOccupancy::Mixed);
+		// Just above the ground, within the margin for slopes between the columns
+		assert_eq!(check.classify(&Ground, &chunk(2.0)), ChunkOccupancy::Mixed);
+		assert_eq!(check.with_slope(0.0).classify(&Ground, &chunk(2.0)), ChunkOccupancy::Empty);
+		// The same chunk around an isosurface raised into it
+		assert_eq!(
+			check.with_slope(0.0).classify_at(&Ground, &chunk(2.0), 3.0),
+			ChunkOccupancy::Mixed
+		);
+		assert_eq!(check.classify_at(&Ground, &chunk(-40.0), 3.0), ChunkOccupancy::Solid);
+		// Below the known signs
+		assert_eq!(check.classify(&Ground, &chunk(-120.0)), ChunkOccupancy::Mixed);
+	}
+
+	#[test]
+	fn test_steep_heightfields_need_their_slope() {
+		let check = OccupancyCheck::default().with_slope(6.0);
+
+		// The peak of the ridge rises through the chunk between its columns, which are all
+		// below the chunk, and only a margin for its slope reaches down to them
+		let chunk = CascadeChunk::cube(Vec3::new(0.0, 5.0, 0.0), 8.0, 2);
+		assert!(Ridge.distance(Vec3::new(2.0, 10.0, 4.0)) < 0.0);
+		assert_eq!(check.classify(&Ridge, &chunk), ChunkOccupancy::Mixed);
+		assert_eq!(OccupancyCheck::default().classify(&Ridge, &chunk), ChunkOccupancy::Empty);
+		// Above the peak
+		let above = CascadeChunk::cube(Vec3::new(0.0, 40.0, 0.0), 8.0, 2);
+		assert_eq!(check.classify(&Ridge, &above), ChunkOccupancy::Empty);
+	}
+
+	#[test]
+	fn test_small_features_between_columns_are_mixed() {
+		let check = OccupancyCheck::default();
+		let chunk = CascadeChunk::cube(Vec3::ZERO, 8.0, 2);
+
+		// A rock floating in the air, away from the corners and the center of the chunk
+		let rock = SphereSdf::new(Vec3::new(2.0, 5.0, 6.0), 0.25);
+		assert_eq!(check.classify(&rock, &chunk), ChunkOccupancy::Mixed);
+		let far = SphereSdf::new(Vec3::new(40.0, 5.0, 6.0), 0.25);
+		assert_eq!(check.classify(&far, &chunk), ChunkOccupancy::Empty);
+	}
+}
@@ -0,0 +1,29 @@
+use crate::cascade::CascadeChunk;
+use crate::cpu::MeshData;
+use sdf::Sdf;
+
+/// A step run over the mesh data of every chunk before it is spawned.
+///
+/// Simplification, ambient occlusion bakes, tangent generation and vertex color tagging are
+/// all processors, so they compose in a registry (engine-bevy's `MeshProcessors`) rather than
+/// each patching the generator. Closures taking the same arguments as [MeshProcessor::process]
+/// are processors.
+pub trait MeshProcessor<S: Sdf>: Send + Sync {
+	/// Name of the processor, for logs.
+	fn name(&self) -> &str {
+		std::any::type_name::<Self>()
+	}
+
+	/// Processes the mesh of the chunk, or returns None to leave the chunk without a mesh.
+	fn process(&self, sdf: &S, chunk: &CascadeChunk, mesh: MeshData) -> Option<MeshData>;
+}
+
+impl<S, F> MeshProcessor<S> for F
+where
+	S: Sdf,
+	F: Fn(&S, &CascadeChunk, MeshData) -> Option<MeshData> + Send + Sync,
+{
+	fn process(&self, sdf: &S, chunk: &CascadeChunk, mesh: MeshData) -> Option<MeshData> {
+		self(sdf, chunk, mesh)
+	}
+}
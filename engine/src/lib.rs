@@ -1,18 +1,6 @@
-pub mod cascade;
-pub mod chunk;
-pub mod chunk_manager;
-pub mod cpu;
-pub mod marching_cubes;
-pub mod shaders;
+pub use engine_bevy::*;
+pub use {engine_bevy, engine_core};
 
-pub use chunk::{ChunkConfig, ChunkCoord, LoadedChunks};
-pub use chunk_manager::{manage_chunks, ChunkResolutionConfig, SdfResource};
-pub use sdf;
-
-// Main exports for the engine
-// Users should register:
-// - ChunkConfig resource
-// - ChunkResolutionConfig resource
-// - SdfResource<S> resource (where S: Sdf + Send + Sync)
-// - LoadedChunks resource
-// - Then add manage_chunks system to their Update schedule
+// Facade over the engine crates, so existing users keep their engine:: paths:
+// - engine-core for chunk math, SDF sampling and meshing, without render dependencies
+// - engine-bevy for the systems, materials and spawning (see its lib.rs for what to register)
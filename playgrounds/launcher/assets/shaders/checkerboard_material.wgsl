@@ -0,0 +1 @@
+../../../objects/assets/shaders/checkerboard_material.wgsl
\ No newline at end of file
@@ -0,0 +1 @@
+../../../terrain/assets/shaders/decal_material.wgsl
\ No newline at end of file
@@ -0,0 +1 @@
+../../../terrain/assets/shaders/edge_material.wgsl
\ No newline at end of file
@@ -0,0 +1 @@
+../../../terrain/assets/shaders/far_terrain_material.wgsl
\ No newline at end of file
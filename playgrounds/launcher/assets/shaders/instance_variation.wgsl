@@ -0,0 +1 @@
+../../../terrain/assets/shaders/instance_variation.wgsl
\ No newline at end of file
@@ -0,0 +1 @@
+../../../terrain/assets/shaders/leaf_material.wgsl
\ No newline at end of file
@@ -0,0 +1,168 @@
+use bevy::camera::visibility::RenderLayers;
+use bevy::prelude::*;
+use engine::lighting::StandardLight;
+use engine::shaders::{leaf_material::LeafMaterial, outline::EdgeMaterial};
+use engine::{StandardLightingPlugin, WorldPalettePlugin};
+use objects_playground::{ObjectsScenePlugin, ObjectsSystems};
+use render_item::mesh::SHADOW_PROXY_LAYER;
+use terrain_playground::{TerrainScenePlugin, TerrainSystems};
+
+/// The playground scene the launcher shows.
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PlaygroundScene {
+	#[default]
+	Terrain,
+	Objects,
+}
+
+impl PlaygroundScene {
+	pub fn next(self) -> Self {
+		match self {
+			PlaygroundScene::Terrain => PlaygroundScene::Objects,
+			PlaygroundScene::Objects => PlaygroundScene::Terrain,
+		}
+	}
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			PlaygroundScene::Terrain => "terrain",
+			PlaygroundScene::Objects => "objects",
+		}
+	}
+}
+
+/// Key that switches to the next scene.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct SceneSwitchKey(pub KeyCode);
+
+/// Switches between the playground scenes, despawning what a scene spawned as it is left and
+/// running the systems of a scene only while it is shown.
+///
+/// Entities the scenes share, such as the lights of the [StandardLightingPlugin], are kept.
+/// Each scene spawns its own camera, as the terrain is in kilometers and the objects in meters.
+#[derive(Debug, Clone, Copy)]
+pub struct SceneSwitcherPlugin {
+	/// The scene shown first
+	pub scene: PlaygroundScene,
+	pub switch_key: KeyCode,
+}
+
+impl Default for SceneSwitcherPlugin {
+	fn default() -> Self {
+		Self { scene: PlaygroundScene::default(), switch_key: KeyCode::F1 }
+	}
+}
+
+impl Plugin for SceneSwitcherPlugin {
+	fn build(&self, app: &mut App) {
+		app.insert_state(self.scene)
+			.insert_resource(SceneSwitchKey(self.switch_key))
+			.configure_sets(Update, TerrainSystems.run_if(in_state(PlaygroundScene::Terrain)))
+			.configure_sets(Update, ObjectsSystems.run_if(in_state(PlaygroundScene::Objects)))
+			.add_systems(Update, switch_scene)
+			.add_systems(OnExit(PlaygroundScene::Terrain), despawn_scene)
+			.add_systems(OnExit(PlaygroundScene::Objects), despawn_scene);
+	}
+}
+
+/// Both playgrounds in one app, sharing their materials, lighting and palette, with a key to
+/// switch between the terrain and objects scenes.
+pub struct LauncherPlugin {
+	pub seed: u32,
+	pub switcher: SceneSwitcherPlugin,
+}
+
+impl Plugin for LauncherPlugin {
+	fn build(&self, app: &mut App) {
+		// Registered once here rather than by each scene
+		app.add_plugins(bevy::pbr::MaterialPlugin::<EdgeMaterial>::default());
+		app.add_plugins(bevy::pbr::MaterialPlugin::<LeafMaterial>::default());
+		// The sun also sees the canopy shadow proxies of the objects scene
+		app.add_plugins(
+			StandardLightingPlugin::default()
+				.with_cycle_key(KeyCode::KeyL)
+				.with_sun_layers(RenderLayers::from_layers(&[0, SHADOW_PROXY_LAYER])),
+		);
+		app.add_plugins(WorldPalettePlugin::default().with_cycle_key(KeyCode::KeyP));
+
+		app.add_plugins(self.switcher);
+		app.add_plugins(
+			TerrainScenePlugin::new(self.seed)
+				.with_setup(OnEnter(PlaygroundScene::Terrain))
+				.with_teardown(OnExit(PlaygroundScene::Terrain)),
+		);
+		app.add_plugins(
+			ObjectsScenePlugin::new(self.seed)
+				.with_setup(OnEnter(PlaygroundScene::Objects))
+				.with_teardown(OnExit(PlaygroundScene::Objects)),
+		);
+	}
+}
+
+/// Moves to the next scene when the switch key is pressed.
+pub fn switch_scene(
+	keyboard_input: Res<ButtonInput<KeyCode>>,
+	switch_key: Res<SceneSwitchKey>,
+	scene: Res<State<PlaygroundScene>>,
+	mut next_scene: ResMut<NextState<PlaygroundScene>>,
+) {
+	if keyboard_input.just_pressed(switch_key.0) {
+		let next = scene.get().next();
+		log::info!("Switching to the {} scene", next.name());
+		next_scene.set(next);
+	}
+}
+
+/// Despawns the cameras, meshes and UI of the scene being left, keeping the shared lights.
+pub fn despawn_scene(
+	mut commands: Commands,
+	scene_query: Query<
+		Entity,
+		(Or<(With<Transform>, With<Node>)>, Without<ChildOf>, Without<StandardLight>),
+	>,
+) {
+	for entity in scene_query.iter() {
+		commands.entity(entity).despawn();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::state::app::StatesPlugin;
+
+	#[test]
+	fn test_switching_scenes_despawns_the_scene_left() {
+		let mut app = App::new();
+		app.add_plugins((StatesPlugin, SceneSwitcherPlugin::default()))
+			.init_resource::<ButtonInput<KeyCode>>();
+		let light = app.world_mut().spawn((StandardLight, Transform::default())).id();
+		let camera = app.world_mut().spawn(Transform::default()).id();
+		let overlay = app.world_mut().spawn(Node::default()).id();
+		let child = app.world_mut().spawn((Transform::default(), ChildOf(camera))).id();
+		app.update();
+		assert_eq!(*app.world().resource::<State<PlaygroundScene>>(), PlaygroundScene::Terrain);
+
+		// The scene is switched on the frame after the key is pressed
+		press(&mut app, KeyCode::F1);
+		app.update();
+		assert_eq!(*app.world().resource::<State<PlaygroundScene>>(), PlaygroundScene::Objects);
+		for entity in [camera, overlay, child] {
+			assert!(app.world().get_entity(entity).is_err());
+		}
+		assert!(app.world().get_entity(light).is_ok());
+
+		press(&mut app, KeyCode::F1);
+		app.update();
+		assert_eq!(*app.world().resource::<State<PlaygroundScene>>(), PlaygroundScene::Terrain);
+	}
+
+	/// Presses the key for a frame.
+	fn press(app: &mut App, key: KeyCode) {
+		app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(key);
+		app.update();
+		let mut keyboard_input = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+		keyboard_input.release(key);
+		keyboard_input.clear();
+	}
+}
@@ -0,0 +1,21 @@
+use bevy::prelude::*;
+use launcher_playground::{LauncherPlugin, SceneSwitcherPlugin};
+
+fn main() {
+	// Parse seed from command line or use default
+	let seed = std::env::args().nth(1).and_then(|s| s.parse::<u32>().ok()).unwrap_or(12345);
+
+	println!("Starting playground launcher with seed: {} (F1 switches scenes)", seed);
+
+	App::new()
+		.add_plugins(DefaultPlugins.set(WindowPlugin {
+			primary_window: Some(Window {
+				title: "Playground Launcher".to_string(),
+				resolution: (1280, 720).into(),
+				..default()
+			}),
+			..default()
+		}))
+		.add_plugins(LauncherPlugin { seed, switcher: SceneSwitcherPlugin::default() })
+		.run();
+}
@@ -1,10 +1,16 @@
 use bevy::prelude::*;
 use buildings::{
-	complex::{fillers::scratchpad::ScratchpadFiller, render::ComplexRenderer, Complex},
-	meshes::walls::wall::WallMesh,
+	complex::{
+		fillers::scratchpad::ScratchpadFiller,
+		render::ComplexRenderer,
+		ruin::{Ruin, Ruinifier},
+		Complex,
+	},
+	meshes::walls::wall::{Wall, WallMesh},
 };
 use chunk::cascade::CascadeChunk;
 use engine::shaders::outline::EdgeMaterial;
+use engine::{PaletteMaterials, PaletteRole, WorldPalette};
 use render_item::{mesh::cache::handle::map::HandleMap, DispatchRenderItem};
 
 #[derive(Resource, Clone)]
@@ -13,11 +19,12 @@ pub struct BuildingMaterial<M: Material>(pub Handle<M>);
 pub fn setup_buildings_material(
 	mut commands: Commands,
 	mut materials: ResMut<Assets<EdgeMaterial>>,
+	palette: Res<WorldPalette>,
+	mut palette_materials: ResMut<PaletteMaterials>,
 ) {
-	let material_handle = materials.add(EdgeMaterial {
-		// brownish color
-		base_color: Vec4::new(0.89, 0.886, 0.604, 1.0),
-	});
+	let material_handle =
+		materials.add(EdgeMaterial::new(palette.base_color(PaletteRole::Structure)));
+	palette_materials.track_edge(material_handle.clone(), PaletteRole::Structure);
 
 	commands.insert_resource(BuildingMaterial(material_handle));
 }
@@ -31,7 +38,7 @@ pub fn building_playground<F: Material, P: Material>(
 
 	let partition_cache = HandleMap::<WallMesh>::new();
 	let mut scratchpad_filler = ScratchpadFiller::new(MeshMaterial3d(partition_material.0.clone()))
-		.with_wall_cache(partition_cache)
+		.with_wall_cache(partition_cache.clone())
 		.with_partition_threshold(0.4);
 	let mut complex = Complex::new(Vec3::ZERO, Vec3::new(4.0, 2.0, 4.0), (32, 32, 32));
 	complex.fill_canonical_members(&mut scratchpad_filler);
@@ -42,4 +49,18 @@ pub fn building_playground<F: Material, P: Material>(
 		DispatchRenderItem::new(complex_renderer),
 		Transform::from_translation(Vec3::ZERO),
 	));
+
+	// A ruin from the same filler beside it, its rubble heaped as blocks of the walls
+	let mut ruined = Complex::new(Vec3::new(140.0, 0.0, 0.0), Vec3::new(4.0, 2.0, 4.0), (8, 4, 8));
+	ruined.fill_canonical_members(&mut scratchpad_filler);
+	let rubble =
+		Wall::new(MeshMaterial3d(partition_material.0.clone())).with_wall_cache(partition_cache);
+	let ruin = Ruin::new(ruined, &Ruinifier::new(7), rubble);
+	log::info!("Ruin left {} rubble piles", ruin.piles.len());
+
+	commands.spawn((
+		CascadeChunk::unit_center_chunk().with_res_2(3),
+		DispatchRenderItem::new(ruin),
+		Transform::from_translation(Vec3::ZERO),
+	));
 }
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use engine::Actions;
 use std::f32::consts::PI;
 
 #[derive(Component)]
@@ -65,7 +66,7 @@ pub fn setup_camera(mut commands: Commands) {
 }
 
 pub fn camera_controller(
-	keyboard_input: Res<ButtonInput<KeyCode>>,
+	actions: Actions,
 	mut mouse_motion: MessageReader<bevy::input::mouse::MouseMotion>,
 	time: Res<Time>,
 	mut query: Query<(&mut Transform, &mut CameraController), With<Camera3d>>,
@@ -80,8 +81,11 @@ pub fn camera_controller(
 		mouse_delta += event.delta;
 	}
 
-	controller.yaw -= mouse_delta.x * controller.sensitivity;
-	controller.pitch -= mouse_delta.y * controller.sensitivity;
+	// Gamepad look
+	let look_delta = actions.look_delta(time.delta_secs());
+
+	controller.yaw -= mouse_delta.x * controller.sensitivity + look_delta.x;
+	controller.pitch -= mouse_delta.y * controller.sensitivity - look_delta.y;
 	controller.pitch = controller.pitch.clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
 
 	// Update camera rotation
@@ -89,32 +93,14 @@ pub fn camera_controller(
 	let pitch_quat = Quat::from_axis_angle(Vec3::X, controller.pitch);
 	transform.rotation = yaw_quat * pitch_quat;
 
-	// Free-fly movement
-	let mut movement = Vec3::ZERO;
-	let forward = transform.forward();
-	let right = transform.right();
-
-	if keyboard_input.pressed(KeyCode::KeyW) {
-		movement += *forward;
-	}
-	if keyboard_input.pressed(KeyCode::KeyS) {
-		movement -= *forward;
-	}
-	if keyboard_input.pressed(KeyCode::KeyA) {
-		movement -= *right;
-	}
-	if keyboard_input.pressed(KeyCode::KeyD) {
-		movement += *right;
-	}
-	if keyboard_input.pressed(KeyCode::Space) {
-		movement += Vec3::Y;
-	}
-	if keyboard_input.pressed(KeyCode::ShiftLeft) {
-		movement -= Vec3::Y;
-	}
+	// Free-fly movement, keeping analog stick magnitude
+	let axis = actions.move_axis();
+	let movement = *transform.forward() * axis.y
+		+ *transform.right() * axis.x
+		+ Vec3::Y * actions.vertical_axis();
 
 	if movement.length() > 0.0 {
-		movement = movement.normalize() * controller.speed * time.delta_secs();
+		let movement = movement.clamp_length_max(1.0) * controller.speed * time.delta_secs();
 		transform.translation += movement;
 	}
 }
@@ -0,0 +1,42 @@
+use crate::CameraController;
+use bevy::prelude::*;
+use engine::{Actions, InputAction, WorldSave};
+use vegetation_sdf::tree::chop::{ChopTree, TreeChopped, TreeTrunk};
+use vegetation_sdf::tree::meshes::trunk::segment::SimpleTrunkSegment;
+
+/// How far from the camera a tree can be chopped.
+const CHOP_REACH: f32 = 24.0;
+
+/// Chops down the tree nearest the camera, felling it away from the viewer.
+pub fn chop_nearest_tree<T: Material>(
+	actions: Actions,
+	camera: Query<&Transform, With<CameraController>>,
+	trees: Query<(Entity, &TreeTrunk<SimpleTrunkSegment, T>)>,
+	mut chop: MessageWriter<ChopTree>,
+) {
+	if !actions.just_pressed(InputAction::Interact) {
+		return;
+	}
+	let Ok(camera) = camera.single() else {
+		return;
+	};
+
+	let nearest = trees
+		.iter()
+		.map(|(entity, trunk)| (entity, trunk.base, trunk.base.distance(camera.translation)))
+		.filter(|(_, _, distance)| *distance <= CHOP_REACH)
+		.min_by(|a, b| a.2.total_cmp(&b.2));
+	match nearest {
+		Some((tree, base, _)) => {
+			chop.write(ChopTree { tree, direction: base - camera.translation });
+		}
+		None => log::info!("No tree within {CHOP_REACH} of the camera"),
+	}
+}
+
+/// Records chopped trees in the world save, so they stay down when the world is loaded.
+pub fn record_chopped_trees(mut chopped: MessageReader<TreeChopped>, mut save: ResMut<WorldSave>) {
+	for chopped in chopped.read() {
+		save.record_destroyed(chopped.base);
+	}
+}
@@ -0,0 +1,246 @@
+//! Offscreen rendering harness for the playground shaders.
+//!
+//! A headless app renders a reference scene with the edge, leaf and checkerboard materials
+//! from a fixed camera into an offscreen image. The render tests need a GPU adapter, so they are
+//! ignored by default: `cargo test -p objects-playground -- --ignored`.
+//!
+//! No golden images are committed yet, so the rendered scene is only checked for being drawn.
+//! Render them with `WCTP_BLESS_GOLDEN=1` on a machine with a GPU adapter and commit `golden/`
+//! to compare against them with a tolerance. Until then the comparison fails on the missing image.
+
+use crate::checkerboard_material::CheckerboardMaterial;
+use bevy::{
+	asset::RenderAssetUsages,
+	camera::RenderTarget,
+	image::{CompressedImageFormats, ImageSampler, ImageType},
+	prelude::*,
+	render::{
+		render_resource::TextureFormat,
+		view::screenshot::{Screenshot, ScreenshotCaptured},
+	},
+	window::ExitCondition,
+};
+use engine::shaders::{leaf_material::LeafMaterial, outline::EdgeMaterial};
+use engine::{LightingPreset, StandardLightingPlugin};
+use std::path::PathBuf;
+
+const SIZE: u32 = 256;
+/// Frames to render before capturing, so shaders and pipelines are ready
+const WARMUP_FRAMES: usize = 60;
+/// Frames to wait for the capture to come back from the GPU
+const CAPTURE_FRAMES: usize = 60;
+/// Per-channel difference allowed before a pixel counts as changed
+const CHANNEL_TOLERANCE: u8 = 8;
+/// Fraction of changed pixels allowed before the test fails
+const MAX_CHANGED_FRACTION: f32 = 0.01;
+
+#[derive(Resource)]
+struct RenderTargetImage(Handle<Image>);
+
+#[derive(Resource, Default)]
+struct CapturedFrame(Option<Image>);
+
+fn golden_path(name: &str) -> PathBuf {
+	PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+		.join("golden")
+		.join(format!("{name}.png"))
+}
+
+/// Fraction of pixels whose color differs from the golden image by more than the tolerance.
+///
+/// Both images are tightly packed RGBA8; alpha is ignored.
+fn changed_fraction(actual: &[u8], expected: &[u8], tolerance: u8) -> Result<f32, String> {
+	if actual.len() != expected.len() || actual.len() % 4 != 0 {
+		return Err(format!(
+			"Image sizes differ: {} bytes rendered, {} bytes expected",
+			actual.len(),
+			expected.len()
+		));
+	}
+	if actual.is_empty() {
+		return Ok(0.0);
+	}
+
+	let changed = actual
+		.chunks_exact(4)
+		.zip(expected.chunks_exact(4))
+		.filter(|(a, e)| a[..3].iter().zip(&e[..3]).any(|(a, e)| a.abs_diff(*e) > tolerance))
+		.count();
+	Ok(changed as f32 / (actual.len() / 4) as f32)
+}
+
+fn setup_reference_scene(
+	mut commands: Commands,
+	mut images: ResMut<Assets<Image>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut edge_materials: ResMut<Assets<EdgeMaterial>>,
+	mut leaf_materials: ResMut<Assets<LeafMaterial>>,
+	mut checkerboard_materials: ResMut<Assets<CheckerboardMaterial>>,
+) {
+	let target = images.add(Image::new_target_texture(SIZE, SIZE, TextureFormat::Rgba8UnormSrgb));
+	commands.insert_resource(RenderTargetImage(target.clone()));
+
+	commands.spawn((
+		Camera3d::default(),
+		Camera { target: RenderTarget::Image(target.into()), ..default() },
+		Transform::from_xyz(0.0, 3.0, 6.0).looking_at(Vec3::new(0.0, 0.5, 0.0), Vec3::Y),
+	));
+
+	commands.spawn((
+		Mesh3d(meshes.add(Plane3d::default().mesh().size(8.0, 8.0))),
+		MeshMaterial3d(checkerboard_materials.add(CheckerboardMaterial {
+			checker_size_m: 1.0,
+			color1: Color::srgb(0.9, 0.9, 0.9).into(),
+			color2: Color::srgb(0.7, 0.7, 0.7).into(),
+		})),
+	));
+
+	commands.spawn((
+		Mesh3d(meshes.add(Sphere::new(0.75))),
+		MeshMaterial3d(edge_materials.add(EdgeMaterial::new(Vec4::new(0.89, 0.886, 0.604, 1.0)))),
+		Transform::from_xyz(-1.2, 0.75, 0.0),
+	));
+
+	commands.spawn((
+		Mesh3d(meshes.add(Sphere::new(0.75))),
+		MeshMaterial3d(leaf_materials.add(LeafMaterial::new(Vec4::new(0.2, 0.8, 0.3, 1.0)))),
+		Transform::from_xyz(1.2, 0.75, 0.0),
+	));
+}
+
+/// Renders the reference scene headlessly and returns the captured frame.
+fn render_reference_scene() -> Result<Image, String> {
+	let mut app = App::new();
+	app.add_plugins(
+		DefaultPlugins
+			.set(WindowPlugin {
+				primary_window: None,
+				exit_condition: ExitCondition::DontExit,
+				..default()
+			})
+			.set(AssetPlugin {
+				file_path: format!("{}/assets", env!("CARGO_MANIFEST_DIR")),
+				..default()
+			})
+			.disable::<bevy::winit::WinitPlugin>(),
+	)
+	.add_plugins(bevy::pbr::MaterialPlugin::<EdgeMaterial>::default())
+	.add_plugins(bevy::pbr::MaterialPlugin::<LeafMaterial>::default())
+	.add_plugins(bevy::pbr::MaterialPlugin::<CheckerboardMaterial>::default())
+	.add_plugins(StandardLightingPlugin::new(LightingPreset::Noon))
+	.insert_resource(ClearColor(Color::hsla(201.0, 0.69, 0.62, 1.0)))
+	.init_resource::<CapturedFrame>()
+	.add_systems(Startup, setup_reference_scene);
+
+	app.finish();
+	app.cleanup();
+
+	for _ in 0..WARMUP_FRAMES {
+		app.update();
+	}
+
+	let target = app.world().resource::<RenderTargetImage>().0.clone();
+	app.world_mut().spawn(Screenshot::image(target)).observe(
+		|captured: On<ScreenshotCaptured>, mut frame: ResMut<CapturedFrame>| {
+			frame.0 = Some(captured.image.clone());
+		},
+	);
+
+	for _ in 0..CAPTURE_FRAMES {
+		app.update();
+		if let Some(image) = app.world_mut().resource_mut::<CapturedFrame>().0.take() {
+			return Ok(image);
+		}
+	}
+	Err(format!("No frame was captured after {} frames", WARMUP_FRAMES + CAPTURE_FRAMES))
+}
+
+fn to_rgba8(image: Image) -> Result<Vec<u8>, String> {
+	image
+		.try_into_dynamic()
+		.map(|image| image.to_rgba8().into_raw())
+		.map_err(|e| e.to_string())
+}
+
+/// Whether the golden images are being blessed, with `WCTP_BLESS_GOLDEN=1`.
+fn blessing() -> bool {
+	std::env::var_os("WCTP_BLESS_GOLDEN").is_some_and(|bless| bless == "1")
+}
+
+/// Compares a rendered image against its golden image, writing the golden image when blessing.
+fn assert_matches_golden(name: &str, rendered: Image) -> Result<(), String> {
+	let path = golden_path(name);
+	let rendered = rendered.try_into_dynamic().map_err(|e| e.to_string())?;
+
+	if blessing() {
+		std::fs::create_dir_all(path.parent().unwrap_or(&path)).map_err(|e| e.to_string())?;
+		rendered.to_rgb8().save(&path).map_err(|e| e.to_string())?;
+		log::warn!("Wrote golden image {}", path.display());
+		return Ok(());
+	}
+	if !path.exists() {
+		return Err(format!(
+			"Missing golden image {}, render it with WCTP_BLESS_GOLDEN=1 and commit it",
+			path.display()
+		));
+	}
+
+	let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+	let golden = Image::from_buffer(
+		&bytes,
+		ImageType::Extension("png"),
+		CompressedImageFormats::NONE,
+		true,
+		ImageSampler::Default,
+		RenderAssetUsages::default(),
+	)
+	.map_err(|e| e.to_string())?;
+
+	let fraction =
+		changed_fraction(&rendered.to_rgba8().into_raw(), &to_rgba8(golden)?, CHANNEL_TOLERANCE)?;
+	if fraction > MAX_CHANGED_FRACTION {
+		return Err(format!(
+			"{name} differs from {} in {:.2}% of pixels (allowed {:.2}%)",
+			path.display(),
+			fraction * 100.0,
+			MAX_CHANGED_FRACTION * 100.0
+		));
+	}
+	Ok(())
+}
+
+#[test]
+fn test_changed_fraction() {
+	let expected = [10, 10, 10, 255, 200, 200, 200, 255];
+
+	// alpha and small differences are ignored
+	let actual = [12, 8, 10, 0, 200, 205, 200, 255];
+	assert_eq!(changed_fraction(&actual, &expected, 8), Ok(0.0));
+
+	let actual = [10, 10, 10, 255, 100, 200, 200, 255];
+	assert_eq!(changed_fraction(&actual, &expected, 8), Ok(0.5));
+
+	assert!(changed_fraction(&actual[..4], &expected, 8).is_err());
+}
+
+#[test]
+#[ignore = "requires a GPU adapter"]
+fn test_reference_scene_renders() -> Result<(), String> {
+	let rendered = render_reference_scene()?;
+	assert_eq!((rendered.width(), rendered.height()), (SIZE, SIZE));
+
+	// Something other than the sky is drawn
+	let pixels = to_rgba8(rendered)?;
+	let sky = &pixels[..4];
+	assert!(pixels
+		.chunks_exact(4)
+		.any(|pixel| changed_fraction(pixel, sky, CHANNEL_TOLERANCE) == Ok(1.0)));
+	Ok(())
+}
+
+#[test]
+#[ignore = "requires a GPU adapter and the golden images, which are not committed yet"]
+fn test_reference_scene_matches_golden() -> Result<(), String> {
+	let rendered = render_reference_scene()?;
+	assert_matches_golden("reference_scene", rendered)
+}
@@ -1,24 +1,35 @@
+use bevy::camera::visibility::RenderLayers;
+use bevy::ecs::schedule::{InternedScheduleLabel, ScheduleLabel};
 use bevy::prelude::*;
-use std::f32::consts::PI;
 
 pub mod buildings_playground;
 mod camera;
 mod checkerboard_material;
+mod chop;
+#[cfg(test)]
+mod golden;
 mod ground;
 pub mod tree;
 mod ui;
 
-use buildings::complex::render::ComplexRenderer;
+use buildings::complex::{render::ComplexRenderer, ruin::Ruin};
 use buildings::meshes::walls::wall::{Wall, WallMesh};
 use engine::shaders::{leaf_material::LeafMaterial, outline::EdgeMaterial};
+use engine::{BarkLibrary, InputMap, StandardLightingPlugin, WorldPalettePlugin, WorldSave};
 use render_item::{
-	mesh::{fetch_meshes, handle::MeshHandle},
+	mesh::{fetch_meshes, handle::MeshHandle, SHADOW_PROXY_LAYER},
 	render_items,
 };
 use vegetation_sdf::{
+	forest::{CanopyCarpet, Forest},
 	grove::Grove,
 	tree::{
-		meshes::canopy::ball::NoisyBall, meshes::trunk::segment::SimpleTrunkSegment, TreeRenderItem,
+		chop::{chop_trees, ChopTree, FallenLog, TreeChopped},
+		meshes::canopy::ball::NoisyBall,
+		meshes::canopy::proxy::CanopyProxy,
+		meshes::trunk::segment::SimpleTrunkSegment,
+		skeleton::{draw_tree_skeleton, SkeletonGizmos},
+		TreeRenderItem,
 	},
 };
 
@@ -26,6 +37,8 @@ pub use camera::CameraController;
 
 pub use sdf;
 
+/// The objects playground on its own, with the materials, lighting and palette it shares with
+/// the other playgrounds.
 pub struct ObjectsPlugin {
 	pub seed: u32,
 }
@@ -34,19 +47,69 @@ impl Plugin for ObjectsPlugin {
 	fn build(&self, app: &mut App) {
 		// Register EdgeMaterial plugin
 		app.add_plugins(bevy::pbr::MaterialPlugin::<EdgeMaterial>::default());
+		// The sun also sees the canopy shadow proxies of the forest
+		app.add_plugins(
+			StandardLightingPlugin::default()
+				.with_cycle_key(KeyCode::KeyL)
+				.with_sun_layers(RenderLayers::from_layers(&[0, SHADOW_PROXY_LAYER])),
+		);
+		app.add_plugins(WorldPalettePlugin::default().with_cycle_key(KeyCode::KeyP));
 		app.add_plugins(bevy::pbr::MaterialPlugin::<LeafMaterial>::default());
+		app.add_plugins(ObjectsScenePlugin::new(self.seed));
+	}
+}
+
+/// The systems of the objects scene, for a launcher to run only while the scene is shown.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ObjectsSystems;
+
+/// The objects scene without the plugins it shares with the other playgrounds, which are
+/// registered once by whoever adds it.
+///
+/// The scene is set up in the setup schedule, Startup unless a launcher sets it up on entering
+/// a state, and its systems run in [ObjectsSystems]. The teardown schedule, if there is one,
+/// drops the tree and building materials so the trees and buildings are placed again the next
+/// time the scene is set up.
+pub struct ObjectsScenePlugin {
+	pub seed: u32,
+	pub setup: InternedScheduleLabel,
+	pub teardown: Option<InternedScheduleLabel>,
+}
+
+impl ObjectsScenePlugin {
+	pub fn new(seed: u32) -> Self {
+		Self { seed, setup: Startup.intern(), teardown: None }
+	}
+
+	pub fn with_setup(mut self, setup: impl ScheduleLabel) -> Self {
+		self.setup = setup.intern();
+		self
+	}
+
+	pub fn with_teardown(mut self, teardown: impl ScheduleLabel) -> Self {
+		self.teardown = Some(teardown.intern());
+		self
+	}
+}
+
+impl Plugin for ObjectsScenePlugin {
+	fn build(&self, app: &mut App) {
 		// Register CheckerboardMaterial plugin
 		app.add_plugins(
 			bevy::pbr::MaterialPlugin::<checkerboard_material::CheckerboardMaterial>::default(),
 		);
 
-		app.insert_resource(ClearColor(Color::hsla(201.0, 0.69, 0.62, 1.0)))
+		app.init_resource::<InputMap>()
+			.init_resource::<SkeletonGizmos>()
+			.init_resource::<BarkLibrary>()
+			.init_resource::<WorldSave>()
+			.add_message::<ChopTree>()
+			.add_message::<TreeChopped>()
 			.insert_resource(ground::CheckerSize::default())
 			.add_systems(
-				Startup,
+				self.setup,
 				(
 					camera::setup_camera,
-					setup_lighting,
 					ground::setup_ground,
 					ui::setup_debug_ui,
 					tree::setup_tree_edge_material,
@@ -61,64 +124,43 @@ impl Plugin for ObjectsPlugin {
 					ui::update_coordinate_display,
 					render_items::<TreeRenderItem<EdgeMaterial, LeafMaterial>>,
 					render_items::<Grove<EdgeMaterial, LeafMaterial>>,
+					render_items::<Forest<EdgeMaterial, LeafMaterial>>,
 					fetch_meshes::<MeshHandle<SimpleTrunkSegment>, EdgeMaterial>,
 					fetch_meshes::<MeshHandle<NoisyBall>, LeafMaterial>,
+					fetch_meshes::<MeshHandle<CanopyCarpet>, LeafMaterial>,
+					fetch_meshes::<MeshHandle<CanopyProxy>, EdgeMaterial>,
 					tree::tree_playground::<EdgeMaterial, LeafMaterial>
-						.run_if(resource_exists::<tree::TreeMaterial<EdgeMaterial>>)
-						.run_if(run_once),
+						.run_if(resource_added::<tree::TreeMaterial<EdgeMaterial>>),
+					tree::forest_playground::<EdgeMaterial, LeafMaterial>
+						.run_if(resource_added::<tree::TreeMaterial<EdgeMaterial>>),
+					(
+						chop::chop_nearest_tree::<EdgeMaterial>,
+						chop_trees::<SimpleTrunkSegment, EdgeMaterial>,
+						render_items::<FallenLog<SimpleTrunkSegment, EdgeMaterial>>,
+						chop::record_chopped_trees,
+					)
+						.chain(),
+					(tree::toggle_skeleton_gizmos, draw_tree_skeleton).chain(),
 					render_items::<ComplexRenderer<Wall<EdgeMaterial>, Wall<EdgeMaterial>>>,
+					render_items::<Ruin<Wall<EdgeMaterial>, Wall<EdgeMaterial>, Wall<EdgeMaterial>>>,
 					fetch_meshes::<MeshHandle<WallMesh>, EdgeMaterial>,
-					buildings_playground::building_playground::<EdgeMaterial, EdgeMaterial>
-						.run_if(
-							resource_exists::<buildings_playground::BuildingMaterial<EdgeMaterial>>,
-						)
-						.run_if(run_once),
-				),
+					buildings_playground::building_playground::<EdgeMaterial, EdgeMaterial>.run_if(
+						resource_added::<buildings_playground::BuildingMaterial<EdgeMaterial>>,
+					),
+				)
+					.in_set(ObjectsSystems),
 			);
+
+		if let Some(teardown) = self.teardown {
+			app.add_systems(teardown, reset_objects_scene);
+		}
 	}
 }
 
-fn setup_lighting(mut commands: Commands) {
-	// Ambient light - significantly increased to simulate global illumination
-	// This provides base lighting for all surfaces, including back faces
-	commands.insert_resource(AmbientLight {
-		color: Color::WHITE,
-		brightness: 2.0, // Much higher for better back-face illumination (simulates bounced light)
-		affects_lightmapped_meshes: true,
-	});
-
-	// Main directional light (sun) - primary light source
-	commands.spawn((
-		DirectionalLight { illuminance: 10000.0, shadows_enabled: true, ..default() },
-		Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -PI / 4.0, PI / 4.0, 0.0)),
-	));
-
-	// Fill light from opposite direction - reduces harsh shadows
-	commands.spawn((
-		DirectionalLight {
-			illuminance: 500.0,     // Increased fill light
-			shadows_enabled: false, // No shadows for fill light
-			..default()
-		},
-		Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, PI / 4.0, -PI / 4.0, 0.0)),
-	));
-
-	// Additional fill lights from sides for omnidirectional illumination
-	// Left side
-	commands.spawn((
-		DirectionalLight { illuminance: 500.0, shadows_enabled: false, ..default() },
-		Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, PI / 2.0, 0.0)),
-	));
-
-	// Right side
-	commands.spawn((
-		DirectionalLight { illuminance: 500.0, shadows_enabled: false, ..default() },
-		Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, -PI / 2.0, 0.0)),
-	));
-
-	// Top-down fill light
-	commands.spawn((
-		DirectionalLight { illuminance: 500.0, shadows_enabled: false, ..default() },
-		Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, 0.0)),
-	));
+/// Drops the materials the scene set up, so the playgrounds that wait for them are placed again
+/// when the scene is set up again.
+fn reset_objects_scene(mut commands: Commands) {
+	commands.remove_resource::<tree::TreeMaterial<EdgeMaterial>>();
+	commands.remove_resource::<tree::TreeMaterial<LeafMaterial>>();
+	commands.remove_resource::<buildings_playground::BuildingMaterial<EdgeMaterial>>();
 }
@@ -1,11 +1,19 @@
 use bevy::prelude::*;
 use chunk::cascade::CascadeChunk;
-use engine::shaders::{leaf_material::LeafMaterial, outline::EdgeMaterial};
+use engine::shaders::{
+	leaf_material::LeafMaterial,
+	outline::{Coverage, EdgeMaterial},
+};
+use engine::{
+	Actions, BarkLibrary, BarkTemplate, InputAction, PaletteMaterials, PaletteRole, WorldPalette,
+};
 use render_item::{mesh::cache::handle::map::HandleMap, DispatchRenderItem};
 use vegetation_sdf::{
+	forest::{Forest, ForestLod},
 	grove::GroveBuilder,
 	tree::{
 		meshes::{canopy::ball::NoisyBall, trunk::segment::SimpleTrunkSegment},
+		skeleton::SkeletonGizmos,
 		TreeRenderItem,
 	},
 };
@@ -13,19 +21,37 @@ use vegetation_sdf::{
 #[derive(Resource, Clone)]
 pub struct TreeMaterial<M: Material>(pub Handle<M>);
 
+/// Toggles the branch graph overlay of the tree in view.
+pub fn toggle_skeleton_gizmos(actions: Actions, mut skeleton: ResMut<SkeletonGizmos>) {
+	if actions.just_pressed(InputAction::ToggleSkeletonGizmos) {
+		skeleton.enabled = !skeleton.enabled;
+		log::info!(
+			"Tree skeleton overlay {}",
+			if skeleton.enabled { "enabled" } else { "disabled" }
+		);
+	}
+}
+
 pub fn setup_tree_edge_material(
 	mut commands: Commands,
 	mut materials: ResMut<Assets<EdgeMaterial>>,
 	mut leaf_materials: ResMut<Assets<LeafMaterial>>,
+	palette: Res<WorldPalette>,
+	mut palette_materials: ResMut<PaletteMaterials>,
+	mut images: ResMut<Assets<Image>>,
+	mut bark_library: ResMut<BarkLibrary>,
 ) {
-	let material_handle = materials.add(EdgeMaterial {
-		// brownish color
-		base_color: Vec4::new(0.89, 0.886, 0.604, 1.0),
-	});
+	let bark = bark_library.textures(&BarkTemplate::oak(), &mut images);
+	let material_handle = materials.add(
+		EdgeMaterial::new(palette.base_color(PaletteRole::Bark))
+			.with_coverage(Coverage::moss())
+			.with_bark(&bark),
+	);
+	palette_materials.track_edge(material_handle.clone(), PaletteRole::Bark);
 
-	// green color
 	let leaf_material_handle =
-		leaf_materials.add(LeafMaterial { base_color: Vec4::new(0.2, 0.8, 0.3, 1.0) });
+		leaf_materials.add(LeafMaterial::new(palette.base_color(PaletteRole::Leaf)));
+	palette_materials.track_leaf(leaf_material_handle.clone(), PaletteRole::Leaf);
 
 	commands.insert_resource(TreeMaterial(material_handle));
 	commands.insert_resource(TreeMaterial(leaf_material_handle));
@@ -56,6 +82,40 @@ pub fn tree_playground<T: Material, L: Material>(
 	));
 }
 
+pub fn forest_playground<T: Material, L: Material>(
+	mut commands: Commands,
+	trunk_material: Res<TreeMaterial<T>>,
+	leaf_material: Res<TreeMaterial<L>>,
+) {
+	log::info!("Spawning forest playground");
+
+	let grove_builder = GroveBuilder::new(
+		MeshMaterial3d(trunk_material.0.clone()),
+		MeshMaterial3d(leaf_material.0.clone()),
+	)
+	.with_tree_cache(HandleMap::<SimpleTrunkSegment>::new())
+	.with_leaf_cache(HandleMap::<NoisyBall>::new())
+	.with_shadow_proxies(HandleMap::new());
+	let forest = Forest::new(grove_builder, MeshMaterial3d(leaf_material.0.clone()));
+
+	// trees in the middle chunks, canopy in the ring around them
+	const N: i32 = 3;
+	const CHUNK_SIZE: f32 = 32.0;
+	let center = Vec3::new(-160.0, 0.0, 160.0);
+	for x in -N..=N {
+		for z in -N..=N {
+			let lod =
+				if x.abs() <= 1 && z.abs() <= 1 { ForestLod::Trees } else { ForestLod::Canopy };
+			let origin = center + Vec3::new(x as f32 - 0.5, -0.5, z as f32 - 0.5) * CHUNK_SIZE;
+			commands.spawn((
+				CascadeChunk::cube(origin, CHUNK_SIZE, 3),
+				DispatchRenderItem::new(forest.clone().with_lod(lod)),
+				Transform::default(),
+			));
+		}
+	}
+}
+
 pub fn square_tree_playground<T: Material, L: Material>(
 	mut commands: Commands,
 	trunk_material: Res<TreeMaterial<T>>,
@@ -0,0 +1,102 @@
+//---------------------------------------------------------
+// Projected decal shader
+//
+// Decal meshes are clipped from chunk meshes with UVs spanning
+// the decal box. The pattern shapes the alpha, and the decal
+// fades out with distance from the camera.
+//---------------------------------------------------------
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings::view,
+    pbr_types::{PbrInput, pbr_input_new},
+    pbr_functions as fns,
+}
+#import bevy_core_pipeline::tonemapping::tone_mapping
+
+
+//---------------------------------------------------------
+// Material uniform
+//---------------------------------------------------------
+struct DecalSettings {
+    color: vec4<f32>,
+    pattern: u32,
+    fade_start: f32,
+    fade_end: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0)
+var<uniform> settings: DecalSettings;
+
+
+//---------------------------------------------------------
+// Pattern utilities
+//---------------------------------------------------------
+fn hash21(p: vec2<f32>) -> f32 {
+    let p3 = fract(vec3<f32>(p.xyx) * 0.1031);
+    let q = p3 + dot(p3, p3.yzx + 33.33);
+    return fract((q.x + q.y) * q.z);
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    var f = fract(p);
+    f = f * f * (3.0 - 2.0 * f);
+    let a = hash21(i);
+    let b = hash21(i + vec2<f32>(1.0, 0.0));
+    let c = hash21(i + vec2<f32>(0.0, 1.0));
+    let d = hash21(i + vec2<f32>(1.0, 1.0));
+    return mix(mix(a, b, f.x), mix(c, d, f.x), f.y);
+}
+
+fn pattern_alpha(uv: vec2<f32>) -> f32 {
+    // soft edges towards the sides of the decal box
+    let centered = abs(uv - 0.5) * 2.0;
+    let edges = 1.0 - smoothstep(0.7, 1.0, max(centered.x, centered.y));
+
+    if settings.pattern == 1u {
+        // twin tracks running along v
+        let track = min(abs(uv.x - 0.3), abs(uv.x - 0.7));
+        let rut = 1.0 - smoothstep(0.04, 0.09, track);
+        return rut * edges * (0.7 + 0.3 * value_noise(uv * vec2<f32>(8.0, 40.0)));
+    }
+    if settings.pattern == 2u {
+        // scattered speckles, thinning out towards the edges
+        let speckle = step(0.55, value_noise(uv * 24.0));
+        let blotch = 1.0 - smoothstep(0.3, 1.0, length(centered));
+        return speckle * max(blotch, 0.0) * edges;
+    }
+    // a soft blotch with a noisy rim
+    let rim = length(centered) + (value_noise(uv * 6.0) - 0.5) * 0.4;
+    return (1.0 - smoothstep(0.5, 1.0, rim)) * edges;
+}
+
+
+//---------------------------------------------------------
+// Fragment Shader
+//---------------------------------------------------------
+@fragment
+fn fragment(
+    mesh: VertexOutput
+) -> @location(0) vec4<f32> {
+    var pbr_input: PbrInput = pbr_input_new();
+    pbr_input.material.base_color = settings.color;
+    pbr_input.frag_coord = mesh.position;
+    pbr_input.world_position = mesh.world_position;
+    pbr_input.world_normal = normalize(mesh.world_normal);
+    pbr_input.is_orthographic = view.clip_from_view[3].w == 1.0;
+    pbr_input.N = pbr_input.world_normal;
+    pbr_input.V = fns::calculate_view(mesh.world_position, pbr_input.is_orthographic);
+    let lit_color = fns::apply_pbr_lighting(pbr_input);
+
+    let distance = length(view.world_position - mesh.world_position.xyz);
+    let fade = 1.0 - smoothstep(settings.fade_start, settings.fade_end, distance);
+#ifdef VERTEX_UVS_A
+    let uv = mesh.uv;
+#else
+    let uv = vec2<f32>(0.5);
+#endif
+    let alpha = pattern_alpha(uv) * settings.color.a * fade;
+
+    let output = tone_mapping(vec4<f32>(lit_color.rgb, 1.0), view.color_grading);
+    return vec4<f32>(output.rgb, alpha);
+}
@@ -3,12 +3,16 @@
 //---------------------------------------------------------
 #import bevy_pbr::{
     forward_io::VertexOutput,
+    mesh_functions,
     mesh_view_bindings::view,
     pbr_types::{PbrInput, pbr_input_new, STANDARD_MATERIAL_FLAGS_DOUBLE_SIDED_BIT},
     pbr_functions as fns,
     pbr_bindings,
 }
 #import bevy_core_pipeline::tonemapping::tone_mapping
+#import "shaders/instance_variation.wgsl"::instance_variation
+#import "shaders/instance_variation.wgsl"::shift_hue
+#import "shaders/instance_variation.wgsl"::INSTANCE_HUE_SHIFT
 
 
 //---------------------------------------------------------
@@ -17,6 +21,72 @@
 @group(#{MATERIAL_BIND_GROUP}) @binding(0)
 var<uniform> base_color: vec4<f32>;
 
+// Snow or moss layered over upward facing surfaces
+struct Coverage {
+    color: vec4<f32>,
+    amount: f32,
+    threshold: f32,
+    softness: f32,
+    altitude_start: f32,
+    altitude_range: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(1)
+var<uniform> coverage: Coverage;
+
+// Share of fragments dithered away while a chunk crossfades; negative keeps only that share
+@group(#{MATERIAL_BIND_GROUP}) @binding(2)
+var<uniform> dissolve: f32;
+
+// Sediment bands painted over steep surfaces
+struct Strata {
+    colors: array<vec4<f32>, 4>,
+    layers: u32,
+    thickness: f32,
+    base_height: f32,
+    amount: f32,
+    steepness: f32,
+    softness: f32,
+    wobble: f32,
+    wobble_frequency: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(3)
+var<uniform> strata: Strata;
+
+// Tileable bark textures projected from the three axes, ridges running up world y
+struct Bark {
+    tile_size: f32,
+    strength: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(4)
+var<uniform> bark: Bark;
+@group(#{MATERIAL_BIND_GROUP}) @binding(5)
+var bark_normal_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(6)
+var bark_normal_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(7)
+var bark_roughness_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(8)
+var bark_roughness_sampler: sampler;
+
+// Light of distant terrain baked from its heightfield, covering a square of the world xz plane
+struct DistantShadow {
+    min: vec2<f32>,
+    size: f32,
+    fade_start: f32,
+    fade_range: f32,
+    strength: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(9)
+var<uniform> distant_shadow: DistantShadow;
+@group(#{MATERIAL_BIND_GROUP}) @binding(10)
+var distant_shadow_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(11)
+var distant_shadow_sampler: sampler;
+
 
 //---------------------------------------------------------
 // Edge utilities
@@ -26,6 +96,129 @@ fn fwidth3(v: vec3<f32>) -> vec3<f32> {
 }
 
 
+//---------------------------------------------------------
+// Dissolve utilities
+//---------------------------------------------------------
+// Interleaved gradient noise, stable per pixel so meshes fading in and out interlock
+fn dither(frag_coord: vec2<f32>) -> f32 {
+    return fract(52.9829189 * fract(dot(frag_coord, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+fn dissolved(frag_coord: vec2<f32>) -> bool {
+    let noise = dither(frag_coord);
+    return (dissolve > 0.0 && noise < dissolve) || (dissolve < 0.0 && noise >= 1.0 + dissolve);
+}
+
+
+//---------------------------------------------------------
+// Coverage utilities
+//---------------------------------------------------------
+fn coverage_weight(world_normal: vec3<f32>, world_height: f32) -> f32 {
+    let up = normalize(world_normal).y;
+    var slope = select(0.0, 1.0, up >= coverage.threshold);
+    if coverage.softness > 0.0 {
+        slope = smoothstep(coverage.threshold, coverage.threshold + coverage.softness, up);
+    }
+    var altitude = 1.0;
+    if coverage.altitude_range > 0.0 {
+        altitude = clamp((world_height - coverage.altitude_start) / coverage.altitude_range, 0.0, 1.0);
+    }
+    return clamp(slope * altitude * coverage.amount, 0.0, 1.0);
+}
+
+
+//---------------------------------------------------------
+// Strata utilities
+//---------------------------------------------------------
+fn lattice_hash(cell: vec2<f32>) -> f32 {
+    return fract(sin(dot(cell, vec2<f32>(127.1, 311.7))) * 43758.547);
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+    let cell = floor(p);
+    let f = p - cell;
+    let u = f * f * (3.0 - 2.0 * f);
+    let bottom = mix(lattice_hash(cell), lattice_hash(cell + vec2<f32>(1.0, 0.0)), u.x);
+    let top = mix(lattice_hash(cell + vec2<f32>(0.0, 1.0)), lattice_hash(cell + vec2<f32>(1.0, 1.0)), u.x);
+    return mix(bottom, top, u.y);
+}
+
+fn strata_weight(world_normal: vec3<f32>) -> f32 {
+    if strata.layers == 0u {
+        return 0.0;
+    }
+    let up = abs(normalize(world_normal).y);
+    var steep = select(0.0, 1.0, up <= strata.steepness);
+    if strata.softness > 0.0 {
+        steep = 1.0 - smoothstep(strata.steepness - strata.softness, strata.steepness, up);
+    }
+    return clamp(steep * strata.amount, 0.0, 1.0);
+}
+
+fn strata_color(world_position: vec3<f32>) -> vec4<f32> {
+    let wobble = (value_noise(world_position.xz * strata.wobble_frequency) * 2.0 - 1.0) * strata.wobble;
+    let band = floor((world_position.y + wobble - strata.base_height) / strata.thickness);
+    let layers = f32(max(strata.layers, 1u));
+    let layer = u32(band - floor(band / layers) * layers);
+    return strata.colors[min(layer, 3u)];
+}
+
+
+//---------------------------------------------------------
+// Bark utilities
+//---------------------------------------------------------
+// Weights of the three axis projections, favoring the axis the surface faces
+fn triplanar_weights(n: vec3<f32>) -> vec3<f32> {
+    let w = pow(abs(n), vec3<f32>(4.0));
+    return w / (w.x + w.y + w.z);
+}
+
+// The normal bent by the bark normal map. The projections are blended with the
+// whiteout blend, so each bends the normal about its own axis.
+fn bark_normal(world_position: vec3<f32>, n: vec3<f32>) -> vec3<f32> {
+    let p = world_position / bark.tile_size;
+    let w = triplanar_weights(n);
+    var tx = textureSample(bark_normal_texture, bark_normal_sampler, p.zy).xyz * 2.0 - 1.0;
+    var ty = textureSample(bark_normal_texture, bark_normal_sampler, p.xz).xyz * 2.0 - 1.0;
+    var tz = textureSample(bark_normal_texture, bark_normal_sampler, p.xy).xyz * 2.0 - 1.0;
+    tx = vec3<f32>(tx.xy * bark.strength + n.zy, abs(tx.z) * n.x);
+    ty = vec3<f32>(ty.xy * bark.strength + n.xz, abs(ty.z) * n.y);
+    tz = vec3<f32>(tz.xy * bark.strength + n.xy, abs(tz.z) * n.z);
+    return normalize(tx.zyx * w.x + ty.xzy * w.y + tz.xyz * w.z);
+}
+
+fn bark_roughness(world_position: vec3<f32>, n: vec3<f32>) -> f32 {
+    let p = world_position / bark.tile_size;
+    let w = triplanar_weights(n);
+    let rx = textureSample(bark_roughness_texture, bark_roughness_sampler, p.zy).r;
+    let ry = textureSample(bark_roughness_texture, bark_roughness_sampler, p.xz).r;
+    let rz = textureSample(bark_roughness_texture, bark_roughness_sampler, p.xy).r;
+    return rx * w.x + ry * w.y + rz * w.z;
+}
+
+
+//---------------------------------------------------------
+// Distant shadow utilities
+//---------------------------------------------------------
+// Share of the light kept at a world position by the baked shadows, fading them in with
+// distance from the camera. Matches DistantShadow::fade in outline.rs.
+fn distant_shadow_light(world_position: vec3<f32>) -> f32 {
+    let uv = (world_position.xz - distant_shadow.min) / distant_shadow.size;
+    // Sampled up front, reading white until a map is baked
+    let light = textureSampleLevel(distant_shadow_texture, distant_shadow_sampler, uv, 0.0).r;
+    if distant_shadow.strength <= 0.0 || any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+        return 1.0;
+    }
+    let distance = length(world_position - view.world_position);
+    var fade = select(0.0, 1.0, distance >= distant_shadow.fade_start);
+    if distant_shadow.fade_range > 0.0 {
+        let fade_end = distant_shadow.fade_start + distant_shadow.fade_range;
+        fade = smoothstep(distant_shadow.fade_start, fade_end, distance);
+    }
+    return 1.0 - (1.0 - light) * distant_shadow.strength * fade;
+}
+
+
 //---------------------------------------------------------
 // Fragment Shader
 //---------------------------------------------------------
@@ -35,13 +228,31 @@ fn fragment(
     mesh: VertexOutput
 ) -> @location(0) vec4<f32> {
 
+    if dissolved(mesh.position.xy) {
+        discard;
+    }
+
     //-----------------------------------------------------
     // 1. Build PBR input (same way StandardMaterial does)
     //-----------------------------------------------------
     var pbr_input: PbrInput = pbr_input_new();
 
-    // basic material
-    pbr_input.material.base_color = base_color;
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    let variation = instance_variation(mesh_functions::get_tag(mesh.instance_index));
+#else
+    let variation = instance_variation(0u);
+#endif
+
+    // basic material in the hue of the instance, banded with strata on steep surfaces and
+    // covered on upward facing ones
+    let tinted = vec4<f32>(
+        shift_hue(base_color.rgb, variation.x * INSTANCE_HUE_SHIFT),
+        base_color.a,
+    );
+    let banded = strata_weight(mesh.world_normal);
+    let rock = mix(tinted, strata_color(mesh.world_position.xyz), banded);
+    let covered = coverage_weight(mesh.world_normal, mesh.world_position.y);
+    pbr_input.material.base_color = mix(rock, coverage.color, covered);
 
     let double_sided = (pbr_input.material.flags & STANDARD_MATERIAL_FLAGS_DOUBLE_SIDED_BIT) != 0u;
 
@@ -57,6 +268,13 @@ fn fragment(
     pbr_input.N = normalize(pbr_input.world_normal);
     pbr_input.V = fns::calculate_view(mesh.world_position, pbr_input.is_orthographic);
 
+    // bark bends the lighting and roughens the furrows, leaving the edges to the mesh normals
+    if (bark.strength > 0.0) {
+        let surface = pbr_input.N;
+        pbr_input.N = bark_normal(mesh.world_position.xyz, surface);
+        pbr_input.material.perceptual_roughness = bark_roughness(mesh.world_position.xyz, surface);
+    }
+
 
     //-----------------------------------------------------
     // 2. Compute PBR lighting (includes shadows)
@@ -77,10 +295,19 @@ fn fragment(
     // invert: 1 → interior, 0 → edge
     let intensity = 1.0 - edge;
 
+    // baked sky visibility darkens caves and tunnels
+#ifdef VERTEX_COLORS
+    let sky_visibility = mesh.color.r;
+#else
+    let sky_visibility = 1.0;
+#endif
+
     //-----------------------------------------------------
     // 4. Mix: apply edges on top of PBR lighting
     //-----------------------------------------------------
-    let shaded = lit_color.rgb * intensity;
+    // baked shadows darken distant terrain past the real-time shadow cascades
+    let distant_light = distant_shadow_light(mesh.world_position.xyz);
+    let shaded = lit_color.rgb * intensity * sky_visibility * distant_light;
 
 
     //-----------------------------------------------------
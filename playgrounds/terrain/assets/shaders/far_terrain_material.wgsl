@@ -0,0 +1,112 @@
+//---------------------------------------------------------
+// Far terrain shader
+//
+// Drawn on an inside-out shell around the camera. Each
+// fragment marches its view ray from the near distance
+// through a heightfield of the distant world, shading the
+// ground it hits and discarding the rays that miss it.
+//---------------------------------------------------------
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings::view,
+}
+#import bevy_core_pipeline::tonemapping::tone_mapping
+
+
+//---------------------------------------------------------
+// Material uniforms
+//---------------------------------------------------------
+struct FarTerrainSettings {
+    color: vec4<f32>,
+    haze: vec4<f32>,
+    toward_sun: vec3<f32>,
+    resolution: u32,
+    min: vec2<f32>,
+    size: f32,
+    top: f32,
+    near: f32,
+    far: f32,
+    haze_distance: f32,
+    max_steps: u32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0)
+var<uniform> settings: FarTerrainSettings;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1)
+var heights: texture_2d<f32>;
+
+
+//---------------------------------------------------------
+// Heightfield
+//---------------------------------------------------------
+fn texel_height(texel: vec2<i32>) -> f32 {
+    let last = i32(settings.resolution) - 1;
+    return textureLoad(heights, clamp(texel, vec2<i32>(0), vec2<i32>(last)), 0).r;
+}
+
+// Heights between the texel centers, which R32Float can't filter
+fn height_at(xz: vec2<f32>) -> f32 {
+    let p = (xz - settings.min) / settings.size * f32(settings.resolution) - 0.5;
+    let cell = floor(p);
+    let f = p - cell;
+    let c = vec2<i32>(cell);
+    let bottom = mix(texel_height(c), texel_height(c + vec2<i32>(1, 0)), f.x);
+    let top = mix(texel_height(c + vec2<i32>(0, 1)), texel_height(c + vec2<i32>(1, 1)), f.x);
+    return mix(bottom, top, f.y);
+}
+
+fn on_heightfield(xz: vec2<f32>) -> bool {
+    let local = (xz - settings.min) / settings.size;
+    return all(local >= vec2<f32>(0.0)) && all(local <= vec2<f32>(1.0));
+}
+
+
+//---------------------------------------------------------
+// Fragment Shader
+//---------------------------------------------------------
+@fragment
+fn fragment(
+    mesh: VertexOutput
+) -> @location(0) vec4<f32> {
+    let origin = view.world_position;
+    let direction = normalize(mesh.world_position.xyz - origin);
+    let texel = settings.size / f32(settings.resolution);
+
+    // Steps shrink with the gap to the ground and grow with the distance
+    var t = settings.near;
+    var previous_t = t;
+    var previous_gap = 0.0;
+    var hit = false;
+    for (var i = 0u; i < settings.max_steps; i++) {
+        let p = origin + direction * t;
+        if t > settings.far || !on_heightfield(p.xz) || (p.y > settings.top && direction.y >= 0.0) {
+            break;
+        }
+        let gap = p.y - height_at(p.xz);
+        if gap < 0.0 {
+            // Between the last point above the ground and this one
+            if i > 0u {
+                t = previous_t + (t - previous_t) * previous_gap / (previous_gap - gap);
+            }
+            hit = true;
+            break;
+        }
+        previous_t = t;
+        previous_gap = gap;
+        t += max(gap * 0.5, max(texel * 0.5, t * 0.004));
+    }
+    if !hit {
+        discard;
+    }
+
+    let p = origin + direction * t;
+    let dx = height_at(p.xz + vec2<f32>(texel, 0.0)) - height_at(p.xz - vec2<f32>(texel, 0.0));
+    let dz = height_at(p.xz + vec2<f32>(0.0, texel)) - height_at(p.xz - vec2<f32>(0.0, texel));
+    let normal = normalize(vec3<f32>(-dx, 2.0 * texel, -dz));
+    let sun = max(dot(normal, normalize(settings.toward_sun)), 0.0);
+    let lit = settings.color.rgb * (0.35 + 0.65 * sun);
+
+    let haze = smoothstep(0.0, 1.0, (t - settings.near) / settings.haze_distance);
+    let color = mix(lit, settings.haze.rgb, haze);
+    return tone_mapping(vec4<f32>(color, 1.0), view.color_grading);
+}
@@ -0,0 +1,30 @@
+//---------------------------------------------------------
+// Instance Variation
+//
+// Shared by the edge and leaf materials, which import it by
+// its asset path. Holds no bindings.
+//---------------------------------------------------------
+// Matches INSTANCE_SCALE_VARIATION in render_item's mesh.rs
+const INSTANCE_SCALE_VARIATION: f32 = 0.08;
+// Largest hue shift of an instance, in radians around the grey axis
+const INSTANCE_HUE_SHIFT: f32 = 0.2;
+
+// Hue, scale and phase of an instance from the hash its mesh is tagged
+// with, matching InstanceVariation in render_item's mesh.rs. Untagged
+// meshes, such as terrain chunks, aren't varied.
+fn instance_variation(tag: u32) -> vec3<f32> {
+    if (tag == 0u) {
+        return vec3<f32>(0.0, 1.0, 0.0);
+    }
+    let hue = f32(tag & 0xffu) / 255.0 * 2.0 - 1.0;
+    let scale = 1.0 + (f32((tag >> 8u) & 0xffu) / 255.0 * 2.0 - 1.0) * INSTANCE_SCALE_VARIATION;
+    let phase = f32(tag >> 16u) / 65536.0;
+    return vec3<f32>(hue, scale, phase);
+}
+
+// Rotates a color around the grey axis, shifting its hue but keeping its brightness
+fn shift_hue(color: vec3<f32>, angle: f32) -> vec3<f32> {
+    let k = vec3<f32>(0.57735);
+    let c = cos(angle);
+    return color * c + cross(k, color) * sin(angle) + k * dot(k, color) * (1.0 - c);
+}
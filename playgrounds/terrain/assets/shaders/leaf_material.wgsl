@@ -0,0 +1,296 @@
+//---------------------------------------------------------
+// Stylized Leaf Material Shader
+// 
+// This shader creates a stylized, abstract leaf appearance
+// suitable for balls of intersecting planes. It uses UV-based
+// alpha shaping to create leaf silhouettes from simple planes.
+// Uses simple directional lighting with rim lighting.
+//---------------------------------------------------------
+
+#import bevy_pbr::{
+    forward_io::{Vertex, VertexOutput},
+    mesh_functions,
+    mesh_view_bindings::view,
+    view_transformations::position_world_to_clip,
+    pbr_types::{PbrInput, pbr_input_new, STANDARD_MATERIAL_FLAGS_DOUBLE_SIDED_BIT},
+    pbr_functions as fns,
+    pbr_bindings,
+}
+#import bevy_core_pipeline::tonemapping::tone_mapping
+#import "shaders/instance_variation.wgsl"::instance_variation
+#import "shaders/instance_variation.wgsl"::shift_hue
+#import "shaders/instance_variation.wgsl"::INSTANCE_HUE_SHIFT
+
+
+//---------------------------------------------------------
+// Material Uniforms
+//---------------------------------------------------------
+@group(#{MATERIAL_BIND_GROUP}) @binding(0)
+var<uniform> base_color: vec4<f32>;
+
+// Matches MAX_FOLIAGE_BENDERS in leaf_material.rs
+const MAX_BENDERS: u32 = 8u;
+
+struct FoliageBender {
+    position: vec3<f32>,
+    radius: f32,
+    strength: f32,
+}
+
+struct FoliageBending {
+    benders: array<FoliageBender, 8>,
+    count: u32,
+    bend: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(1)
+var<uniform> bending: FoliageBending;
+
+struct FoliageFade {
+    start: f32,
+    end: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(2)
+var<uniform> fade: FoliageFade;
+
+
+//---------------------------------------------------------
+// Vertex Shader
+//---------------------------------------------------------
+// Pushes foliage away from the benders. Vertices bend more the
+// higher they sit above the mesh origin, so stems stay rooted.
+fn bend_offset(world_position: vec3<f32>, root: vec3<f32>) -> vec3<f32> {
+    var offset = vec3<f32>(0.0);
+    for (var i = 0u; i < min(bending.count, MAX_BENDERS); i++) {
+        let bender = bending.benders[i];
+        let away = world_position.xz - bender.position.xz;
+        let reach = length(away);
+        if (reach >= bender.radius || bender.radius <= 0.0) {
+            continue;
+        }
+
+        let falloff = 1.0 - reach / bender.radius;
+        let height = clamp((world_position.y - root.y) / bender.radius, 0.0, 1.0);
+        let push = bending.bend * bender.radius * falloff * height * bender.strength;
+        let direction = select(vec2<f32>(1.0, 0.0), away / reach, reach > 0.0001);
+        // Bent foliage leans down as well as away
+        offset += vec3<f32>(direction.x * push, -0.5 * push, direction.y * push);
+    }
+    return offset;
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    let root = world_from_local[3].xyz;
+    // Instances are scaled about their origin, so their roots stay put
+    let variation = instance_variation(mesh_functions::get_tag(vertex.instance_index));
+    let unbent = mesh_functions::mesh_position_local_to_world(
+        world_from_local,
+        vec4<f32>(vertex.position * variation.y, 1.0),
+    );
+    out.world_position = vec4<f32>(unbent.xyz + bend_offset(unbent.xyz, root), unbent.w);
+    out.position = position_world_to_clip(out.world_position.xyz);
+
+#ifdef VERTEX_NORMALS
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(
+        vertex.normal,
+        vertex.instance_index,
+    );
+#endif
+
+#ifdef VERTEX_UVS_A
+    out.uv = vertex.uv;
+#endif
+
+#ifdef VERTEX_COLORS
+    out.color = vertex.color;
+#endif
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+
+    return out;
+}
+
+
+//---------------------------------------------------------
+// Perlin Noise
+//---------------------------------------------------------
+// Classic Perlin noise using gradient vectors at grid points
+fn hash22(p: vec2<f32>) -> vec2<f32> {
+    let p3 = fract(vec3<f32>(p.xyx) * vec3<f32>(0.1031, 0.1030, 0.0973));
+    let dot_val = dot(p3, p3 + 33.33);
+    let p3_xy = vec2<f32>(p3.x, p3.y);
+    let p3_yz = vec2<f32>(p3.y, p3.z);
+    return fract((p3_xy + p3_yz) * vec2<f32>(dot_val, dot_val * 1.618));
+}
+
+// Get gradient vector at grid point
+fn grad(p: vec2<f32>) -> vec2<f32> {
+    let h = hash22(p);
+    // Map to gradient vectors - use angle from hash
+    let angle = h.x * 6.28318; // 2 * PI
+    return vec2<f32>(cos(angle), sin(angle));
+}
+
+fn perlin_noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    var f = fract(p);
+    
+    // Smooth interpolation (smoothstep)
+    f = f * f * (3.0 - 2.0 * f);
+    
+    // Get gradients at the four corners
+    let g00 = grad(i);
+    let g10 = grad(i + vec2<f32>(1.0, 0.0));
+    let g01 = grad(i + vec2<f32>(0.0, 1.0));
+    let g11 = grad(i + vec2<f32>(1.0, 1.0));
+    
+    // Distance vectors from grid points
+    let d00 = f;
+    let d10 = f - vec2<f32>(1.0, 0.0);
+    let d01 = f - vec2<f32>(0.0, 1.0);
+    let d11 = f - vec2<f32>(1.0, 1.0);
+    
+    // Dot products
+    let n00 = dot(g00, d00);
+    let n10 = dot(g10, d10);
+    let n01 = dot(g01, d01);
+    let n11 = dot(g11, d11);
+    
+    // Bilinear interpolation
+    let nx0 = mix(n00, n10, f.x);
+    let nx1 = mix(n01, n11, f.x);
+    return mix(nx0, nx1, f.y);
+}
+
+// Fractal Perlin noise (multi-octave)
+fn fractal_noise(p: vec2<f32>) -> f32 {
+    var value = 0.0;
+    var amplitude = 0.5;
+    var frequency = 10.0;
+    
+    // Multiple octaves for fractal detail
+    for (var i = 0; i < 5; i++) {
+        value += perlin_noise(p * frequency) * amplitude;
+        frequency *= 2.0;
+        amplitude *= 0.5;
+    }
+    
+    // Perlin noise is in range [-1, 1], normalize to [0, 1]
+    return value * 0.5 + 0.5;
+}
+
+
+//---------------------------------------------------------
+// Distance Fade
+//---------------------------------------------------------
+// 4x4 ordered dither threshold of a pixel in (0, 1)
+fn bayer4(frag_coord: vec2<f32>) -> f32 {
+    let p = vec2<u32>(frag_coord) % 4u;
+    let matrix = array<f32, 16>(
+        0.0, 8.0, 2.0, 10.0,
+        12.0, 4.0, 14.0, 6.0,
+        3.0, 11.0, 1.0, 9.0,
+        15.0, 7.0, 13.0, 5.0,
+    );
+    return (matrix[p.y * 4u + p.x] + 0.5) / 16.0;
+}
+
+// Whether a fragment is dithered away by its distance from the
+// camera, fading foliage out over the fade span instead of popping
+fn faded_out(world_position: vec3<f32>, frag_coord: vec2<f32>) -> bool {
+    if (fade.end <= fade.start) {
+        return false;
+    }
+    let distance = length(world_position - view.world_position);
+    let t = clamp((distance - fade.start) / (fade.end - fade.start), 0.0, 1.0);
+    return t >= bayer4(frag_coord);
+}
+
+
+//---------------------------------------------------------
+// Fragment Shader
+//---------------------------------------------------------
+@fragment
+fn fragment(
+    @builtin(front_facing) is_front: bool,
+    mesh: VertexOutput
+) -> @location(0) vec4<f32> {
+
+    if (faded_out(mesh.world_position.xyz, mesh.position.xy)) {
+        discard;
+    }
+
+    //-----------------------------------------------------
+    // 1. Calculate leaf shape alpha from noise
+    //-----------------------------------------------------
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    let variation = instance_variation(mesh_functions::get_tag(mesh.instance_index));
+#else
+    let variation = instance_variation(0u);
+#endif
+
+    // Sample noise at UV coordinates, offset by the phase of the instance
+    // so instances of the same mesh have their own leaf shapes
+    let noise_scale = 6.0;
+    let noise_value = fractal_noise(mesh.uv * noise_scale + variation.z * 17.0);
+    
+    // Threshold: above = visible, below = transparent
+    let threshold = 0.5;
+    
+    // If noise is above threshold, visible, otherwise transparent
+    let alpha = step(threshold, noise_value);
+    
+    // Early exit optimization: if fully transparent, skip lighting
+    if (alpha < 0.001) {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    }
+
+
+    //-----------------------------------------------------
+    // 2. Build PBR input (same way StandardMaterial does)
+    //-----------------------------------------------------
+    var pbr_input: PbrInput = pbr_input_new();
+
+    // Set base color, with the hue of the instance
+    pbr_input.material.base_color = vec4<f32>(
+        shift_hue(base_color.rgb, variation.x * INSTANCE_HUE_SHIFT),
+        base_color.a,
+    );
+
+    let double_sided = (pbr_input.material.flags & STANDARD_MATERIAL_FLAGS_DOUBLE_SIDED_BIT) != 0u;
+
+    // Basic PBR required fields
+    pbr_input.frag_coord = mesh.position;
+    pbr_input.world_position = mesh.world_position;
+    pbr_input.world_normal = fns::prepare_world_normal(
+        mesh.world_normal,
+        double_sided,
+        is_front,
+    );
+    pbr_input.is_orthographic = view.clip_from_view[3].w == 1.0;
+    pbr_input.N = normalize(pbr_input.world_normal);
+    pbr_input.V = fns::calculate_view(mesh.world_position, pbr_input.is_orthographic);
+
+
+    //-----------------------------------------------------
+    // 3. Compute PBR lighting (includes shadows)
+    //-----------------------------------------------------
+    let lit_color = fns::apply_pbr_lighting(pbr_input);
+
+
+    //-----------------------------------------------------
+    // 4. Apply alpha and output
+    //-----------------------------------------------------
+    let output_color = vec4<f32>(lit_color.rgb, base_color.a * alpha);
+
+    // Apply tonemapping, color grading, exposure
+    return tone_mapping(output_color, view.color_grading);
+}
+
@@ -1,6 +1,6 @@
 use crate::terrain::TerrainSdf;
 use bevy::prelude::*;
-use engine::SdfResource;
+use engine::{Actions, FoliageActor, InputAction, ResolutionFocus, SdfResource, WorldConfined};
 use std::f32::consts::PI;
 
 #[derive(Component)]
@@ -35,30 +35,37 @@ pub fn setup_camera(mut commands: Commands) {
 			character_mode: false,
 			velocity: Vec3::ZERO,
 		},
+		WorldConfined,
+		// Bends the foliage within 2 m as the character walks through
+		FoliageActor::new(0.002),
 	));
 }
 
 pub fn camera_controller(
-	keyboard_input: Res<ButtonInput<KeyCode>>,
+	mut commands: Commands,
+	actions: Actions,
 	mut mouse_motion: MessageReader<bevy::input::mouse::MouseMotion>,
 	time: Res<Time>,
 	terrain_sdf: Res<SdfResource<TerrainSdf>>,
-	mut query: Query<(&mut Transform, &mut CameraController), With<Camera3d>>,
+	mut query: Query<(Entity, &mut Transform, &mut CameraController), With<Camera3d>>,
 ) {
-	let Ok((mut transform, mut controller)) = query.single_mut() else {
+	let Ok((entity, mut transform, mut controller)) = query.single_mut() else {
 		return;
 	};
 
-	// Toggle character mode with 'C' key
-	if keyboard_input.just_pressed(KeyCode::KeyC) {
+	// Toggle character mode
+	if actions.just_pressed(InputAction::ToggleCharacterMode) {
 		controller.character_mode = !controller.character_mode;
 		if controller.character_mode {
 			log::info!("Character mode enabled");
 			// When entering character mode, drop to terrain
 			controller.velocity = Vec3::ZERO;
+			// Sharpen the ground ahead of the character
+			commands.entity(entity).insert(ResolutionFocus::default());
 		} else {
 			log::info!("Character mode disabled");
 			controller.velocity = Vec3::ZERO;
+			commands.entity(entity).remove::<ResolutionFocus>();
 		}
 	}
 
@@ -68,8 +75,11 @@ pub fn camera_controller(
 		mouse_delta += event.delta;
 	}
 
-	controller.yaw -= mouse_delta.x * controller.sensitivity;
-	controller.pitch -= mouse_delta.y * controller.sensitivity;
+	// Gamepad look
+	let look_delta = actions.look_delta(time.delta_secs());
+
+	controller.yaw -= mouse_delta.x * controller.sensitivity + look_delta.x;
+	controller.pitch -= mouse_delta.y * controller.sensitivity - look_delta.y;
 	controller.pitch = controller.pitch.clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
 
 	// Update camera rotation
@@ -79,57 +89,33 @@ pub fn camera_controller(
 
 	if controller.character_mode {
 		// Character mode: gravity and terrain sticking
-		character_mode_movement(
-			&keyboard_input,
-			&time,
-			&terrain_sdf,
-			&mut transform,
-			&mut controller,
-		);
+		character_mode_movement(&actions, &time, &terrain_sdf, &mut transform, &mut controller);
 	} else {
 		// Free-fly mode: normal movement
-		free_fly_movement(&keyboard_input, &time, &mut transform, &mut controller);
+		free_fly_movement(&actions, &time, &mut transform, &mut controller);
 	}
 }
 
 fn free_fly_movement(
-	keyboard_input: &Res<ButtonInput<KeyCode>>,
+	actions: &Actions,
 	time: &Res<Time>,
 	transform: &mut Transform,
 	controller: &mut CameraController,
 ) {
-	// Handle movement
-	let mut movement = Vec3::ZERO;
-	let forward = transform.forward();
-	let right = transform.right();
-
-	if keyboard_input.pressed(KeyCode::KeyW) {
-		movement += *forward;
-	}
-	if keyboard_input.pressed(KeyCode::KeyS) {
-		movement -= *forward;
-	}
-	if keyboard_input.pressed(KeyCode::KeyA) {
-		movement -= *right;
-	}
-	if keyboard_input.pressed(KeyCode::KeyD) {
-		movement += *right;
-	}
-	if keyboard_input.pressed(KeyCode::Space) {
-		movement += Vec3::Y;
-	}
-	if keyboard_input.pressed(KeyCode::ShiftLeft) {
-		movement -= Vec3::Y;
-	}
+	// Handle movement, keeping analog stick magnitude
+	let axis = actions.move_axis();
+	let movement = *transform.forward() * axis.y
+		+ *transform.right() * axis.x
+		+ Vec3::Y * actions.vertical_axis();
 
 	if movement.length() > 0.0 {
-		movement = movement.normalize() * controller.speed * time.delta_secs();
+		let movement = movement.clamp_length_max(1.0) * controller.speed * time.delta_secs();
 		transform.translation += movement;
 	}
 }
 
 fn character_mode_movement(
-	keyboard_input: &Res<ButtonInput<KeyCode>>,
+	actions: &Actions,
 	time: &Res<Time>,
 	terrain_sdf: &Res<SdfResource<TerrainSdf>>,
 	transform: &mut Transform,
@@ -145,8 +131,8 @@ fn character_mode_movement(
 	let dt = time.delta_secs();
 	let pos = transform.translation;
 
-	// Sample terrain height at current position (Box implements Deref, so we can call distance directly)
-	let terrain_distance = terrain_sdf.sdf.sdf.distance(pos);
+	// Sample terrain height at current position in world space
+	let terrain_distance = terrain_sdf.distance(pos);
 	let is_on_ground = terrain_distance <= GROUND_STICK_DISTANCE;
 
 	// Apply gravity
@@ -163,32 +149,18 @@ fn character_mode_movement(
 	}
 
 	// Handle jump
-	if keyboard_input.just_pressed(KeyCode::Space) && is_on_ground {
+	if actions.just_pressed(InputAction::Jump) && is_on_ground {
 		controller.velocity.y = JUMP_FORCE;
 	}
 
 	// Handle horizontal movement
-	let forward = transform.forward();
-	let right = transform.right();
-	let mut horizontal_movement = Vec3::ZERO;
-
-	if keyboard_input.pressed(KeyCode::KeyW) {
-		horizontal_movement += *forward;
-	}
-	if keyboard_input.pressed(KeyCode::KeyS) {
-		horizontal_movement -= *forward;
-	}
-	if keyboard_input.pressed(KeyCode::KeyA) {
-		horizontal_movement -= *right;
-	}
-	if keyboard_input.pressed(KeyCode::KeyD) {
-		horizontal_movement += *right;
-	}
+	let axis = actions.move_axis();
+	let mut horizontal_movement = *transform.forward() * axis.y + *transform.right() * axis.x;
 
-	// Normalize horizontal movement and apply speed
+	// Scale horizontal movement by stick magnitude and apply speed
+	horizontal_movement.y = 0.0; // Remove vertical component
 	if horizontal_movement.length() > 0.0 {
-		horizontal_movement.y = 0.0; // Remove vertical component
-		horizontal_movement = horizontal_movement.normalize() * CHARACTER_SPEED;
+		horizontal_movement = horizontal_movement.normalize() * axis.length() * CHARACTER_SPEED;
 		controller.velocity.x = horizontal_movement.x;
 		controller.velocity.z = horizontal_movement.z;
 	}
@@ -197,7 +169,7 @@ fn character_mode_movement(
 	let new_pos = pos + controller.velocity * dt;
 
 	// Find terrain height at new position
-	let new_terrain_distance = terrain_sdf.sdf.sdf.distance(new_pos);
+	let new_terrain_distance = terrain_sdf.distance(new_pos);
 
 	// If we're going to be below ground or too close to it, stick to surface
 	// Check if we're below surface (negative distance) or within character height
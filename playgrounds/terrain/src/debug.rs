@@ -0,0 +1,178 @@
+use crate::terrain::TerrainSdf;
+use bevy::prelude::*;
+use engine::{chunk::TerrainChunk, Actions, CascadeGizmos, InputAction, RegionGizmos, SdfResource};
+use sdf::{Sdf, Sign};
+use vegetation_sdf::tree::skeleton::SkeletonGizmos;
+
+/// Debug view of the chunks and sign uniform intervals in the column under the camera.
+///
+/// The column is drawn ahead of the camera so that it stays in view.
+/// Interval bars are colored by sign, and sampled distances are drawn as ticks beside them.
+/// Samples whose sign contradicts their interval are drawn in magenta.
+#[derive(Resource, Debug, Clone)]
+pub struct IntervalDebug {
+	pub enabled: bool,
+	/// Number of distance samples along the column
+	pub samples: usize,
+	/// Half height of the column when no chunk covers it
+	pub fallback_half_height: f32,
+	/// Number of sampled signs that contradicted the intervals on the last frame
+	pub mismatches: usize,
+}
+
+impl Default for IntervalDebug {
+	fn default() -> Self {
+		Self { enabled: false, samples: 64, fallback_half_height: 5.0, mismatches: 0 }
+	}
+}
+
+fn sign_color(sign: &Sign) -> Color {
+	match sign {
+		Sign::Positive => Color::srgb(0.2, 0.9, 0.3),
+		Sign::Negative => Color::srgb(0.9, 0.2, 0.2),
+		Sign::Top => Color::srgb(1.0, 0.85, 0.1),
+		Sign::Bottom => Color::srgb(0.5, 0.5, 0.5),
+	}
+}
+
+pub fn toggle_interval_debug(actions: Actions, mut debug: ResMut<IntervalDebug>) {
+	if actions.just_pressed(InputAction::ToggleDebugView) {
+		debug.enabled = !debug.enabled;
+		log::info!("Interval debug {}", if debug.enabled { "enabled" } else { "disabled" });
+	}
+}
+
+/// Toggles the cascade bounds, region boundary and tree skeleton overlays.
+pub fn toggle_gizmo_overlays(
+	actions: Actions,
+	mut cascade: ResMut<CascadeGizmos>,
+	mut regions: ResMut<RegionGizmos<TerrainSdf>>,
+	mut skeleton: ResMut<SkeletonGizmos>,
+) {
+	let toggles = [
+		(InputAction::ToggleCascadeGizmos, "Cascade bounds", &mut cascade.enabled),
+		(InputAction::ToggleRegionGizmos, "Region boundaries", &mut regions.enabled),
+		(InputAction::ToggleSkeletonGizmos, "Tree skeleton", &mut skeleton.enabled),
+	];
+	for (action, name, enabled) in toggles {
+		if actions.just_pressed(action) {
+			*enabled = !*enabled;
+			log::info!("{name} overlay {}", if *enabled { "enabled" } else { "disabled" });
+		}
+	}
+}
+
+/// Keeps the region overlay on the valleys and roads of the current terrain.
+pub fn sync_region_gizmos(
+	sdf_resource: Res<SdfResource<TerrainSdf>>,
+	mut regions: ResMut<RegionGizmos<TerrainSdf>>,
+) {
+	if sdf_resource.is_changed() {
+		regions.regions = sdf_resource.sdf.regions.clone();
+	}
+}
+
+pub fn draw_interval_debug(
+	mut gizmos: Gizmos,
+	mut debug: ResMut<IntervalDebug>,
+	terrain_sdf: Res<SdfResource<TerrainSdf>>,
+	camera_query: Query<&Transform, With<Camera3d>>,
+	chunk_query: Query<&TerrainChunk>,
+) {
+	if !debug.enabled {
+		return;
+	}
+	let Ok(camera) = camera_query.single() else {
+		return;
+	};
+
+	// Work in the local space of the SDF, where the intervals are defined
+	let world_transform = terrain_sdf.transform;
+	let local_camera = world_transform.to_local(camera.translation);
+	let (x, z) = (local_camera.x, local_camera.z);
+
+	// Chunk boundaries of every chunk in the column
+	let mut column_range: Option<(f32, f32)> = None;
+	for terrain_chunk in chunk_query.iter() {
+		let chunk = terrain_chunk.chunk;
+		let max = chunk.origin + chunk.size;
+		if x < chunk.origin.x || x >= max.x || z < chunk.origin.z || z >= max.z {
+			continue;
+		}
+
+		gizmos.cuboid(
+			world_transform.to_transform()
+				* Transform::from_translation(chunk.origin + chunk.size / 2.0)
+					.with_scale(chunk.size),
+			Color::WHITE,
+		);
+		column_range = Some(match column_range {
+			Some((min_y, max_y)) => (min_y.min(chunk.origin.y), max_y.max(max.y)),
+			None => (chunk.origin.y, max.y),
+		});
+	}
+	let (min_y, max_y) = column_range.unwrap_or((
+		local_camera.y - debug.fallback_half_height,
+		local_camera.y + debug.fallback_half_height,
+	));
+	let height = max_y - min_y;
+
+	// Offset the drawn column ahead of the camera
+	let forward = (camera.forward().as_vec3() * Vec3::new(1.0, 0.0, 1.0)).normalize_or(Vec3::X);
+	let right = forward.cross(Vec3::Y);
+	let draw_offset = forward * height * 0.5;
+	let draw = |y: f32, across: f32| {
+		world_transform.to_world(Vec3::new(x, y, z)) + draw_offset + right * across
+	};
+
+	// Interval bars
+	let intervals = terrain_sdf.sdf.sign_uniform_on_y(x, z);
+	let mut clamped_intervals = Vec::new();
+	for interval in intervals {
+		let (low, high) = interval.open_range();
+		let (low, high) = (low.max(min_y), high.min(max_y));
+		if low >= high {
+			continue;
+		}
+		gizmos.line(draw(low, 0.0), draw(high, 0.0), sign_color(&interval.left.sign));
+		gizmos.line(draw(low, -0.02 * height), draw(low, 0.02 * height), Color::WHITE);
+		clamped_intervals.push((low, high, interval.left.sign));
+	}
+
+	// Sampled distances
+	let tick_scale = 0.1 * height;
+	let samples = debug.samples.max(2);
+	let mut mismatches = 0;
+	for i in 0..samples {
+		let y = min_y + height * i as f32 / (samples - 1) as f32;
+		let distance = terrain_sdf.sdf.distance(Vec3::new(x, y, z));
+		let interval_sign = clamped_intervals
+			.iter()
+			.find(|(low, high, _)| y >= *low && y <= *high)
+			.map(|(_, _, sign)| sign);
+
+		let contradicts = match interval_sign {
+			Some(Sign::Positive) => distance < 0.0,
+			Some(Sign::Negative) => distance > 0.0,
+			_ => false,
+		};
+		let color = if contradicts {
+			mismatches += 1;
+			Color::srgb(1.0, 0.0, 1.0)
+		} else if distance < 0.0 {
+			sign_color(&Sign::Negative)
+		} else {
+			sign_color(&Sign::Positive)
+		};
+
+		let length = distance.abs().min(height) / height * tick_scale;
+		gizmos.line(draw(y, 0.0), draw(y, length.max(0.005 * height)), color);
+	}
+
+	if mismatches != debug.mismatches {
+		if mismatches > 0 {
+			log::warn!("{mismatches} sampled distances contradict the intervals at ({x}, {z})");
+		}
+		debug.mismatches = mismatches;
+	}
+}
@@ -0,0 +1,667 @@
+use crate::terrain::{BuildingSites, TerrainConfig, TerrainSdf};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use engine::{
+	Actions, ChunkConfig, Decal, DecalId, DecalKind, Decals, InputAction, SdfResource,
+	TerrainRegionDirty, WorldEdit, WorldEditHistory, WorldSave, WorldSaveMigrations,
+};
+use sdf::Sdf;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Where placements are saved and loaded from by default.
+pub const PLACEMENTS_PATH: &str = "assets/placements.json";
+
+/// The kinds of objects the editor places.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Stamp {
+	#[default]
+	Tree,
+	Rock,
+	Building,
+	RoadWaypoint,
+}
+
+impl Stamp {
+	pub const ALL: [Stamp; 4] = [Stamp::Tree, Stamp::Rock, Stamp::Building, Stamp::RoadWaypoint];
+
+	/// The stamp after this one, wrapping around.
+	pub fn next(self) -> Self {
+		let index = Self::ALL.iter().position(|stamp| *stamp == self).unwrap_or(0);
+		Self::ALL[(index + 1) % Self::ALL.len()]
+	}
+
+	fn color(self) -> Color {
+		match self {
+			Stamp::Tree => Color::srgb(0.2, 0.7, 0.25),
+			Stamp::Rock => Color::srgb(0.55, 0.55, 0.6),
+			Stamp::Building => Color::srgb(0.85, 0.5, 0.25),
+			Stamp::RoadWaypoint => Color::srgb(0.95, 0.9, 0.3),
+		}
+	}
+
+	/// Draws the stamp standing on a point of the terrain, in world space.
+	fn draw(self, gizmos: &mut Gizmos, position: Vec3, scale: f32, color: Color) {
+		let up = Vec3::Y * scale;
+		match self {
+			Stamp::Tree => {
+				gizmos.line(position, position + up, color);
+				gizmos.sphere(
+					Isometry3d::from_translation(position + up * 1.3),
+					0.4 * scale,
+					color,
+				);
+			}
+			Stamp::Rock => {
+				gizmos.sphere(Isometry3d::from_translation(position), 0.3 * scale, color);
+			}
+			Stamp::Building => {
+				gizmos.cuboid(
+					Transform::from_translation(position + up * 0.5).with_scale(Vec3::splat(scale)),
+					color,
+				);
+			}
+			Stamp::RoadWaypoint => {
+				gizmos.circle(
+					Isometry3d::new(position, Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)),
+					0.3 * scale,
+					color,
+				);
+				gizmos.line(position, position + up * 0.5, color);
+			}
+		}
+	}
+}
+
+/// A stamp placed on the terrain, in the local space of the terrain SDF.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Placement {
+	pub stamp: Stamp,
+	pub position: [f32; 3],
+}
+
+/// Placements files from before world saves held the placements alone, as version 0.
+fn migrate_placements_file(mut save: Value) -> Result<Value, String> {
+	let object = save.as_object_mut().ok_or("Placements file is not an object")?;
+	let placements = match object.remove("placements") {
+		Some(Value::Array(placements)) => placements,
+		_ => Vec::new(),
+	};
+	let stamps = placements
+		.into_iter()
+		.map(|mut placement| {
+			if let Some(placement) = placement.as_object_mut() {
+				let stamp = placement.remove("stamp").unwrap_or_default();
+				placement.insert("kind".to_string(), stamp);
+			}
+			placement
+		})
+		.collect();
+	object.insert("stamps".to_string(), Value::Array(stamps));
+	object.entry("seed").or_insert(Value::from(0));
+	Ok(save)
+}
+
+/// The migrations of the world saves the editor loads.
+pub fn save_migrations() -> WorldSaveMigrations {
+	WorldSaveMigrations::default().with_migration(0, migrate_placements_file)
+}
+
+/// The placed stamps, kept as the stamps of a [WorldSave].
+///
+/// Road waypoints are joined in the order they were placed.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Placements {
+	pub placements: Vec<Placement>,
+}
+
+impl Placements {
+	/// The placements among the stamps of a save, skipping stamps of unknown kinds.
+	pub fn from_save(save: &WorldSave) -> Self {
+		let placements = save
+			.stamps
+			.iter()
+			.filter_map(|stamp| match serde_json::from_value(Value::String(stamp.kind.clone())) {
+				Ok(kind) => Some(Placement { stamp: kind, position: stamp.position }),
+				Err(_) => {
+					log::warn!("Skipping a stamp of unknown kind {}", stamp.kind);
+					None
+				}
+			})
+			.collect();
+		Self { placements }
+	}
+
+	/// Replaces the stamps of the save with the placements.
+	pub fn write_to(&self, save: &mut WorldSave) {
+		save.stamps.clear();
+		for placement in &self.placements {
+			let Ok(Value::String(kind)) = serde_json::to_value(placement.stamp) else {
+				continue;
+			};
+			save.record_stamp(kind, Vec3::from_array(placement.position));
+		}
+	}
+
+	pub fn place(&mut self, stamp: Stamp, position: Vec3) {
+		self.placements.push(Placement { stamp, position: position.to_array() });
+	}
+
+	/// The index of the placement nearest to a point, if any is within the radius.
+	pub fn nearest(&self, position: Vec3, radius: f32) -> Option<usize> {
+		self.placements
+			.iter()
+			.enumerate()
+			.map(|(i, placement)| (i, Vec3::from_array(placement.position).distance(position)))
+			.filter(|(_, distance)| *distance <= radius)
+			.min_by(|(_, a), (_, b)| a.total_cmp(b))
+			.map(|(i, _)| i)
+	}
+
+	/// Removes the placement nearest to a point, if any is within the radius.
+	pub fn remove_nearest(&mut self, position: Vec3, radius: f32) -> Option<Placement> {
+		let index = self.nearest(position, radius)?;
+		Some(self.placements.remove(index))
+	}
+
+	/// The footprints of the buildings, each a square the size of the drawn stamp.
+	pub fn building_footprints(&self, scale: f32) -> Vec<Rect> {
+		self.placements
+			.iter()
+			.filter(|placement| placement.stamp == Stamp::Building)
+			.map(|placement| {
+				let position = Vec3::from_array(placement.position);
+				Rect::from_center_size(position.xz(), Vec2::splat(scale))
+			})
+			.collect()
+	}
+
+	/// Ground detail for the placements: leaf litter under trees, dirt around buildings
+	/// and ruts along the road between waypoints.
+	pub fn decals(&self, scale: f32) -> Vec<Decal> {
+		let mut decals = Vec::new();
+		let mut previous_waypoint = None;
+		for placement in &self.placements {
+			let position = Vec3::from_array(placement.position);
+			match placement.stamp {
+				Stamp::Tree => decals.push(Decal::new(
+					DecalKind::LeafLitter,
+					position,
+					Vec3::new(2.5, 2.0, 2.5) * scale,
+				)),
+				Stamp::Building => decals.push(Decal::new(
+					DecalKind::DirtSkirt,
+					position,
+					Vec3::new(1.5, 2.0, 1.5) * scale,
+				)),
+				Stamp::RoadWaypoint => {
+					if let Some(previous) = previous_waypoint.replace(position) {
+						let along = position - previous;
+						decals.push(
+							Decal::new(
+								DecalKind::TireRuts,
+								(previous + position) / 2.0,
+								Vec3::new(0.6 * scale, 2.0 * scale, along.length() / 2.0),
+							)
+							.with_yaw(along.x.atan2(along.z)),
+						);
+					}
+				}
+				Stamp::Rock => {}
+			}
+		}
+		decals
+	}
+}
+
+/// The decals registered for the placements, replaced whenever the placements change.
+#[derive(Resource, Debug, Default)]
+pub struct PlacementDecals {
+	ids: Vec<DecalId>,
+}
+
+/// Projects ground detail under the placements.
+pub fn sync_placement_decals(
+	placements: Res<Placements>,
+	editor: Res<PlacementEditor>,
+	mut placement_decals: ResMut<PlacementDecals>,
+	mut decals: ResMut<Decals<TerrainSdf>>,
+) {
+	if !placements.is_changed() {
+		return;
+	}
+	for id in placement_decals.ids.drain(..) {
+		decals.remove(id);
+	}
+	placement_decals.ids = placements
+		.decals(editor.stamp_scale)
+		.into_iter()
+		.map(|decal| decals.insert(decal))
+		.collect();
+}
+
+/// Flattens the terrain under the placed buildings, so they neither hover over nor clip into
+/// slopes.
+///
+/// The terrain is rebuilt with the [BuildingSites] whenever a building is placed or removed,
+/// and only the chunks around the footprints that changed are regenerated.
+pub fn flatten_building_sites(
+	placements: Res<Placements>,
+	editor: Res<PlacementEditor>,
+	config: Res<TerrainConfig>,
+	chunk_config: Res<ChunkConfig<TerrainSdf>>,
+	mut sites: ResMut<BuildingSites>,
+	mut terrain_sdf: ResMut<SdfResource<TerrainSdf>>,
+	mut dirty: MessageWriter<TerrainRegionDirty>,
+) {
+	if !placements.is_changed() {
+		return;
+	}
+	let footprints = placements.building_footprints(editor.stamp_scale);
+	if footprints == sites.footprints {
+		return;
+	}
+
+	let changed: Vec<Rect> = footprints
+		.iter()
+		.filter(|footprint| !sites.footprints.contains(footprint))
+		.chain(sites.footprints.iter().filter(|footprint| !footprints.contains(footprint)))
+		.copied()
+		.collect();
+	sites.footprints = footprints;
+	terrain_sdf.sdf = Arc::new(TerrainSdf::new(&config, chunk_config.world_bounds(), &sites));
+	for footprint in changed {
+		dirty.write(TerrainRegionDirty(sites.dirty_region(footprint, config.height_scale)));
+	}
+}
+
+/// Places a stamp as an undoable edit.
+pub struct PlaceStamp(pub Placement);
+
+impl WorldEdit for PlaceStamp {
+	fn apply(&mut self, world: &mut World) {
+		world.resource_mut::<Placements>().placements.push(self.0);
+	}
+
+	fn revert(&mut self, world: &mut World) {
+		let mut placements = world.resource_mut::<Placements>();
+		if let Some(index) =
+			placements.placements.iter().rposition(|placement| *placement == self.0)
+		{
+			placements.placements.remove(index);
+		}
+	}
+}
+
+/// Removes a placement as an undoable edit, restoring it in its place on undo.
+pub struct RemoveStamp {
+	pub index: usize,
+	removed: Option<Placement>,
+}
+
+impl RemoveStamp {
+	pub fn new(index: usize) -> Self {
+		Self { index, removed: None }
+	}
+}
+
+impl WorldEdit for RemoveStamp {
+	fn apply(&mut self, world: &mut World) {
+		let mut placements = world.resource_mut::<Placements>();
+		if self.index < placements.placements.len() {
+			self.removed = Some(placements.placements.remove(self.index));
+		}
+	}
+
+	fn revert(&mut self, world: &mut World) {
+		let mut placements = world.resource_mut::<Placements>();
+		if let Some(placement) = self.removed.take() {
+			let index = self.index.min(placements.placements.len());
+			placements.placements.insert(index, placement);
+		}
+	}
+}
+
+/// Sphere-traces a ray against the SDF, returning the first point on its surface.
+pub fn raycast_sdf(
+	sdf: &dyn Sdf,
+	origin: Vec3,
+	direction: Vec3,
+	max_distance: f32,
+	max_steps: usize,
+) -> Option<Vec3> {
+	let direction = direction.normalize_or_zero();
+	if direction == Vec3::ZERO {
+		return None;
+	}
+	let mut t = 0.0;
+	for _ in 0..max_steps {
+		let point = origin + direction * t;
+		let distance = sdf.distance(point);
+		if distance.abs() < 1e-4 {
+			return Some(point);
+		}
+		if distance < 0.0 {
+			// Started inside the terrain
+			return None;
+		}
+		t += distance;
+		if t > max_distance {
+			return None;
+		}
+	}
+	None
+}
+
+/// Editor mode for laying out stamps on the terrain.
+///
+/// The stamp under the cursor is placed with the left mouse button and the nearest placement
+/// removed with the right, as edits in the [WorldEditHistory]. Placements are saved to the path and loaded from it on the next run.
+#[derive(Resource, Debug, Clone)]
+pub struct PlacementEditor {
+	pub enabled: bool,
+	pub stamp: Stamp,
+	pub path: PathBuf,
+	/// Where the cursor ray hits the terrain, in the local space of the SDF
+	pub cursor_hit: Option<Vec3>,
+	/// Placements within this distance of the cursor hit are removed
+	pub remove_radius: f32,
+	/// Rays longer than this miss the terrain
+	pub max_distance: f32,
+	/// Size of the drawn stamps in local units
+	pub stamp_scale: f32,
+}
+
+impl Default for PlacementEditor {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			stamp: Stamp::default(),
+			path: PathBuf::from(PLACEMENTS_PATH),
+			cursor_hit: None,
+			remove_radius: 1.0,
+			max_distance: 500.0,
+			stamp_scale: 1.0,
+		}
+	}
+}
+
+/// Loads the saved world and its placements, starting with none if it can't be read.
+pub fn load_placements(mut commands: Commands, editor: Res<PlacementEditor>) {
+	let save = WorldSave::load(&editor.path, &save_migrations()).unwrap_or_else(|e| {
+		log::error!("{e}");
+		WorldSave::default()
+	});
+	let placements = Placements::from_save(&save);
+	log::info!("Loaded {} placements from {}", placements.placements.len(), editor.path.display());
+	commands.insert_resource(placements);
+	commands.insert_resource(save);
+}
+
+/// Toggles the editor, cycles the stamp, undoes and redoes edits and saves the placements.
+pub fn editor_actions(
+	actions: Actions,
+	mut editor: ResMut<PlacementEditor>,
+	mut history: ResMut<WorldEditHistory>,
+	placements: Res<Placements>,
+	config: Res<TerrainConfig>,
+	mut save: ResMut<WorldSave>,
+) {
+	if actions.just_pressed(InputAction::ToggleEditor) {
+		editor.enabled = !editor.enabled;
+		log::info!("Placement editor {}", if editor.enabled { "enabled" } else { "disabled" });
+	}
+	if !editor.enabled {
+		return;
+	}
+	if actions.just_pressed(InputAction::EditorNextStamp) {
+		editor.stamp = editor.stamp.next();
+		log::info!("Placing {:?}", editor.stamp);
+	}
+	if actions.just_pressed(InputAction::Undo) {
+		history.undo();
+	}
+	if actions.just_pressed(InputAction::Redo) {
+		history.redo();
+	}
+	if actions.just_pressed(InputAction::EditorSave) {
+		save.seed = config.seed;
+		placements.write_to(&mut save);
+		match save.save(&editor.path) {
+			Ok(()) => log::info!(
+				"Saved {} placements to {}",
+				placements.placements.len(),
+				editor.path.display()
+			),
+			Err(e) => log::error!("{e}"),
+		}
+	}
+}
+
+/// Casts the cursor ray at the terrain and queues edits placing or removing stamps where it hits.
+pub fn edit_placements(
+	mut editor: ResMut<PlacementEditor>,
+	mut history: ResMut<WorldEditHistory>,
+	placements: Res<Placements>,
+	mouse_buttons: Res<ButtonInput<MouseButton>>,
+	window_query: Query<&Window, With<PrimaryWindow>>,
+	camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+	terrain_sdf: Res<SdfResource<TerrainSdf>>,
+) {
+	if !editor.enabled {
+		editor.cursor_hit = None;
+		return;
+	}
+	let (Ok(window), Ok((camera, camera_transform))) =
+		(window_query.single(), camera_query.single())
+	else {
+		return;
+	};
+	let Some(ray) = window
+		.cursor_position()
+		.and_then(|cursor| camera.viewport_to_world(camera_transform, cursor).ok())
+	else {
+		editor.cursor_hit = None;
+		return;
+	};
+
+	// Trace in the local space of the SDF
+	let world_transform = terrain_sdf.transform;
+	let origin = world_transform.to_local(ray.origin);
+	let direction = world_transform.to_local(ray.origin + *ray.direction) - origin;
+	editor.cursor_hit =
+		raycast_sdf(terrain_sdf.sdf.as_ref(), origin, direction, editor.max_distance, 256);
+
+	let Some(hit) = editor.cursor_hit else {
+		return;
+	};
+	if mouse_buttons.just_pressed(MouseButton::Left) {
+		history.push(PlaceStamp(Placement { stamp: editor.stamp, position: hit.to_array() }));
+	} else if mouse_buttons.just_pressed(MouseButton::Right) {
+		if let Some(index) = placements.nearest(hit, editor.remove_radius) {
+			history.push(RemoveStamp::new(index));
+		}
+	}
+}
+
+/// Draws the placements, joining the road waypoints, and the stamp under the cursor.
+pub fn draw_placements(
+	mut gizmos: Gizmos,
+	editor: Res<PlacementEditor>,
+	placements: Res<Placements>,
+	terrain_sdf: Res<SdfResource<TerrainSdf>>,
+) {
+	let world_transform = terrain_sdf.transform;
+	let to_world = |position: Vec3| world_transform.to_world(position);
+	let scale = to_world(Vec3::Y * editor.stamp_scale).distance(to_world(Vec3::ZERO));
+
+	let mut previous_waypoint = None;
+	for placement in &placements.placements {
+		let position = to_world(Vec3::from_array(placement.position));
+		placement.stamp.draw(&mut gizmos, position, scale, placement.stamp.color());
+		if placement.stamp == Stamp::RoadWaypoint {
+			if let Some(previous) = previous_waypoint.replace(position) {
+				gizmos.line(previous, position, Stamp::RoadWaypoint.color());
+			}
+		}
+	}
+
+	if let Some(hit) = editor.cursor_hit {
+		editor.stamp.draw(&mut gizmos, to_world(hit), scale, Color::WHITE);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Ground;
+
+	impl Sdf for Ground {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.y - 2.0
+		}
+	}
+
+	#[test]
+	fn test_raycast_hits_the_surface() {
+		let origin = Vec3::new(1.0, 10.0, 1.0);
+		let Some(hit) = raycast_sdf(&Ground, origin, Vec3::new(1.0, -1.0, 0.0), 100.0, 64) else {
+			panic!("expected the ray to hit the ground");
+		};
+		assert!((hit - Vec3::new(9.0, 2.0, 1.0)).length() < 1e-3);
+
+		assert_eq!(raycast_sdf(&Ground, origin, Vec3::Y, 100.0, 64), None);
+		assert_eq!(raycast_sdf(&Ground, origin, Vec3::NEG_Y, 5.0, 64), None);
+	}
+
+	#[test]
+	fn test_placements_round_trip() {
+		let mut placements = Placements::default();
+		placements.place(Stamp::Tree, Vec3::new(1.0, 2.0, 3.0));
+		placements.place(Stamp::RoadWaypoint, Vec3::new(4.0, 2.0, 3.0));
+		placements.place(Stamp::Building, Vec3::new(10.0, 2.0, 3.0));
+
+		let removed = placements.remove_nearest(Vec3::new(4.5, 2.0, 3.0), 1.0);
+		assert_eq!(removed.map(|placement| placement.stamp), Some(Stamp::RoadWaypoint));
+		assert_eq!(placements.remove_nearest(Vec3::new(6.0, 2.0, 3.0), 1.0), None);
+
+		let path = std::env::temp_dir()
+			.join(format!("terrain-playground-placements-{}.json", std::process::id()));
+		let mut save = WorldSave::new(3);
+		placements.write_to(&mut save);
+		assert_eq!(save.save(&path), Ok(()));
+		let loaded = WorldSave::load(&path, &save_migrations());
+		std::fs::remove_file(&path).ok();
+		assert_eq!(loaded.as_ref().map(Placements::from_save), Ok(placements.clone()));
+
+		let Ok(empty) = WorldSave::load(&path, &save_migrations()) else {
+			panic!("expected a missing save to load empty");
+		};
+		assert_eq!(Placements::from_save(&empty), Placements::default());
+
+		// Placements files from before world saves are migrated
+		let Ok(legacy) = serde_json::to_string(&placements) else {
+			panic!("expected the placements to serialize");
+		};
+		let migrated = WorldSave::from_json(&legacy, &save_migrations());
+		assert_eq!(migrated.as_ref().map(Placements::from_save), Ok(placements));
+	}
+
+	#[test]
+	fn test_road_ruts_join_waypoints() {
+		let mut placements = Placements::default();
+		placements.place(Stamp::RoadWaypoint, Vec3::new(0.0, 1.0, 0.0));
+		placements.place(Stamp::Rock, Vec3::new(2.0, 1.0, 0.0));
+		placements.place(Stamp::RoadWaypoint, Vec3::new(6.0, 1.0, 8.0));
+
+		let [ruts] = placements.decals(1.0)[..] else {
+			panic!("expected ruts between the waypoints only");
+		};
+		assert_eq!(ruts.kind, DecalKind::TireRuts);
+		// The ends of the segment lie on the ends of the decal box
+		for (end, z) in [(Vec3::new(0.0, 1.0, 0.0), -1.0), (Vec3::new(6.0, 1.0, 8.0), 1.0)] {
+			assert!(ruts.to_decal_space(end).abs_diff_eq(Vec3::new(0.0, 0.0, z), 1e-5));
+		}
+	}
+
+	#[test]
+	fn test_undo_stamp_edits() {
+		let mut app = App::new();
+		app.init_resource::<Placements>()
+			.init_resource::<WorldEditHistory>()
+			.add_systems(Update, engine::apply_world_edits);
+		let tree = Placement { stamp: Stamp::Tree, position: [1.0, 2.0, 3.0] };
+		let rock = Placement { stamp: Stamp::Rock, position: [4.0, 2.0, 3.0] };
+
+		let mut history = app.world_mut().resource_mut::<WorldEditHistory>();
+		history.push(PlaceStamp(tree));
+		history.push(PlaceStamp(rock));
+		history.push(RemoveStamp::new(0));
+		app.update();
+		assert_eq!(app.world().resource::<Placements>().placements, vec![rock]);
+
+		let mut history = app.world_mut().resource_mut::<WorldEditHistory>();
+		history.undo();
+		history.undo();
+		app.update();
+		assert_eq!(app.world().resource::<Placements>().placements, vec![tree]);
+
+		app.world_mut().resource_mut::<WorldEditHistory>().redo();
+		app.update();
+		assert_eq!(app.world().resource::<Placements>().placements, vec![tree, rock]);
+	}
+
+	#[test]
+	fn test_building_sites_are_flattened() {
+		let config = TerrainConfig::new(3);
+		let chunk_config = ChunkConfig::<TerrainSdf>::default();
+		let sites = BuildingSites::default();
+		let terrain_sdf = TerrainSdf::new(&config, chunk_config.world_bounds(), &sites);
+		let mut app = App::new();
+		app.insert_resource(config)
+			.insert_resource(chunk_config)
+			.insert_resource(sites)
+			.insert_resource(SdfResource::new(terrain_sdf))
+			.init_resource::<PlacementEditor>()
+			.init_resource::<Placements>()
+			.add_message::<TerrainRegionDirty>()
+			.add_systems(Update, flatten_building_sites);
+		let dirty = |app: &mut App| {
+			let mut messages = app.world_mut().resource_mut::<Messages<TerrainRegionDirty>>();
+			messages.drain().count()
+		};
+		app.update();
+		assert_eq!(dirty(&mut app), 0);
+
+		let mut placements = app.world_mut().resource_mut::<Placements>();
+		placements.place(Stamp::Tree, Vec3::new(50.0, 0.0, -50.0));
+		placements.place(Stamp::Building, Vec3::new(60.0, 0.0, -60.0));
+		app.update();
+		assert_eq!(dirty(&mut app), 1);
+		let footprints = &app.world().resource::<BuildingSites>().footprints;
+		assert_eq!(footprints, &vec![Rect::from_center_size(Vec2::new(60.0, -60.0), Vec2::ONE)]);
+
+		// Every corner of the footprint is at the same height
+		let terrain_sdf = app.world().resource::<SdfResource<TerrainSdf>>();
+		let corners = [(59.5, -60.5), (60.5, -60.5), (59.5, -59.5), (60.5, -59.5)];
+		let distances: Vec<f32> = corners
+			.iter()
+			.map(|(x, z)| terrain_sdf.sdf.distance(Vec3::new(*x, 0.0, *z)))
+			.collect();
+		assert!(distances.iter().all(|distance| (distance - distances[0]).abs() < 1e-4));
+
+		// Placing trees leaves the terrain as it is
+		let mut placements = app.world_mut().resource_mut::<Placements>();
+		placements.place(Stamp::Tree, Vec3::new(70.0, 0.0, -50.0));
+		app.update();
+		assert_eq!(dirty(&mut app), 0);
+
+		app.world_mut().resource_mut::<Placements>().placements.clear();
+		app.update();
+		assert_eq!(dirty(&mut app), 1);
+		assert!(app.world().resource::<BuildingSites>().footprints.is_empty());
+	}
+}
@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use engine::{
+	cascade::CascadeChunk,
+	chunk::TerrainChunk,
+	sdf::Sdf,
+	shaders::{leaf_material::LeafMaterial, outline::EdgeMaterial},
+	FoliageFalloff, SdfResource,
+};
+use render_item::DispatchRenderItem;
+use std::sync::Arc;
+use vegetation_sdf::forest::{DensityFalloff, Forest, ForestLod, GroundHeight};
+
+/// Iterations of the bisection that finds the ground in a chunk
+const GROUND_ITERATIONS: usize = 16;
+
+/// A forest scattered over the terrain chunks as they stream in.
+///
+/// Scattering is off until this resource is inserted.
+#[derive(Resource, Clone)]
+pub struct ChunkForest {
+	forest: Forest<EdgeMaterial, LeafMaterial>,
+	/// Chunks up to this size get individual trees, larger chunks a canopy carpet
+	max_tree_chunk_size: f32,
+}
+
+impl ChunkForest {
+	pub fn new(forest: Forest<EdgeMaterial, LeafMaterial>) -> Self {
+		Self { forest, max_tree_chunk_size: 64.0 }
+	}
+
+	pub fn with_max_tree_chunk_size(mut self, max_tree_chunk_size: f32) -> Self {
+		self.max_tree_chunk_size = max_tree_chunk_size;
+		self
+	}
+
+	/// Number of meshes cached for the forest.
+	pub fn cached_meshes(&self) -> usize {
+		self.forest.cached_meshes()
+	}
+
+	pub fn lod(&self, chunk: &CascadeChunk) -> ForestLod {
+		if chunk.size.max_element() <= self.max_tree_chunk_size {
+			ForestLod::Trees
+		} else {
+			ForestLod::Canopy
+		}
+	}
+}
+
+/// The chunk type the render items are spawned for.
+fn render_chunk(chunk: &CascadeChunk) -> chunk::cascade::CascadeChunk {
+	chunk::cascade::CascadeChunk {
+		origin: chunk.origin,
+		size: chunk.size,
+		res_2: chunk.res_2,
+		omit: chunk.omit,
+	}
+}
+
+/// The height of the SDF surface within the vertical span of the chunk.
+///
+/// Columns with no surface in the chunk fall below it when they are all air and on its top
+/// when they are all solid, so the forest leaves them to the chunks above or below.
+pub fn chunk_ground<S: Sdf + Send + Sync + 'static>(
+	sdf: Arc<S>,
+	chunk: &CascadeChunk,
+) -> GroundHeight {
+	let bottom = chunk.origin.y;
+	let top = chunk.origin.y + chunk.size.y;
+	Arc::new(move |xz: Vec2| {
+		let distance = |y: f32| sdf.distance(Vec3::new(xz.x, y, xz.y));
+		if distance(top) <= 0.0 {
+			return top;
+		}
+		if distance(bottom) > 0.0 {
+			return bottom - 1.0;
+		}
+
+		let (mut low, mut high) = (bottom, top);
+		for _ in 0..GROUND_ITERATIONS {
+			let mid = (low + high) / 2.0;
+			if distance(mid) > 0.0 {
+				high = mid;
+			} else {
+				low = mid;
+			}
+		}
+		(low + high) / 2.0
+	})
+}
+
+/// Dispatches the forest onto each terrain chunk that is spawned.
+///
+/// The forest is a child of the chunk, so unloading or regenerating the chunk despawns it
+/// with everything it spawned. Trees are placed in the local space of the SDF.
+///
+/// With a [FoliageFalloff], trees thin out over its span from where the camera is when their
+/// chunk is spawned.
+pub fn scatter_chunk_forests<S: Sdf + Send + Sync + 'static>(
+	mut commands: Commands,
+	chunk_forest: Option<Res<ChunkForest>>,
+	falloff: Option<Res<FoliageFalloff>>,
+	sdf_resource: Res<SdfResource<S>>,
+	camera_query: Query<&Transform, With<Camera3d>>,
+	chunks: Query<(Entity, &TerrainChunk), Added<TerrainChunk>>,
+) {
+	let Some(chunk_forest) = chunk_forest else {
+		return;
+	};
+	let falloff = falloff.zip(camera_query.single().ok()).map(|(falloff, camera)| {
+		let center = sdf_resource.transform.to_local(camera.translation);
+		DensityFalloff::new(center, falloff.start, falloff.end)
+	});
+
+	for (entity, chunk) in &chunks {
+		let forest = chunk_forest
+			.forest
+			.clone()
+			.with_ground(chunk_ground(sdf_resource.sdf.clone(), &chunk.chunk))
+			.with_lod(chunk_forest.lod(&chunk.chunk));
+		let forest = match falloff {
+			Some(falloff) => forest.with_density_falloff(falloff),
+			None => forest,
+		};
+		commands.spawn((
+			render_chunk(&chunk.chunk),
+			DispatchRenderItem::new(forest),
+			Transform::default(),
+			ChildOf(entity),
+		));
+	}
+}
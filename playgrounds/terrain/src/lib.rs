@@ -1,20 +1,50 @@
+use bevy::ecs::schedule::{InternedScheduleLabel, ScheduleLabel};
 use bevy::prelude::*;
-use std::f32::consts::PI;
 
 mod camera;
+mod debug;
+mod editor;
+mod forest;
+#[cfg(test)]
+mod streaming;
 mod terrain;
+mod tweak;
 mod ui;
 
 use engine::{
-	manage_chunks, shaders::outline::EdgeMaterial, ChunkConfig, ChunkResolutionConfig,
-	LoadedChunks, SdfResource,
+	apply_world_edits, bake_terrain_shadows, bend_foliage, boot_world, collect_world_stats,
+	confine_to_world, crossfade_chunks, draw_cascade_bounds, draw_region_boundaries,
+	drift_ambient_particles, emit_ambient_particles, fade_distant_decals, manage_chunks,
+	prewarm_chunks, project_chunk_decals, queue_dirty_chunks, queue_dirty_region_chunks,
+	regenerate_queued_chunks, scale_resolution,
+	shaders::{decal_material::DecalMaterial, leaf_material::LeafMaterial, outline::EdgeMaterial},
+	tag_chunk_features, track_camera_projection, track_foliage_actors, BootProgress, CascadeGizmos,
+	ChunkConfig, ChunkCrossfade, ChunkMaterialRegistry, ChunkPrewarm, ChunkRegenerationQueue,
+	ChunkResolutionConfig, DecalMaterials, Decals, FoliageInteraction, GenerationPool,
+	GenerationPoolConfig, HoleRepair, InputMap, LoadedChunks, MeshHoles, MeshProcessors,
+	RegionGizmos, ResolutionScaling, ScreenSpaceError, SdfResource, StandardLightingPlugin,
+	TerrainDirty, TerrainRegionDirty, TerrainShadowMap, WindField, WorldAtlas, WorldBoot,
+	WorldBootState, WorldBoundary, WorldEdge, WorldEditHistory, WorldPalettePlugin, WorldStats,
+};
+use render_item::{mesh::fetch_meshes, mesh::handle::MeshHandle, render_items};
+use vegetation_sdf::{
+	forest::{CanopyCarpet, Forest},
+	tree::leaf_budget::{budget_tree_leaves, LeafBudget},
+	tree::meshes::{canopy::ball::NoisyBall, trunk::segment::SimpleTrunkSegment},
+	tree::skeleton::{draw_tree_skeleton, SkeletonGizmos},
 };
 
 pub use camera::CameraController;
-pub use terrain::TerrainConfig;
+pub use debug::IntervalDebug;
+pub use editor::{Placement, PlacementEditor, Placements, Stamp};
+pub use forest::{chunk_ground, scatter_chunk_forests, ChunkForest};
+pub use terrain::{world_preview, TerrainConfig};
+pub use tweak::TerrainTweakPanel;
 
 pub use sdf;
 
+/// The terrain playground on its own, with the materials, lighting and palette it shares with
+/// the other playgrounds.
 pub struct TerrainPlugin {
 	pub seed: u32,
 }
@@ -23,75 +53,228 @@ impl Plugin for TerrainPlugin {
 	fn build(&self, app: &mut App) {
 		// Register EdgeMaterial plugin
 		app.add_plugins(bevy::pbr::MaterialPlugin::<EdgeMaterial>::default());
+		app.add_plugins(bevy::pbr::MaterialPlugin::<LeafMaterial>::default());
+		app.add_plugins(StandardLightingPlugin::default().with_cycle_key(KeyCode::KeyL));
+		app.add_plugins(WorldPalettePlugin::default().with_cycle_key(KeyCode::KeyP));
+		app.add_plugins(TerrainScenePlugin::new(self.seed));
+	}
+}
+
+/// The systems of the terrain scene, for a launcher to run only while the scene is shown.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TerrainSystems;
+
+/// The terrain scene without the plugins it shares with the other playgrounds, which are
+/// registered once by whoever adds it.
+///
+/// The scene is set up in the setup schedule, Startup unless a launcher sets it up on entering
+/// a state, and its systems run in [TerrainSystems]. The teardown schedule, if there is one,
+/// forgets the loaded chunks and boots the world again the next time the scene is set up.
+pub struct TerrainScenePlugin {
+	pub seed: u32,
+	pub setup: InternedScheduleLabel,
+	pub teardown: Option<InternedScheduleLabel>,
+}
+
+impl TerrainScenePlugin {
+	pub fn new(seed: u32) -> Self {
+		Self { seed, setup: Startup.intern(), teardown: None }
+	}
+
+	pub fn with_setup(mut self, setup: impl ScheduleLabel) -> Self {
+		self.setup = setup.intern();
+		self
+	}
+
+	pub fn with_teardown(mut self, teardown: impl ScheduleLabel) -> Self {
+		self.teardown = Some(teardown.intern());
+		self
+	}
+}
+
+impl Plugin for TerrainScenePlugin {
+	fn build(&self, app: &mut App) {
+		app.add_plugins(bevy::pbr::MaterialPlugin::<DecalMaterial>::default());
+		#[cfg(feature = "reflect")]
+		app.add_plugins(engine::EngineReflectPlugin::<terrain::TerrainSdf>::default())
+			.register_type::<vegetation_sdf::tree::meshes::trunk::segment::SegmentConfig>()
+			.register_type::<vegetation_sdf::species::SpeciesProperties>();
 
 		// Set up geographic features
-		let terrain_chunk_config = ChunkConfig::<terrain::TerrainSdf>::default();
-		let terrain_resolution_config = ChunkResolutionConfig::<terrain::TerrainSdf>::default();
+		// The world ends 100km out from the origin in X and Z, sinking over its last 10km
+		let terrain_chunk_config = ChunkConfig::<terrain::TerrainSdf> {
+			world_size: 200.0,
+			boundary: WorldBoundary::Edge(WorldEdge::new(10.0, 3.0)),
+			..default()
+		};
+		let terrain_resolution_config = ChunkResolutionConfig::<terrain::TerrainSdf> {
+			screen_space: Some(ScreenSpaceError::default()),
+			..default()
+		};
 		let terrain_config = TerrainConfig::new(self.seed);
-		let terrain_sdf = terrain::TerrainSdf { sdf: terrain::create_terrain_sdf(&terrain_config) };
+		let building_sites = terrain::BuildingSites::default();
+		let terrain_sdf = terrain::TerrainSdf::new(
+			&terrain_config,
+			terrain_chunk_config.world_bounds(),
+			&building_sites,
+		);
 		let terrain_sdf_resource = SdfResource::new(terrain_sdf);
+		let mesh_holes = MeshHoles::default();
+		let mesh_processors = MeshProcessors::<terrain::TerrainSdf>::default()
+			.with_processor(HoleRepair::new(mesh_holes.clone()));
 
-		app.insert_resource(terrain_config)
-			.insert_resource(ClearColor(Color::hsla(201.0, 0.69, 0.62, 1.0)))
+		match GenerationPool::new(GenerationPoolConfig::default()) {
+			Ok(generation_pool) => {
+				app.insert_resource(generation_pool);
+			}
+			Err(e) => log::error!("Generating chunks on the global pool: {e}"),
+		}
+
+		// Distant terrain is shaded from a map 200km across, past the 10km of the cascade
+		let terrain_shadow_map = TerrainShadowMap::<terrain::TerrainSdf>::default()
+			.with_resolution(128)
+			.with_extent(200.0)
+			.with_height_range(
+				-2.0 * terrain_config.height_scale,
+				2.0 * terrain_config.height_scale,
+			)
+			.with_fade(5.0, 5.0);
+
+		app.insert_resource(terrain::biome_tagger(&terrain_config))
+			.insert_resource(terrain::ambient_particles(&terrain_config))
+			.init_resource::<WindField>()
+			.insert_resource(terrain_config)
+			.init_resource::<InputMap>()
+			.init_resource::<IntervalDebug>()
+			.init_resource::<CascadeGizmos>()
+			.init_resource::<RegionGizmos<terrain::TerrainSdf>>()
+			.init_resource::<WorldAtlas>()
+			.init_state::<WorldBootState>()
+			.init_resource::<WorldBoot<terrain::TerrainSdf>>()
+			.init_resource::<BootProgress>()
+			.init_resource::<SkeletonGizmos>()
+			.init_resource::<LeafBudget>()
+			.init_resource::<WorldStats>()
+			.init_resource::<FoliageInteraction>()
+			.init_resource::<TerrainTweakPanel>()
+			.init_resource::<PlacementEditor>()
+			.init_resource::<Placements>()
+			.insert_resource(building_sites)
+			.init_resource::<WorldEditHistory>()
+			.init_resource::<Decals<terrain::TerrainSdf>>()
+			.init_resource::<DecalMaterials>()
+			.init_resource::<editor::PlacementDecals>()
+			.register_type::<TerrainConfig>()
+			.add_message::<TerrainDirty>()
+			.add_message::<TerrainRegionDirty>()
+			.init_resource::<ChunkRegenerationQueue<terrain::TerrainSdf>>()
+			.init_resource::<ChunkCrossfade<terrain::TerrainSdf>>()
 			.insert_resource(LoadedChunks::default())
+			.init_resource::<ChunkMaterialRegistry>()
 			// terrain
 			.insert_resource(terrain_chunk_config)
 			.insert_resource(terrain_resolution_config)
+			.init_resource::<ResolutionScaling<terrain::TerrainSdf>>()
+			// The camera flies fast, so only the next moments of its path are warmed
+			.insert_resource(ChunkPrewarm::<terrain::TerrainSdf>::default().with_horizon(0.25))
 			.insert_resource(terrain_sdf_resource)
+			.insert_resource(mesh_processors)
+			.insert_resource(mesh_holes)
+			.insert_resource(terrain_shadow_map)
 			// forest
-			.add_systems(Startup, (camera::setup_camera, setup_lighting, ui::setup_debug_ui))
+			.add_systems(
+				self.setup,
+				(
+					camera::setup_camera,
+					ui::setup_debug_ui,
+					ui::setup_stats_overlay,
+					ui::setup_boot_overlay,
+					tweak::setup_tweak_panel,
+					editor::load_placements,
+					terrain::setup_terrain_coverage,
+					terrain::log_rare_features,
+				),
+			)
+			.add_systems(OnEnter(WorldBootState::Playing), ui::hide_boot_overlay)
 			.add_systems(
 				Update,
 				(
-					camera::camera_controller,
-					manage_chunks::<terrain::TerrainSdf>,
+					(boot_world::<terrain::TerrainSdf>, ui::update_boot_overlay)
+						.chain()
+						.run_if(in_state(WorldBootState::Booting)),
+					// The camera waits for the ground around it to be generated before it moves
+					(
+						camera::camera_controller.run_if(in_state(WorldBootState::Playing)),
+						confine_to_world::<terrain::TerrainSdf>,
+						track_foliage_actors,
+						bend_foliage,
+					)
+						.chain(),
+					(
+						tweak::tweak_terrain_config,
+						tweak::rebuild_terrain_sdf,
+						scale_resolution::<terrain::TerrainSdf>,
+						track_camera_projection::<terrain::TerrainSdf>,
+						manage_chunks::<terrain::TerrainSdf>
+							.run_if(in_state(WorldBootState::Playing)),
+						prewarm_chunks::<terrain::TerrainSdf>,
+						queue_dirty_chunks::<terrain::TerrainSdf>,
+						queue_dirty_region_chunks::<terrain::TerrainSdf>,
+						regenerate_queued_chunks::<terrain::TerrainSdf>,
+						crossfade_chunks,
+						project_chunk_decals::<terrain::TerrainSdf>,
+						scatter_chunk_forests::<terrain::TerrainSdf>,
+						tag_chunk_features::<terrain::TerrainSdf>,
+						terrain::dusk_particles,
+						emit_ambient_particles::<terrain::TerrainSdf>,
+						drift_ambient_particles::<terrain::TerrainSdf>,
+					)
+						.chain(),
+					(
+						render_items::<Forest<EdgeMaterial, LeafMaterial>>,
+						fetch_meshes::<MeshHandle<SimpleTrunkSegment>, EdgeMaterial>,
+						fetch_meshes::<MeshHandle<NoisyBall>, LeafMaterial>,
+						fetch_meshes::<MeshHandle<CanopyCarpet>, LeafMaterial>,
+						budget_tree_leaves,
+					)
+						.chain(),
+					bake_terrain_shadows::<terrain::TerrainSdf>,
+					fade_distant_decals,
+					tweak::update_tweak_panel,
 					ui::update_coordinate_display,
-				),
+					(
+						ui::toggle_stats_overlay,
+						collect_world_stats::<terrain::TerrainSdf>,
+						ui::count_vegetation,
+						ui::update_stats_overlay,
+					)
+						.chain(),
+					debug::toggle_interval_debug,
+					debug::draw_interval_debug,
+					(
+						debug::toggle_gizmo_overlays,
+						terrain::sync_world_atlas,
+						debug::sync_region_gizmos,
+						draw_cascade_bounds::<terrain::TerrainSdf>,
+						draw_region_boundaries::<terrain::TerrainSdf>,
+						draw_tree_skeleton,
+					)
+						.chain(),
+					(
+						editor::editor_actions,
+						editor::edit_placements,
+						apply_world_edits,
+						editor::flatten_building_sites,
+						editor::sync_placement_decals,
+						editor::draw_placements,
+					)
+						.chain(),
+				)
+					.in_set(TerrainSystems),
 			);
-	}
-}
 
-fn setup_lighting(mut commands: Commands) {
-	// Ambient light - significantly increased to simulate global illumination
-	// This provides base lighting for all surfaces, including back faces
-	commands.insert_resource(AmbientLight {
-		color: Color::WHITE,
-		brightness: 2.0, // Much higher for better back-face illumination (simulates bounced light)
-		affects_lightmapped_meshes: true,
-	});
-
-	// Main directional light (sun) - primary light source
-	commands.spawn((
-		DirectionalLight { illuminance: 10000.0, shadows_enabled: true, ..default() },
-		Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -PI / 4.0, PI / 4.0, 0.0)),
-	));
-
-	// Fill light from opposite direction - reduces harsh shadows
-	commands.spawn((
-		DirectionalLight {
-			illuminance: 500.0,     // Increased fill light
-			shadows_enabled: false, // No shadows for fill light
-			..default()
-		},
-		Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, PI / 4.0, -PI / 4.0, 0.0)),
-	));
-
-	// Additional fill lights from sides for omnidirectional illumination
-	// Left side
-	commands.spawn((
-		DirectionalLight { illuminance: 500.0, shadows_enabled: false, ..default() },
-		Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, PI / 2.0, 0.0)),
-	));
-
-	// Right side
-	commands.spawn((
-		DirectionalLight { illuminance: 500.0, shadows_enabled: false, ..default() },
-		Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, -PI / 2.0, 0.0)),
-	));
-
-	// Top-down fill light
-	commands.spawn((
-		DirectionalLight { illuminance: 500.0, shadows_enabled: false, ..default() },
-		Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, 0.0)),
-	));
+		if let Some(teardown) = self.teardown {
+			app.add_systems(teardown, terrain::reset_terrain_scene);
+		}
+	}
 }
@@ -0,0 +1,164 @@
+//! Streaming test for forests scattered over terrain chunks.
+//!
+//! A headless app runs the chunk manager and forest scattering over flat ground, with the
+//! default grove noise seed, while a scripted camera flies out and back home. Every frame
+//! checks that the chunks are unique and that every tree belongs to a loaded chunk; once home,
+//! the entity count must match the count before the flight, so nothing leaks across unloads.
+
+use crate::forest::{scatter_chunk_forests, ChunkForest};
+use bevy::prelude::*;
+use engine::{
+	chunk::TerrainChunk,
+	manage_chunks,
+	sdf::Sdf,
+	shaders::{leaf_material::LeafMaterial, outline::EdgeMaterial},
+	ChunkConfig, ChunkMaterialRegistry, ChunkResolutionConfig, LoadedChunks, SdfResource,
+};
+use render_item::{
+	mesh::{fetch_meshes, handle::MeshHandle},
+	render_items, DispatchRenderItem, PartOfRenderItem,
+};
+use std::collections::HashSet;
+use vegetation_sdf::{
+	forest::{CanopyCarpet, Forest},
+	grove::GroveBuilder,
+	tree::{
+		chop::TreeTrunk,
+		meshes::{canopy::ball::NoisyBall, trunk::segment::SimpleTrunkSegment},
+	},
+};
+
+type TestForest = Forest<EdgeMaterial, LeafMaterial>;
+
+/// Chunks along the camera path before it turns back
+const PATH_STEPS: usize = 12;
+/// Distance the camera moves each frame
+const STEP: f32 = 6.0;
+
+/// A flat ground, off the chunk boundaries
+struct Ground;
+
+impl Sdf for Ground {
+	fn distance(&self, p: Vec3) -> f32 {
+		p.y - 2.0
+	}
+}
+
+fn streaming_app() -> App {
+	let grove = GroveBuilder::new(
+		MeshMaterial3d(Handle::<EdgeMaterial>::default()),
+		MeshMaterial3d(Handle::<LeafMaterial>::default()),
+	);
+	let forest = Forest::new(grove, MeshMaterial3d(Handle::default()));
+
+	let mut app = App::new();
+	app.add_plugins(AssetPlugin::default())
+		.init_asset::<Mesh>()
+		.init_asset::<EdgeMaterial>()
+		.init_asset::<LeafMaterial>()
+		.init_resource::<ChunkMaterialRegistry>()
+		.insert_resource(ChunkConfig::<Ground> {
+			min_size: Vec3::splat(8.0),
+			number_of_rings: 1,
+			grid_radius: 1,
+			grid_multiple_2: 1,
+			..default()
+		})
+		.insert_resource(ChunkResolutionConfig::<Ground> { base_res_2: 2, ..default() })
+		.insert_resource(SdfResource::new(Ground))
+		.insert_resource(LoadedChunks::default())
+		.insert_resource(ChunkForest::new(forest).with_max_tree_chunk_size(8.0))
+		.add_systems(
+			Update,
+			(
+				manage_chunks::<Ground>,
+				scatter_chunk_forests::<Ground>,
+				render_items::<TestForest>,
+				fetch_meshes::<MeshHandle<SimpleTrunkSegment>, EdgeMaterial>,
+				fetch_meshes::<MeshHandle<NoisyBall>, LeafMaterial>,
+				fetch_meshes::<MeshHandle<CanopyCarpet>, LeafMaterial>,
+			)
+				.chain(),
+		);
+	app
+}
+
+/// Checks the chunks and the vegetation on them, returning the number of standing trees.
+fn check_frame(app: &mut App, step: usize) -> usize {
+	let world = app.world_mut();
+
+	let mut keys = HashSet::new();
+	for chunk in world.query::<&TerrainChunk>().iter(world) {
+		let key = (chunk.chunk.origin.to_array().map(f32::to_bits), chunk.chunk.size.x.to_bits());
+		assert!(keys.insert(key), "Duplicate chunk at {} on step {step}", chunk.chunk.origin);
+	}
+
+	// Every forest hangs off a loaded chunk
+	let mut forests =
+		world.query_filtered::<(Entity, &ChildOf), With<DispatchRenderItem<TestForest>>>();
+	let forests: Vec<(Entity, Entity)> = forests
+		.iter(world)
+		.map(|(forest, child_of)| (forest, child_of.parent()))
+		.collect();
+	for (forest, chunk) in &forests {
+		assert!(
+			world.get::<TerrainChunk>(*chunk).is_some(),
+			"Forest {forest:?} outlived its chunk on step {step}"
+		);
+	}
+
+	// Every part, down to the fetched meshes, has a live owner
+	let mut parts = world.query::<(Entity, &PartOfRenderItem)>();
+	for (part, owner) in parts.iter(world) {
+		assert!(world.get_entity(owner.0).is_ok(), "{part:?} outlived its owner on step {step}");
+	}
+
+	// Every tree stands in the chunk of its forest
+	let mut trees =
+		world.query::<(&TreeTrunk<SimpleTrunkSegment, EdgeMaterial>, &PartOfRenderItem)>();
+	let mut count = 0;
+	for (trunk, owner) in trees.iter(world) {
+		let Some((_, chunk)) = forests.iter().find(|(forest, _)| *forest == owner.0) else {
+			panic!("Tree at {} isn't part of a forest on step {step}", trunk.base);
+		};
+		let Some(chunk) = world.get::<TerrainChunk>(*chunk) else {
+			panic!("Tree at {} has no chunk on step {step}", trunk.base);
+		};
+		let max = chunk.chunk.origin + chunk.chunk.size;
+		assert!(
+			trunk.base.cmpge(chunk.chunk.origin).all() && trunk.base.cmplt(max).all(),
+			"Tree at {} is outside its chunk at {} on step {step}",
+			trunk.base,
+			chunk.chunk.origin
+		);
+		count += 1;
+	}
+	count
+}
+
+#[test]
+fn test_forest_streaming_leaves_no_leaks() {
+	let mut app = streaming_app();
+	let camera = app.world_mut().spawn((Camera3d::default(), Transform::default())).id();
+
+	// Settle at home before taking the baseline
+	app.update();
+	app.update();
+	check_frame(&mut app, 0);
+	let baseline = app.world().entities().len();
+
+	let path: Vec<f32> =
+		(0..=PATH_STEPS).chain((0..PATH_STEPS).rev()).map(|i| i as f32 * STEP).collect();
+	let mut max_trees = 0;
+	for (step, x) in path.into_iter().enumerate() {
+		app.world_mut().entity_mut(camera).insert(Transform::from_xyz(x, 0.0, x / 2.0));
+		app.update();
+		max_trees = max_trees.max(check_frame(&mut app, step));
+	}
+	assert!(max_trees > 0, "No trees were scattered along the path");
+
+	// Home again, everything spawned along the way is gone
+	app.update();
+	check_frame(&mut app, usize::MAX);
+	assert_eq!(app.world().entities().len(), baseline);
+}
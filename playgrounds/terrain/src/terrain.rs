@@ -1,20 +1,57 @@
 // use crate::geography::FeatureRegistry;
-use crate::sdf::{Bounds, Difference, Ellipse3d, Sdf, SignUniformIntervals, TubeSdf};
+use crate::sdf::{Axis, Bounds, Difference, Ellipse3d, Sdf, SignUniformIntervals, TubeSdf};
+use bevy::math::bounding::Aabb3d;
 use bevy::prelude::*;
+use engine::{
+	shaders::outline::{Coverage, EdgeMaterial, Strata},
+	surface_height, AmbientParticles, AtlasKind, BootProgress, ChunkFeatureTagger, ChunkKind,
+	ChunkMaterialRegistry, ChunkRegenerationQueue, ChunkTag, EdgeFade, LightingPreset,
+	LoadedChunks, ParticleEmitter, SdfResource, WorldAtlas, WorldBoot, WorldBootState, WorldBounds,
+};
 use noise::Perlin;
 use terrain_sdf::{
+	climate::{Biome, ClimateModel},
+	preview::WorldPreview,
+	province::{ProvinceMap, ProvinceParams},
+	rare::RareFeatures,
 	region::affine::RegionAffineModulation,
 	region::branching::BranchingPlan,
+	region::flattening::RegionFlatteningModulation,
 	region::grading::RegionGradingModulation,
 	region::rounding::RegionRoundingModulation,
 	region::{CircleRegion, RectRegion, Region2D, RegionNoise},
-	PerlinTerrainSdf,
+	ModulationPriority, PerlinTerrainSdf,
 };
 
+/// Colors of the valley and road boundaries in the region overlay
+const VALLEY_COLOR: Color = Color::srgb(0.3, 0.6, 1.0);
+const ROAD_COLOR: Color = Color::srgb(1.0, 0.55, 0.15);
+const LAKE_COLOR: Color = Color::srgb(0.2, 0.85, 0.8);
+
+/// Half the width of the playground world, over which rare features are planned
+const RARE_FEATURE_EXTENT: f32 = 100.0;
+
 /// Resource containing the terrain SDF for runtime queries
 #[derive(Resource)]
+#[cfg_attr(feature = "reflect", derive(TypePath))]
 pub struct TerrainSdf {
 	pub sdf: Box<dyn Sdf>,
+	/// The valleys and roads modulating the terrain, with their overlay colors
+	pub regions: Vec<(Region2D, Color)>,
+	/// The valleys, roads, lakes and landmarks generated for the terrain
+	pub atlas: WorldAtlas,
+}
+
+impl TerrainSdf {
+	/// Builds the terrain, sunk toward the edge of the world when it has one
+	pub fn new(config: &TerrainConfig, bounds: Option<WorldBounds>, sites: &BuildingSites) -> Self {
+		let (sdf, regions, atlas) = create_terrain_sdf(config, sites);
+		let terrain = Self { sdf, regions: regions.clone(), atlas: atlas.clone() };
+		match bounds {
+			Some(bounds) => Self { sdf: Box::new(EdgeFade::new(terrain, bounds)), regions, atlas },
+			None => terrain,
+		}
+	}
 }
 
 impl Sdf for TerrainSdf {
@@ -26,15 +63,182 @@ impl Sdf for TerrainSdf {
 		self.sdf.sign_uniform_on_y(x, z)
 	}
 
+	fn sign_uniform_on_axis(&self, axis: Axis, a: f32, b: f32) -> SignUniformIntervals {
+		self.sdf.sign_uniform_on_axis(axis, a, b)
+	}
+
 	fn bounds(&self) -> Bounds {
 		self.sdf.bounds()
 	}
 }
 
-/// Create the terrain SDF with all modulations
-pub fn create_terrain_sdf(config: &TerrainConfig) -> Box<dyn Sdf> {
+/// The footprints of the placed buildings, which the terrain is flattened under.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct BuildingSites {
+	/// Footprints in X and Z, in the local space of the terrain SDF
+	pub footprints: Vec<Rect>,
+	/// Distance around a footprint over which the terrain returns to its own height
+	pub falloff: f32,
+}
+
+impl Default for BuildingSites {
+	fn default() -> Self {
+		Self { footprints: Vec::new(), falloff: 0.5 }
+	}
+}
+
+impl BuildingSites {
+	/// The region whose chunks change with a footprint, from bedrock to the top of the terrain.
+	pub fn dirty_region(&self, footprint: Rect, height_scale: f32) -> Aabb3d {
+		let area = footprint.inflate(self.falloff);
+		let height = height_scale.abs() * 4.0;
+		Aabb3d {
+			min: Vec3::new(area.min.x, -height, area.min.y).into(),
+			max: Vec3::new(area.max.x, height, area.max.y).into(),
+		}
+	}
+}
+
+/// Logs the rare features of the world, so the landmarks of a seed can be found.
+pub fn log_rare_features(config: Res<TerrainConfig>) {
+	let extent = Vec2::splat(RARE_FEATURE_EXTENT);
+	for feature in config.rare_features().features_in(-extent, extent) {
+		log::info!("Rare {} at {:?}", feature.stamp.name, feature.position);
+	}
+}
+
+/// Snows over the flat ground of the default terrain material, thickening up the highlands,
+/// and bands its cliffs with sandstone strata
+pub fn setup_terrain_coverage(
+	config: Res<TerrainConfig>,
+	registry: Res<ChunkMaterialRegistry>,
+	mut materials: ResMut<Assets<EdgeMaterial>>,
+) {
+	let snow = Coverage::snow().with_altitude(config.height_scale * 0.5, config.height_scale);
+	let strata = Strata::sandstone().with_layers(-config.height_scale, config.height_scale * 0.08);
+	for kind in [ChunkKind::Cascade, ChunkKind::Grid] {
+		let Some(handle) = registry.get(kind, ChunkTag::DEFAULT) else {
+			continue;
+		};
+		if let Some(material) = materials.get_mut(&handle) {
+			material.coverage = snow;
+			material.strata = strata;
+		}
+	}
+}
+
+/// The climate arranging the biomes of the world
+fn climate_model(config: &TerrainConfig) -> ClimateModel {
+	ClimateModel::new(config.seed.wrapping_add(7))
+		.with_lapse_rate(12.0 / config.height_scale.max(f32::EPSILON))
+}
+
+/// Tags each chunk with the biome of the climate where its column meets the ground
+pub fn biome_tagger(config: &TerrainConfig) -> ChunkFeatureTagger<TerrainSdf> {
+	let climate = climate_model(config);
+	ChunkFeatureTagger::new(move |sdf: &TerrainSdf, chunk| {
+		let center = chunk.origin + chunk.size / 2.0;
+		let bottom = chunk.origin.y;
+		let top = bottom + chunk.size.y;
+		let altitude = surface_height(sdf, center.xz(), bottom, top).unwrap_or(center.y);
+		vec![ChunkTag(climate.biome_at(center.x, center.z, altitude).name())]
+	})
+}
+
+/// Motes over the forests, fireflies over the forests and grassland, and flurries on the peaks
+pub fn ambient_particles(config: &TerrainConfig) -> AmbientParticles<TerrainSdf> {
+	let forests = [
+		Biome::Taiga,
+		Biome::TemperateForest,
+		Biome::TemperateRainforest,
+		Biome::TropicalForest,
+		Biome::TropicalRainforest,
+	]
+	.map(|biome| ChunkTag(biome.name()));
+	let mut meadows = forests.to_vec();
+	meadows.push(ChunkTag(Biome::Grassland.name()));
+
+	AmbientParticles::default()
+		.with_emitter(ParticleEmitter::motes(forests.to_vec()))
+		.with_emitter(ParticleEmitter::fireflies(meadows))
+		.with_emitter(ParticleEmitter::flurries(config.height_scale * 0.5))
+}
+
+/// Brings out the fireflies at golden hour, the darkest of the lighting presets
+pub fn dusk_particles(
+	preset: Res<LightingPreset>,
+	mut particles: ResMut<AmbientParticles<TerrainSdf>>,
+) {
+	if preset.is_changed() {
+		particles.night = *preset == LightingPreset::GoldenHour;
+	}
+}
+
+/// Rolling hills, rugged highlands and flat lowlands, varied around the configured terrain
+fn create_province_map(config: &TerrainConfig) -> ProvinceMap {
+	let hills = ProvinceParams::new(config.seed, config.height_scale)
+		.with_base_frequency(config.base_frequency);
+	let highlands = ProvinceParams::new(config.seed.wrapping_add(1), config.height_scale * 2.0)
+		.with_base_frequency(config.base_frequency * 0.6)
+		.with_offset(3.0);
+	let lowlands = ProvinceParams::new(config.seed.wrapping_add(2), config.height_scale * 0.4)
+		.with_base_frequency(config.base_frequency * 1.5)
+		.with_offset(-1.5);
+
+	ProvinceMap::new(config.seed, config.province_size, vec![hills, highlands, lowlands])
+		.with_falloff(config.province_falloff)
+}
+
+/// A biome-tinted thumbnail of the seed's provinces, for picking a world before meshing it
+pub fn world_preview(config: &TerrainConfig, preview: &WorldPreview) -> Image {
+	let sdf = PerlinTerrainSdf::new(config.seed, config.height_scale)
+		.with_base_frequency(config.base_frequency)
+		.with_provinces(create_province_map(config));
+	preview.render(&sdf, Some(&climate_model(config)))
+}
+
+/// Keeps the world atlas on the generated content of the current terrain.
+pub fn sync_world_atlas(sdf_resource: Res<SdfResource<TerrainSdf>>, mut atlas: ResMut<WorldAtlas>) {
+	if sdf_resource.is_changed() {
+		*atlas = sdf_resource.sdf.atlas.clone();
+		log::info!("World atlas lists {} generated features", atlas.len());
+	}
+}
+
+/// Forgets the chunks of the scene as it is torn down, so they are booted and loaded again
+/// when it is set up again.
+pub fn reset_terrain_scene(
+	mut loaded_chunks: ResMut<LoadedChunks>,
+	mut regeneration_queue: ResMut<ChunkRegenerationQueue<TerrainSdf>>,
+	mut boot: ResMut<WorldBoot<TerrainSdf>>,
+	mut progress: ResMut<BootProgress>,
+	mut next_state: ResMut<NextState<WorldBootState>>,
+) {
+	*loaded_chunks = LoadedChunks::default();
+	*regeneration_queue = ChunkRegenerationQueue::default();
+	*boot = WorldBoot::default();
+	*progress = BootProgress::default();
+	next_state.set(WorldBootState::Booting);
+}
+
+/// Registers a region in the atlas by its bounds.
+fn register_region(atlas: &mut WorldAtlas, kind: AtlasKind, name: String, region: &Region2D) {
+	let (min, max) = region.bounds();
+	atlas.register(kind, name, min, max);
+}
+
+/// Create the terrain SDF with all modulations, the regions of its valleys and roads, and an
+/// atlas of the valleys, roads, lakes, landmarks and building sites it generated
+pub fn create_terrain_sdf(
+	config: &TerrainConfig,
+	sites: &BuildingSites,
+) -> (Box<dyn Sdf>, Vec<(Region2D, Color)>, WorldAtlas) {
+	let mut atlas = WorldAtlas::new(config.seed);
+
 	// Create base terrain SDF
-	let mut sdf = PerlinTerrainSdf::new(config.seed, config.height_scale);
+	let mut sdf = PerlinTerrainSdf::new(config.seed, config.height_scale)
+		.with_base_frequency(config.base_frequency)
+		.with_provinces(create_province_map(config));
 
 	let big_valley_sdf = RegionAffineModulation::new(
 		Region2D::Rect(RectRegion {
@@ -42,7 +246,7 @@ pub fn create_terrain_sdf(config: &TerrainConfig) -> Box<dyn Sdf> {
 			half_extents: Vec2::new(90.0, 90.0),
 			round: 2.0,
 		}),
-		0.5,
+		config.valley_scale,
 		0.0,
 		10.0,
 		10.0,
@@ -51,13 +255,20 @@ pub fn create_terrain_sdf(config: &TerrainConfig) -> Box<dyn Sdf> {
 
 	let intersecting_big_valley_sdf = RegionAffineModulation::new(
 		Region2D::Circle(CircleRegion { center: Vec2::new(10.0, 70.0), radius: 80.0 }),
-		0.5,
-		-1.7,
+		config.valley_scale,
+		config.valley_offset,
 		10.0,
 		10.0,
 	)
 	.with_noise(RegionNoise { noise: Perlin::new(config.seed), frequency: 0.2, amplitude: 2.0 });
 
+	let mut regions = vec![(intersecting_big_valley_sdf.region.clone(), VALLEY_COLOR)];
+	register_region(
+		&mut atlas,
+		AtlasKind::Valley,
+		"intersecting valley".into(),
+		&intersecting_big_valley_sdf.region,
+	);
 	sdf.add_elevation_modulation(Box::new(intersecting_big_valley_sdf));
 
 	// branching regions
@@ -65,7 +276,14 @@ pub fn create_terrain_sdf(config: &TerrainConfig) -> Box<dyn Sdf> {
 
 	let modulations = branch_plan.generate_regions();
 
-	for modulation in modulations {
+	for (index, modulation) in modulations.into_iter().enumerate() {
+		register_region(
+			&mut atlas,
+			AtlasKind::Valley,
+			format!("valley {index}"),
+			&modulation.region,
+		);
+		regions.push((modulation.region.clone(), VALLEY_COLOR));
 		sdf.add_elevation_modulation(Box::new(modulation));
 	}
 
@@ -79,8 +297,11 @@ pub fn create_terrain_sdf(config: &TerrainConfig) -> Box<dyn Sdf> {
 		None,
 		0.4,
 		0.2,
-	);
+	)
+	.with_priority(ModulationPriority::Constraint);
 
+	register_region(&mut atlas, AtlasKind::Road, "main road".into(), &road_sdf.region);
+	regions.push((road_sdf.region.clone(), ROAD_COLOR));
 	sdf.add_elevation_modulation(Box::new(road_sdf));
 
 	let start_point = Vec2::new(0.0, 20.0);
@@ -103,8 +324,42 @@ pub fn create_terrain_sdf(config: &TerrainConfig) -> Box<dyn Sdf> {
 		0.1,
 	);
 
+	register_region(&mut atlas, AtlasKind::Road, "graded road".into(), &graded_road.region);
+	regions.push((graded_road.region.clone(), ROAD_COLOR));
 	sdf.add_elevation_modulation(Box::new(graded_road));
 
+	// Crater lakes sink a flattened bowl into the terrain around them
+	// and the other rare features are listed as landmarks
+	let extent = Vec2::splat(RARE_FEATURE_EXTENT);
+	for feature in config.rare_features().features_in(-extent, extent) {
+		let name = format!("{} {},{}", feature.stamp.name, feature.cell.x, feature.cell.y);
+		if feature.stamp.name != "crater_lake" {
+			atlas.register_point(AtlasKind::Landmark, name, feature.position);
+			continue;
+		}
+		let crater = RegionAffineModulation::new(
+			Region2D::Circle(CircleRegion {
+				center: feature.position,
+				radius: feature.stamp.radius,
+			}),
+			0.1,
+			-0.4,
+			0.1,
+			0.4,
+		)
+		.with_priority(ModulationPriority::Detail);
+		register_region(&mut atlas, AtlasKind::Lake, name, &crater.region);
+		regions.push((crater.region.clone(), LAKE_COLOR));
+		sdf.add_elevation_modulation(Box::new(crater));
+	}
+
+	// Building sites are flattened last, to the lowest corner of the ground shaped above
+	for (i, footprint) in sites.footprints.iter().enumerate() {
+		let site = RegionFlatteningModulation::footprint(&sdf, *footprint, sites.falloff);
+		register_region(&mut atlas, AtlasKind::Settlement, format!("building {i}"), &site.region);
+		sdf.add_elevation_modulation(Box::new(site));
+	}
+
 	// Create a large vertical tube to bore a hole through the terrain
 	// Position it near the origin, going from well below ground to well above
 	let tube_start = Vec3::new(-30.0, -1.0, -30.0); // Start deep below
@@ -131,19 +386,32 @@ pub fn create_terrain_sdf(config: &TerrainConfig) -> Box<dyn Sdf> {
 
 	let tube_sdf = TubeSdf::new(tube_start, tube_end, tube_ellipse)
 		.with_noise(Perlin::new(config.seed))
-		.with_noise_factor(0.4);
+		.with_noise_factor(config.tube_noise_factor);
 
 	// Use Difference to bore the hole (subtract tube from terrain)
-	Box::new(Difference::new(sdf, tube_sdf))
+	(Box::new(Difference::new(sdf, tube_sdf)), regions, atlas)
 }
 
 /// Configuration for terrain generation
-#[derive(Resource, Clone)]
+#[derive(Resource, Clone, Reflect)]
+#[reflect(Resource)]
 pub struct TerrainConfig {
 	pub seed: u32,
 	pub base_res_2: u8, // Full resolution vertices per chunk side
 	pub height_scale: f32,
 	pub use_volumetric: bool, // If true, use marching cubes; if false, use heightfield
+	/// Frequency of the first noise octave
+	pub base_frequency: f64,
+	/// Height scale inside the valleys
+	pub valley_scale: f32,
+	/// Height offset of the intersecting valley
+	pub valley_offset: f32,
+	/// Noise factor of the tube bored through the terrain
+	pub tube_noise_factor: f32,
+	/// Approximate size of the differently seeded provinces
+	pub province_size: f32,
+	/// Distance over which neighbouring provinces are blended
+	pub province_falloff: f32,
 }
 
 impl TerrainConfig {
@@ -153,6 +421,17 @@ impl TerrainConfig {
 			base_res_2: 7, // 128x128x128 voxels per chunk at full resolution
 			height_scale: 5.0,
 			use_volumetric: true, // Default to volumetric for true 3D terrain
+			base_frequency: 0.05,
+			valley_scale: 0.5,
+			valley_offset: -1.7,
+			tube_noise_factor: 0.4,
+			province_size: 600.0,
+			province_falloff: 60.0,
 		}
 	}
+
+	/// The rare landmarks of the seed, such as giant trees and crater lakes.
+	pub fn rare_features(&self) -> RareFeatures {
+		RareFeatures::with_default_stamps(self.seed.wrapping_add(13))
+	}
 }
@@ -0,0 +1,165 @@
+use crate::terrain::{BuildingSites, TerrainConfig, TerrainSdf};
+use bevy::{prelude::*, reflect::Struct};
+use engine::{Actions, ChunkConfig, InputAction, SdfResource, TerrainDirty};
+use std::sync::Arc;
+
+/// Debug panel that edits the numeric fields of the [TerrainConfig] through reflection.
+///
+/// Every change rebuilds the terrain SDF and regenerates the loaded chunks, nearest first.
+#[derive(Resource, Debug, Default)]
+pub struct TerrainTweakPanel {
+	pub enabled: bool,
+	/// Index of the selected field among the tweakable fields
+	pub selected: usize,
+}
+
+#[derive(Component)]
+pub struct TerrainTweakText;
+
+/// Names of the fields the panel can edit, in declaration order.
+fn tweakable_fields(config: &TerrainConfig) -> Vec<String> {
+	(0..config.field_len())
+		.filter(|&i| {
+			config.field_at(i).is_some_and(|field| {
+				field.represents::<f32>() || field.represents::<f64>() || field.represents::<u32>()
+			})
+		})
+		.filter_map(|i| config.name_at(i).map(str::to_string))
+		.collect()
+}
+
+/// Steps a numeric field up or down: floats by a tenth of their magnitude, integers by one.
+fn step_field(config: &mut TerrainConfig, name: &str, direction: f32) {
+	let Some(field) = config.field_mut(name) else {
+		return;
+	};
+	if let Some(value) = field.try_downcast_mut::<f32>() {
+		*value += direction * (value.abs() * 0.1).max(0.01);
+	} else if let Some(value) = field.try_downcast_mut::<f64>() {
+		*value += direction as f64 * (value.abs() * 0.1).max(0.001);
+	} else if let Some(value) = field.try_downcast_mut::<u32>() {
+		*value = if direction > 0.0 { value.saturating_add(1) } else { value.saturating_sub(1) };
+	}
+}
+
+pub fn setup_tweak_panel(mut commands: Commands) {
+	commands
+		.spawn((
+			Node {
+				position_type: PositionType::Absolute,
+				top: Val::Px(10.0),
+				right: Val::Px(10.0),
+				padding: UiRect::all(Val::Px(10.0)),
+				..default()
+			},
+			BackgroundColor(Color::hsla(201.0, 0.69, 0.32, 0.7)),
+			Visibility::Hidden,
+			TerrainTweakText,
+		))
+		.with_children(|parent| {
+			parent.spawn((
+				Text::new(""),
+				TextFont { font_size: 16.0, ..default() },
+				TextColor(Color::WHITE),
+			));
+		});
+}
+
+/// Selects and steps the config fields while the panel is open.
+pub fn tweak_terrain_config(
+	actions: Actions,
+	mut panel: ResMut<TerrainTweakPanel>,
+	mut config: ResMut<TerrainConfig>,
+) {
+	if actions.just_pressed(InputAction::ToggleTweakPanel) {
+		panel.enabled = !panel.enabled;
+	}
+	if !panel.enabled {
+		return;
+	}
+
+	let fields = tweakable_fields(&config);
+	if fields.is_empty() {
+		return;
+	}
+	if actions.just_pressed(InputAction::TweakNext) {
+		panel.selected = (panel.selected + 1) % fields.len();
+	}
+	if actions.just_pressed(InputAction::TweakPrevious) {
+		panel.selected = (panel.selected + fields.len() - 1) % fields.len();
+	}
+	panel.selected = panel.selected.min(fields.len() - 1);
+
+	let name = &fields[panel.selected];
+	if actions.just_pressed(InputAction::TweakIncrease) {
+		step_field(&mut config, name, 1.0);
+	} else if actions.just_pressed(InputAction::TweakDecrease) {
+		step_field(&mut config, name, -1.0);
+	}
+}
+
+pub fn update_tweak_panel(
+	panel: Res<TerrainTweakPanel>,
+	config: Res<TerrainConfig>,
+	mut panel_query: Query<(&mut Visibility, &Children), With<TerrainTweakText>>,
+	mut text_query: Query<&mut Text>,
+) {
+	if !panel.is_changed() && !config.is_changed() {
+		return;
+	}
+	let Ok((mut visibility, children)) = panel_query.single_mut() else {
+		return;
+	};
+	*visibility = if panel.enabled { Visibility::Visible } else { Visibility::Hidden };
+
+	let Some(mut text) = children.first().and_then(|&child| text_query.get_mut(child).ok()) else {
+		return;
+	};
+	let lines: Vec<String> = tweakable_fields(&config)
+		.into_iter()
+		.enumerate()
+		.map(|(i, name)| {
+			let marker = if i == panel.selected { ">" } else { " " };
+			let value = config.field(&name).map(|field| format!("{field:?}")).unwrap_or_default();
+			format!("{marker} {name}: {value}")
+		})
+		.collect();
+	text.0 = format!("Terrain (arrows to edit)\n{}", lines.join("\n"));
+}
+
+/// Rebuilds the terrain SDF after the config changes and marks the terrain dirty.
+pub fn rebuild_terrain_sdf(
+	config: Res<TerrainConfig>,
+	chunk_config: Res<ChunkConfig<TerrainSdf>>,
+	sites: Res<BuildingSites>,
+	mut terrain_sdf: ResMut<SdfResource<TerrainSdf>>,
+	mut dirty: MessageWriter<TerrainDirty>,
+) {
+	if !config.is_changed() || config.is_added() {
+		return;
+	}
+
+	terrain_sdf.sdf = Arc::new(TerrainSdf::new(&config, chunk_config.world_bounds(), &sites));
+	dirty.write(TerrainDirty);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_step_tweakable_fields() {
+		let mut config = TerrainConfig::new(0);
+		let fields = tweakable_fields(&config);
+		let has = |name: &str| fields.iter().any(|field| field == name);
+		assert!(has("height_scale") && has("seed"));
+		assert!(!has("use_volumetric") && !has("base_res_2"));
+
+		step_field(&mut config, "height_scale", 1.0);
+		assert!((config.height_scale - 5.5).abs() < 1e-6);
+		step_field(&mut config, "seed", -1.0);
+		assert_eq!(config.seed, 0);
+		step_field(&mut config, "base_frequency", -1.0);
+		assert!((config.base_frequency - 0.045).abs() < 1e-9);
+	}
+}
@@ -1,9 +1,40 @@
+use crate::forest::ChunkForest;
+use crate::terrain::TerrainSdf;
 use bevy::prelude::*;
-use engine::LoadedChunks;
+use engine::{
+	shaders::{leaf_material::LeafMaterial, outline::EdgeMaterial},
+	Actions, BootProgress, ChunkConfig, GenerationPool, InputAction, LoadedChunks,
+	ResolutionScaling, SdfResource, WorldStats,
+};
+use render_item::{DispatchRenderItem, PartOfRenderItem};
+use vegetation_sdf::{forest::Forest, tree::leaf_budget::LeafBudget};
 
 #[derive(Component)]
 pub struct CoordinateDisplay;
 
+/// The panel of world stats, shown while [WorldStats] are collected.
+#[derive(Component)]
+pub struct StatsOverlay;
+
+/// The text of the [StatsOverlay].
+#[derive(Component)]
+pub struct StatsText;
+
+/// The loading screen shown while the ground around the spawn point is generated.
+#[derive(Component)]
+pub struct BootOverlay;
+
+/// The text of the [BootOverlay].
+#[derive(Component)]
+pub struct BootText;
+
+/// The terrain resources the display reports on
+type TerrainResources<'w> = (
+	Res<'w, ChunkConfig<TerrainSdf>>,
+	Res<'w, SdfResource<TerrainSdf>>,
+	Option<Res<'w, ResolutionScaling<TerrainSdf>>>,
+);
+
 pub fn setup_debug_ui(mut commands: Commands) {
 	log::info!("Setting up debug UI");
 
@@ -28,12 +59,158 @@ pub fn setup_debug_ui(mut commands: Commands) {
 		});
 }
 
+pub fn setup_stats_overlay(mut commands: Commands) {
+	commands
+		.spawn((
+			Node {
+				position_type: PositionType::Absolute,
+				top: Val::Px(10.0),
+				right: Val::Px(10.0),
+				padding: UiRect::all(Val::Px(10.0)),
+				display: Display::None,
+				..default()
+			},
+			BackgroundColor(Color::hsla(201.0, 0.69, 0.2, 0.8)),
+			StatsOverlay,
+		))
+		.with_children(|parent| {
+			parent.spawn((
+				Text::new(""),
+				TextFont { font_size: 16.0, ..default() },
+				TextColor(Color::WHITE),
+				StatsText,
+			));
+		});
+}
+
+pub fn setup_boot_overlay(mut commands: Commands) {
+	commands
+		.spawn((
+			Node {
+				width: Val::Percent(100.0),
+				height: Val::Percent(100.0),
+				justify_content: JustifyContent::Center,
+				align_items: AlignItems::Center,
+				..default()
+			},
+			BackgroundColor(Color::hsla(201.0, 0.4, 0.1, 1.0)),
+			BootOverlay,
+		))
+		.with_children(|parent| {
+			parent.spawn((
+				Text::new("Generating world"),
+				TextFont { font_size: 28.0, ..default() },
+				TextColor(Color::WHITE),
+				BootText,
+			));
+		});
+}
+
+/// Shows how much of the ground around the spawn point is generated.
+pub fn update_boot_overlay(
+	progress: Res<BootProgress>,
+	mut text_query: Query<&mut Text, With<BootText>>,
+) {
+	if !progress.is_changed() {
+		return;
+	}
+	for mut text in &mut text_query {
+		text.0 = format!(
+			"Generating world {:.0}% ({} of {} chunks)",
+			progress.fraction() * 100.0,
+			progress.generated,
+			progress.total
+		);
+	}
+}
+
+/// Lifts the loading screen once play starts.
+pub fn hide_boot_overlay(mut commands: Commands, overlay_query: Query<Entity, With<BootOverlay>>) {
+	for overlay in &overlay_query {
+		commands.entity(overlay).despawn();
+	}
+}
+
+/// Shows or hides the stats overlay, collecting the stats only while it is shown.
+pub fn toggle_stats_overlay(
+	actions: Actions,
+	mut stats: ResMut<WorldStats>,
+	mut overlay_query: Query<&mut Node, With<StatsOverlay>>,
+) {
+	if !actions.just_pressed(InputAction::ToggleStatsOverlay) {
+		return;
+	}
+	stats.enabled = !stats.enabled;
+	for mut node in &mut overlay_query {
+		node.display = if stats.enabled { Display::Flex } else { Display::None };
+	}
+}
+
+/// Reports the vegetation the engine can't see to the [WorldStats].
+pub fn count_vegetation(
+	mut stats: ResMut<WorldStats>,
+	forest_query: Query<(), With<DispatchRenderItem<Forest<EdgeMaterial, LeafMaterial>>>>,
+	part_query: Query<(), With<PartOfRenderItem>>,
+	chunk_forest: Option<Res<ChunkForest>>,
+	leaf_budget: Option<Res<LeafBudget>>,
+) {
+	if !stats.enabled {
+		return;
+	}
+	stats.set_count("Forests", forest_query.iter().count());
+	stats.set_count("Vegetation entities", part_query.iter().count());
+	stats.set_count(
+		"Forest mesh cache",
+		chunk_forest.map_or(0, |chunk_forest| chunk_forest.cached_meshes()),
+	);
+	if let Some(leaf_budget) = leaf_budget {
+		stats.set_count("Leaves shown", leaf_budget.shown);
+		stats.set_count("Trees over the leaf budget", leaf_budget.bare_trees);
+	}
+}
+
+pub fn update_stats_overlay(
+	stats: Res<WorldStats>,
+	mut text_query: Query<&mut Text, With<StatsText>>,
+) {
+	if !stats.enabled {
+		return;
+	}
+	let Ok(mut text) = text_query.single_mut() else {
+		return;
+	};
+
+	text.0 = format!("Chunks loaded: {}", stats.loaded_chunks());
+	for (ring, count) in &stats.chunks_per_ring {
+		text.0.push_str(&format!("\n  ring {ring}: {count}"));
+	}
+	text.0.push_str(&format!(
+		"\nChunks skipped: {}\nChunks pinned: {}",
+		stats.skipped_chunks, stats.pinned_chunks
+	));
+	text.0.push_str(&format!(
+		"\nTerrain triangles: {}\nMesh assets: {}\nMesh memory: {:.1} MiB",
+		stats.terrain_triangles,
+		stats.mesh_assets,
+		stats.mesh_bytes as f64 / (1024.0 * 1024.0)
+	));
+	text.0.push_str(&format!(
+		"\nMesh holes: {} repaired, {} open",
+		stats.repaired_holes, stats.open_holes
+	));
+	for (name, count) in &stats.counts {
+		text.0.push_str(&format!("\n{name}: {count}"));
+	}
+}
+
 pub fn update_coordinate_display(
 	camera_query: Query<&Transform, (With<Camera3d>, Without<CoordinateDisplay>)>,
 	mut text_query: Query<&mut Text>,
 	coordinate_display_query: Query<Entity, With<CoordinateDisplay>>,
 	children_query: Query<&Children>,
 	loaded_chunks: Res<LoadedChunks>,
+	generation_pool: Option<Res<GenerationPool>>,
+	(chunk_config, terrain_sdf, scaling): TerrainResources,
 ) {
 	if let Ok(transform) = camera_query.single() {
 		let pos = transform.translation;
@@ -49,6 +226,33 @@ pub fn update_coordinate_display(
 							pos.z,
 							loaded_chunks.chunks.len()
 						);
+						if let Some(pool) = generation_pool.as_deref() {
+							let (near, far) = (pool.near.metrics(), pool.far.metrics());
+							text.0.push_str(&format!(
+								"\nGeneration near: {:.0}% of {} threads, far: {:.0}% of {} threads",
+								near.utilization() * 100.0,
+								near.threads,
+								far.utilization() * 100.0,
+								far.threads
+							));
+						}
+						if let Some(reduction) = scaling
+							.as_deref()
+							.map(ResolutionScaling::reduction)
+							.filter(|&reduction| reduction > 0)
+						{
+							text.0.push_str(&format!(
+								"\nResolution lowered by {reduction} to keep up"
+							));
+						}
+						let local = terrain_sdf.transform.to_local(pos);
+						if let Some(distance) =
+							chunk_config.world_bounds().and_then(|bounds| bounds.hint(local))
+						{
+							text.0.push_str(&format!(
+								"\nWorld edge ahead: {distance:.2}km, turn back"
+							));
+						}
 					}
 				}
 			}
@@ -1,5 +1,7 @@
 pub mod fillers;
 pub mod render;
+pub mod rooms;
+pub mod ruin;
 
 use bevy::prelude::*;
 use render_item::RenderItem;
@@ -116,6 +118,13 @@ impl<P: Partition, F: Floor> Complex<P, F> {
 		}
 	}
 
+	/// The ground the complex covers in X and Z, from its anchor across all of its steps.
+	pub fn footprint(&self) -> Rect {
+		let extent =
+			self.step_size * Vec3::new(self.step_count.0 as f32, 0.0, self.step_count.2 as f32);
+		Rect::from_corners(self.anchor.xz(), (self.anchor + extent).xz())
+	}
+
 	#[inline(always)]
 	pub fn insert_member(&mut self, member: ComplexMember<P, F>) {
 		match member {
@@ -25,10 +25,13 @@ impl<P: Partition, F: Floor> RenderItem for ComplexRenderer<P, F> {
 	) -> Vec<Entity> {
 		let mut entities = Vec::new();
 
+		// Walls are unit meshes centered on their transform, placed here by their lower corner
 		for (floor_coordinates, floor) in self.complex.floors.floors.iter() {
-			let transform = transform.with_translation(floor_coordinates.position).with_scale(
-				Vec3::new(self.complex.step_size.x, self.floor_thickness, self.complex.step_size.z),
-			);
+			let scale =
+				Vec3::new(self.complex.step_size.x, self.floor_thickness, self.complex.step_size.z);
+			let transform = transform
+				.with_translation(floor_coordinates.position + scale / 2.0)
+				.with_scale(scale);
 
 			entities.extend(floor.spawn_render_items(commands, cascade_chunk, transform));
 		}
@@ -48,12 +51,14 @@ impl<P: Partition, F: Floor> RenderItem for ComplexRenderer<P, F> {
 				partition_coordinates.end.x - partition_coordinates.start.x
 			};
 
+			let scale = Vec3::new(x_scale, y_scale, z_scale);
 			let transform = transform
 				.with_translation(
 					partition_coordinates.start
-						+ Vec3::new(0.0, self.complex.step_size.y / 2.0, 0.0),
+						+ Vec3::new(0.0, self.complex.step_size.y / 2.0, 0.0)
+						+ scale / 2.0,
 				)
-				.with_scale(Vec3::new(x_scale, y_scale, z_scale));
+				.with_scale(scale);
 
 			entities.extend(partition.spawn_render_items(commands, cascade_chunk, transform));
 		}
@@ -0,0 +1,367 @@
+use crate::complex::{Complex, Floor, FloorCoordinates, Partition, PartitionCoordinates};
+use bevy::prelude::*;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// A cell of a complex by its step indices, in X, level and Z.
+type Cell = (i32, i32, i32);
+
+/// The direction a partition runs in, which decides the cells on either side of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Run {
+	/// Runs along X, between the cells before and after it in Z
+	X,
+	/// Runs along Z, between the cells before and after it in X
+	Z,
+}
+
+/// A room of a complex: the floors on one level joined without partitions between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Room {
+	/// The level of the complex the room is on
+	pub level: usize,
+	/// The floors of the room, ordered by Z then X
+	pub floors: Vec<FloorCoordinates>,
+	/// The middle of the floor area, on the floor
+	pub center: Vec3,
+	/// The floor area of the room
+	pub area: f32,
+	/// Whether partitions close the room off all around, other than at its doorways.
+	/// Rooms that aren't are open to the outside or to a drop where a floor is missing.
+	pub enclosed: bool,
+}
+
+/// An opening in a partition, joining a room to another or to the outside.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Doorway {
+	/// The partition with the opening
+	pub coordinates: PartitionCoordinates,
+	/// The room on one side of the opening
+	pub from: usize,
+	/// The room on the other side, or None for the outside
+	pub to: Option<usize>,
+}
+
+impl Doorway {
+	/// The middle of the opening, at the bottom of the partition.
+	pub fn position(&self) -> Vec3 {
+		(self.coordinates.start + self.coordinates.end) / 2.0
+	}
+
+	/// The room on the other side of the opening from the given one.
+	pub fn other(&self, room: usize) -> Option<usize> {
+		if self.from == room {
+			self.to
+		} else {
+			Some(self.from)
+		}
+	}
+}
+
+/// The rooms of a [Complex] and the doorways between them, for navigation and for placing
+/// furniture in rooms.
+///
+/// Rooms are found level by level, by joining the floors of the level wherever there is no
+/// partition standing between them. Partitions stand on the floors of the level below theirs,
+/// as the fillers place them. A partition only separates two floors along its whole length, so
+/// broken ones leave a gap. Which partitions have openings is up to the caller, since the
+/// partitions themselves don't say, and those between two rooms or a room and the outside
+/// become doorways. Stairs are not followed between levels.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoomGraph {
+	pub rooms: Vec<Room>,
+	pub doorways: Vec<Doorway>,
+}
+
+impl RoomGraph {
+	/// Finds the rooms of the complex, with doorways at the partitions with openings.
+	pub fn new<P: Partition, F: Floor>(
+		complex: &Complex<P, F>,
+		is_opening: impl Fn(&PartitionCoordinates, &P) -> bool,
+	) -> Self {
+		let step = complex.step_size;
+		let cell_of = |position: Vec3| {
+			let cell = ((position - complex.anchor) / step).round();
+			(cell.x as i32, cell.y as i32, cell.z as i32)
+		};
+
+		// Partitions by the level of the floors they stand on, skipping any that don't span
+		// a whole step
+		let mut partitions: HashMap<(Cell, Run), (&PartitionCoordinates, &P)> = HashMap::new();
+		for (coordinates, partition) in &complex.partitions.partitions {
+			let span = (coordinates.end - coordinates.start) / step;
+			let run = if span.abs_diff_eq(Vec3::X, 1e-3) {
+				Run::X
+			} else if span.abs_diff_eq(Vec3::Z, 1e-3) {
+				Run::Z
+			} else {
+				continue;
+			};
+			let (x, level, z) = cell_of(coordinates.start);
+			partitions.insert(((x, level - 1, z), run), (coordinates, partition));
+		}
+
+		let mut cells: Vec<(Cell, &FloorCoordinates)> = complex
+			.floors
+			.floors
+			.keys()
+			.map(|coordinates| (cell_of(coordinates.position), coordinates))
+			.collect();
+		cells.sort_by_key(|((x, level, z), _)| (*level, *z, *x));
+		let floors: HashMap<Cell, &FloorCoordinates> = cells.iter().cloned().collect();
+
+		// Each neighbour of a cell, with the partition slot between them
+		let neighbours = |(x, level, z): Cell| {
+			[
+				((x + 1, level, z), ((x + 1, level, z), Run::Z)),
+				((x - 1, level, z), ((x, level, z), Run::Z)),
+				((x, level, z + 1), ((x, level, z + 1), Run::X)),
+				((x, level, z - 1), ((x, level, z), Run::X)),
+			]
+		};
+
+		let mut graph = RoomGraph::default();
+		let mut room_of: HashMap<Cell, usize> = HashMap::new();
+		let mut openings: BTreeMap<(Cell, Run), (Cell, Cell)> = BTreeMap::new();
+		for (start, _) in &cells {
+			if room_of.contains_key(start) {
+				continue;
+			}
+			let index = graph.rooms.len();
+			let mut room_cells = Vec::new();
+			let mut enclosed = true;
+			let mut queue = VecDeque::from([*start]);
+			room_of.insert(*start, index);
+			while let Some(cell) = queue.pop_front() {
+				room_cells.push(cell);
+				for (neighbour, slot) in neighbours(cell) {
+					match partitions.get(&slot) {
+						None if floors.contains_key(&neighbour) => {
+							if !room_of.contains_key(&neighbour) {
+								room_of.insert(neighbour, index);
+								queue.push_back(neighbour);
+							}
+						}
+						None => enclosed = false,
+						Some((coordinates, partition)) if is_opening(*coordinates, *partition) => {
+							openings.insert(slot, (cell, neighbour));
+						}
+						Some(_) => {}
+					}
+				}
+			}
+
+			room_cells.sort_by_key(|(x, _, z)| (*z, *x));
+			let room_floors: Vec<FloorCoordinates> = room_cells
+				.iter()
+				.filter_map(|cell| floors.get(cell).map(|coordinates| (*coordinates).clone()))
+				.collect();
+			let center = room_floors
+				.iter()
+				.map(|coordinates| coordinates.position + Vec3::new(step.x, 0.0, step.z) / 2.0)
+				.sum::<Vec3>()
+				/ room_floors.len().max(1) as f32;
+			graph.rooms.push(Room {
+				level: start.1.max(0) as usize,
+				area: room_floors.len() as f32 * (step.x * step.z).abs(),
+				floors: room_floors,
+				center,
+				enclosed,
+			});
+		}
+
+		for (slot, (cell, neighbour)) in openings {
+			let (Some(from), to) = (room_of.get(&cell), room_of.get(&neighbour)) else {
+				continue;
+			};
+			if to == Some(from) {
+				continue;
+			}
+			let Some((coordinates, _)) = partitions.get(&slot) else {
+				continue;
+			};
+			graph.doorways.push(Doorway {
+				coordinates: (*coordinates).clone(),
+				from: *from,
+				to: to.copied(),
+			});
+		}
+
+		graph
+	}
+
+	/// The doorways of a room.
+	pub fn doorways_of(&self, room: usize) -> impl Iterator<Item = &Doorway> {
+		self.doorways
+			.iter()
+			.filter(move |doorway| doorway.from == room || doorway.to == Some(room))
+	}
+
+	/// The rooms a room has doorways to, in order and without repeats.
+	pub fn neighbours(&self, room: usize) -> Vec<usize> {
+		let mut neighbours: Vec<usize> =
+			self.doorways_of(room).filter_map(|doorway| doorway.other(room)).collect();
+		neighbours.sort_unstable();
+		neighbours.dedup();
+		neighbours
+	}
+
+	/// The doorways that lead outside.
+	pub fn exits(&self) -> impl Iterator<Item = &Doorway> {
+		self.doorways.iter().filter(|doorway| doorway.to.is_none())
+	}
+
+	/// The room whose floor a position stands on, by the step size of its complex.
+	pub fn room_at(&self, position: Vec3, step_size: Vec3) -> Option<usize> {
+		self.rooms.iter().position(|room| {
+			room.floors.iter().any(|coordinates| {
+				let offset = (position - coordinates.position) / step_size;
+				(0.0..1.0).contains(&offset.x)
+					&& (0.0..1.0).contains(&offset.y)
+					&& (0.0..1.0).contains(&offset.z)
+			})
+		})
+	}
+
+	/// The doorways to pass through, in order, to get from one room to another through the
+	/// fewest of them, or None when there is no way through.
+	pub fn route(&self, from: usize, to: usize) -> Option<Vec<&Doorway>> {
+		if from >= self.rooms.len() || to >= self.rooms.len() {
+			return None;
+		}
+		let mut came_through: Vec<Option<usize>> = vec![None; self.rooms.len()];
+		let mut visited = vec![false; self.rooms.len()];
+		visited[from] = true;
+		let mut queue = VecDeque::from([from]);
+		while let Some(room) = queue.pop_front() {
+			if room == to {
+				break;
+			}
+			for (index, doorway) in self.doorways.iter().enumerate() {
+				if doorway.from != room && doorway.to != Some(room) {
+					continue;
+				}
+				let Some(next) = doorway.other(room) else {
+					continue;
+				};
+				if !visited[next] {
+					visited[next] = true;
+					came_through[next] = Some(index);
+					queue.push_back(next);
+				}
+			}
+		}
+		if !visited[to] {
+			return None;
+		}
+
+		let mut route = Vec::new();
+		let mut room = to;
+		while let Some(index) = came_through[room] {
+			let doorway = &self.doorways[index];
+			route.push(doorway);
+			room = doorway.other(room).unwrap_or(from);
+		}
+		route.reverse();
+		Some(route)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::meshes::walls::wall::Wall;
+
+	type StoneComplex = Complex<Wall<StandardMaterial>, Wall<StandardMaterial>>;
+
+	fn wall() -> Wall<StandardMaterial> {
+		Wall::new(MeshMaterial3d(Handle::default()))
+	}
+
+	/// A partition on the floors of level 0, from a cell corner along a whole step.
+	fn partition(x: f32, z: f32, along_x: bool) -> PartitionCoordinates {
+		let start = Vec3::new(x, 1.0, z);
+		let end = if along_x { start + Vec3::X } else { start + Vec3::Z };
+		PartitionCoordinates { start, end }
+	}
+
+	/// A 3 by 1 strip of floors walled all around, with walls between each of them.
+	fn strip() -> StoneComplex {
+		let mut complex = Complex::new(Vec3::ZERO, Vec3::ONE, (3, 2, 1));
+		for x in 0..3 {
+			let position = Vec3::new(x as f32, 0.0, 0.0);
+			complex.floors.floors.insert(FloorCoordinates { position }, wall());
+			for z in [0.0, 1.0] {
+				complex.partitions.partitions.insert(partition(x as f32, z, true), wall());
+			}
+		}
+		for x in 0..4 {
+			complex.partitions.partitions.insert(partition(x as f32, 0.0, false), wall());
+		}
+		complex
+	}
+
+	#[test]
+	fn test_rooms_are_joined_by_doorways() {
+		let mut complex = strip();
+		let doors = [partition(1.0, 0.0, false), partition(3.0, 0.0, false)];
+		let is_door = |coordinates: &PartitionCoordinates, _: &Wall<StandardMaterial>| {
+			doors.contains(coordinates)
+		};
+
+		// Three closed rooms, the first two joined, the last opening outside
+		let graph = RoomGraph::new(&complex, is_door);
+		assert_eq!(graph.rooms.len(), 3);
+		assert!(graph.rooms.iter().all(|room| room.enclosed && room.area == 1.0));
+		assert_eq!(graph.doorways.len(), 2);
+		assert_eq!(graph.neighbours(0), vec![1]);
+		assert!(graph.neighbours(2).is_empty());
+		assert_eq!(graph.exits().count(), 1);
+		assert_eq!(graph.room_at(Vec3::new(2.5, 0.2, 0.5), Vec3::ONE), Some(2));
+		assert_eq!(graph.room_at(Vec3::new(5.0, 0.2, 0.5), Vec3::ONE), None);
+		assert_eq!(graph.route(0, 1).map(|route| route.len()), Some(1));
+		assert_eq!(graph.route(0, 2), None);
+
+		// Taking a wall down joins its rooms into one
+		complex.partitions.partitions.remove(&partition(2.0, 0.0, false));
+		let graph = RoomGraph::new(&complex, is_door);
+		assert_eq!(graph.rooms.len(), 2);
+		assert_eq!(graph.rooms[1].area, 2.0);
+		assert_eq!(graph.rooms[1].center, Vec3::new(2.0, 0.0, 0.5));
+		let route = graph.route(0, 1).unwrap_or_default();
+		assert_eq!(route.len(), 1);
+		assert_eq!(route[0].position(), Vec3::new(1.0, 1.0, 0.5));
+
+		// As does a broken wall, and a missing outer wall opens the room up
+		complex.partitions.partitions.remove(&partition(1.0, 0.0, false));
+		complex.partitions.partitions.insert(
+			PartitionCoordinates { start: Vec3::new(1.0, 1.0, 0.0), end: Vec3::new(1.0, 1.0, 0.4) },
+			wall(),
+		);
+		complex.partitions.partitions.remove(&partition(0.0, 0.0, false));
+		let graph = RoomGraph::new(&complex, is_door);
+		assert_eq!(graph.rooms.len(), 1);
+		assert_eq!(graph.rooms[0].floors.len(), 3);
+		assert!(!graph.rooms[0].enclosed);
+		assert_eq!(graph.exits().count(), 1);
+	}
+
+	#[test]
+	fn test_levels_have_their_own_rooms() {
+		let mut complex = Complex::new(Vec3::ZERO, Vec3::ONE, (2, 2, 2));
+		for y in 0..2 {
+			for z in 0..2 {
+				for x in 0..2 {
+					let position = Vec3::new(x as f32, y as f32, z as f32);
+					complex.floors.floors.insert(FloorCoordinates { position }, wall());
+				}
+			}
+		}
+
+		let graph = RoomGraph::new(&complex, |_, _| false);
+		assert_eq!(graph.rooms.len(), 2);
+		assert_eq!(graph.rooms.iter().map(|room| room.level).collect::<Vec<_>>(), vec![0, 1]);
+		assert!(graph.rooms.iter().all(|room| room.area == 4.0 && !room.enclosed));
+		assert!(graph.doorways.is_empty());
+		assert_eq!(graph.room_at(Vec3::new(0.5, 1.5, 1.5), Vec3::ONE), Some(1));
+	}
+}
@@ -0,0 +1,346 @@
+use crate::complex::{
+	render::ComplexRenderer, Complex, Floor, FloorCoordinates, Partition, PartitionCoordinates,
+};
+use bevy::prelude::*;
+use chunk::cascade::CascadeChunk;
+use noise::{NoiseFn, Perlin};
+use render_item::RenderItem;
+use std::collections::HashMap;
+
+/// Ages a generated [Complex] into a ruin, so ruins come from the same fillers as intact
+/// buildings.
+///
+/// One corner of the complex collapses, worst toward the top, sections of the roof fall in,
+/// and a share of everything else decays. Partitions left without the floors below them fall
+/// too, and some of those still standing are broken off short. What falls piles up as rubble
+/// on the ground below. The same seed always ruins a complex the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ruinifier {
+	pub seed: u32,
+	/// Chance of any member falling, on top of the collapse and the roof
+	pub decay: f32,
+	/// Share of the diagonal of the footprint, from the collapsed corner, within which
+	/// members fall
+	pub collapse: f32,
+	/// Share of the roof that falls in, in patches
+	pub roof_loss: f32,
+	/// Chance of a standing partition being broken off short
+	pub breakage: f32,
+	/// Chance of a fallen member leaving rubble on the ground
+	pub rubble: f32,
+}
+
+impl Default for Ruinifier {
+	fn default() -> Self {
+		Self { seed: 0, decay: 0.1, collapse: 0.4, roof_loss: 0.5, breakage: 0.3, rubble: 0.6 }
+	}
+}
+
+/// A pile of rubble on the ground of a ruin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RubblePile {
+	/// Center of the base of the pile
+	pub position: Vec3,
+	/// Width of the pile, which grows with the members fallen onto it
+	pub size: f32,
+}
+
+impl Ruinifier {
+	pub fn new(seed: u32) -> Self {
+		Self { seed, ..Default::default() }
+	}
+
+	pub fn with_decay(mut self, decay: f32) -> Self {
+		self.decay = decay.clamp(0.0, 1.0);
+		self
+	}
+
+	pub fn with_collapse(mut self, collapse: f32) -> Self {
+		self.collapse = collapse.max(0.0);
+		self
+	}
+
+	pub fn with_roof_loss(mut self, roof_loss: f32) -> Self {
+		self.roof_loss = roof_loss.clamp(0.0, 1.0);
+		self
+	}
+
+	pub fn with_breakage(mut self, breakage: f32) -> Self {
+		self.breakage = breakage.clamp(0.0, 1.0);
+		self
+	}
+
+	pub fn with_rubble(mut self, rubble: f32) -> Self {
+		self.rubble = rubble.clamp(0.0, 1.0);
+		self
+	}
+
+	/// Ruins the complex, returning what is left standing and the rubble piled around it.
+	pub fn ruin<P: Partition, F: Floor>(
+		&self,
+		mut complex: Complex<P, F>,
+	) -> (Complex<P, F>, Vec<RubblePile>) {
+		let corner = self.collapsed_corner(&complex);
+		let diagonal = complex.footprint().size().length().max(f32::EPSILON);
+		let height = (complex.step_size.y * complex.step_count.1 as f32).max(f32::EPSILON);
+		let ground = complex.anchor.y;
+		let roof_noise = Perlin::new(self.seed);
+
+		// How likely a member is to fall with the collapsed corner
+		let collapse_chance = |center: Vec3| {
+			if self.collapse <= 0.0 {
+				return 0.0;
+			}
+			let t = center.xz().distance(corner) / diagonal / self.collapse;
+			let h = (center.y - ground) / height;
+			((1.0 - t) * (0.5 + h)).clamp(0.0, 1.0)
+		};
+
+		// The roof of each column is its highest floor
+		let mut roofs: HashMap<(u32, u32), f32> = HashMap::new();
+		for coordinates in complex.floors.floors.keys() {
+			let roof = roofs.entry(column(coordinates.position)).or_insert(f32::NEG_INFINITY);
+			*roof = roof.max(coordinates.position.y);
+		}
+
+		let mut fallen = Vec::new();
+		let floors: Vec<FloorCoordinates> = complex.floors.floors.keys().cloned().collect();
+		for coordinates in floors {
+			let center = floor_center(&complex, &coordinates);
+			let is_roof = coordinates.position.y > ground
+				&& roofs.get(&column(coordinates.position)) == Some(&coordinates.position.y);
+			let roof_roll = roof_noise.get([center.x as f64 * 0.15, center.z as f64 * 0.15]) as f32;
+			let falls = roll(self.seed, center, 0) < self.decay.max(collapse_chance(center))
+				|| (is_roof && roof_roll * 0.5 + 0.5 < self.roof_loss);
+			if falls {
+				complex.floors.floors.remove(&coordinates);
+				fallen.push(center);
+			}
+		}
+
+		let partitions: Vec<PartitionCoordinates> =
+			complex.partitions.partitions.keys().cloned().collect();
+		for coordinates in partitions {
+			let center = (coordinates.start + coordinates.end) / 2.0;
+			let unsupported = coordinates.start.y > ground
+				&& complex.partition_to_floors_below(&coordinates).is_empty();
+			if unsupported || roll(self.seed, center, 1) < self.decay.max(collapse_chance(center)) {
+				complex.partitions.partitions.remove(&coordinates);
+				fallen.push(center);
+			} else if roll(self.seed, center, 2) < self.breakage {
+				// Broken off somewhere along its length
+				let Some(partition) = complex.partitions.partitions.remove(&coordinates) else {
+					continue;
+				};
+				let keep = 0.3 + 0.5 * roll(self.seed, center, 3);
+				let end = coordinates.start + (coordinates.end - coordinates.start) * keep;
+				complex
+					.partitions
+					.partitions
+					.insert(PartitionCoordinates { start: coordinates.start, end }, partition);
+			}
+		}
+
+		let piles = self.pile_rubble(&complex, &fallen);
+		(complex, piles)
+	}
+
+	/// The corner of the footprint that collapses.
+	fn collapsed_corner<P: Partition, F: Floor>(&self, complex: &Complex<P, F>) -> Vec2 {
+		let footprint = complex.footprint();
+		match roll(self.seed, Vec3::ZERO, 4) {
+			r if r < 0.25 => footprint.min,
+			r if r < 0.5 => Vec2::new(footprint.max.x, footprint.min.y),
+			r if r < 0.75 => Vec2::new(footprint.min.x, footprint.max.y),
+			_ => footprint.max,
+		}
+	}
+
+	/// Piles the rubble of the fallen members on the ground below them, one pile per column.
+	fn pile_rubble<P: Partition, F: Floor>(
+		&self,
+		complex: &Complex<P, F>,
+		fallen: &[Vec3],
+	) -> Vec<RubblePile> {
+		let step = complex.step_size.x.min(complex.step_size.z).abs();
+		let last_cell = Vec2::new(complex.step_count.0 as f32, complex.step_count.2 as f32) - 1.0;
+		let mut columns: HashMap<(i32, i32), (Vec2, usize)> = HashMap::new();
+		for center in fallen {
+			if roll(self.seed, *center, 5) >= self.rubble {
+				continue;
+			}
+			let cell = ((center.xz() - complex.anchor.xz()) / complex.step_size.xz())
+				.floor()
+				.clamp(Vec2::ZERO, last_cell.max(Vec2::ZERO));
+			let (position, count) = columns
+				.entry((cell.x as i32, cell.y as i32))
+				.or_insert((complex.anchor.xz() + (cell + 0.5) * complex.step_size.xz(), 0));
+			*position = position.lerp(center.xz(), 0.5 / (*count + 1) as f32);
+			*count += 1;
+		}
+
+		let mut piles: Vec<RubblePile> = columns
+			.into_values()
+			.map(|(position, count)| RubblePile {
+				position: Vec3::new(position.x, complex.anchor.y, position.y),
+				size: step * 0.3 * (count as f32).cbrt(),
+			})
+			.collect();
+		piles.sort_by(|a, b| {
+			a.position
+				.x
+				.total_cmp(&b.position.x)
+				.then(a.position.z.total_cmp(&b.position.z))
+		});
+		piles
+	}
+}
+
+/// A ruined complex with its rubble, spawning the rubble as the given render item.
+///
+/// The rubble item is spawned once per pile, scaled to the pile and standing on the ground,
+/// so a unit rock reads as a heap of stones.
+#[derive(Debug, Clone)]
+pub struct Ruin<P: Partition, F: Floor, R: RenderItem> {
+	renderer: ComplexRenderer<P, F>,
+	rubble: R,
+	pub piles: Vec<RubblePile>,
+}
+
+impl<P: Partition, F: Floor, R: RenderItem> Ruin<P, F, R> {
+	/// Ruins the complex with the ruinifier.
+	pub fn new(complex: Complex<P, F>, ruinifier: &Ruinifier, rubble: R) -> Self {
+		let (complex, piles) = ruinifier.ruin(complex);
+		Self { renderer: ComplexRenderer::new(complex), rubble, piles }
+	}
+}
+
+impl<P: Partition, F: Floor, R: RenderItem> RenderItem for Ruin<P, F, R> {
+	fn spawn_render_items(
+		&self,
+		commands: &mut Commands,
+		cascade_chunk: &CascadeChunk,
+		transform: Transform,
+	) -> Vec<Entity> {
+		let mut entities = self.renderer.spawn_render_items(commands, cascade_chunk, transform);
+
+		for pile in &self.piles {
+			// Squat, so the heap spreads more than it stands
+			let scale = Vec3::new(pile.size, pile.size * 0.5, pile.size);
+			let transform = transform
+				.with_translation(pile.position + Vec3::Y * scale.y / 2.0)
+				.with_scale(scale);
+			entities.extend(self.rubble.spawn_render_items(commands, cascade_chunk, transform));
+		}
+
+		entities
+	}
+}
+
+/// The column of the complex a position is in, by its coordinates in X and Z.
+fn column(position: Vec3) -> (u32, u32) {
+	(position.x.to_bits(), position.z.to_bits())
+}
+
+/// The center of a floor, which is placed by its lower corner.
+fn floor_center<P: Partition, F: Floor>(
+	complex: &Complex<P, F>,
+	coordinates: &FloorCoordinates,
+) -> Vec3 {
+	coordinates.position + Vec3::new(complex.step_size.x, 0.0, complex.step_size.z) / 2.0
+}
+
+/// Hash of a position, the seed and a salt into 0 to 1, so each kind of damage rolls
+/// independently for each member.
+fn roll(seed: u32, position: Vec3, salt: u32) -> f32 {
+	let mut hash = position.x.to_bits().wrapping_mul(0x8da6_b343)
+		^ position.y.to_bits().wrapping_mul(0xd816_3841)
+		^ position.z.to_bits().wrapping_mul(0xcb1a_b31f)
+		^ seed.wrapping_mul(0x1656_67b1)
+		^ salt.wrapping_mul(0x27d4_eb2d);
+	hash ^= hash >> 13;
+	hash = hash.wrapping_mul(0x5bd1_e995);
+	hash ^= hash >> 15;
+	hash as f32 / u32::MAX as f32
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::complex::{ComplexCoordinates, ComplexMember, Filler};
+	use crate::meshes::walls::wall::Wall;
+
+	type StoneComplex = Complex<Wall<StandardMaterial>, Wall<StandardMaterial>>;
+
+	/// Fills every floor, and every partition with the floors below it.
+	struct Solid;
+
+	impl Filler<Wall<StandardMaterial>, Wall<StandardMaterial>> for Solid {
+		fn fill(
+			&mut self,
+			complex: &mut StoneComplex,
+			coordinates: ComplexCoordinates,
+		) -> Option<ComplexMember<Wall<StandardMaterial>, Wall<StandardMaterial>>> {
+			let wall = Wall::new(MeshMaterial3d(Handle::default()));
+			match coordinates {
+				ComplexCoordinates::Floor(coordinates) => {
+					Some(ComplexMember::Floor(coordinates, wall))
+				}
+				ComplexCoordinates::Partition(coordinates) => {
+					(!complex.partition_to_floors_below(&coordinates).is_empty())
+						.then_some(ComplexMember::Partition(coordinates, wall))
+				}
+			}
+		}
+	}
+
+	fn building() -> StoneComplex {
+		let mut complex = Complex::new(Vec3::ZERO, Vec3::new(4.0, 2.0, 4.0), (6, 4, 6));
+		complex.fill_canonical_members(&mut Solid);
+		complex
+	}
+
+	#[test]
+	fn test_ruins_fall_into_rubble() {
+		let intact = building();
+		let ruinifier = Ruinifier::new(7);
+		let (ruin, piles) = ruinifier.ruin(intact.clone());
+		let count = |complex: &StoneComplex| {
+			(complex.floors.floors.len(), complex.partitions.partitions.len())
+		};
+		let (floors, partitions) = count(&ruin);
+		assert!(floors < intact.floors.floors.len());
+		assert!(partitions < intact.partitions.partitions.len());
+		assert!(!piles.is_empty());
+
+		// Piles lie on the ground within the footprint
+		let footprint = intact.footprint();
+		for pile in &piles {
+			assert_eq!(pile.position.y, intact.anchor.y);
+			assert!(footprint.contains(pile.position.xz()), "{pile:?}");
+		}
+
+		// Every standing partition stands on its floors and no longer than it was
+		for coordinates in ruin.partitions.partitions.keys() {
+			assert!(!ruin.partition_to_floors_below(coordinates).is_empty());
+			assert!(coordinates.end.distance(coordinates.start) <= 4.0 + 1e-5);
+		}
+
+		// The same seed ruins the complex the same way, and another seed differently
+		let (again, again_piles) = ruinifier.ruin(intact.clone());
+		assert_eq!(count(&again), count(&ruin));
+		assert_eq!(again_piles, piles);
+		let (other, _) = Ruinifier::new(8).ruin(intact.clone());
+		assert!(other.floors.floors.keys().any(|key| !ruin.floors.floors.contains_key(key)));
+
+		// Nothing falls from a ruinifier without damage
+		let untouched = Ruinifier::new(7)
+			.with_decay(0.0)
+			.with_collapse(0.0)
+			.with_roof_loss(0.0)
+			.with_breakage(0.0);
+		let (standing, rubble) = untouched.ruin(intact.clone());
+		assert_eq!(count(&standing), count(&intact));
+		assert!(rubble.is_empty());
+	}
+}
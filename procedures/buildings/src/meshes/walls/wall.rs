@@ -1,10 +1,10 @@
 use crate::complex::{Floor, Partition};
-use bevy::prelude::*;
+use bevy::{math::bounding::Aabb3d, prelude::*};
 use chunk::cascade::CascadeChunk;
 use render_item::{
 	mesh::{
 		cache::handle::map::HandleMap, handle::MeshHandle, IdentifiedMesh, MeshBuilder,
-		MeshDispatch, MeshId,
+		MeshDispatch, MeshId, MeshSpace,
 	},
 	NormalizeChunk, RenderItem,
 };
@@ -23,7 +23,7 @@ impl WallMesh {
 
 impl NormalizeChunk for WallMesh {
 	fn normalize_chunk(&self, cascade_chunk: &CascadeChunk) -> CascadeChunk {
-		CascadeChunk::unit_3d_center_chunk().with_res_2(cascade_chunk.res_2)
+		CascadeChunk::unit_3d_center_chunk().with_axis_res_2(cascade_chunk.res_2)
 	}
 }
 
@@ -32,12 +32,20 @@ impl IdentifiedMesh for WallMesh {
 		let debug_string = format!("{:?}", self);
 		MeshId::new(debug_string)
 	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		MeshSpace::Unit
+	}
 }
 
 impl MeshBuilder for WallMesh {
 	fn build_mesh_impl(&self, _cascade_chunk: &CascadeChunk) -> Option<Mesh> {
 		Some(Mesh::from(Cuboid::new(1.0, 1.0, 1.0)))
 	}
+
+	fn local_bounds(&self) -> Aabb3d {
+		Aabb3d::new(Vec3::ZERO, Vec3::splat(0.5))
+	}
 }
 
 #[derive(Component, Clone)]
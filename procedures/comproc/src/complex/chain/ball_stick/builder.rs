@@ -2,7 +2,8 @@ use crate::noise::config::NoiseConfig;
 use bevy::prelude::*;
 use noise::NoiseFn;
 use noise::Seedable;
-use std::collections::{HashMap, HashSet, VecDeque};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::hash::Hasher;
@@ -26,6 +27,8 @@ pub struct BallStickBuilder<
 	pub splitting_coefficient: f32,
 	pub min_segment_length: f32,
 	pub max_segment_length: f32,
+	/// Most nodes a build grows; deeper levels are truncated once it is reached
+	pub max_nodes: Option<usize>,
 }
 
 impl<
@@ -49,6 +52,7 @@ impl<
 			splitting_coefficient: 0.0,
 			min_segment_length: 0.0,
 			max_segment_length: 0.0,
+			max_nodes: None,
 		}
 	}
 
@@ -70,6 +74,7 @@ impl<
 			splitting_coefficient: 0.6,
 			min_segment_length: 0.0,
 			max_segment_length: 0.0,
+			max_nodes: None,
 		}
 	}
 
@@ -118,6 +123,11 @@ impl<
 		self
 	}
 
+	pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+		self.max_nodes = Some(max_nodes);
+		self
+	}
+
 	pub fn with_min_radius(mut self, min_radius: f32) -> Self {
 		self.min_radius = min_radius;
 		self
@@ -228,33 +238,60 @@ impl<
 		radius
 	}
 
-	pub fn build(&self) -> BallStick {
+	/// The children grown from a node, with the rays they were grown along.
+	fn children_of(&self, node: &BallStickNode, ray: Vec3) -> Vec<(BallStickNode, Vec3)> {
+		(0..self.node_children_from(node.position))
+			.map(|i| {
+				let child_ray = self.ray_from(node.position, ray, i);
+				let child_position = node.position + child_ray;
+				let child_radius = self.radius_from(node.position, i);
+				(BallStickNode::new(child_position, child_radius), child_ray)
+			})
+			.collect()
+	}
+
+	/// Grows the ball stick level by level.
+	///
+	/// The children of each level are grown in parallel and added in the order of their
+	/// parents, so a build is the same however its work is split. Once `max_nodes` is reached
+	/// the remaining children are dropped.
+	pub fn build(&self) -> BallStick
+	where
+		N: Sync,
+		M: Sync,
+	{
 		let mut ballstick = BallStick::new();
+		if self.depth == 0 {
+			return ballstick;
+		}
 
 		let initial_node = BallStickNode::new(self.anchor, self.initial_radius);
-
-		let mut queue = VecDeque::new();
-		queue.push_back((initial_node.clone(), self.initial_ray.clone()));
-
-		for _ in 0..self.depth {
-			let mut next_queue = VecDeque::new();
-			while let Some((node, ray)) = queue.pop_front() {
-				let children = self.node_children_from(node.position);
-				for i in 0..children {
-					// generate the child attributes
-					let child_ray = self.ray_from(node.position, ray, i);
-					let child_position = node.position + child_ray;
-					let child_radius = self.radius_from(node.position, i);
-					let child_node = BallStickNode::new(child_position, child_radius);
-
-					// add the child to the ballstick and queue it for processing
+		ballstick.add_node(initial_node.clone());
+		let max_nodes = self.max_nodes.unwrap_or(usize::MAX);
+
+		let mut level = vec![(initial_node, self.initial_ray)];
+		for depth in 0..self.depth {
+			let children: Vec<Vec<(BallStickNode, Vec3)>> =
+				level.par_iter().map(|(node, ray)| self.children_of(node, *ray)).collect();
+
+			let mut next_level = Vec::new();
+			for ((node, _), children) in level.iter().zip(children) {
+				for (child_node, child_ray) in children {
+					if ballstick.nodes.len() >= max_nodes {
+						log::warn!(
+							"Ball stick truncated at {} nodes on level {} of {}",
+							max_nodes,
+							depth + 1,
+							self.depth
+						);
+						return ballstick;
+					}
 					ballstick.add_node(child_node.clone());
 					ballstick.add_child(node.clone(), child_node.clone());
-					next_queue.push_back((child_node.clone(), child_ray));
+					next_level.push((child_node, child_ray));
 				}
 			}
-			// swap the queues
-			queue = next_queue;
+			level = next_level;
 		}
 
 		ballstick
@@ -294,6 +331,14 @@ impl<'a> BallStickSegment<'a> {
 	pub fn ray(&self) -> Vec3 {
 		self.end.position - self.start.position
 	}
+
+	/// The ratio of the end radius to the start radius, 1 for a segment of constant thickness.
+	pub fn taper(&self) -> f32 {
+		if self.start.radius <= 0.0 {
+			return 1.0;
+		}
+		self.end.radius / self.start.radius
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -321,6 +366,14 @@ impl BallStick {
 		self.nodes.get(node).map(|children| children.iter()).unwrap_or_default()
 	}
 
+	/// Maps each child node to the node it grows from.
+	pub fn parents(&self) -> HashMap<&BallStickNode, &BallStickNode> {
+		self.nodes
+			.iter()
+			.flat_map(|(node, children)| children.iter().map(move |child| (child, node)))
+			.collect()
+	}
+
 	pub fn nodes(&self) -> impl Iterator<Item = &BallStickNode> {
 		self.nodes.keys().collect::<Vec<&BallStickNode>>().into_iter()
 	}
@@ -390,6 +443,35 @@ mod tests {
 		// we may solve this by moving the whole thing to fastnoise.
 	}
 
+	#[test]
+	fn test_deep_builds_are_deterministic_and_capped() {
+		let builder = BallStickBuilder::<Fbm<OpenSimplex>, Fbm<OpenSimplex>>::common_tree_builder()
+			.with_noise_config_3d(NoiseConfig::new(Fbm::new(1)))
+			.with_noise_config_4d(NoiseConfig::new(Fbm::new(2)))
+			.with_initial_ray(Vec3::Y)
+			.with_bias_ray(Vec3::Y)
+			.with_min_segment_length(0.01)
+			.with_max_segment_length(0.02)
+			.with_splitting_coefficient(0.3)
+			.with_depth(7);
+
+		let positions = |ballstick: &BallStick| {
+			let mut positions: Vec<[u32; 3]> = ballstick
+				.nodes()
+				.map(|node| node.position.to_array().map(f32::to_bits))
+				.collect();
+			positions.sort();
+			positions
+		};
+		let full = builder.build();
+		assert!(full.nodes().count() > 100);
+		assert_eq!(positions(&full), positions(&builder.build()));
+
+		let capped = builder.clone().with_max_nodes(100).build();
+		assert_eq!(capped.nodes().count(), 100);
+		assert_eq!(capped.segments().count(), 99);
+	}
+
 	#[test]
 	fn test_builder_build() {
 		let mut ballstick_builder =
@@ -0,0 +1,19 @@
+use crate::complex::chain::ball_stick::builder::BallStick;
+use bevy::prelude::*;
+
+/// Draws the nodes of the ball-stick as spheres of their radius and its segments as lines.
+///
+/// Node positions are taken in world space, as they are when the ball-stick is rendered.
+pub fn draw_ball_stick(
+	gizmos: &mut Gizmos,
+	ball_stick: &BallStick,
+	node_color: Color,
+	segment_color: Color,
+) {
+	for node in ball_stick.nodes() {
+		gizmos.sphere(Isometry3d::from_translation(node.position), node.radius, node_color);
+	}
+	for segment in ball_stick.segments() {
+		gizmos.line(segment.start.position, segment.end.position, segment_color);
+	}
+}
@@ -3,16 +3,25 @@ pub mod mesh_handle_stack;
 use crate::complex::chain::ball_stick::builder::{BallStick, BallStickNode, BallStickSegment};
 use bevy::prelude::*;
 use chunk::cascade::CascadeChunk;
-use render_item::RenderItem;
+use render_item::{debug_name, RenderItem};
+
+/// A stick mesh that can narrow from its base to its top.
+pub trait TaperedMesh: Sized {
+	/// The mesh with its top radius at `taper` times its base radius.
+	fn with_taper(&self, taper: f32) -> Self;
+}
 
 pub trait BallStickSpawner {
 	/// Computes the appropriate transform for the ball at the given node.
+	///
+	/// The parent is the segment leading into the node, which root nodes don't have.
 	fn spawn_ball(
 		&self,
 		commands: &mut Commands,
 		transform: Transform,
 		cascade_chunk: &CascadeChunk,
 		node: &BallStickNode,
+		parent: Option<&BallStickSegment>,
 		index: usize,
 	) -> Vec<Entity>;
 
@@ -54,9 +63,10 @@ impl<P: BallStickSpawner> BallStickRenderItem<P> {
 		transform: Transform,
 		cascade_chunk: &CascadeChunk,
 		node: &BallStickNode,
+		parent: Option<&BallStickSegment>,
 		index: usize,
 	) -> Vec<Entity> {
-		self.spawner.spawn_ball(commands, transform, cascade_chunk, node, index)
+		self.spawner.spawn_ball(commands, transform, cascade_chunk, node, parent, index)
 	}
 
 	pub fn spawn_stick(
@@ -82,12 +92,27 @@ impl<P: BallStickSpawner + Clone> RenderItem for BallStickRenderItem<P> {
 		cascade_chunk: &CascadeChunk,
 		transform: Transform,
 	) -> Vec<Entity> {
+		let mut entities = Vec::new();
+		let parents = self.ballstick.parents();
 		for (index, ball) in self.ballstick.nodes().enumerate() {
-			let _entities = self.spawn_ball(commands, transform, cascade_chunk, ball, index);
+			let parent = parents.get(ball).map(|start| BallStickSegment { start, end: ball });
+			for entity in
+				self.spawn_ball(commands, transform, cascade_chunk, ball, parent.as_ref(), index)
+			{
+				debug_name(commands, entity, || {
+					format!("branch node {}/{}", index + 1, self.ballstick.nodes().count())
+				});
+				entities.push(entity);
+			}
 		}
 		for (index, segment) in self.ballstick.segments().enumerate() {
-			let _entities = self.spawn_stick(commands, transform, cascade_chunk, &segment, index);
+			for entity in self.spawn_stick(commands, transform, cascade_chunk, &segment, index) {
+				debug_name(commands, entity, || {
+					format!("branch seg {}/{}", index + 1, self.ballstick.segments().count())
+				});
+				entities.push(entity);
+			}
 		}
-		vec![]
+		entities
 	}
 }
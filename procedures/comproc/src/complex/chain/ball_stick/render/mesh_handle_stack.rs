@@ -1,5 +1,6 @@
 use crate::complex::chain::ball_stick::builder::{BallStickNode, BallStickSegment};
-use crate::complex::chain::ball_stick::render::BallStickSpawner;
+use crate::complex::chain::ball_stick::render::{BallStickSpawner, TaperedMesh};
+use bevy::light::NotShadowCaster;
 use bevy::prelude::*;
 use chunk::cascade::CascadeChunk;
 use render_item::mesh::{handle::MeshHandle, IdentifiedMesh, MeshBuilder, MeshDispatch};
@@ -16,6 +17,14 @@ pub struct MeshHandleStackSpawner<
 	pub stick_mesh_handle_stack: Vec<MeshHandle<S>>,
 	pub stick_material: MeshMaterial3d<M>,
 	pub stick_scale: Vec3,
+	/// Whether balls turn their up axis along the segment leading into them
+	pub align_balls: bool,
+	/// How far aligned balls lean back up toward the light, from 0 along the branch to 1 upright
+	pub phototropism: f32,
+	/// Steps the taper of sticks is snapped to, so tapered meshes are shared; 0 disables tapering
+	pub taper_steps: u32,
+	/// Whether balls cast shadows, off when a proxy casts them instead
+	pub ball_shadows: bool,
 }
 
 impl<B: MeshBuilder + IdentifiedMesh, S: MeshBuilder + IdentifiedMesh, M: Material>
@@ -29,6 +38,10 @@ impl<B: MeshBuilder + IdentifiedMesh, S: MeshBuilder + IdentifiedMesh, M: Materi
 			stick_mesh_handle_stack: vec![],
 			stick_scale: Vec3::splat(1.0),
 			stick_material,
+			align_balls: false,
+			phototropism: 0.0,
+			taper_steps: 8,
+			ball_shadows: true,
 		}
 	}
 
@@ -58,6 +71,46 @@ impl<B: MeshBuilder + IdentifiedMesh, S: MeshBuilder + IdentifiedMesh, M: Materi
 		self
 	}
 
+	/// Aligns balls to the branch they grow from, leaning up by the phototropism.
+	pub fn with_ball_alignment(mut self, phototropism: f32) -> Self {
+		self.align_balls = true;
+		self.phototropism = phototropism.clamp(0.0, 1.0);
+		self
+	}
+
+	/// The rotation of the ball at the end of the parent segment.
+	///
+	/// Unaligned balls and balls without a parent keep the axes of their mesh.
+	pub fn ball_rotation(&self, parent: Option<&BallStickSegment>) -> Quat {
+		if !self.align_balls {
+			return Quat::IDENTITY;
+		}
+		let Some(direction) = parent.and_then(|parent| parent.ray().try_normalize()) else {
+			return Quat::IDENTITY;
+		};
+		let up = direction.lerp(Vec3::Y, self.phototropism).try_normalize().unwrap_or(Vec3::Y);
+		Quat::from_rotation_arc(Vec3::Y, up)
+	}
+
+	pub fn with_ball_shadows(mut self, ball_shadows: bool) -> Self {
+		self.ball_shadows = ball_shadows;
+		self
+	}
+
+	pub fn with_taper_steps(mut self, taper_steps: u32) -> Self {
+		self.taper_steps = taper_steps;
+		self
+	}
+
+	/// The segment's taper, snapped to the taper steps and kept between one step and 1.
+	pub fn taper(&self, segment: &BallStickSegment) -> Option<f32> {
+		if self.taper_steps == 0 {
+			return None;
+		}
+		let steps = self.taper_steps as f32;
+		Some((segment.taper().clamp(0.0, 1.0) * steps).round().max(1.0) / steps)
+	}
+
 	pub fn get_ball_for_index(&self, index: usize) -> Option<MeshHandle<B>> {
 		if self.ball_mesh_handle_stack.is_empty() {
 			return None;
@@ -73,8 +126,34 @@ impl<B: MeshBuilder + IdentifiedMesh, S: MeshBuilder + IdentifiedMesh, M: Materi
 	}
 }
 
-impl<B: MeshBuilder + IdentifiedMesh, S: MeshBuilder + IdentifiedMesh, M: Material> BallStickSpawner
-	for MeshHandleStackSpawner<B, S, M>
+impl<
+		B: MeshBuilder + IdentifiedMesh,
+		S: MeshBuilder + IdentifiedMesh + TaperedMesh,
+		M: Material,
+	> MeshHandleStackSpawner<B, S, M>
+{
+	/// The stick for the segment, narrowing from its start radius toward its end radius.
+	pub fn get_tapered_stick(
+		&self,
+		segment: &BallStickSegment,
+		index: usize,
+	) -> Option<MeshHandle<S>> {
+		let mesh_handle = self.get_stick_for_index(index)?;
+		match self.taper(segment) {
+			Some(taper) => {
+				let builder = mesh_handle.builder().with_taper(taper);
+				Some(mesh_handle.with_builder(builder))
+			}
+			None => Some(mesh_handle),
+		}
+	}
+}
+
+impl<
+		B: MeshBuilder + IdentifiedMesh,
+		S: MeshBuilder + IdentifiedMesh + TaperedMesh,
+		M: Material,
+	> BallStickSpawner for MeshHandleStackSpawner<B, S, M>
 where
 	(
 		CascadeChunk,
@@ -96,21 +175,26 @@ where
 		_transform: Transform,
 		cascade_chunk: &CascadeChunk,
 		node: &BallStickNode,
+		parent: Option<&BallStickSegment>,
 		index: usize,
 	) -> Vec<Entity> {
 		if let Some(mesh_handle) = self.get_ball_for_index(index) {
 			let scale = self.ball_scale;
 
 			// spawn one on the point
-			let ball_transform = Transform::from_translation(node.position).with_scale(scale); // Scale for leaf ball size
-			commands.spawn((
+			let ball_transform = Transform::from_translation(node.position)
+				.with_rotation(self.ball_rotation(parent))
+				.with_scale(scale); // Scale for leaf ball size
+			let mut ball = commands.spawn((
 				cascade_chunk.clone(),
 				MeshDispatch::new(mesh_handle.clone()),
 				ball_transform,
 				MeshMaterial3d(self.ball_material.0.clone()),
 			));
-
-			vec![]
+			if !self.ball_shadows {
+				ball.insert(NotShadowCaster);
+			}
+			vec![ball.id()]
 		} else {
 			vec![]
 		}
@@ -124,7 +208,7 @@ where
 		segment: &BallStickSegment,
 		index: usize,
 	) -> Vec<Entity> {
-		if let Some(mesh_handle) = self.get_stick_for_index(index) {
+		if let Some(mesh_handle) = self.get_tapered_stick(segment, index) {
 			let ray = segment.ray();
 			let direction = ray.clone().normalize();
 			let length = ray.length();
@@ -139,26 +223,83 @@ where
 
 			let rotation = Quat::from_mat3(&Mat3::from_cols(right, up, forward));
 
-			let pivot_offset = Vec3::new(0.5, 0.0, 0.5);
+			// The mesh narrows toward the end, so the start radius scales the whole stick
 			let scale =
 				Vec3::new(segment.start.radius, length, segment.start.radius) * self.stick_scale;
 
-			let transform = Transform {
-				translation: segment.start.position - rotation * (pivot_offset * scale),
-				rotation,
-				scale,
-			};
+			let transform = Transform { translation: segment.start.position, rotation, scale };
 
-			commands.spawn((
-				cascade_chunk.clone(),
-				MeshDispatch::new(mesh_handle.clone()),
-				transform,
-				MeshMaterial3d(self.stick_material.0.clone()),
-			));
-
-			vec![]
+			vec![commands
+				.spawn((
+					cascade_chunk.clone(),
+					MeshDispatch::new(mesh_handle.clone()),
+					transform,
+					MeshMaterial3d(self.stick_material.0.clone()),
+				))
+				.id()]
 		} else {
 			vec![]
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::complex::chain::ball_stick::builder::BallStickNode;
+	use crate::geometry::spherical::unit_cylindrical_segment::UnitCylindricalSegment;
+	use sdf::Sdf;
+
+	#[test]
+	fn test_sticks_narrow_toward_the_end() {
+		let spawner = MeshHandleStackSpawner::<UnitCylindricalSegment, _, StandardMaterial>::new(
+			MeshMaterial3d(Handle::default()),
+			MeshMaterial3d(Handle::default()),
+		)
+		.with_stick_mesh_handle_stack(vec![MeshHandle::new(UnitCylindricalSegment::new())]);
+
+		let start = BallStickNode::new(Vec3::ZERO, 0.2);
+		let end = BallStickNode::new(Vec3::Y, 0.09);
+		let segment = BallStickSegment { start: &start, end: &end };
+		assert_eq!(spawner.taper(&segment), Some(0.5));
+
+		// The top of the unit stick sits at half its base radius
+		let Some(stick) = spawner.get_tapered_stick(&segment, 0) else {
+			panic!("expected a stick");
+		};
+		assert!(stick.builder().distance(Vec3::new(0.25, 1.0, 0.0)).abs() < 1e-6);
+		assert!(stick.builder().distance(Vec3::new(0.5, 0.0, 0.0)).abs() < 1e-6);
+
+		// Segments of similar taper share a mesh, and tapering can be turned off
+		let other_end = BallStickNode::new(Vec3::Y, 0.1);
+		let similar = BallStickSegment { start: &start, end: &other_end };
+		let Some(similar) = spawner.get_tapered_stick(&similar, 0) else {
+			panic!("expected a stick");
+		};
+		assert_eq!(similar.id(), stick.id());
+		assert_eq!(spawner.clone().with_taper_steps(0).taper(&segment), None);
+	}
+
+	#[test]
+	fn test_balls_follow_the_branch() {
+		let spawner = MeshHandleStackSpawner::<
+			UnitCylindricalSegment,
+			UnitCylindricalSegment,
+			StandardMaterial,
+		>::new(MeshMaterial3d(Handle::default()), MeshMaterial3d(Handle::default()));
+		let start = BallStickNode::new(Vec3::ZERO, 0.2);
+		let end = BallStickNode::new(Vec3::new(2.0, 0.0, 0.0), 0.1);
+		let parent = BallStickSegment { start: &start, end: &end };
+
+		assert_eq!(spawner.ball_rotation(Some(&parent)), Quat::IDENTITY);
+
+		let along = spawner.clone().with_ball_alignment(0.0).ball_rotation(Some(&parent));
+		assert!((along * Vec3::Y).abs_diff_eq(Vec3::X, 1e-5));
+
+		// Leaning halfway back up toward the light
+		let leaning = spawner.clone().with_ball_alignment(0.5).ball_rotation(Some(&parent));
+		assert!((leaning * Vec3::Y).abs_diff_eq(Vec3::new(1.0, 1.0, 0.0).normalize(), 1e-5));
+
+		assert_eq!(spawner.with_ball_alignment(0.5).ball_rotation(None), Quat::IDENTITY);
+	}
+}
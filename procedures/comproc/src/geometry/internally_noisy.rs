@@ -1,9 +1,9 @@
 use crate::noise::config::{InternalNoise, NoiseConfig};
-use bevy::prelude::*;
+use bevy::{math::bounding::Aabb3d, prelude::*};
 use chunk::cascade::CascadeChunk;
 use noise::{NoiseFn, Seedable};
 use render_item::{
-	mesh::{IdentifiedMesh, MeshBuilder, MeshId},
+	mesh::{IdentifiedMesh, MeshBuilder, MeshId, MeshResolution, MeshSpace},
 	NormalizeChunk,
 };
 
@@ -51,6 +51,15 @@ impl<
 	fn build_mesh_impl(&self, cascade_chunk: &CascadeChunk) -> Option<Mesh> {
 		self.mesh_builder.build_mesh_impl(cascade_chunk)
 	}
+
+	fn build_with_resolution(&self, res: MeshResolution) -> Mesh {
+		self.mesh_builder.build_with_resolution(res)
+	}
+
+	/// The inner builder already accounts for the internal noise.
+	fn local_bounds(&self) -> Aabb3d {
+		self.mesh_builder.local_bounds()
+	}
 }
 
 // TODO: for some reason just requiring [IdentifiedMesh] isn't enough.
@@ -63,4 +72,14 @@ impl<
 	fn id(&self) -> MeshId {
 		self.mesh_builder.id()
 	}
+
+	/// The inner builder's id only covers the noise if it hashes its internal noise, so the
+	/// noise is part of the config.
+	fn config(&self) -> Option<String> {
+		Some(format!("{:?} {:?}", self.mesh_builder.config(), self.noise_config))
+	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		self.mesh_builder.mesh_space()
+	}
 }
@@ -3,7 +3,7 @@ use bevy::prelude::*;
 use chunk::cascade::CascadeChunk;
 use noise::{NoiseFn, Seedable};
 use render_item::{
-	mesh::{IdentifiedMesh, MeshId},
+	mesh::{IdentifiedMesh, MeshId, MeshSpace},
 	NormalizeChunk,
 };
 use sdf::Sdf;
@@ -33,6 +33,14 @@ impl<T: Sdf + IdentifiedMesh, N: NoiseFn<f64, 3> + Seedable + Send + Sync> Ident
 	fn id(&self) -> MeshId {
 		self.sdf.id().with_suffix(&format!("{:?}", self.noise_config))
 	}
+
+	fn config(&self) -> Option<String> {
+		Some(format!("{:?} {:?}", self.sdf.config(), self.noise_config))
+	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		self.sdf.mesh_space()
+	}
 }
 
 impl<T: Sdf + NormalizeChunk, N: NoiseFn<f64, 3> + Seedable + Send + Sync> NormalizeChunk
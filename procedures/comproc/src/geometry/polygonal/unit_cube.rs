@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use chunk::cascade::CascadeChunk;
 use render_item::{
-	mesh::{IdentifiedMesh, MeshId},
+	mesh::{IdentifiedMesh, MeshId, MeshSpace},
 	NormalizeChunk,
 };
 use sdf::Sdf;
@@ -28,7 +28,7 @@ impl Sdf for UnitCube {
 
 impl NormalizeChunk for UnitCube {
 	fn normalize_chunk(&self, cascade_chunk: &CascadeChunk) -> CascadeChunk {
-		CascadeChunk::unit_3d_center_chunk().with_res_2(cascade_chunk.res_2)
+		CascadeChunk::unit_3d_center_chunk().with_axis_res_2(cascade_chunk.res_2)
 	}
 }
 
@@ -37,4 +37,8 @@ impl IdentifiedMesh for UnitCube {
 		let debug_string = format!("{:?}", self);
 		MeshId::new(debug_string)
 	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		MeshSpace::Unit
+	}
 }
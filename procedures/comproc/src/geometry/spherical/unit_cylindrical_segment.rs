@@ -1,7 +1,8 @@
+use crate::complex::chain::ball_stick::render::TaperedMesh;
 use bevy::prelude::*;
 use chunk::cascade::CascadeChunk;
 use render_item::{
-	mesh::{IdentifiedMesh, MeshId},
+	mesh::{IdentifiedMesh, MeshId, MeshSpace},
 	NormalizeChunk,
 };
 use sdf::Sdf;
@@ -19,6 +20,12 @@ impl UnitCylindricalSegment {
 	}
 }
 
+impl TaperedMesh for UnitCylindricalSegment {
+	fn with_taper(&self, taper: f32) -> Self {
+		Self { top_radius: self.base_radius * taper, ..self.clone() }
+	}
+}
+
 /// We should get the MeshBuilder trait for free since this is an SDF.
 impl Sdf for UnitCylindricalSegment {
 	/// NOTE: early on there appeared to be a  bug that gives this some slightly weird sharp facets.
@@ -59,7 +66,7 @@ impl Sdf for UnitCylindricalSegment {
 
 impl NormalizeChunk for UnitCylindricalSegment {
 	fn normalize_chunk(&self, cascade_chunk: &CascadeChunk) -> CascadeChunk {
-		CascadeChunk::unit_center_chunk().with_res_2(cascade_chunk.res_2)
+		CascadeChunk::unit_center_chunk().with_axis_res_2(cascade_chunk.res_2)
 	}
 }
 
@@ -68,4 +75,8 @@ impl IdentifiedMesh for UnitCylindricalSegment {
 		let debug_string = format!("{:?}", self);
 		MeshId::new(debug_string)
 	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		MeshSpace::Unit
+	}
 }
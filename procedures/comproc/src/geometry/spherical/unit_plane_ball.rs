@@ -1,15 +1,15 @@
 pub mod scratchpad;
 
 use crate::noise::config::NoiseConfig;
-use bevy::prelude::*;
+use bevy::{math::bounding::Aabb3d, prelude::*};
 use chunk::cascade::CascadeChunk;
 use noise::{NoiseFn, Seedable};
 use render_item::{
-	mesh::{IdentifiedMesh, MeshBuilder, MeshId},
+	mesh::{IdentifiedMesh, MeshBuilder, MeshId, MeshResolution, MeshSpace},
 	NormalizeChunk,
 };
 use scratchpad::{generate_unit_disk, generate_unit_triangle};
-use std::f32::consts::PI;
+use std::f32::consts::{PI, SQRT_2};
 
 /// Noisy sphere: a sphere with Perlin noise perturbation for organic surface variation
 #[derive(Debug, Clone)]
@@ -44,7 +44,9 @@ impl<N: NoiseFn<f64, 3> + Seedable + Clone> NormalizeChunk for UnitPlaneBall<N>
 	fn normalize_chunk(&self, cascade_chunk: &CascadeChunk) -> CascadeChunk {
 		let mu = self.noise_config.as_ref().map(|config| config.amplitude + 0.001).unwrap_or(0.0);
 
-		CascadeChunk::unit_3d_center_chunk().with_res_2(cascade_chunk.res_2).with_mu(mu)
+		CascadeChunk::unit_3d_center_chunk()
+			.with_axis_res_2(cascade_chunk.res_2)
+			.with_mu(mu)
 	}
 }
 
@@ -53,6 +55,10 @@ impl<N: NoiseFn<f64, 3> + Seedable + Clone> IdentifiedMesh for UnitPlaneBall<N>
 		let debug_string = format!("{:?}-{:?}", self.radius, self.noise_config);
 		MeshId::new(debug_string)
 	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		MeshSpace::Unit
+	}
 }
 
 #[derive(Clone, Copy)]
@@ -63,12 +69,17 @@ enum ShapeType {
 }
 
 impl<N: NoiseFn<f64, 3> + Seedable + Clone> MeshBuilder for UnitPlaneBall<N> {
+	/// The planes keep a fixed tessellation for chunk builds.
 	fn build_mesh_impl(&self, _cascade_chunk: &CascadeChunk) -> Option<Mesh> {
+		Some(self.build_with_resolution(MeshResolution::default()))
+	}
+
+	fn build_with_resolution(&self, res: MeshResolution) -> Mesh {
 		// Generate a mix of 8 plane meshes (discs, triangles, rectangles) intersecting at the origin
 		let num_planes = 8;
 		let size = 1.0; // Unit-sized shapes
 		let radius = 1.0; // For discs
-		let segments = 32; // For discs
+		let segments = res.segments().max_element().max(3); // For discs
 
 		// Use Fibonacci sphere algorithm for even distribution of directions
 		let golden_angle = PI * (3.0 - (5.0_f32).sqrt());
@@ -179,6 +190,13 @@ impl<N: NoiseFn<f64, 3> + Seedable + Clone> MeshBuilder for UnitPlaneBall<N> {
 		mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, all_uvs);
 		mesh.insert_indices(bevy::mesh::Indices::U32(all_indices));
 
-		Some(mesh)
+		mesh
+	}
+
+	/// Unit planes, with edges perturbed by up to the noise amplitude along both in-plane axes.
+	fn local_bounds(&self) -> Aabb3d {
+		let amplitude =
+			self.noise_config.as_ref().map(|config| config.amplitude.abs()).unwrap_or(0.0);
+		Aabb3d::new(Vec3::ZERO, Vec3::splat(1.0 + amplitude * SQRT_2))
 	}
 }
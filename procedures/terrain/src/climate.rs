@@ -0,0 +1,265 @@
+use crate::region::Region2D;
+use bevy::prelude::*;
+use noise::{NoiseFn, Perlin};
+
+/// Biomes of a Whittaker diagram, classified from temperature and moisture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+	Ice,
+	Tundra,
+	Taiga,
+	Grassland,
+	TemperateForest,
+	TemperateRainforest,
+	Desert,
+	Savanna,
+	TropicalForest,
+	TropicalRainforest,
+}
+
+impl Biome {
+	pub const ALL: [Biome; 10] = [
+		Biome::Ice,
+		Biome::Tundra,
+		Biome::Taiga,
+		Biome::Grassland,
+		Biome::TemperateForest,
+		Biome::TemperateRainforest,
+		Biome::Desert,
+		Biome::Savanna,
+		Biome::TropicalForest,
+		Biome::TropicalRainforest,
+	];
+
+	/// Name of the biome, usable as a chunk tag.
+	pub fn name(self) -> &'static str {
+		match self {
+			Biome::Ice => "ice",
+			Biome::Tundra => "tundra",
+			Biome::Taiga => "taiga",
+			Biome::Grassland => "grassland",
+			Biome::TemperateForest => "temperate_forest",
+			Biome::TemperateRainforest => "temperate_rainforest",
+			Biome::Desert => "desert",
+			Biome::Savanna => "savanna",
+			Biome::TropicalForest => "tropical_forest",
+			Biome::TropicalRainforest => "tropical_rainforest",
+		}
+	}
+
+	/// Color of the biome on maps and previews.
+	pub fn color(self) -> Color {
+		match self {
+			Biome::Ice => Color::srgb(0.92, 0.95, 0.98),
+			Biome::Tundra => Color::srgb(0.62, 0.65, 0.56),
+			Biome::Taiga => Color::srgb(0.2, 0.38, 0.3),
+			Biome::Grassland => Color::srgb(0.55, 0.7, 0.32),
+			Biome::TemperateForest => Color::srgb(0.25, 0.5, 0.2),
+			Biome::TemperateRainforest => Color::srgb(0.12, 0.42, 0.25),
+			Biome::Desert => Color::srgb(0.87, 0.78, 0.52),
+			Biome::Savanna => Color::srgb(0.72, 0.68, 0.35),
+			Biome::TropicalForest => Color::srgb(0.2, 0.55, 0.15),
+			Biome::TropicalRainforest => Color::srgb(0.08, 0.45, 0.12),
+		}
+	}
+
+	/// Looks the biome up in a Whittaker-style table.
+	///
+	/// Bands of mean temperature in degrees Celsius are split by moisture from 0 (arid)
+	/// to 1 (saturated); colder bands support fewer biomes.
+	pub fn classify(climate: Climate) -> Self {
+		let Climate { temperature, moisture } = climate;
+		if temperature < -10.0 {
+			Biome::Ice
+		} else if temperature < -2.0 {
+			Biome::Tundra
+		} else if temperature < 5.0 {
+			if moisture < 0.25 {
+				Biome::Tundra
+			} else {
+				Biome::Taiga
+			}
+		} else if temperature < 20.0 {
+			if moisture < 0.2 {
+				Biome::Desert
+			} else if moisture < 0.45 {
+				Biome::Grassland
+			} else if moisture < 0.8 {
+				Biome::TemperateForest
+			} else {
+				Biome::TemperateRainforest
+			}
+		} else if moisture < 0.25 {
+			Biome::Desert
+		} else if moisture < 0.5 {
+			Biome::Savanna
+		} else if moisture < 0.8 {
+			Biome::TropicalForest
+		} else {
+			Biome::TropicalRainforest
+		}
+	}
+}
+
+/// Mean temperature in degrees Celsius and moisture from 0 to 1 at a point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Climate {
+	pub temperature: f32,
+	pub moisture: f32,
+}
+
+/// A lightweight climate layer arranging biomes at the scale of the world.
+///
+/// Temperature falls with altitude above sea level and with latitude, the distance from
+/// an equator line in the xz plane. Moisture is noise raised near water bodies.
+#[derive(Debug, Clone)]
+pub struct ClimateModel {
+	/// A point on the equator
+	pub equator_origin: Vec2,
+	/// Direction of the equator line
+	pub equator_direction: Vec2,
+	/// Temperature on the equator at sea level
+	pub equator_temperature: f32,
+	/// Temperature drop per unit of distance from the equator
+	pub latitude_gradient: f32,
+	/// Temperature drop per unit of altitude above sea level
+	pub lapse_rate: f32,
+	pub sea_level: f32,
+	/// Moisture away from water, before noise
+	pub base_moisture: f32,
+	/// Frequency of the moisture noise
+	pub moisture_frequency: f64,
+	/// Most moisture the noise adds or removes
+	pub moisture_amplitude: f32,
+	/// Lakes, rivers and seas, which moisten the land around them
+	pub water: Vec<Region2D>,
+	/// Distance from water over which its moisture fades out
+	pub water_reach: f32,
+	perlin: Perlin,
+}
+
+impl ClimateModel {
+	pub fn new(seed: u32) -> Self {
+		Self {
+			equator_origin: Vec2::ZERO,
+			equator_direction: Vec2::X,
+			equator_temperature: 28.0,
+			latitude_gradient: 0.02,
+			lapse_rate: 2.0,
+			sea_level: 0.0,
+			base_moisture: 0.45,
+			moisture_frequency: 0.002,
+			moisture_amplitude: 0.35,
+			water: Vec::new(),
+			water_reach: 100.0,
+			perlin: Perlin::new(seed),
+		}
+	}
+
+	pub fn with_equator(mut self, origin: Vec2, direction: Vec2) -> Self {
+		self.equator_origin = origin;
+		self.equator_direction = direction.normalize_or(Vec2::X);
+		self
+	}
+
+	pub fn with_equator_temperature(mut self, equator_temperature: f32) -> Self {
+		self.equator_temperature = equator_temperature;
+		self
+	}
+
+	pub fn with_latitude_gradient(mut self, latitude_gradient: f32) -> Self {
+		self.latitude_gradient = latitude_gradient;
+		self
+	}
+
+	pub fn with_lapse_rate(mut self, lapse_rate: f32) -> Self {
+		self.lapse_rate = lapse_rate;
+		self
+	}
+
+	pub fn with_sea_level(mut self, sea_level: f32) -> Self {
+		self.sea_level = sea_level;
+		self
+	}
+
+	pub fn with_moisture(mut self, base_moisture: f32, frequency: f64, amplitude: f32) -> Self {
+		self.base_moisture = base_moisture;
+		self.moisture_frequency = frequency;
+		self.moisture_amplitude = amplitude;
+		self
+	}
+
+	pub fn with_water(mut self, water: Region2D) -> Self {
+		self.water.push(water);
+		self
+	}
+
+	pub fn with_water_reach(mut self, water_reach: f32) -> Self {
+		self.water_reach = water_reach;
+		self
+	}
+
+	/// Distance from the equator line.
+	pub fn latitude(&self, x: f32, z: f32) -> f32 {
+		let offset = Vec2::new(x, z) - self.equator_origin;
+		offset.perp_dot(self.equator_direction).abs()
+	}
+
+	pub fn temperature_at(&self, x: f32, z: f32, altitude: f32) -> f32 {
+		let above_sea = (altitude - self.sea_level).max(0.0);
+		self.equator_temperature
+			- self.latitude_gradient * self.latitude(x, z)
+			- self.lapse_rate * above_sea
+	}
+
+	pub fn moisture_at(&self, x: f32, z: f32) -> f32 {
+		let frequency = self.moisture_frequency;
+		let noise = self.perlin.get([x as f64 * frequency, z as f64 * frequency]) as f32;
+		let water = self
+			.water
+			.iter()
+			.map(|water| water.sdf(Vec2::new(x, z)))
+			.fold(f32::INFINITY, f32::min);
+		let wetting = if self.water_reach > 0.0 {
+			(1.0 - water.max(0.0) / self.water_reach).max(0.0)
+		} else {
+			0.0
+		};
+		(self.base_moisture + self.moisture_amplitude * noise + wetting).clamp(0.0, 1.0)
+	}
+
+	pub fn climate_at(&self, x: f32, z: f32, altitude: f32) -> Climate {
+		Climate {
+			temperature: self.temperature_at(x, z, altitude),
+			moisture: self.moisture_at(x, z),
+		}
+	}
+
+	pub fn biome_at(&self, x: f32, z: f32, altitude: f32) -> Biome {
+		Biome::classify(self.climate_at(x, z, altitude))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::region::CircleRegion;
+
+	#[test]
+	fn test_climate_arranges_biomes() {
+		let lake = Region2D::Circle(CircleRegion { center: Vec2::new(0.0, 0.0), radius: 20.0 });
+		let climate = ClimateModel::new(3)
+			.with_moisture(0.1, 0.002, 0.0)
+			.with_water(lake)
+			.with_water_reach(50.0);
+
+		// Warm and wet by the lake on the equator, dry further out
+		assert_eq!(climate.biome_at(0.0, 0.0, 0.0), Biome::TropicalRainforest);
+		assert_eq!(climate.biome_at(500.0, 0.0, 0.0), Biome::Desert);
+		// Colder up a mountain and toward the poles
+		assert_eq!(climate.biome_at(0.0, 0.0, 20.0), Biome::Ice);
+		assert_eq!(climate.biome_at(0.0, 1200.0, 0.0), Biome::Tundra);
+		assert!(climate.temperature_at(0.0, 500.0, 0.0) < climate.temperature_at(0.0, 100.0, 0.0));
+		assert_eq!(climate.latitude(10.0, -40.0), 40.0);
+	}
+}
@@ -0,0 +1,14 @@
+/// Stable identifier assigned to an elevation modulation when it is added to the terrain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FeatureId(pub u64);
+
+/// A feature affecting a queried point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeatureHit {
+	/// The id of the feature.
+	pub id: FeatureId,
+	/// Signed distance to the feature's region (negative inside).
+	pub signed_distance: f32,
+	/// How strongly the feature affects the point, from 0 (not at all) to 1 (fully).
+	pub influence: f32,
+}
@@ -0,0 +1,334 @@
+pub mod exr;
+
+use crate::{ElevationModulation, ModulationPriority, PerlinTerrainSdf};
+use bevy::prelude::*;
+use exr::ExrImage;
+use rayon::prelude::*;
+use std::path::Path;
+
+/// Channel holding the heights in exported EXR files.
+pub const HEIGHT_CHANNEL: &str = "Y";
+/// Channel holding the stamp mask in exported EXR files.
+pub const MASK_CHANNEL: &str = "A";
+const REGION_MIN_ATTRIBUTE: &str = "regionMin";
+const REGION_MAX_ATTRIBUTE: &str = "regionMax";
+
+/// A grid of world heights over an axis-aligned region of the xz plane, with a mask saying
+/// where the heights apply.
+///
+/// Regions are exported from the procedural terrain with [`Heightfield::capture`], edited in an
+/// external tool as EXR, and brought back as a [`HeightfieldStamp`].
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+	/// Corner of the region with the lowest x and z; the first sample sits on it
+	pub min: Vec2,
+	/// Corner of the region with the highest x and z; the last sample sits on it
+	pub max: Vec2,
+	pub width: usize,
+	pub height: usize,
+	/// World heights, row-major with rows along x
+	pub heights: Vec<f32>,
+	/// How much each sample applies, from 0 to 1
+	pub mask: Vec<f32>,
+}
+
+impl Heightfield {
+	/// A flat heightfield at zero with a full mask.
+	pub fn new(min: Vec2, max: Vec2, width: usize, height: usize) -> Self {
+		let width = width.max(2);
+		let height = height.max(2);
+		Self {
+			min,
+			max,
+			width,
+			height,
+			heights: vec![0.0; width * height],
+			mask: vec![1.0; width * height],
+		}
+	}
+
+	/// Samples the terrain with all its modulations over the region.
+	pub fn capture(
+		terrain: &PerlinTerrainSdf,
+		min: Vec2,
+		max: Vec2,
+		width: usize,
+		height: usize,
+	) -> Self {
+		let mut heightfield = Self::new(min, max, width, height);
+		let width = heightfield.width;
+		let positions: Vec<Vec2> =
+			(0..heightfield.height).map(|j| heightfield.position(0, j)).collect();
+		let spacing = heightfield.spacing();
+		heightfield
+			.heights
+			.par_chunks_mut(width)
+			.zip(positions)
+			.for_each(|(row, start)| {
+				for (i, h) in row.iter_mut().enumerate() {
+					let p = start + Vec2::X * spacing.x * i as f32;
+					*h = terrain.height_at_with_all_modulations(p.x, p.y);
+				}
+			});
+		heightfield
+	}
+
+	/// Moves the heightfield to another region, for files exported without one.
+	pub fn with_region(mut self, min: Vec2, max: Vec2) -> Self {
+		self.min = min;
+		self.max = max;
+		self
+	}
+
+	/// Distance between neighbouring samples along x and z.
+	pub fn spacing(&self) -> Vec2 {
+		(self.max - self.min) / Vec2::new((self.width - 1) as f32, (self.height - 1) as f32)
+	}
+
+	/// World position of the sample in column `i` and row `j`.
+	pub fn position(&self, i: usize, j: usize) -> Vec2 {
+		self.min + self.spacing() * Vec2::new(i as f32, j as f32)
+	}
+
+	/// Whether the xz position lies in the region.
+	pub fn contains(&self, p: Vec2) -> bool {
+		p.cmpge(self.min).all() && p.cmple(self.max).all()
+	}
+
+	/// Bilinearly interpolated height at the xz position, or `None` outside the region.
+	pub fn height_at(&self, p: Vec2) -> Option<f32> {
+		self.interpolate(&self.heights, p)
+	}
+
+	/// Bilinearly interpolated mask at the xz position, zero outside the region.
+	pub fn mask_at(&self, p: Vec2) -> f32 {
+		self.interpolate(&self.mask, p).unwrap_or(0.0)
+	}
+
+	fn interpolate(&self, values: &[f32], p: Vec2) -> Option<f32> {
+		if !self.contains(p) {
+			return None;
+		}
+		let grid = (p - self.min) / self.spacing();
+		let i = (grid.x.floor() as usize).min(self.width - 2);
+		let j = (grid.y.floor() as usize).min(self.height - 2);
+		let t = grid - Vec2::new(i as f32, j as f32);
+		let at = |i: usize, j: usize| values[j * self.width + i];
+		let bottom = at(i, j) + (at(i + 1, j) - at(i, j)) * t.x;
+		let top = at(i, j + 1) + (at(i + 1, j + 1) - at(i, j + 1)) * t.x;
+		Some(bottom + (top - bottom) * t.y)
+	}
+
+	/// Encodes the heights and mask as an EXR, with the region in the header.
+	pub fn to_exr(&self) -> Vec<u8> {
+		ExrImage {
+			width: self.width,
+			height: self.height,
+			channels: vec![
+				(HEIGHT_CHANNEL.to_string(), self.heights.clone()),
+				(MASK_CHANNEL.to_string(), self.mask.clone()),
+			],
+			vectors: vec![
+				(REGION_MIN_ATTRIBUTE.to_string(), self.min),
+				(REGION_MAX_ATTRIBUTE.to_string(), self.max),
+			],
+		}
+		.encode()
+	}
+
+	/// Decodes a heightfield from an EXR.
+	/// A missing mask channel means a full mask; a missing region falls back to the unit square.
+	pub fn from_exr(bytes: &[u8]) -> Result<Self, String> {
+		let image = ExrImage::decode(bytes)?;
+		if image.width < 2 || image.height < 2 {
+			return Err(format!("Heightfield is only {}x{} samples", image.width, image.height));
+		}
+		let heights = image
+			.channel(HEIGHT_CHANNEL)
+			.ok_or_else(|| format!("EXR has no {HEIGHT_CHANNEL} channel"))?
+			.to_vec();
+		let mask = match image.channel(MASK_CHANNEL) {
+			Some(mask) => mask.iter().map(|m| m.clamp(0.0, 1.0)).collect(),
+			None => vec![1.0; heights.len()],
+		};
+		let (min, max) =
+			match (image.vector(REGION_MIN_ATTRIBUTE), image.vector(REGION_MAX_ATTRIBUTE)) {
+				(Some(min), Some(max)) => (min, max),
+				_ => {
+					log::warn!("EXR heightfield has no region, placing it on the unit square");
+					(Vec2::ZERO, Vec2::ONE)
+				}
+			};
+		Ok(Self { min, max, width: image.width, height: image.height, heights, mask })
+	}
+
+	pub fn save_exr(&self, path: &Path) -> Result<(), String> {
+		std::fs::write(path, self.to_exr()).map_err(|e| format!("Failed to write {path:?}: {e}"))
+	}
+
+	pub fn load_exr(path: &Path) -> Result<Self, String> {
+		let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {path:?}: {e}"))?;
+		Self::from_exr(&bytes)
+	}
+}
+
+/// A hand-authored heightfield blended over the procedural terrain.
+///
+/// Inside its mask the stamp replaces the terrain height, and it fades back to the procedural
+/// height over `falloff` world units from the edge of the mask or the region.
+#[derive(Debug, Clone)]
+pub struct HeightfieldStamp {
+	heightfield: Heightfield,
+	/// Width of the blend at the mask edge, in world units
+	pub falloff: f32,
+	/// The priority level of the modulation.
+	pub priority: ModulationPriority,
+	/// Distance of each sample to the nearest unmasked sample or region edge
+	edge_distance: Vec<f32>,
+}
+
+impl HeightfieldStamp {
+	pub fn new(heightfield: Heightfield, falloff: f32) -> Self {
+		let edge_distance = edge_distance(&heightfield);
+		Self { heightfield, falloff, priority: ModulationPriority::Detail, edge_distance }
+	}
+
+	/// Sets the priority level of the modulation
+	pub fn with_priority(mut self, priority: ModulationPriority) -> Self {
+		self.priority = priority;
+		self
+	}
+
+	pub fn heightfield(&self) -> &Heightfield {
+		&self.heightfield
+	}
+
+	/// How much of the stamp's height applies at the xz position, from 0 to 1.
+	pub fn weight(&self, p: Vec2) -> f32 {
+		let mask = self.heightfield.mask_at(p);
+		if mask <= 0.0 {
+			return 0.0;
+		}
+		let distance = self.heightfield.interpolate(&self.edge_distance, p).unwrap_or(0.0);
+		let t = (distance / self.falloff.max(f32::EPSILON)).clamp(0.0, 1.0);
+		mask * t * t * (3.0 - 2.0 * t)
+	}
+}
+
+/// Chamfer distance from every masked sample to the nearest unmasked one or the region edge.
+fn edge_distance(heightfield: &Heightfield) -> Vec<f32> {
+	let (width, height) = (heightfield.width, heightfield.height);
+	let spacing = heightfield.spacing();
+	let diagonal = spacing.length();
+	let mut distance: Vec<f32> = (0..width * height)
+		.map(|index| {
+			if heightfield.mask[index] < 0.5 {
+				return 0.0;
+			}
+			let (i, j) = ((index % width) as f32, (index / width) as f32);
+			let x = i.min((width - 1) as f32 - i) * spacing.x;
+			let z = j.min((height - 1) as f32 - j) * spacing.y;
+			x.min(z)
+		})
+		.collect();
+
+	let neighbours =
+		[(-1, 0, spacing.x), (-1, -1, diagonal), (0, -1, spacing.y), (1, -1, diagonal)];
+	let mut relax = |i: usize, j: usize, sign: isize| {
+		for (di, dj, step) in neighbours {
+			let (ni, nj) = (i as isize + di * sign, j as isize + dj * sign);
+			if ni < 0 || nj < 0 || ni >= width as isize || nj >= height as isize {
+				continue;
+			}
+			let candidate = distance[nj as usize * width + ni as usize] + step;
+			let current = &mut distance[j * width + i];
+			*current = current.min(candidate);
+		}
+	};
+	for j in 0..height {
+		for i in 0..width {
+			relax(i, j, 1);
+		}
+	}
+	for j in (0..height).rev() {
+		for i in (0..width).rev() {
+			relax(i, j, -1);
+		}
+	}
+	distance
+}
+
+impl ElevationModulation for HeightfieldStamp {
+	fn modify_elevation(
+		&self,
+		_perlin_terrain: &PerlinTerrainSdf,
+		elevation: f32,
+		x: f32,
+		z: f32,
+		_index: usize,
+	) -> f32 {
+		let p = Vec2::new(x, z);
+		let weight = self.weight(p);
+		match self.heightfield.height_at(p) {
+			Some(height) if weight > 0.0 => elevation + (height - elevation) * weight,
+			_ => elevation,
+		}
+	}
+
+	fn priority(&self) -> ModulationPriority {
+		self.priority
+	}
+
+	fn region_distance(&self, x: f32, z: f32) -> Option<f32> {
+		let center = (self.heightfield.min + self.heightfield.max) * 0.5;
+		let half_extents = (self.heightfield.max - self.heightfield.min) * 0.5;
+		let q = (Vec2::new(x, z) - center).abs() - half_extents;
+		Some(q.max(Vec2::ZERO).length() + q.max_element().min(0.0))
+	}
+
+	fn influence(&self, x: f32, z: f32) -> f32 {
+		self.weight(Vec2::new(x, z))
+	}
+
+	fn elevation_bounds(&self) -> Option<Rect> {
+		Some(Rect::from_corners(self.heightfield.min, self.heightfield.max))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_edited_export_reimports_as_a_blended_stamp() {
+		let mut terrain = PerlinTerrainSdf::new(7, 1.0);
+		let (min, max) = (Vec2::new(-1.0, -1.0), Vec2::new(1.0, 1.0));
+		let captured = Heightfield::capture(&terrain, min, max, 33, 33);
+
+		let Ok(mut edited) = Heightfield::from_exr(&captured.to_exr()) else {
+			panic!("exported heightfield should decode");
+		};
+		assert_eq!((edited.min, edited.max), (min, max));
+		assert_eq!(edited.heights, captured.heights);
+
+		// Raise a plateau in the middle and mask out everything around it.
+		for j in 0..edited.height {
+			for i in 0..edited.width {
+				let inside = edited.position(i, j).length() < 0.6;
+				edited.heights[j * edited.width + i] = 2.0;
+				edited.mask[j * edited.width + i] = if inside { 1.0 } else { 0.0 };
+			}
+		}
+
+		let outside = Vec2::new(0.9, 0.9);
+		let edge = Vec2::new(0.55, 0.0);
+		let before_outside = terrain.height_at_with_all_modulations(outside.x, outside.y);
+		let before_edge = terrain.height_at_with_all_modulations(edge.x, edge.y);
+		terrain.add_elevation_modulation(Box::new(HeightfieldStamp::new(edited, 0.2)));
+
+		assert!((terrain.height_at_with_all_modulations(0.0, 0.0) - 2.0).abs() < 1e-4);
+		assert_eq!(terrain.height_at_with_all_modulations(outside.x, outside.y), before_outside);
+		let blended = terrain.height_at_with_all_modulations(edge.x, edge.y);
+		assert!(blended > before_edge && blended < 2.0, "edge should blend, got {blended}");
+	}
+}
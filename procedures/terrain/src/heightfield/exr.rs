@@ -0,0 +1,253 @@
+//! A minimal OpenEXR codec for heightfields.
+//! Writes and reads single-part, uncompressed scanline images with `FLOAT` or `HALF` channels,
+//! which covers what painting tools produce when saving without compression.
+
+use bevy::prelude::*;
+
+const MAGIC: i32 = 20000630;
+const VERSION: u8 = 2;
+const PIXEL_HALF: i32 = 1;
+const PIXEL_FLOAT: i32 = 2;
+const NO_COMPRESSION: u8 = 0;
+
+/// A decoded EXR image: named float channels over a `width` x `height` grid, row-major.
+#[derive(Debug, Clone, Default)]
+pub struct ExrImage {
+	pub width: usize,
+	pub height: usize,
+	pub channels: Vec<(String, Vec<f32>)>,
+	/// `v2f` attributes carried in the header, such as the world region of the image.
+	pub vectors: Vec<(String, Vec2)>,
+}
+
+impl ExrImage {
+	/// The samples of the named channel, if present.
+	pub fn channel(&self, name: &str) -> Option<&[f32]> {
+		self.channels
+			.iter()
+			.find(|(n, _)| n == name)
+			.map(|(_, values)| values.as_slice())
+	}
+
+	/// The named `v2f` header attribute, if present.
+	pub fn vector(&self, name: &str) -> Option<Vec2> {
+		self.vectors.iter().find(|(n, _)| n == name).map(|(_, v)| *v)
+	}
+
+	/// Encodes the image as an uncompressed `FLOAT` EXR.
+	pub fn encode(&self) -> Vec<u8> {
+		let mut channels: Vec<&(String, Vec<f32>)> = self.channels.iter().collect();
+		channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+		let mut out = Vec::new();
+		out.extend_from_slice(&MAGIC.to_le_bytes());
+		out.extend_from_slice(&[VERSION, 0, 0, 0]);
+
+		let mut chlist = Vec::new();
+		for (name, _) in &channels {
+			chlist.extend_from_slice(name.as_bytes());
+			chlist.push(0);
+			chlist.extend_from_slice(&PIXEL_FLOAT.to_le_bytes());
+			chlist.extend_from_slice(&[0, 0, 0, 0]);
+			chlist.extend_from_slice(&1i32.to_le_bytes());
+			chlist.extend_from_slice(&1i32.to_le_bytes());
+		}
+		chlist.push(0);
+		write_attribute(&mut out, "channels", "chlist", &chlist);
+		write_attribute(&mut out, "compression", "compression", &[NO_COMPRESSION]);
+
+		let window: Vec<u8> = [0, 0, self.width as i32 - 1, self.height as i32 - 1]
+			.iter()
+			.flat_map(|v| v.to_le_bytes())
+			.collect();
+		write_attribute(&mut out, "dataWindow", "box2i", &window);
+		write_attribute(&mut out, "displayWindow", "box2i", &window);
+		write_attribute(&mut out, "lineOrder", "lineOrder", &[0]);
+		write_attribute(&mut out, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+		write_attribute(&mut out, "screenWindowCenter", "v2f", &[0; 8]);
+		write_attribute(&mut out, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+		for (name, v) in &self.vectors {
+			let value: Vec<u8> = [v.x, v.y].iter().flat_map(|c| c.to_le_bytes()).collect();
+			write_attribute(&mut out, name, "v2f", &value);
+		}
+		out.push(0);
+
+		let line_size = channels.len() * self.width * 4;
+		let table_start = out.len();
+		let first_line = table_start + self.height * 8;
+		for y in 0..self.height {
+			let offset = first_line + y * (line_size + 8);
+			out.extend_from_slice(&(offset as u64).to_le_bytes());
+		}
+
+		for y in 0..self.height {
+			out.extend_from_slice(&(y as i32).to_le_bytes());
+			out.extend_from_slice(&(line_size as i32).to_le_bytes());
+			for (_, values) in &channels {
+				for value in &values[y * self.width..(y + 1) * self.width] {
+					out.extend_from_slice(&value.to_le_bytes());
+				}
+			}
+		}
+		out
+	}
+
+	/// Decodes an uncompressed, single-part scanline EXR.
+	pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+		let mut reader = Reader { bytes, at: 0 };
+		if reader.i32()? != MAGIC {
+			return Err("Not an OpenEXR file".to_string());
+		}
+		let version = reader.take(4)?;
+		if version[0] != VERSION || version[1] & 0x1a != 0 {
+			return Err("Only single-part scanline EXR files are supported".to_string());
+		}
+
+		let mut channel_types: Vec<(String, i32)> = Vec::new();
+		let mut window = None;
+		let mut vectors = Vec::new();
+		loop {
+			let name = reader.string()?;
+			if name.is_empty() {
+				break;
+			}
+			let kind = reader.string()?;
+			let size = reader.i32()? as usize;
+			let value = reader.take(size)?;
+			let mut attribute = Reader { bytes: value, at: 0 };
+			match (name.as_str(), kind.as_str()) {
+				("channels", "chlist") => loop {
+					let channel = attribute.string()?;
+					if channel.is_empty() {
+						break;
+					}
+					let pixel_type = attribute.i32()?;
+					attribute.take(4)?;
+					if attribute.i32()? != 1 || attribute.i32()? != 1 {
+						return Err(format!("Channel {channel} is subsampled"));
+					}
+					channel_types.push((channel, pixel_type));
+				},
+				("compression", _) => {
+					if value.first() != Some(&NO_COMPRESSION) {
+						return Err("EXR is compressed; save it without compression".to_string());
+					}
+				}
+				("dataWindow", "box2i") => {
+					let min_x = attribute.i32()?;
+					let min_y = attribute.i32()?;
+					let max_x = attribute.i32()?;
+					let max_y = attribute.i32()?;
+					window =
+						Some((min_y, (max_x - min_x + 1) as usize, (max_y - min_y + 1) as usize));
+				}
+				(_, "v2f") => vectors.push((name, Vec2::new(attribute.f32()?, attribute.f32()?))),
+				_ => {}
+			}
+		}
+
+		let (min_y, width, height) = window.ok_or("EXR has no data window")?;
+		for (name, pixel_type) in &channel_types {
+			if *pixel_type != PIXEL_FLOAT && *pixel_type != PIXEL_HALF {
+				return Err(format!("Channel {name} is neither FLOAT nor HALF"));
+			}
+		}
+
+		let mut offsets = Vec::with_capacity(height);
+		for _ in 0..height {
+			offsets.push(reader.u64()? as usize);
+		}
+
+		let mut channels: Vec<(String, Vec<f32>)> = channel_types
+			.iter()
+			.map(|(name, _)| (name.clone(), vec![0.0; width * height]))
+			.collect();
+		for offset in offsets {
+			let mut line = Reader { bytes, at: offset };
+			let y = (line.i32()? - min_y) as usize;
+			if y >= height {
+				return Err(format!("Scanline {y} is outside the data window"));
+			}
+			line.i32()?;
+			for ((_, pixel_type), (_, values)) in channel_types.iter().zip(channels.iter_mut()) {
+				for value in &mut values[y * width..(y + 1) * width] {
+					*value = if *pixel_type == PIXEL_FLOAT {
+						line.f32()?
+					} else {
+						half_to_f32(u16::from_le_bytes([line.u8()?, line.u8()?]))
+					};
+				}
+			}
+		}
+
+		Ok(Self { width, height, channels, vectors })
+	}
+}
+
+fn write_attribute(out: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+	out.extend_from_slice(name.as_bytes());
+	out.push(0);
+	out.extend_from_slice(kind.as_bytes());
+	out.push(0);
+	out.extend_from_slice(&(value.len() as i32).to_le_bytes());
+	out.extend_from_slice(value);
+}
+
+/// Converts an IEEE 754 half-precision float to single precision.
+fn half_to_f32(bits: u16) -> f32 {
+	let sign = ((bits >> 15) as u32) << 31;
+	let exponent = ((bits >> 10) & 0x1f) as u32;
+	let mantissa = (bits & 0x3ff) as u32;
+	let value = match exponent {
+		0 if mantissa == 0 => sign,
+		0 => {
+			// Subnormal: renormalize the mantissa.
+			let shift = mantissa.leading_zeros() - 21;
+			sign | ((113 - shift) << 23) | (((mantissa << shift) & 0x3ff) << 13)
+		}
+		0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+		_ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+	};
+	f32::from_bits(value)
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+	at: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+		let end = self.at.checked_add(len).filter(|end| *end <= self.bytes.len());
+		let end = end.ok_or("Unexpected end of EXR data")?;
+		let slice = &self.bytes[self.at..end];
+		self.at = end;
+		Ok(slice)
+	}
+
+	fn u8(&mut self) -> Result<u8, String> {
+		Ok(self.take(1)?[0])
+	}
+
+	fn i32(&mut self) -> Result<i32, String> {
+		Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap_or_default()))
+	}
+
+	fn u64(&mut self) -> Result<u64, String> {
+		Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap_or_default()))
+	}
+
+	fn f32(&mut self) -> Result<f32, String> {
+		Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap_or_default()))
+	}
+
+	fn string(&mut self) -> Result<String, String> {
+		let len = self.bytes[self.at..]
+			.iter()
+			.position(|b| *b == 0)
+			.ok_or("Unterminated string in EXR header")?;
+		let text = String::from_utf8_lossy(self.take(len)?).into_owned();
+		self.take(1)?;
+		Ok(text)
+	}
+}
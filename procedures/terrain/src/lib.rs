@@ -1,9 +1,36 @@
+pub mod climate;
+pub mod feature;
+pub mod heightfield;
+pub mod modulation_grid;
+pub mod placement;
+pub mod preview;
+pub mod province;
+pub mod rare;
 pub mod region;
 
 use bevy::prelude::*;
-use noise::{NoiseFn, Perlin};
+use feature::{FeatureHit, FeatureId};
+use modulation_grid::ModulationGrid;
+use noise::Perlin;
+use province::{noise_height, ProvinceMap};
 use sdf::{Sdf, Sign, SignBoundary, SignUniformIntervals};
 use std::fmt::Debug;
+use std::sync::OnceLock;
+
+/// Priority level of an elevation modulation.
+/// Modulations are applied from the lowest to the highest level, so a higher level
+/// always has the final say over the height within its region.
+/// Within a level, modulations are applied in insertion order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ModulationPriority {
+	/// Broad landforms such as valleys and plateaus.
+	#[default]
+	Landform,
+	/// Local detail layered over the landforms.
+	Detail,
+	/// Hard constraints such as roads, which must not be cut by other modulations.
+	Constraint,
+}
 
 /// Trait for elevation modulations that modify terrain height in 2.5D
 /// Returns the height offset at a given (x, z) position (Y is ignored)
@@ -16,6 +43,28 @@ pub trait ElevationModulation: Send + Sync + Debug {
 		z: f32,
 		index: usize,
 	) -> f32;
+
+	/// The priority level at which this modulation is applied.
+	fn priority(&self) -> ModulationPriority {
+		ModulationPriority::default()
+	}
+
+	/// Signed distance to the region of the modulation (negative inside).
+	/// Modulations without a region return `None` and are never reported as features.
+	fn region_distance(&self, _x: f32, _z: f32) -> Option<f32> {
+		None
+	}
+
+	/// How strongly the modulation affects the given position, from 0 to 1.
+	fn influence(&self, _x: f32, _z: f32) -> f32 {
+		0.0
+	}
+
+	/// Box over (x, z) outside of which the modulation leaves the elevation as it is.
+	/// Modulations without bounds return `None` and are applied everywhere.
+	fn elevation_bounds(&self) -> Option<Rect> {
+		None
+	}
 }
 
 /// SDF representation of Perlin noise-based terrain
@@ -25,10 +74,20 @@ pub struct PerlinTerrainSdf {
 	perlin: Perlin,
 	/// The height scale
 	height_scale: f32,
-	/// The elevation modulations
-	elevation_modulations: Vec<Box<dyn ElevationModulation>>,
+	/// Frequency of the first noise octave
+	base_frequency: f64,
+	/// The elevation modulations and their feature ids, kept sorted by priority
+	elevation_modulations: Vec<(FeatureId, Box<dyn ElevationModulation>)>,
+	/// The id assigned to the next added modulation
+	next_feature_id: u64,
+	/// Size of the cells the modulations are looked up by
+	modulation_cell_size: f32,
+	/// The modulations near each cell, built on the first sample after they change
+	modulation_grid: OnceLock<ModulationGrid>,
 	/// Square describing bounds outside of which terrain is value 0
 	bounds: Option<[Vec2; 4]>,
+	/// Provinces replacing the single noise stack, if any
+	provinces: Option<ProvinceMap>,
 }
 
 impl PerlinTerrainSdf {
@@ -36,18 +95,85 @@ impl PerlinTerrainSdf {
 		Self {
 			perlin: Perlin::new(seed),
 			height_scale,
+			base_frequency: 0.05,
 			elevation_modulations: Vec::new(),
+			next_feature_id: 0,
+			modulation_cell_size: 64.0,
+			modulation_grid: OnceLock::new(),
 			bounds: None,
+			provinces: None,
 		}
 	}
 
+	pub fn with_base_frequency(mut self, base_frequency: f64) -> Self {
+		self.base_frequency = base_frequency;
+		self
+	}
+
 	pub fn with_bounds(mut self, bounds: [Vec2; 4]) -> Self {
 		self.bounds = Some(bounds);
 		self
 	}
 
-	pub fn add_elevation_modulation(&mut self, modulation: Box<dyn ElevationModulation>) {
-		self.elevation_modulations.push(modulation);
+	/// Sets the size of the cells the modulations are looked up by while sampling. Smaller
+	/// cells skip more of the modulations far from a point, at the cost of more memory.
+	pub fn with_modulation_cell_size(mut self, cell_size: f32) -> Self {
+		self.modulation_cell_size = cell_size;
+		self.modulation_grid = OnceLock::new();
+		self
+	}
+
+	/// Takes the base height from the provinces instead of this terrain's own noise.
+	/// The height scale still sets the bedrock level.
+	pub fn with_provinces(mut self, provinces: ProvinceMap) -> Self {
+		self.provinces = Some(provinces);
+		self
+	}
+
+	/// Adds a modulation after all modulations of the same or lower priority.
+	/// Returns the stable id of the modulation.
+	pub fn add_elevation_modulation(
+		&mut self,
+		modulation: Box<dyn ElevationModulation>,
+	) -> FeatureId {
+		let id = FeatureId(self.next_feature_id);
+		self.next_feature_id += 1;
+
+		let priority = modulation.priority();
+		let index = self.elevation_modulations.partition_point(|(_, m)| m.priority() <= priority);
+		self.elevation_modulations.insert(index, (id, modulation));
+		self.modulation_grid = OnceLock::new();
+		id
+	}
+
+	/// The grid of the modulations near each cell, built if they changed since the last sample.
+	pub fn modulation_grid(&self) -> &ModulationGrid {
+		self.modulation_grid.get_or_init(|| {
+			let bounds = self.elevation_modulations.iter().map(|(_, m)| m.elevation_bounds());
+			ModulationGrid::new(self.modulation_cell_size, bounds)
+		})
+	}
+
+	/// The modulations that can change the height at the (x, z) position, in the order they
+	/// are applied.
+	fn modulations_at(
+		&self,
+		world_x: f32,
+		world_z: f32,
+	) -> impl Iterator<Item = &(FeatureId, Box<dyn ElevationModulation>)> {
+		let indices = self.modulation_grid().modulations_at(world_x, world_z);
+		indices.iter().map(|index| &self.elevation_modulations[*index])
+	}
+
+	/// Returns the features affecting the given (x, z) position in the order they are applied.
+	pub fn features_at(&self, world_x: f32, world_z: f32) -> Vec<FeatureHit> {
+		self.modulations_at(world_x, world_z)
+			.filter_map(|(id, modulation)| {
+				let signed_distance = modulation.region_distance(world_x, world_z)?;
+				let influence = modulation.influence(world_x, world_z);
+				(influence > 0.0).then_some(FeatureHit { id: *id, signed_distance, influence })
+			})
+			.collect()
 	}
 
 	/// Calculate the terrain height at a given (x, z) position
@@ -63,27 +189,11 @@ impl PerlinTerrainSdf {
 			}
 		}
 
-		// Generate height using multiple octaves of noise
-		let mut height = 0.0;
-		let mut amplitude = 1.0;
-		let mut frequency = 0.05;
-		// let max_value = 0.0;
-
-		for _ in 0..4 {
-			let sample =
-				self.perlin.get([world_x as f64 * frequency, world_z as f64 * frequency]) as f32;
-			height += sample * amplitude;
-			// max_value += amplitude;
-			amplitude *= 0.5;
-			frequency *= 2.0;
+		if let Some(provinces) = &self.provinces {
+			return provinces.height_at(world_x, world_z);
 		}
 
-		let exponent = 1.1; // >1 exaggerates contrast, <1 flattens
-		let sign = height.signum();
-		let height = sign * height.abs().powf(exponent);
-		let height = height * self.height_scale;
-
-		height
+		noise_height(&self.perlin, self.height_scale, self.base_frequency, world_x, world_z)
 	}
 
 	/*pub fn height_at_with_modulations_up_to(&self, world_x: f32, world_z: f32, index: usize) -> f32 {
@@ -97,17 +207,17 @@ impl PerlinTerrainSdf {
 
 	pub fn height_at_with_all_modulations(&self, world_x: f32, world_z: f32) -> f32 {
 		let mut terrain_height = self.height_at(world_x, world_z);
-		for modulation in &self.elevation_modulations {
+		for (_, modulation) in self.modulations_at(world_x, world_z) {
 			terrain_height = modulation.modify_elevation(self, terrain_height, world_x, world_z, 0);
 		}
 		terrain_height
 	}
-}
 
-impl Sdf for PerlinTerrainSdf {
-	fn distance(&self, p: Vec3) -> f32 {
+	/// Height of the rendered surface at the (x, z) position, which is the modulated height
+	/// softened above and below the height limits.
+	pub fn surface_height(&self, world_x: f32, world_z: f32) -> f32 {
 		// Apply elevation modulations (2.5D height offsets)
-		let mut terrain_height = self.height_at_with_all_modulations(p.x, p.z);
+		let mut terrain_height = self.height_at_with_all_modulations(world_x, world_z);
 
 		// This keeps the terrain height within a max.
 		// TODO: make this configurable via the TerrainConfig.
@@ -120,6 +230,13 @@ impl Sdf for PerlinTerrainSdf {
 		} else if terrain_height < -10.0 {
 			terrain_height = -10.0 - (0.75 * (terrain_height + 10.0));
 		}
+		terrain_height
+	}
+}
+
+impl Sdf for PerlinTerrainSdf {
+	fn distance(&self, p: Vec3) -> f32 {
+		let terrain_height = self.surface_height(p.x, p.z);
 
 		// Define bedrock level (bottom of world)
 		let bedrock_level = -self.height_scale * 4.0;
@@ -155,3 +272,116 @@ impl Sdf for PerlinTerrainSdf {
 		intervals
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::region::{
+		affine::RegionAffineModulation, flattening::RegionFlatteningModulation,
+		grading::RegionGradingModulation, CircleRegion, RectRegion, Region2D,
+	};
+
+	fn road() -> RegionGradingModulation {
+		RegionGradingModulation::new(
+			Region2D::Rect(RectRegion {
+				center: Vec2::ZERO,
+				half_extents: Vec2::new(10.0, 1.0),
+				round: 0.1,
+			}),
+			Vec2::new(-10.0, 0.0),
+			1.0,
+			Vec2::new(10.0, 0.0),
+			1.0,
+			None,
+			0.4,
+			0.2,
+		)
+	}
+
+	fn valley(offset: f32) -> RegionAffineModulation {
+		RegionAffineModulation::new(
+			Region2D::Circle(CircleRegion { center: Vec2::ZERO, radius: 100.0 }),
+			0.0,
+			offset,
+			10.0,
+			10.0,
+		)
+	}
+
+	#[test]
+	fn test_later_valley_does_not_cut_road() {
+		let mut sdf = PerlinTerrainSdf::new(0, 5.0);
+		sdf.add_elevation_modulation(Box::new(road()));
+		sdf.add_elevation_modulation(Box::new(valley(-3.0)));
+
+		// on the road the graded height wins
+		assert!((sdf.height_at_with_all_modulations(0.0, 0.0) - 1.0).abs() < 1e-5);
+
+		// off the road the valley still applies
+		assert!((sdf.height_at_with_all_modulations(30.0, 30.0) + 3.0).abs() < 1e-5);
+	}
+
+	#[test]
+	fn test_priority_overrides_insertion_order() {
+		let mut sdf = PerlinTerrainSdf::new(0, 5.0);
+		sdf.add_elevation_modulation(Box::new(
+			valley(2.0).with_priority(ModulationPriority::Detail),
+		));
+		sdf.add_elevation_modulation(Box::new(valley(-3.0)));
+
+		assert!((sdf.height_at_with_all_modulations(0.0, 0.0) - 2.0).abs() < 1e-5);
+	}
+
+	#[test]
+	fn test_same_priority_keeps_insertion_order() {
+		let mut sdf = PerlinTerrainSdf::new(0, 5.0);
+		sdf.add_elevation_modulation(Box::new(valley(2.0)));
+		sdf.add_elevation_modulation(Box::new(valley(-3.0)));
+
+		assert!((sdf.height_at_with_all_modulations(0.0, 0.0) + 3.0).abs() < 1e-5);
+	}
+
+	#[test]
+	fn test_features_at() {
+		let mut sdf = PerlinTerrainSdf::new(0, 5.0);
+		let valley_id = sdf.add_elevation_modulation(Box::new(valley(-3.0)));
+		let road_id = sdf.add_elevation_modulation(Box::new(road()));
+
+		let hits = sdf.features_at(0.0, 0.0);
+		assert_eq!(hits.iter().map(|hit| hit.id).collect::<Vec<_>>(), vec![valley_id, road_id]);
+		assert!(hits.iter().all(|hit| hit.signed_distance < 0.0 && hit.influence == 1.0));
+
+		// only the valley reaches this far from the road
+		let hits = sdf.features_at(30.0, 30.0);
+		assert_eq!(hits.iter().map(|hit| hit.id).collect::<Vec<_>>(), vec![valley_id]);
+
+		assert!(sdf.features_at(500.0, 500.0).is_empty());
+	}
+
+	#[test]
+	fn test_footprint_is_flattened_to_its_lowest_corner() {
+		let mut sdf = PerlinTerrainSdf::new(0, 5.0);
+		let footprint = Rect::new(4.0, 4.0, 8.0, 10.0);
+		let corners = [(4.0, 4.0), (8.0, 4.0), (4.0, 10.0), (8.0, 10.0)];
+		let lowest = corners
+			.iter()
+			.map(|(x, z)| sdf.height_at_with_all_modulations(*x, *z))
+			.fold(f32::INFINITY, f32::min);
+
+		let flattening = RegionFlatteningModulation::footprint(&sdf, footprint, 2.0);
+		assert_eq!(flattening.bounds(), footprint.inflate(2.0));
+		let outside = sdf.height_at_with_all_modulations(20.0, 20.0);
+		sdf.add_elevation_modulation(Box::new(flattening));
+
+		// The whole footprint is level with its lowest corner
+		for (x, z) in corners.into_iter().chain([(6.0, 7.0), (4.5, 9.5)]) {
+			assert!((sdf.height_at_with_all_modulations(x, z) - lowest).abs() < 1e-5);
+		}
+
+		// Past the falloff the terrain keeps its own height
+		assert!((sdf.height_at_with_all_modulations(20.0, 20.0) - outside).abs() < 1e-5);
+		let hits = sdf.features_at(6.0, 11.0);
+		assert!(hits.iter().all(|hit| hit.influence > 0.0 && hit.influence < 1.0));
+		assert_eq!(hits.len(), 1);
+	}
+}
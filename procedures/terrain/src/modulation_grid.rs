@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+/// Most cells a modulation is bucketed into before it is treated as covering everything.
+const MAX_CELLS: i64 = 4096;
+
+/// A grid over (x, z) of the elevation modulations that can change the height within each cell,
+/// so sampling a point only applies the modulations near it rather than every one.
+///
+/// Modulations without bounds, or with bounds too large to bucket, are kept in every cell.
+/// Each cell lists its modulations in the order they are applied.
+#[derive(Debug, Clone, Default)]
+pub struct ModulationGrid {
+	cell_size: f32,
+	cells: HashMap<IVec2, Vec<usize>>,
+	/// The modulations applied everywhere, which are all a cell without its own list has
+	unbounded: Vec<usize>,
+}
+
+impl ModulationGrid {
+	/// Buckets the bounds of the modulations, in the order they are applied, into cells of the
+	/// size.
+	pub fn new(cell_size: f32, bounds: impl IntoIterator<Item = Option<Rect>>) -> Self {
+		let cell_size = cell_size.max(f32::EPSILON);
+		let mut grid = Self { cell_size, ..default() };
+		let bucketed: Vec<(usize, Option<(IVec2, IVec2)>)> = bounds
+			.into_iter()
+			.enumerate()
+			.map(|(index, bounds)| (index, bounds.and_then(|bounds| grid.cells_of(bounds))))
+			.collect();
+
+		for (index, cells) in &bucketed {
+			match cells {
+				Some((min, max)) => {
+					for x in min.x..=max.x {
+						for y in min.y..=max.y {
+							grid.cells.entry(IVec2::new(x, y)).or_default().push(*index);
+						}
+					}
+				}
+				None => grid.unbounded.push(*index),
+			}
+		}
+
+		// Cells apply the modulations everywhere in order with their own
+		if !grid.unbounded.is_empty() {
+			for indices in grid.cells.values_mut() {
+				indices.extend_from_slice(&grid.unbounded);
+				indices.sort_unstable();
+			}
+		}
+		grid
+	}
+
+	pub fn cell_size(&self) -> f32 {
+		self.cell_size
+	}
+
+	/// The cell the (x, z) position is in.
+	pub fn cell(&self, x: f32, z: f32) -> IVec2 {
+		(Vec2::new(x, z) / self.cell_size).floor().as_ivec2()
+	}
+
+	/// The indices of the modulations that can change the height at the (x, z) position, in
+	/// the order they are applied.
+	pub fn modulations_at(&self, x: f32, z: f32) -> &[usize] {
+		self.cells.get(&self.cell(x, z)).map_or(&self.unbounded, Vec::as_slice)
+	}
+
+	/// The first and last cells covering the bounds, or none when they cover too many.
+	fn cells_of(&self, bounds: Rect) -> Option<(IVec2, IVec2)> {
+		if !bounds.min.is_finite() || !bounds.max.is_finite() {
+			return None;
+		}
+		let (min, max) =
+			(self.cell(bounds.min.x, bounds.min.y), self.cell(bounds.max.x, bounds.max.y));
+		let span = |min: i32, max: i32| i64::from(max) - i64::from(min) + 1;
+		(span(min.x, max.x) * span(min.y, max.y) <= MAX_CELLS).then_some((min, max))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::region::{affine::RegionAffineModulation, CircleRegion, Region2D};
+	use crate::PerlinTerrainSdf;
+
+	#[test]
+	fn test_cells_keep_the_nearby_modulations_in_order() {
+		let near = Rect::new(0.0, 0.0, 10.0, 10.0);
+		let far = Rect::new(100.0, 100.0, 110.0, 110.0);
+		let grid = ModulationGrid::new(16.0, [Some(near), None, Some(far), Some(near)]);
+
+		assert_eq!(grid.modulations_at(5.0, 5.0), &[0, 1, 3]);
+		assert_eq!(grid.modulations_at(105.0, 105.0), &[1, 2]);
+		assert_eq!(grid.modulations_at(-50.0, 50.0), &[1]);
+
+		// Bounds over too many cells apply everywhere
+		let world = Rect::new(-1e6, -1e6, 1e6, 1e6);
+		assert_eq!(ModulationGrid::new(16.0, [Some(world)]).modulations_at(-50.0, 50.0), &[0]);
+	}
+
+	#[test]
+	fn test_hashed_heights_match_every_modulation() {
+		let mut sdf = PerlinTerrainSdf::new(3, 5.0).with_modulation_cell_size(8.0);
+		for i in 0..64 {
+			let center = Vec2::new((i % 8) as f32, (i / 8) as f32) * 30.0;
+			sdf.add_elevation_modulation(Box::new(RegionAffineModulation::new(
+				Region2D::Circle(CircleRegion { center, radius: 6.0 + i as f32 * 0.1 }),
+				0.5,
+				(i % 5) as f32 - 2.0,
+				2.0,
+				4.0,
+			)));
+		}
+		// Thin triangles, whose sharp tips reach much further than the outer radius
+		for i in 0..8 {
+			let base = Vec2::new(i as f32 * 30.0, -400.0);
+			let tip = base + Vec2::new(0.0, 20.0) * if i % 2 == 0 { 1.0 } else { -1.0 };
+			let (left, right) = (base - Vec2::X, base + Vec2::X);
+			let vertices = if i % 2 == 0 { [left, right, tip] } else { [right, left, tip] };
+			sdf.add_elevation_modulation(Box::new(RegionAffineModulation::new(
+				Region2D::convex_from_ccw_vertices(&vertices),
+				0.5,
+				2.0,
+				2.0,
+				4.0,
+			)));
+		}
+
+		let around_circles =
+			(0..400).map(|i| ((i % 20) as f32 * 12.7 - 10.0, (i / 20) as f32 * 12.3 - 10.0));
+		let past_tips = (0..8).flat_map(|i| {
+			let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+			(0..40).map(move |t| (i as f32 * 30.0, -400.0 + side * (20.0 + t as f32 * 2.5)))
+		});
+		for (x, z) in around_circles.chain(past_tips) {
+			let mut expected = sdf.height_at(x, z);
+			for (_, modulation) in &sdf.elevation_modulations {
+				expected = modulation.modify_elevation(&sdf, expected, x, z, 0);
+			}
+			assert_eq!(sdf.height_at_with_all_modulations(x, z), expected, "at ({x}, {z})");
+			assert!(sdf.modulation_grid().modulations_at(x, z).len() <= 4);
+		}
+	}
+}
@@ -0,0 +1,195 @@
+use crate::province::cell_hash01;
+use crate::region::{affine::RegionAffineModulation, CircleRegion, Region2D};
+use crate::{ModulationPriority, PerlinTerrainSdf};
+use bevy::prelude::*;
+
+/// Salt of the placement yaw hash, so yaws don't correlate with other per-position draws.
+const YAW_SALT: u32 = 0x7a3;
+/// Positions are hashed at this many cells per world unit when drawing yaws.
+const YAW_CELLS_PER_UNIT: f32 = 1024.0;
+
+/// A patch of terrain flattened under a placed object.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flattening {
+	/// Radius of the flat patch around the object
+	pub radius: f32,
+	/// Width of the blend from the patch back to the terrain
+	pub falloff: f32,
+}
+
+/// Places objects on the terrain surface, oriented to its normal within constraints.
+///
+/// The object's local up follows the surface normal, tilted at most `max_tilt` radians from
+/// vertical, and turned about it by a yaw drawn from the seed and the position. Objects with a
+/// footprint sit at the lowest surface point under it so they don't float on slopes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfacePlacement {
+	/// Largest angle between the object's up and the world up, in radians
+	pub max_tilt: f32,
+	/// Seed of the random yaw; `None` keeps the object facing along its own axes
+	pub yaw_seed: Option<u32>,
+	/// Half extents of the object's base in the xz plane
+	pub footprint: Vec2,
+	/// Step of the finite differences the normal is taken from
+	pub normal_step: f32,
+	/// Flattens the terrain under the object, which then stands upright
+	pub flattening: Option<Flattening>,
+}
+
+impl Default for SurfacePlacement {
+	fn default() -> Self {
+		Self {
+			max_tilt: std::f32::consts::FRAC_PI_2,
+			yaw_seed: None,
+			footprint: Vec2::ZERO,
+			normal_step: 0.001,
+			flattening: None,
+		}
+	}
+}
+
+/// Where and how a [`SurfacePlacement`] put an object.
+#[derive(Debug, Clone)]
+pub struct Placement {
+	pub transform: Transform,
+	/// Surface normal at the placement, before the tilt constraint
+	pub normal: Vec3,
+	/// Modulation flattening the terrain under the object, to add to the terrain
+	pub flattening: Option<RegionAffineModulation>,
+}
+
+impl SurfacePlacement {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn with_max_tilt(mut self, max_tilt: f32) -> Self {
+		self.max_tilt = max_tilt.max(0.0);
+		self
+	}
+
+	pub fn with_yaw_seed(mut self, seed: u32) -> Self {
+		self.yaw_seed = Some(seed);
+		self
+	}
+
+	pub fn with_footprint(mut self, footprint: Vec2) -> Self {
+		self.footprint = footprint.abs();
+		self
+	}
+
+	pub fn with_normal_step(mut self, normal_step: f32) -> Self {
+		self.normal_step = normal_step;
+		self
+	}
+
+	pub fn with_flattening(mut self, radius: f32, falloff: f32) -> Self {
+		self.flattening = Some(Flattening { radius, falloff });
+		self
+	}
+
+	/// Surface normal of the terrain at the xz position.
+	pub fn surface_normal(&self, terrain: &PerlinTerrainSdf, p: Vec2) -> Vec3 {
+		let h = self.normal_step;
+		let dx = terrain.surface_height(p.x + h, p.y) - terrain.surface_height(p.x - h, p.y);
+		let dz = terrain.surface_height(p.x, p.y + h) - terrain.surface_height(p.x, p.y - h);
+		Vec3::new(-dx, 2.0 * h, -dz).normalize()
+	}
+
+	/// Yaw of an object at the xz position, in radians.
+	pub fn yaw(&self, p: Vec2) -> f32 {
+		match self.yaw_seed {
+			Some(seed) => {
+				let cell = (p * YAW_CELLS_PER_UNIT).floor().as_ivec2();
+				cell_hash01(seed, cell, YAW_SALT) * std::f32::consts::TAU
+			}
+			None => 0.0,
+		}
+	}
+
+	/// Places an object at the xz position.
+	pub fn place(&self, terrain: &PerlinTerrainSdf, p: Vec2) -> Placement {
+		let normal = self.surface_normal(terrain, p);
+		let up = match self.flattening {
+			Some(_) => Vec3::Y,
+			None => tilt_towards(normal, self.max_tilt),
+		};
+		let rotation = Quat::from_rotation_arc(Vec3::Y, up) * Quat::from_rotation_y(self.yaw(p));
+
+		let height = match self.flattening {
+			Some(_) => terrain.surface_height(p.x, p.y),
+			None => self.footprint_height(terrain, p, rotation),
+		};
+
+		let flattening = self.flattening.map(|flattening| {
+			RegionAffineModulation::new(
+				Region2D::Circle(CircleRegion { center: p, radius: flattening.radius }),
+				0.0,
+				height,
+				0.0,
+				flattening.falloff,
+			)
+			.with_priority(ModulationPriority::Detail)
+		});
+
+		Placement {
+			transform: Transform::from_translation(Vec3::new(p.x, height, p.y))
+				.with_rotation(rotation),
+			normal,
+			flattening,
+		}
+	}
+
+	/// Lowest surface height under the corners and center of the rotated footprint.
+	fn footprint_height(&self, terrain: &PerlinTerrainSdf, p: Vec2, rotation: Quat) -> f32 {
+		let f = self.footprint;
+		[Vec2::ZERO, Vec2::new(f.x, f.y), Vec2::new(-f.x, f.y), Vec2::new(f.x, -f.y), -f]
+			.into_iter()
+			.map(|corner| {
+				let offset = rotation * Vec3::new(corner.x, 0.0, corner.y);
+				terrain.surface_height(p.x + offset.x, p.y + offset.z)
+			})
+			.fold(f32::INFINITY, f32::min)
+	}
+}
+
+/// The normal, tilted back towards vertical until it is at most `max_tilt` from it.
+fn tilt_towards(normal: Vec3, max_tilt: f32) -> Vec3 {
+	let tilt = normal.angle_between(Vec3::Y);
+	if tilt <= max_tilt {
+		return normal;
+	}
+	Vec3::Y.slerp(normal, max_tilt / tilt)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_placement_follows_the_surface_within_constraints() {
+		let mut terrain = PerlinTerrainSdf::new(3, 1.0);
+		let p = Vec2::new(0.37, -0.21);
+		let normal = SurfacePlacement::new().surface_normal(&terrain, p);
+
+		let free = SurfacePlacement::new().place(&terrain, p);
+		assert!((free.transform.translation.y - terrain.surface_height(p.x, p.y)).abs() < 1e-6);
+		assert!((free.transform.up().dot(normal) - 1.0).abs() < 1e-4);
+
+		let upright =
+			SurfacePlacement::new().with_max_tilt(0.0).with_yaw_seed(9).place(&terrain, p);
+		assert!(upright.transform.up().dot(Vec3::Y) > 0.9999);
+		let again = SurfacePlacement::new().with_max_tilt(0.0).with_yaw_seed(9).place(&terrain, p);
+		assert_eq!(upright.transform.rotation, again.transform.rotation);
+
+		let flat = SurfacePlacement::new().with_flattening(0.05, 0.02).place(&terrain, p);
+		let Some(flattening) = flat.flattening else {
+			panic!("flattening placement should return a stamp");
+		};
+		terrain.add_elevation_modulation(Box::new(flattening));
+		let edge = p + Vec2::new(0.04, 0.0);
+		assert!(
+			(terrain.surface_height(edge.x, edge.y) - flat.transform.translation.y).abs() < 1e-5
+		);
+	}
+}
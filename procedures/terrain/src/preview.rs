@@ -0,0 +1,168 @@
+use crate::climate::ClimateModel;
+use crate::PerlinTerrainSdf;
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use rayon::prelude::*;
+
+const LOW_COLOR: Vec3 = Vec3::new(0.36, 0.52, 0.28);
+const HIGH_COLOR: Vec3 = Vec3::new(0.85, 0.84, 0.8);
+const WATER_COLOR: Vec3 = Vec3::new(0.16, 0.34, 0.55);
+
+/// A small top-down image of a world, rasterized on the CPU from its heights.
+///
+/// Heights are shaded by elevation over the range found in the region and lit from the
+/// north-west; with a climate, each pixel is tinted by its biome. Nothing is meshed, so a grid
+/// of seeds can be previewed in milliseconds.
+#[derive(Debug, Clone)]
+pub struct WorldPreview {
+	pub width: u32,
+	pub height: u32,
+	/// Corner of the previewed region with the lowest x and z
+	pub min: Vec2,
+	/// Corner of the previewed region with the highest x and z
+	pub max: Vec2,
+	/// Heights below this are drawn as water
+	pub sea_level: Option<f32>,
+	/// How much biome colors replace the elevation colors, from 0 to 1
+	pub biome_tint: f32,
+	/// Strength of the hill shading
+	pub relief: f32,
+}
+
+impl WorldPreview {
+	pub fn new(width: u32, height: u32) -> Self {
+		Self {
+			width: width.max(1),
+			height: height.max(1),
+			min: Vec2::splat(-100.0),
+			max: Vec2::splat(100.0),
+			sea_level: None,
+			biome_tint: 0.6,
+			relief: 4.0,
+		}
+	}
+
+	pub fn with_region(mut self, min: Vec2, max: Vec2) -> Self {
+		self.min = min;
+		self.max = max;
+		self
+	}
+
+	pub fn with_sea_level(mut self, sea_level: f32) -> Self {
+		self.sea_level = Some(sea_level);
+		self
+	}
+
+	pub fn with_biome_tint(mut self, biome_tint: f32) -> Self {
+		self.biome_tint = biome_tint.clamp(0.0, 1.0);
+		self
+	}
+
+	pub fn with_relief(mut self, relief: f32) -> Self {
+		self.relief = relief;
+		self
+	}
+
+	/// World position at the center of a pixel, with rows running along x from the lowest z.
+	pub fn position(&self, x: u32, y: u32) -> Vec2 {
+		let t = (Vec2::new(x as f32, y as f32) + 0.5)
+			/ Vec2::new(self.width as f32, self.height as f32);
+		self.min + (self.max - self.min) * t
+	}
+
+	/// Samples the height of every pixel.
+	pub fn heights(&self, height_at: impl Fn(Vec2) -> f32 + Sync) -> Vec<f32> {
+		(0..self.width * self.height)
+			.into_par_iter()
+			.map(|index| height_at(self.position(index % self.width, index / self.width)))
+			.collect()
+	}
+
+	/// Rasterizes RGBA pixels from a height function, tinted by the climate's biomes if given.
+	pub fn rasterize(
+		&self,
+		height_at: impl Fn(Vec2) -> f32 + Sync,
+		climate: Option<&ClimateModel>,
+	) -> Vec<[u8; 4]> {
+		let heights = self.heights(height_at);
+		let (low, high) = heights
+			.iter()
+			.fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), h| (low.min(*h), high.max(*h)));
+		let range = (high - low).max(f32::EPSILON);
+		let pixel = (self.max - self.min) / Vec2::new(self.width as f32, self.height as f32);
+		let light = Vec3::new(-1.0, 1.0, -1.0).normalize();
+		let (width, height) = (self.width as usize, self.height as usize);
+
+		(0..width * height)
+			.into_par_iter()
+			.map(|index| {
+				let (x, y) = (index % width, index / width);
+				let h = heights[index];
+				let at = |x: usize, y: usize| heights[y * width + x];
+				if self.sea_level.is_some_and(|sea_level| h < sea_level) {
+					return to_rgba(WATER_COLOR);
+				}
+
+				let t = (h - low) / range;
+				let mut color = LOW_COLOR.lerp(HIGH_COLOR, t);
+				if let Some(climate) = climate {
+					let p = self.position(x as u32, y as u32);
+					let biome = climate.biome_at(p.x, p.y, h).color().to_linear();
+					let biome = Vec3::new(biome.red, biome.green, biome.blue);
+					color = color.lerp(biome, self.biome_tint);
+				}
+
+				// Hill shading from the slope across neighbouring pixels
+				let dx = (at((x + 1).min(width - 1), y) - at(x.saturating_sub(1), y)) / pixel.x;
+				let dz = (at(x, (y + 1).min(height - 1)) - at(x, y.saturating_sub(1))) / pixel.y;
+				let normal = Vec3::new(-dx * 0.5, 1.0 / self.relief.max(f32::EPSILON), -dz * 0.5);
+				let shade = 0.55 + 0.45 * normal.normalize().dot(light).max(0.0);
+				to_rgba(color * shade)
+			})
+			.collect()
+	}
+
+	/// Renders a preview of the terrain into an image for UI nodes.
+	pub fn render(&self, terrain: &PerlinTerrainSdf, climate: Option<&ClimateModel>) -> Image {
+		let pixels = self.rasterize(|p| terrain.surface_height(p.x, p.y), climate);
+		Image::new(
+			Extent3d { width: self.width, height: self.height, depth_or_array_layers: 1 },
+			TextureDimension::D2,
+			pixels.into_iter().flatten().collect(),
+			TextureFormat::Rgba8UnormSrgb,
+			RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+		)
+	}
+}
+
+/// Encodes a linear color as sRGB bytes.
+fn to_rgba(color: Vec3) -> [u8; 4] {
+	Color::linear_rgb(color.x, color.y, color.z).to_srgba().to_u8_array()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Instant;
+
+	#[test]
+	fn test_previews_differ_by_seed_and_stay_fast() {
+		let preview = WorldPreview::new(64, 64).with_region(Vec2::splat(-50.0), Vec2::splat(50.0));
+		let climate = ClimateModel::new(1);
+
+		let (one, two) = (PerlinTerrainSdf::new(1, 5.0), PerlinTerrainSdf::new(2, 5.0));
+		let start = Instant::now();
+		let first = preview.rasterize(|p| one.surface_height(p.x, p.y), Some(&climate));
+		let second = preview.rasterize(|p| two.surface_height(p.x, p.y), Some(&climate));
+		assert!(start.elapsed().as_secs_f32() < 1.0);
+
+		assert_eq!(first.len(), 64 * 64);
+		assert_ne!(first, second);
+		let flat = preview.with_sea_level(1.0).rasterize(|_| 0.0, None);
+		assert!(flat.iter().all(|pixel| *pixel == to_rgba(WATER_COLOR)));
+
+		let image = WorldPreview::new(8, 4).render(&two, None);
+		assert_eq!(image.size(), UVec2::new(8, 4));
+	}
+}
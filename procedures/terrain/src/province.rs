@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+use noise::{NoiseFn, Perlin};
+
+/// Terrain parameters of a province.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProvinceParams {
+	/// Seed of the province's noise
+	pub seed: u32,
+	/// The height scale
+	pub height_scale: f32,
+	/// Frequency of the first noise octave
+	pub base_frequency: f64,
+	/// Height added to the whole province, to raise plateaus or sink lowlands
+	pub offset: f32,
+}
+
+impl ProvinceParams {
+	pub fn new(seed: u32, height_scale: f32) -> Self {
+		Self { seed, height_scale, base_frequency: 0.05, offset: 0.0 }
+	}
+
+	pub fn with_base_frequency(mut self, base_frequency: f64) -> Self {
+		self.base_frequency = base_frequency;
+		self
+	}
+
+	pub fn with_offset(mut self, offset: f32) -> Self {
+		self.offset = offset;
+		self
+	}
+}
+
+/// Identifies a province by the grid cell holding its site.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProvinceId(pub IVec2);
+
+/// A province's parameters and noise.
+#[derive(Debug, Clone)]
+pub struct Province {
+	pub params: ProvinceParams,
+	perlin: Perlin,
+}
+
+impl Province {
+	pub fn new(params: ProvinceParams) -> Self {
+		Self { params, perlin: Perlin::new(params.seed) }
+	}
+
+	/// The noise height of the province, before any modulations.
+	pub fn height_at(&self, world_x: f32, world_z: f32) -> f32 {
+		noise_height(
+			&self.perlin,
+			self.params.height_scale,
+			self.params.base_frequency,
+			world_x,
+			world_z,
+		) + self.params.offset
+	}
+}
+
+/// Octave noise shared by [PerlinTerrainSdf](crate::PerlinTerrainSdf) and the provinces.
+pub(crate) fn noise_height(
+	perlin: &Perlin,
+	height_scale: f32,
+	base_frequency: f64,
+	world_x: f32,
+	world_z: f32,
+) -> f32 {
+	// Generate height using multiple octaves of noise
+	let mut height = 0.0;
+	let mut amplitude = 1.0;
+	let mut frequency = base_frequency;
+
+	for _ in 0..4 {
+		let sample = perlin.get([world_x as f64 * frequency, world_z as f64 * frequency]) as f32;
+		height += sample * amplitude;
+		amplitude *= 0.5;
+		frequency *= 2.0;
+	}
+
+	let exponent = 1.1; // >1 exaggerates contrast, <1 flattens
+	let sign = height.signum();
+	let height = sign * height.abs().powf(exponent);
+	height * height_scale
+}
+
+/// Partitions the world into provinces, each with its own terrain parameters.
+///
+/// Provinces are the Voronoi cells of one jittered site per grid cell. Each province picks
+/// a parameter set from the palette by hash, and heights are blended across borders
+/// within the falloff distance.
+#[derive(Debug, Clone)]
+pub struct ProvinceMap {
+	seed: u32,
+	/// Size of the grid cells, roughly the size of a province
+	cell_size: f32,
+	/// Distance from a border over which neighbouring provinces are blended
+	falloff: f32,
+	palette: Vec<Province>,
+}
+
+impl ProvinceMap {
+	pub fn new(seed: u32, cell_size: f32, palette: Vec<ProvinceParams>) -> Self {
+		Self {
+			seed,
+			cell_size: cell_size.max(f32::EPSILON),
+			falloff: cell_size * 0.1,
+			palette: palette.into_iter().map(Province::new).collect(),
+		}
+	}
+
+	pub fn with_falloff(mut self, falloff: f32) -> Self {
+		self.falloff = falloff;
+		self
+	}
+
+	/// The site of the province in a grid cell.
+	pub fn site(&self, id: ProvinceId) -> Vec2 {
+		let jitter = Vec2::new(self.hash01(id.0, 0), self.hash01(id.0, 1));
+		(id.0.as_vec2() + jitter) * self.cell_size
+	}
+
+	/// The province whose site is nearest to the position.
+	pub fn province_at(&self, position: Vec2) -> ProvinceId {
+		self.sites_around(position)
+			.min_by(|(_, a), (_, b)| {
+				a.distance_squared(position).total_cmp(&b.distance_squared(position))
+			})
+			.map(|(id, _)| id)
+			.unwrap_or(ProvinceId(IVec2::ZERO))
+	}
+
+	/// The parameters and noise of a province, or `None` if the palette is empty.
+	pub fn province(&self, id: ProvinceId) -> Option<&Province> {
+		if self.palette.is_empty() {
+			return None;
+		}
+		let index = self.hash(id.0, 2) as usize % self.palette.len();
+		self.palette.get(index)
+	}
+
+	/// The blend weights of the provinces at a position, summing to one.
+	///
+	/// The nearest province always has a weight; a neighbour is blended in as the position
+	/// gets within the falloff distance of their shared border, reaching an even split on it.
+	pub fn weights(&self, position: Vec2) -> Vec<(ProvinceId, f32)> {
+		let distances: Vec<_> = self
+			.sites_around(position)
+			.map(|(id, site)| (id, site.distance(position)))
+			.collect();
+		let nearest = distances.iter().map(|(_, distance)| *distance).fold(f32::INFINITY, f32::min);
+
+		// Near a border the difference in distance is about twice the distance to the border
+		let mut weights: Vec<_> = distances
+			.into_iter()
+			.filter_map(|(id, distance)| {
+				let difference = distance - nearest;
+				let t = if self.falloff > 0.0 {
+					difference / (2.0 * self.falloff)
+				} else if difference > 0.0 {
+					f32::INFINITY
+				} else {
+					0.0
+				};
+				(t < 1.0).then(|| (id, 1.0 - smoothstep(t)))
+			})
+			.collect();
+
+		let total: f32 = weights.iter().map(|(_, weight)| weight).sum();
+		for (_, weight) in &mut weights {
+			*weight /= total;
+		}
+		weights
+	}
+
+	/// The province height at a position, blended across borders.
+	pub fn height_at(&self, world_x: f32, world_z: f32) -> f32 {
+		self.weights(Vec2::new(world_x, world_z))
+			.into_iter()
+			.filter_map(|(id, weight)| {
+				self.province(id).map(|province| weight * province.height_at(world_x, world_z))
+			})
+			.sum()
+	}
+
+	/// The sites of the cells around the position's cell, which always include its nearest site.
+	///
+	/// Jittered sites stay in their cells, so two rings of cells cover every site within
+	/// a cell of the border of the nearest province.
+	fn sites_around(&self, position: Vec2) -> impl Iterator<Item = (ProvinceId, Vec2)> + '_ {
+		let cell = (position / self.cell_size).floor().as_ivec2();
+		(-2..=2).flat_map(move |j| {
+			(-2..=2).map(move |i| {
+				let id = ProvinceId(cell + IVec2::new(i, j));
+				(id, self.site(id))
+			})
+		})
+	}
+
+	fn hash(&self, cell: IVec2, salt: u32) -> u32 {
+		cell_hash(self.seed, cell, salt)
+	}
+
+	fn hash01(&self, cell: IVec2, salt: u32) -> f32 {
+		cell_hash01(self.seed, cell, salt)
+	}
+}
+
+/// A hash of a grid cell under a seed, with a salt for drawing several values per cell.
+pub(crate) fn cell_hash(seed: u32, cell: IVec2, salt: u32) -> u32 {
+	let mut h = (cell.x as u32).wrapping_mul(0x8da6_b343)
+		^ (cell.y as u32).wrapping_mul(0xd816_3841)
+		^ seed.wrapping_mul(0xcb1a_b31f)
+		^ salt.wrapping_mul(0x1656_67b1);
+	h ^= h >> 15;
+	h = h.wrapping_mul(0x2c1b_3c6d);
+	h ^= h >> 12;
+	h = h.wrapping_mul(0x297a_2d39);
+	h ^= h >> 15;
+	h
+}
+
+/// [cell_hash] as a value from 0 to 1.
+pub(crate) fn cell_hash01(seed: u32, cell: IVec2, salt: u32) -> f32 {
+	(cell_hash(seed, cell, salt) >> 8) as f32 / (1u32 << 24) as f32
+}
+
+fn smoothstep(t: f32) -> f32 {
+	let t = t.clamp(0.0, 1.0);
+	t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn map() -> ProvinceMap {
+		ProvinceMap::new(
+			7,
+			200.0,
+			vec![
+				ProvinceParams::new(1, 5.0),
+				ProvinceParams::new(2, 12.0).with_base_frequency(0.02).with_offset(4.0),
+				ProvinceParams::new(3, 2.0).with_offset(-2.0),
+			],
+		)
+		.with_falloff(20.0)
+	}
+
+	#[test]
+	fn test_weights_blend_only_near_borders() {
+		let map = map();
+		for id in [IVec2::ZERO, IVec2::new(3, -2), IVec2::new(-5, 4)].map(ProvinceId) {
+			// A site is further than the falloff from any border
+			let site = map.site(id);
+			assert_eq!(map.province_at(site), id);
+			assert_eq!(map.weights(site), vec![(id, 1.0)]);
+			assert_eq!(
+				map.height_at(site.x, site.y),
+				map.province(id).map_or(0.0, |p| p.height_at(site.x, site.y))
+			);
+		}
+
+		for step in 0..200 {
+			let position = Vec2::new(step as f32 * 7.3 - 700.0, step as f32 * -3.1 + 50.0);
+			let weights = map.weights(position);
+			let total: f32 = weights.iter().map(|(_, weight)| weight).sum();
+			assert!((total - 1.0).abs() < 1e-5);
+		}
+	}
+
+	#[test]
+	fn test_heights_are_continuous_across_borders() {
+		let map = map();
+		let mut crossed = 0;
+		let mut previous = (map.province_at(Vec2::ZERO), map.height_at(0.0, 0.0));
+		for step in 1..20_000 {
+			let x = step as f32 * 0.1;
+			let province = map.province_at(Vec2::new(x, 0.0));
+			let height = map.height_at(x, 0.0);
+			if province != previous.0 {
+				crossed += 1;
+			}
+			assert!(
+				(height - previous.1).abs() < 0.5,
+				"jump of {} at x = {x}",
+				height - previous.1
+			);
+			previous = (province, height);
+		}
+		assert!(crossed > 0);
+	}
+}
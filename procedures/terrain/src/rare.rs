@@ -0,0 +1,180 @@
+use crate::province::cell_hash01;
+use bevy::prelude::*;
+
+/// A rare landmark that worlds place a few of, such as a giant tree or a crater lake.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RareStamp {
+	/// Name of the stamp, for listing and for games to match on
+	pub name: &'static str,
+	/// Chance of the stamp in each area of the planner, from 0 to 1
+	pub probability: f32,
+	/// Reach of the stamp, which keeps it clear of the edges of its cell
+	pub radius: f32,
+}
+
+impl RareStamp {
+	pub fn new(name: &'static str, probability: f32, radius: f32) -> Self {
+		Self { name, probability: probability.clamp(0.0, 1.0), radius }
+	}
+
+	pub const fn giant_tree() -> Self {
+		Self { name: "giant_tree", probability: 0.02, radius: 0.05 }
+	}
+
+	pub const fn crater_lake() -> Self {
+		Self { name: "crater_lake", probability: 0.005, radius: 0.8 }
+	}
+
+	pub const fn stone_circle() -> Self {
+		Self { name: "stone_circle", probability: 0.01, radius: 0.03 }
+	}
+}
+
+/// A rare stamp placed in the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RareFeature {
+	pub stamp: RareStamp,
+	/// Center of the feature in the xz plane
+	pub position: Vec2,
+	/// Yaw of the feature in radians, for stamps that aren't round
+	pub rotation: f32,
+	/// The planner cell the feature was drawn in
+	pub cell: IVec2,
+}
+
+/// Plans the rare features of a world from its seed.
+///
+/// The world is split into cells of the planner's area, and each stamp is drawn once per cell
+/// with its probability, so the same seed always gets the same landmarks and any region can be
+/// listed without planning the rest of the world.
+#[derive(Debug, Clone)]
+pub struct RareFeatures {
+	seed: u32,
+	/// Area the stamp probabilities are given per, 10 km² in kilometre worlds
+	pub area: f32,
+	pub stamps: Vec<RareStamp>,
+}
+
+impl RareFeatures {
+	/// A planner without stamps.
+	pub fn new(seed: u32) -> Self {
+		Self { seed, area: 10.0, stamps: Vec::new() }
+	}
+
+	/// A planner with the giant tree, crater lake and stone circle stamps.
+	pub fn with_default_stamps(seed: u32) -> Self {
+		Self::new(seed)
+			.with_stamp(RareStamp::giant_tree())
+			.with_stamp(RareStamp::crater_lake())
+			.with_stamp(RareStamp::stone_circle())
+	}
+
+	pub fn with_area(mut self, area: f32) -> Self {
+		self.area = area;
+		self
+	}
+
+	pub fn with_stamp(mut self, stamp: RareStamp) -> Self {
+		self.stamps.push(stamp);
+		self
+	}
+
+	pub fn seed(&self) -> u32 {
+		self.seed
+	}
+
+	/// Side of the square cells the stamps are drawn in.
+	pub fn cell_size(&self) -> f32 {
+		self.area.max(f32::EPSILON).sqrt()
+	}
+
+	/// The features drawn in a cell, in the order of the stamps.
+	pub fn features_in_cell(&self, cell: IVec2) -> Vec<RareFeature> {
+		let size = self.cell_size();
+		self.stamps
+			.iter()
+			.enumerate()
+			.filter_map(|(index, stamp)| {
+				let salt = index as u32 * 4;
+				if cell_hash01(self.seed, cell, salt) >= stamp.probability {
+					return None;
+				}
+				// Keep the stamp within its cell so neighbouring cells can't overlap it
+				let margin = stamp.radius.min(size / 2.0);
+				let jitter = Vec2::new(
+					cell_hash01(self.seed, cell, salt + 1),
+					cell_hash01(self.seed, cell, salt + 2),
+				);
+				let position =
+					cell.as_vec2() * size + Vec2::splat(margin) + jitter * (size - 2.0 * margin);
+				let rotation = cell_hash01(self.seed, cell, salt + 3) * std::f32::consts::TAU;
+				Some(RareFeature { stamp: *stamp, position, rotation, cell })
+			})
+			.collect()
+	}
+
+	/// The features centered within a rectangle of the xz plane.
+	pub fn features_in(&self, min: Vec2, max: Vec2) -> Vec<RareFeature> {
+		let size = self.cell_size();
+		let (first, last) = ((min / size).floor().as_ivec2(), (max / size).floor().as_ivec2());
+		let mut features = Vec::new();
+		for y in first.y..=last.y {
+			for x in first.x..=last.x {
+				features.extend(self.features_in_cell(IVec2::new(x, y)).into_iter().filter(
+					|feature| {
+						feature.position.cmpge(min).all() && feature.position.cmple(max).all()
+					},
+				));
+			}
+		}
+		features
+	}
+
+	/// The features of a stamp centered within a rectangle of the xz plane.
+	pub fn features_named(&self, name: &str, min: Vec2, max: Vec2) -> Vec<RareFeature> {
+		self.features_in(min, max)
+			.into_iter()
+			.filter(|feature| feature.stamp.name == name)
+			.collect()
+	}
+
+	/// The feature nearest to a position within a distance, if any.
+	pub fn nearest(&self, position: Vec2, distance: f32) -> Option<RareFeature> {
+		self.features_in(position - distance, position + distance)
+			.into_iter()
+			.filter(|feature| feature.position.distance(position) <= distance)
+			.min_by(|a, b| {
+				a.position
+					.distance_squared(position)
+					.total_cmp(&b.position.distance_squared(position))
+			})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_rare_features_follow_the_seed() {
+		let planner = RareFeatures::with_default_stamps(11);
+		let (min, max) = (Vec2::splat(-100.0), Vec2::splat(100.0));
+		let features = planner.features_in(min, max);
+		assert_eq!(features, RareFeatures::with_default_stamps(11).features_in(min, max));
+		assert_ne!(features, RareFeatures::with_default_stamps(12).features_in(min, max));
+
+		// 4000 cells of 10 km² expect 80 giant trees, 20 crater lakes and 40 stone circles
+		let count = |name| planner.features_named(name, min, max).len();
+		assert!((50..120).contains(&count("giant_tree")));
+		assert!((5..40).contains(&count("crater_lake")));
+		assert!((20..70).contains(&count("stone_circle")));
+
+		// Listing a region finds the same features as listing the world
+		let region = planner.features_in(Vec2::ZERO, max);
+		assert!(region.iter().all(|feature| features.contains(feature)));
+		let Some(feature) = region.first() else {
+			panic!("expected features in a quarter of the world");
+		};
+		assert_eq!(planner.nearest(feature.position + 0.001, 0.01), Some(*feature));
+	}
+}
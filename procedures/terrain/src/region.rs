@@ -1,5 +1,6 @@
 pub mod affine;
 pub mod branching;
+pub mod flattening;
 pub mod rounding;
 pub mod grading;
 
@@ -247,4 +248,133 @@ impl Region2D {
 			.reanchor(anchor)
 			.scale(scale_body, scale_detail)
 	}
+
+	/// Corners of the box bounding the region, leaving out any noise on its boundary.
+	pub fn bounds(&self) -> (Vec2, Vec2) {
+		match self {
+			Region2D::Rect(RectRegion { center, half_extents, .. }) => {
+				(*center - *half_extents, *center + *half_extents)
+			}
+			Region2D::Circle(CircleRegion { center, radius }) => {
+				(*center - Vec2::splat(*radius), *center + Vec2::splat(*radius))
+			}
+			Region2D::ConvexPoly(ConvexPolyRegion { normals, offsets }) => {
+				// Each vertex is where an edge meets the one before it
+				let mut bounds = (Vec2::splat(f32::INFINITY), Vec2::splat(-f32::INFINITY));
+				for i in 0..normals.len() {
+					let j = (i + normals.len() - 1) % normals.len();
+					let (a, b) = (normals[j], normals[i]);
+					let det = a.perp_dot(b);
+					if det.abs() <= f32::EPSILON {
+						continue;
+					}
+					let vertex = Vec2::new(
+						offsets[i] * a.y - offsets[j] * b.y,
+						offsets[j] * b.x - offsets[i] * a.x,
+					) / det;
+					bounds = (bounds.0.min(vertex), bounds.1.max(vertex));
+				}
+				bounds
+			}
+		}
+	}
+
+	/// Box over every point within the distance of the region, including as far as the noise
+	/// can push its boundary out.
+	pub fn bounds_within(&self, distance: f32, noise: Option<&RegionNoise>) -> Rect {
+		let noise = noise.map_or(0.0, |noise| noise.amplitude.abs());
+		let distance = distance.max(0.0) + noise;
+		match self {
+			// The distance to a polygon is to the lines of its edges, so the points within it
+			// form the polygon with its edges pushed out, whose corners move out further than
+			// the distance the sharper they are
+			Region2D::ConvexPoly(ConvexPolyRegion { normals, offsets }) => {
+				let pushed = Region2D::ConvexPoly(ConvexPolyRegion {
+					normals: normals.clone(),
+					offsets: offsets.iter().map(|offset| offset - distance).collect(),
+				});
+				let (min, max) = pushed.bounds();
+				Rect::from_corners(min, max)
+			}
+			_ => {
+				let (min, max) = self.bounds();
+				Rect::from_corners(min, max).inflate(distance)
+			}
+		}
+	}
+
+	/// The boundary of the region within [min, max] as line segments, traced with marching
+	/// squares over a grid with cells of the given size.
+	pub fn contour(&self, min: Vec2, max: Vec2, step: f32) -> Vec<(Vec2, Vec2)> {
+		let mut segments = Vec::new();
+		if step <= 0.0 {
+			return segments;
+		}
+		let cells = ((max - min) / step).ceil().max(Vec2::ZERO);
+		let (nx, nz) = (cells.x as usize, cells.y as usize);
+		let corner = |i: usize, j: usize| min + Vec2::new(i as f32, j as f32) * step;
+		let distances: Vec<f32> = (0..=nz)
+			.flat_map(|j| (0..=nx).map(move |i| (i, j)))
+			.map(|(i, j)| self.sdf(corner(i, j)))
+			.collect();
+		let distance = |i: usize, j: usize| distances[j * (nx + 1) + i];
+
+		for j in 0..nz {
+			for i in 0..nx {
+				// Corners counter-clockwise from the lowest, with the edges between them
+				let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+				let crossings: Vec<Vec2> = (0..4)
+					.filter_map(|edge| {
+						let (a, b) = (corners[edge], corners[(edge + 1) % 4]);
+						let (da, db) = (distance(a.0, a.1), distance(b.0, b.1));
+						if (da < 0.0) == (db < 0.0) {
+							return None;
+						}
+						let t = da / (da - db);
+						Some(corner(a.0, a.1).lerp(corner(b.0, b.1), t))
+					})
+					.collect();
+				// Saddle cells cross all four edges and are split into two segments
+				for pair in crossings.chunks_exact(2) {
+					segments.push((pair[0], pair[1]));
+				}
+			}
+		}
+		segments
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_contour_traces_circle_boundary() {
+		let region = Region2D::Circle(CircleRegion { center: Vec2::new(4.0, -2.0), radius: 10.0 });
+		let segments = region.contour(Vec2::splat(-20.0), Vec2::splat(20.0), 1.0);
+
+		assert!(!segments.is_empty());
+		for (a, b) in &segments {
+			assert!(region.sdf(*a).abs() < 0.1, "{a} is off the boundary");
+			assert!(region.sdf(*b).abs() < 0.1, "{b} is off the boundary");
+		}
+		let length: f32 = segments.iter().map(|(a, b)| a.distance(*b)).sum();
+		let circumference = std::f32::consts::TAU * 10.0;
+		assert!((length - circumference).abs() < 1.0, "The contour is {length} long");
+	}
+
+	#[test]
+	fn test_bounds_enclose_the_region() {
+		let triangle = Region2D::convex_from_ccw_vertices(&[
+			Vec2::new(1.0, -2.0),
+			Vec2::new(5.0, 0.0),
+			Vec2::new(2.0, 3.0),
+		]);
+		let (min, max) = triangle.bounds();
+		assert!(min.distance(Vec2::new(1.0, -2.0)) < 1e-4, "{min}");
+		assert!(max.distance(Vec2::new(5.0, 3.0)) < 1e-4, "{max}");
+
+		let circle = Region2D::Circle(CircleRegion { center: Vec2::new(4.0, -2.0), radius: 3.0 });
+		assert_eq!(circle.bounds(), (Vec2::new(1.0, -5.0), Vec2::new(7.0, 1.0)));
+	}
 }
@@ -1,5 +1,5 @@
 use crate::region::{Region2D, RegionNoise};
-use crate::{ElevationModulation, PerlinTerrainSdf};
+use crate::{ElevationModulation, ModulationPriority, PerlinTerrainSdf};
 use bevy::prelude::*;
 
 /// A unified modulation: applies both scaling (`a`) and offset (`b`) inside a smooth region.
@@ -14,6 +14,8 @@ pub struct RegionAffineModulation {
 	pub outer_radius: f32,
 	/// Optional noise for perturbing the region boundary
 	pub noise: Option<RegionNoise>,
+	/// The priority level of the modulation.
+	pub priority: ModulationPriority,
 }
 
 impl RegionAffineModulation {
@@ -31,6 +33,7 @@ impl RegionAffineModulation {
 			inner_radius,
 			outer_radius: outer_radius.max(inner_radius + 0.001),
 			noise: None,
+			priority: ModulationPriority::Landform,
 		}
 	}
 
@@ -40,6 +43,12 @@ impl RegionAffineModulation {
 		self
 	}
 
+	/// Sets the priority level of the modulation
+	pub fn with_priority(mut self, priority: ModulationPriority) -> Self {
+		self.priority = priority;
+		self
+	}
+
 	#[inline(always)]
 	fn smoothstep(t: f32) -> f32 {
 		let t = t.clamp(0.0, 1.0);
@@ -93,6 +102,7 @@ impl RegionAffineModulation {
 			inner_radius: new_inner_radius,
 			outer_radius: new_outer_radius,
 			noise: Some(noise.clone()),
+			priority: self.priority,
 		}
 	}
 }
@@ -115,4 +125,20 @@ impl ElevationModulation for RegionAffineModulation {
 
 		a * elevation + b
 	}
+
+	fn priority(&self) -> ModulationPriority {
+		self.priority
+	}
+
+	fn region_distance(&self, x: f32, z: f32) -> Option<f32> {
+		Some(self.region.sdf_with_noise(Vec2::new(x, z), self.noise.as_ref()))
+	}
+
+	fn influence(&self, x: f32, z: f32) -> f32 {
+		1.0 - self.region_weight(Vec2::new(x, z))
+	}
+
+	fn elevation_bounds(&self) -> Option<Rect> {
+		Some(self.region.bounds_within(self.outer_radius, self.noise.as_ref()))
+	}
 }
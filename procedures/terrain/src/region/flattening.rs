@@ -0,0 +1,108 @@
+use crate::region::{RectRegion, Region2D};
+use crate::{ElevationModulation, ModulationPriority, PerlinTerrainSdf};
+use bevy::prelude::*;
+
+/// Flattens the terrain to a single elevation over a building footprint.
+///
+/// The footprint is level all the way to its edges, and the terrain eases back to its own height
+/// over the falloff around it. The falloff is measured from the nearest point of the footprint,
+/// so it rounds off around the corners.
+#[derive(Debug, Clone)]
+pub struct RegionFlatteningModulation {
+	/// The footprint to flatten.
+	pub region: Region2D,
+	/// The elevation of the flattened footprint.
+	pub elevation: f32,
+	/// The distance beyond the footprint over which the terrain returns to its own height.
+	pub falloff: f32,
+	/// The priority level of the modulation.
+	pub priority: ModulationPriority,
+}
+
+impl RegionFlatteningModulation {
+	pub fn new(region: Region2D, elevation: f32, falloff: f32) -> Self {
+		Self {
+			region,
+			elevation,
+			falloff: falloff.max(0.001),
+			priority: ModulationPriority::Constraint,
+		}
+	}
+
+	/// Flattens a footprint to the height of its lowest corner on the terrain, so a building
+	/// on it is sunk into the slope rather than hovering over its low side.
+	///
+	/// The corners are sampled with the modulations already added to the terrain.
+	pub fn footprint(terrain: &PerlinTerrainSdf, footprint: Rect, falloff: f32) -> Self {
+		let corners = [
+			footprint.min,
+			Vec2::new(footprint.max.x, footprint.min.y),
+			Vec2::new(footprint.min.x, footprint.max.y),
+			footprint.max,
+		];
+		let elevation = corners
+			.iter()
+			.map(|corner| terrain.height_at_with_all_modulations(corner.x, corner.y))
+			.fold(f32::INFINITY, f32::min);
+		let region = Region2D::Rect(RectRegion {
+			center: footprint.center(),
+			half_extents: footprint.half_size(),
+			round: 0.0,
+		});
+		Self::new(region, elevation, falloff)
+	}
+
+	/// Sets the priority level of the modulation
+	pub fn with_priority(mut self, priority: ModulationPriority) -> Self {
+		self.priority = priority;
+		self
+	}
+
+	/// The area the modulation changes, which is the footprint grown by the falloff.
+	pub fn bounds(&self) -> Rect {
+		let (min, max) = self.region.bounds();
+		Rect::from_corners(min, max).inflate(self.falloff)
+	}
+
+	#[inline(always)]
+	fn smoothstep(t: f32) -> f32 {
+		let t = t.clamp(0.0, 1.0);
+		t * t * (3.0 - 2.0 * t)
+	}
+
+	#[inline(always)]
+	fn region_weight(&self, p: Vec2) -> f32 {
+		Self::smoothstep(self.region.sdf(p) / self.falloff)
+	}
+}
+
+impl ElevationModulation for RegionFlatteningModulation {
+	fn modify_elevation(
+		&self,
+		_perlin_terrain: &PerlinTerrainSdf,
+		elevation: f32,
+		x: f32,
+		z: f32,
+		_index: usize,
+	) -> f32 {
+		let weight = self.region_weight(Vec2::new(x, z));
+
+		weight * elevation + (1.0 - weight) * self.elevation
+	}
+
+	fn priority(&self) -> ModulationPriority {
+		self.priority
+	}
+
+	fn region_distance(&self, x: f32, z: f32) -> Option<f32> {
+		Some(self.region.sdf(Vec2::new(x, z)))
+	}
+
+	fn influence(&self, x: f32, z: f32) -> f32 {
+		1.0 - self.region_weight(Vec2::new(x, z))
+	}
+
+	fn elevation_bounds(&self) -> Option<Rect> {
+		Some(self.bounds())
+	}
+}
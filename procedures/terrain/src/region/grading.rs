@@ -1,5 +1,5 @@
 use crate::region::{Region2D, RegionNoise};
-use crate::{ElevationModulation, PerlinTerrainSdf};
+use crate::{ElevationModulation, ModulationPriority, PerlinTerrainSdf};
 use bevy::prelude::*;
 
 /// Rounds the terrain height to the nearest unit amount.
@@ -21,6 +21,8 @@ pub struct RegionGradingModulation {
 	pub inner_radius: f32,
 	/// The outer radius of the region.
 	pub outer_radius: f32,
+	/// The priority level of the modulation.
+	pub priority: ModulationPriority,
 }
 
 impl RegionGradingModulation {
@@ -43,9 +45,16 @@ impl RegionGradingModulation {
 			noise,
 			inner_radius,
 			outer_radius,
+			priority: ModulationPriority::Constraint,
 		}
 	}
 
+	/// Sets the priority level of the modulation
+	pub fn with_priority(mut self, priority: ModulationPriority) -> Self {
+		self.priority = priority;
+		self
+	}
+
 	#[inline(always)]
 	fn smoothstep(t: f32) -> f32 {
 		let t = t.clamp(0.0, 1.0);
@@ -91,4 +100,20 @@ impl ElevationModulation for RegionGradingModulation {
 
 		weight * elevation + (1.0 - weight) * interpolated_elevation
 	}
+
+	fn priority(&self) -> ModulationPriority {
+		self.priority
+	}
+
+	fn region_distance(&self, x: f32, z: f32) -> Option<f32> {
+		Some(self.region.sdf_with_noise(Vec2::new(x, z), self.noise.as_ref()))
+	}
+
+	fn influence(&self, x: f32, z: f32) -> f32 {
+		1.0 - self.region_weight(Vec2::new(x, z))
+	}
+
+	fn elevation_bounds(&self) -> Option<Rect> {
+		Some(self.region.bounds_within(self.outer_radius, self.noise.as_ref()))
+	}
 }
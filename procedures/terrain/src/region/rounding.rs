@@ -1,5 +1,5 @@
 use crate::region::{Region2D, RegionNoise};
-use crate::{ElevationModulation, PerlinTerrainSdf};
+use crate::{ElevationModulation, ModulationPriority, PerlinTerrainSdf};
 use bevy::prelude::*;
 
 /// Rounds the terrain height to the nearest unit amount.
@@ -15,6 +15,8 @@ pub struct RegionRoundingModulation {
 	pub inner_radius: f32,
 	/// The outer radius of the region.
 	pub outer_radius: f32,
+	/// The priority level of the modulation.
+	pub priority: ModulationPriority,
 }
 
 impl RegionRoundingModulation {
@@ -25,7 +27,20 @@ impl RegionRoundingModulation {
 		inner_radius: f32,
 		outer_radius: f32,
 	) -> Self {
-		Self { region, nearest, noise, inner_radius, outer_radius }
+		Self {
+			region,
+			nearest,
+			noise,
+			inner_radius,
+			outer_radius,
+			priority: ModulationPriority::Detail,
+		}
+	}
+
+	/// Sets the priority level of the modulation
+	pub fn with_priority(mut self, priority: ModulationPriority) -> Self {
+		self.priority = priority;
+		self
 	}
 
 	#[inline(always)]
@@ -64,4 +79,20 @@ impl ElevationModulation for RegionRoundingModulation {
 
 		weight * elevation + (1.0 - weight) * rounded
 	}
+
+	fn priority(&self) -> ModulationPriority {
+		self.priority
+	}
+
+	fn region_distance(&self, x: f32, z: f32) -> Option<f32> {
+		Some(self.region.sdf_with_noise(Vec2::new(x, z), self.noise.as_ref()))
+	}
+
+	fn influence(&self, x: f32, z: f32) -> f32 {
+		1.0 - self.region_weight(Vec2::new(x, z))
+	}
+
+	fn elevation_bounds(&self) -> Option<Rect> {
+		Some(self.region.bounds_within(self.outer_radius, self.noise.as_ref()))
+	}
 }
@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+use std::path::PathBuf;
+use vegetation_sdf::prototype::{TreePrototypeLibrary, TreeSpecies};
+
+/// Bakes a library of tree prototypes for spawning without generating trees at runtime.
+///
+/// Usage: bake_tree_prototypes [output] [prototypes per species]
+fn main() {
+	let mut args = std::env::args().skip(1);
+	let output = args
+		.next()
+		.map(PathBuf::from)
+		.unwrap_or_else(|| PathBuf::from("assets/trees.prototypes"));
+	let count = args.next().and_then(|s| s.parse::<usize>().ok()).unwrap_or(8);
+
+	let species = [
+		TreeSpecies::new("broadleaf").with_height(6.0).with_seed(1),
+		TreeSpecies::new("spire")
+			.with_height(9.0)
+			.with_branch_count(3)
+			.with_leaf_ball_scale(Vec3::new(0.8, 1.3, 0.8))
+			.with_seed(2),
+		TreeSpecies::new("shrub")
+			.with_height(2.5)
+			.with_branch_count(5)
+			.with_leaf_ball_scale(Vec3::splat(1.2))
+			.with_seed(3),
+	];
+
+	println!("Baking {count} prototypes of {} species to {}", species.len(), output.display());
+	let library = TreePrototypeLibrary::bake(&species, count);
+	if let Err(e) = library.save(&output) {
+		eprintln!("{e}");
+		std::process::exit(1);
+	}
+}
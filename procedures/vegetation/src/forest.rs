@@ -1 +1,402 @@
+use crate::grove::GroveBuilder;
+use bevy::{
+	asset::RenderAssetUsages, math::bounding::Aabb3d, mesh::Indices, mesh::PrimitiveTopology,
+	prelude::*,
+};
+use chunk::cascade::CascadeChunk;
+use comproc::noise::config::NoiseConfig;
+use noise::Perlin;
+use render_item::{
+	debug_name,
+	mesh::{
+		cache::handle::map::HandleMap, handle::MeshHandle, IdentifiedMesh, MeshBuilder,
+		MeshDispatch, MeshId, MeshIdBuilder, MeshResolution, MeshSpace,
+	},
+	NormalizeChunk, RenderItem,
+};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
+/// Height of the ground at a world xz position.
+pub type GroundHeight = Arc<dyn Fn(Vec2) -> f32 + Send + Sync>;
+
+fn flat_ground() -> GroundHeight {
+	Arc::new(|_| 0.0)
+}
+
+/// Tree density over the ground in [0, 1].
+///
+/// Zero wherever a grove with the same noise and threshold would place no trees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForestDensity {
+	noise_config_3d: NoiseConfig<3, Perlin>,
+	threshold: f32,
+}
+
+impl ForestDensity {
+	pub fn new(noise_config_3d: NoiseConfig<3, Perlin>, threshold: f32) -> Self {
+		Self { noise_config_3d, threshold }
+	}
+
+	pub fn density(&self, position: Vec2) -> f32 {
+		if self.threshold >= 1.0 {
+			return 0.0;
+		}
+		let noise =
+			self.noise_config_3d.vec3_on_unit(Vec3::new(position.x, 0.0, position.y)) as f32;
+		((noise - self.threshold) / (1.0 - self.threshold)).clamp(0.0, 1.0)
+	}
+}
+
+impl Hash for ForestDensity {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.noise_config_3d.hash(state);
+		self.threshold.to_bits().hash(state);
+	}
+}
+
+/// Thins trees out with distance from a point, usually the camera, from all of them at the
+/// start radius to none at the end radius.
+///
+/// Which trees are kept depends only on where they stand, so the same trees thin out first
+/// wherever the point is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DensityFalloff {
+	pub center: Vec3,
+	pub start: f32,
+	pub end: f32,
+}
+
+impl DensityFalloff {
+	pub fn new(center: Vec3, start: f32, end: f32) -> Self {
+		Self { center, start, end: end.max(start) }
+	}
+
+	/// Share of the trees kept at the position.
+	pub fn density(&self, position: Vec3) -> f32 {
+		let distance = position.distance(self.center);
+		if distance <= self.start {
+			return 1.0;
+		}
+		if distance >= self.end {
+			return 0.0;
+		}
+		let t = (distance - self.start) / (self.end - self.start);
+		1.0 - t * t * (3.0 - 2.0 * t)
+	}
+
+	/// Whether the tree placed from the position is kept.
+	pub fn keeps(&self, placed_from: Vec3, position: Vec3) -> bool {
+		let mut hash = placed_from.x.to_bits().wrapping_mul(0x8da6_b343)
+			^ placed_from.z.to_bits().wrapping_mul(0xcb1a_b31f);
+		hash ^= hash >> 13;
+		hash = hash.wrapping_mul(0x5bd1_e995);
+		hash ^= hash >> 15;
+		(hash as f32 / u32::MAX as f32) < self.density(position)
+	}
+}
+
+/// A far-field stand-in for a forest: the ground raised by the canopy height where trees are dense.
+///
+/// Quads with no trees are left out, and vertices are tinted from sparse to dense.
+/// Vertices are relative to the chunk origin.
+#[derive(Clone)]
+pub struct CanopyCarpet {
+	density: ForestDensity,
+	ground: GroundHeight,
+	/// Height of the canopy above the ground at full density
+	canopy_height: f32,
+	sparse_color: LinearRgba,
+	dense_color: LinearRgba,
+	resolution: MeshResolution,
+	/// Size of the chunks the carpet is built for, which bounds the mesh
+	chunk_size: Vec3,
+}
+
+impl CanopyCarpet {
+	pub fn new(density: ForestDensity) -> Self {
+		Self {
+			density,
+			ground: flat_ground(),
+			canopy_height: 4.0,
+			sparse_color: LinearRgba::rgb(0.35, 0.6, 0.25),
+			dense_color: LinearRgba::rgb(0.1, 0.35, 0.12),
+			resolution: MeshResolution::new(4),
+			chunk_size: Vec3::ONE,
+		}
+	}
+
+	pub fn with_ground(mut self, ground: GroundHeight) -> Self {
+		self.ground = ground;
+		self
+	}
+
+	pub fn with_canopy_height(mut self, canopy_height: f32) -> Self {
+		self.canopy_height = canopy_height;
+		self
+	}
+
+	pub fn with_colors(mut self, sparse_color: LinearRgba, dense_color: LinearRgba) -> Self {
+		self.sparse_color = sparse_color;
+		self.dense_color = dense_color;
+		self
+	}
+
+	pub fn with_resolution(mut self, resolution: MeshResolution) -> Self {
+		self.resolution = resolution;
+		self
+	}
+
+	pub fn with_chunk_size(mut self, chunk_size: Vec3) -> Self {
+		self.chunk_size = chunk_size;
+		self
+	}
+}
+
+impl IdentifiedMesh for CanopyCarpet {
+	fn id(&self) -> MeshId {
+		MeshIdBuilder::new("canopy_carpet")
+			.with(&self.density)
+			.with_f32(self.canopy_height)
+			.with_debug(&self.sparse_color)
+			.with_debug(&self.dense_color)
+			.with(&self.resolution)
+			.with_vec3(self.chunk_size)
+			.build()
+	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		MeshSpace::ChunkLocal
+	}
+}
+
+impl NormalizeChunk for CanopyCarpet {}
+
+impl MeshBuilder for CanopyCarpet {
+	fn build_mesh_impl(&self, cascade_chunk: &CascadeChunk) -> Option<Mesh> {
+		let segments = self.resolution.segments();
+		let (nx, nz) = (segments.x as usize, segments.z as usize);
+		let size = cascade_chunk.size;
+		let step = Vec2::new(size.x / nx as f32, size.z / nz as f32);
+		let max_y = size.y + self.canopy_height;
+
+		let vertex_count = (nx + 1) * (nz + 1);
+		let mut positions = Vec::with_capacity(vertex_count);
+		let mut uvs = Vec::with_capacity(vertex_count);
+		let mut colors = Vec::with_capacity(vertex_count);
+		let mut grounds = Vec::with_capacity(vertex_count);
+		let mut densities = Vec::with_capacity(vertex_count);
+		for k in 0..=nz {
+			for i in 0..=nx {
+				let local = Vec2::new(i as f32 * step.x, k as f32 * step.y);
+				let world = cascade_chunk.origin.xz() + local;
+				let ground = (self.ground)(world) - cascade_chunk.origin.y;
+				let density = self.density.density(world);
+
+				let y = (ground + density * self.canopy_height).clamp(0.0, max_y);
+				positions.push([local.x, y, local.y]);
+				uvs.push([i as f32 / nx as f32, k as f32 / nz as f32]);
+				colors.push(self.sparse_color.mix(&self.dense_color, density).to_f32_array());
+				grounds.push(ground);
+				densities.push(density);
+			}
+		}
+
+		// Only quads with trees whose ground falls in this chunk, so stacked chunks don't overlap
+		let mut indices = Vec::new();
+		for k in 0..nz {
+			for i in 0..nx {
+				let a = k * (nx + 1) + i;
+				let (b, c, d) = (a + 1, a + nx + 1, a + nx + 2);
+				let corners = [a, b, c, d];
+				let ground = corners.iter().map(|&v| grounds[v]).sum::<f32>() / 4.0;
+				if corners.iter().all(|&v| densities[v] <= 0.0) || ground < 0.0 || ground >= size.y
+				{
+					continue;
+				}
+				indices.extend([a, c, b, b, c, d].map(|v| v as u32));
+			}
+		}
+		if indices.is_empty() {
+			return None;
+		}
+
+		let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+		mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+		mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+		mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+		mesh.insert_indices(Indices::U32(indices));
+		mesh.compute_smooth_normals();
+		Some(mesh)
+	}
+
+	fn local_bounds(&self) -> Aabb3d {
+		Aabb3d {
+			min: Vec3::ZERO.into(),
+			max: (self.chunk_size + Vec3::Y * self.canopy_height).into(),
+		}
+	}
+}
+
+/// How a forest chunk is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForestLod {
+	/// Individual trees, for chunks inside the cascade
+	Trees,
+	/// A canopy carpet, for far grid chunks
+	Canopy,
+}
+
+/// A forest that renders trees or a canopy carpet on each chunk, from the same density.
+#[derive(Component, Clone)]
+pub struct Forest<T: Material, L: Material> {
+	grove: GroveBuilder<T, L>,
+	canopy: CanopyCarpet,
+	canopy_material: MeshMaterial3d<L>,
+	canopy_cache: HandleMap<CanopyCarpet>,
+	lod: ForestLod,
+}
+
+impl<T: Material, L: Material> Forest<T, L> {
+	pub fn new(grove: GroveBuilder<T, L>, canopy_material: MeshMaterial3d<L>) -> Self {
+		Self {
+			canopy: CanopyCarpet::new(grove.density()),
+			grove,
+			canopy_material,
+			canopy_cache: HandleMap::new(),
+			lod: ForestLod::Trees,
+		}
+	}
+
+	/// Places both the trees and the canopy on the ground.
+	pub fn with_ground(mut self, ground: GroundHeight) -> Self {
+		self.grove = self.grove.with_ground(ground.clone());
+		self.canopy = self.canopy.with_ground(ground);
+		self
+	}
+
+	pub fn with_canopy(mut self, canopy: CanopyCarpet) -> Self {
+		self.canopy = canopy;
+		self
+	}
+
+	pub fn with_canopy_cache(mut self, canopy_cache: HandleMap<CanopyCarpet>) -> Self {
+		self.canopy_cache = canopy_cache;
+		self
+	}
+
+	pub fn with_lod(mut self, lod: ForestLod) -> Self {
+		self.lod = lod;
+		self
+	}
+
+	/// Thins the trees out with distance, leaving the canopy as it is.
+	pub fn with_density_falloff(mut self, falloff: DensityFalloff) -> Self {
+		self.grove = self.grove.with_density_falloff(falloff);
+		self
+	}
+
+	/// Number of tree and canopy meshes in the forest's caches.
+	pub fn cached_meshes(&self) -> usize {
+		self.grove.cached_meshes() + self.canopy_cache.len()
+	}
+}
+
+impl<T: Material, L: Material> RenderItem for Forest<T, L> {
+	fn spawn_render_items(
+		&self,
+		commands: &mut Commands,
+		cascade_chunk: &CascadeChunk,
+		transform: Transform,
+	) -> Vec<Entity> {
+		match self.lod {
+			ForestLod::Trees => self.grove.for_chunk(cascade_chunk).build().spawn_render_items(
+				commands,
+				cascade_chunk,
+				transform,
+			),
+			ForestLod::Canopy => {
+				let canopy = self.canopy.clone().with_chunk_size(cascade_chunk.size);
+				let mesh_handle =
+					MeshHandle::new(canopy).with_handle_cache(self.canopy_cache.clone());
+				let canopy = commands
+					.spawn((
+						*cascade_chunk,
+						MeshDispatch::new(mesh_handle),
+						transform,
+						self.canopy_material.clone(),
+					))
+					.id();
+				debug_name(commands, canopy, || {
+					let origin = cascade_chunk.origin;
+					format!("canopy ({}, {}, {})", origin.x, origin.y, origin.z)
+				});
+				vec![canopy]
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grove::TreePlacement;
+
+	fn chunk() -> CascadeChunk {
+		CascadeChunk::cube(Vec3::new(-16.0, -8.0, -16.0), 32.0, 0)
+	}
+
+	#[test]
+	fn test_canopy_follows_density() {
+		let density = ForestDensity::new(NoiseConfig::default(), 0.5);
+		let canopy = CanopyCarpet::new(density.clone()).with_chunk_size(chunk().size);
+		let Some(mesh) = canopy.build_mesh(&chunk()) else {
+			panic!("expected a canopy over the forest");
+		};
+		let Some(positions) = mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(|p| p.as_float3())
+		else {
+			panic!("expected positions");
+		};
+
+		let bounds = canopy.local_bounds();
+		for position in positions {
+			let position = Vec3::from_array(*position);
+			assert!(
+				position.cmpge(bounds.min.into()).all() && position.cmple(bounds.max.into()).all()
+			);
+
+			// Raised above the flat ground by the density at the vertex
+			let world = chunk().origin + position;
+			let expected = density.density(world.xz()) * canopy.canopy_height - chunk().origin.y;
+			assert!((position.y - expected).abs() < 1e-4);
+		}
+
+		let bare = CanopyCarpet::new(ForestDensity::new(NoiseConfig::default(), 1.0));
+		assert!(bare.build_mesh(&chunk()).is_none());
+	}
+
+	#[test]
+	fn test_trees_thin_out_over_the_falloff() {
+		let grove = GroveBuilder::<StandardMaterial, StandardMaterial>::new(
+			MeshMaterial3d(Handle::default()),
+			MeshMaterial3d(Handle::default()),
+		)
+		.for_chunk(&CascadeChunk::cube(Vec3::new(-64.0, -8.0, -64.0), 128.0, 0));
+		let all = grove.placements();
+		let falloff = DensityFalloff::new(Vec3::ZERO, 16.0, 48.0);
+		let thinned = grove.clone().with_density_falloff(falloff).placements();
+
+		// Every tree inside the start is kept, none past the end, and some in between
+		let within = |placements: &[TreePlacement], min: f32, max: f32| {
+			placements
+				.iter()
+				.filter(|tree| (min..max).contains(&tree.position.length()))
+				.count()
+		};
+		assert_eq!(within(&thinned, 0.0, 16.0), within(&all, 0.0, 16.0));
+		assert_eq!(within(&thinned, 48.0, f32::INFINITY), 0);
+		let (kept, placed) = (within(&thinned, 16.0, 48.0), within(&all, 16.0, 48.0));
+		assert!(kept > 0 && kept < placed, "kept {kept} of {placed}");
+		assert!(thinned.iter().all(|tree| all.contains(tree)));
+	}
+}
@@ -1,6 +1,10 @@
+use crate::forest::{DensityFalloff, ForestDensity, GroundHeight};
+use crate::species::SpeciesProperties;
 use crate::tree::builder::{Tree, TreeBuilder};
 use crate::tree::meshes::canopy::ball::NoisyBall;
+use crate::tree::meshes::canopy::proxy::CanopyProxy;
 use crate::tree::meshes::trunk::segment::SimpleTrunkSegment;
+use crate::tree::spine::TrunkShape;
 use bevy::prelude::*;
 use chunk::cascade::CascadeChunk;
 use comproc::noise::config::NoiseConfig;
@@ -21,8 +25,22 @@ pub struct GroveBuilder<T: Material, L: Material> {
 	leaf_material: MeshMaterial3d<L>,
 	tree_cache: HandleMap<SimpleTrunkSegment>,
 	leaf_cache: HandleMap<NoisyBall>,
+	/// Cache of the canopy shadow proxies, when trees cast shadows from proxies
+	proxy_cache: Option<HandleMap<CanopyProxy>>,
 	min_height: f32,
 	max_height: f32,
+	/// Buckets tree_num is quantized to for trunk meshes
+	stick_buckets: u32,
+	/// Buckets tree_num is quantized to for leaf meshes
+	leaf_buckets: u32,
+	ground: Option<GroundHeight>,
+	/// Thins the trees out with distance, when set
+	falloff: Option<DensityFalloff>,
+	/// How the trunks bend and lean
+	trunk_shape: TrunkShape,
+	/// Attached to every tree the grove grows, for the simulation layers
+	properties: SpeciesProperties,
+	chunk: Option<CascadeChunk>,
 }
 
 impl<T: Material, L: Material> GroveBuilder<T, L> {
@@ -38,8 +56,16 @@ impl<T: Material, L: Material> GroveBuilder<T, L> {
 			leaf_material,
 			tree_cache: HandleMap::new(),
 			leaf_cache: HandleMap::new(),
+			proxy_cache: None,
 			min_height: 2.0,
 			max_height: 6.0,
+			stick_buckets: 16,
+			leaf_buckets: 8,
+			ground: None,
+			falloff: None,
+			trunk_shape: TrunkShape::default(),
+			properties: SpeciesProperties::default(),
+			chunk: None,
 		}
 	}
 
@@ -53,6 +79,93 @@ impl<T: Material, L: Material> GroveBuilder<T, L> {
 		self
 	}
 
+	/// Casts canopy shadows from low-poly proxies sharing meshes in the cache, instead of the leaves.
+	///
+	/// Proxies are only seen by lights that include the shadow proxy render layer.
+	pub fn with_shadow_proxies(mut self, proxy_cache: HandleMap<CanopyProxy>) -> Self {
+		self.proxy_cache = Some(proxy_cache);
+		self
+	}
+
+	/// Shares trunk and leaf meshes between trees by quantizing their tree_num.
+	///
+	/// A grove builds at most this many distinct meshes of each, however many trees it has.
+	pub fn with_variant_buckets(mut self, stick_buckets: u32, leaf_buckets: u32) -> Self {
+		self.stick_buckets = stick_buckets;
+		self.leaf_buckets = leaf_buckets;
+		self
+	}
+
+	pub fn with_anchor(mut self, anchor: Vec3) -> Self {
+		self.anchor = anchor;
+		self
+	}
+
+	/// Places the trees on the ground instead of at the anchor height.
+	pub fn with_ground(mut self, ground: GroundHeight) -> Self {
+		self.ground = Some(ground);
+		self
+	}
+
+	/// Thins the trees out with distance from the falloff's center.
+	pub fn with_density_falloff(mut self, falloff: DensityFalloff) -> Self {
+		self.falloff = Some(falloff);
+		self
+	}
+
+	/// Bends and leans the trunks, such as downwind for wind-swept trees.
+	///
+	/// Each tree gets its own spine from the shape, and its branches attach along it.
+	pub fn with_trunk_shape(mut self, trunk_shape: TrunkShape) -> Self {
+		self.trunk_shape = trunk_shape;
+		self
+	}
+
+	/// Attaches the physical parameters of the species to the trees, which rendering ignores.
+	pub fn with_species_properties(mut self, properties: SpeciesProperties) -> Self {
+		self.properties = properties;
+		self
+	}
+
+	pub fn species_properties(&self) -> SpeciesProperties {
+		self.properties
+	}
+
+	/// The density of trees this grove places.
+	/// Number of trunk and leaf meshes in the grove's caches.
+	pub fn cached_meshes(&self) -> usize {
+		self.tree_cache.len()
+			+ self.leaf_cache.len()
+			+ self.proxy_cache.as_ref().map_or(0, HandleMap::len)
+	}
+
+	pub fn density(&self) -> ForestDensity {
+		ForestDensity::new(self.noise_config_3d.clone(), self.threshold)
+	}
+
+	/// A copy of the grove limited to the trees that stand in the chunk.
+	///
+	/// The anchor snaps to the grove's step, so neighboring chunks split the trees without overlap.
+	pub fn for_chunk(&self, cascade_chunk: &CascadeChunk) -> Self {
+		let origin = cascade_chunk.origin;
+		let snapped = (origin.xz() / self.step_size).floor() * self.step_size - self.step_size;
+		let extent = cascade_chunk.size.x.max(cascade_chunk.size.z);
+
+		let mut grove = self.clone();
+		grove.anchor = Vec3::new(snapped.x, self.anchor.y, snapped.y);
+		grove.count = (extent / self.step_size).ceil() as usize + 2;
+		grove.chunk = Some(*cascade_chunk);
+		grove
+	}
+
+	fn in_chunk(&self, position: Vec3) -> bool {
+		let Some(chunk) = self.chunk else {
+			return true;
+		};
+		let max = chunk.origin + chunk.size;
+		position.cmpge(chunk.origin).all() && position.cmplt(max).all()
+	}
+
 	pub fn meets_threshold(&self, position: Vec3) -> bool {
 		let noise = self.noise_config_3d.vec3_on_unit(position);
 		noise as f32 > self.threshold
@@ -67,8 +180,9 @@ impl<T: Material, L: Material> GroveBuilder<T, L> {
 		noise as f32 * (self.max_height - self.min_height) + self.min_height
 	}
 
-	pub fn build(&self) -> Grove<T, L> {
-		let mut trees = Vec::new();
+	/// Where the grove's trees stand, in placement order.
+	pub fn placements(&self) -> Vec<TreePlacement> {
+		let mut placements = Vec::new();
 		for i in 0..self.count {
 			for j in 0..self.count {
 				let pre_position = self.anchor
@@ -81,37 +195,76 @@ impl<T: Material, L: Material> GroveBuilder<T, L> {
 						self.inner_noise(pre_position),
 					);
 
-				if self.meets_threshold(position) {
-					let tree_builder = TreeBuilder {
-						anchor: position,
-						height: self.get_height(position),
-						branch_count: 4,
-						leaf_ball_scale: Vec3::new(1.0, 1.0, 1.0),
-						noise_config_3d: self.noise_config_3d.clone(),
-						noise_config_4d: self.noise_config_4d.clone(),
-						ball_variety: 0,
-						ball_cache: self.leaf_cache.clone(),
-						stick_variety: 1,
-						stick_cache: self.tree_cache.clone(),
-						leaf_variety: 1,
-						leaf_cache: self.leaf_cache.clone(),
-						stick_material: self.trunk_material.clone(),
-						leaf_material: self.leaf_material.clone(),
-					};
-
-					let tree = tree_builder.build();
-
-					trees.push((position, tree));
+				if !self.meets_threshold(position) {
+					continue;
+				}
+				let height = self.get_height(position);
+				let position = match &self.ground {
+					Some(ground) => position.with_y(ground(position.xz())),
+					None => position,
+				};
+				if !self.in_chunk(position) {
+					continue;
 				}
+				if self.falloff.is_some_and(|falloff| !falloff.keeps(pre_position, position)) {
+					continue;
+				}
+
+				placements.push(TreePlacement { position, height });
 			}
 		}
-		Grove { trees }
+		placements
+	}
+
+	pub fn build(&self) -> Grove<T, L> {
+		let trees = self
+			.placements()
+			.into_iter()
+			.map(|TreePlacement { position, height }| {
+				let tree_builder = TreeBuilder {
+					anchor: position,
+					height,
+					branch_count: 4,
+					leaf_ball_scale: Vec3::new(1.0, 1.0, 1.0),
+					noise_config_3d: self.noise_config_3d.clone(),
+					noise_config_4d: self.noise_config_4d.clone(),
+					ball_variety: 0,
+					ball_buckets: self.leaf_buckets,
+					ball_cache: self.leaf_cache.clone(),
+					stick_variety: 1,
+					stick_buckets: self.stick_buckets,
+					stick_cache: self.tree_cache.clone(),
+					leaf_variety: 1,
+					leaf_buckets: self.leaf_buckets,
+					leaf_cache: self.leaf_cache.clone(),
+					stick_material: self.trunk_material.clone(),
+					leaf_material: self.leaf_material.clone(),
+					shadow_proxy_cache: self.proxy_cache.clone(),
+					trunk_shape: self.trunk_shape.clone(),
+				};
+
+				(position, tree_builder.build())
+			})
+			.collect();
+		Grove { trees, properties: self.properties }
+	}
+
+	pub(crate) fn materials(&self) -> (&MeshMaterial3d<T>, &MeshMaterial3d<L>) {
+		(&self.trunk_material, &self.leaf_material)
 	}
 }
 
+/// A tree the grove places, before any meshes are chosen for it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreePlacement {
+	pub position: Vec3,
+	pub height: f32,
+}
+
 #[derive(Component, Clone)]
 pub struct Grove<T: Material, L: Material> {
 	trees: Vec<(Vec3, Tree<NoisyBall, SimpleTrunkSegment, NoisyBall, T, L>)>,
+	properties: SpeciesProperties,
 }
 
 impl<T: Material, L: Material> RenderItem for Grove<T, L> {
@@ -124,7 +277,10 @@ impl<T: Material, L: Material> RenderItem for Grove<T, L> {
 		let mut entities = Vec::new();
 		for (position, tree) in &self.trees {
 			let transform = transform.with_translation(*position);
-			entities.extend(tree.spawn_render_items(commands, cascade_chunk, transform));
+			for root in tree.spawn_render_items(commands, cascade_chunk, transform) {
+				commands.entity(root).insert(self.properties);
+				entities.push(root);
+			}
 		}
 		entities
 	}
@@ -0,0 +1,355 @@
+pub mod leaf_card;
+
+use bevy::prelude::*;
+use chunk::cascade::CascadeChunk;
+use comproc::{
+	complex::chain::ball_stick::render::TaperedMesh,
+	geometry::spherical::unit_cylindrical_segment::UnitCylindricalSegment,
+	noise::config::NoiseConfig,
+};
+use leaf_card::LeafCard;
+use noise::Perlin;
+use render_item::{
+	mesh::{cache::handle::map::HandleMap, handle::MeshHandle, MeshDispatch},
+	RenderItem,
+};
+use sdf::Sdf;
+use std::sync::Arc;
+
+/// Offset of the central differences that estimate the host's surface normal
+const NORMAL_EPSILON: f32 = 1e-3;
+/// Iterations that pull a point onto the host's surface
+const PROJECTION_ITERATIONS: usize = 4;
+/// Turn between successive leaves around the surface normal, the golden angle
+const LEAF_TWIST: f32 = 2.399_963;
+
+/// A point along a vine, resting on the surface of its host.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IvyNode {
+	pub position: Vec3,
+	/// The host's surface normal under the node
+	pub normal: Vec3,
+	/// Radius of the vine at the node
+	pub radius: f32,
+}
+
+/// The outward normal of the SDF at the point, if it has a gradient there.
+fn surface_normal(host: &dyn Sdf, p: Vec3) -> Option<Vec3> {
+	let dx = Vec3::X * NORMAL_EPSILON;
+	let dy = Vec3::Y * NORMAL_EPSILON;
+	let dz = Vec3::Z * NORMAL_EPSILON;
+	Vec3::new(
+		host.distance(p + dx) - host.distance(p - dx),
+		host.distance(p + dy) - host.distance(p - dy),
+		host.distance(p + dz) - host.distance(p - dz),
+	)
+	.try_normalize()
+}
+
+/// Pulls the point onto the surface along the gradient, returning it with its normal.
+fn project_onto_surface(host: &dyn Sdf, mut p: Vec3) -> Option<(Vec3, Vec3)> {
+	for _ in 0..PROJECTION_ITERATIONS {
+		let normal = surface_normal(host, p)?;
+		p -= normal * host.distance(p);
+	}
+	Some((p, surface_normal(host, p)?))
+}
+
+/// Grows vines over the surface of a host SDF, such as a wall, a rock or a tree trunk.
+///
+/// Each vine starts from a seed pulled onto the surface, then walks along it: the heading is
+/// projected onto the tangent plane, each step is pulled back onto the surface, and the heading
+/// climbs and wanders with noise. A vine stops when a step loses the surface, like at an edge.
+#[derive(Clone)]
+pub struct IvyBuilder<T: Material, L: Material> {
+	host: Arc<dyn Sdf>,
+	seeds: Vec<Vec3>,
+	steps: usize,
+	step_length: f32,
+	/// Pull of the heading toward +Y
+	climb: f32,
+	/// Pull of the heading toward the noise
+	wander: f32,
+	noise_config_3d: NoiseConfig<3, Perlin>,
+	/// Radius of the vines at their roots, thinning toward their tips
+	radius: f32,
+	/// Steps between leaves, 0 for bare vines
+	leaf_spacing: usize,
+	leaf_size: f32,
+	stick_material: MeshMaterial3d<T>,
+	leaf_material: MeshMaterial3d<L>,
+	stick_cache: HandleMap<UnitCylindricalSegment>,
+	leaf_cache: HandleMap<LeafCard>,
+}
+
+impl<T: Material, L: Material> IvyBuilder<T, L> {
+	pub fn new(
+		host: Arc<dyn Sdf>,
+		stick_material: MeshMaterial3d<T>,
+		leaf_material: MeshMaterial3d<L>,
+	) -> Self {
+		Self {
+			host,
+			seeds: Vec::new(),
+			steps: 48,
+			step_length: 0.2,
+			climb: 0.6,
+			wander: 0.8,
+			noise_config_3d: NoiseConfig::default(),
+			radius: 0.04,
+			leaf_spacing: 2,
+			leaf_size: 0.25,
+			stick_material,
+			leaf_material,
+			stick_cache: HandleMap::new(),
+			leaf_cache: HandleMap::new(),
+		}
+	}
+
+	/// Adds a vine rooted at the point of the surface nearest the seed.
+	pub fn with_seed(mut self, seed: Vec3) -> Self {
+		self.seeds.push(seed);
+		self
+	}
+
+	pub fn with_steps(mut self, steps: usize, step_length: f32) -> Self {
+		self.steps = steps;
+		self.step_length = step_length;
+		self
+	}
+
+	pub fn with_climb(mut self, climb: f32) -> Self {
+		self.climb = climb;
+		self
+	}
+
+	pub fn with_wander(mut self, wander: f32) -> Self {
+		self.wander = wander;
+		self
+	}
+
+	pub fn with_noise_config_3d(mut self, noise_config_3d: NoiseConfig<3, Perlin>) -> Self {
+		self.noise_config_3d = noise_config_3d;
+		self
+	}
+
+	pub fn with_radius(mut self, radius: f32) -> Self {
+		self.radius = radius;
+		self
+	}
+
+	pub fn with_leaves(mut self, leaf_spacing: usize, leaf_size: f32) -> Self {
+		self.leaf_spacing = leaf_spacing;
+		self.leaf_size = leaf_size;
+		self
+	}
+
+	pub fn with_stick_cache(mut self, stick_cache: HandleMap<UnitCylindricalSegment>) -> Self {
+		self.stick_cache = stick_cache;
+		self
+	}
+
+	pub fn with_leaf_cache(mut self, leaf_cache: HandleMap<LeafCard>) -> Self {
+		self.leaf_cache = leaf_cache;
+		self
+	}
+
+	/// A direction to wander in, varying smoothly over space.
+	fn wander_at(&self, p: Vec3) -> Vec3 {
+		Vec3::new(
+			self.noise_config_3d.vec3_amp(p) as f32,
+			self.noise_config_3d.vec3_amp(p + Vec3::new(31.7, 0.0, 0.0)) as f32,
+			self.noise_config_3d.vec3_amp(p + Vec3::new(0.0, 0.0, 17.3)) as f32,
+		)
+	}
+
+	/// Walks a vine over the surface from the seed, empty if the seed can't reach the surface.
+	pub fn grow_vine(&self, seed: Vec3) -> Vec<IvyNode> {
+		let host = self.host.as_ref();
+		let tolerance = self.step_length / 2.0;
+		let Some((mut surface, mut normal)) = project_onto_surface(host, seed) else {
+			return Vec::new();
+		};
+		if host.distance(surface).abs() > tolerance {
+			return Vec::new();
+		}
+
+		let radius_at =
+			|step: usize| self.radius * (1.0 - 0.6 * step as f32 / self.steps.max(1) as f32);
+		let mut nodes = vec![IvyNode {
+			position: surface + normal * radius_at(0),
+			normal,
+			radius: radius_at(0),
+		}];
+		let mut heading = Vec3::Y;
+		for step in 1..=self.steps {
+			let pull = heading + Vec3::Y * self.climb + self.wander_at(surface) * self.wander;
+			let Some(tangent) = (pull - normal * pull.dot(normal)).try_normalize() else {
+				break;
+			};
+			let Some((next, next_normal)) =
+				project_onto_surface(host, surface + tangent * self.step_length)
+			else {
+				break;
+			};
+			if host.distance(next).abs() > tolerance || next.distance(surface) < 1e-4 {
+				break;
+			}
+
+			heading = (next - surface).normalize();
+			surface = next;
+			normal = next_normal;
+			let radius = radius_at(step);
+			nodes.push(IvyNode { position: surface + normal * radius, normal, radius });
+		}
+		nodes
+	}
+
+	pub fn build(&self) -> Ivy<T, L> {
+		Ivy {
+			vines: self.seeds.iter().map(|seed| self.grow_vine(*seed)).collect(),
+			leaf_spacing: self.leaf_spacing,
+			leaf_size: self.leaf_size,
+			stick: MeshHandle::new(UnitCylindricalSegment::new().with_taper(1.0))
+				.with_handle_cache(self.stick_cache.clone()),
+			leaf: MeshHandle::new(LeafCard::default()).with_handle_cache(self.leaf_cache.clone()),
+			stick_material: self.stick_material.clone(),
+			leaf_material: self.leaf_material.clone(),
+		}
+	}
+}
+
+/// Vines grown by an [IvyBuilder], spawned as a stick per step and leaves along the way.
+#[derive(Component, Clone)]
+pub struct Ivy<T: Material, L: Material> {
+	vines: Vec<Vec<IvyNode>>,
+	leaf_spacing: usize,
+	leaf_size: f32,
+	stick: MeshHandle<UnitCylindricalSegment>,
+	leaf: MeshHandle<LeafCard>,
+	stick_material: MeshMaterial3d<T>,
+	leaf_material: MeshMaterial3d<L>,
+}
+
+impl<T: Material, L: Material> Ivy<T, L> {
+	pub fn vines(&self) -> &[Vec<IvyNode>] {
+		&self.vines
+	}
+
+	/// The stick from one node to the next, sized by the first node's radius.
+	fn stick_transform(start: &IvyNode, end: &IvyNode) -> Option<Transform> {
+		let ray = end.position - start.position;
+		let direction = ray.try_normalize()?;
+		let diameter = start.radius * 2.0;
+		Some(Transform {
+			translation: start.position,
+			rotation: Quat::from_rotation_arc(Vec3::Y, direction),
+			scale: Vec3::new(diameter, ray.length(), diameter),
+		})
+	}
+
+	/// A leaf lying on the surface at the node, turned a little further than the last.
+	fn leaf_transform(&self, node: &IvyNode, index: usize) -> Transform {
+		let rotation = Quat::from_rotation_arc(Vec3::Y, node.normal)
+			* Quat::from_rotation_y(index as f32 * LEAF_TWIST);
+		Transform {
+			translation: node.position + node.normal * node.radius,
+			rotation,
+			scale: Vec3::splat(self.leaf_size),
+		}
+	}
+}
+
+impl<T: Material, L: Material> RenderItem for Ivy<T, L>
+where
+	(CascadeChunk, MeshDispatch<MeshHandle<UnitCylindricalSegment>>, Transform, MeshMaterial3d<T>):
+		Bundle,
+	(CascadeChunk, MeshDispatch<MeshHandle<LeafCard>>, Transform, MeshMaterial3d<L>): Bundle,
+{
+	fn spawn_render_items(
+		&self,
+		commands: &mut Commands,
+		cascade_chunk: &CascadeChunk,
+		_transform: Transform,
+	) -> Vec<Entity> {
+		let mut entities = Vec::new();
+		for vine in &self.vines {
+			for (start, end) in vine.iter().zip(vine.iter().skip(1)) {
+				let Some(transform) = Self::stick_transform(start, end) else {
+					continue;
+				};
+				entities.push(
+					commands
+						.spawn((
+							*cascade_chunk,
+							MeshDispatch::new(self.stick.clone()),
+							transform,
+							self.stick_material.clone(),
+						))
+						.id(),
+				);
+			}
+
+			if self.leaf_spacing == 0 {
+				continue;
+			}
+			for (index, node) in vine.iter().enumerate().skip(1).step_by(self.leaf_spacing) {
+				entities.push(
+					commands
+						.spawn((
+							*cascade_chunk,
+							MeshDispatch::new(self.leaf.clone()),
+							self.leaf_transform(node, index),
+							self.leaf_material.clone(),
+						))
+						.id(),
+				);
+			}
+		}
+		entities
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sdf::SphereSdf;
+
+	/// A wall facing +X
+	struct Wall;
+
+	impl Sdf for Wall {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.x
+		}
+	}
+
+	fn builder(host: Arc<dyn Sdf>) -> IvyBuilder<StandardMaterial, StandardMaterial> {
+		IvyBuilder::new(host, MeshMaterial3d(Handle::default()), MeshMaterial3d(Handle::default()))
+	}
+
+	#[test]
+	fn test_vines_climb_along_the_surface() {
+		let ivy = builder(Arc::new(Wall)).with_seed(Vec3::new(0.5, 0.0, 0.0)).build();
+		let [vine] = ivy.vines() else {
+			panic!("expected one vine");
+		};
+		assert_eq!(vine.len(), 49);
+		for node in vine {
+			assert!((node.position.x - node.radius).abs() < 1e-4);
+			assert!(node.normal.abs_diff_eq(Vec3::X, 1e-4));
+		}
+		let Some(tip) = vine.last() else {
+			panic!("expected a tip");
+		};
+		assert!(tip.position.y > 2.0);
+		assert!(tip.radius < vine[0].radius);
+
+		// Around a rock, the vine stays on the surface
+		let rock = Arc::new(SphereSdf::new(Vec3::new(0.0, 1.0, 0.0), 1.5));
+		let ivy = builder(rock.clone()).with_seed(Vec3::new(3.0, 0.5, 0.0)).build();
+		for node in &ivy.vines()[0] {
+			assert!((rock.distance(node.position) - node.radius).abs() < 1e-2);
+		}
+	}
+}
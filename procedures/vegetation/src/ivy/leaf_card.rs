@@ -0,0 +1,72 @@
+use bevy::{asset::RenderAssetUsages, mesh::Indices, mesh::PrimitiveTopology, prelude::*};
+use chunk::cascade::CascadeChunk;
+use render_item::{
+	mesh::{IdentifiedMesh, MeshBuilder, MeshId, MeshSpace},
+	NormalizeChunk,
+};
+
+/// A flat, double sided leaf lying in the XZ plane, from its stem at -Z to its tip at +Z.
+///
+/// The leaf spans the unit square, so the spawn transform scales it to the leaf size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeafCard {
+	/// Width at the widest point, as a fraction of the length
+	pub width: f32,
+	/// Where along the leaf it is widest, from 0 at the stem to 1 at the tip
+	pub widest: f32,
+}
+
+impl Default for LeafCard {
+	fn default() -> Self {
+		Self { width: 0.6, widest: 0.4 }
+	}
+}
+
+impl NormalizeChunk for LeafCard {
+	fn normalize_chunk(&self, cascade_chunk: &CascadeChunk) -> CascadeChunk {
+		CascadeChunk::unit_3d_center_chunk().with_axis_res_2(cascade_chunk.res_2)
+	}
+}
+
+impl IdentifiedMesh for LeafCard {
+	fn id(&self) -> MeshId {
+		MeshId::new(format!("{self:?}"))
+	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		MeshSpace::Unit
+	}
+}
+
+impl MeshBuilder for LeafCard {
+	fn build_mesh_impl(&self, cascade_chunk: &CascadeChunk) -> Option<Mesh> {
+		let half_width = self.width.clamp(0.0, 1.0) / 2.0;
+		let widest = self.widest.clamp(0.0, 1.0) - 0.5;
+		let outline = [
+			Vec3::new(0.0, 0.0, -0.5),
+			Vec3::new(half_width, 0.0, widest),
+			Vec3::new(0.0, 0.0, 0.5),
+			Vec3::new(-half_width, 0.0, widest),
+		];
+
+		// Each side gets its own vertices, so the two faces light independently
+		let mut positions = Vec::with_capacity(8);
+		let mut normals = Vec::with_capacity(8);
+		let mut uvs = Vec::with_capacity(8);
+		for normal in [Vec3::Y, Vec3::NEG_Y] {
+			for vertex in outline {
+				positions.push((vertex - cascade_chunk.origin).to_array());
+				normals.push(normal.to_array());
+				uvs.push([vertex.x + 0.5, vertex.z + 0.5]);
+			}
+		}
+		let indices = vec![0, 2, 1, 0, 3, 2, 4, 5, 6, 4, 6, 7];
+
+		let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+		mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+		mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+		mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+		mesh.insert_indices(Indices::U32(indices));
+		Some(mesh)
+	}
+}
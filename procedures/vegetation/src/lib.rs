@@ -1,3 +1,6 @@
 pub mod forest;
 pub mod grove;
+pub mod ivy;
+pub mod prototype;
+pub mod species;
 pub mod tree;
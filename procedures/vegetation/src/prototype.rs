@@ -0,0 +1,637 @@
+use crate::grove::{GroveBuilder, TreePlacement};
+use crate::tree::builder::{Tree, TreeBuilder};
+use crate::tree::meshes::canopy::ball::NoisyBall;
+use crate::tree::meshes::trunk::segment::SimpleTrunkSegment;
+use crate::tree::spine::TrunkShape;
+use bevy::{
+	asset::RenderAssetUsages,
+	mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+	prelude::*,
+};
+use chunk::cascade::CascadeChunk;
+use comproc::noise::config::NoiseConfig;
+use noise::Perlin;
+use render_item::{
+	debug_name,
+	mesh::{cache::handle::map::HandleMap, fetch_meshes, handle::MeshHandle},
+	render_items, DispatchRenderItem, RenderItem,
+};
+use std::path::Path;
+
+/// Marks the start of a saved prototype library.
+const MAGIC: &[u8; 8] = b"WCTPTREE";
+
+/// Spacing of the anchors the prototypes of a species are grown at, off the noise lattice so
+/// each anchor gets its own tree.
+const PROTOTYPE_SPACING: f32 = 13.7;
+
+/// Updates that resolve a tree into meshes: one spawns its parts, one fetches their meshes.
+const BAKE_UPDATES: usize = 2;
+
+type BakedTree = Tree<NoisyBall, SimpleTrunkSegment, NoisyBall, StandardMaterial, StandardMaterial>;
+
+/// A kind of tree that is baked into prototypes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeSpecies {
+	pub name: String,
+	/// Height the prototypes are grown to, which spawned trees are scaled from
+	pub height: f32,
+	pub branch_count: usize,
+	pub leaf_ball_scale: Vec3,
+	/// Seed of the noise that shapes the branches
+	pub seed: u32,
+	/// Resolution the parts are meshed at
+	pub res_2: u8,
+	/// How the trunks bend and lean
+	pub trunk_shape: TrunkShape,
+}
+
+impl TreeSpecies {
+	pub fn new(name: impl Into<String>) -> Self {
+		Self {
+			name: name.into(),
+			height: 4.0,
+			branch_count: 4,
+			leaf_ball_scale: Vec3::ONE,
+			seed: 0,
+			res_2: 3,
+			trunk_shape: TrunkShape::default(),
+		}
+	}
+
+	pub fn with_height(mut self, height: f32) -> Self {
+		self.height = height;
+		self
+	}
+
+	pub fn with_branch_count(mut self, branch_count: usize) -> Self {
+		self.branch_count = branch_count;
+		self
+	}
+
+	pub fn with_leaf_ball_scale(mut self, leaf_ball_scale: Vec3) -> Self {
+		self.leaf_ball_scale = leaf_ball_scale;
+		self
+	}
+
+	pub fn with_seed(mut self, seed: u32) -> Self {
+		self.seed = seed;
+		self
+	}
+
+	pub fn with_res_2(mut self, res_2: u8) -> Self {
+		self.res_2 = res_2;
+		self
+	}
+
+	pub fn with_trunk_shape(mut self, trunk_shape: TrunkShape) -> Self {
+		self.trunk_shape = trunk_shape;
+		self
+	}
+}
+
+/// The vertices of a baked mesh, in the space of the tree with its base at the origin.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrototypeMesh {
+	pub positions: Vec<[f32; 3]>,
+	pub normals: Vec<[f32; 3]>,
+	/// Empty unless every merged part had UVs
+	pub uvs: Vec<[f32; 2]>,
+	pub indices: Vec<u32>,
+}
+
+impl PrototypeMesh {
+	/// Merges meshes placed by their transforms into one.
+	pub fn merge<'a>(parts: impl IntoIterator<Item = (&'a Mesh, Transform)>) -> Self {
+		let mut merged = Self::default();
+		let mut all_uvs = true;
+		let mut all_normals = true;
+		for (mesh, transform) in parts {
+			let Some(positions) =
+				mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(|p| p.as_float3())
+			else {
+				continue;
+			};
+			let base = merged.positions.len() as u32;
+			merged.positions.extend(
+				positions
+					.iter()
+					.map(|p| transform.transform_point(Vec3::from_array(*p)).to_array()),
+			);
+
+			match mesh.attribute(Mesh::ATTRIBUTE_NORMAL).and_then(|n| n.as_float3()) {
+				Some(normals) => merged.normals.extend(normals.iter().map(|n| {
+					(transform.rotation * (Vec3::from_array(*n) / transform.scale))
+						.normalize_or_zero()
+						.to_array()
+				})),
+				None => all_normals = false,
+			}
+			match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+				Some(VertexAttributeValues::Float32x2(uvs)) if all_uvs => {
+					merged.uvs.extend_from_slice(uvs);
+				}
+				_ => all_uvs = false,
+			}
+
+			match mesh.indices() {
+				Some(indices) => merged.indices.extend(indices.iter().map(|i| base + i as u32)),
+				None => merged.indices.extend(base..base + positions.len() as u32),
+			}
+		}
+		if !all_uvs {
+			merged.uvs.clear();
+		}
+		if !all_normals {
+			let mut mesh = merged.clone().with_normals(Vec::new()).into_mesh();
+			mesh.compute_smooth_normals();
+			merged.normals = mesh
+				.attribute(Mesh::ATTRIBUTE_NORMAL)
+				.and_then(|n| n.as_float3())
+				.map(<[[f32; 3]]>::to_vec)
+				.unwrap_or_default();
+		}
+		merged
+	}
+
+	fn with_normals(mut self, normals: Vec<[f32; 3]>) -> Self {
+		self.normals = normals;
+		self
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.indices.is_empty()
+	}
+
+	pub fn into_mesh(self) -> Mesh {
+		let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+		mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+		if !self.normals.is_empty() {
+			mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+		}
+		if !self.uvs.is_empty() {
+			mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+		}
+		mesh.insert_indices(Indices::U32(self.indices));
+		mesh
+	}
+}
+
+/// One baked tree, split by material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreePrototype {
+	pub wood: PrototypeMesh,
+	pub leaves: PrototypeMesh,
+	/// Height the tree was grown to
+	pub height: f32,
+}
+
+/// The baked prototypes of a species.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeciesPrototypes {
+	pub species: String,
+	pub prototypes: Vec<TreePrototype>,
+}
+
+/// Grows a tree of the species at the anchor and merges its parts into a prototype.
+///
+/// The tree is spawned through the same render items and mesh fetching as a grove's trees, in
+/// a headless app, so the prototype looks like the trees grown at runtime.
+fn bake_tree(species: &TreeSpecies, anchor: Vec3) -> TreePrototype {
+	let mut app = App::new();
+	app.add_plugins(AssetPlugin::default())
+		.init_asset::<Mesh>()
+		.init_asset::<StandardMaterial>()
+		.add_systems(
+			Update,
+			(
+				render_items::<BakedTree>,
+				fetch_meshes::<MeshHandle<SimpleTrunkSegment>, StandardMaterial>,
+				fetch_meshes::<MeshHandle<NoisyBall>, StandardMaterial>,
+			)
+				.chain(),
+		);
+	let mut materials = app.world_mut().resource_mut::<Assets<StandardMaterial>>();
+	let wood_material = materials.add(StandardMaterial::default());
+	let leaf_material = materials.add(StandardMaterial::default());
+
+	let tree: BakedTree = TreeBuilder {
+		anchor,
+		height: species.height,
+		branch_count: species.branch_count,
+		leaf_ball_scale: species.leaf_ball_scale,
+		noise_config_3d: NoiseConfig::new(Perlin::default()).with_seed(species.seed),
+		noise_config_4d: NoiseConfig::new(Perlin::default()).with_seed(species.seed),
+		ball_variety: 0,
+		ball_buckets: 0,
+		ball_cache: HandleMap::new(),
+		stick_variety: 1,
+		stick_buckets: 0,
+		stick_cache: HandleMap::new(),
+		leaf_variety: 1,
+		leaf_buckets: 0,
+		leaf_cache: HandleMap::new(),
+		stick_material: MeshMaterial3d(wood_material.clone()),
+		leaf_material: MeshMaterial3d(leaf_material.clone()),
+		shadow_proxy_cache: None,
+		trunk_shape: species.trunk_shape.clone(),
+	}
+	.build();
+	app.world_mut().spawn((
+		CascadeChunk::unit_center_chunk().with_res_2(species.res_2),
+		DispatchRenderItem::new(tree),
+		Transform::from_translation(anchor),
+	));
+	for _ in 0..BAKE_UPDATES {
+		app.update();
+	}
+
+	let mut wood = Vec::new();
+	let mut leaves = Vec::new();
+	let world = app.world_mut();
+	let mut query = world.query::<(&Mesh3d, &Transform, &MeshMaterial3d<StandardMaterial>)>();
+	let meshes = world.resource::<Assets<Mesh>>();
+	for (mesh, transform, material) in query.iter(world) {
+		let Some(mesh) = meshes.get(&mesh.0) else {
+			continue;
+		};
+		// Based at the origin, wherever the tree was grown
+		let transform = Transform::from_translation(-anchor) * *transform;
+		if material.0 == wood_material {
+			wood.push((mesh, transform));
+		} else {
+			leaves.push((mesh, transform));
+		}
+	}
+
+	TreePrototype {
+		wood: PrototypeMesh::merge(wood),
+		leaves: PrototypeMesh::merge(leaves),
+		height: species.height,
+	}
+}
+
+/// Bakes count prototypes of the species, each grown at its own anchor.
+pub fn bake_species(species: &TreeSpecies, count: usize) -> SpeciesPrototypes {
+	let prototypes = (0..count)
+		.map(|i| {
+			let anchor = Vec3::new(i as f32 * PROTOTYPE_SPACING, 0.0, PROTOTYPE_SPACING / 2.0);
+			bake_tree(species, anchor)
+		})
+		.collect();
+	log::info!("Baked {count} prototypes of {}", species.name);
+	SpeciesPrototypes { species: species.name.clone(), prototypes }
+}
+
+/// Trees baked ahead of time, a few per species, for spawning without generating any meshes.
+///
+/// Built with [TreePrototypeLibrary::bake], usually offline by the `bake_tree_prototypes`
+/// binary, and saved to a file that is loaded at runtime.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TreePrototypeLibrary {
+	species: Vec<SpeciesPrototypes>,
+}
+
+impl TreePrototypeLibrary {
+	pub fn bake(species: &[TreeSpecies], count: usize) -> Self {
+		Self { species: species.iter().map(|species| bake_species(species, count)).collect() }
+	}
+
+	pub fn species(&self, name: &str) -> Option<&SpeciesPrototypes> {
+		self.species.iter().find(|species| species.species == name)
+	}
+
+	pub fn species_names(&self) -> impl Iterator<Item = &str> {
+		self.species.iter().map(|species| species.species.as_str())
+	}
+
+	/// Adds the prototypes of the species to the mesh assets, for a [PrototypeForestSpawner].
+	pub fn add_meshes(&self, name: &str, meshes: &mut Assets<Mesh>) -> Option<PrototypeMeshes> {
+		let species = self.species(name)?;
+		if species.prototypes.is_empty() {
+			return None;
+		}
+		let prototypes = species
+			.prototypes
+			.iter()
+			.map(|prototype| PrototypeHandles {
+				wood: meshes.add(prototype.wood.clone().into_mesh()),
+				leaves: meshes.add(prototype.leaves.clone().into_mesh()),
+				height: prototype.height,
+			})
+			.collect();
+		Some(PrototypeMeshes { prototypes })
+	}
+
+	/// Serializes the library to little endian bytes.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = MAGIC.to_vec();
+		write_u32(&mut bytes, self.species.len() as u32);
+		for species in &self.species {
+			write_u32(&mut bytes, species.species.len() as u32);
+			bytes.extend_from_slice(species.species.as_bytes());
+			write_u32(&mut bytes, species.prototypes.len() as u32);
+			for prototype in &species.prototypes {
+				bytes.extend_from_slice(&prototype.height.to_le_bytes());
+				write_mesh(&mut bytes, &prototype.wood);
+				write_mesh(&mut bytes, &prototype.leaves);
+			}
+		}
+		bytes
+	}
+
+	/// Reads a library written by [TreePrototypeLibrary::to_bytes].
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+		if bytes.get(..MAGIC.len()) != Some(MAGIC.as_slice()) {
+			return Err("Not a tree prototype library".to_string());
+		}
+		let mut reader = Reader { bytes, cursor: MAGIC.len() };
+		let mut species = Vec::new();
+		for _ in 0..reader.u32()? {
+			let name_len = reader.u32()? as usize;
+			let name = String::from_utf8(reader.take(name_len)?.to_vec())
+				.map_err(|e| format!("Invalid species name: {e}"))?;
+			let mut prototypes = Vec::new();
+			for _ in 0..reader.u32()? {
+				let height = reader.f32()?;
+				let wood = reader.mesh()?;
+				let leaves = reader.mesh()?;
+				prototypes.push(TreePrototype { wood, leaves, height });
+			}
+			species.push(SpeciesPrototypes { species: name, prototypes });
+		}
+		if reader.cursor != bytes.len() {
+			return Err(format!("{} trailing bytes", bytes.len() - reader.cursor));
+		}
+		Ok(Self { species })
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), String> {
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)
+				.map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+		}
+		std::fs::write(path, self.to_bytes())
+			.map_err(|e| format!("Failed to write prototypes to {}: {e}", path.display()))
+	}
+
+	pub fn load(path: &Path) -> Result<Self, String> {
+		let bytes = std::fs::read(path)
+			.map_err(|e| format!("Failed to read prototypes from {}: {e}", path.display()))?;
+		Self::from_bytes(&bytes)
+			.map_err(|e| format!("Failed to parse prototypes from {}: {e}", path.display()))
+	}
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+	bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_mesh(bytes: &mut Vec<u8>, mesh: &PrototypeMesh) {
+	for len in [mesh.positions.len(), mesh.normals.len(), mesh.uvs.len(), mesh.indices.len()] {
+		write_u32(bytes, len as u32);
+	}
+	for value in mesh
+		.positions
+		.iter()
+		.chain(&mesh.normals)
+		.flatten()
+		.chain(mesh.uvs.iter().flatten())
+	{
+		bytes.extend_from_slice(&value.to_le_bytes());
+	}
+	for index in &mesh.indices {
+		write_u32(bytes, *index);
+	}
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+	cursor: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+		let end = self.cursor.checked_add(len).filter(|&end| end <= self.bytes.len());
+		let Some(end) = end else {
+			return Err(format!("Unexpected end of library at byte {}", self.cursor));
+		};
+		let slice = &self.bytes[self.cursor..end];
+		self.cursor = end;
+		Ok(slice)
+	}
+
+	fn word(&mut self) -> Result<[u8; 4], String> {
+		let mut word = [0u8; 4];
+		word.copy_from_slice(self.take(4)?);
+		Ok(word)
+	}
+
+	fn u32(&mut self) -> Result<u32, String> {
+		self.word().map(u32::from_le_bytes)
+	}
+
+	fn f32(&mut self) -> Result<f32, String> {
+		self.word().map(f32::from_le_bytes)
+	}
+
+	fn floats<const N: usize>(&mut self, count: usize) -> Result<Vec<[f32; N]>, String> {
+		(0..count)
+			.map(|_| {
+				let mut values = [0.0; N];
+				for value in &mut values {
+					*value = self.f32()?;
+				}
+				Ok(values)
+			})
+			.collect()
+	}
+
+	fn mesh(&mut self) -> Result<PrototypeMesh, String> {
+		let [positions, normals, uvs, indices] =
+			[self.u32()?, self.u32()?, self.u32()?, self.u32()?].map(|len| len as usize);
+		let mesh = PrototypeMesh {
+			positions: self.floats(positions)?,
+			normals: self.floats(normals)?,
+			uvs: self.floats(uvs)?,
+			indices: (0..indices).map(|_| self.u32()).collect::<Result<_, _>>()?,
+		};
+		if mesh.indices.iter().any(|&i| i as usize >= mesh.positions.len()) {
+			return Err("Index out of range of the vertices".to_string());
+		}
+		Ok(mesh)
+	}
+}
+
+/// The meshes of a baked tree once added to the assets.
+#[derive(Debug, Clone)]
+pub struct PrototypeHandles {
+	pub wood: Handle<Mesh>,
+	pub leaves: Handle<Mesh>,
+	pub height: f32,
+}
+
+/// The prototypes of one species, ready to spawn.
+#[derive(Debug, Clone)]
+pub struct PrototypeMeshes {
+	prototypes: Vec<PrototypeHandles>,
+}
+
+impl PrototypeMeshes {
+	pub fn len(&self) -> usize {
+		self.prototypes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.prototypes.is_empty()
+	}
+
+	/// Index of the prototype standing at the position, spread evenly over the library.
+	pub fn index_for_position(&self, position: Vec3) -> usize {
+		let hash =
+			(position.x.to_bits() ^ position.z.to_bits().rotate_left(16)).wrapping_mul(0x9E37_79B9);
+		(hash >> 8) as usize % self.prototypes.len()
+	}
+
+	/// The prototype standing at the position.
+	pub fn for_position(&self, position: Vec3) -> &PrototypeHandles {
+		&self.prototypes[self.index_for_position(position)]
+	}
+}
+
+/// A forest placed like a grove, but made only of baked prototypes.
+///
+/// No tree meshes are generated at runtime: each tree the grove places gets the meshes of one
+/// of the prototypes, scaled to its height. Trees repeat where a grove would vary them, which
+/// is cheaper to spawn and shares meshes on low-end targets.
+///
+/// Both parts of a tree carry the grove's [SpeciesProperties](crate::species::SpeciesProperties).
+#[derive(Component, Clone)]
+pub struct PrototypeForestSpawner<T: Material, L: Material> {
+	grove: GroveBuilder<T, L>,
+	prototypes: PrototypeMeshes,
+}
+
+impl<T: Material, L: Material> PrototypeForestSpawner<T, L> {
+	pub fn new(grove: GroveBuilder<T, L>, prototypes: PrototypeMeshes) -> Self {
+		Self { grove, prototypes }
+	}
+
+	fn spawn_tree(
+		&self,
+		commands: &mut Commands,
+		placement: TreePlacement,
+		transform: Transform,
+	) -> [Entity; 2] {
+		let index = self.prototypes.index_for_position(placement.position);
+		let prototype = &self.prototypes.prototypes[index];
+		let (trunk_material, leaf_material) = self.grove.materials();
+		let scale = if prototype.height > 0.0 { placement.height / prototype.height } else { 1.0 };
+		let transform =
+			transform.with_translation(placement.position).with_scale(Vec3::splat(scale));
+		let properties = self.grove.species_properties();
+		let wood = commands
+			.spawn((Mesh3d(prototype.wood.clone()), trunk_material.clone(), transform, properties))
+			.id();
+		let leaves = commands
+			.spawn((Mesh3d(prototype.leaves.clone()), leaf_material.clone(), transform, properties))
+			.id();
+		let position = placement.position;
+		for (entity, part) in [(wood, "wood"), (leaves, "leaves")] {
+			debug_name(commands, entity, || {
+				format!(
+					"tree prototype#{index}@({}, {}, {}) {part}",
+					position.x, position.y, position.z
+				)
+			});
+		}
+		[wood, leaves]
+	}
+}
+
+impl<T: Material, L: Material> RenderItem for PrototypeForestSpawner<T, L> {
+	fn spawn_render_items(
+		&self,
+		commands: &mut Commands,
+		cascade_chunk: &CascadeChunk,
+		transform: Transform,
+	) -> Vec<Entity> {
+		self.grove
+			.for_chunk(cascade_chunk)
+			.placements()
+			.into_iter()
+			.flat_map(|placement| self.spawn_tree(commands, placement, transform))
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use render_item::mesh::MeshDispatch;
+
+	#[test]
+	fn test_prototype_forest_spawns_from_the_library() {
+		let species = [TreeSpecies::new("birch").with_height(5.0).with_seed(7)];
+		let library = TreePrototypeLibrary::bake(&species, 2);
+		let Some(birch) = library.species("birch") else {
+			panic!("expected the birch to be baked");
+		};
+		assert_eq!(birch.prototypes.len(), 2);
+		for prototype in &birch.prototypes {
+			assert!(!prototype.wood.is_empty() && !prototype.leaves.is_empty());
+			// Standing over the origin, wherever it was grown
+			let center =
+				prototype.wood.positions.iter().map(|p| Vec3::from_array(*p).xz()).sum::<Vec2>()
+					/ prototype.wood.positions.len() as f32;
+			assert!(center.length() < 1.0, "The wood is centered at {center}");
+		}
+
+		let Ok(loaded) = TreePrototypeLibrary::from_bytes(&library.to_bytes()) else {
+			panic!("expected the library to load");
+		};
+		assert_eq!(loaded, library);
+		let bytes = library.to_bytes();
+		assert!(TreePrototypeLibrary::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+		let mut app = App::new();
+		app.add_plugins(AssetPlugin::default()).init_asset::<Mesh>().add_systems(
+			Update,
+			render_items::<PrototypeForestSpawner<StandardMaterial, StandardMaterial>>,
+		);
+		let Some(prototypes) =
+			loaded.add_meshes("birch", &mut app.world_mut().resource_mut::<Assets<Mesh>>())
+		else {
+			panic!("expected the birch meshes");
+		};
+		assert!(loaded.add_meshes("oak", &mut Assets::default()).is_none());
+		let library_meshes: Vec<_> = prototypes
+			.prototypes
+			.iter()
+			.flat_map(|prototype| [prototype.wood.id(), prototype.leaves.id()])
+			.collect();
+
+		let grove = GroveBuilder::<StandardMaterial, StandardMaterial>::new(
+			MeshMaterial3d(Handle::default()),
+			MeshMaterial3d(Handle::default()),
+		);
+		let chunk = CascadeChunk::cube(Vec3::new(-32.0, -8.0, -32.0), 64.0, 3);
+		let placements = grove.for_chunk(&chunk).placements();
+		assert!(!placements.is_empty());
+		app.world_mut().spawn((
+			chunk,
+			DispatchRenderItem::new(PrototypeForestSpawner::new(grove, prototypes)),
+			Transform::default(),
+		));
+		app.update();
+
+		// Two library meshes per placed tree and nothing left to generate
+		let world = app.world_mut();
+		let spawned: Vec<_> = world.query::<&Mesh3d>().iter(world).map(|mesh| mesh.id()).collect();
+		assert_eq!(spawned.len(), placements.len() * 2);
+		assert!(spawned.iter().all(|mesh| library_meshes.contains(mesh)));
+		let properties = world.query::<&crate::species::SpeciesProperties>().iter(world).count();
+		assert_eq!(properties, spawned.len());
+		assert_eq!(world.query::<&MeshDispatch<MeshHandle<NoisyBall>>>().iter(world).count(), 0);
+	}
+}
@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+
+/// Physical parameters of the wood and leaves of a tree species, for the simulation layers.
+///
+/// Rendering never reads them. They are attached as a component to every tree a grove, forest
+/// or prototype spawner generates, so physics, destruction and wind can be developed against
+/// them. They are kept out of the tree shapes and the prototype library, so they can be tuned
+/// without regenerating or rebaking any trees.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Component, Debug, Default))]
+pub struct SpeciesProperties {
+	/// Density of the wood, in kilograms per cubic meter
+	pub wood_density: f32,
+	/// Bending stress the branches and trunk break at, in megapascals
+	pub break_threshold: f32,
+	/// Leaf area of the canopy per square meter of ground it covers
+	pub leaf_area: f32,
+}
+
+impl Default for SpeciesProperties {
+	/// A temperate broadleaf.
+	fn default() -> Self {
+		Self { wood_density: 650.0, break_threshold: 80.0, leaf_area: 4.0 }
+	}
+}
+
+impl SpeciesProperties {
+	pub fn new(wood_density: f32, break_threshold: f32, leaf_area: f32) -> Self {
+		Self {
+			wood_density: wood_density.max(0.0),
+			break_threshold: break_threshold.max(0.0),
+			leaf_area: leaf_area.max(0.0),
+		}
+	}
+
+	/// A light, resinous conifer with a dense evergreen canopy.
+	pub fn conifer() -> Self {
+		Self::new(450.0, 60.0, 6.0)
+	}
+
+	pub fn with_wood_density(mut self, wood_density: f32) -> Self {
+		self.wood_density = wood_density.max(0.0);
+		self
+	}
+
+	pub fn with_break_threshold(mut self, break_threshold: f32) -> Self {
+		self.break_threshold = break_threshold.max(0.0);
+		self
+	}
+
+	pub fn with_leaf_area(mut self, leaf_area: f32) -> Self {
+		self.leaf_area = leaf_area.max(0.0);
+		self
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grove::{Grove, GroveBuilder};
+	use chunk::cascade::CascadeChunk;
+	use render_item::{render_items, DispatchRenderItem};
+
+	#[test]
+	fn test_grown_trees_carry_their_species_properties() {
+		let properties = SpeciesProperties::conifer().with_break_threshold(45.0);
+		let grove = GroveBuilder::<StandardMaterial, StandardMaterial>::new(
+			MeshMaterial3d(Handle::default()),
+			MeshMaterial3d(Handle::default()),
+		)
+		.with_species_properties(properties);
+		let chunk = CascadeChunk::cube(Vec3::new(-16.0, -8.0, -16.0), 32.0, 0);
+		let trees = grove.for_chunk(&chunk).placements().len();
+		assert!(trees > 0);
+
+		let mut app = App::new();
+		app.add_plugins(AssetPlugin::default())
+			.init_asset::<Mesh>()
+			.add_systems(Update, render_items::<Grove<StandardMaterial, StandardMaterial>>);
+		app.world_mut().spawn((
+			chunk,
+			DispatchRenderItem::new(grove.for_chunk(&chunk).build()),
+			Transform::default(),
+		));
+		app.update();
+
+		// One per tree, on its root rather than on each of its parts
+		let world = app.world_mut();
+		let carried: Vec<_> = world.query::<&SpeciesProperties>().iter(world).copied().collect();
+		assert_eq!(carried.len(), trees);
+		assert!(carried.iter().all(|carried| *carried == properties));
+	}
+}
@@ -1,23 +1,41 @@
-use bevy::prelude::*;
+use crate::tree::{
+	chop::TreeTrunk,
+	leaf_budget::TreeLeaf,
+	meshes::canopy::proxy::CanopyProxy,
+	radial_branches::RadialBranchesSegment,
+	skeleton::TreeSkeleton,
+	spine::{TrunkShape, TrunkSpine},
+};
+use bevy::{camera::visibility::RenderLayers, prelude::*};
 use chunk::cascade::CascadeChunk;
 use comproc::{
 	complex::chain::ball_stick::{
 		builder::{BallStick, BallStickBuilder},
-		render::{mesh_handle_stack::MeshHandleStackSpawner, BallStickRenderItem},
+		render::{mesh_handle_stack::MeshHandleStackSpawner, BallStickRenderItem, TaperedMesh},
 	},
 	noise::config::NoiseConfig,
 };
 use noise::{NoiseFn, Seedable};
 use render_item::{
+	debug_name,
 	mesh::{
 		cache::handle::map::HandleMap, handle::MeshHandle, IdentifiedMesh, MeshBuilder,
-		MeshDispatch,
+		MeshDispatch, MeshId, SHADOW_PROXY_LAYER,
 	},
-	NormalizeChunk, RenderItem,
+	NormalizeChunk, PartOfRenderItem, RenderItem,
 };
 use std::fmt::Debug;
 
-pub trait MeshFromTreeNum: MeshBuilder + NormalizeChunk + IdentifiedMesh {
+/// Horizontal scale of the outer trunk segment.
+const TRUNK_WIDTH: f32 = 0.9;
+
+/// How far leaf clusters lean from their branch back up toward the light.
+const LEAF_PHOTOTROPISM: f32 = 0.35;
+
+/// Share of each leaf ball's reach the shadow proxy covers, as light gets through the leaves.
+const PROXY_LEAF_COVERAGE: f32 = 0.7;
+
+pub trait MeshFromTreeNum: MeshBuilder + NormalizeChunk + IdentifiedMesh + TaperedMesh {
 	fn from_tree_num(tree_num: f32) -> Self;
 }
 
@@ -38,6 +56,10 @@ pub struct Tree<
 	branch_ball_sticks: Vec<BallStick>,
 	branch_spawner: MeshHandleStackSpawner<BallMesh, StickMesh, StickMaterial>,
 	leaf_spawner: MeshHandleStackSpawner<LeafMesh, LeafMesh, LeafMaterial>,
+	/// Casts the canopy's shadow in place of the leaves, if any
+	shadow_proxy: Option<MeshHandle<CanopyProxy>>,
+	/// The curve the trunk segments are swept along, from the anchor
+	spine: TrunkSpine,
 }
 
 impl<
@@ -51,33 +73,103 @@ where
 	(CascadeChunk, MeshDispatch<MeshHandle<StickMesh>>, Transform, MeshMaterial3d<StickMaterial>):
 		Bundle,
 {
-	pub fn centroid_anchor(&self) -> Vec3 {
-		let pivot_offset = Vec3::new(0.5, 0.0, 0.5);
-		self.anchor - pivot_offset * Vec3::new(1.0, 1.0, 1.0)
-	}
-
-	pub fn spawn_trunk(&self, commands: &mut Commands, cascade_chunk: &CascadeChunk) {
+	pub fn spawn_trunk(
+		&self,
+		commands: &mut Commands,
+		cascade_chunk: &CascadeChunk,
+	) -> Vec<Entity> {
 		// Build tree segment dispatch
-		if let Some(mesh_handle) = self.trunk_meshes.get(0) {
-			commands.spawn((
-				CascadeChunk::unit_center_chunk().with_res_2(3),
+		let Some(mesh_handle) = self.trunk_meshes.first() else {
+			return vec![];
+		};
+
+		// The core fills the lower half of the first segment, and the trunk is swept along
+		// the spine a segment at a time
+		let segments = self.spine.segments();
+		let Some(&(base, first_top)) = segments.first() else {
+			return vec![];
+		};
+		let core = commands.spawn((
+			CascadeChunk::unit_center_chunk().with_res_2(3),
+			MeshDispatch::new(mesh_handle.clone()),
+			segment_transform(self.anchor + base, first_top - base, Vec2::ONE, 0.5),
+			MeshMaterial3d(self.stick_material.0.clone()),
+		));
+		let core = core.id();
+		debug_name(commands, core, || "trunk core".to_string());
+
+		let mut entities = vec![core];
+		for (i, (start, end)) in segments.into_iter().enumerate() {
+			let trunk = commands.spawn((
+				cascade_chunk.clone(),
 				MeshDispatch::new(mesh_handle.clone()),
-				Transform::from_translation(self.centroid_anchor() + Vec3::new(0.0, 0.0, 0.0))
-					.with_scale(Vec3::new(1.0, self.height / 2.0, 1.0)),
+				segment_transform(self.anchor + start, end - start, Vec2::splat(TRUNK_WIDTH), 1.0),
 				MeshMaterial3d(self.stick_material.0.clone()),
 			));
+			let trunk = trunk.id();
+			debug_name(commands, trunk, || match i {
+				0 => "trunk".to_string(),
+				i => format!("trunk segment {i}"),
+			});
+			entities.push(trunk);
+		}
+		entities
+	}
 
-			commands.spawn((
-				cascade_chunk.clone(),
+	/// The curve the trunk follows from its anchor.
+	pub fn spine(&self) -> &TrunkSpine {
+		&self.spine
+	}
+
+	/// The box around the leaf balls of the canopy, shrunk by how much light they let through.
+	pub fn canopy_bounds(&self) -> Option<(Vec3, Vec3)> {
+		let reach = self.leaf_spawner.ball_scale * PROXY_LEAF_COVERAGE;
+		self.branch_ball_sticks
+			.iter()
+			.flat_map(BallStick::nodes)
+			.fold(None, |bounds, node| {
+				let (min, max) = bounds.unwrap_or((node.position, node.position));
+				Some((min.min(node.position - reach), max.max(node.position + reach)))
+			})
+	}
+
+	/// Spawns the blob casting the canopy's shadow, seen only by lights on the shadow proxy layer.
+	pub fn spawn_shadow_proxy(&self, commands: &mut Commands) -> Option<Entity> {
+		let mesh_handle = self.shadow_proxy.as_ref()?;
+		let (min, max) = self.canopy_bounds()?;
+		let center = (min + max) / 2.0;
+		let proxy = commands
+			.spawn((
+				CascadeChunk::unit_center_chunk(),
 				MeshDispatch::new(mesh_handle.clone()),
-				Transform::from_translation(self.centroid_anchor()).with_scale(Vec3::new(
-					0.9,
-					self.height,
-					0.9,
-				)),
+				Transform::from_translation(center.with_y(min.y)).with_scale(max - min),
 				MeshMaterial3d(self.stick_material.0.clone()),
-			));
-		};
+				RenderLayers::layer(SHADOW_PROXY_LAYER),
+			))
+			.id();
+		debug_name(commands, proxy, || "canopy shadow proxy".to_string());
+		Some(proxy)
+	}
+
+	/// Inspector label of the tree, from its trunk mesh id and anchor.
+	pub fn name(&self) -> String {
+		let id = self.trunk_meshes.first().map(|mesh| mesh.id());
+		let id = id.as_ref().map(MeshId::as_str).unwrap_or("bare");
+		format!("tree#{id}@({}, {}, {})", self.anchor.x, self.anchor.y, self.anchor.z)
+	}
+
+	/// The standing trunk, for chopping the tree down later.
+	pub fn trunk(&self) -> Option<TreeTrunk<StickMesh, StickMaterial>> {
+		let mesh_handle = self.trunk_meshes.first()?;
+		Some(
+			TreeTrunk::new(
+				self.anchor,
+				self.height,
+				mesh_handle.clone(),
+				self.stick_material.clone(),
+			)
+			.with_width(TRUNK_WIDTH),
+		)
 	}
 }
 
@@ -95,6 +187,7 @@ where
 		Bundle,
 	(CascadeChunk, MeshDispatch<MeshHandle<LeafMesh>>, Transform, MeshMaterial3d<LeafMaterial>):
 		Bundle,
+	TreeTrunk<StickMesh, StickMaterial>: Component,
 {
 	fn spawn_render_items(
 		&self,
@@ -102,32 +195,65 @@ where
 		cascade_chunk: &CascadeChunk,
 		transform: Transform,
 	) -> Vec<Entity> {
-		let mut entities = Vec::new();
+		let mut parts = Vec::new();
 		for branch in &self.branch_ball_sticks {
 			let branch_render_item =
 				BallStickRenderItem::new(branch.clone(), self.branch_spawner.clone());
-			entities.extend(branch_render_item.spawn_render_items(
-				commands,
-				cascade_chunk,
-				transform,
-			));
+			parts.extend(branch_render_item.spawn_render_items(commands, cascade_chunk, transform));
 
 			let (ballstick, _spawner) = branch_render_item.into_parts();
 			let leaf_render_item =
 				BallStickRenderItem::new(ballstick.clone(), self.leaf_spawner.clone());
-			entities.extend(leaf_render_item.spawn_render_items(
-				commands,
-				cascade_chunk,
-				transform,
-			));
+			for leaf in leaf_render_item.spawn_render_items(commands, cascade_chunk, transform) {
+				commands.entity(leaf).insert(TreeLeaf);
+				parts.push(leaf);
+			}
 		}
 
-		self.spawn_trunk(commands, cascade_chunk);
+		parts.extend(self.spawn_trunk(commands, cascade_chunk));
+		parts.extend(self.spawn_shadow_proxy(commands));
 
-		entities
+		// The tree's parts hang off a single root, so it can be removed without its neighbours
+		let mut root =
+			commands.spawn(TreeSkeleton::new(self.anchor, self.branch_ball_sticks.clone()));
+		if let Some(trunk) = self.trunk() {
+			root.insert(trunk);
+		}
+		let root = root.id();
+		debug_name(commands, root, || self.name());
+		for part in parts {
+			commands.entity(part).insert(PartOfRenderItem(root));
+		}
+
+		vec![root]
 	}
 }
 
+/// Places a unit trunk segment at the base, standing along the run and stretched over `share`
+/// of its length, with its width scaled across it.
+fn segment_transform(base: Vec3, run: Vec3, width: Vec2, share: f32) -> Transform {
+	let length = run.length();
+	let rotation = Quat::from_rotation_arc(Vec3::Y, run.try_normalize().unwrap_or(Vec3::Y));
+	Transform::from_translation(base).with_rotation(rotation).with_scale(Vec3::new(
+		width.x,
+		length * share,
+		width.y,
+	))
+}
+
+/// Snaps tree_num to the middle of one of `buckets` equal buckets over the unit interval.
+///
+/// Trees in the same bucket build identical meshes, so they share the cached handles
+/// instead of each tree getting its own. With no buckets, tree_num is kept as is.
+pub fn quantize_tree_num(tree_num: f32, buckets: u32) -> f32 {
+	if buckets == 0 {
+		return tree_num;
+	}
+	let buckets = buckets as f32;
+	let bucket = (tree_num.clamp(0.0, 1.0) * buckets).floor().min(buckets - 1.0);
+	(bucket + 0.5) / buckets
+}
+
 pub struct TreeBuilder<
 	BallMesh: MeshFromTreeNum,
 	StickMesh: MeshFromTreeNum,
@@ -144,13 +270,21 @@ pub struct TreeBuilder<
 	pub noise_config_3d: NoiseConfig<3, M>,
 	pub noise_config_4d: NoiseConfig<4, N>,
 	pub ball_variety: u32,
+	/// Buckets tree_num is quantized to for ball meshes, see [quantize_tree_num]
+	pub ball_buckets: u32,
 	pub ball_cache: HandleMap<BallMesh>,
 	pub stick_variety: u32,
+	pub stick_buckets: u32,
 	pub stick_cache: HandleMap<StickMesh>,
 	pub leaf_variety: u32,
+	pub leaf_buckets: u32,
 	pub leaf_cache: HandleMap<LeafMesh>,
 	pub stick_material: MeshMaterial3d<StickMaterial>,
 	pub leaf_material: MeshMaterial3d<LeafMaterial>,
+	/// Cache of the canopy shadow proxy; with one, the leaves cast no shadows of their own
+	pub shadow_proxy_cache: Option<HandleMap<CanopyProxy>>,
+	/// How the trunk bends and leans, seeded by the tree's tree_num
+	pub trunk_shape: TrunkShape,
 }
 
 impl<
@@ -163,11 +297,6 @@ impl<
 		M: NoiseFn<f64, 3> + Seedable + Debug + Clone,
 	> TreeBuilder<BallMesh, StickMesh, LeafMesh, N, M, StickMaterial, LeafMaterial>
 {
-	pub fn get_branch_height(&self, last_position: Vec3) -> f32 {
-		let noise_value = self.noise_config_3d.vec3_on_unit(last_position) as f32;
-		noise_value * self.height
-	}
-
 	pub fn branch_builder(&self, anchor: Vec3, initial_ray: Vec3) -> BallStickBuilder<N, M> {
 		BallStickBuilder::common_tree_builder()
 			.with_anchor(anchor)
@@ -185,53 +314,61 @@ impl<
 			.with_noise_config_4d(self.noise_config_4d.clone())
 	}
 
-	pub fn compute_radial_branches(&self) -> Vec<BallStick> {
-		let mut branches = Vec::new();
-		let pre_height = self.get_branch_height(self.anchor);
-		let mut last_position = self.anchor + Vec3::new(0.0, pre_height, 0.0);
-
-		for i in 0..self.branch_count {
-			let height = self.get_branch_height(last_position);
-			let angle = i as f32 * 2.0 * std::f32::consts::PI / self.branch_count as f32;
-			let initial_ray =
-				Vec3::new(angle.cos(), angle.sin() + angle.cos(), angle.sin()).normalize();
-
-			let branch_builder = self.branch_builder(last_position, initial_ray);
-			let branch = branch_builder.build();
-			branches.push(branch);
+	/// The curve the trunk follows, generated from the trunk shape and the tree_num.
+	pub fn trunk_spine(&self) -> TrunkSpine {
+		self.trunk_shape.spine(self.height, self.tree_num())
+	}
 
-			let biased_height = (self.anchor.y + height).max(last_position.y - height);
-			last_position = self.anchor + Vec3::new(0.0, biased_height, 0.0);
-		}
+	/// The branches of the tree around its anchor, attached along the trunk spine.
+	pub fn radial_branches(&self) -> RadialBranchesSegment<N, M> {
+		RadialBranchesSegment::new(self.branch_builder(self.anchor, Vec3::Y))
+			.with_anchor(self.anchor)
+			.with_height(self.height)
+			.with_branch_count(self.branch_count)
+			.with_spine(self.trunk_spine())
+	}
 
-		branches
+	pub fn compute_radial_branches(&self) -> Vec<BallStick>
+	where
+		N: Sync,
+		M: Sync,
+	{
+		self.radial_branches().branches()
 	}
 
 	pub fn tree_num(&self) -> f32 {
 		self.noise_config_3d.vec3_on_unit(self.anchor) as f32
 	}
 
-	pub fn build(self) -> Tree<BallMesh, StickMesh, LeafMesh, StickMaterial, LeafMaterial> {
+	pub fn build(self) -> Tree<BallMesh, StickMesh, LeafMesh, StickMaterial, LeafMaterial>
+	where
+		N: Sync,
+		M: Sync,
+	{
 		let branch_ball_sticks = self.compute_radial_branches();
 		let tree_num = self.tree_num();
+		let spine = self.trunk_spine();
 
+		let stick_tree_num = quantize_tree_num(tree_num, self.stick_buckets);
 		let stick_meshes: Vec<MeshHandle<StickMesh>> = (0..self.stick_variety)
 			.map(|i| {
-				MeshHandle::new(StickMesh::from_tree_num(tree_num + i as f32))
+				MeshHandle::new(StickMesh::from_tree_num(stick_tree_num + i as f32))
 					.with_handle_cache(self.stick_cache.clone())
 			})
 			.collect();
 
+		let ball_tree_num = quantize_tree_num(tree_num, self.ball_buckets);
 		let ball_meshes: Vec<MeshHandle<BallMesh>> = (0..self.ball_variety)
 			.map(|i| {
-				MeshHandle::new(BallMesh::from_tree_num(tree_num + i as f32))
+				MeshHandle::new(BallMesh::from_tree_num(ball_tree_num + i as f32))
 					.with_handle_cache(self.ball_cache.clone())
 			})
 			.collect();
 
+		let leaf_tree_num = quantize_tree_num(tree_num, self.leaf_buckets);
 		let leaf_meshes: Vec<MeshHandle<LeafMesh>> = (0..self.leaf_variety)
 			.map(|i| {
-				MeshHandle::new(LeafMesh::from_tree_num(tree_num + i as f32))
+				MeshHandle::new(LeafMesh::from_tree_num(leaf_tree_num + i as f32))
 					.with_handle_cache(self.leaf_cache.clone())
 			})
 			.collect();
@@ -244,7 +381,12 @@ impl<
 		let leaf_spawner =
 			MeshHandleStackSpawner::new(self.leaf_material.clone(), self.leaf_material.clone())
 				.with_ball_mesh_handle_stack(leaf_meshes.clone())
-				.with_ball_scale(self.leaf_ball_scale);
+				.with_ball_scale(self.leaf_ball_scale)
+				.with_ball_alignment(LEAF_PHOTOTROPISM)
+				.with_ball_shadows(self.shadow_proxy_cache.is_none());
+		let shadow_proxy = self
+			.shadow_proxy_cache
+			.map(|cache| MeshHandle::new(CanopyProxy::default()).with_handle_cache(cache));
 
 		Tree {
 			anchor: self.anchor,
@@ -255,6 +397,142 @@ impl<
 			branch_ball_sticks,
 			branch_spawner,
 			leaf_spawner,
+			shadow_proxy,
+			spine,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grove::{Grove, GroveBuilder};
+	use crate::tree::meshes::canopy::ball::NoisyBall;
+	use crate::tree::meshes::trunk::segment::SimpleTrunkSegment;
+	use bevy::light::NotShadowCaster;
+	use noise::Perlin;
+	use render_item::{mesh::fetch_meshes, render_items, DispatchRenderItem};
+	use std::collections::HashSet;
+
+	#[test]
+	fn test_quantized_trees_share_meshes() {
+		assert_eq!(quantize_tree_num(0.0, 4), 0.125);
+		assert_eq!(quantize_tree_num(1.0, 4), 0.875);
+		assert_eq!(quantize_tree_num(0.3, 4), 0.375);
+		assert_eq!(quantize_tree_num(0.3, 0), 0.3);
+
+		// A thousand trees with continuously varying tree_num build only a few leaf meshes
+		let ids: HashSet<_> = (0..1000)
+			.map(|i| NoisyBall::from_tree_num(quantize_tree_num(i as f32 / 999.0, 4)).id())
+			.collect();
+		assert_eq!(ids.len(), 4);
+	}
+
+	#[test]
+	fn test_shadow_proxies_cast_the_canopy_shadows() {
+		let mut app = App::new();
+		app.add_plugins(AssetPlugin::default())
+			.init_asset::<Mesh>()
+			.init_asset::<StandardMaterial>()
+			.add_systems(
+				Update,
+				(
+					render_items::<Grove<StandardMaterial, StandardMaterial>>,
+					fetch_meshes::<MeshHandle<SimpleTrunkSegment>, StandardMaterial>,
+					fetch_meshes::<MeshHandle<NoisyBall>, StandardMaterial>,
+					fetch_meshes::<MeshHandle<CanopyProxy>, StandardMaterial>,
+				)
+					.chain(),
+			);
+		let proxy_cache = HandleMap::<CanopyProxy>::new();
+		let grove = GroveBuilder::new(
+			MeshMaterial3d(Handle::<StandardMaterial>::default()),
+			MeshMaterial3d(Handle::<StandardMaterial>::default()),
+		)
+		.with_shadow_proxies(proxy_cache.clone())
+		.for_chunk(&CascadeChunk::cube(Vec3::new(0.0, -8.0, 0.0), 16.0, 2))
+		.build();
+		app.world_mut().spawn((
+			CascadeChunk::unit_center_chunk().with_res_2(2),
+			DispatchRenderItem::new(grove),
+			Transform::default(),
+		));
+		app.update();
+
+		let mut trees = app.world_mut().query::<&TreeSkeleton>();
+		let tree_count = trees.iter(app.world()).count();
+		assert!(tree_count > 0);
+
+		// One proxy per tree, seen only on the proxy layer and sharing one mesh
+		let mut proxies = app.world_mut().query::<(&Mesh3d, &RenderLayers)>();
+		let proxies: Vec<_> = proxies.iter(app.world()).collect();
+		assert_eq!(proxies.len(), tree_count);
+		assert!(proxies
+			.iter()
+			.all(|(_, layers)| **layers == RenderLayers::layer(SHADOW_PROXY_LAYER)));
+		assert_eq!(proxy_cache.len(), 1);
+
+		// The leaves no longer cast shadows, the trunks still do
+		let mut meshes = app.world_mut().query::<(&Mesh3d, Has<NotShadowCaster>)>();
+		let leaves = meshes.iter(app.world()).filter(|(_, not_caster)| *not_caster).count();
+		assert!(leaves > 0 && leaves < meshes.iter(app.world()).count() - tree_count);
+	}
+
+	#[test]
+	fn test_trunks_are_swept_along_their_spine() {
+		type TestTree =
+			Tree<NoisyBall, SimpleTrunkSegment, NoisyBall, StandardMaterial, StandardMaterial>;
+		let anchor = Vec3::new(3.0, 1.0, -2.0);
+		let tree: TestTree = TreeBuilder {
+			anchor,
+			height: 6.0,
+			branch_count: 3,
+			leaf_ball_scale: Vec3::ONE,
+			noise_config_3d: NoiseConfig::new(Perlin::default()),
+			noise_config_4d: NoiseConfig::new(Perlin::default()),
+			ball_variety: 0,
+			ball_buckets: 0,
+			ball_cache: HandleMap::new(),
+			stick_variety: 1,
+			stick_buckets: 0,
+			stick_cache: HandleMap::new(),
+			leaf_variety: 1,
+			leaf_buckets: 0,
+			leaf_cache: HandleMap::new(),
+			stick_material: MeshMaterial3d(Handle::default()),
+			leaf_material: MeshMaterial3d(Handle::default()),
+			shadow_proxy_cache: None,
+			trunk_shape: TrunkShape::wind_swept(Vec2::X, 0.3),
+		}
+		.build();
+		let segments = tree.spine().segments();
+		assert_eq!(segments.len(), 3);
+
+		let mut app = App::new();
+		app.add_systems(Update, render_items::<TestTree>);
+		app.world_mut().spawn((
+			CascadeChunk::unit_center_chunk(),
+			DispatchRenderItem::new(tree),
+			Transform::default(),
+		));
+		app.update();
+
+		// Each segment stands on the end of the one below, tilted along the spine
+		let world = app.world_mut();
+		let transforms: Vec<Transform> = world
+			.query_filtered::<&Transform, With<MeshDispatch<MeshHandle<SimpleTrunkSegment>>>>()
+			.iter(world)
+			.copied()
+			.collect();
+		for (start, end) in segments {
+			let Some(segment) = transforms.iter().find(|transform| {
+				transform.translation.distance(anchor + start) < 1e-4
+					&& transform.scale.x == TRUNK_WIDTH
+			}) else {
+				panic!("expected a trunk segment at {start}");
+			};
+			let up = segment.rotation * Vec3::Y * segment.scale.y;
+			assert!(up.distance(end - start) < 1e-3, "{up} along {}", end - start);
 		}
 	}
 }
@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use chunk::cascade::CascadeChunk;
+use render_item::{
+	mesh::{handle::MeshHandle, IdentifiedMesh, MeshBuilder, MeshDispatch},
+	DispatchRenderItem, RenderItem,
+};
+use std::collections::HashSet;
+
+/// Asks for a standing tree to be chopped down.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ChopTree {
+	/// The root entity of the tree, which carries its [TreeTrunk]
+	pub tree: Entity,
+	/// The direction the tree falls in, flattened onto the ground
+	pub direction: Vec3,
+}
+
+/// Written once a tree has been replaced by a fallen log and a stump.
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+pub struct TreeChopped {
+	pub tree: Entity,
+	pub base: Vec3,
+	pub direction: Vec3,
+	pub log: Entity,
+	pub stump: Entity,
+}
+
+/// The trunk of a standing tree, kept on the root entity that owns the tree's parts.
+#[derive(Component, Clone)]
+pub struct TreeTrunk<M: MeshBuilder + IdentifiedMesh + Clone, T: Material> {
+	pub base: Vec3,
+	pub height: f32,
+	/// Horizontal scale of the trunk mesh
+	pub width: f32,
+	/// Height of the stump left behind, as a fraction of the trunk height
+	pub stump_fraction: f32,
+	mesh: MeshHandle<M>,
+	material: MeshMaterial3d<T>,
+}
+
+impl<M: MeshBuilder + IdentifiedMesh + Clone, T: Material> TreeTrunk<M, T> {
+	pub fn new(base: Vec3, height: f32, mesh: MeshHandle<M>, material: MeshMaterial3d<T>) -> Self {
+		Self { base, height, width: 1.0, stump_fraction: 0.15, mesh, material }
+	}
+
+	pub fn with_width(mut self, width: f32) -> Self {
+		self.width = width;
+		self
+	}
+
+	pub fn with_stump_fraction(mut self, stump_fraction: f32) -> Self {
+		self.stump_fraction = stump_fraction.clamp(0.0, 1.0);
+		self
+	}
+
+	/// Flattens the direction onto the ground, falling back to +X when it is vertical.
+	pub fn fall_direction(direction: Vec3) -> Vec3 {
+		Vec3::new(direction.x, 0.0, direction.z).try_normalize().unwrap_or(Vec3::X)
+	}
+
+	/// The stump, a short unit segment standing at the base.
+	pub fn stump_transform(&self) -> Transform {
+		Transform::from_translation(self.base).with_scale(Vec3::new(
+			self.width,
+			self.height * self.stump_fraction,
+			self.width,
+		))
+	}
+
+	/// The rest of the trunk, lying on the ground in the fall direction.
+	///
+	/// Unit segments run along +Y from their base, so the log is rotated onto the direction
+	/// and lifted by its radius so it rests on the ground beside the stump.
+	pub fn log_transform(&self, direction: Vec3) -> Transform {
+		let direction = Self::fall_direction(direction);
+		let radius = self.width / 2.0;
+		Transform::from_translation(self.base + direction * radius + Vec3::Y * radius)
+			.with_rotation(Quat::from_rotation_arc(Vec3::Y, direction))
+			.with_scale(Vec3::new(
+				self.width,
+				self.height * (1.0 - self.stump_fraction),
+				self.width,
+			))
+	}
+}
+
+/// A felled trunk, spawned as a single segment along the dispatch transform.
+#[derive(Component, Clone)]
+pub struct FallenLog<M: MeshBuilder + IdentifiedMesh + Clone, T: Material> {
+	mesh: MeshHandle<M>,
+	material: MeshMaterial3d<T>,
+}
+
+impl<M: MeshBuilder + IdentifiedMesh + Clone, T: Material> FallenLog<M, T> {
+	pub fn new(mesh: MeshHandle<M>, material: MeshMaterial3d<T>) -> Self {
+		Self { mesh, material }
+	}
+}
+
+impl<M: MeshBuilder + IdentifiedMesh + Clone, T: Material> RenderItem for FallenLog<M, T>
+where
+	(CascadeChunk, MeshDispatch<MeshHandle<M>>, Transform, MeshMaterial3d<T>): Bundle,
+{
+	fn spawn_render_items(
+		&self,
+		commands: &mut Commands,
+		cascade_chunk: &CascadeChunk,
+		transform: Transform,
+	) -> Vec<Entity> {
+		vec![commands
+			.spawn((
+				*cascade_chunk,
+				MeshDispatch::new(self.mesh.clone()),
+				transform,
+				self.material.clone(),
+			))
+			.id()]
+	}
+}
+
+/// Replaces each requested tree with a [FallenLog] and a stump.
+///
+/// Despawning the tree's root despawns every part it owns, down to the fetched meshes.
+/// The log needs [render_items](render_item::render_items) for its [FallenLog] type, and both
+/// need [fetch_meshes](render_item::mesh::fetch_meshes) for the trunk mesh.
+pub fn chop_trees<M: MeshBuilder + IdentifiedMesh + Clone, T: Material>(
+	mut commands: Commands,
+	mut requests: MessageReader<ChopTree>,
+	trees: Query<&TreeTrunk<M, T>>,
+	mut chopped: MessageWriter<TreeChopped>,
+) where
+	TreeTrunk<M, T>: Component,
+	FallenLog<M, T>: Component,
+	(CascadeChunk, MeshDispatch<MeshHandle<M>>, Transform, MeshMaterial3d<T>): Bundle,
+{
+	let mut felled = HashSet::new();
+	for request in requests.read() {
+		if !felled.insert(request.tree) {
+			continue;
+		}
+		let Ok(trunk) = trees.get(request.tree) else {
+			log::warn!("{:?} is not a standing tree", request.tree);
+			continue;
+		};
+
+		let direction = TreeTrunk::<M, T>::fall_direction(request.direction);
+		commands.entity(request.tree).despawn();
+
+		let chunk = CascadeChunk::unit_center_chunk().with_res_2(3);
+		let log = commands
+			.spawn((
+				chunk,
+				DispatchRenderItem::new(FallenLog::new(trunk.mesh.clone(), trunk.material.clone())),
+				trunk.log_transform(direction),
+			))
+			.id();
+		let stump = commands
+			.spawn((
+				chunk,
+				MeshDispatch::new(trunk.mesh.clone()),
+				trunk.stump_transform(),
+				trunk.material.clone(),
+			))
+			.id();
+
+		log::info!("Chopped down the tree at {}", trunk.base);
+		chopped.write(TreeChopped { tree: request.tree, base: trunk.base, direction, log, stump });
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tree::meshes::trunk::segment::{SegmentConfig, SimpleTrunkSegment};
+	use render_item::PartOfRenderItem;
+
+	type Trunk = TreeTrunk<SimpleTrunkSegment, StandardMaterial>;
+
+	#[test]
+	fn test_chopping_replaces_the_tree() {
+		let mut app = App::new();
+		app.add_message::<ChopTree>()
+			.add_message::<TreeChopped>()
+			.add_systems(Update, chop_trees::<SimpleTrunkSegment, StandardMaterial>);
+
+		let trunk = Trunk::new(
+			Vec3::new(4.0, 1.0, -2.0),
+			2.0,
+			MeshHandle::new(SimpleTrunkSegment::new(SegmentConfig::default())),
+			MeshMaterial3d(Handle::default()),
+		)
+		.with_width(0.9);
+		let tree = app.world_mut().spawn(trunk).id();
+		let dispatch = app.world_mut().spawn(PartOfRenderItem(tree)).id();
+		let mesh = app.world_mut().spawn(PartOfRenderItem(dispatch)).id();
+		let neighbour = app.world_mut().spawn_empty().id();
+
+		let mut requests = app.world_mut().resource_mut::<Messages<ChopTree>>();
+		requests.write(ChopTree { tree, direction: Vec3::new(0.0, -1.0, 3.0) });
+		requests.write(ChopTree { tree, direction: Vec3::X });
+		requests.write(ChopTree { tree: neighbour, direction: Vec3::X });
+		app.update();
+
+		// The tree is gone down to its meshes, and only it was chopped
+		for entity in [tree, dispatch, mesh] {
+			assert!(app.world().get_entity(entity).is_err());
+		}
+		assert!(app.world().get_entity(neighbour).is_ok());
+		let chopped: Vec<_> =
+			app.world_mut().resource_mut::<Messages<TreeChopped>>().drain().collect();
+		let [chopped] = chopped.as_slice() else {
+			panic!("expected one chopped tree, got {}", chopped.len());
+		};
+		assert_eq!(chopped.base, Vec3::new(4.0, 1.0, -2.0));
+		assert!(chopped.direction.abs_diff_eq(Vec3::Z, 1e-6));
+
+		// The log lies along the fall direction and the stump stands at the base
+		let Some(log) = app.world().get::<Transform>(chopped.log) else {
+			panic!("expected the log to be spawned");
+		};
+		let tip = log.transform_point(Vec3::Y);
+		assert!((tip.y - log.translation.y).abs() < 1e-5);
+		assert!(tip.z > log.translation.z + 1.0);
+		let Some(stump) = app.world().get::<Transform>(chopped.stump) else {
+			panic!("expected the stump to be spawned");
+		};
+		assert_eq!(stump.translation, chopped.base);
+		assert!(stump.scale.y < 1.0);
+	}
+}
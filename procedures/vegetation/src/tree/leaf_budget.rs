@@ -0,0 +1,130 @@
+use crate::tree::skeleton::TreeSkeleton;
+use bevy::prelude::*;
+use render_item::RenderItemParts;
+use std::collections::HashSet;
+
+/// Marks the leaf dispatches of a tree, which [budget_tree_leaves] may drop.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct TreeLeaf;
+
+/// Caps the leaf entities shown across all trees.
+///
+/// When the trees hold more leaves than the budget, the furthest from the camera drop theirs
+/// and keep their trunks and branches, taking them back as the camera comes closer.
+#[derive(Resource, Debug, Clone)]
+pub struct LeafBudget {
+	pub max_leaves: usize,
+	/// Leaves shown on the last frame
+	pub shown: usize,
+	/// Trees whose leaves were dropped on the last frame
+	pub bare_trees: usize,
+}
+
+impl Default for LeafBudget {
+	fn default() -> Self {
+		Self { max_leaves: 20_000, shown: 0, bare_trees: 0 }
+	}
+}
+
+impl LeafBudget {
+	pub fn new(max_leaves: usize) -> Self {
+		Self { max_leaves, ..default() }
+	}
+
+	/// The trees that keep their leaves, nearest the camera first until the budget runs out.
+	///
+	/// Trees are given as their entity, base and leaf count.
+	pub fn leafy_trees(
+		&self,
+		camera: Vec3,
+		trees: impl IntoIterator<Item = (Entity, Vec3, usize)>,
+	) -> HashSet<Entity> {
+		let mut trees: Vec<_> = trees.into_iter().collect();
+		trees.sort_by(|a, b| a.1.distance_squared(camera).total_cmp(&b.1.distance_squared(camera)));
+
+		let mut shown = 0;
+		trees
+			.into_iter()
+			.take_while(|(_, _, leaves)| {
+				shown += leaves;
+				shown <= self.max_leaves
+			})
+			.map(|(tree, _, _)| tree)
+			.collect()
+	}
+}
+
+/// Hides the leaves of the trees past the [LeafBudget] and shows them again once they fit.
+///
+/// Trees are found by their [TreeSkeleton] roots, and their leaves through the parts the
+/// roots own: the [TreeLeaf] dispatches, and the meshes those fetched.
+pub fn budget_tree_leaves(
+	mut budget: ResMut<LeafBudget>,
+	camera_query: Query<&Transform, With<Camera3d>>,
+	trees: Query<(Entity, &TreeSkeleton, &RenderItemParts)>,
+	leaves: Query<&RenderItemParts, With<TreeLeaf>>,
+	mut visibility: Query<&mut Visibility>,
+) {
+	let Ok(camera) = camera_query.single() else {
+		return;
+	};
+
+	let leaves_of = |parts: &RenderItemParts| {
+		parts
+			.parts()
+			.iter()
+			.filter_map(|part| leaves.get(*part).ok())
+			.collect::<Vec<_>>()
+	};
+	let leafy = budget.leafy_trees(
+		camera.translation,
+		trees
+			.iter()
+			.map(|(tree, skeleton, parts)| (tree, skeleton.base, leaves_of(parts).len())),
+	);
+
+	let (mut shown, mut bare_trees) = (0, 0);
+	for (tree, _, parts) in &trees {
+		let tree_leaves = leaves_of(parts);
+		let shows = leafy.contains(&tree);
+		if shows {
+			shown += tree_leaves.len();
+		} else if !tree_leaves.is_empty() {
+			bare_trees += 1;
+		}
+
+		let leaf_visibility = if shows { Visibility::Inherited } else { Visibility::Hidden };
+		for mesh in tree_leaves.iter().flat_map(|leaf| leaf.parts()) {
+			if let Ok(mut visibility) = visibility.get_mut(*mesh) {
+				visibility.set_if_neq(leaf_visibility);
+			}
+		}
+	}
+	budget.shown = shown;
+	budget.bare_trees = bare_trees;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_furthest_trees_drop_their_leaves() {
+		let mut world = World::new();
+		let [near, middle, far] = [(); 3].map(|_| world.spawn_empty().id());
+		let trees = [
+			(far, Vec3::new(0.0, 0.0, 90.0), 100),
+			(near, Vec3::new(5.0, 0.0, 0.0), 100),
+			(middle, Vec3::new(0.0, 0.0, -30.0), 100),
+		];
+
+		let leafy = LeafBudget::new(250).leafy_trees(Vec3::ZERO, trees);
+		assert_eq!(leafy, HashSet::from([near, middle]));
+
+		// Closer to the far tree, it takes the middle one's place
+		let leafy = LeafBudget::new(250).leafy_trees(Vec3::new(0.0, 0.0, 60.0), trees);
+		assert_eq!(leafy, HashSet::from([near, far]));
+
+		assert_eq!(LeafBudget::new(99).leafy_trees(Vec3::ZERO, trees), HashSet::new());
+	}
+}
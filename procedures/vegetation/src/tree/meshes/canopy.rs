@@ -1,5 +1,6 @@
 pub mod ball;
 pub mod branch;
+pub mod proxy;
 
 use bevy::prelude::*;
 use sdf::{EllipsoidSdf, Sdf};
@@ -1,15 +1,23 @@
 pub mod scratchpad;
 
 use crate::tree::builder::MeshFromTreeNum;
-use bevy::prelude::*;
+use bevy::{math::bounding::Aabb3d, prelude::*};
 use chunk::cascade::CascadeChunk;
+use comproc::complex::chain::ball_stick::render::TaperedMesh;
 use noise::{NoiseFn, Perlin};
 use render_item::{
-	mesh::{IdentifiedMesh, MeshBuilder, MeshId},
+	mesh::{IdentifiedMesh, MeshBuilder, MeshId, MeshResolution, MeshSpace},
 	NormalizeChunk,
 };
 use scratchpad::{generate_unit_disk, generate_unit_triangle};
-use std::f32::consts::PI;
+use std::f32::consts::{PI, SQRT_2};
+
+/// How far lobes push the canopy out from (or pull it into) the unit radius
+const LOBE_DEPTH: f32 = 0.3;
+/// Triangles used by the fixed planes, besides the disk segments
+const FIXED_TRIANGLES: u32 = 10;
+/// Number of distinct canopies per unit of tree_num, so meshes can be shared between trees
+const TREE_NUM_VARIANTS: f32 = 8.0;
 
 /// Configuration for a noisy sphere/ball
 /// All balls work in unit space (0-1) and are transformed later
@@ -28,11 +36,66 @@ pub struct NoisyBallConfig {
 	/// Number of noise octaves for fractal detail
 	/// More octaves = more detailed but potentially slower
 	pub noise_octaves: u32,
+	/// Vertical scale of the canopy
+	/// Values below 1 flatten the canopy, values above 1 stretch it upward
+	pub squash: f32,
+	/// Number of lobes in the canopy silhouette
+	/// 0 or 1 gives a single rounded mass, more lobes give a clumpier outline
+	pub lobes: u32,
+	/// How much the plane edges are perturbed, giving a ragged silhouette
+	pub raggedness: f32,
+	/// Maximum number of triangles in the mesh
+	pub poly_budget: u32,
 }
 
 impl Default for NoisyBallConfig {
 	fn default() -> Self {
-		Self { seed: 0, radius: 0.5, noise_amplitude: 0.1, noise_frequency: 3.0, noise_octaves: 3 }
+		Self {
+			seed: 0,
+			radius: 0.5,
+			noise_amplitude: 0.1,
+			noise_frequency: 3.0,
+			noise_octaves: 3,
+			squash: 1.0,
+			lobes: 1,
+			raggedness: 0.15,
+			poly_budget: 256,
+		}
+	}
+}
+
+impl NoisyBallConfig {
+	pub fn with_seed(mut self, seed: u32) -> Self {
+		self.seed = seed;
+		self
+	}
+
+	pub fn with_squash(mut self, squash: f32) -> Self {
+		self.squash = squash;
+		self
+	}
+
+	pub fn with_lobes(mut self, lobes: u32) -> Self {
+		self.lobes = lobes;
+		self
+	}
+
+	pub fn with_raggedness(mut self, raggedness: f32) -> Self {
+		self.raggedness = raggedness;
+		self
+	}
+
+	pub fn with_poly_budget(mut self, poly_budget: u32) -> Self {
+		self.poly_budget = poly_budget;
+		self
+	}
+
+	/// Disk segments that fit the poly budget, at most the segments of the resolution.
+	///
+	/// Each of the three disks uses two triangles per segment (front and back faces).
+	pub fn disk_segments(&self, res: MeshResolution) -> u32 {
+		let budget_segments = self.poly_budget.saturating_sub(FIXED_TRIANGLES) / 6;
+		res.segments().max_element().min(budget_segments).max(3)
 	}
 }
 
@@ -48,6 +111,33 @@ impl NoisyBall {
 		let noise = Perlin::new(config.seed);
 		Self { config, noise }
 	}
+
+	/// Radial scale of the lobes in a given direction.
+	fn lobe_scale(&self, direction: Vec3) -> f32 {
+		if self.config.lobes <= 1 {
+			return 1.0;
+		}
+
+		// Low frequency noise over directions, with more lobes for higher frequencies
+		let frequency = self.config.lobes as f64 * 0.5;
+		let noise = self.noise.get([
+			direction.x as f64 * frequency,
+			direction.y as f64 * frequency + 200.0,
+			direction.z as f64 * frequency,
+		]) as f32;
+		1.0 + noise * LOBE_DEPTH
+	}
+
+	/// Applies the lobes and squash to a vertex of the rotated planes.
+	fn shape_vertex(&self, vertex: Vec3) -> Vec3 {
+		let scaled = vertex * self.lobe_scale(vertex.normalize_or_zero());
+		scaled * Vec3::new(1.0, self.config.squash, 1.0)
+	}
+
+	/// Applies the squash to a normal of the rotated planes.
+	fn shape_normal(&self, normal: Vec3) -> Vec3 {
+		(normal / Vec3::new(1.0, self.config.squash.max(f32::EPSILON), 1.0)).normalize_or_zero()
+	}
 }
 
 /*impl Sdf for NoisyBall {
@@ -80,7 +170,7 @@ impl NoisyBall {
 impl NormalizeChunk for NoisyBall {
 	fn normalize_chunk(&self, cascade_chunk: &CascadeChunk) -> CascadeChunk {
 		CascadeChunk::unit_3d_center_chunk()
-			.with_res_2(cascade_chunk.res_2)
+			.with_axis_res_2(cascade_chunk.res_2)
 			.with_mu(self.config.noise_amplitude + 0.001)
 	}
 }
@@ -90,6 +180,10 @@ impl IdentifiedMesh for NoisyBall {
 		let debug_string = format!("{:?}", self);
 		MeshId::new(debug_string)
 	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		MeshSpace::Unit
+	}
 }
 
 #[derive(Clone, Copy)]
@@ -100,13 +194,18 @@ enum ShapeType {
 }
 
 impl MeshBuilder for NoisyBall {
+	/// The planes keep a fixed tessellation for chunk builds, within the poly budget.
 	fn build_mesh_impl(&self, _cascade_chunk: &CascadeChunk) -> Option<Mesh> {
+		Some(self.build_with_resolution(MeshResolution::default()))
+	}
+
+	fn build_with_resolution(&self, res: MeshResolution) -> Mesh {
 		// Generate a mix of 8 plane meshes (discs, triangles, rectangles) intersecting at the origin
 		let num_planes = 8;
 		let size = 1.0; // Unit-sized shapes
 		let radius = 1.0; // For discs
-		let segments = 32; // For discs
-		let edge_noise_amplitude = 0.15; // How much to perturb edges
+		let segments = self.config.disk_segments(res); // For discs
+		let edge_noise_amplitude = self.config.raggedness; // How much to perturb edges
 		let edge_noise_frequency = 8.0; // Frequency of edge noise
 
 		// Use Fibonacci sphere algorithm for even distribution of directions
@@ -188,13 +287,13 @@ impl MeshBuilder for NoisyBall {
 				Quat::from_rotation_arc(Vec3::Z, direction)
 			};
 
-			// Apply rotation to vertices and normals
+			// Apply rotation, then the canopy shape, to vertices and normals
 			let vertex_offset = all_vertices.len() as u32;
 			for (vertex, normal) in plane_vertices.iter().zip(plane_normals.iter()) {
 				let v = Vec3::new(vertex[0], vertex[1], vertex[2]);
 				let n = Vec3::new(normal[0], normal[1], normal[2]);
-				let rotated_v = rotation * v;
-				let rotated_n = rotation * n;
+				let rotated_v = self.shape_vertex(rotation * v);
+				let rotated_n = self.shape_normal(rotation * n);
 				all_vertices.push([rotated_v.x, rotated_v.y, rotated_v.z]);
 				all_normals.push([rotated_n.x, rotated_n.y, rotated_n.z]);
 			}
@@ -216,12 +315,87 @@ impl MeshBuilder for NoisyBall {
 		mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, all_uvs);
 		mesh.insert_indices(bevy::mesh::Indices::U32(all_indices));
 
-		Some(mesh)
+		mesh
+	}
+
+	/// Unit planes with ragged edges along both in-plane axes, then lobed and squashed.
+	fn local_bounds(&self) -> Aabb3d {
+		let lobe_depth = if self.config.lobes > 1 { LOBE_DEPTH } else { 0.0 };
+		let radius = (1.0 + self.config.raggedness.abs() * SQRT_2) * (1.0 + lobe_depth);
+		Aabb3d::new(Vec3::ZERO, Vec3::new(radius, radius * self.config.squash.abs(), radius))
+	}
+}
+
+/// Balls are round, so they are used as is when spawned as sticks.
+impl TaperedMesh for NoisyBall {
+	fn with_taper(&self, _taper: f32) -> Self {
+		self.clone()
 	}
 }
 
 impl MeshFromTreeNum for NoisyBall {
-	fn from_tree_num(_tree_num: f32) -> Self {
-		Self::new(NoisyBallConfig::default())
+	/// Maps tree_num to one of a few canopy variants per unit.
+	///
+	/// Variants range from flat, smooth crowns to tall, lobed, ragged ones.
+	fn from_tree_num(tree_num: f32) -> Self {
+		let variant = (tree_num * TREE_NUM_VARIANTS).floor();
+
+		// Hashes the variant to the unit interval, decorrelated per parameter
+		let unit =
+			|salt: f32| ((variant * 12.9898 + salt * 78.233).sin() * 43758.547).fract().abs();
+
+		let config = NoisyBallConfig::default()
+			.with_seed(variant as i32 as u32)
+			.with_squash(0.6 + 0.5 * unit(1.0))
+			.with_lobes(1 + (unit(2.0) * 4.0) as u32)
+			.with_raggedness(0.05 + 0.25 * unit(3.0));
+
+		Self::new(config)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::math::bounding::BoundingVolume;
+
+	#[test]
+	fn test_local_bounds_contain_mesh() {
+		let balls = [NoisyBall::new(NoisyBallConfig::default()), NoisyBall::from_tree_num(0.37)];
+
+		for ball in balls {
+			let bounds = ball.local_bounds();
+			for res_2 in [2, 5] {
+				let mesh = ball.build_with_resolution(MeshResolution::new(res_2));
+				let Some(positions) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+					panic!("mesh has no positions");
+				};
+				let positions = positions.as_float3().unwrap_or(&[]);
+				assert!(!positions.is_empty());
+				for position in positions {
+					let point = Aabb3d::new(Vec3::from(*position), Vec3::ZERO);
+					assert!(bounds.contains(&point), "{position:?} is outside {bounds:?}");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_poly_budget() {
+		let ball = NoisyBall::new(NoisyBallConfig::default().with_poly_budget(64));
+		let mesh = ball.build_with_resolution(MeshResolution::new(7));
+		let triangles = mesh.indices().map(|indices| indices.len() / 3).unwrap_or(0);
+		assert!(triangles <= 64, "{triangles} triangles exceed the budget");
+		assert!(triangles > 0);
+	}
+
+	#[test]
+	fn test_from_tree_num_variants() {
+		// nearby tree nums share a variant and therefore a mesh
+		assert_eq!(NoisyBall::from_tree_num(0.5).id(), NoisyBall::from_tree_num(0.51).id());
+
+		let ids: std::collections::HashSet<_> =
+			(0..16).map(|i| NoisyBall::from_tree_num(i as f32 / 8.0).id()).collect();
+		assert_eq!(ids.len(), 16);
 	}
 }
@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+use chunk::cascade::CascadeChunk;
+use render_item::{
+	mesh::{IdentifiedMesh, MeshId, MeshIdBuilder, MeshSpace},
+	NormalizeChunk,
+};
+use sdf::Sdf;
+
+/// A low-poly blob standing in for a tree's canopy in the shadow pass.
+///
+/// A unit sphere resting on the origin, scaled to the bounds of the canopy when spawned.
+/// It is meshed at its own coarse resolution whatever chunk it is spawned for, so every proxy
+/// shares one mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CanopyProxy {
+	res_2: u8,
+}
+
+impl Default for CanopyProxy {
+	fn default() -> Self {
+		Self { res_2: 2 }
+	}
+}
+
+impl CanopyProxy {
+	pub fn with_res_2(mut self, res_2: u8) -> Self {
+		self.res_2 = res_2;
+		self
+	}
+}
+
+impl Sdf for CanopyProxy {
+	fn distance(&self, p: Vec3) -> f32 {
+		p.distance(Vec3::new(0.0, 0.5, 0.0)) - 0.5
+	}
+}
+
+impl NormalizeChunk for CanopyProxy {
+	fn normalize_chunk(&self, _cascade_chunk: &CascadeChunk) -> CascadeChunk {
+		CascadeChunk::unit_center_chunk().with_res_2(self.res_2).with_mu(0.01)
+	}
+}
+
+impl IdentifiedMesh for CanopyProxy {
+	fn id(&self) -> MeshId {
+		MeshIdBuilder::new("canopy_proxy").with(&self.res_2).build()
+	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		MeshSpace::Unit
+	}
+}
@@ -1,9 +1,10 @@
 use crate::tree::builder::MeshFromTreeNum;
 use bevy::prelude::*;
 use chunk::cascade::CascadeChunk;
+use comproc::complex::chain::ball_stick::render::TaperedMesh;
 use noise::{NoiseFn, Perlin};
 use render_item::{
-	mesh::{IdentifiedMesh, MeshId},
+	mesh::{IdentifiedMesh, MeshId, MeshSpace},
 	NormalizeChunk,
 };
 use sdf::Sdf;
@@ -11,6 +12,7 @@ use sdf::Sdf;
 /// Base configuration for a trunk segment
 /// All segments work in unit space (0-1) and are transformed later
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Debug, Default))]
 pub struct SegmentConfig {
 	/// Seed for noise generation
 	pub seed: u32,
@@ -100,7 +102,7 @@ impl Sdf for SimpleTrunkSegment {
 impl NormalizeChunk for SimpleTrunkSegment {
 	fn normalize_chunk(&self, cascade_chunk: &CascadeChunk) -> CascadeChunk {
 		CascadeChunk::unit_center_chunk()
-			.with_res_2(cascade_chunk.res_2)
+			.with_axis_res_2(cascade_chunk.res_2)
 			.with_mu(self.config.noise_amplitude + 0.001)
 	}
 }
@@ -110,6 +112,18 @@ impl IdentifiedMesh for SimpleTrunkSegment {
 		let debug_string = format!("{:?}", self);
 		MeshId::new(debug_string)
 	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		MeshSpace::Unit
+	}
+}
+
+impl TaperedMesh for SimpleTrunkSegment {
+	fn with_taper(&self, taper: f32) -> Self {
+		let config =
+			SegmentConfig { top_radius: self.config.base_radius * taper, ..self.config.clone() };
+		Self { config, noise: self.noise }
+	}
 }
 
 impl MeshFromTreeNum for SimpleTrunkSegment {
@@ -0,0 +1,196 @@
+use crate::tree::spine::TrunkSpine;
+use bevy::prelude::*;
+use comproc::complex::chain::ball_stick::builder::{BallStick, BallStickBuilder};
+use noise::{NoiseFn, Seedable};
+use std::fmt::Debug;
+
+/// A whorl of branches radiating from a trunk at noisy heights.
+///
+/// The segment places the branches and grows each one from its branch builder,
+/// whose anchor and rays are set per branch.
+#[derive(Debug, Clone)]
+pub struct RadialBranchesSegment<
+	N: NoiseFn<f64, 4> + Seedable + Debug + Clone,
+	M: NoiseFn<f64, 3> + Seedable + Debug + Clone,
+> {
+	/// The base of the trunk
+	pub anchor: Vec3,
+	/// Branches attach at most this far above the anchor
+	pub height: f32,
+	pub branch_count: usize,
+	/// Maximum offset of a branch from its evenly spaced angle, as a fraction of the spacing
+	pub angular_jitter: f32,
+	/// The spine of a bent trunk the branches follow, straight up from the anchor when unset
+	pub spine: Option<TrunkSpine>,
+	pub branch_builder: BallStickBuilder<N, M>,
+}
+
+impl<
+		N: NoiseFn<f64, 4> + Seedable + Debug + Clone,
+		M: NoiseFn<f64, 3> + Seedable + Debug + Clone,
+	> RadialBranchesSegment<N, M>
+{
+	pub fn new(branch_builder: BallStickBuilder<N, M>) -> Self {
+		Self {
+			anchor: Vec3::ZERO,
+			height: 1.0,
+			branch_count: 4,
+			angular_jitter: 0.0,
+			spine: None,
+			branch_builder,
+		}
+	}
+
+	pub fn with_anchor(mut self, anchor: Vec3) -> Self {
+		self.anchor = anchor;
+		self
+	}
+
+	pub fn with_height(mut self, height: f32) -> Self {
+		self.height = height;
+		self
+	}
+
+	pub fn with_branch_count(mut self, branch_count: usize) -> Self {
+		self.branch_count = branch_count;
+		self
+	}
+
+	pub fn with_angular_jitter(mut self, angular_jitter: f32) -> Self {
+		self.angular_jitter = angular_jitter;
+		self
+	}
+
+	pub fn with_spine(mut self, spine: TrunkSpine) -> Self {
+		self.spine = Some(spine);
+		self
+	}
+
+	/// Where a branch attaching at the height above the anchor meets the trunk.
+	pub fn attachment_point(&self, height: f32) -> Vec3 {
+		let offset = match &self.spine {
+			Some(spine) => spine.point_at_height(height),
+			None => Vec3::new(0.0, height, 0.0),
+		};
+		self.anchor + offset
+	}
+
+	fn noise_height(&self, offset: f32) -> f32 {
+		self.branch_builder.unit_freqo3(self.anchor + Vec3::new(0.0, offset, 0.0)) as f32
+			* self.height
+	}
+
+	/// The heights above the anchor at which the branches attach, in branch order.
+	///
+	/// Each branch attaches at the height of the previous one, which then steps down
+	/// by a noisy amount unless that would go below the next noise height.
+	pub fn attachment_heights(&self) -> Vec<f32> {
+		let mut offset = self.noise_height(0.0);
+		(0..self.branch_count)
+			.map(|_| {
+				let attachment = offset;
+				let height = self.noise_height(offset);
+				offset = height.max(offset - height);
+				attachment
+			})
+			.collect()
+	}
+
+	/// The angle of a branch around the trunk, in radians.
+	pub fn branch_angle(&self, index: usize) -> f32 {
+		let jitter = self
+			.branch_builder
+			.unit_freqo3(self.anchor + Vec3::new(index as f32 * 31.7, 0.0, index as f32 * -17.3))
+			as f32 * 2.0
+			- 1.0;
+		let spacing = std::f32::consts::TAU / self.branch_count.max(1) as f32;
+		(index as f32 + jitter * self.angular_jitter) * spacing
+	}
+
+	/// The initial ray of a branch, rising out from the trunk.
+	pub fn branch_ray(&self, index: usize) -> Vec3 {
+		let angle = self.branch_angle(index);
+		Vec3::new(angle.cos(), angle.sin() + angle.cos(), angle.sin()).normalize()
+	}
+
+	/// Grows the branches.
+	pub fn branches(&self) -> Vec<BallStick>
+	where
+		N: Sync,
+		M: Sync,
+	{
+		self.attachment_heights()
+			.into_iter()
+			.enumerate()
+			.map(|(index, height)| {
+				let initial_ray = self.branch_ray(index);
+				self.branch_builder
+					.clone()
+					.with_anchor(self.attachment_point(height))
+					.with_initial_ray(initial_ray)
+					.with_bias_ray(initial_ray + Vec3::new(0.0, 0.01, 0.0))
+					.build()
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tree::spine::TrunkShape;
+	use comproc::noise::config::NoiseConfig;
+	use noise::Perlin;
+
+	fn segment() -> RadialBranchesSegment<Perlin, Perlin> {
+		let branch_builder = BallStickBuilder::common_tree_builder()
+			.with_min_segment_length(0.5)
+			.with_max_segment_length(1.0)
+			.with_noise_config_3d(NoiseConfig::new(Perlin::new(3)))
+			.with_noise_config_4d(NoiseConfig::new(Perlin::new(4)));
+		RadialBranchesSegment::new(branch_builder)
+			.with_anchor(Vec3::new(4.0, 1.0, -2.0))
+			.with_height(3.0)
+			.with_branch_count(6)
+	}
+
+	#[test]
+	fn test_branches_attach_along_the_trunk() {
+		let segment = segment();
+		let heights = segment.attachment_heights();
+		assert_eq!(heights.len(), 6);
+		assert!(heights.iter().all(|height| (0.0..=3.0).contains(height)));
+
+		let branches = segment.branches();
+		assert_eq!(branches.len(), 6);
+		for (branch, height) in branches.iter().zip(&heights) {
+			let root = segment.anchor + Vec3::new(0.0, *height, 0.0);
+			assert!(branch.nodes().any(|node| node.position == root));
+		}
+	}
+
+	#[test]
+	fn test_branches_follow_a_bent_trunk() {
+		let spine = TrunkShape::wind_swept(Vec2::X, 0.4).spine(3.0, 0.6);
+		let segment = segment().with_spine(spine.clone());
+		let branches = segment.branches();
+		for (branch, height) in branches.iter().zip(segment.attachment_heights()) {
+			let root = segment.anchor + spine.point_at_height(height);
+			assert!(branch.nodes().any(|node| node.position == root));
+		}
+		let top = segment.attachment_point(3.0) - segment.anchor;
+		assert!(top.x > 0.5 && top.y == 3.0, "{top}");
+	}
+
+	#[test]
+	fn test_angular_jitter_stays_within_spacing() {
+		let spacing = std::f32::consts::TAU / 6.0;
+		let even = segment();
+		let jittered = segment().with_angular_jitter(0.4);
+		for index in 0..6 {
+			assert!((even.branch_angle(index) - index as f32 * spacing).abs() < 1e-5);
+			let offset = jittered.branch_angle(index) - even.branch_angle(index);
+			assert!(offset.abs() <= 0.4 * spacing + 1e-5);
+		}
+	}
+}
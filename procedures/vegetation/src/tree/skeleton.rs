@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use comproc::complex::chain::ball_stick::{builder::BallStick, gizmos::draw_ball_stick};
+
+/// The branch graphs a tree was grown from, kept on its root entity for inspection.
+#[derive(Component, Debug, Clone)]
+pub struct TreeSkeleton {
+	pub base: Vec3,
+	pub branches: Vec<BallStick>,
+}
+
+impl TreeSkeleton {
+	pub fn new(base: Vec3, branches: Vec<BallStick>) -> Self {
+		Self { base, branches }
+	}
+}
+
+/// Draws the skeleton of the tree the camera looks at, within reach.
+#[derive(Resource, Debug, Clone)]
+pub struct SkeletonGizmos {
+	pub enabled: bool,
+	pub reach: f32,
+	pub node_color: Color,
+	pub segment_color: Color,
+	/// The tree drawn on the last frame
+	pub selected: Option<Entity>,
+}
+
+impl Default for SkeletonGizmos {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			reach: 32.0,
+			node_color: Color::srgb(1.0, 0.8, 0.2),
+			segment_color: Color::srgb(0.2, 1.0, 0.4),
+			selected: None,
+		}
+	}
+}
+
+/// The tree within reach of the camera whose base is nearest the center of its view.
+pub fn select_tree<'a>(
+	camera: &Transform,
+	reach: f32,
+	trees: impl IntoIterator<Item = (Entity, &'a TreeSkeleton)>,
+) -> Option<Entity> {
+	let forward = camera.forward();
+	trees
+		.into_iter()
+		.filter_map(|(entity, skeleton)| {
+			let offset = skeleton.base - camera.translation;
+			let distance = offset.length();
+			(distance <= reach).then(|| (entity, offset.dot(*forward) / distance.max(f32::EPSILON)))
+		})
+		.max_by(|a, b| a.1.total_cmp(&b.1))
+		.map(|(entity, _)| entity)
+}
+
+/// Selects the tree the camera looks at and draws its branch graphs while
+/// [SkeletonGizmos] is enabled.
+pub fn draw_tree_skeleton(
+	mut gizmos: Gizmos,
+	mut skeleton_gizmos: ResMut<SkeletonGizmos>,
+	camera_query: Query<&Transform, With<Camera3d>>,
+	trees: Query<(Entity, &TreeSkeleton)>,
+) {
+	if !skeleton_gizmos.enabled {
+		return;
+	}
+	let Ok(camera) = camera_query.single() else {
+		return;
+	};
+
+	skeleton_gizmos.selected = select_tree(camera, skeleton_gizmos.reach, trees.iter());
+	let Some(skeleton) = skeleton_gizmos.selected.and_then(|tree| trees.get(tree).ok()) else {
+		return;
+	};
+	for branch in &skeleton.1.branches {
+		draw_ball_stick(
+			&mut gizmos,
+			branch,
+			skeleton_gizmos.node_color,
+			skeleton_gizmos.segment_color,
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_select_tree_in_view() {
+		let camera = Transform::default().looking_to(Vec3::X, Vec3::Y);
+		let ahead = TreeSkeleton::new(Vec3::new(10.0, 0.0, 1.0), Vec::new());
+		let behind = TreeSkeleton::new(Vec3::new(-2.0, 0.0, 0.0), Vec::new());
+		let beyond = TreeSkeleton::new(Vec3::new(50.0, 0.0, 0.0), Vec::new());
+		let mut world = World::new();
+		let [a, b, c] = [(); 3].map(|_| world.spawn_empty().id());
+		let trees = [(a, &ahead), (b, &behind), (c, &beyond)];
+
+		assert_eq!(select_tree(&camera, 32.0, trees), Some(a));
+		assert_eq!(select_tree(&camera, 1.0, trees), None);
+	}
+}
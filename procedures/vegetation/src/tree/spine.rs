@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+
+/// The curve a trunk follows from its base to its top.
+///
+/// Control points are horizontal offsets from the base, evenly spaced in height, and the spine
+/// passes through each of them. Heights along the spine are kept, so a branch attached at some
+/// height still attaches at that height, moved over to where the trunk is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrunkSpine {
+	/// Height of the top of the trunk above its base
+	pub height: f32,
+	/// Horizontal offsets of the control points from the base, from the base to the top
+	pub offsets: Vec<Vec2>,
+}
+
+impl TrunkSpine {
+	/// A trunk standing straight up.
+	pub fn straight(height: f32) -> Self {
+		Self { height, offsets: vec![Vec2::ZERO, Vec2::ZERO] }
+	}
+
+	pub fn is_straight(&self) -> bool {
+		self.offsets.iter().all(|offset| *offset == Vec2::ZERO)
+	}
+
+	/// The horizontal offset of the spine at `t` of the way up, through the control points.
+	pub fn offset(&self, t: f32) -> Vec2 {
+		let Some(last) = self.offsets.len().checked_sub(1).filter(|last| *last > 0) else {
+			return self.offsets.first().copied().unwrap_or_default();
+		};
+		let scaled = t.clamp(0.0, 1.0) * last as f32;
+		let i = (scaled.floor() as usize).min(last - 1);
+		let local = scaled - i as f32;
+		let point = |i: isize| self.offsets[i.clamp(0, last as isize) as usize];
+		let i = i as isize;
+		catmull_rom(point(i - 1), point(i), point(i + 1), point(i + 2), local)
+	}
+
+	/// The point of the spine at the height above the base.
+	pub fn point_at_height(&self, height: f32) -> Vec3 {
+		let t = if self.height > 0.0 { height / self.height } else { 0.0 };
+		let offset = self.offset(t);
+		Vec3::new(offset.x, height, offset.y)
+	}
+
+	/// The points splitting the spine into as many segments of equal height, from the base to
+	/// the top.
+	pub fn points(&self, segments: usize) -> Vec<Vec3> {
+		let segments = segments.max(1);
+		(0..=segments)
+			.map(|i| self.point_at_height(self.height * i as f32 / segments as f32))
+			.collect()
+	}
+
+	/// The segments the trunk meshes are swept along: one for a straight trunk, and one
+	/// between each pair of control points for a bent one.
+	pub fn segments(&self) -> Vec<(Vec3, Vec3)> {
+		let count = if self.is_straight() { 1 } else { self.offsets.len().saturating_sub(1) };
+		let points = self.points(count);
+		points.windows(2).map(|pair| (pair[0], pair[1])).collect()
+	}
+}
+
+/// A uniform Catmull-Rom spline between `b` and `c`.
+fn catmull_rom(a: Vec2, b: Vec2, c: Vec2, d: Vec2, t: f32) -> Vec2 {
+	let (t2, t3) = (t * t, t * t * t);
+	0.5 * (2.0 * b
+		+ (c - a) * t
+		+ (2.0 * a - 5.0 * b + 4.0 * c - d) * t2
+		+ (3.0 * b - a - 3.0 * c + d) * t3)
+}
+
+/// How trunks bend and lean, for generating their [TrunkSpine] from a seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrunkShape {
+	/// How far the top leans over, as a fraction of the height
+	pub lean: f32,
+	/// Direction the trunks lean toward, such as downwind for wind-swept trees
+	pub lean_direction: Vec2,
+	/// How much the trunks wander sideways, as a fraction of the height
+	pub curvature: f32,
+	/// Control points of the spine, base and top included
+	pub control_points: usize,
+}
+
+impl Default for TrunkShape {
+	/// Straight vertical trunks.
+	fn default() -> Self {
+		Self { lean: 0.0, lean_direction: Vec2::X, curvature: 0.0, control_points: 4 }
+	}
+}
+
+impl TrunkShape {
+	/// Trunks bent over downwind, more so toward the top.
+	pub fn wind_swept(downwind: Vec2, lean: f32) -> Self {
+		Self::default().with_lean(lean, downwind).with_curvature(lean * 0.25)
+	}
+
+	pub fn with_lean(mut self, lean: f32, direction: Vec2) -> Self {
+		self.lean = lean;
+		self.lean_direction = direction.try_normalize().unwrap_or(Vec2::X);
+		self
+	}
+
+	pub fn with_curvature(mut self, curvature: f32) -> Self {
+		self.curvature = curvature;
+		self
+	}
+
+	pub fn with_control_points(mut self, control_points: usize) -> Self {
+		self.control_points = control_points.max(2);
+		self
+	}
+
+	/// The spine of a trunk of the height, varied by the seed on the unit interval.
+	///
+	/// The lean grows with the square of the height, as trunks bend most near the top, and
+	/// the curvature wanders sideways of it in a direction and phase picked by the seed.
+	pub fn spine(&self, height: f32, seed: f32) -> TrunkSpine {
+		if self.lean == 0.0 && self.curvature == 0.0 {
+			return TrunkSpine::straight(height);
+		}
+		let count = self.control_points.max(2);
+		let angle = seed * std::f32::consts::TAU;
+		let wander = Vec2::from_angle(angle);
+		let phase = seed * 7.3;
+		let offsets = (0..count)
+			.map(|i| {
+				let t = i as f32 / (count - 1) as f32;
+				let lean = self.lean_direction * self.lean * t * t;
+				let bend = wander * self.curvature * (std::f32::consts::PI * t).sin() * phase.cos();
+				(lean + bend) * height
+			})
+			.collect();
+		TrunkSpine { height, offsets }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_spines_lean_downwind_through_their_control_points() {
+		let straight = TrunkShape::default().spine(5.0, 0.3);
+		assert!(straight.is_straight());
+		assert_eq!(straight.segments(), vec![(Vec3::ZERO, Vec3::new(0.0, 5.0, 0.0))]);
+		assert_eq!(straight.point_at_height(2.0), Vec3::new(0.0, 2.0, 0.0));
+
+		let downwind = Vec2::new(1.0, 1.0).normalize();
+		let swept = TrunkShape::wind_swept(downwind, 0.3).spine(5.0, 0.3);
+		assert_eq!(swept.offsets.len(), 4);
+		assert_eq!(swept.offsets[0], Vec2::ZERO);
+		assert_eq!(swept.segments().len(), 3);
+
+		// The top leans downwind by about the lean, and the spine keeps its heights
+		let top = swept.point_at_height(5.0);
+		assert!(top.xz().dot(downwind) > 5.0 * 0.3 * 0.5, "{top}");
+		for (i, offset) in swept.offsets.iter().enumerate() {
+			let point = swept.point_at_height(5.0 * i as f32 / 3.0);
+			assert!(point.xz().distance(*offset) < 1e-4);
+		}
+		for (start, end) in swept.segments() {
+			assert!(end.y > start.y);
+		}
+
+		// The same seed grows the same spine
+		assert_eq!(swept, TrunkShape::wind_swept(downwind, 0.3).spine(5.0, 0.3));
+	}
+}
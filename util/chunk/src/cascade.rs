@@ -10,18 +10,23 @@ pub trait ResolutionMap: Debug + Clone + Copy {
 	fn ring_to_resolution(&self, ring: u8) -> usize {
 		2_usize.pow(self.ring_to_power_of_2(ring) as u32)
 	}
+
+	/// The per-axis powers of 2 for the ring; uniform unless overridden.
+	fn ring_to_axis_power_of_2(&self, ring: u8) -> UVec3 {
+		UVec3::splat(self.ring_to_power_of_2(ring) as u32)
+	}
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Ring {
-	pub size: f32,
-	pub res_2: u8,
+	pub size: Vec3,
+	pub res_2: UVec3,
 	// the point at the lower left bottom corner of the ring
 	pub lower_left_bottom: Vec3,
 }
 
 impl Ring {
-	pub fn new(size: f32, res_2: u8, lower_left_bottom: Vec3) -> Self {
+	pub fn new(size: Vec3, res_2: UVec3, lower_left_bottom: Vec3) -> Self {
 		Self { size, res_2, lower_left_bottom }
 	}
 
@@ -36,11 +41,7 @@ impl Ring {
 
 					chunks.push(CascadeChunk {
 						origin: self.lower_left_bottom
-							+ Vec3::new(
-								x as f32 * self.size,
-								y as f32 * self.size,
-								z as f32 * self.size,
-							),
+							+ Vec3::new(x as f32, y as f32, z as f32) * self.size,
 						size: self.size,
 						res_2: self.res_2,
 						omit: None,
@@ -82,8 +83,10 @@ fn lex_cmp(a: &Vec3, b: &Vec3) -> std::cmp::Ordering {
 #[derive(Component, Debug, Clone, Copy, PartialEq)]
 pub struct CascadeChunk {
 	pub origin: Vec3,
-	pub size: f32,
-	pub res_2: u8,
+	/// The size of the chunk along each axis.
+	pub size: Vec3,
+	/// The resolution along each axis as a power of 2.
+	pub res_2: UVec3,
 	pub omit: Option<Aabb3d>,
 }
 
@@ -92,29 +95,37 @@ impl Hash for CascadeChunk {
 		self.origin.x.to_bits().hash(state);
 		self.origin.y.to_bits().hash(state);
 		self.origin.z.to_bits().hash(state);
-		self.size.to_bits().hash(state);
+		self.size.x.to_bits().hash(state);
+		self.size.y.to_bits().hash(state);
+		self.size.z.to_bits().hash(state);
 		self.res_2.hash(state);
 	}
 }
 
 impl CascadeChunk {
-	pub fn resolution(&self) -> usize {
-		2_usize.pow(self.res_2 as u32)
+	/// Creates a cubic chunk with the same size and resolution along each axis.
+	pub fn cube(origin: Vec3, size: f32, res_2: u8) -> Self {
+		Self { origin, size: Vec3::splat(size), res_2: UVec3::splat(res_2 as u32), omit: None }
+	}
+
+	/// The number of voxels along each axis.
+	pub fn resolution(&self) -> UVec3 {
+		UVec3::new(1 << self.res_2.x, 1 << self.res_2.y, 1 << self.res_2.z)
 	}
 
 	/// Creates a chunk with a bottom left corner at the origin and a size of 1.0.
 	pub fn unit_chunk() -> Self {
-		Self { origin: Vec3::ZERO, size: 1.0, res_2: 0, omit: None }
+		Self::cube(Vec3::ZERO, 1.0, 0)
 	}
 
 	/// Creates a chunk with the center at the origin and diameters of 1.0.
 	pub fn unit_center_chunk() -> Self {
-		Self { origin: Vec3::new(-0.5, 0.0, -0.5), size: 1.0, res_2: 0, omit: None }
+		Self::cube(Vec3::new(-0.5, 0.0, -0.5), 1.0, 0)
 	}
 
 	/// Creates a chunk with the center at the origin and a size of 1.0.
 	pub fn unit_3d_center_chunk() -> Self {
-		Self { origin: Vec3::new(-0.5, -0.5, -0.5), size: 1.0, res_2: 0, omit: None }
+		Self::cube(Vec3::new(-0.5, -0.5, -0.5), 1.0, 0)
 	}
 
 	/// Updates a chunk with some Mu for the geometry that goes slightly beyond the unit.
@@ -124,7 +135,14 @@ impl CascadeChunk {
 		Self { origin, size, res_2: self.res_2, omit: self.omit }
 	}
 
+	/// Sets the same resolution along each axis.
 	pub fn with_res_2(mut self, res_2: u8) -> Self {
+		self.res_2 = UVec3::splat(res_2 as u32);
+		self
+	}
+
+	/// Sets the resolution along each axis.
+	pub fn with_axis_res_2(mut self, res_2: UVec3) -> Self {
 		self.res_2 = res_2;
 		self
 	}
@@ -153,12 +171,8 @@ impl PartialOrd for CascadeChunk {
 	fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
 		// compare the size first, then the resolution, then the origin, then the omission
 		Some(
-			self.size
-				.partial_cmp(&other.size)
-				.unwrap_or(std::cmp::Ordering::Equal)
-				.then_with(|| {
-					self.res_2.partial_cmp(&other.res_2).unwrap_or(std::cmp::Ordering::Equal)
-				})
+			lex_cmp(&self.size, &other.size)
+				.then_with(|| self.res_2.to_array().cmp(&other.res_2.to_array()))
 				.then_with(|| lex_cmp(&self.origin, &other.origin))
 				.then_with(|| aabb_cmp(&self.omit, &other.omit)),
 		)
@@ -173,10 +187,24 @@ impl Ord for CascadeChunk {
 	}
 }
 
+/// How the rings of the cascade follow the position they are centered on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OriginSnapping {
+	/// Every ring moves whenever the position crosses a min_size boundary,
+	/// keeping the rings concentric.
+	#[default]
+	MinSize,
+	/// Each ring only moves when the position crosses a boundary of its own size.
+	///
+	/// Where a ring's cells only partly overlap the ring inside it,
+	/// the rest of the cell is filled with chunks of the inner ring's size.
+	Ring,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Cascade<R: ResolutionMap> {
-	/// The minimum size of the chunk used in the interior of the cascade
-	pub min_size: f32,
+	/// The minimum size of the chunk used in the interior of the cascade, per axis
+	pub min_size: Vec3,
 	/// The number of rings in the cascade
 	pub number_of_rings: u8,
 	/// The resolution map for the cascade and grid.
@@ -185,6 +213,8 @@ pub struct Cascade<R: ResolutionMap> {
 	pub grid_radius: usize,
 	/// The base two power of the multiple of the size of the largest ring in the cascade.
 	pub grid_multiple_2: u8,
+	/// How the rings snap to the position.
+	pub snapping: OriginSnapping,
 }
 
 #[derive(Debug, Clone)]
@@ -211,15 +241,12 @@ impl CascadeOutput {
 }
 
 impl<R: ResolutionMap> Cascade<R> {
-	pub fn size_for_ring(&self, ring: u8) -> f32 {
+	pub fn size_for_ring(&self, ring: u8) -> Vec3 {
 		self.min_size * 3_u32.pow(ring as u32) as f32
 	}
 
 	pub fn position_to_origin(&self, position: Vec3) -> Vec3 {
-		let x = (position.x / self.min_size).floor() * self.min_size;
-		let y = (position.y / self.min_size).floor() * self.min_size;
-		let z = (position.z / self.min_size).floor() * self.min_size;
-		Vec3::new(x, y, z)
+		(position / self.min_size).floor() * self.min_size
 	}
 
 	pub fn center_chunk(&self, position: Vec3) -> CascadeChunk {
@@ -227,19 +254,97 @@ impl<R: ResolutionMap> Cascade<R> {
 		CascadeChunk {
 			origin,
 			size: self.min_size,
-			res_2: self.resolution_map.ring_to_power_of_2(0),
+			res_2: self.resolution_map.ring_to_axis_power_of_2(0),
 			omit: None,
 		}
 	}
 
 	/// The chunks in the cascade.
 	pub fn cascade_chunks(&self, position: Vec3) -> Result<Vec<CascadeChunk>, String> {
+		match self.snapping {
+			OriginSnapping::MinSize => self.concentric_chunks(position),
+			OriginSnapping::Ring => Ok(self.ring_snapped_chunks(position)),
+		}
+	}
+
+	/// The cell of the ring's size that holds the position, in units of the ring's size.
+	///
+	/// Derived from the cells of the inner rings, so every ring's cells nest exactly.
+	fn ring_cell(&self, position: Vec3, ring: u8) -> IVec3 {
+		let mut cell = (position / self.min_size).floor().as_ivec3();
+		for _ in 0..ring {
+			cell = cell.div_euclid(IVec3::splat(3));
+		}
+		cell
+	}
+
+	/// The block of 3x3x3 cells covered by the ring, in units of min_size.
+	///
+	/// Ring `-1` is the center chunk.
+	fn ring_block(&self, position: Vec3, ring: i32) -> (IVec3, IVec3) {
+		if ring < 0 {
+			let cell = self.ring_cell(position, 0);
+			return (cell, cell + IVec3::ONE);
+		}
+		let multiple = 3_i32.pow(ring as u32);
+		let cell = self.ring_cell(position, ring as u8);
+		((cell - IVec3::ONE) * multiple, (cell + IVec3::splat(2)) * multiple)
+	}
+
+	/// The chunks of the cascade when each ring snaps to its own size.
+	fn ring_snapped_chunks(&self, position: Vec3) -> Vec<CascadeChunk> {
+		let chunk = |lower: IVec3, ring: u8| CascadeChunk {
+			origin: lower.as_vec3() * self.min_size,
+			size: self.size_for_ring(ring),
+			res_2: self.resolution_map.ring_to_axis_power_of_2(ring),
+			omit: None,
+		};
+		let inside = |lower: IVec3, upper: IVec3, (min, max): (IVec3, IVec3)| {
+			lower.cmpge(min).all() && upper.cmple(max).all()
+		};
+		let disjoint = |lower: IVec3, upper: IVec3, (min, max): (IVec3, IVec3)| {
+			lower.cmpge(max).any() || upper.cmple(min).any()
+		};
+		let cells = |lower: IVec3, multiple: i32| {
+			(0..27).map(move |i| lower + IVec3::new(i % 3, (i / 3) % 3, i / 9) * multiple)
+		};
+
+		let (center, _) = self.ring_block(position, -1);
+		let mut chunks = vec![chunk(center, 0)];
+		for ring in 0..self.number_of_rings {
+			let inner = self.ring_block(position, ring as i32 - 1);
+			let (lower, _) = self.ring_block(position, ring as i32);
+			let multiple = 3_i32.pow(ring as u32);
+
+			for cell in cells(lower, multiple) {
+				let upper = cell + IVec3::splat(multiple);
+				if inside(cell, upper, inner) {
+					continue;
+				}
+				if disjoint(cell, upper, inner) {
+					chunks.push(chunk(cell, ring));
+					continue;
+				}
+
+				// Partly covered by the inner ring, which is aligned to the inner ring's cells
+				let sub_multiple = multiple / 3;
+				for sub_cell in cells(cell, sub_multiple) {
+					if !inside(sub_cell, sub_cell + IVec3::splat(sub_multiple), inner) {
+						chunks.push(chunk(sub_cell, ring - 1));
+					}
+				}
+			}
+		}
+		chunks
+	}
+
+	/// The chunks in the cascade when the rings stay concentric around the center chunk.
+	fn concentric_chunks(&self, position: Vec3) -> Result<Vec<CascadeChunk>, String> {
 		// copmute the center chunk
 		let center_chunk = self.center_chunk(position);
 
 		// move to the lower bottom left for the 0th ring
-		let mut lower_left_bottom =
-			center_chunk.origin - Vec3::new(self.min_size, self.min_size, self.min_size);
+		let mut lower_left_bottom = center_chunk.origin - self.min_size;
 
 		// add the center chunk to the chunks vector
 		let mut chunks = Vec::new();
@@ -253,16 +358,19 @@ impl<R: ResolutionMap> Cascade<R> {
 			let size = self.size_for_ring(ring);
 
 			// create the ring chunks
-			let ring_chunks =
-				Ring::new(size, self.resolution_map.ring_to_power_of_2(ring), lower_left_bottom)
-					.ring_chunks()?;
+			let ring_chunks = Ring::new(
+				size,
+				self.resolution_map.ring_to_axis_power_of_2(ring),
+				lower_left_bottom,
+			)
+			.ring_chunks()?;
 
 			// add the ring chunks to the chunks vector
 			chunks.extend(ring_chunks.chunks);
 
 			// move to the new lower bottom left for the next ring
 			let next_size = self.size_for_ring(ring + 1);
-			lower_left_bottom = lower_left_bottom - Vec3::new(next_size, next_size, next_size);
+			lower_left_bottom -= next_size;
 		}
 		Ok(chunks)
 	}
@@ -273,7 +381,7 @@ impl<R: ResolutionMap> Cascade<R> {
 	}
 
 	/// Computes the size of the grid chunks.
-	pub fn grid_chunk_size(&self) -> f32 {
+	pub fn grid_chunk_size(&self) -> Vec3 {
 		self.span() * self.grid_multiple() as f32
 	}
 
@@ -284,25 +392,21 @@ impl<R: ResolutionMap> Cascade<R> {
 	/// You don't always have to cascade out to the general world resolution that you want.
 	pub fn grid_chunks(&self, position: Vec3) -> Result<Vec<CascadeChunk>, String> {
 		let omit = Some(self.cascade_aabb(position));
-		let origin_x = (position.x / self.grid_chunk_size()).floor() * self.grid_chunk_size();
-		let origin_y = self.grid_chunk_size() / -2.0;
-		let origin_z = (position.z / self.grid_chunk_size()).floor() * self.grid_chunk_size();
+		let grid_chunk_size = self.grid_chunk_size();
+		let origin_x = (position.x / grid_chunk_size.x).floor() * grid_chunk_size.x;
+		let origin_y = grid_chunk_size.y / -2.0;
+		let origin_z = (position.z / grid_chunk_size.z).floor() * grid_chunk_size.z;
 		let origin = Vec3::new(origin_x, origin_y, origin_z);
 		let mut chunks = Vec::new();
 
 		// construct the 2D grid of chunks
 		for x in -(self.grid_radius as i32)..=(self.grid_radius as i32) {
 			for z in -(self.grid_radius as i32)..=(self.grid_radius as i32) {
-				let chunk_origin = origin
-					+ Vec3::new(
-						x as f32 * self.grid_chunk_size(),
-						0.0,
-						z as f32 * self.grid_chunk_size(),
-					);
+				let chunk_origin = origin + Vec3::new(x as f32, 0.0, z as f32) * grid_chunk_size;
 				let chunk = CascadeChunk {
 					origin: chunk_origin,
-					size: self.grid_chunk_size(),
-					res_2: self.resolution_map.ring_to_power_of_2(self.number_of_rings),
+					size: grid_chunk_size,
+					res_2: self.resolution_map.ring_to_axis_power_of_2(self.number_of_rings),
 					omit,
 				};
 				chunks.push(chunk);
@@ -318,30 +422,41 @@ impl<R: ResolutionMap> Cascade<R> {
 		Ok(CascadeOutput { cascade_chunks, grid_chunks })
 	}
 
+	/// Whether moving the position changes any chunks.
+	///
+	/// The center chunk always moves with min_size, so this is the case for either snapping.
 	pub fn needs_new_chunks(&self, prev: Vec3, new: Vec3) -> bool {
 		self.position_to_origin(prev) != self.position_to_origin(new)
 	}
 
-	/// Computes the number of units in x = y = z that the cube formed by the cascade spans
+	/// Whether moving the position moves the given ring.
+	pub fn ring_moves(&self, ring: u8, prev: Vec3, new: Vec3) -> bool {
+		match self.snapping {
+			OriginSnapping::MinSize => self.needs_new_chunks(prev, new),
+			OriginSnapping::Ring => self.ring_cell(prev, ring) != self.ring_cell(new, ring),
+		}
+	}
+
+	/// Computes the number of units along each axis that the box formed by the cascade spans
 	///
 	/// This is merely the the largest of the rings in the cascade.
 	///
 	/// For the most part, total world size should be a multiple of this value,
 	/// s.t. coordinate wrap arounds align nicely with the chunks.
-	pub fn span(&self) -> f32 {
+	pub fn span(&self) -> Vec3 {
 		self.min_size * 3_u32.pow(self.number_of_rings as u32) as f32
 	}
 
 	/// Computes the lower bottom left for the entire cascade.
 	pub fn cascade_lower_left_bottom(&self, position: Vec3) -> Vec3 {
+		if self.snapping == OriginSnapping::Ring {
+			let (lower, _) = self.ring_block(position, self.number_of_rings as i32 - 1);
+			return lower.as_vec3() * self.min_size;
+		}
+
 		let mut position = self.position_to_origin(position);
 		for ring in 0..self.number_of_rings {
-			position = position
-				- Vec3::new(
-					self.size_for_ring(ring),
-					self.size_for_ring(ring),
-					self.size_for_ring(ring),
-				);
+			position -= self.size_for_ring(ring);
 		}
 		position
 	}
@@ -349,8 +464,8 @@ impl<R: ResolutionMap> Cascade<R> {
 	/// Computes the AaBb for the entire cascade.
 	pub fn cascade_aabb(&self, position: Vec3) -> Aabb3d {
 		let lower_left_bottom = self.cascade_lower_left_bottom(position);
-		let upper_right_top = lower_left_bottom + Vec3::new(self.span(), self.span(), self.span());
-		Aabb3d::new(lower_left_bottom, upper_right_top)
+		let upper_right_top = lower_left_bottom + self.span();
+		Aabb3d { min: lower_left_bottom.into(), max: upper_right_top.into() }
 	}
 }
 
@@ -378,166 +493,166 @@ mod tests {
 			// z = 0 level (9 chunks)
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(0.0 * size, 0.0 * size, 0.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(1.0 * size, 0.0 * size, 0.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(2.0 * size, 0.0 * size, 0.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(0.0 * size, 1.0 * size, 0.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(1.0 * size, 1.0 * size, 0.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			}, // center
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(2.0 * size, 1.0 * size, 0.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(0.0 * size, 2.0 * size, 0.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(1.0 * size, 2.0 * size, 0.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(2.0 * size, 2.0 * size, 0.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			// z = 1 level (9 chunks)
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(0.0 * size, 0.0 * size, 1.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(1.0 * size, 0.0 * size, 1.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(2.0 * size, 0.0 * size, 1.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(0.0 * size, 1.0 * size, 1.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(1.0 * size, 1.0 * size, 1.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(2.0 * size, 1.0 * size, 1.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(0.0 * size, 2.0 * size, 1.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(1.0 * size, 2.0 * size, 1.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(2.0 * size, 2.0 * size, 1.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			// z = 2 level (9 chunks)
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(0.0 * size, 0.0 * size, 2.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(1.0 * size, 0.0 * size, 2.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(2.0 * size, 0.0 * size, 2.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(0.0 * size, 1.0 * size, 2.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(1.0 * size, 1.0 * size, 2.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(2.0 * size, 1.0 * size, 2.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(0.0 * size, 2.0 * size, 2.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(1.0 * size, 2.0 * size, 2.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 			CascadeChunk {
 				origin: lower_left_bottom + Vec3::new(2.0 * size, 2.0 * size, 2.0 * size),
-				size,
-				res_2,
+				size: Vec3::splat(size),
+				res_2: UVec3::splat(res_2 as u32),
 				omit: None,
 			},
 		]
@@ -553,8 +668,8 @@ mod tests {
 		// Remove the middle chunk (center is at lower_left_bottom + (1*size, 1*size, 1*size))
 		let center_chunk = CascadeChunk {
 			origin: lower_left_bottom + Vec3::new(1.0 * size, 1.0 * size, 1.0 * size),
-			size,
-			res_2,
+			size: Vec3::splat(size),
+			res_2: UVec3::splat(res_2 as u32),
 			omit: None,
 		};
 		chunks_set.remove(&center_chunk);
@@ -566,11 +681,12 @@ mod tests {
 	#[test]
 	fn test_cascade_ones() -> Result<(), String> {
 		let cascade = Cascade {
-			min_size: 1.0,
+			min_size: Vec3::splat(1.0),
 			number_of_rings: 1,
 			resolution_map: ConstantResolutionMap { res_2: 0 },
 			grid_radius: 1,
 			grid_multiple_2: 0,
+			snapping: OriginSnapping::MinSize,
 		};
 		let chunks = cascade.chunks(Vec3::new(0.0, 0.0, 0.0))?.cascade();
 
@@ -607,11 +723,12 @@ mod tests {
 	#[test]
 	fn test_cascade_concentric_rings() -> Result<(), String> {
 		let cascade = Cascade {
-			min_size: 1.0,
+			min_size: Vec3::splat(1.0),
 			number_of_rings: 2,
 			resolution_map: ConstantResolutionMap { res_2: 0 },
 			grid_radius: 1,
 			grid_multiple_2: 0,
+			snapping: OriginSnapping::MinSize,
 		};
 		let chunks = cascade.chunks(Vec3::new(0.0, 0.0, 0.0))?.cascade();
 
@@ -622,8 +739,7 @@ mod tests {
 		let mut expected_chunks = BTreeSet::new();
 
 		// Center chunk
-		let center_chunk =
-			CascadeChunk { origin: Vec3::new(0.0, 0.0, 0.0), size: 1.0, res_2: 0, omit: None };
+		let center_chunk = CascadeChunk::cube(Vec3::new(0.0, 0.0, 0.0), 1.0, 0);
 		expected_chunks.insert(center_chunk);
 
 		// Ring 0: lower_left_bottom = center - (min_size, min_size, min_size)
@@ -655,11 +771,12 @@ mod tests {
 	#[test]
 	fn test_cascade_size_greater_than_one() -> Result<(), String> {
 		let cascade = Cascade {
-			min_size: 2.5,
+			min_size: Vec3::splat(2.5),
 			number_of_rings: 1,
 			resolution_map: ConstantResolutionMap { res_2: 1 },
 			grid_radius: 1,
 			grid_multiple_2: 0,
+			snapping: OriginSnapping::MinSize,
 		};
 		let chunks = cascade.chunks(Vec3::new(0.0, 0.0, 0.0))?.cascade();
 
@@ -670,8 +787,7 @@ mod tests {
 		let mut expected_chunks = BTreeSet::new();
 
 		// Center chunk
-		let center_chunk =
-			CascadeChunk { origin: Vec3::new(0.0, 0.0, 0.0), size: 2.5, res_2: 1, omit: None };
+		let center_chunk = CascadeChunk::cube(Vec3::new(0.0, 0.0, 0.0), 2.5, 1);
 		expected_chunks.insert(center_chunk);
 
 		// Ring 0: lower_left_bottom = center - (min_size, min_size, min_size)
@@ -695,11 +811,12 @@ mod tests {
 	#[test]
 	fn test_cascade_size_less_than_one() -> Result<(), String> {
 		let cascade = Cascade {
-			min_size: 0.5,
+			min_size: Vec3::splat(0.5),
 			number_of_rings: 1,
 			resolution_map: ConstantResolutionMap { res_2: 2 },
 			grid_radius: 1,
 			grid_multiple_2: 0,
+			snapping: OriginSnapping::MinSize,
 		};
 		let chunks = cascade.chunks(Vec3::new(0.0, 0.0, 0.0))?.cascade();
 
@@ -710,8 +827,7 @@ mod tests {
 		let mut expected_chunks = BTreeSet::new();
 
 		// Center chunk
-		let center_chunk =
-			CascadeChunk { origin: Vec3::new(0.0, 0.0, 0.0), size: 0.5, res_2: 2, omit: None };
+		let center_chunk = CascadeChunk::cube(Vec3::new(0.0, 0.0, 0.0), 0.5, 2);
 		expected_chunks.insert(center_chunk);
 
 		// Ring 0: lower_left_bottom = center - (min_size, min_size, min_size)
@@ -731,4 +847,109 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn test_cascade_rectangular() -> Result<(), String> {
+		let cascade = Cascade {
+			min_size: Vec3::new(4.0, 1.0, 4.0),
+			number_of_rings: 1,
+			resolution_map: ConstantResolutionMap { res_2: 2 },
+			grid_radius: 1,
+			grid_multiple_2: 0,
+			snapping: OriginSnapping::MinSize,
+		};
+
+		// the center chunk is snapped per axis
+		let center_chunk = cascade.center_chunk(Vec3::new(5.0, 1.5, -3.0));
+		assert_eq!(center_chunk.origin, Vec3::new(4.0, 1.0, -4.0));
+		assert_eq!(center_chunk.size, Vec3::new(4.0, 1.0, 4.0));
+
+		let chunks = cascade.chunks(Vec3::ZERO)?.cascade();
+		assert_eq!(chunks.len(), 27);
+
+		// the rings tile the span of the cascade without gaps
+		let min = chunks.iter().fold(Vec3::INFINITY, |min, chunk| min.min(chunk.origin));
+		let max = chunks
+			.iter()
+			.fold(Vec3::NEG_INFINITY, |max, chunk| max.max(chunk.origin + chunk.size));
+		assert_eq!(min, cascade.cascade_lower_left_bottom(Vec3::ZERO));
+		assert_eq!(max - min, cascade.span());
+		let volume: f32 =
+			chunks.iter().map(|chunk| chunk.size.x * chunk.size.y * chunk.size.z).sum();
+		assert_eq!(volume, 12.0 * 3.0 * 12.0);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_rectangular_resolution() {
+		let chunk = CascadeChunk::cube(Vec3::ZERO, 1.0, 3).with_axis_res_2(UVec3::new(4, 1, 4));
+		assert_eq!(chunk.resolution(), UVec3::new(16, 2, 16));
+	}
+	fn ring_snapped_cascade() -> Cascade<ConstantResolutionMap> {
+		Cascade {
+			min_size: Vec3::new(2.0, 1.0, 2.0),
+			number_of_rings: 3,
+			resolution_map: ConstantResolutionMap { res_2: 0 },
+			grid_radius: 1,
+			grid_multiple_2: 0,
+			snapping: OriginSnapping::Ring,
+		}
+	}
+
+	#[test]
+	fn test_ring_snapping_tiles_the_cascade() -> Result<(), String> {
+		let cascade = ring_snapped_cascade();
+		for position in [Vec3::ZERO, Vec3::new(7.5, -3.2, 11.0), Vec3::new(-25.0, 4.0, 31.0)] {
+			let chunks = cascade.chunks(position)?.cascade();
+			let aabb = cascade.cascade_aabb(position);
+
+			// The chunks fill the span of the cascade without overlapping
+			let volume: f32 = chunks.iter().map(|chunk| chunk.size.element_product()).sum();
+			assert!((volume - cascade.span().element_product()).abs() < 1e-3);
+			for (i, a) in chunks.iter().enumerate() {
+				assert!(a.origin.cmpge(aabb.min.into()).all());
+				assert!((a.origin + a.size).cmple(aabb.max.into()).all());
+				for b in &chunks[i + 1..] {
+					let overlap =
+						(a.origin + a.size).min(b.origin + b.size) - a.origin.max(b.origin);
+					assert!(overlap.cmple(Vec3::ZERO).any(), "{a:?} overlaps {b:?}");
+				}
+			}
+
+			// The center chunk holds the position
+			assert!(chunks[0].origin.cmple(position).all());
+			assert!((chunks[0].origin + chunks[0].size).cmpgt(position).all());
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_ring_snapping_keeps_outer_rings() -> Result<(), String> {
+		let ring_snapped = ring_snapped_cascade();
+		let concentric = Cascade { snapping: OriginSnapping::MinSize, ..ring_snapped };
+		let largest = ring_snapped.size_for_ring(2);
+		let outer = |cascade: &Cascade<ConstantResolutionMap>, position: Vec3| {
+			cascade.chunks(position).map(|output| {
+				output
+					.cascade()
+					.into_iter()
+					.filter(|chunk| chunk.size == largest)
+					.collect::<BTreeSet<_>>()
+			})
+		};
+
+		// Crossing a min_size boundary inside the same cell of the largest ring
+		let (prev, new) = (Vec3::new(1.5, 0.5, 1.5), Vec3::new(2.5, 0.5, 1.5));
+		assert!(ring_snapped.needs_new_chunks(prev, new));
+		assert!(ring_snapped.ring_moves(0, prev, new));
+		assert!(!ring_snapped.ring_moves(2, prev, new));
+		assert!(concentric.ring_moves(2, prev, new));
+
+		let (before, after) = (outer(&ring_snapped, prev)?, outer(&ring_snapped, new)?);
+		assert!(!before.is_empty());
+		assert_eq!(before, after);
+		assert_ne!(outer(&concentric, prev)?, outer(&concentric, new)?);
+		Ok(())
+	}
 }
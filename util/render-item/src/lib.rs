@@ -15,6 +15,34 @@ pub trait RenderItem: Clone {
 	) -> Vec<Entity>;
 }
 
+/// Links an entity to the render item, or mesh dispatch, that spawned it.
+///
+/// Despawning the owner despawns everything it spawned, so a render item can be removed
+/// from the world as a whole.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[relationship(relationship_target = RenderItemParts)]
+pub struct PartOfRenderItem(pub Entity);
+
+/// The entities spawned by a render item or mesh dispatch.
+#[derive(Component, Debug, Default)]
+#[relationship_target(relationship = PartOfRenderItem, linked_spawn)]
+pub struct RenderItemParts(Vec<Entity>);
+
+impl RenderItemParts {
+	pub fn parts(&self) -> &[Entity] {
+		&self.0
+	}
+}
+
+/// Names an entity for the inspector, from the deterministic ids of what it shows.
+///
+/// Does nothing without the debug-names feature, so release builds don't format names.
+#[cfg_attr(not(feature = "debug-names"), allow(unused_variables))]
+pub fn debug_name(commands: &mut Commands, entity: Entity, name: impl FnOnce() -> String) {
+	#[cfg(feature = "debug-names")]
+	commands.entity(entity).insert(Name::new(name()));
+}
+
 /// Signals an intent to render an item into the world.
 #[derive(Component)]
 pub struct DispatchRenderItem<T: RenderItem> {
@@ -42,6 +70,8 @@ impl<T: RenderItem> DispatchRenderItem<T> {
 /// NOTE: this is not procedural contract for all produce all items of the type.
 /// Rather, when a render item is dispatched, this begins the process of rendering said item.
 ///
+/// The entities returned by the item become [PartOfRenderItem] the dispatching entity.
+///
 /// TODO: this needs to be made event-based.
 pub fn render_items<T: RenderItem + Send + Sync + 'static>(
 	mut commands: Commands,
@@ -50,8 +80,10 @@ pub fn render_items<T: RenderItem + Send + Sync + 'static>(
 		Added<DispatchRenderItem<T>>,
 	>,
 ) {
-	for (_entity, dispatch, chunk, transform) in &query {
-		dispatch.spawn_render_items(&mut commands, chunk, *transform);
+	for (entity, dispatch, chunk, transform) in &query {
+		for part in dispatch.spawn_render_items(&mut commands, chunk, *transform) {
+			commands.entity(part).insert(PartOfRenderItem(entity));
+		}
 	}
 }
 
@@ -61,7 +93,8 @@ pub trait NormalizeChunk {
 	/// Some reusable meshes may normalize the chunk space to something like the origin,
 	/// then rely on transforms to position the mesh in the world.
 	///
-	/// Higher order systems are responsible for accounting for whether the mesh is normalized or not.
+	/// Must agree with the mesh's [MeshSpace](mesh::MeshSpace), which tells the spawn path
+	/// how to account for the normalization.
 	fn normalize_chunk(&self, cascade_chunk: &CascadeChunk) -> CascadeChunk {
 		cascade_chunk.clone()
 	}
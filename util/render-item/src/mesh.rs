@@ -1,11 +1,19 @@
 pub mod cache;
 pub mod handle;
 
-use crate::NormalizeChunk;
-use bevy::prelude::*;
+use crate::{debug_name, NormalizeChunk, PartOfRenderItem};
+use bevy::{
+	asset::RenderAssetUsages,
+	camera::{primitives::Aabb, visibility::RenderLayers},
+	light::NotShadowCaster,
+	math::bounding::Aabb3d,
+	mesh::{MeshTag, PrimitiveTopology},
+	prelude::*,
+};
 use cache::{handle::MeshHandleCache, mesh::MeshCache};
 use chunk::cascade::CascadeChunk;
-use std::hash::Hash;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MeshId(String);
@@ -18,10 +26,233 @@ impl MeshId {
 	pub fn with_suffix(&self, suffix: &str) -> Self {
 		Self(format!("{}{}", self.0, suffix))
 	}
+
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+}
+
+/// Builds a [MeshId] by hashing the config fields that change the built mesh.
+///
+/// The hash is FNV-1a, so ids are stable across runs and toolchains, unlike the std hasher.
+/// Any field left out is a possible collision; builders can report their full config through
+/// [IdentifiedMesh::config] so a [HandleMap](cache::handle::map::HandleMap) can catch those.
+#[derive(Debug, Clone)]
+pub struct MeshIdBuilder {
+	name: String,
+	hasher: StableHasher,
+}
+
+impl MeshIdBuilder {
+	pub fn new(name: &str) -> Self {
+		Self { name: name.to_string(), hasher: StableHasher::default() }
+	}
+
+	pub fn with<T: Hash + ?Sized>(mut self, field: &T) -> Self {
+		field.hash(&mut self.hasher);
+		self
+	}
+
+	/// Hashes a float by its bits, so -0.0 and 0.0 are different fields.
+	pub fn with_f32(mut self, field: f32) -> Self {
+		self.hasher.write_u32(field.to_bits());
+		self
+	}
+
+	pub fn with_vec3(self, field: Vec3) -> Self {
+		self.with_f32(field.x).with_f32(field.y).with_f32(field.z)
+	}
+
+	/// Hashes the debug representation, for fields that don't implement [Hash].
+	pub fn with_debug<T: Debug + ?Sized>(self, field: &T) -> Self {
+		self.with(format!("{field:?}").as_str())
+	}
+
+	pub fn build(&self) -> MeshId {
+		MeshId(format!("{}_{:016x}", self.name, self.hasher.finish()))
+	}
+}
+
+/// 64 bit FNV-1a.
+#[derive(Debug, Clone, Copy)]
+struct StableHasher(u64);
+
+impl Default for StableHasher {
+	fn default() -> Self {
+		Self(0xcbf29ce484222325)
+	}
+}
+
+impl Hasher for StableHasher {
+	fn finish(&self) -> u64 {
+		self.0
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 ^= *byte as u64;
+			self.0 = self.0.wrapping_mul(0x100000001b3);
+		}
+	}
+
+	/// Lengths are hashed as u64 so ids don't depend on the pointer width.
+	fn write_usize(&mut self, i: usize) {
+		self.write(&(i as u64).to_le_bytes());
+	}
+}
+
+/// Share of its size by which shaders scale an instance up or down, at most.
+pub const INSTANCE_SCALE_VARIATION: f32 = 0.08;
+
+/// A hash of where an instance is placed, tagging its mesh so shaders can vary it.
+///
+/// Placements are hashed rather than entities, so an instance looks the same when its chunk
+/// streams out and back in, and the parts of an item placed together vary together. The
+/// hash is never 0, which shaders read as an untagged mesh, such as a terrain chunk.
+pub fn instance_hash(transform: &Transform, cascade_chunk: &CascadeChunk) -> u32 {
+	let mut hasher = StableHasher::default();
+	for field in [transform.translation, cascade_chunk.origin] {
+		for axis in field.to_array() {
+			hasher.write_u32(axis.to_bits());
+		}
+	}
+	let hash = hasher.finish();
+	((hash ^ (hash >> 32)) as u32).max(1)
+}
+
+/// The variation shaders derive from the tag of an instance, mirroring `instance_variation`
+/// in the shaders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstanceVariation {
+	/// Shift of the hue, from -1 to 1 of the shader's largest shift
+	pub hue: f32,
+	/// Scale of the instance about its origin
+	pub scale: f32,
+	/// Offset of patterns and animations, from 0 to 1
+	pub phase: f32,
+}
+
+impl InstanceVariation {
+	pub const NONE: Self = Self { hue: 0.0, scale: 1.0, phase: 0.0 };
+
+	pub fn from_tag(tag: u32) -> Self {
+		if tag == 0 {
+			return Self::NONE;
+		}
+		let signed = |byte: u32| (byte & 0xff) as f32 / 255.0 * 2.0 - 1.0;
+		Self {
+			hue: signed(tag),
+			scale: 1.0 + signed(tag >> 8) * INSTANCE_SCALE_VARIATION,
+			phase: (tag >> 16) as f32 / 65536.0,
+		}
+	}
 }
 
 pub trait IdentifiedMesh {
 	fn id(&self) -> MeshId;
+
+	/// The full config the id was derived from, if the builder can describe it.
+	///
+	/// Ids that hash or leave out fields can collide, and a [HandleMap](cache::handle::map::HandleMap)
+	/// with a collision check compares configs to catch two builders sharing an id.
+	fn config(&self) -> Option<String> {
+		None
+	}
+
+	/// The space the built mesh's vertices are in.
+	fn mesh_space(&self) -> MeshSpace;
+}
+
+/// The space a built mesh's vertices are in, which decides how its spawn transform is applied.
+///
+/// Meshes are built relative to the origin of their normalized chunk, which the spawn path
+/// accounts for, so spawn transforms always place the space the mesh was modeled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshSpace {
+	/// Modeled around the origin in a normalized chunk that doesn't depend on the chunk spawned for.
+	/// The spawn transform places, rotates and scales the mesh about its model origin.
+	Unit,
+	/// Built for the chunk spawned for, relative to the chunk origin.
+	/// The spawn transform is applied to the world the chunk is in.
+	ChunkLocal,
+	/// Built in world coordinates. The spawn transform is used as is.
+	World,
+}
+
+impl MeshSpace {
+	/// Whether a builder's normalized chunks match the space.
+	///
+	/// Unit meshes normalize every chunk to the same chunk as the unit chunk of the same resolution;
+	/// other meshes don't normalize chunks at all.
+	pub fn is_normalized(
+		&self,
+		cascade_chunk: &CascadeChunk,
+		normalized_chunk: &CascadeChunk,
+		normalized_unit_chunk: &CascadeChunk,
+	) -> bool {
+		let expected = match self {
+			MeshSpace::Unit => normalized_unit_chunk,
+			MeshSpace::ChunkLocal | MeshSpace::World => cascade_chunk,
+		};
+		normalized_chunk.origin == expected.origin && normalized_chunk.size == expected.size
+	}
+
+	/// The transform of the spawned mesh, given the spawn transform and the normalized chunk
+	/// the mesh was built in.
+	pub fn mesh_transform(
+		&self,
+		transform: Transform,
+		normalized_chunk: &CascadeChunk,
+	) -> Transform {
+		match self {
+			MeshSpace::Unit | MeshSpace::ChunkLocal => Transform {
+				translation: transform.transform_point(normalized_chunk.origin),
+				..transform
+			},
+			MeshSpace::World => transform,
+		}
+	}
+}
+
+/// Tessellation resolution of a mesh as a power of 2 per axis, like [CascadeChunk] resolutions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshResolution {
+	pub res_2: UVec3,
+}
+
+impl Default for MeshResolution {
+	/// 32 segments along each axis.
+	fn default() -> Self {
+		Self::new(5)
+	}
+}
+
+impl MeshResolution {
+	pub fn new(res_2: u8) -> Self {
+		Self { res_2: UVec3::splat(res_2 as u32) }
+	}
+
+	pub fn from_chunk(cascade_chunk: &CascadeChunk) -> Self {
+		Self { res_2: cascade_chunk.res_2 }
+	}
+
+	/// The number of segments along each axis.
+	pub fn segments(&self) -> UVec3 {
+		UVec3::ONE << self.res_2
+	}
+}
+
+/// Render layer of meshes seen only by lights, such as shadow proxies.
+///
+/// Cameras render layer 0 by default and never see it; shadow casting lights include it.
+pub const SHADOW_PROXY_LAYER: usize = 1;
+
+/// The render layers and shadow casting of a mesh dispatch, carried over to the fetched mesh.
+type ShadowConfig = (Option<&'static RenderLayers>, Has<NotShadowCaster>);
+
+/// An empty triangle mesh, for builders that produce no geometry.
+pub fn empty_mesh() -> Mesh {
+	Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
 }
 
 pub trait MeshBuilder: Clone + NormalizeChunk {
@@ -33,6 +264,20 @@ pub trait MeshBuilder: Clone + NormalizeChunk {
 		let normalized_chunk = self.normalize_chunk(cascade_chunk);
 		self.build_mesh_impl(&normalized_chunk)
 	}
+
+	/// Builds the mesh at the given resolution, independent of any chunk.
+	fn build_with_resolution(&self, res: MeshResolution) -> Mesh {
+		let cascade_chunk = CascadeChunk::unit_3d_center_chunk().with_axis_res_2(res.res_2);
+		self.build_mesh(&cascade_chunk).unwrap_or_else(empty_mesh)
+	}
+
+	/// The bounds of the built mesh in its local space, before any transform is applied.
+	///
+	/// Defaults to the normalized unit chunk, which bounds meshes built in the normalized chunk space.
+	fn local_bounds(&self) -> Aabb3d {
+		let chunk = self.normalize_chunk(&CascadeChunk::unit_3d_center_chunk());
+		Aabb3d { min: chunk.origin.into(), max: (chunk.origin + chunk.size).into() }
+	}
 }
 
 pub trait MeshFetcher: Clone + IdentifiedMesh {
@@ -42,6 +287,13 @@ pub trait MeshFetcher: Clone + IdentifiedMesh {
 		meshes: &mut ResMut<Assets<Mesh>>,
 		cascade_chunk: &CascadeChunk,
 	) -> Option<Handle<Mesh>>;
+
+	/// The bounds of the fetched mesh in its local space.
+	fn local_bounds(&self) -> Aabb3d;
+
+	/// The transform of the fetched mesh that places it as the spawn transform intends,
+	/// according to its [MeshSpace].
+	fn mesh_transform(&self, transform: Transform, cascade_chunk: &CascadeChunk) -> Transform;
 }
 
 /// If it's already defined how the mesh is built, cached, and fetched, this trait can be used to fetch the mesh.
@@ -75,6 +327,31 @@ impl<T: MeshBuilder + MeshCache + MeshHandleCache> MeshFetcher for T {
 			handle
 		})
 	}
+
+	fn local_bounds(&self) -> Aabb3d {
+		MeshBuilder::local_bounds(self)
+	}
+
+	/// Panics in debug builds if the builder normalizes chunks against its declared space.
+	fn mesh_transform(&self, transform: Transform, cascade_chunk: &CascadeChunk) -> Transform {
+		let mesh_space = self.mesh_space();
+		let normalized_chunk = self.normalize_chunk(cascade_chunk);
+		debug_assert!(
+			mesh_space.is_normalized(
+				cascade_chunk,
+				&normalized_chunk,
+				&self.normalize_chunk(
+					&CascadeChunk::unit_3d_center_chunk().with_axis_res_2(cascade_chunk.res_2)
+				),
+			),
+			"{:?} normalizes {:?} to {:?}, which doesn't match its {:?} mesh space",
+			self.id(),
+			cascade_chunk,
+			normalized_chunk,
+			mesh_space
+		);
+		mesh_space.mesh_transform(transform, &normalized_chunk)
+	}
 }
 
 /// A mesh dispatch signals an intent for the item to be spawned into the world.
@@ -93,18 +370,120 @@ impl<T: MeshFetcher> MeshDispatch<T> {
 
 /// Fetches meshes and spawns them into the world.
 ///
+/// The spawned entities get their AABB from the reported local bounds,
+/// rather than having Bevy compute it from the vertices, and their transform from the mesh space.
+/// They are [PartOfRenderItem] the dispatch, so despawning it removes the mesh.
+///
+/// Each is tagged with the [instance_hash] of its placement, for shaders to vary instances
+/// of the same mesh. The AABB leaves room for the largest [InstanceVariation] scale.
+///
 /// TODO: this needs to be made event-based.
 pub fn fetch_meshes<T: MeshFetcher + Send + Sync + 'static, M: Material>(
 	mut commands: Commands,
 	mut meshes: ResMut<Assets<Mesh>>,
 	query: Query<
-		(Entity, &MeshDispatch<T>, &CascadeChunk, &Transform, &MeshMaterial3d<M>),
+		(Entity, &MeshDispatch<T>, &CascadeChunk, &Transform, &MeshMaterial3d<M>, ShadowConfig),
 		Added<MeshDispatch<T>>,
 	>,
 ) {
-	for (_entity, mesh_dispatch, cascade_chunk, transform, material) in &query {
+	for (entity, mesh_dispatch, cascade_chunk, transform, material, (layers, not_caster)) in &query
+	{
 		if let Some(mesh) = mesh_dispatch.fetcher.fetch_mesh(&mut meshes, cascade_chunk) {
-			commands.spawn((Mesh3d(mesh), *transform, material.clone()));
+			let bounds = mesh_dispatch.fetcher.local_bounds();
+			let (min, max) = (Vec3::from(bounds.min), Vec3::from(bounds.max));
+			let grown = 1.0 + INSTANCE_SCALE_VARIATION;
+			let mut spawned = commands.spawn((
+				Mesh3d(mesh),
+				mesh_dispatch.fetcher.mesh_transform(*transform, cascade_chunk),
+				material.clone(),
+				Aabb::from_min_max(min.min(min * grown), max.max(max * grown)),
+				MeshTag(instance_hash(transform, cascade_chunk)),
+				PartOfRenderItem(entity),
+			));
+			if let Some(layers) = layers {
+				spawned.insert(layers.clone());
+			}
+			if not_caster {
+				spawned.insert(NotShadowCaster);
+			}
+			let spawned = spawned.id();
+			debug_name(&mut commands, spawned, || mesh_dispatch.fetcher.id().as_str().to_string());
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_mesh_transform_places_the_model_space() {
+		let transform = Transform::from_translation(Vec3::new(3.0, -1.0, 2.0))
+			.with_rotation(Quat::from_rotation_y(1.2))
+			.with_scale(Vec3::new(2.0, 0.5, 1.5));
+		let chunk = CascadeChunk::cube(Vec3::new(40.0, 8.0, -16.0), 8.0, 3);
+
+		// Unit meshes are built relative to the corner of their normalized chunk
+		let normalized = CascadeChunk::unit_center_chunk().with_mu(0.1);
+		let mesh_transform = MeshSpace::Unit.mesh_transform(transform, &normalized);
+		for point in [Vec3::ZERO, Vec3::new(0.3, 0.9, -0.2)] {
+			let vertex = point - normalized.origin;
+			let placed = mesh_transform.transform_point(vertex);
+			assert!(placed.abs_diff_eq(transform.transform_point(point), 1e-5));
 		}
+
+		// Chunk local meshes are built relative to the chunk origin
+		let mesh_transform = MeshSpace::ChunkLocal.mesh_transform(transform, &chunk);
+		let vertex = Vec3::new(1.0, 2.0, 3.0);
+		let placed = mesh_transform.transform_point(vertex);
+		assert!(placed.abs_diff_eq(transform.transform_point(chunk.origin + vertex), 1e-4));
+
+		assert_eq!(MeshSpace::World.mesh_transform(transform, &chunk), transform);
+	}
+
+	#[test]
+	fn test_mesh_ids_are_stable() {
+		let id = MeshIdBuilder::new("canopy").with("leaf").with_f32(1.0).with(&[1u32, 2, 3][..]);
+		assert_eq!(id.build(), MeshId::new("canopy_644ab1db87ff96cc".to_string()));
+		assert_ne!(id.clone().with_f32(-0.0).build(), id.with_f32(0.0).build());
+	}
+
+	#[test]
+	fn test_instances_vary_by_placement() {
+		let chunk = CascadeChunk::cube(Vec3::new(40.0, 8.0, -16.0), 8.0, 3);
+		let here = Transform::from_translation(Vec3::new(3.0, -1.0, 2.0));
+		let there = Transform::from_translation(Vec3::new(3.0, -1.0, 2.5));
+
+		// The same placement gets the same tag whenever it is spawned, whatever its rotation
+		let tag = instance_hash(&here, &chunk);
+		assert_eq!(tag, instance_hash(&here.with_rotation(Quat::from_rotation_y(1.0)), &chunk));
+		assert_ne!(tag, instance_hash(&there, &chunk));
+
+		let variations: Vec<_> = (0..64)
+			.map(|i| {
+				let placement = Transform::from_translation(Vec3::X * i as f32);
+				InstanceVariation::from_tag(instance_hash(&placement, &chunk))
+			})
+			.collect();
+		for variation in &variations {
+			assert!((-1.0..=1.0).contains(&variation.hue));
+			assert!((variation.scale - 1.0).abs() <= INSTANCE_SCALE_VARIATION + 1e-6);
+			assert!((0.0..1.0).contains(&variation.phase));
+		}
+		assert!(variations.iter().any(|variation| variation.hue > 0.5));
+		assert!(variations.iter().any(|variation| variation.hue < -0.5));
+
+		// Untagged meshes, such as terrain, aren't varied
+		assert_eq!(InstanceVariation::from_tag(0), InstanceVariation::NONE);
+	}
+
+	#[test]
+	fn test_is_normalized_checks_the_space() {
+		let chunk = CascadeChunk::cube(Vec3::new(40.0, 8.0, -16.0), 8.0, 3);
+		let unit = CascadeChunk::unit_3d_center_chunk().with_res_2(3);
+		assert!(MeshSpace::Unit.is_normalized(&chunk, &unit, &unit));
+		assert!(!MeshSpace::Unit.is_normalized(&chunk, &chunk, &unit));
+		assert!(MeshSpace::ChunkLocal.is_normalized(&chunk, &chunk, &unit));
+		assert!(!MeshSpace::World.is_normalized(&chunk, &unit, &unit));
 	}
 }
@@ -48,26 +48,85 @@ impl<T: IdentifiedMesh> ChunkMeshKey<T> {
 	}
 }
 
+/// What a [HandleMap] does when two builders with different configs share an id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionCheck {
+	Log,
+	Panic,
+}
+
+type ConfigMap<T> = Arc<RwLock<HashMap<ChunkMeshKey<T>, String>>>;
+
 #[derive(Debug, Clone)]
 pub struct HandleMap<T: IdentifiedMesh> {
 	cache: Arc<RwLock<HashMap<ChunkMeshKey<T>, Handle<Mesh>>>>,
+	/// The config first seen for each key, kept only when collisions are checked
+	configs: Option<(CollisionCheck, ConfigMap<T>)>,
 }
 
 impl<T: IdentifiedMesh> HandleMap<T> {
 	pub fn new() -> Self {
-		Self { cache: Arc::new(RwLock::new(HashMap::new())) }
+		Self { cache: Arc::new(RwLock::new(HashMap::new())), configs: None }
+	}
+
+	/// Checks that builders sharing a cached handle have the same [config](IdentifiedMesh::config).
+	///
+	/// This is a debugging aid: it keeps a copy of every config, and builders without one
+	/// aren't checked.
+	pub fn with_collision_check(mut self, check: CollisionCheck) -> Self {
+		self.configs = Some((check, Arc::new(RwLock::new(HashMap::new()))));
+		self
 	}
 
 	pub fn get(&self, chunk: &CascadeChunk, mesh_builder: &T) -> Option<Handle<Mesh>> {
+		let key = ChunkMeshKey::new(chunk.clone(), mesh_builder.id());
+		self.check_collision(&key, mesh_builder);
 		let cache = self.cache.read().unwrap();
-		cache.get(&ChunkMeshKey::new(chunk.clone(), mesh_builder.id())).cloned()
+		cache.get(&key).cloned()
 	}
 
 	pub fn insert(&self, chunk: &CascadeChunk, mesh_builder: &T, mesh: Handle<Mesh>) {
-		let mut cache = self.cache.write().unwrap();
 		let key = ChunkMeshKey::new(chunk.clone(), mesh_builder.id());
+		self.check_collision(&key, mesh_builder);
+		let mut cache = self.cache.write().unwrap();
 		cache.insert(key, mesh);
 	}
+
+	/// Number of cached handles.
+	pub fn len(&self) -> usize {
+		self.cache.read().map_or(0, |cache| cache.len())
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Records the builder's config for the key, or compares it with the one already recorded.
+	fn check_collision(&self, key: &ChunkMeshKey<T>, mesh_builder: &T) {
+		let Some((check, configs)) = &self.configs else {
+			return;
+		};
+		let Some(config) = mesh_builder.config() else {
+			return;
+		};
+
+		let Ok(mut configs) = configs.write() else {
+			return;
+		};
+		let Some(existing) = configs.get(key) else {
+			configs.insert(ChunkMeshKey::new(key.chunk, key.mesh_id.clone()), config);
+			return;
+		};
+		if *existing == config {
+			return;
+		}
+
+		let message = format!("Mesh id collision for {key}: {existing} and {config} share an id");
+		match check {
+			CollisionCheck::Log => log::error!("{message}"),
+			CollisionCheck::Panic => panic!("{message}"),
+		}
+	}
 }
 
 impl<T: IdentifiedMesh> Default for HandleMap<T> {
@@ -75,3 +134,42 @@ impl<T: IdentifiedMesh> Default for HandleMap<T> {
 		Self::new()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mesh::{MeshIdBuilder, MeshSpace};
+
+	/// A builder whose id leaves out its color.
+	struct Tinted {
+		size: u32,
+		color: u32,
+	}
+
+	impl IdentifiedMesh for Tinted {
+		fn id(&self) -> MeshId {
+			MeshIdBuilder::new("tinted").with(&self.size).build()
+		}
+
+		fn config(&self) -> Option<String> {
+			Some(format!("size {} color {}", self.size, self.color))
+		}
+
+		fn mesh_space(&self) -> MeshSpace {
+			MeshSpace::Unit
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "Mesh id collision")]
+	fn test_collision_check_catches_shared_ids() {
+		let map = HandleMap::new().with_collision_check(CollisionCheck::Panic);
+		let chunk = CascadeChunk::unit_center_chunk();
+
+		map.insert(&chunk, &Tinted { size: 2, color: 1 }, Handle::default());
+		assert!(map.get(&chunk, &Tinted { size: 2, color: 1 }).is_some());
+		assert!(map.get(&chunk, &Tinted { size: 3, color: 2 }).is_none());
+
+		map.get(&chunk, &Tinted { size: 2, color: 2 });
+	}
+}
@@ -1,11 +1,11 @@
 use crate::{
 	mesh::{
 		cache::handle::map::HandleMap, cache::handle::MeshHandleCache, cache::mesh::MeshCache,
-		IdentifiedMesh, MeshBuilder, MeshId,
+		IdentifiedMesh, MeshBuilder, MeshId, MeshResolution, MeshSpace,
 	},
 	NormalizeChunk,
 };
-use bevy::prelude::*;
+use bevy::{math::bounding::Aabb3d, prelude::*};
 use chunk::cascade::CascadeChunk;
 
 #[derive(Debug, Clone, Component)]
@@ -19,6 +19,16 @@ impl<T: MeshBuilder + IdentifiedMesh + Clone> MeshHandle<T> {
 		Self { handle_cache: HandleMap::new(), builder }
 	}
 
+	pub fn builder(&self) -> &T {
+		&self.builder
+	}
+
+	/// Swaps the builder, keeping the handle cache.
+	pub fn with_builder(mut self, builder: T) -> Self {
+		self.builder = builder;
+		self
+	}
+
 	/// Adds a handle cache to the mesh handle.
 	pub fn with_handle_cache(mut self, handle_cache: HandleMap<T>) -> Self {
 		self.handle_cache = handle_cache;
@@ -31,6 +41,14 @@ impl<T: MeshBuilder + IdentifiedMesh + Clone> IdentifiedMesh for MeshHandle<T> {
 	fn id(&self) -> MeshId {
 		self.builder.id()
 	}
+
+	fn config(&self) -> Option<String> {
+		self.builder.config()
+	}
+
+	fn mesh_space(&self) -> MeshSpace {
+		self.builder.mesh_space()
+	}
 }
 
 /// We need to implement the normalize chunk trait to allow this to work with any of the other traits.
@@ -46,6 +64,14 @@ impl<T: MeshBuilder + IdentifiedMesh + Clone> MeshBuilder for MeshHandle<T> {
 	fn build_mesh_impl(&self, cascade_chunk: &CascadeChunk) -> Option<Mesh> {
 		self.builder.build_mesh(cascade_chunk)
 	}
+
+	fn build_with_resolution(&self, res: MeshResolution) -> Mesh {
+		self.builder.build_with_resolution(res)
+	}
+
+	fn local_bounds(&self) -> Aabb3d {
+		self.builder.local_bounds()
+	}
 }
 
 /// We implement the mesh cache trait to allow the MeshHandle<T>.
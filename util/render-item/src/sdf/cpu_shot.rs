@@ -1,5 +1,6 @@
 pub mod marching_cubes;
 
+use bevy::math::bounding::Aabb3d;
 use bevy::prelude::*;
 use chunk::cascade::CascadeChunk;
 use sdf::{Sign, Sdf};
@@ -13,15 +14,15 @@ pub trait CpuShotSdf: Sdf + Clone {
         // ---------- grid setup ---------------------------------------------------
 		let chunk_size = cascade_chunk.size;
 		let res = cascade_chunk.resolution();
-		let cube_size = chunk_size / res as f32;
+		let cube_size = chunk_size / res.as_vec3();
 		let chunk_origin = cascade_chunk.origin;
 
 		// ---------- grid setup ---------------------------------------------------
 		// Grid resolution (sample points); cubes are (n-1) in each axis
 		// Y is now treated the same as X and Z - a voxel cube
-		let nx = res + 1;
-		let ny = res + 1;
-		let nz = res + 1;
+		let nx = res.x as usize + 1;
+		let ny = res.y as usize + 1;
+		let nz = res.z as usize + 1;
 
 		// Helper: linear index with X fastest, then Z, then Y (consistent)
 		let idx = |x: usize, y: usize, z: usize| -> usize { (y * nz + z) * nx + x };
@@ -39,12 +40,12 @@ pub trait CpuShotSdf: Sdf + Clone {
 		let z_slices: Vec<_> = (0..nz)
 			.into_par_iter()
 			.map(|z| {
-				let wz = chunk_origin.z + z as f32 * cube_size;
+				let wz = chunk_origin.z + z as f32 * cube_size.z;
 				let mut slice = vec![0.0f32; nx * ny];
 
 				// For each x position, compute intervals and sample sparsely
 				for x in 0..nx {
-					let wx = chunk_origin.x + x as f32 * cube_size;
+					let wx = chunk_origin.x + x as f32 * cube_size.x;
 					// Get intervals for this (x, z) position
 					let intervals = sdf_clone.sign_uniform_on_y(wx, wz);
 
@@ -63,11 +64,11 @@ pub trait CpuShotSdf: Sdf + Clone {
 						// Convert world Y coordinates to grid indices
 						// Clamp to chunk bounds
 						let y_start_world = y_min_world.max(chunk_origin.y);
-						let y_end_world = y_max_world.min(chunk_origin.y + chunk_size);
+						let y_end_world = y_max_world.min(chunk_origin.y + chunk_size.y);
 
 						let y_start =
-							((y_start_world - chunk_origin.y) / cube_size).floor() as usize;
-						let y_end = ((y_end_world - chunk_origin.y) / cube_size)
+							((y_start_world - chunk_origin.y) / cube_size.y).floor() as usize;
+						let y_end = ((y_end_world - chunk_origin.y) / cube_size.y)
 							.ceil()
 							.min(ny as f32) as usize;
 
@@ -86,7 +87,7 @@ pub trait CpuShotSdf: Sdf + Clone {
 								Sign::Top | Sign::Bottom => {
 									// Unknown/undefined sign - need to sample normally
 									for yi in y_begin..y_finish {
-										let wy = chunk_origin.y + yi as f32 * cube_size;
+										let wy = chunk_origin.y + yi as f32 * cube_size.y;
 										let distance = sdf_clone.distance(Vec3::new(wx, wy, wz));
 										slice[yi * nx + x] = distance;
 									}
@@ -100,7 +101,7 @@ pub trait CpuShotSdf: Sdf + Clone {
 									// If interval is small, just sample everything
 									if interval_size <= TRANSITION_VOXELS * 2 {
 										for yi in y_begin..y_finish {
-											let wy = chunk_origin.y + yi as f32 * cube_size;
+											let wy = chunk_origin.y + yi as f32 * cube_size.y;
 											let distance = sdf_clone.distance(Vec3::new(wx, wy, wz));
 											slice[yi * nx + x] = distance;
 										}
@@ -108,7 +109,7 @@ pub trait CpuShotSdf: Sdf + Clone {
 										// Sample at START boundary (where surface transition might be)
 										let start_sample_end = (y_begin + TRANSITION_VOXELS).min(y_finish);
 										for yi in y_begin..start_sample_end {
-											let wy = chunk_origin.y + yi as f32 * cube_size;
+											let wy = chunk_origin.y + yi as f32 * cube_size.y;
 											let distance = sdf_clone.distance(Vec3::new(wx, wy, wz));
 											slice[yi * nx + x] = distance;
 										}
@@ -129,7 +130,7 @@ pub trait CpuShotSdf: Sdf + Clone {
 										
 										// Sample at END boundary (where next interval starts = surface transition)
 										for yi in fill_end.max(fill_start)..y_finish {
-											let wy = chunk_origin.y + yi as f32 * cube_size;
+											let wy = chunk_origin.y + yi as f32 * cube_size.y;
 											let distance = sdf_clone.distance(Vec3::new(wx, wy, wz));
 											slice[yi * nx + x] = distance;
 										}
@@ -157,7 +158,7 @@ pub trait CpuShotSdf: Sdf + Clone {
 					if y_current < ny {
 						// Treat remaining as Top (unknown) and sample
 						for yi in y_current..ny {
-							let wy = chunk_origin.y + yi as f32 * cube_size;
+							let wy = chunk_origin.y + yi as f32 * cube_size.y;
 							let distance = sdf_clone.distance(Vec3::new(wx, wy, wz));
 							slice[yi * nx + x] = distance;
 						}
@@ -209,8 +210,7 @@ pub trait CpuShotSdf: Sdf + Clone {
 			.into_par_iter()
 			.filter_map(|(x, y, z)| {
 				// Local-space cube origin (all dimensions relative to chunk origin)
-				let cube_pos_local =
-					Vec3::new(x as f32 * cube_size, y as f32 * cube_size, z as f32 * cube_size);
+				let cube_pos_local = Vec3::new(x as f32, y as f32, z as f32) * cube_size;
 				
 				
 				// Corner scalar values (standard MC corner ordering assumed by your helpers)
@@ -308,9 +308,9 @@ pub trait CpuShotSdf: Sdf + Clone {
 			.par_iter()
 			.map(|v| {
 				// Convert vertex local position to grid coordinates
-				let gx = (v[0] / cube_size).clamp(0.0, (nx - 1) as f32);
-				let gy = (v[1] / cube_size).clamp(0.0, (ny - 1) as f32);
-				let gz = (v[2] / cube_size).clamp(0.0, (nz - 1) as f32);
+				let gx = (v[0] / cube_size.x).clamp(0.0, (nx - 1) as f32);
+				let gy = (v[1] / cube_size.y).clamp(0.0, (ny - 1) as f32);
+				let gz = (v[2] / cube_size.z).clamp(0.0, (nz - 1) as f32);
 
 				// Get integer grid indices (truncate for now, could interpolate)
 				let ix = gx as usize;
@@ -318,55 +318,55 @@ pub trait CpuShotSdf: Sdf + Clone {
 				let iz = gz as usize;
 
 				// Compute finite differences using central differences where possible
-				// ∂f/∂x = (f(x+1) - f(x-1)) / (2 * cube_size)
+				// ∂f/∂x = (f(x+1) - f(x-1)) / (2 * cube_size.x)
 				let dx = if ix > 0 && ix < nx - 1 {
 					let f_xp1 = grid_slice[idx(ix + 1, iy, iz)];
 					let f_xm1 = grid_slice[idx(ix - 1, iy, iz)];
-					(f_xp1 - f_xm1) / (2.0 * cube_size)
+					(f_xp1 - f_xm1) / (2.0 * cube_size.x)
 				} else if ix < nx - 1 {
 					// Forward difference at left boundary
 					let f_xp1 = grid_slice[idx(ix + 1, iy, iz)];
 					let f_x = grid_slice[idx(ix, iy, iz)];
-					(f_xp1 - f_x) / cube_size
+					(f_xp1 - f_x) / cube_size.x
 				} else {
 					// Backward difference at right boundary
 					let f_x = grid_slice[idx(ix, iy, iz)];
 					let f_xm1 = grid_slice[idx(ix - 1, iy, iz)];
-					(f_x - f_xm1) / cube_size
+					(f_x - f_xm1) / cube_size.x
 				};
 
-				// ∂f/∂y = (f(y+1) - f(y-1)) / (2 * cube_size)
+				// ∂f/∂y = (f(y+1) - f(y-1)) / (2 * cube_size.y)
 				let dy = if iy > 0 && iy < ny - 1 {
 					let f_yp1 = grid_slice[idx(ix, iy + 1, iz)];
 					let f_ym1 = grid_slice[idx(ix, iy - 1, iz)];
-					(f_yp1 - f_ym1) / (2.0 * cube_size)
+					(f_yp1 - f_ym1) / (2.0 * cube_size.y)
 				} else if iy < ny - 1 {
 					// Forward difference at bottom boundary
 					let f_yp1 = grid_slice[idx(ix, iy + 1, iz)];
 					let f_y = grid_slice[idx(ix, iy, iz)];
-					(f_yp1 - f_y) / cube_size
+					(f_yp1 - f_y) / cube_size.y
 				} else {
 					// Backward difference at top boundary
 					let f_y = grid_slice[idx(ix, iy, iz)];
 					let f_ym1 = grid_slice[idx(ix, iy - 1, iz)];
-					(f_y - f_ym1) / cube_size
+					(f_y - f_ym1) / cube_size.y
 				};
 
-				// ∂f/∂z = (f(z+1) - f(z-1)) / (2 * cube_size)
+				// ∂f/∂z = (f(z+1) - f(z-1)) / (2 * cube_size.z)
 				let dz = if iz > 0 && iz < nz - 1 {
 					let f_zp1 = grid_slice[idx(ix, iy, iz + 1)];
 					let f_zm1 = grid_slice[idx(ix, iy, iz - 1)];
-					(f_zp1 - f_zm1) / (2.0 * cube_size)
+					(f_zp1 - f_zm1) / (2.0 * cube_size.z)
 				} else if iz < nz - 1 {
 					// Forward difference at front boundary
 					let f_zp1 = grid_slice[idx(ix, iy, iz + 1)];
 					let f_z = grid_slice[idx(ix, iy, iz)];
-					(f_zp1 - f_z) / cube_size
+					(f_zp1 - f_z) / cube_size.z
 				} else {
 					// Backward difference at back boundary
 					let f_z = grid_slice[idx(ix, iy, iz)];
 					let f_zm1 = grid_slice[idx(ix, iy, iz - 1)];
-					(f_z - f_zm1) / cube_size
+					(f_z - f_zm1) / cube_size.z
 				};
 
 				// Normalize the gradient to get the normal
@@ -385,8 +385,10 @@ pub trait CpuShotSdf: Sdf + Clone {
 
 		// Simple tiled UVs (local X/Z across the chunk)
 		let start_time = std::time::Instant::now();
-		let uvs: Vec<[f32; 2]> =
-			vertices.par_iter().map(|v| [v[0] / chunk_size, v[2] / chunk_size]).collect();
+		let uvs: Vec<[f32; 2]> = vertices
+			.par_iter()
+			.map(|v| [v[0] / chunk_size.x, v[2] / chunk_size.z])
+			.collect();
 		let end_time = std::time::Instant::now();
 		let duration = end_time.duration_since(start_time);
 		log::debug!("UVs time: {:?}", duration);
@@ -412,4 +414,10 @@ impl <T: CpuShotSdf + NormalizeChunk> MeshBuilder for T {
 		log::info!("Building mesh for chunk: {:?}", cascade_chunk);
 		self.cpu_chunk_mesh(cascade_chunk)
 	}
+
+	/// Vertices are relative to the normalized chunk origin.
+	fn local_bounds(&self) -> Aabb3d {
+		let chunk = self.normalize_chunk(&CascadeChunk::unit_3d_center_chunk());
+		Aabb3d { min: Vec3A::ZERO, max: chunk.size.into() }
+	}
 }
\ No newline at end of file
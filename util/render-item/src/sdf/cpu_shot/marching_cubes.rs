@@ -57,7 +57,7 @@ pub fn get_cube_index(corners: [f32; 8]) -> usize {
 pub fn interpolate_vertex(
 	edge: usize,
 	cube_origin: Vec3,
-	cube_size: f32,
+	cube_size: Vec3,
 	corner_values: [f32; 8],
 ) -> Vec3 {
 	// Standard cube corner positions in local space (same as TRIANGULATIONS assumes)
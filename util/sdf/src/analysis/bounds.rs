@@ -1,4 +1,4 @@
-use bevy::math::bounding::Aabb3d;
+use bevy_math::bounding::Aabb3d;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Bounds {
@@ -48,10 +48,13 @@ impl Sign {
 	/// Returns the difference of the two signs.
 	pub fn difference(&self, other: &Self) -> Self {
 		match (self, other) {
-			// whatever the self sign is, if the other is negative, then the result is positive
-			(_, Sign::Negative) => Sign::Positive,
-			// otherwise, the sign stays the same
-			_ => self.clone(),
+			// whatever the self sign is, if the other is negative, then the result is positive,
+			// and outside of self nothing the other does can put us inside
+			(_, Sign::Negative) | (Sign::Positive, _) => Sign::Positive,
+			// if the other is positive, the sign stays the same
+			(_, Sign::Positive) => self.clone(),
+			// inside of self, an unknown other may carve us out
+			_ => Sign::Top,
 		}
 	}
 }
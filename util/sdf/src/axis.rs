@@ -0,0 +1,70 @@
+use bevy_math::prelude::*;
+
+/// An axis of space, which columns of sign uniform intervals can run along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Axis {
+	X,
+	#[default]
+	Y,
+	Z,
+}
+
+impl Axis {
+	pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+	/// Index of the axis in a vector.
+	pub fn index(self) -> usize {
+		match self {
+			Axis::X => 0,
+			Axis::Y => 1,
+			Axis::Z => 2,
+		}
+	}
+
+	/// Indices of the two axes a column along this one is placed by, in the order they are
+	/// given: `(x, z)` along Y, as for [Sdf::sign_uniform_on_y](crate::Sdf::sign_uniform_on_y),
+	/// `(y, z)` along X and `(x, y)` along Z.
+	pub fn across(self) -> [usize; 2] {
+		match self {
+			Axis::X => [1, 2],
+			Axis::Y => [0, 2],
+			Axis::Z => [0, 1],
+		}
+	}
+
+	/// The point at `along` on the column at `(a, b)`.
+	pub fn point(self, a: f32, b: f32, along: f32) -> Vec3 {
+		let [ia, ib] = self.across();
+		let mut point = Vec3::ZERO;
+		point[ia] = a;
+		point[ib] = b;
+		point[self.index()] = along;
+		point
+	}
+
+	/// The point in the space of the columns along the axis, where the axis is Y and the
+	/// columns are placed by X and Z.
+	///
+	/// Swapping axes keeps distances, so shapes moved into column space keep their signs and
+	/// the vertical column analysis holds along any axis.
+	pub fn to_column(self, p: Vec3) -> Vec3 {
+		let [ia, ib] = self.across();
+		Vec3::new(p[ia], p[self.index()], p[ib])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_columns_round_trip_along_every_axis() {
+		let p = Vec3::new(1.0, 2.0, 3.0);
+		for axis in Axis::ALL {
+			let column = axis.to_column(p);
+			assert_eq!(column.y, p[axis.index()]);
+			assert_eq!(axis.point(column.x, column.z, column.y), p);
+		}
+		assert_eq!(Axis::Y.to_column(p), p);
+	}
+}
@@ -1,5 +1,6 @@
-use crate::Sdf;
-use bevy::prelude::*;
+use crate::column::{intersect, linear_between, quadratic_below_zero, solid_column, sphere_range};
+use crate::{Axis, Sdf, SignUniformIntervals};
+use bevy_math::prelude::*;
 
 /// A capsule SDF (cylinder with rounded ends)
 pub struct CapsuleSdf {
@@ -22,4 +23,58 @@ impl Sdf for CapsuleSdf {
 		let closest_point = self.start + ba * h;
 		(p - closest_point).length() - self.radius
 	}
+
+	fn sign_uniform_on_y(&self, x: f32, z: f32) -> SignUniformIntervals {
+		self.sign_uniform_on_axis(Axis::Y, x, z)
+	}
+
+	fn sign_uniform_on_axis(&self, axis: Axis, x: f32, z: f32) -> SignUniformIntervals {
+		// Worked out for a vertical column, in the space of the columns along the axis
+		let (start, end) = (axis.to_column(self.start), axis.to_column(self.end));
+		let ba = end - start;
+		let len = ba.length();
+		let start_cap = sphere_range(start, self.radius, x, z);
+		if len <= f32::EPSILON {
+			return solid_column(start_cap);
+		}
+		let dir = ba / len;
+
+		// Along the column, the squared distance to the axis is quadratic in y and the axis
+		// position is linear in y
+		let base = Vec3::new(x, 0.0, z) - start;
+		let along = base.dot(dir);
+		let a = 1.0 - dir.y * dir.y;
+		let b = 2.0 * (base.y - along * dir.y);
+		let c = base.length_squared() - along * along - self.radius * self.radius;
+		let cylinder =
+			intersect(quadratic_below_zero(a, b, c), linear_between(along, dir.y, 0.0, len));
+
+		// The capsule is convex, so its pieces cover a single range of the column
+		let inside = [start_cap, cylinder, sphere_range(end, self.radius, x, z)]
+			.into_iter()
+			.flatten()
+			.reduce(|(a_min, a_max), (b_min, b_max)| (a_min.min(b_min), a_max.max(b_max)));
+		solid_column(inside)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::column::check_against_dense;
+
+	#[test]
+	fn test_capsule_columns_match_dense_sampling() {
+		let bounds = (Vec3::splat(-4.0), Vec3::splat(4.0));
+		for (start, end) in [
+			(Vec3::new(-2.0, -1.0, 0.5), Vec3::new(2.5, 1.5, -1.0)),
+			(Vec3::new(0.2, -2.0, 0.1), Vec3::new(0.2, 2.0, 0.1)),
+			(Vec3::new(-2.5, 0.3, -2.0), Vec3::new(2.5, 0.3, 2.0)),
+		] {
+			let capsule = CapsuleSdf::new(start, end, 1.2);
+			for axis in Axis::ALL {
+				assert!(check_against_dense(&capsule, axis, bounds.0, bounds.1) > 0);
+			}
+		}
+	}
 }
@@ -0,0 +1,118 @@
+//! Ranges of vertical columns inside simple shapes, for exact [Sdf::sign_uniform_on_y](crate::Sdf::sign_uniform_on_y).
+//!
+//! Columns along other axes use the same ranges in [Axis::to_column](crate::Axis::to_column) space.
+
+use crate::{Sign, SignBoundary, SignUniformIntervals};
+use bevy_math::prelude::*;
+
+/// The open range of `y` where `a * y^2 + b * y + c < 0`, if it is a single non-empty range.
+///
+/// `a` must not be negative.
+pub(crate) fn quadratic_below_zero(a: f32, b: f32, c: f32) -> Option<(f32, f32)> {
+	if a <= f32::EPSILON {
+		// Constant along the column
+		return (c < 0.0).then_some((f32::NEG_INFINITY, f32::INFINITY));
+	}
+	let discriminant = b * b - 4.0 * a * c;
+	if discriminant <= 0.0 {
+		return None;
+	}
+	let root = discriminant.sqrt();
+	Some(((-b - root) / (2.0 * a), (-b + root) / (2.0 * a)))
+}
+
+/// The open range of `y` where `min < offset + slope * y < max`, if it is non-empty.
+pub(crate) fn linear_between(offset: f32, slope: f32, min: f32, max: f32) -> Option<(f32, f32)> {
+	if min >= max {
+		return None;
+	}
+	if slope.abs() <= f32::EPSILON {
+		return (offset > min && offset < max).then_some((f32::NEG_INFINITY, f32::INFINITY));
+	}
+	let (a, b) = ((min - offset) / slope, (max - offset) / slope);
+	Some((a.min(b), a.max(b)))
+}
+
+/// The overlap of two open ranges, if it is non-empty.
+pub(crate) fn intersect(a: Option<(f32, f32)>, b: Option<(f32, f32)>) -> Option<(f32, f32)> {
+	let ((a_min, a_max), (b_min, b_max)) = (a?, b?);
+	let range = (a_min.max(b_min), a_max.min(b_max));
+	(range.0 < range.1).then_some(range)
+}
+
+/// The open range of `y` where the column at `(x, z)` is inside the sphere, if any.
+pub(crate) fn sphere_range(center: Vec3, radius: f32, x: f32, z: f32) -> Option<(f32, f32)> {
+	let (dx, dz) = (x - center.x, z - center.z);
+	quadratic_below_zero(
+		1.0,
+		-2.0 * center.y,
+		center.y * center.y + dx * dx + dz * dz - radius * radius,
+	)
+}
+
+/// The intervals of a column that is inside a solid over one range and outside it elsewhere.
+pub(crate) fn solid_column(inside: Option<(f32, f32)>) -> SignUniformIntervals {
+	let mut intervals = SignUniformIntervals::default();
+	let Some((min, max)) = inside else {
+		intervals.insert_boundary(SignBoundary { min: f32::NEG_INFINITY, sign: Sign::Positive });
+		return intervals;
+	};
+	if min > f32::NEG_INFINITY {
+		intervals.insert_boundary(SignBoundary { min: f32::NEG_INFINITY, sign: Sign::Positive });
+	}
+	intervals.insert_boundary(SignBoundary { min, sign: Sign::Negative });
+	if max < f32::INFINITY {
+		intervals.insert_boundary(SignBoundary { min: max, sign: Sign::Positive });
+	}
+	intervals
+}
+
+/// Densely samples columns along the axis over the box and checks the sign of every sample
+/// against its interval, skipping samples right at a boundary. Returns the number of samples in
+/// negative intervals.
+///
+/// Panics if a column has an unknown interval.
+#[cfg(test)]
+pub(crate) fn check_against_dense(
+	sdf: &impl crate::Sdf,
+	axis: crate::Axis,
+	min: Vec3,
+	max: Vec3,
+) -> usize {
+	let mut negative = 0;
+	let steps = 40;
+	let (min, max) = (axis.to_column(min), axis.to_column(max));
+	let step = (max - min) / steps as f32;
+	for xi in 0..=steps {
+		for zi in 0..=steps {
+			let (x, z) = (min.x + xi as f32 * step.x, min.z + zi as f32 * step.z);
+			let intervals: Vec<_> = sdf.sign_uniform_on_axis(axis, x, z).into_iter().collect();
+			for yi in 0..=steps * 4 {
+				let y = min.y + yi as f32 * step.y / 4.0;
+				let Some(interval) = intervals.iter().find(|interval| {
+					let (low, high) = interval.open_range();
+					low <= y && y < high
+				}) else {
+					panic!("no interval at ({x}, {y}, {z}) along {axis:?}");
+				};
+				let (low, high) = interval.open_range();
+				if y - low < 1e-3 || high - y < 1e-3 {
+					continue;
+				}
+				let point = axis.point(x, z, y);
+				let distance = sdf.distance(point);
+				match interval.left.sign {
+					Sign::Negative => {
+						assert!(distance <= 0.0, "{distance} at {point} along {axis:?}");
+						negative += 1;
+					}
+					Sign::Positive => {
+						assert!(distance >= 0.0, "{distance} at {point} along {axis:?}")
+					}
+					_ => panic!("unknown sign at {point} along {axis:?}"),
+				}
+			}
+		}
+	}
+	negative
+}
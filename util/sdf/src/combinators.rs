@@ -1,5 +1,5 @@
-use crate::{Sdf, SignBoundary, SignUniformInterval, SignUniformIntervals};
-use bevy::prelude::*;
+use crate::{Axis, Sdf, SignBoundary, SignUniformInterval, SignUniformIntervals};
+use bevy_math::prelude::*;
 
 /// Add two SDFs together - adds their heights (for heightfield-like SDFs)
 /// This is useful for adding features to terrain (bumps, depressions, etc.)
@@ -48,8 +48,12 @@ impl<A: Sdf, B: Sdf> Sdf for Union<A, B> {
 	}
 
 	fn sign_uniform_on_y(&self, x: f32, z: f32) -> SignUniformIntervals {
-		let a_intervals = self.a.sign_uniform_on_y(x, z);
-		let b_intervals = self.b.sign_uniform_on_y(x, z);
+		self.sign_uniform_on_axis(Axis::Y, x, z)
+	}
+
+	fn sign_uniform_on_axis(&self, axis: Axis, a: f32, b: f32) -> SignUniformIntervals {
+		let a_intervals = self.a.sign_uniform_on_axis(axis, a, b);
+		let b_intervals = self.b.sign_uniform_on_axis(axis, a, b);
 		a_intervals.interval_mapping(&b_intervals).union().normalize()
 	}
 }
@@ -104,8 +108,12 @@ impl<A: Sdf, B: Sdf> Sdf for Difference<A, B> {
 	}
 
 	fn sign_uniform_on_y(&self, x: f32, z: f32) -> SignUniformIntervals {
-		let a_intervals = self.a.sign_uniform_on_y(x, z);
-		let b_intervals = self.b.sign_uniform_on_y(x, z);
+		self.sign_uniform_on_axis(Axis::Y, x, z)
+	}
+
+	fn sign_uniform_on_axis(&self, axis: Axis, a: f32, b: f32) -> SignUniformIntervals {
+		let a_intervals = self.a.sign_uniform_on_axis(axis, a, b);
+		let b_intervals = self.b.sign_uniform_on_axis(axis, a, b);
 		a_intervals.interval_mapping(&b_intervals).difference().normalize()
 	}
 }
@@ -203,18 +211,21 @@ impl<A: Sdf> Sdf for Translate<A> {
 	}
 
 	fn sign_uniform_on_y(&self, x: f32, z: f32) -> SignUniformIntervals {
+		self.sign_uniform_on_axis(Axis::Y, x, z)
+	}
+
+	fn sign_uniform_on_axis(&self, axis: Axis, a: f32, b: f32) -> SignUniformIntervals {
 		let mut translated_intervals = SignUniformIntervals::default();
-		let translated_x = x - self.offset.x;
-		let translated_z = z - self.offset.z;
+		let offset = axis.to_column(self.offset);
+		let translated_a = a - offset.x;
+		let translated_b = b - offset.z;
 
-		for interval in self.sdf.sign_uniform_on_y(translated_x, translated_z).into_iter() {
+		for interval in self.sdf.sign_uniform_on_axis(axis, translated_a, translated_b).into_iter()
+		{
 			translated_intervals.insert_interval(SignUniformInterval {
-				left: SignBoundary {
-					min: interval.left.min - self.offset.y,
-					sign: interval.left.sign,
-				},
+				left: SignBoundary { min: interval.left.min + offset.y, sign: interval.left.sign },
 				right: SignBoundary {
-					min: interval.right.min - self.offset.y,
+					min: interval.right.min + offset.y,
 					sign: interval.right.sign,
 				},
 			});
@@ -348,3 +359,70 @@ impl<A: Sdf> Sdf for Elongate<A> {
 		self.sdf.distance(q)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::column::check_against_dense;
+	use crate::{CapsuleSdf, SphereSdf};
+
+	/// A sphere with a tunnel through it, moved into the space of the columns along the axis.
+	fn cave(axis: Axis) -> Difference<SphereSdf, CapsuleSdf> {
+		let (start, end) = (Vec3::new(-3.0, 0.5, -1.0), Vec3::new(3.0, 1.0, 1.0));
+		Difference::new(
+			SphereSdf::new(Vec3::ZERO, 3.0),
+			CapsuleSdf::new(axis.to_column(start), axis.to_column(end), 0.8),
+		)
+	}
+
+	#[test]
+	fn test_translated_columns_move_with_the_offset() {
+		let offset = Vec3::new(0.5, 1.0, -0.5);
+		let translated = Translate::new(SphereSdf::new(Vec3::ZERO, 1.5), offset);
+		let sphere = SphereSdf::new(offset, 1.5);
+		for (x, z) in [(0.5, -0.5), (1.0, 0.0), (-0.3, -1.2)] {
+			let ranges = |sdf: &dyn Sdf| -> Vec<(f32, f32)> {
+				let intervals = sdf.sign_uniform_on_y(x, z);
+				intervals.into_iter().map(|interval| interval.open_range()).collect()
+			};
+			let (moved_ranges, sphere_ranges) = (ranges(&translated), ranges(&sphere));
+			assert_eq!(moved_ranges.len(), sphere_ranges.len());
+			for ((a_min, a_max), (b_min, b_max)) in moved_ranges.into_iter().zip(sphere_ranges) {
+				assert!(a_min == b_min || (a_min - b_min).abs() < 1e-4);
+				assert!(a_max == b_max || (a_max - b_max).abs() < 1e-4);
+			}
+		}
+		assert!(check_against_dense(&translated, Axis::Y, Vec3::splat(-4.0), Vec3::splat(4.0)) > 0);
+	}
+
+	#[test]
+	fn test_combined_columns_follow_the_axis() {
+		let offset = Vec3::new(0.5, -0.5, 0.0);
+		let sphere = SphereSdf::new(offset, 1.5);
+		let moved = Translate::new(SphereSdf::new(Vec3::ZERO, 1.5), offset);
+		let (min, max) = (Vec3::splat(-4.0), Vec3::splat(4.0));
+		for axis in Axis::ALL {
+			// Moved by the offset, whichever way the columns run
+			let column = axis.to_column(offset);
+			let ranges = |sdf: &dyn Sdf| -> Vec<(f32, f32)> {
+				let intervals = sdf.sign_uniform_on_axis(axis, column.x + 0.25, column.z);
+				intervals.into_iter().map(|interval| interval.open_range()).collect()
+			};
+			let (moved_ranges, sphere_ranges) = (ranges(&moved), ranges(&sphere));
+			assert_eq!(moved_ranges.len(), sphere_ranges.len());
+			for ((a_min, a_max), (b_min, b_max)) in moved_ranges.into_iter().zip(sphere_ranges) {
+				assert!(a_min == b_min || (a_min - b_min).abs() < 1e-4);
+				assert!(a_max == b_max || (a_max - b_max).abs() < 1e-4);
+			}
+			assert!(check_against_dense(&moved, axis, min, max) > 0);
+
+			// A tunnel along the axis has the intervals of the same tunnel standing upright
+			for (a, b) in [(0.0, 0.0), (0.9, -0.3), (-2.0, 1.5)] {
+				assert_eq!(
+					cave(Axis::Y).sign_uniform_on_axis(axis, a, b),
+					cave(axis).sign_uniform_on_y(a, b)
+				);
+			}
+		}
+	}
+}
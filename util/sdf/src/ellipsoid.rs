@@ -1,5 +1,6 @@
-use crate::Sdf;
-use bevy::prelude::*;
+use crate::column::{quadratic_below_zero, solid_column};
+use crate::{Axis, Sdf, SignUniformIntervals};
+use bevy_math::prelude::*;
 
 /// An ellipsoid SDF with arbitrary radii along each axis
 pub struct EllipsoidSdf {
@@ -23,5 +24,33 @@ impl Sdf for EllipsoidSdf {
 			-self.radii.min_element()
 		}
 	}
+
+	fn sign_uniform_on_y(&self, x: f32, z: f32) -> SignUniformIntervals {
+		self.sign_uniform_on_axis(Axis::Y, x, z)
+	}
+
+	fn sign_uniform_on_axis(&self, axis: Axis, a: f32, b: f32) -> SignUniformIntervals {
+		let (center, radii) = (axis.to_column(self.center), axis.to_column(self.radii));
+		// The surface is where the scaled offset has unit length
+		let (u, w) = ((a - center.x) / radii.x, (b - center.z) / radii.z);
+		let inside = quadratic_below_zero(1.0, 0.0, u * u + w * w - 1.0)
+			.map(|(min, max)| (center.y + min * radii.y, center.y + max * radii.y));
+		solid_column(inside)
+	}
 }
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::column::check_against_dense;
+
+	#[test]
+	fn test_ellipsoid_columns_match_dense_sampling() {
+		let ellipsoid = EllipsoidSdf::new(Vec3::new(-0.4, 0.6, 0.2), Vec3::new(3.0, 1.5, 2.2));
+		for axis in Axis::ALL {
+			let negative =
+				check_against_dense(&ellipsoid, axis, Vec3::splat(-4.0), Vec3::splat(4.0));
+			assert!(negative > 0);
+		}
+	}
+}
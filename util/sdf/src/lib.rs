@@ -1,5 +1,7 @@
 pub mod analysis;
+pub mod axis;
 pub mod capsule;
+mod column;
 pub mod combinators;
 pub mod ellipsoid;
 pub mod sphere;
@@ -9,6 +11,7 @@ pub mod tube;
 
 pub use analysis::bounds::Bounds;
 pub use analysis::interval::{Sign, SignBoundary, SignUniformInterval, SignUniformIntervals};
+pub use axis::Axis;
 pub use capsule::CapsuleSdf;
 pub use combinators::{
 	AddY, Difference, Elongate, Intersection, RotateAlongRay, RotateY, Round, Scale,
@@ -18,7 +21,7 @@ pub use ellipsoid::EllipsoidSdf;
 pub use sphere::SphereSdf;
 pub use tube::{Ellipse3d, TubeSdf};
 
-use bevy::prelude::*;
+use bevy_math::prelude::*;
 
 /// Trait for Signed Distance Fields
 /// Returns the signed distance from a point to the surface:
@@ -39,6 +42,18 @@ pub trait Sdf: Send + Sync {
 		SignUniformIntervals::default()
 	}
 
+	/// Computes intervals of sign uniformity along any axis, for the column at `(a, b)` across
+	/// it, as given by [Axis::across].
+	///
+	/// Cliff walls and caves are often uniform along a horizontal axis rather than along Y.
+	/// Defaults to [Sdf::sign_uniform_on_y] along Y and to unknown intervals along X and Z.
+	fn sign_uniform_on_axis(&self, axis: Axis, a: f32, b: f32) -> SignUniformIntervals {
+		match axis {
+			Axis::Y => self.sign_uniform_on_y(a, b),
+			Axis::X | Axis::Z => SignUniformIntervals::default(),
+		}
+	}
+
 	/// Returns the bounds of the SDF, i.e., the region over which the SDF is defined.
 	/// This can form pessimistic boundaries for analysis of the SDF.
 	///
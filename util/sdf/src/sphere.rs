@@ -1,5 +1,7 @@
-use crate::Sdf;
-use bevy::prelude::*;
+use crate::column::{solid_column, sphere_range};
+use crate::{Axis, Bounds, Sdf, SignUniformIntervals};
+use bevy_math::bounding::Aabb3d;
+use bevy_math::prelude::*;
 
 /// A sphere SDF
 pub struct SphereSdf {
@@ -17,5 +19,31 @@ impl Sdf for SphereSdf {
 	fn distance(&self, p: Vec3) -> f32 {
 		(p - self.center).length() - self.radius
 	}
+
+	fn sign_uniform_on_y(&self, x: f32, z: f32) -> SignUniformIntervals {
+		self.sign_uniform_on_axis(Axis::Y, x, z)
+	}
+
+	fn sign_uniform_on_axis(&self, axis: Axis, a: f32, b: f32) -> SignUniformIntervals {
+		solid_column(sphere_range(axis.to_column(self.center), self.radius, a, b))
+	}
+
+	fn bounds(&self) -> Bounds {
+		Bounds::Cuboid(Aabb3d::new(self.center, Vec3::splat(self.radius)))
+	}
 }
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::column::check_against_dense;
+
+	#[test]
+	fn test_sphere_columns_match_dense_sampling() {
+		let sphere = SphereSdf::new(Vec3::new(0.3, -1.2, 0.7), 2.5);
+		for axis in Axis::ALL {
+			let negative = check_against_dense(&sphere, axis, Vec3::splat(-4.0), Vec3::splat(4.0));
+			assert!(negative > 0);
+		}
+	}
+}
@@ -1,5 +1,5 @@
 use crate::Sdf;
-use bevy::prelude::*;
+use bevy_math::prelude::*;
 
 pub struct TetrahedronSdf {
 	pub vertices: [Vec3; 4],
@@ -1,5 +1,5 @@
 use crate::Sdf;
-use bevy::prelude::*;
+use bevy_math::prelude::*;
 use noise::{NoiseFn, Perlin};
 
 /// Trapezoidal (frustum-shaped) prism SDF.
@@ -1,5 +1,6 @@
-use crate::Sdf;
-use bevy::prelude::*;
+use crate::column::{intersect, linear_between, quadratic_below_zero};
+use crate::{Sdf, Sign, SignBoundary, SignUniformIntervals};
+use bevy_math::prelude::*;
 use noise::{NoiseFn, Perlin};
 
 /// A 3D ellipse defining the cross-section shape of the tube.
@@ -95,6 +96,15 @@ impl TubeSdf {
 		let up = dir.cross(right).normalize();
 		[right, up]
 	}
+
+	/// The largest magnitude the surface noise can add to the distance.
+	fn noise_amplitude(&self) -> f32 {
+		if self.noise.is_some() {
+			self.noise_factor.abs()
+		} else {
+			0.0
+		}
+	}
 }
 
 impl Sdf for TubeSdf {
@@ -144,4 +154,188 @@ impl Sdf for TubeSdf {
 
 		sdf
 	}
+
+	/// A column crosses the tube's surface twice: it enters where it is both within the
+	/// cross-section and between the ends, and leaves where either stops holding.
+	///
+	/// Without noise or flanging the crossings are exact. Otherwise the column is negative
+	/// where it is inside the narrowest, noise-eroded tube and positive outside the widest,
+	/// noise-inflated tube, with the bands between left as [Sign::Top].
+	fn sign_uniform_on_y(&self, x: f32, z: f32) -> SignUniformIntervals {
+		let ray = self.ray_end - self.ray_start;
+		let len = ray.length();
+		if len <= f32::EPSILON {
+			return SignUniformIntervals::default();
+		}
+		let dir = ray / len;
+		let [right, up] = Self::orthonormal_basis(dir);
+
+		// Along the column, the cross-section coordinates and the axis position are linear in y
+		let base = Vec3::new(x, 0.0, z) - self.ray_start;
+		let (u, du) = (base.dot(right) / self.ellipse.radii.x, right.y / self.ellipse.radii.x);
+		let (v, dv) = (base.dot(up) / self.ellipse.radii.y, up.y / self.ellipse.radii.y);
+		let (t, dt) = (base.dot(dir), dir.y);
+
+		// Squared radial distance in units of the radii is a*y^2 + b*y + c
+		let a = du * du + dv * dv;
+		let b = 2.0 * (u * du + v * dv);
+		let c = u * u + v * v;
+		let within_radius = |radius: f32| {
+			if radius <= 0.0 {
+				return None;
+			}
+			quadratic_below_zero(a, b, c - radius * radius)
+		};
+
+		let noise = self.noise_amplitude();
+		let (min_flange, max_flange) =
+			(1.0f32.min(1.0 + self.flanging), 1.0f32.max(1.0 + self.flanging));
+		let rounding = self.end_rounding;
+
+		// Inside the narrowest tube by more than the noise
+		let negative = intersect(
+			within_radius(min_flange * (1.0 - noise)),
+			linear_between(t, dt, rounding + noise, len - rounding - noise),
+		);
+
+		// Outside the widest tube by more than the noise; the ends only bound the tube where
+		// the clamped axis position can pass them
+		let start = if rounding > noise { rounding - noise } else { f32::NEG_INFINITY };
+		let end = if rounding > noise { len - rounding + noise } else { f32::INFINITY };
+		let not_positive =
+			intersect(within_radius(max_flange * (1.0 + noise)), linear_between(t, dt, start, end));
+
+		let mut boundaries = vec![(f32::NEG_INFINITY, Sign::Positive)];
+		if let Some((outer_min, outer_max)) = not_positive {
+			boundaries.push((outer_min, Sign::Top));
+			if let Some((inner_min, inner_max)) = negative {
+				boundaries.push((inner_min.max(outer_min), Sign::Negative));
+				boundaries.push((inner_max.min(outer_max), Sign::Top));
+			}
+			boundaries.push((outer_max, Sign::Positive));
+		}
+
+		// A boundary at the same height as the next one bounds an empty range
+		let mut intervals = SignUniformIntervals::default();
+		for (i, (min, sign)) in boundaries.iter().enumerate() {
+			if boundaries.get(i + 1).is_some_and(|(next, _)| next <= min) {
+				continue;
+			}
+			intervals.insert_boundary(SignBoundary { min: *min, sign: sign.clone() });
+		}
+		intervals
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Difference;
+
+	/// A flat ground at the given height
+	struct Ground(f32);
+
+	impl Sdf for Ground {
+		fn distance(&self, p: Vec3) -> f32 {
+			p.y - self.0
+		}
+
+		fn sign_uniform_on_y(&self, _x: f32, _z: f32) -> SignUniformIntervals {
+			let mut intervals = SignUniformIntervals::default();
+			intervals
+				.insert_boundary(SignBoundary { min: f32::NEG_INFINITY, sign: Sign::Negative });
+			intervals.insert_boundary(SignBoundary { min: self.0, sign: Sign::Positive });
+			intervals
+		}
+	}
+
+	/// The tube bored through the terrain playground
+	fn tunnel() -> TubeSdf {
+		let ellipse =
+			Ellipse3d { center: Vec3::ZERO, axes: [Vec3::X, Vec3::Z], radii: Vec2::new(2.0, 2.0) };
+		TubeSdf::new(Vec3::new(-30.0, -1.0, -30.0), Vec3::new(-50.0, 4.0, -50.0), ellipse)
+	}
+
+	/// Samples columns over the tube's footprint and checks every known sign against the distance.
+	/// Returns the number of samples in known negative and unknown intervals.
+	fn check_columns(sdf: &impl Sdf) -> (usize, usize) {
+		let (mut negative, mut unknown) = (0, 0);
+		for xi in -60..=-20 {
+			for zi in -60..=-20 {
+				let (x, z) = (xi as f32, zi as f32);
+				for interval in sdf.sign_uniform_on_y(x, z) {
+					let (min, max) = interval.open_range();
+					let (min, max) = (min.max(-20.0), max.min(20.0));
+					let mut y = min.ceil();
+					while y < max {
+						if y - min > 1e-3 && max - y > 1e-3 {
+							let distance = sdf.distance(Vec3::new(x, y, z));
+							match interval.left.sign {
+								Sign::Negative => {
+									assert!(distance <= 0.0, "{distance} at ({x}, {y}, {z})");
+									negative += 1;
+								}
+								Sign::Positive => {
+									assert!(distance >= 0.0, "{distance} at ({x}, {y}, {z})");
+								}
+								_ => unknown += 1,
+							}
+						}
+						y += 0.25;
+					}
+				}
+			}
+		}
+		(negative, unknown)
+	}
+
+	#[test]
+	fn test_tube_columns_cross_twice() {
+		// Without rounding the distance is zero past the ends, which is left unknown
+		let tube = tunnel().with_end_rounding(0.5);
+		let (negative, unknown) = check_columns(&tube);
+		assert!(negative > 0);
+		assert_eq!(unknown, 0);
+
+		for xi in -60..=-20 {
+			let crossings = tube
+				.sign_uniform_on_y(xi as f32, xi as f32)
+				.into_iter()
+				.filter(|interval| {
+					let (min, max) = interval.open_range();
+					min < max && interval.is_well_behaved()
+				})
+				.count();
+			assert!(crossings == 1 || crossings == 3);
+		}
+	}
+
+	#[test]
+	fn test_noisy_tube_columns_are_conservative() {
+		let tube = tunnel()
+			.with_noise(Perlin::new(3))
+			.with_noise_factor(0.4)
+			.with_end_rounding(1.0)
+			.with_flanging(0.3);
+		let (negative, unknown) = check_columns(&tube);
+		assert!(negative > 0 && unknown > 0);
+	}
+
+	#[test]
+	fn test_difference_columns_through_tunnel() {
+		let terrain = Difference::new(
+			Ground(1.0),
+			tunnel().with_noise(Perlin::new(3)).with_noise_factor(0.4),
+		);
+		let (negative, unknown) = check_columns(&terrain);
+		assert!(negative > 0 && unknown > 0);
+
+		// Through the middle of the tunnel the ground is carved out below the surface
+		let center = (Vec3::new(-30.0, -1.0, -30.0) + Vec3::new(-50.0, 4.0, -50.0)) / 2.0;
+		let carved = terrain.sign_uniform_on_y(center.x, center.z).into_iter().any(|interval| {
+			let (min, max) = interval.open_range();
+			interval.left.sign == Sign::Positive && min < center.y && center.y < max
+		});
+		assert!(carved);
+	}
 }